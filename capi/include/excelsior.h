@@ -0,0 +1,76 @@
+#ifndef EXCELSIOR_H
+#define EXCELSIOR_H
+
+/* Generated by cbindgen from capi/src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Открытая книга; создаётся `xlsx_open`.
+ */
+typedef struct XlsxHandle XlsxHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Открывает лист `sheet_name` книги `path`. При ошибке возвращает NULL.
+ *
+ * # Safety
+ * `path` и `sheet_name` — строки UTF-8 с нулём в конце.
+ */
+XlsxHandle *xlsx_open(const char *path, const char *sheet_name);
+
+/**
+ * Дописывает строку из JSON-массива, например `[1, "a", true, null, "=A1*2"]`:
+ * `null` — пустая ячейка, строки — как в `append_row` (`=` — формула,
+ * числовой текст — число).
+ *
+ * # Safety
+ * `h` — указатель из `xlsx_open`; `json` — строка UTF-8 с нулём в конце.
+ */
+int xlsx_append_row_json(XlsxHandle *h, const char *json);
+
+/**
+ * Записывает `value` в ячейку `coord` (`"B7"`), с тем же разбором, что у
+ * строк `xlsx_append_row_json`.
+ *
+ * # Safety
+ * `h` — указатель из `xlsx_open`; `coord` и `value` — строки UTF-8 с нулём
+ * в конце.
+ */
+int xlsx_set_cell(XlsxHandle *h, const char *coord, const char *value);
+
+/**
+ * Сохраняет книгу в `path`; редактор остаётся открытым.
+ *
+ * # Safety
+ * `h` — указатель из `xlsx_open`; `path` — строка UTF-8 с нулём в конце.
+ */
+int xlsx_save(XlsxHandle *h, const char *path);
+
+/**
+ * Закрывает редактор без сохранения. NULL допустим.
+ *
+ * # Safety
+ * `h` — NULL или указатель из `xlsx_open`, переданный сюда один раз.
+ */
+void xlsx_free(XlsxHandle *h);
+
+/**
+ * Текст ошибки последнего вызова в этом потоке или NULL, если он прошёл
+ * успешно. Строка принадлежит библиотеке и действительна до следующего
+ * вызова в том же потоке.
+ */
+const char *xlsx_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* EXCELSIOR_H */
@@ -0,0 +1,151 @@
+//! Плоский C ABI поверх `rust-core` для .NET (P/Invoke), Delphi и прочих
+//! языков с FFI. Заголовок — `include/excelsior.h`.
+//!
+//! Редактор — непрозрачный указатель из `xlsx_open`, освобождается
+//! `xlsx_free`. Функции с кодом возврата `int` отдают 0 при успехе и -1 при
+//! ошибке; текст ошибки — `xlsx_last_error()` в том же потоке. Строки — UTF-8
+//! с нулём в конце.
+
+use anyhow::{Context, Result, anyhow};
+use rust_core::XlsxEditor;
+use rust_core::json_part::parse_row;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+
+/// Открытая книга; создаётся `xlsx_open`.
+pub struct XlsxHandle {
+    editor: XlsxEditor,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: anyhow::Error) {
+    // нулевой байт внутри сообщения обрезал бы его в C
+    let msg = format!("{e:#}").replace('\0', " ");
+    LAST_ERROR.with(|l| *l.borrow_mut() = CString::new(msg).ok());
+}
+
+/// Выполняет `f`, ошибку или панику кладёт в `LAST_ERROR`. Ошибка прошлого
+/// вызова сбрасывается заранее, чтобы после успеха не читалась старая.
+fn guard<T>(f: impl FnOnce() -> Result<T>) -> Option<T> {
+    LAST_ERROR.with(|l| l.borrow_mut().take());
+    let res = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|p| {
+        let msg = p
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| p.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".into());
+        Err(anyhow!("panic: {msg}"))
+    });
+    res.map_err(set_error).ok()
+}
+
+fn status(r: Option<()>) -> c_int {
+    if r.is_some() { 0 } else { -1 }
+}
+
+/// # Safety
+/// `s` — null или строка с нулём в конце.
+unsafe fn arg<'a>(s: *const c_char, name: &str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(anyhow!("{name} is null"));
+    }
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .with_context(|| format!("{name} is not valid UTF-8"))
+}
+
+/// # Safety
+/// `h` — null или указатель из `xlsx_open`, ещё не переданный в `xlsx_free`.
+unsafe fn handle<'a>(h: *mut XlsxHandle) -> Result<&'a mut XlsxEditor> {
+    unsafe { h.as_mut() }
+        .map(|h| &mut h.editor)
+        .context("editor handle is null")
+}
+
+/// Открывает лист `sheet_name` книги `path`. При ошибке возвращает NULL.
+///
+/// # Safety
+/// `path` и `sheet_name` — строки UTF-8 с нулём в конце.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xlsx_open(
+    path: *const c_char,
+    sheet_name: *const c_char,
+) -> *mut XlsxHandle {
+    guard(|| {
+        let (path, sheet_name) = unsafe { (arg(path, "path")?, arg(sheet_name, "sheet_name")?) };
+        let editor = XlsxEditor::open(path, sheet_name)?;
+        Ok(Box::into_raw(Box::new(XlsxHandle { editor })))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Дописывает строку из JSON-массива, например `[1, "a", true, null, "=A1*2"]`:
+/// `null` — пустая ячейка, строки — как в `append_row` (`=` — формула,
+/// числовой текст — число).
+///
+/// # Safety
+/// `h` — указатель из `xlsx_open`; `json` — строка UTF-8 с нулём в конце.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xlsx_append_row_json(h: *mut XlsxHandle, json: *const c_char) -> c_int {
+    status(guard(|| {
+        let editor = unsafe { handle(h) }?;
+        let cells = parse_row(unsafe { arg(json, "json") }?).context("bad row JSON")?;
+        let cells: Vec<_> = cells.into_iter().map(|v| (v, None)).collect();
+        Ok(editor.append_values(&cells)?)
+    }))
+}
+
+/// Записывает `value` в ячейку `coord` (`"B7"`), с тем же разбором, что у
+/// строк `xlsx_append_row_json`.
+///
+/// # Safety
+/// `h` — указатель из `xlsx_open`; `coord` и `value` — строки UTF-8 с нулём
+/// в конце.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xlsx_set_cell(
+    h: *mut XlsxHandle,
+    coord: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    status(guard(|| {
+        let editor = unsafe { handle(h) }?;
+        let (coord, value) = unsafe { (arg(coord, "coord")?, arg(value, "value")?) };
+        Ok(editor.set_cell(coord, value)?)
+    }))
+}
+
+/// Сохраняет книгу в `path`; редактор остаётся открытым.
+///
+/// # Safety
+/// `h` — указатель из `xlsx_open`; `path` — строка UTF-8 с нулём в конце.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xlsx_save(h: *mut XlsxHandle, path: *const c_char) -> c_int {
+    status(guard(|| {
+        let editor = unsafe { handle(h) }?;
+        Ok(editor.save(unsafe { arg(path, "path") }?)?)
+    }))
+}
+
+/// Закрывает редактор без сохранения. NULL допустим.
+///
+/// # Safety
+/// `h` — NULL или указатель из `xlsx_open`, переданный сюда один раз.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xlsx_free(h: *mut XlsxHandle) {
+    if !h.is_null() {
+        drop(unsafe { Box::from_raw(h) });
+    }
+}
+
+/// Текст ошибки последнего вызова в этом потоке или NULL, если он прошёл
+/// успешно. Строка принадлежит библиотеке и действительна до следующего
+/// вызова в том же потоке.
+#[unsafe(no_mangle)]
+pub extern "C" fn xlsx_last_error() -> *const c_char {
+    LAST_ERROR.with(|l| l.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
@@ -0,0 +1,91 @@
+//! C ABI через Rust-обёртку: коды возврата, `xlsx_last_error` и круг
+//! open → append → set_cell → save на `test/test.xlsx`.
+
+use anyhow::Result;
+use excelsior_c::*;
+use rust_core::read_part::ReadValue;
+use rust_core::{XlsxEditor, scan};
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+fn c(s: &str) -> CString {
+    CString::new(s).unwrap()
+}
+
+fn last_error() -> Option<String> {
+    let p = xlsx_last_error();
+    (!p.is_null()).then(|| unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned())
+}
+
+fn open_test() -> Result<*mut XlsxHandle> {
+    let sheet = scan("../test/test.xlsx")?.remove(0);
+    let h = unsafe { xlsx_open(c("../test/test.xlsx").as_ptr(), c(&sheet).as_ptr()) };
+    assert!(!h.is_null(), "{:?}", last_error());
+    Ok(h)
+}
+
+#[test]
+fn round_trip_on_test_xlsx() -> Result<()> {
+    let out = "../test/capi_round_trip_out.xlsx";
+    let h = open_test()?;
+    unsafe {
+        assert_eq!(
+            xlsx_append_row_json(h, c(r#"["capi-row", 42, true, null]"#).as_ptr()),
+            0
+        );
+        assert_eq!(xlsx_set_cell(h, c("H30").as_ptr(), c("from C").as_ptr()), 0);
+        assert_eq!(xlsx_save(h, c(out).as_ptr()), 0);
+        xlsx_free(h);
+    }
+    assert_eq!(last_error(), None);
+
+    let mut ed = XlsxEditor::open(out, &scan(out)?[0])?;
+    let found = ed.find("capi-row")?;
+    assert_eq!(found.len(), 1);
+    let row = found[0].trim_start_matches('A');
+    assert_eq!(ed.get_cell(&format!("B{row}"))?, ReadValue::Number(42.0));
+    assert_eq!(ed.get_cell(&format!("C{row}"))?, ReadValue::Bool(true));
+    assert_eq!(ed.get_cell("H30")?, ReadValue::Text("from C".into()));
+    Ok(())
+}
+
+#[test]
+fn null_arguments_return_error() -> Result<()> {
+    unsafe {
+        assert_eq!(xlsx_append_row_json(ptr::null_mut(), c("[1]").as_ptr()), -1);
+        assert_eq!(last_error().as_deref(), Some("editor handle is null"));
+        assert_eq!(xlsx_save(ptr::null_mut(), c("x.xlsx").as_ptr()), -1);
+        assert!(xlsx_open(ptr::null(), c("Sheet1").as_ptr()).is_null());
+        assert_eq!(last_error().as_deref(), Some("path is null"));
+
+        let h = open_test()?;
+        assert_eq!(xlsx_append_row_json(h, ptr::null()), -1);
+        assert_eq!(last_error().as_deref(), Some("json is null"));
+        assert_eq!(xlsx_set_cell(h, c("A1").as_ptr(), ptr::null()), -1);
+        assert_eq!(last_error().as_deref(), Some("value is null"));
+        assert_eq!(xlsx_save(h, ptr::null()), -1);
+        assert_eq!(last_error().as_deref(), Some("path is null"));
+        xlsx_free(h);
+    }
+    Ok(())
+}
+
+#[test]
+fn bad_json_sets_last_error_until_next_success() -> Result<()> {
+    let h = open_test()?;
+    unsafe {
+        assert_eq!(xlsx_append_row_json(h, c("[1, ").as_ptr()), -1);
+        let err = last_error().expect("error is set");
+        assert!(err.starts_with("bad row JSON"), "{err}");
+
+        assert_eq!(xlsx_append_row_json(h, c("[1]").as_ptr()), 0);
+        assert_eq!(last_error(), None);
+        xlsx_free(h);
+    }
+    Ok(())
+}
+
+#[test]
+fn free_null_is_noop() {
+    unsafe { xlsx_free(ptr::null_mut()) };
+}
@@ -0,0 +1,290 @@
+//! xlsx-append – правка книг из командной строки: список листов, дозапись CSV,
+//! ячейки, шаблоны и стили. Данные можно подать через stdin (`-`); книга
+//! пишется во временный файл рядом и подменяется переименованием, так что
+//! оборванный запуск не оставляет полузаписанный файл.
+
+use anyhow::{Context, Result};
+use rust_core::csv_part::CsvOptions;
+use rust_core::json_part::parse_map;
+use rust_core::{XlsxEditor, scan};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[cfg(feature = "alloc-mimalloc")]
+#[global_allocator]
+static GLOBAL: rust_core::MiMalloc = rust_core::MiMalloc;
+
+const USAGE: &str = "\
+usage:
+  xlsx-append scan <book>
+  xlsx-append append <book> [--csv FILE|-] [--delimiter C] [--no-header] [--write-header]
+  xlsx-append set-cell <book> <CELL> <VALUE>
+  xlsx-append fill-template <book> --json FILE|-
+  xlsx-append style <book> --range RANGE [--bold] [--italic] [--font NAME] [--size PT]
+                    [--fill RRGGBB] [--border STYLE] [--format CODE]
+
+common options:
+  --sheet NAME      sheet to edit (default: the first one)
+  -o, --output OUT  write to OUT instead of replacing <book>
+  -                 read CSV or JSON from stdin";
+
+/// Аргументы после имени команды; флаги снимаются по мере разбора,
+/// лишние в конце — ошибка.
+struct Args(Vec<String>);
+
+impl Args {
+    fn flag(&mut self, name: &str) -> bool {
+        let found = self.0.iter().position(|a| a == name);
+        found.map(|i| self.0.remove(i)).is_some()
+    }
+
+    /// `--name VALUE` или `--name=VALUE` (для каждого из `names`).
+    fn value(&mut self, names: &[&str]) -> Result<Option<String>> {
+        for name in names {
+            if let Some(i) = self.0.iter().position(|a| a == name) {
+                self.0.remove(i);
+                ensure_arg(i < self.0.len(), || format!("{name} needs a value"))?;
+                return Ok(Some(self.0.remove(i)));
+            }
+            let prefix = format!("{name}=");
+            if let Some(i) = self.0.iter().position(|a| a.starts_with(&prefix)) {
+                return Ok(Some(self.0.remove(i)[prefix.len()..].to_owned()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Первый аргумент, не похожий на флаг (`-` и отрицательные числа —
+    /// позиционные). Вызывать после разбора всех флагов со значениями.
+    fn positional(&mut self, what: &str) -> Result<String> {
+        let i = self
+            .0
+            .iter()
+            .position(|a| a == "-" || !a.starts_with('-') || a.parse::<f64>().is_ok());
+        ensure_arg(i.is_some(), || format!("missing {what}"))?;
+        Ok(self.0.remove(i.unwrap_or_default()))
+    }
+
+    fn finish(self) -> Result<()> {
+        ensure_arg(self.0.is_empty(), || {
+            format!("unexpected argument {}", self.0.join(" "))
+        })
+    }
+}
+
+/// Ошибка разбора аргументов: печатается с подсказкой по использованию.
+#[derive(Debug)]
+struct UsageError(String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+fn ensure_arg(ok: bool, msg: impl FnOnce() -> String) -> Result<()> {
+    if ok {
+        Ok(())
+    } else {
+        Err(UsageError(msg()).into())
+    }
+}
+
+/// Содержимое `path`, для `-` — stdin.
+fn read_input(path: &str) -> Result<Vec<u8>> {
+    if path == "-" {
+        let mut buf = Vec::new();
+        io::stdin()
+            .read_to_end(&mut buf)
+            .context("cannot read stdin")?;
+        Ok(buf)
+    } else {
+        fs::read(path).with_context(|| format!("cannot read {path}"))
+    }
+}
+
+/// Общие флаги команд правки: `--sheet` и `--output`.
+struct Common {
+    sheet: Option<String>,
+    output: Option<PathBuf>,
+}
+
+impl Common {
+    fn take(args: &mut Args) -> Result<Self> {
+        Ok(Common {
+            sheet: args.value(&["--sheet"])?,
+            output: args.value(&["-o", "--output"])?.map(PathBuf::from),
+        })
+    }
+
+    fn open(self, book: String) -> Result<Target> {
+        let book = PathBuf::from(book);
+        let sheet = match self.sheet {
+            Some(s) => s,
+            None => scan(&book)?
+                .into_iter()
+                .next()
+                .with_context(|| format!("{} has no sheets", book.display()))?,
+        };
+        let editor = XlsxEditor::open(&book, &sheet)?;
+        let output = self.output.unwrap_or(book);
+        Ok(Target { editor, output })
+    }
+}
+
+/// Книга для правки и путь, куда её сохранить.
+struct Target {
+    editor: XlsxEditor,
+    output: PathBuf,
+}
+
+impl Target {
+    /// Сохраняет во временный файл в той же папке и переименовывает поверх
+    /// `output`. Расширение временного файла то же (xlsx/xlsm важно для save).
+    fn save(mut self) -> Result<()> {
+        let dst = &self.output;
+        let dir = dst
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let stem = dst
+            .file_stem()
+            .context("output path has no file name")?
+            .to_string_lossy();
+        let ext = dst
+            .extension()
+            .map(|e| e.to_string_lossy())
+            .unwrap_or_default();
+        let tmp = dir.join(format!(".{stem}.{}.{ext}", std::process::id()));
+        let res = self
+            .editor
+            .save(&tmp)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| {
+                fs::rename(&tmp, dst).with_context(|| format!("cannot replace {}", dst.display()))
+            });
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        res
+    }
+}
+
+fn run(cmd: &str, mut args: Args) -> Result<()> {
+    match cmd {
+        "scan" => {
+            let book = args.positional("workbook path")?;
+            args.finish()?;
+            for name in scan(book)? {
+                println!("{name}");
+            }
+            Ok(())
+        }
+        "append" => {
+            let common = Common::take(&mut args)?;
+            let input = args.value(&["--csv"])?.unwrap_or_else(|| "-".into());
+            let mut opts = CsvOptions {
+                has_header: !args.flag("--no-header"),
+                write_header: args.flag("--write-header"),
+                ..Default::default()
+            };
+            if let Some(d) = args.value(&["--delimiter"])? {
+                opts.delimiter = match d.as_str() {
+                    "\\t" | "tab" => b'\t',
+                    d if d.len() == 1 => d.as_bytes()[0],
+                    _ => return Err(UsageError(format!("bad delimiter {d:?}")).into()),
+                };
+            }
+            let book = args.positional("workbook path")?;
+            args.finish()?;
+            let mut t = common.open(book)?;
+            let rows = t.editor.append_csv(read_input(&input)?.as_slice(), &opts)?;
+            t.save()?;
+            eprintln!("appended {rows} rows");
+            Ok(())
+        }
+        "set-cell" => {
+            let common = Common::take(&mut args)?;
+            let book = args.positional("workbook path")?;
+            let cell = args.positional("cell")?;
+            let value = args.positional("value")?;
+            args.finish()?;
+            let mut t = common.open(book)?;
+            t.editor.set_cell(&cell, value)?;
+            t.save()
+        }
+        "fill-template" => {
+            let common = Common::take(&mut args)?;
+            let input = args.value(&["--json"])?;
+            ensure_arg(input.is_some(), || "fill-template needs --json FILE".into())?;
+            let book = args.positional("workbook path")?;
+            args.finish()?;
+            let mut t = common.open(book)?;
+            let json = String::from_utf8(read_input(&input.unwrap_or_default())?)
+                .context("JSON is not valid UTF-8")?;
+            let values = parse_map(&json).context("bad template JSON")?;
+            t.editor.fill_template(&values)?;
+            t.save()
+        }
+        "style" => {
+            let common = Common::take(&mut args)?;
+            let range = args.value(&["--range"])?;
+            ensure_arg(range.is_some(), || "style needs --range".into())?;
+            let range = range.unwrap_or_default();
+            let (bold, italic) = (args.flag("--bold"), args.flag("--italic"));
+            let font = args.value(&["--font"])?;
+            let size = args
+                .value(&["--size"])?
+                .map(|s| {
+                    s.parse::<f32>()
+                        .with_context(|| format!("bad font size {s}"))
+                })
+                .transpose()?;
+            let fill = args.value(&["--fill"])?;
+            let border = args.value(&["--border"])?;
+            let format = args.value(&["--format"])?;
+            let book = args.positional("workbook path")?;
+            args.finish()?;
+            let mut t = common.open(book)?;
+            if bold || italic || font.is_some() || size.is_some() {
+                let font = font.as_deref().unwrap_or("Calibri");
+                t.editor
+                    .set_font(&range, font, size.unwrap_or(11.0), bold, italic)?;
+            }
+            if let Some(rgb) = fill {
+                t.editor.set_fill(&range, &rgb)?;
+            }
+            if let Some(style) = border {
+                t.editor.set_border(&range, &style)?;
+            }
+            if let Some(fmt) = format {
+                t.editor.set_number_format(&range, &fmt)?;
+            }
+            t.save()
+        }
+        _ => Err(UsageError(format!("unknown command {cmd}")).into()),
+    }
+}
+
+fn main() -> ExitCode {
+    let mut argv = std::env::args().skip(1);
+    let Some(cmd) = argv.next().filter(|c| c != "-h" && c != "--help") else {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    };
+    match run(&cmd, Args(argv.collect())) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<UsageError>() => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
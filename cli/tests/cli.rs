@@ -0,0 +1,188 @@
+//! Прогон собранного `xlsx-append` на копиях `test/test.xlsx`; результат
+//! проверяется чтением книги через `rust-core`.
+
+use anyhow::Result;
+use rust_core::read_part::ReadValue;
+use rust_core::theme_part::Color;
+use rust_core::{XlsxEditor, scan};
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const SRC: &str = "../test/test.xlsx";
+
+fn xlsx_append(args: &[&str], stdin: Option<&str>) -> Result<Output> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_xlsx-append"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut pipe = child.stdin.take().unwrap();
+    pipe.write_all(stdin.unwrap_or_default().as_bytes())?;
+    drop(pipe);
+    Ok(child.wait_with_output()?)
+}
+
+/// Как [`xlsx_append`], но команда обязана пройти.
+fn run_ok(args: &[&str], stdin: Option<&str>) -> Result<Output> {
+    let out = xlsx_append(args, stdin)?;
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    Ok(out)
+}
+
+/// Копия исходной книги, которую тест правит на месте.
+fn copy_of_test(name: &str) -> Result<String> {
+    let path = format!("../test/{name}");
+    fs::copy(SRC, &path)?;
+    Ok(path)
+}
+
+fn open(path: &str) -> Result<XlsxEditor> {
+    Ok(XlsxEditor::open(path, &scan(path)?[0])?)
+}
+
+/// Строка, в которой лежит текст `marker` (ровно одна).
+fn row_of(ed: &mut XlsxEditor, marker: &str) -> Result<String> {
+    let found = ed.find(marker)?;
+    assert_eq!(found.len(), 1, "{marker}: {found:?}");
+    Ok(found[0].trim_start_matches(char::is_alphabetic).to_owned())
+}
+
+#[test]
+fn scan_lists_sheets() -> Result<()> {
+    let out = run_ok(&["scan", SRC], None)?;
+    let listed: Vec<String> = String::from_utf8(out.stdout)?
+        .lines()
+        .map(str::to_owned)
+        .collect();
+    assert_eq!(listed, scan(SRC)?);
+    Ok(())
+}
+
+#[test]
+fn append_csv_from_file_and_stdin() -> Result<()> {
+    let book = copy_of_test("cli_append_out.xlsx")?;
+    let csv = "../test/cli_append_out.csv";
+    fs::write(csv, "name,score\ncli-file,7\n")?;
+    run_ok(&["append", &book, "--csv", csv], None)?;
+    let out = run_ok(
+        &[
+            "append",
+            &book,
+            "--no-header",
+            "--delimiter",
+            ";",
+            "--csv",
+            "-",
+        ],
+        Some("cli-stdin;8\n"),
+    )?;
+    assert!(String::from_utf8_lossy(&out.stderr).contains("appended 1 rows"));
+
+    let mut ed = open(&book)?;
+    let row = row_of(&mut ed, "cli-file")?;
+    assert_eq!(ed.get_cell(&format!("B{row}"))?, ReadValue::Number(7.0));
+    assert!(ed.find("score")?.is_empty());
+    let row = row_of(&mut ed, "cli-stdin")?;
+    assert_eq!(ed.get_cell(&format!("B{row}"))?, ReadValue::Number(8.0));
+    Ok(())
+}
+
+#[test]
+fn set_cell_writes_value() -> Result<()> {
+    let book = copy_of_test("cli_set_cell_out.xlsx")?;
+    run_ok(&["set-cell", &book, "H30", "42"], None)?;
+    run_ok(&["set-cell", &book, "H31", "-1.5"], None)?;
+    run_ok(&["set-cell", &book, "H32", "cli text"], None)?;
+
+    let mut ed = open(&book)?;
+    assert_eq!(ed.get_cell("H30")?, ReadValue::Number(42.0));
+    assert_eq!(ed.get_cell("H31")?, ReadValue::Number(-1.5));
+    assert_eq!(ed.get_cell("H32")?, ReadValue::Text("cli text".into()));
+    Ok(())
+}
+
+#[test]
+fn fill_template_from_json() -> Result<()> {
+    let book = copy_of_test("cli_template_out.xlsx")?;
+    run_ok(&["set-cell", &book, "H30", "Hello, {{name}}!"], None)?;
+    run_ok(&["set-cell", &book, "H31", "{{total}}"], None)?;
+    let json = "../test/cli_template_out.json";
+    fs::write(json, r#"{"name": "World"}"#)?;
+    run_ok(&["fill-template", &book, "--json", json], None)?;
+    run_ok(
+        &["fill-template", &book, "--json", "-"],
+        Some(r#"{"total": 12.5}"#),
+    )?;
+
+    let mut ed = open(&book)?;
+    assert_eq!(ed.get_cell("H30")?, ReadValue::Text("Hello, World!".into()));
+    assert_eq!(ed.get_cell("H31")?, ReadValue::Number(12.5));
+    Ok(())
+}
+
+#[test]
+fn style_range() -> Result<()> {
+    let book = copy_of_test("cli_style_out.xlsx")?;
+    let out = "../test/cli_style_out_styled.xlsx";
+    run_ok(
+        &[
+            "style", &book, "--range", "A1:F1", "--bold", "--fill", "FFFF00", "-o", out,
+        ],
+        None,
+    )?;
+
+    let mut ed = open(out)?;
+    // в строке 1 книги есть только A1 и B1
+    for coord in ["A1", "B1"] {
+        let spec = ed.get_cell_style(coord)?;
+        assert!(spec.font.bold, "{coord}");
+        assert_eq!(spec.fill, Some(Color::Rgb("FFFF00".into())), "{coord}");
+    }
+    assert!(!ed.get_cell_style("A2")?.font.bold);
+    // с -o исходник не тронут
+    assert!(!open(&book)?.get_cell_style("A1")?.font.bold);
+    Ok(())
+}
+
+#[test]
+fn failed_save_leaves_output_and_no_temp_file() -> Result<()> {
+    let book = copy_of_test("cli_atomic_out.xlsx")?;
+    let before = fs::read(&book)?;
+    let out = xlsx_append(&["set-cell", &book, "not-a-cell", "1"], None)?;
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(fs::read(&book)?, before);
+
+    // книга сохраняется, но подменить ею каталог нельзя
+    let dir = "../test/cli_atomic_dir_out.xlsx";
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir(dir)?;
+    fs::write(format!("{dir}/keep.txt"), "keep")?;
+    let out = xlsx_append(&["set-cell", &book, "A1", "x", "-o", dir], None)?;
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(1), "{stderr}");
+    assert!(stderr.contains("cannot replace"), "{stderr}");
+    assert_eq!(fs::read_to_string(format!("{dir}/keep.txt"))?, "keep");
+    fs::remove_dir_all(dir)?;
+
+    let leftovers: Vec<_> = fs::read_dir("../test")?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|n| n.starts_with(".cli_atomic"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+    Ok(())
+}
+
+#[test]
+fn bad_usage_exits_with_2() -> Result<()> {
+    let out = xlsx_append(&["set-cell", SRC, "A1"], None)?;
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("missing value"));
+    Ok(())
+}
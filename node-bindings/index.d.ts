@@ -0,0 +1,50 @@
+/* tslint:disable */
+/* eslint-disable */
+
+/* auto-generated by NAPI-RS */
+
+/** Выравнивание для `setAlignment` и `setFont`. */
+export interface Alignment {
+  /** `left`, `center`, `right`, `fill` или `justify`. */
+  horiz?: string
+  /** `top`, `center`, `bottom` или `justify`. */
+  vert?: string
+  wrap?: boolean
+}
+/** Имена листов книги по порядку. */
+export declare function scanExcel(path: string): Array<string>
+/** Пара «имя листа → редактор»; один лист открыт в каждый момент. */
+export declare class XlsxEditor {
+  /** Открывает лист `sheetName` книги `path`. */
+  constructor(path: string, sheetName: string)
+  /**
+   * Дописывает строку после последней: `null` — пустая ячейка, `Date` —
+   * дата, строка с `=` — формула, числовая строка — число.
+   */
+  appendRow(cells: Array<unknown>): void
+  /** Дописывает строки подряд, как `appendRow` для каждой. */
+  appendTable(rows: Array<Array<unknown>>): void
+  /**
+   * Пишет столбцы `{ name, values }` с ячейки `startCell`: строка имён,
+   * под ней значения.
+   */
+  appendColumns(startCell: string, columns: Array<{ name: string, values: unknown[] }>): void
+  /** Переключается на лист `sheetName`. */
+  withWorksheet(sheetName: string): void
+  /** Добавляет лист в конец книги и переключается на него. */
+  addWorksheet(sheetName: string): void
+  setNumberFormat(range: string, fmt: string): void
+  /** Заливка цветом `rgb` (`"FFFF00"`). */
+  setFill(range: string, rgb: string): void
+  setFont(range: string, name: string, size: number, bold?: boolean | undefined | null, italic?: boolean | undefined | null, align?: Alignment | undefined | null): void
+  /** Рамка стиля `borderStyle` (`thin`, `medium`, `thick`...). */
+  setBorder(range: string, borderStyle: string): void
+  setAlignment(range: string, align: Alignment): void
+  setColumnWidth(col: string, width: number): void
+  /** Высота строк `rows` (`"3"` или `"3:7"`) в пунктах. */
+  setRowHeight(rows: string, height: number): void
+  mergeCells(range: string): void
+  save(path: string): void
+  /** Книга целиком как `Buffer`, без записи на диск. */
+  toBuffer(): Buffer
+}
@@ -0,0 +1,311 @@
+//! Привязки к Node.js через napi-rs: тот же набор, что у `Editor` в Python, —
+//! открытие, дописывание строк и столбцов типизированными значениями, стили,
+//! сохранение в файл или в `Buffer`.
+
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, JsDate, JsObject, JsUnknown, Result, ValueType};
+use napi_derive::napi;
+use rust_core::XlsxEditor as Core;
+use rust_core::XlsxError;
+use rust_core::columns_part::ColumnData;
+use rust_core::style::AlignSpec;
+use rust_core::template_part::{CellValue, DATE_FORMAT, DATETIME_FORMAT};
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+fn js_err(e: XlsxError) -> Error {
+    Error::from_reason(format!("{e:#}"))
+}
+
+/// Значение JS в ячейку: `null`/`undefined` — пустая, `boolean`/`number` —
+/// значение, `Date` — сериал даты в UTC (без времени — формат даты), строка
+/// разбирается как в `append_row`: `=` — формула, число — число.
+fn to_typed_cell(v: JsUnknown) -> Result<(CellValue, Option<&'static str>)> {
+    Ok(match v.get_type()? {
+        ValueType::Null | ValueType::Undefined => (CellValue::Empty, None),
+        ValueType::Boolean => (CellValue::Bool(v.coerce_to_bool()?.get_value()?), None),
+        ValueType::Number => match v.coerce_to_number()?.get_double()? {
+            n if n.is_finite() => (CellValue::Number(n), None),
+            _ => (CellValue::Empty, None),
+        },
+        ValueType::Object if v.is_date()? => {
+            let ms = unsafe { v.cast::<JsDate>() }.value_of()?;
+            let fmt = if ms % MS_PER_DAY == 0.0 {
+                DATE_FORMAT
+            } else {
+                DATETIME_FORMAT
+            };
+            (CellValue::unix_days(ms / MS_PER_DAY), Some(fmt))
+        }
+        _ => {
+            let s = v.coerce_to_string()?.into_utf8()?;
+            (CellValue::parse(s.as_str()?), None)
+        }
+    })
+}
+
+/// Столбец `{ name, values }` для `appendColumns`: тип берётся по первому
+/// непустому значению (`number`, `boolean`, `string` или `Date`).
+fn to_column(col: &JsObject) -> Result<(String, ColumnData)> {
+    let name: String = col.get_named_property("name")?;
+    let values: JsObject = col.get_named_property("values")?;
+    let len = values.get_array_length()?;
+    let items = (0..len)
+        .map(|i| values.get_element::<JsUnknown>(i))
+        .collect::<Result<Vec<_>>>()?;
+    let mut kind = ValueType::Null;
+    for v in &items {
+        match v.get_type()? {
+            ValueType::Null | ValueType::Undefined => continue,
+            ValueType::Object if v.is_date()? => kind = ValueType::Object,
+            t => kind = t,
+        }
+        break;
+    }
+    let bad = |v: &JsUnknown| -> Result<Error> {
+        Ok(Error::from_reason(format!(
+            "column {name}: mixed value types ({:?} after {kind:?})",
+            v.get_type()?
+        )))
+    };
+    let empty = |v: &JsUnknown| -> Result<bool> {
+        Ok(matches!(
+            v.get_type()?,
+            ValueType::Null | ValueType::Undefined
+        ))
+    };
+    let data = match kind {
+        ValueType::Null | ValueType::Number => {
+            let mut out = Vec::with_capacity(items.len());
+            for v in items {
+                out.push(match v.get_type()? {
+                    ValueType::Number => v.coerce_to_number()?.get_double()?,
+                    _ if empty(&v)? => f64::NAN,
+                    _ => return Err(bad(&v)?),
+                });
+            }
+            ColumnData::F64(out)
+        }
+        ValueType::Boolean => {
+            let mut out = Vec::with_capacity(items.len());
+            for v in items {
+                match v.get_type()? {
+                    ValueType::Boolean => out.push(v.coerce_to_bool()?.get_value()?),
+                    _ => return Err(bad(&v)?),
+                }
+            }
+            ColumnData::Bool(out)
+        }
+        ValueType::String => {
+            let mut out = Vec::with_capacity(items.len());
+            for v in items {
+                out.push(match v.get_type()? {
+                    ValueType::String => v.coerce_to_string()?.into_utf8()?.into_owned()?,
+                    _ if empty(&v)? => String::new(),
+                    _ => return Err(bad(&v)?),
+                });
+            }
+            ColumnData::Str(out)
+        }
+        ValueType::Object => {
+            let mut out = Vec::with_capacity(items.len());
+            for v in items {
+                if !v.is_date()? {
+                    return Err(bad(&v)?);
+                }
+                out.push(unsafe { v.cast::<JsDate>() }.value_of()? as i64);
+            }
+            ColumnData::DateTime(out)
+        }
+        _ => return Err(bad(&items[0])?),
+    };
+    Ok((name, data))
+}
+
+/// Выравнивание для `setAlignment` и `setFont`.
+#[napi(object)]
+pub struct Alignment {
+    /// `left`, `center`, `right`, `fill` или `justify`.
+    pub horiz: Option<String>,
+    /// `top`, `center`, `bottom` или `justify`.
+    pub vert: Option<String>,
+    pub wrap: Option<bool>,
+}
+
+impl Alignment {
+    fn to_spec(&self) -> Result<AlignSpec> {
+        Ok(AlignSpec {
+            horiz: self
+                .horiz
+                .as_deref()
+                .map(str::parse)
+                .transpose()
+                .map_err(js_err)?,
+            vert: self
+                .vert
+                .as_deref()
+                .map(str::parse)
+                .transpose()
+                .map_err(js_err)?,
+            wrap: self.wrap.unwrap_or(false),
+        })
+    }
+}
+
+/// Пара «имя листа → редактор»; один лист открыт в каждый момент.
+#[napi]
+pub struct XlsxEditor {
+    editor: Core,
+}
+
+#[napi]
+impl XlsxEditor {
+    /// Открывает лист `sheetName` книги `path`.
+    #[napi(constructor)]
+    pub fn new(path: String, sheet_name: String) -> Result<Self> {
+        let editor = Core::open(path, &sheet_name).map_err(js_err)?;
+        Ok(XlsxEditor { editor })
+    }
+
+    /// Дописывает строку после последней: `null` — пустая ячейка, `Date` —
+    /// дата, строка с `=` — формула, числовая строка — число.
+    #[napi]
+    pub fn append_row(&mut self, cells: Vec<JsUnknown>) -> Result<()> {
+        let cells = cells
+            .into_iter()
+            .map(to_typed_cell)
+            .collect::<Result<Vec<_>>>()?;
+        self.editor.append_values(&cells).map_err(js_err)
+    }
+
+    /// Дописывает строки подряд, как `appendRow` для каждой.
+    #[napi]
+    pub fn append_table(&mut self, rows: Vec<Vec<JsUnknown>>) -> Result<()> {
+        for row in rows {
+            self.append_row(row)?;
+        }
+        Ok(())
+    }
+
+    /// Пишет столбцы `{ name, values }` с ячейки `startCell`: строка имён,
+    /// под ней значения.
+    #[napi(ts_args_type = "startCell: string, columns: Array<{ name: string, values: unknown[] }>")]
+    pub fn append_columns(&mut self, start_cell: String, columns: Vec<JsObject>) -> Result<()> {
+        let (names, data): (Vec<String>, Vec<ColumnData>) = columns
+            .iter()
+            .map(to_column)
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        let columns: Vec<(&str, ColumnData)> = names.iter().map(String::as_str).zip(data).collect();
+        self.editor
+            .append_columns(&start_cell, &columns)
+            .map_err(js_err)
+    }
+
+    /// Переключается на лист `sheetName`.
+    #[napi]
+    pub fn with_worksheet(&mut self, sheet_name: String) -> Result<()> {
+        self.editor.with_worksheet(&sheet_name).map_err(js_err)?;
+        Ok(())
+    }
+
+    /// Добавляет лист в конец книги и переключается на него.
+    #[napi]
+    pub fn add_worksheet(&mut self, sheet_name: String) -> Result<()> {
+        self.editor.add_worksheet(&sheet_name).map_err(js_err)?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn set_number_format(&mut self, range: String, fmt: String) -> Result<()> {
+        self.editor.set_number_format(&range, &fmt).map_err(js_err)
+    }
+
+    /// Заливка цветом `rgb` (`"FFFF00"`).
+    #[napi]
+    pub fn set_fill(&mut self, range: String, rgb: String) -> Result<()> {
+        self.editor.set_fill(&range, &rgb).map_err(js_err)?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn set_font(
+        &mut self,
+        range: String,
+        name: String,
+        size: f64,
+        bold: Option<bool>,
+        italic: Option<bool>,
+        align: Option<Alignment>,
+    ) -> Result<()> {
+        let (bold, italic) = (bold.unwrap_or(false), italic.unwrap_or(false));
+        match align {
+            Some(a) => self.editor.set_font_with_alignment(
+                &range,
+                &name,
+                size as f32,
+                bold,
+                italic,
+                &a.to_spec()?,
+            ),
+            None => self
+                .editor
+                .set_font(&range, &name, size as f32, bold, italic),
+        }
+        .map_err(js_err)?;
+        Ok(())
+    }
+
+    /// Рамка стиля `borderStyle` (`thin`, `medium`, `thick`...).
+    #[napi]
+    pub fn set_border(&mut self, range: String, border_style: String) -> Result<()> {
+        self.editor
+            .set_border(&range, &border_style)
+            .map_err(js_err)?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn set_alignment(&mut self, range: String, align: Alignment) -> Result<()> {
+        self.editor
+            .set_alignment(&range, &align.to_spec()?)
+            .map_err(js_err)?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn set_column_width(&mut self, col: String, width: f64) -> Result<()> {
+        self.editor.set_column_width(&col, width).map_err(js_err)?;
+        Ok(())
+    }
+
+    /// Высота строк `rows` (`"3"` или `"3:7"`) в пунктах.
+    #[napi]
+    pub fn set_row_height(&mut self, rows: String, height: f64) -> Result<()> {
+        self.editor.set_row_height(&rows, height).map_err(js_err)?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn merge_cells(&mut self, range: String) -> Result<()> {
+        self.editor.merge_cells(&range).map_err(js_err)
+    }
+
+    #[napi]
+    pub fn save(&mut self, path: String) -> Result<()> {
+        self.editor.save(path).map_err(js_err)
+    }
+
+    /// Книга целиком как `Buffer`, без записи на диск.
+    #[napi]
+    pub fn to_buffer(&mut self) -> Result<Buffer> {
+        Ok(self.editor.save_to_bytes().map_err(js_err)?.into())
+    }
+}
+
+/// Имена листов книги по порядку.
+#[napi]
+pub fn scan_excel(path: String) -> Result<Vec<String>> {
+    rust_core::scan(path).map_err(js_err)
+}
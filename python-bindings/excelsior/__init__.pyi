@@ -1,8 +1,16 @@
 # type: ignore[list-item]
-from typing import List, Optional
+import datetime
+import os
+from typing import Any, Dict, List, Literal, Optional, Tuple, Union, overload
 from polars import DataFrame
 from enum import Enum  # <-- Важно импортировать Enum
 
+# Значение для записи: None — пустая ячейка, date/datetime — дата с форматом,
+# строки разбираются (`=` — формула, число — число).
+CellInput = Union[str, int, float, bool, None, datetime.date, datetime.datetime]
+# Прочитанное значение: целые числа — int, числа в формате даты — date/datetime.
+CellOutput = Union[None, int, float, bool, str, datetime.date, datetime.datetime]
+
 # --- НОВЫЕ КЛАССЫ, ВИДИМЫЕ В PYTHON ---
 
 class HorizAlignment(Enum):
@@ -29,25 +37,281 @@ class AlignSpec:
         wrap: bool = False,
     ) -> None: ...
 
+# --- ИСКЛЮЧЕНИЯ ---
+
+class SheetNotFoundError(RuntimeError):
+    """Листа с таким именем нет в книге."""
+    sheet: str
+
+class XlsxCorruptError(RuntimeError):
+    """Файл повреждён: XML части или архив не читаются."""
+
+class MalformedSheetError(XlsxCorruptError):
+    """XML части книги не разбирается."""
+    part: str
+    offset: int
+
+class RowLimitExceededError(RuntimeError):
+    """Запись за пределы строки 1 048 576."""
+    row: int
+
+class InvalidRangeError(RuntimeError):
+    """Неверная ссылка на ячейку или диапазон."""
+    range: str
+
+class ZipError(XlsxCorruptError):
+    """Архив не открывается или не читается."""
+
 # --- СУЩЕСТВУЮЩИЕ И ОБНОВЛЕННЫЕ КЛАССЫ ---
 
 def scan_excel(path: str) -> List[str]: ...
+def scan_workbook(path: str) -> Dict[str, Any]: ...
+def diff(path_a: str, path_b: str) -> Dict[str, Any]: ...
+def adjust_formula(formula: str, row_delta: int, col_delta: int = 0) -> str: ...
+def translate_r1c1_to_a1(formula: str, cell: str) -> str: ...
+
+class NumFmt:
+    GENERAL: str
+    INTEGER: str
+    DECIMAL: str
+    THOUSANDS: str
+    THOUSANDS_DECIMAL: str
+    PERCENT: str
+    PERCENT_DECIMAL: str
+    SCIENTIFIC: str
+    FRACTION: str
+    DATE: str
+    DATE_LONG: str
+    TIME: str
+    TIME_SECONDS: str
+    TIME_AM_PM: str
+    DATETIME: str
+    DURATION: str
+    ACCOUNTING: str
+    ACCOUNTING_RED: str
+    ACCOUNTING_DECIMAL: str
+    ACCOUNTING_DECIMAL_RED: str
+    TEXT: str
+    @staticmethod
+    def date_iso() -> str: ...
+    @staticmethod
+    def datetime_iso() -> str: ...
+    @staticmethod
+    def decimal(places: int = 2) -> str: ...
+    @staticmethod
+    def thousands(places: int = 2) -> str: ...
+    @staticmethod
+    def percent(places: int = 0) -> str: ...
+    @staticmethod
+    def scientific(places: int = 2) -> str: ...
+    @staticmethod
+    def currency(iso: str) -> str: ...
+    @staticmethod
+    def builtin_id(code: str) -> Optional[int]: ...
+
+class RowIterator:
+    """Строки листа по одной: кортежи значений, пропущенные строки — пустые."""
+    def __iter__(self) -> "RowIterator": ...
+    def __next__(self) -> Tuple[CellOutput, ...]: ...
+
+class Transaction:
+    """Контекст `Editor.transaction()`: исключение в блоке откатывает правки."""
+    def __enter__(self) -> "Editor": ...
+    def __exit__(self, exc_type: Any, _exc_value: Any, _traceback: Any) -> bool: ...
 
 class Editor:
-    def __init__(self, path: str, sheet_name: str) -> None: ...
-    def append_row(self, cells: List[str]) -> None: ...
+    def __init__(self, path: str, sheet_name: str, save_to: Optional[str] = None) -> None: ...
+    def __enter__(self) -> "Editor": ...
+    def __exit__(self, exc_type: Any, _exc_value: Any, _traceback: Any) -> bool: ...
+    def begin(self) -> "Editor": ...
+    def commit(self) -> "Editor": ...
+    def rollback(self) -> "Editor": ...
+    def transaction(self) -> Transaction: ...
+    def set_save_path(self, path: str) -> "Editor": ...
+    def append_row(
+        self,
+        cells: List[CellInput],
+    ) -> None: ...
+    def append_table(
+        self,
+        rows: List[List[CellInput]],
+    ) -> None: ...
+    def append_records(
+        self,
+        records: List[Dict[str, Any]],
+        add_columns: bool = False,
+    ) -> None: ...
+    def upsert_rows(self, key_column: str, rows: List[Dict[str, Any]]) -> None: ...
     def append_table_at(self, cells: List[List[str]], start_cell: str) -> None: ...
+    def repair_sheet(self) -> List[str]: ...
+    def validate(self) -> List[Dict[str, str]]: ...
+    def change_summary(self) -> Dict[str, Any]: ...
+    def write_audit_sheet(self, name: str = "audit") -> "Editor": ...
     def save(self, path: str) -> None: ...
+    @staticmethod
+    def open_encrypted(path: str, password: str, sheet_name: str) -> "Editor": ...
+    def save_encrypted(self, path: str, password: str) -> None: ...
+    def set_recalc_on_change(self, enabled: bool = True) -> "Editor": ...
+    def set_row_spans(self, enabled: bool = True) -> "Editor": ...
+    def set_inherit_column_styles(self, enabled: bool = True) -> "Editor": ...
+    def set_xml_layout(self, layout: Literal["as_written", "match_source", "compact"]) -> "Editor": ...
+    def set_preserve_zip_metadata(self, enabled: bool = True) -> "Editor": ...
+    def set_compression_threads(self, threads: int) -> "Editor": ...
+    def set_allow_macro_removal(self, enabled: bool = True) -> "Editor": ...
+    def has_macros(self) -> bool: ...
+    def set_full_calc_on_load(self) -> "Editor": ...
+    def evaluate_formulas(self) -> "Editor": ...
     def last_row_index(self, col_name: str) -> int: ...
     def last_rows_index(self, col_name: str) -> List[int]: ...
+    def used_range(self) -> Dict[str, Any]: ...
+    def get_cell(self, coord: str) -> CellOutput: ...
+    def get_number_format(self, coord: str) -> Optional[str]: ...
+    def get_cell_style(self, coord: str) -> Dict[str, Any]: ...
+    def set_sheet_default_style(self, style: Dict[str, Any]) -> "Editor": ...
+    def set_column_style(
+        self, cols: str, style: Dict[str, Any], stamp_cells: bool = False
+    ) -> "Editor": ...
+    def get_range(self, range: str) -> List[List[CellOutput]]: ...
+    def rows(self, sheet: Optional[str] = None) -> "RowIterator": ...
+    def to_pandas(self, range: Optional[str] = None, has_header: bool = True) -> Any: ...
     def with_polars(self, df: DataFrame, start_cell: Optional[str] = None, default_width: float = 15.0) -> None: ...
+    def with_polars_chunked(self, df: DataFrame, chunk_rows: int, start_cell: Optional[str] = None) -> None: ...
+    def write_polars(
+        self,
+        df: DataFrame,
+        start_cell: Optional[str] = None,
+        number_formats: Optional[Dict[str, str]] = None,
+        header_bold: bool = False,
+        header_fill: Optional[str] = None,
+        header_border: Optional[str] = None,
+        autofit: bool = False,
+        freeze_header: bool = False,
+        table: Optional[str] = None,
+        table_style: Optional[str] = None,
+        autofilter: bool = False,
+    ) -> List[Tuple[str, str]]: ...
+    def append_polars(self, df: DataFrame, include_header: bool = False) -> None: ...
+    def to_polars(self, range: Optional[str] = None, has_header: bool = True) -> DataFrame: ...
     def add_worksheet(self, sheet_name: str) -> "Editor": ...
     def add_worksheet_at(self, sheet_name: str, index: int) -> "Editor": ...
+    def rename_worksheet(self, old: str, new: str) -> "Editor": ...
+    def delete_worksheet(self, sheet_name: str) -> "Editor": ...
+    def copy_worksheet(self, src: str, new_name: str) -> "Editor": ...
+    def duplicate_sheet_as_template(self, src_sheet: str, new_name: str) -> "Editor": ...
+    def move_worksheet(self, sheet_name: str, index: int) -> "Editor": ...
+    def hide_worksheet(
+        self, sheet_name: str, visibility: Literal["visible", "hidden", "veryHidden"] = "hidden"
+    ) -> "Editor": ...
+    def set_active_sheet(self, sheet_name: str) -> "Editor": ...
+    def protect_sheet(
+        self,
+        sheet_name: str,
+        password: Optional[str] = None,
+        allow: Optional[
+            List[
+                Literal[
+                    "select_locked_cells",
+                    "select_unlocked_cells",
+                    "format_cells",
+                    "format_columns",
+                    "format_rows",
+                    "insert_columns",
+                    "insert_rows",
+                    "insert_hyperlinks",
+                    "delete_columns",
+                    "delete_rows",
+                    "sort",
+                    "auto_filter",
+                    "pivot_tables",
+                    "objects",
+                    "scenarios",
+                ]
+            ]
+        ] = None,
+    ) -> "Editor": ...
+    def unprotect_sheet(self, sheet_name: str) -> "Editor": ...
+    def protect_workbook(self, password: Optional[str] = None) -> "Editor": ...
+    def unprotect_workbook(self) -> "Editor": ...
+    def set_tab_color(self, sheet_name: str, rgb: str) -> "Editor": ...
+    def set_sheet_properties(
+        self,
+        sheet_name: str,
+        default_col_width: Optional[float] = None,
+        default_row_height: Optional[float] = None,
+        summary_below: Optional[bool] = None,
+        summary_right: Optional[bool] = None,
+    ) -> "Editor": ...
+    def set_doc_properties(
+        self,
+        title: Optional[str] = None,
+        subject: Optional[str] = None,
+        author: Optional[str] = None,
+        keywords: Optional[str] = None,
+        description: Optional[str] = None,
+        category: Optional[str] = None,
+        status: Optional[str] = None,
+        company: Optional[str] = None,
+        manager: Optional[str] = None,
+    ) -> "Editor": ...
+    def set_custom_property(
+        self, name: str, value: Union[str, float, bool, datetime.date]
+    ) -> "Editor": ...
+    def set_last_modified_by(self, name: str) -> "Editor": ...
+    def set_stamp_modified(self, enabled: bool = True) -> "Editor": ...
     def with_worksheet(self, sheet_name: str) -> "Editor": ...
     def set_number_format(self, range: str, fmt: str) -> "Editor": ...
     def set_fill(self, range: str, fmt: str) -> "Editor": ...
+    def set_cell_protection(
+        self, range: str, locked: bool = True, hidden: bool = False
+    ) -> "Editor": ...
     def merge_cells(self, range: str) -> "Editor": ...
-    def set_border(self, range: str, style: str) -> "Editor": ...
+    def unmerge_cells(self, range: str) -> "Editor": ...
+    def get_merged_ranges(self) -> List[str]: ...
+    def insert_rows(self, at_row: int, count: int) -> "Editor": ...
+    def insert_row_sorted(self, column: str, cells: List[Any]) -> int: ...
+    def insert_columns(self, before: str, count: int) -> "Editor": ...
+    def delete_rows(self, rows: str) -> "Editor": ...
+    def delete_columns(self, cols: str) -> "Editor": ...
+    def clear_rows(self, rows: str) -> "Editor": ...
+    def group_rows(self, rows: str, level: int = 1, collapsed: bool = False) -> "Editor": ...
+    def group_columns(
+        self, cols: str, level: int = 1, collapsed: bool = False
+    ) -> "Editor": ...
+    def hide_columns(self, cols: str) -> "Editor": ...
+    def show_columns(self, cols: str) -> "Editor": ...
+    def set_column_outline_level(self, cols: str, level: int) -> "Editor": ...
+    def copy_range(self, src: str, dst: str, adjust_formulas: bool = True) -> "Editor": ...
+    def move_range(self, src: str, dst: str, adjust_formulas: bool = True) -> "Editor": ...
+    def clear_range(self, range: str, mode: Literal["contents", "formats", "all"] = "all") -> "Editor": ...
+    def find(
+        self,
+        pattern: str,
+        match_case: bool = False,
+        whole_cell: bool = False,
+        regex: bool = False,
+        range: Optional[str] = None,
+    ) -> List[str]: ...
+    def replace_all(
+        self,
+        pattern: str,
+        replacement: str,
+        match_case: bool = False,
+        whole_cell: bool = False,
+        regex: bool = False,
+        range: Optional[str] = None,
+    ) -> int: ...
+    def append_csv(
+        self,
+        path: Union[str, os.PathLike],
+        delimiter: str = ",",
+        has_header: bool = True,
+        write_header: bool = False,
+        schema: Optional[List[Literal["auto", "text", "number", "bool", "date"]]] = None,
+    ) -> int: ...
+    def fill_template(self, values: Dict[str, Any]) -> "Editor": ...
+    def repeat_block(self, range: str, records: List[Dict[str, Any]]) -> "Editor": ...
+    def set_border(self, range: str, style: str, color: Optional[str] = None) -> "Editor": ...
+    def get_theme_colors(self) -> List[Dict[str, str]]: ...
     
     # --- ОБНОВЛЕННЫЙ МЕТОД ---
     def set_font(
@@ -57,15 +321,93 @@ class Editor:
         size: float, 
         bold: bool = False, 
         italic: bool = False, 
-        align: Optional[AlignSpec] = None  # <-- Добавлен опциональный аргумент
+        align: Optional[AlignSpec] = None,  # <-- Добавлен опциональный аргумент
+        horiz: Union[HorizAlignment, str, None] = None,
+        vert: Union[VertAlignment, str, None] = None,
+        wrap: bool = False,
+        color: Optional[str] = None,
     ) -> "Editor": ...
     
     # --- НОВЫЙ МЕТОД ---
+    @overload
     def set_alignment(self, range: str, spec: AlignSpec) -> "Editor": ...
+    @overload
+    def set_alignment(
+        self,
+        range: str,
+        *,
+        horiz: Union[HorizAlignment, str, None] = None,
+        vert: Union[VertAlignment, str, None] = None,
+        wrap: bool = False,
+    ) -> "Editor": ...
 
     def set_cell(self, coords: str, cell: str) -> None: ...
     def set_column_width(self, col_letter: str, width: float) -> "Editor": ...
+    def set_column_best_fit(self, cols: str, enabled: bool = True) -> "Editor": ...
     def set_columns_width(self, col_letters: List[str], width: float) -> "Editor": ...
+    def set_row_height(self, rows: str, height: float) -> "Editor": ...
+    def autofit_columns(self, range: Optional[str] = None) -> "Editor": ...
+    def freeze_panes(self, cell: str) -> "Editor": ...
+    def freeze_top_row(self) -> "Editor": ...
+    def freeze_first_column(self) -> "Editor": ...
+    def unfreeze_panes(self) -> "Editor": ...
+    def set_auto_filter(self, range: str) -> "Editor": ...
+    def add_table(
+        self,
+        range: str,
+        name: str,
+        style: Optional[str] = None,
+        columns: Optional[List[str]] = None,
+    ) -> "Editor": ...
+    def extend_table(self, name: str, extra_rows: int) -> "Editor": ...
+    def mark_pivot_caches_dirty(self) -> "Editor": ...
+    def remove_auto_filter(self) -> "Editor": ...
+    def set_sheet_view(
+        self,
+        show_grid_lines: Optional[bool] = None,
+        zoom_scale: Optional[int] = None,
+        right_to_left: Optional[bool] = None,
+        active_cell: Optional[str] = None,
+        selection: Optional[str] = None,
+    ) -> "Editor": ...
+    def set_page_setup(
+        self,
+        orientation: Optional[Literal["portrait", "landscape"]] = None,
+        paper_size: Optional[int] = None,
+        scale: Optional[int] = None,
+        fit_to_pages: Optional[Tuple[int, int]] = None,
+        margins: Optional[Tuple[float, float, float, float, float, float]] = None,
+        center_horizontally: Optional[bool] = None,
+        center_vertically: Optional[bool] = None,
+        print_grid_lines: Optional[bool] = None,
+        print_headings: Optional[bool] = None,
+    ) -> "Editor": ...
+    def set_print_area(self, range: str) -> "Editor": ...
+    def set_print_titles(
+        self, rows: Optional[str] = None, cols: Optional[str] = None
+    ) -> "Editor": ...
+    def set_header_footer(
+        self,
+        header: Optional[str] = None,
+        footer: Optional[str] = None,
+        even_header: Optional[str] = None,
+        even_footer: Optional[str] = None,
+        first_header: Optional[str] = None,
+        first_footer: Optional[str] = None,
+        different_odd_even: bool = False,
+        different_first: bool = False,
+    ) -> "Editor": ...
+    def insert_page_break(self, after_row: int) -> "Editor": ...
+    def insert_image(
+        self,
+        coord: str,
+        image: Union[bytes, str, "os.PathLike[str]"],
+        width: Optional[int] = None,
+        height: Optional[int] = None,
+        offset_x: int = 0,
+        offset_y: int = 0,
+        alt_text: Optional[str] = None,
+    ) -> "Editor": ...
 
 class Scanner:
     def __init__(self, path: str) -> None: ...
@@ -1,14 +1,19 @@
-use pyo3::exceptions::PyRuntimeError;
+use pyo3::exceptions::{PyBaseException, PyRuntimeError};
 use pyo3::prelude::*;
 
 use pyo3::PyRefMut;
-use pyo3::types::PyDict;
-use rust_core::{XlsxEditor, scan};
+use pyo3::types::{PyBool, PyBytes, PyDate, PyDateTime, PyDict, PyString, PyTuple};
+use rust_core::numfmt::{self, NumFmt};
+use rust_core::{XlsxEditor, diff_part, formula, scan, scan_info};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[cfg(feature = "polars")]
 use pyo3_polars::PyDataFrame;
 #[cfg(feature = "polars")]
+use rust_core::polars_part::{HeaderStyle, PolarsWriteOptions};
+#[cfg(feature = "polars")]
 fn index_to_excel_col(mut idx: usize) -> String {
     let mut col = String::new();
     idx += 1; // 1-based
@@ -20,7 +25,64 @@ fn index_to_excel_col(mut idx: usize) -> String {
     col
 }
 // Импортируем типы из rust_core
-use rust_core::style::{AlignSpec, HorizAlignment, VertAlignment};
+use rust_core::XlsxError;
+use rust_core::csv_part::{CsvOptions, CsvType};
+use rust_core::docprops_part::{DocProperties, PropertyValue};
+use rust_core::find_part::FindOptions;
+use rust_core::format_part::{BorderSide, StyleSpec};
+use rust_core::image_part::ImageOptions;
+use rust_core::layout_part::XmlLayout;
+use rust_core::print_part::{HeaderFooter, Orientation, PageMargins, PageSetup};
+use rust_core::protection_part::SheetPermissions;
+use rust_core::range_part::ClearMode;
+use rust_core::read_part::{ReadValue, Rows};
+use rust_core::sheets_part::{SheetProperties, SheetVisibility};
+use rust_core::style::{AlignSpec, HorizAlignment, VertAlignment, col_letter};
+use rust_core::template_part::{CellValue, DATE_FORMAT, DATETIME_FORMAT};
+use rust_core::theme_part::Color;
+use rust_core::view_part::SheetViewOptions;
+
+// --- ИСКЛЮЧЕНИЯ ---
+// Наследуются от RuntimeError, чтобы старый `except RuntimeError` продолжал работать.
+
+pyo3::create_exception!(excelsior, SheetNotFoundError, PyRuntimeError);
+// битый файл: не разбирается XML части или сам архив
+pyo3::create_exception!(excelsior, XlsxCorruptError, PyRuntimeError);
+pyo3::create_exception!(excelsior, MalformedSheetError, XlsxCorruptError);
+pyo3::create_exception!(excelsior, RowLimitExceededError, PyRuntimeError);
+pyo3::create_exception!(excelsior, InvalidRangeError, PyRuntimeError);
+pyo3::create_exception!(excelsior, ZipError, XlsxCorruptError);
+
+/// Переводит ошибку ядра в Python-исключение по варианту [`XlsxError`].
+/// Сообщение — вся цепочка контекста; лист, часть, строка или ссылка из
+/// варианта доступны атрибутами исключения (`sheet`, `part`/`offset`, `row`, `range`).
+fn py_err(e: XlsxError) -> PyErr {
+    let msg = format!("{e:#}");
+    let err = match &e {
+        XlsxError::SheetNotFound(_) => SheetNotFoundError::new_err(msg),
+        XlsxError::MalformedSheet { .. } => MalformedSheetError::new_err(msg),
+        XlsxError::RowLimitExceeded { .. } => RowLimitExceededError::new_err(msg),
+        XlsxError::InvalidRange(_) => InvalidRangeError::new_err(msg),
+        XlsxError::Zip(_) => ZipError::new_err(msg),
+        XlsxError::Other(_) => return PyRuntimeError::new_err(msg),
+    };
+    // атрибуты — подсказка; без них исключение всё равно верное
+    Python::with_gil(|py| error_attrs(err.value(py), &e)).ok();
+    err
+}
+
+fn error_attrs(value: &Bound<'_, PyBaseException>, kind: &XlsxError) -> PyResult<()> {
+    match kind {
+        XlsxError::SheetNotFound(name) => value.setattr("sheet", name),
+        XlsxError::MalformedSheet { part, offset, .. } => {
+            value.setattr("part", part)?;
+            value.setattr("offset", offset)
+        }
+        XlsxError::RowLimitExceeded { row } => value.setattr("row", row),
+        XlsxError::InvalidRange(r) => value.setattr("range", r),
+        XlsxError::Zip(_) | XlsxError::Other(_) => Ok(()),
+    }
+}
 
 // --- ОБЕРТКИ ДЛЯ ENUM-ОВ ---
 
@@ -36,7 +98,6 @@ struct PyVertAlignment(VertAlignment);
 #[derive(Clone)]
 struct PyAlignSpec(AlignSpec);
 
-
 #[pymethods]
 impl PyAlignSpec {
     #[new]
@@ -79,31 +140,497 @@ impl PyAlignSpec {
         }))
     }
 }
+/// Выравнивание из enum-а `HorizAlignment`/`VertAlignment` или строки ("center").
+fn align_arg<T, W>(obj: &Bound<'_, PyAny>, inner: fn(&W) -> T) -> PyResult<T>
+where
+    T: FromStr<Err = XlsxError>,
+    W: pyo3::PyClass,
+{
+    if let Ok(s) = obj.extract::<String>() {
+        return s.parse().map_err(py_err);
+    }
+    let value = if obj.hasattr("value")? { obj.getattr("value")? } else { obj.clone() };
+    let w: PyRef<W> = value.extract()?;
+    Ok(inner(&w))
+}
+/// Выравнивание из `spec` либо из kwargs horiz/vert/wrap; `None` — не задано.
+fn to_align_spec(
+    spec: Option<PyAlignSpec>,
+    horiz: Option<&Bound<'_, PyAny>>,
+    vert: Option<&Bound<'_, PyAny>>,
+    wrap: bool,
+) -> PyResult<Option<AlignSpec>> {
+    if let Some(spec) = spec {
+        if horiz.is_some() || vert.is_some() || wrap {
+            return Err(PyRuntimeError::new_err("pass either spec or horiz/vert/wrap, not both"));
+        }
+        return Ok(Some(spec.0));
+    }
+    if horiz.is_none() && vert.is_none() && !wrap {
+        return Ok(None);
+    }
+    Ok(Some(AlignSpec {
+        horiz: horiz
+            .map(|h| align_arg(h, |w: &PyHorizAlignment| w.0.clone()))
+            .transpose()?,
+        vert: vert
+            .map(|v| align_arg(v, |w: &PyVertAlignment| w.0.clone()))
+            .transpose()?,
+        wrap,
+    }))
+}
+/// Оформление из словаря в форме `get_cell_style`; отсутствующие ключи и
+/// None — значения по умолчанию (Calibri 11, «Общий», ячейка защищена).
+fn to_style_spec(d: &Bound<'_, PyDict>) -> PyResult<StyleSpec> {
+    fn get<'py, T: FromPyObject<'py>>(d: &Bound<'py, PyDict>, key: &str) -> PyResult<Option<T>> {
+        match d.get_item(key)? {
+            Some(v) if !v.is_none() => Ok(Some(v.extract()?)),
+            _ => Ok(None),
+        }
+    }
+    let color = |c: Option<String>| c.map(|c| c.parse::<Color>().map_err(py_err)).transpose();
+    let mut spec = StyleSpec {
+        number_format: get(d, "number_format")?,
+        fill: color(get(d, "fill")?)?,
+        ..StyleSpec::default()
+    };
+    spec.locked = get(d, "locked")?.unwrap_or(true);
+    spec.hidden = get(d, "hidden")?.unwrap_or(false);
+    if let Some(font) = get::<Bound<PyDict>>(d, "font")? {
+        let f = &mut spec.font;
+        f.name = get(&font, "name")?.unwrap_or_else(|| f.name.clone());
+        f.size = get(&font, "size")?.unwrap_or(f.size);
+        f.bold = get(&font, "bold")?.unwrap_or(false);
+        f.italic = get(&font, "italic")?.unwrap_or(false);
+        f.color = color(get(&font, "color")?)?;
+    }
+    if let Some(border) = get::<Bound<PyDict>>(d, "border")? {
+        let b = &mut spec.border;
+        for (name, side) in [
+            ("left", &mut b.left),
+            ("right", &mut b.right),
+            ("top", &mut b.top),
+            ("bottom", &mut b.bottom),
+        ] {
+            let Some(s) = get::<Bound<PyDict>>(&border, name)? else {
+                continue;
+            };
+            *side = Some(BorderSide {
+                style: get(&s, "style")?.unwrap_or_else(|| "thin".to_owned()),
+                color: color(get(&s, "color")?)?,
+            });
+        }
+    }
+    if let Some(align) = get::<Bound<PyDict>>(d, "alignment")? {
+        let a = &mut spec.alignment;
+        a.horiz = get::<String>(&align, "horiz")?
+            .map(|h| h.parse())
+            .transpose()
+            .map_err(py_err)?;
+        a.vert = get::<String>(&align, "vert")?
+            .map(|v| v.parse())
+            .transpose()
+            .map_err(py_err)?;
+        a.wrap = get(&align, "wrap")?.unwrap_or(false);
+    }
+    Ok(spec)
+}
+/// None → пустая ячейка, bool/int/float → значение, строка с `=` → формула.
+fn to_cell_value(obj: &Bound<'_, PyAny>) -> PyResult<CellValue> {
+    if obj.is_none() {
+        return Ok(CellValue::Empty);
+    }
+    if obj.is_instance_of::<PyBool>() {
+        return Ok(CellValue::Bool(obj.extract()?));
+    }
+    if let Ok(n) = obj.extract::<f64>() {
+        return Ok(CellValue::Number(n));
+    }
+    let s: String = obj.str()?.extract()?;
+    Ok(match s.strip_prefix('=') {
+        Some(f) => CellValue::Formula(f.to_owned()),
+        None => CellValue::Text(s),
+    })
+}
+/// Ячейка строки `append_row` с числовым форматом: None → пусто, bool/int/
+/// float → значение, date/datetime → сериал с форматом даты (datetime с поясом —
+/// в UTC). Строки разбираются как раньше: `=` — формула, число — число.
+fn to_typed_cell(obj: &Bound<'_, PyAny>) -> PyResult<(CellValue, Option<&'static str>)> {
+    if obj.is_none() {
+        return Ok((CellValue::Empty, None));
+    }
+    if obj.is_instance_of::<PyBool>() {
+        return Ok((CellValue::Bool(obj.extract()?), None));
+    }
+    if obj.is_instance_of::<PyDate>() {
+        let mut dt = obj.clone();
+        let with_time = obj.is_instance_of::<PyDateTime>();
+        if with_time && !obj.getattr("tzinfo")?.is_none() {
+            let utc = obj.py().import("datetime")?.getattr("timezone")?.getattr("utc")?;
+            dt = obj.call_method1("astimezone", (utc,))?;
+        }
+        // 719_163 — порядковый номер 1970-01-01
+        let mut days = (dt.call_method0("toordinal")?.extract::<i64>()? - 719_163) as f64;
+        if !with_time {
+            return Ok((CellValue::unix_days(days), Some(DATE_FORMAT)));
+        }
+        let part = |name: &str| -> PyResult<f64> { dt.getattr(name)?.extract() };
+        let secs = part("hour")? * 3600.0 + part("minute")? * 60.0 + part("second")?;
+        days += (secs + part("microsecond")? / 1e6) / 86_400.0;
+        return Ok((CellValue::unix_days(days), Some(DATETIME_FORMAT)));
+    }
+    if !obj.is_instance_of::<PyString>()
+        && let Ok(n) = obj.extract::<f64>()
+    {
+        return Ok((CellValue::Number(n), None));
+    }
+    let s: String = obj.str()?.extract()?;
+    Ok((CellValue::parse(&s), None))
+}
+/// Прочитанное значение в Python: None, float (целые — int), bool, str,
+/// date/datetime для чисел в формате даты.
+fn read_to_py(py: Python<'_>, value: &ReadValue) -> PyResult<PyObject> {
+    Ok(match value {
+        ReadValue::Empty => py.None(),
+        ReadValue::Number(x) if x.fract() == 0.0 && x.abs() < 9e15 => {
+            (*x as i64).into_pyobject(py)?.into_any().unbind()
+        }
+        ReadValue::Number(x) => x.into_pyobject(py)?.into_any().unbind(),
+        ReadValue::Bool(b) => b.into_pyobject(py)?.to_owned().into_any().unbind(),
+        ReadValue::Text(t) => t.into_pyobject(py)?.into_any().unbind(),
+        ReadValue::Date(x) => {
+            let dt = py.import("datetime")?;
+            // до 1900-03-01 Excel считает несуществующее 29 февраля
+            let serial = if *x < 60.0 { x + 1.0 } else { *x };
+            let base = dt.getattr("datetime")?.call1((1899, 12, 30))?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("milliseconds", (serial * 86_400_000.0).round())?;
+            let delta = dt.getattr("timedelta")?.call((), Some(&kwargs))?;
+            let value = base.add(delta)?;
+            if x.fract() == 0.0 {
+                value.call_method0("date")?.unbind()
+            } else {
+                value.unbind()
+            }
+        }
+    })
+}
+/// Значение пользовательского свойства: bool, число, datetime/date или текст.
+fn to_property_value(obj: &Bound<'_, PyAny>) -> PyResult<PropertyValue> {
+    if obj.is_instance_of::<PyBool>() {
+        return Ok(PropertyValue::Bool(obj.extract()?));
+    }
+    if let Ok(n) = obj.extract::<f64>() {
+        return Ok(PropertyValue::Number(n));
+    }
+    if obj.hasattr("isoformat")? {
+        let mut iso: String = obj.call_method0("isoformat")?.extract()?;
+        if !iso.contains('T') {
+            iso.push_str("T00:00:00Z"); // date
+        } else if obj.getattr("tzinfo")?.is_none() {
+            iso.push('Z');
+        }
+        return Ok(PropertyValue::Date(iso));
+    }
+    Ok(PropertyValue::Text(obj.str()?.extract()?))
+}
+/// Разрешения листа: к умолчаниям Excel добавляются действия из `allow`.
+fn to_permissions(allow: Option<Vec<String>>) -> PyResult<SheetPermissions> {
+    let mut p = SheetPermissions::default();
+    for action in allow.unwrap_or_default() {
+        let flag = match action.as_str() {
+            "select_locked_cells" => &mut p.select_locked_cells,
+            "select_unlocked_cells" => &mut p.select_unlocked_cells,
+            "format_cells" => &mut p.format_cells,
+            "format_columns" => &mut p.format_columns,
+            "format_rows" => &mut p.format_rows,
+            "insert_columns" => &mut p.insert_columns,
+            "insert_rows" => &mut p.insert_rows,
+            "insert_hyperlinks" => &mut p.insert_hyperlinks,
+            "delete_columns" => &mut p.delete_columns,
+            "delete_rows" => &mut p.delete_rows,
+            "sort" => &mut p.sort,
+            "auto_filter" => &mut p.auto_filter,
+            "pivot_tables" => &mut p.pivot_tables,
+            "objects" => &mut p.objects,
+            "scenarios" => &mut p.scenarios,
+            other => {
+                return Err(PyRuntimeError::new_err(format!(
+                    "unknown sheet permission: {other}"
+                )));
+            }
+        };
+        *flag = true;
+    }
+    Ok(p)
+}
+fn to_record(values: HashMap<String, Bound<'_, PyAny>>) -> PyResult<HashMap<String, CellValue>> {
+    values
+        .into_iter()
+        .map(|(k, v)| Ok((k, to_cell_value(&v)?)))
+        .collect()
+}
 #[pyfunction]
 fn scan_excel(path: PathBuf) -> PyResult<Vec<String>> {
-    scan(&path).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    scan(&path).map_err(py_err)
+}
+/// Сведения о книге без открытия редактора: sheets (name, visibility, dimension,
+/// rows, last_row), defined_names (name, value, local_sheet_id, hidden),
+/// has_macros, application, app_version.
+#[pyfunction]
+fn scan_workbook<'py>(py: Python<'py>, path: PathBuf) -> PyResult<Bound<'py, PyDict>> {
+    let info = scan_info(&path).map_err(py_err)?;
+    let sheets = info
+        .sheets
+        .iter()
+        .map(|s| {
+            let d = PyDict::new(py);
+            d.set_item("name", &s.name)?;
+            d.set_item("visibility", s.visibility.to_string())?;
+            d.set_item("dimension", &s.dimension)?;
+            d.set_item("rows", s.rows)?;
+            d.set_item("last_row", s.last_row)?;
+            Ok(d)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let names = info
+        .defined_names
+        .iter()
+        .map(|n| {
+            let d = PyDict::new(py);
+            d.set_item("name", &n.name)?;
+            d.set_item("value", &n.value)?;
+            d.set_item("local_sheet_id", n.local_sheet_id)?;
+            d.set_item("hidden", n.hidden)?;
+            Ok(d)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let d = PyDict::new(py);
+    d.set_item("sheets", sheets)?;
+    d.set_item("defined_names", names)?;
+    d.set_item("has_macros", info.has_macros)?;
+    d.set_item("application", info.application)?;
+    d.set_item("app_version", info.app_version)?;
+    Ok(d)
+}
+/// Различия двух книг по ячейкам: added_sheets, removed_sheets и sheets
+/// (name, cells: cell, before, after, value_changed, style_changed).
+#[pyfunction]
+#[pyo3(name = "diff")]
+fn diff_workbooks<'py>(
+    py: Python<'py>,
+    path_a: PathBuf,
+    path_b: PathBuf,
+) -> PyResult<Bound<'py, PyDict>> {
+    let d = rust_core::diff(&path_a, &path_b).map_err(py_err)?;
+    let state = |c: &diff_part::CellState| -> PyResult<Bound<'py, PyDict>> {
+        let style = PyDict::new(py);
+        style.set_item("number_format", &c.style.number_format)?;
+        style.set_item("font", &c.style.font)?;
+        style.set_item("fill", &c.style.fill)?;
+        style.set_item("border", &c.style.border)?;
+        style.set_item("alignment", &c.style.alignment)?;
+        style.set_item("protection", &c.style.protection)?;
+        let st = PyDict::new(py);
+        st.set_item("value", read_to_py(py, &c.value)?)?;
+        st.set_item("formula", &c.formula)?;
+        st.set_item("style", style)?;
+        Ok(st)
+    };
+    let sheets = d
+        .sheets
+        .iter()
+        .map(|s| {
+            let cells = s
+                .cells
+                .iter()
+                .map(|c| {
+                    let cd = PyDict::new(py);
+                    cd.set_item("cell", &c.cell)?;
+                    cd.set_item("before", state(&c.before)?)?;
+                    cd.set_item("after", state(&c.after)?)?;
+                    cd.set_item("value_changed", c.value_changed())?;
+                    cd.set_item("style_changed", c.style_changed())?;
+                    Ok(cd)
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            let sd = PyDict::new(py);
+            sd.set_item("name", &s.name)?;
+            sd.set_item("cells", cells)?;
+            Ok(sd)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let out = PyDict::new(py);
+    out.set_item("added_sheets", &d.added_sheets)?;
+    out.set_item("removed_sheets", &d.removed_sheets)?;
+    out.set_item("sheets", sheets)?;
+    Ok(out)
+}
+#[pyfunction]
+#[pyo3(signature = (formula, row_delta, col_delta = 0))]
+fn adjust_formula(formula: &str, row_delta: i64, col_delta: i64) -> String {
+    formula::adjust_formula(formula, row_delta, col_delta)
+}
+#[pyfunction]
+fn translate_r1c1_to_a1(formula: &str, cell: &str) -> PyResult<String> {
+    formula::translate_r1c1_to_a1(formula, cell).map_err(py_err)
+}
+/// Коды числовых форматов: атрибуты — встроенные форматы Excel,
+/// статические методы собирают типовые коды (`NumFmt.currency("EUR")`).
+#[pyclass(name = "NumFmt")]
+struct PyNumFmt;
+
+#[pymethods]
+impl PyNumFmt {
+    #[classattr]
+    const GENERAL: &'static str = "General";
+    #[classattr]
+    const INTEGER: &'static str = "0";
+    #[classattr]
+    const DECIMAL: &'static str = "0.00";
+    #[classattr]
+    const THOUSANDS: &'static str = "#,##0";
+    #[classattr]
+    const THOUSANDS_DECIMAL: &'static str = "#,##0.00";
+    #[classattr]
+    const PERCENT: &'static str = "0%";
+    #[classattr]
+    const PERCENT_DECIMAL: &'static str = "0.00%";
+    #[classattr]
+    const SCIENTIFIC: &'static str = "0.00E+00";
+    #[classattr]
+    const FRACTION: &'static str = "# ?/?";
+    #[classattr]
+    const DATE: &'static str = "mm-dd-yy";
+    #[classattr]
+    const DATE_LONG: &'static str = "d-mmm-yy";
+    #[classattr]
+    const TIME: &'static str = "h:mm";
+    #[classattr]
+    const TIME_SECONDS: &'static str = "h:mm:ss";
+    #[classattr]
+    const TIME_AM_PM: &'static str = "h:mm AM/PM";
+    #[classattr]
+    const DATETIME: &'static str = "m/d/yy h:mm";
+    #[classattr]
+    const DURATION: &'static str = "[h]:mm:ss";
+    #[classattr]
+    const ACCOUNTING: &'static str = "#,##0 ;(#,##0)";
+    #[classattr]
+    const ACCOUNTING_RED: &'static str = "#,##0 ;[Red](#,##0)";
+    #[classattr]
+    const ACCOUNTING_DECIMAL: &'static str = "#,##0.00;(#,##0.00)";
+    #[classattr]
+    const ACCOUNTING_DECIMAL_RED: &'static str = "#,##0.00;[Red](#,##0.00)";
+    #[classattr]
+    const TEXT: &'static str = "@";
+
+    #[staticmethod]
+    fn date_iso() -> String {
+        NumFmt::date_iso().to_string()
+    }
+    #[staticmethod]
+    fn datetime_iso() -> String {
+        NumFmt::datetime_iso().to_string()
+    }
+    #[staticmethod]
+    #[pyo3(signature = (places = 2))]
+    fn decimal(places: u8) -> String {
+        NumFmt::decimal(places).to_string()
+    }
+    #[staticmethod]
+    #[pyo3(signature = (places = 2))]
+    fn thousands(places: u8) -> String {
+        NumFmt::thousands(places).to_string()
+    }
+    #[staticmethod]
+    #[pyo3(signature = (places = 0))]
+    fn percent(places: u8) -> String {
+        NumFmt::percent(places).to_string()
+    }
+    #[staticmethod]
+    #[pyo3(signature = (places = 2))]
+    fn scientific(places: u8) -> String {
+        NumFmt::scientific(places).to_string()
+    }
+    /// Денежный формат валюты ISO 4217 (`"EUR"`).
+    #[staticmethod]
+    fn currency(iso: &str) -> PyResult<String> {
+        Ok(NumFmt::currency(iso).map_err(py_err)?.to_string())
+    }
+    /// Номер встроенного формата с кодом `code`; `None` — пользовательский.
+    #[staticmethod]
+    fn builtin_id(code: &str) -> Option<u32> {
+        numfmt::builtin_id(code)
+    }
 }
+
 #[pyclass]
 struct Editor {
     editor: XlsxEditor,
+    /// Куда `__exit__` сохраняет книгу; `None` — сохранять некуда.
+    save_to: Option<PathBuf>,
 }
 
 #[pymethods]
 impl Editor {
     #[new]
-    #[pyo3(signature = (path, sheet_name))]
-    fn new(path: PathBuf, sheet_name: &str) -> PyResult<Self> {
-        let openned = XlsxEditor::open(path, sheet_name)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-        Ok(Editor { editor: openned })
+    #[pyo3(signature = (path, sheet_name, save_to = None))]
+    fn new(path: PathBuf, sheet_name: &str, save_to: Option<PathBuf>) -> PyResult<Self> {
+        let openned = XlsxEditor::open(&path, sheet_name).map_err(py_err)?;
+        Ok(Editor {
+            editor: openned,
+            save_to: Some(save_to.unwrap_or(path)),
+        })
+    }
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+    /// Без исключения сохраняет книгу в `save_to`, с исключением — ничего не
+    /// пишет; исключение не подавляется.
+    fn __exit__(
+        &mut self,
+        exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> PyResult<bool> {
+        if exc_type.is_none() {
+            let path = self.save_to.clone().ok_or_else(|| {
+                PyRuntimeError::new_err("no save path: call set_save_path() before leaving `with`")
+            })?;
+            self.editor.save(path).map_err(py_err)?;
+        }
+        Ok(false)
+    }
+    /// Начинает транзакцию: правки до `rollback()` можно отменить.
+    fn begin<'py>(mut slf: PyRefMut<'py, Self>) -> PyRefMut<'py, Self> {
+        slf.editor.begin();
+        slf
+    }
+    fn commit<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.commit().map_err(py_err)?;
+        Ok(slf)
+    }
+    fn rollback<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.rollback().map_err(py_err)?;
+        Ok(slf)
+    }
+    /// `with editor.transaction():` — исключение в блоке откатывает его правки.
+    fn transaction(slf: Bound<'_, Self>) -> Transaction {
+        Transaction {
+            editor: slf.unbind(),
+        }
+    }
+    /// Путь для сохранения при выходе из `with`.
+    fn set_save_path<'py>(mut slf: PyRefMut<'py, Self>, path: PathBuf) -> PyRefMut<'py, Self> {
+        slf.save_to = Some(path);
+        slf
     }
     fn add_worksheet<'py>(
         mut slf: PyRefMut<'py, Self>,
         sheet_name: &str,
     ) -> PyResult<PyRefMut<'py, Self>> {
-        slf.editor
-            .add_worksheet(sheet_name)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        slf.editor.add_worksheet(sheet_name).map_err(py_err)?;
         Ok(slf)
     }
     fn add_worksheet_at<'py>(
@@ -113,51 +640,585 @@ impl Editor {
     ) -> PyResult<PyRefMut<'py, Self>> {
         slf.editor
             .add_worksheet_at(sheet_name, index)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            .map_err(py_err)?;
         Ok(slf)
     }
-    fn with_worksheet<'py>(
+    fn rename_worksheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        old: &str,
+        new: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.rename_worksheet(old, new).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn delete_worksheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.delete_worksheet(sheet_name).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn copy_worksheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        src: &str,
+        new_name: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.copy_worksheet(src, new_name).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn duplicate_sheet_as_template<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        src_sheet: &str,
+        new_name: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .duplicate_sheet_as_template(src_sheet, new_name)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    fn move_worksheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+        index: usize,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .move_worksheet(sheet_name, index)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    /// visibility: "visible" | "hidden" | "veryHidden"
+    #[pyo3(signature = (sheet_name, visibility = "hidden"))]
+    fn hide_worksheet<'py>(
         mut slf: PyRefMut<'py, Self>,
         sheet_name: &str,
+        visibility: &str,
     ) -> PyResult<PyRefMut<'py, Self>> {
+        let visibility: SheetVisibility = visibility.parse().map_err(py_err)?;
         slf.editor
-            .with_worksheet(sheet_name)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            .hide_worksheet(sheet_name, visibility)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_active_sheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_active_sheet(sheet_name).map_err(py_err)?;
+        Ok(slf)
+    }
+    /// allow: действия, разрешённые сверх умолчаний Excel ("sort", "auto_filter", …)
+    #[pyo3(signature = (sheet_name, password = None, allow = None))]
+    fn protect_sheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+        password: Option<&str>,
+        allow: Option<Vec<String>>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let permissions = to_permissions(allow)?;
+        slf.editor
+            .protect_sheet(sheet_name, password, permissions)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    fn unprotect_sheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.unprotect_sheet(sheet_name).map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (password = None))]
+    fn protect_workbook<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        password: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.protect_workbook(password).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn unprotect_workbook<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.unprotect_workbook().map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_tab_color<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+        rgb: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_tab_color(sheet_name, rgb).map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (
+        sheet_name,
+        default_col_width = None,
+        default_row_height = None,
+        summary_below = None,
+        summary_right = None
+    ))]
+    fn set_sheet_properties<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+        default_col_width: Option<f64>,
+        default_row_height: Option<f64>,
+        summary_below: Option<bool>,
+        summary_right: Option<bool>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let props = SheetProperties {
+            default_col_width,
+            default_row_height,
+            summary_below,
+            summary_right,
+        };
+        slf.editor
+            .set_sheet_properties(sheet_name, props)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (
+        title = None,
+        subject = None,
+        author = None,
+        keywords = None,
+        description = None,
+        category = None,
+        status = None,
+        company = None,
+        manager = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn set_doc_properties<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        title: Option<String>,
+        subject: Option<String>,
+        author: Option<String>,
+        keywords: Option<String>,
+        description: Option<String>,
+        category: Option<String>,
+        status: Option<String>,
+        company: Option<String>,
+        manager: Option<String>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let props = DocProperties {
+            title,
+            subject,
+            author,
+            keywords,
+            description,
+            category,
+            status,
+            company,
+            manager,
+        };
+        slf.editor.set_doc_properties(props).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_custom_property<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        name: &str,
+        value: Bound<'py, PyAny>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let value = to_property_value(&value)?;
+        slf.editor
+            .set_custom_property(name, value)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_last_modified_by<'py>(mut slf: PyRefMut<'py, Self>, name: &str) -> PyRefMut<'py, Self> {
+        slf.editor.set_last_modified_by(name);
+        slf
+    }
+    #[pyo3(signature = (enabled = true))]
+    fn set_stamp_modified<'py>(mut slf: PyRefMut<'py, Self>, enabled: bool) -> PyRefMut<'py, Self> {
+        slf.editor.set_stamp_modified(enabled);
+        slf
+    }
+    fn with_worksheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sheet_name: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.with_worksheet(sheet_name).map_err(py_err)?;
         Ok(slf)
     }
 
     fn set_cell(&mut self, coords: &str, cell: String) -> PyResult<()> {
-        self.editor
-            .set_cell(coords, cell)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        self.editor.set_cell(coords, cell).map_err(py_err)
+    }
+
+    fn append_row(&mut self, cells: Vec<Bound<'_, PyAny>>) -> PyResult<()> {
+        let cells = cells
+            .iter()
+            .map(to_typed_cell)
+            .collect::<PyResult<Vec<_>>>()?;
+        self.editor.append_values(&cells).map_err(py_err)
     }
 
-    fn append_row(&mut self, cells: Vec<String>) -> PyResult<()> {
+    /// Строки под данными листа; значения — как в `append_row`.
+    fn append_table(&mut self, rows: Vec<Vec<Bound<'_, PyAny>>>) -> PyResult<()> {
+        for row in &rows {
+            let cells = row.iter().map(to_typed_cell).collect::<PyResult<Vec<_>>>()?;
+            self.editor.append_values(&cells).map_err(py_err)?;
+        }
+        Ok(())
+    }
+
+    /// Записи `{столбец: значение}` под столбцы заголовка листа; значения —
+    /// как в `fill_template`.
+    #[pyo3(signature = (records, add_columns = false))]
+    fn append_records(
+        &mut self,
+        records: Vec<HashMap<String, Bound<'_, PyAny>>>,
+        add_columns: bool,
+    ) -> PyResult<()> {
+        let records = records
+            .into_iter()
+            .map(to_record)
+            .collect::<PyResult<Vec<_>>>()?;
         self.editor
-            .append_row(cells)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+            .append_records(records, add_columns)
+            .map_err(py_err)
+    }
+
+    /// Обновляет строки с тем же значением в столбце `key_column`, новые
+    /// ключи дописывает; записи — как в `append_records`.
+    fn upsert_rows(
+        &mut self,
+        key_column: &str,
+        rows: Vec<HashMap<String, Bound<'_, PyAny>>>,
+    ) -> PyResult<()> {
+        let rows = rows
+            .into_iter()
+            .map(to_record)
+            .collect::<PyResult<Vec<_>>>()?;
+        self.editor.upsert_rows(key_column, rows).map_err(py_err)
     }
 
     fn append_table_at(&mut self, cells: Vec<Vec<String>>, start_cell: &str) -> PyResult<()> {
         self.editor
             .append_table_at(start_cell, cells)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+            .map_err(py_err)
     }
     fn last_row_index(&mut self, col_name: String) -> PyResult<u32> {
-        self.editor
-            .get_last_row_index(&col_name)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        self.editor.get_last_row_index(&col_name).map_err(py_err)
     }
     fn last_rows_index(&mut self, col_name: String) -> PyResult<Vec<u32>> {
-        self.editor
-            .get_last_roww_index(&col_name)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        self.editor.get_last_roww_index(&col_name).map_err(py_err)
+    }
+    fn get_cell(&mut self, py: Python<'_>, coord: &str) -> PyResult<PyObject> {
+        let value = self.editor.get_cell(coord).map_err(py_err)?;
+        read_to_py(py, &value)
+    }
+    /// Код числового формата ячейки (`"B4"`) или столбца (`"B"`); `None` — «Общий».
+    fn get_number_format(&mut self, coord: &str) -> PyResult<Option<String>> {
+        self.editor.get_number_format(coord).map_err(py_err)
+    }
+    /// Оформление ячейки или столбца: `number_format`, `font`, `fill`,
+    /// `border`, `alignment`, `locked`, `hidden`. Цвета и выравнивание —
+    /// строками, как их принимают сеттеры.
+    fn get_cell_style<'py>(
+        &mut self,
+        py: Python<'py>,
+        coord: &str,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let spec = self.editor.get_cell_style(coord).map_err(py_err)?;
+        let color = |c: &Option<Color>| c.as_ref().map(|c| c.to_string());
+        let font = PyDict::new(py);
+        font.set_item("name", &spec.font.name)?;
+        font.set_item("size", spec.font.size)?;
+        font.set_item("bold", spec.font.bold)?;
+        font.set_item("italic", spec.font.italic)?;
+        font.set_item("color", color(&spec.font.color))?;
+        let border = PyDict::new(py);
+        for (name, side) in [
+            ("left", &spec.border.left),
+            ("right", &spec.border.right),
+            ("top", &spec.border.top),
+            ("bottom", &spec.border.bottom),
+        ] {
+            let side = match side {
+                Some(b) => {
+                    let d = PyDict::new(py);
+                    d.set_item("style", &b.style)?;
+                    d.set_item("color", color(&b.color))?;
+                    Some(d)
+                }
+                None => None,
+            };
+            border.set_item(name, side)?;
+        }
+        let align = PyDict::new(py);
+        align.set_item(
+            "horiz",
+            spec.alignment.horiz.as_ref().map(|h| h.to_string()),
+        )?;
+        align.set_item("vert", spec.alignment.vert.as_ref().map(|v| v.to_string()))?;
+        align.set_item("wrap", spec.alignment.wrap)?;
+        let d = PyDict::new(py);
+        d.set_item("number_format", &spec.number_format)?;
+        d.set_item("font", font)?;
+        d.set_item("fill", color(&spec.fill))?;
+        d.set_item("border", border)?;
+        d.set_item("alignment", align)?;
+        d.set_item("locked", spec.locked)?;
+        d.set_item("hidden", spec.hidden)?;
+        Ok(d)
+    }
+    /// Оформление листа по умолчанию; `style` — словарь как у
+    /// `get_cell_style`, ключи можно опускать.
+    fn set_sheet_default_style<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        style: &Bound<'_, PyDict>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let spec = to_style_spec(style)?;
+        slf.editor.set_sheet_default_style(&spec).map_err(py_err)?;
+        Ok(slf)
+    }
+    /// Стиль столбцов `cols`; `style` — словарь как у `get_cell_style`.
+    #[pyo3(signature = (cols, style, stamp_cells = false))]
+    fn set_column_style<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cols: &str,
+        style: &Bound<'_, PyDict>,
+        stamp_cells: bool,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let spec = to_style_spec(style)?;
+        slf.editor
+            .set_column_style(cols, &spec, stamp_cells)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    /// Строки листа `sheet` (по умолчанию текущего) по одной, с первой.
+    #[pyo3(signature = (sheet = None))]
+    fn rows(&mut self, sheet: Option<&str>) -> PyResult<RowIterator> {
+        let rows = self.editor.rows(sheet).map_err(py_err)?;
+        Ok(RowIterator { rows })
+    }
+    fn get_range(&mut self, py: Python<'_>, range: &str) -> PyResult<Vec<Vec<PyObject>>> {
+        let rows = self.editor.get_range(range).map_err(py_err)?;
+        rows.iter()
+            .map(|row| row.iter().map(|v| read_to_py(py, v)).collect())
+            .collect()
+    }
+    /// Диапазон (по умолчанию — данные листа) в pandas.DataFrame; имена
+    /// столбцов — из первой строки при `has_header`, как у `to_polars`.
+    #[pyo3(signature = (range = None, has_header = true))]
+    #[allow(clippy::wrong_self_convention)]
+    fn to_pandas<'py>(
+        &mut self,
+        py: Python<'py>,
+        range: Option<&str>,
+        has_header: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let pandas = py.import("pandas")?;
+        let range = match range {
+            Some(r) => r.to_owned(),
+            None => match self.editor.used_range().map_err(py_err)?.to_ref() {
+                Some(r) => r,
+                None => return pandas.getattr("DataFrame")?.call0(),
+            },
+        };
+        let mut rows = self.editor.get_range(&range).map_err(py_err)?;
+        let header = if has_header { rows.remove(0) } else { Vec::new() };
+        let width = header.len().max(rows.first().map_or(0, Vec::len));
+        let mut used = HashSet::new();
+        let mut names = Vec::with_capacity(width);
+        for i in 0..width {
+            let mut name = header
+                .get(i)
+                .and_then(ReadValue::to_text)
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| format!("column_{}", i + 1));
+            let base = name.clone();
+            let mut n = 1;
+            while !used.insert(name.clone()) {
+                name = format!("{base}_{n}");
+                n += 1;
+            }
+            names.push(name);
+        }
+        let data = rows
+            .iter()
+            .map(|row| row.iter().map(|v| read_to_py(py, v)).collect())
+            .collect::<PyResult<Vec<Vec<PyObject>>>>()?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("columns", names)?;
+        pandas.getattr("DataFrame")?.call((data,), Some(&kwargs))
+    }
+    /// Границы данных листа: range ("A1:D8" или None), first_row/last_row,
+    /// first_col/last_col (буквами), last_row_by_col и last_col_by_row.
+    fn used_range<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let used = self.editor.used_range().map_err(py_err)?;
+        let letter = |c: u32| (!used.is_empty()).then(|| col_letter(c));
+        let d = PyDict::new(py);
+        d.set_item("range", used.to_ref())?;
+        d.set_item("first_row", used.first_row)?;
+        d.set_item("last_row", used.last_row)?;
+        d.set_item("first_col", letter(used.first_col))?;
+        d.set_item("last_col", letter(used.last_col))?;
+        let by_col: HashMap<String, u32> = used
+            .last_row_by_col
+            .iter()
+            .map(|(c, r)| (col_letter(*c), *r))
+            .collect();
+        let by_row: HashMap<u32, String> = used
+            .last_col_by_row
+            .iter()
+            .map(|(r, c)| (*r, col_letter(*c)))
+            .collect();
+        d.set_item("last_row_by_col", by_col)?;
+        d.set_item("last_col_by_row", by_row)?;
+        Ok(d)
+    }
+
+    /// Чинит текущий лист (count, повторы и порядок строк); список исправлений.
+    fn repair_sheet(&mut self) -> PyResult<Vec<String>> {
+        self.editor.repair_sheet().map_err(py_err)
+    }
+
+    /// Проверка перед save(): список проблем {part, kind, message}; пустой — всё в порядке.
+    fn validate<'py>(&mut self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let report = self.editor.validate().map_err(py_err)?;
+        report
+            .issues
+            .iter()
+            .map(|i| {
+                let d = PyDict::new(py);
+                d.set_item("part", &i.part)?;
+                d.set_item("kind", i.kind.to_string())?;
+                d.set_item("message", &i.message)?;
+                Ok(d)
+            })
+            .collect()
+    }
+
+    /// Правки с открытия: parts [{path, kind}], sheets [{name, path,
+    /// rows_appended, cells_overwritten}], styles {cell_formats, fonts, fills,
+    /// borders, number_formats}.
+    fn change_summary<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let sum = self.editor.change_summary().map_err(py_err)?;
+        let parts = sum
+            .parts
+            .iter()
+            .map(|p| {
+                let d = PyDict::new(py);
+                d.set_item("path", &p.path)?;
+                d.set_item("kind", p.kind.to_string())?;
+                Ok(d)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let sheets = sum
+            .sheets
+            .iter()
+            .map(|s| {
+                let d = PyDict::new(py);
+                d.set_item("name", &s.name)?;
+                d.set_item("path", &s.path)?;
+                d.set_item("rows_appended", s.rows_appended)?;
+                d.set_item("cells_overwritten", &s.cells_overwritten)?;
+                Ok(d)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let styles = PyDict::new(py);
+        styles.set_item("cell_formats", sum.styles.cell_formats)?;
+        styles.set_item("fonts", sum.styles.fonts)?;
+        styles.set_item("fills", sum.styles.fills)?;
+        styles.set_item("borders", sum.styles.borders)?;
+        styles.set_item("number_formats", &sum.styles.number_formats)?;
+        let d = PyDict::new(py);
+        d.set_item("parts", parts)?;
+        d.set_item("sheets", sheets)?;
+        d.set_item("styles", styles)?;
+        Ok(d)
+    }
+    /// Дописывает change_summary() в скрытый лист `name`.
+    #[pyo3(signature = (name = "audit"))]
+    fn write_audit_sheet<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        name: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.write_audit_sheet(name).map_err(py_err)?;
+        Ok(slf)
     }
 
     fn save(&mut self, path: PathBuf) -> PyResult<()> {
-        self.editor
-            .save(path)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        self.editor.save(path).map_err(py_err)
+    }
+    #[staticmethod]
+    fn open_encrypted(path: PathBuf, password: &str, sheet_name: &str) -> PyResult<Self> {
+        let openned = XlsxEditor::open_encrypted(path, password, sheet_name).map_err(py_err)?;
+        // без шифрования поверх исходного файла не сохраняем
+        Ok(Editor {
+            editor: openned,
+            save_to: None,
+        })
+    }
+    fn save_encrypted(&mut self, path: PathBuf, password: &str) -> PyResult<()> {
+        self.editor.save_encrypted(path, password).map_err(py_err)
+    }
+    #[pyo3(signature = (enabled = true))]
+    fn set_recalc_on_change<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        enabled: bool,
+    ) -> PyRefMut<'py, Self> {
+        slf.editor.set_recalc_on_change(enabled);
+        slf
+    }
+    #[pyo3(signature = (enabled = true))]
+    fn set_row_spans<'py>(mut slf: PyRefMut<'py, Self>, enabled: bool) -> PyRefMut<'py, Self> {
+        slf.editor.set_row_spans(enabled);
+        slf
+    }
+    #[pyo3(signature = (enabled = true))]
+    fn set_inherit_column_styles<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        enabled: bool,
+    ) -> PyRefMut<'py, Self> {
+        slf.editor.set_inherit_column_styles(enabled);
+        slf
+    }
+    /// Отступы в `<sheetData>` изменённых листов при save():
+    /// "as_written", "match_source" или "compact".
+    fn set_xml_layout<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        layout: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let layout: XmlLayout = layout.parse().map_err(py_err)?;
+        slf.editor.set_xml_layout(layout);
+        Ok(slf)
+    }
+    #[pyo3(signature = (enabled = true))]
+    fn set_preserve_zip_metadata<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        enabled: bool,
+    ) -> PyRefMut<'py, Self> {
+        slf.editor.set_preserve_zip_metadata(enabled);
+        slf
+    }
+    /// Потоки для сжатия переписанных частей при save(): 0 — общий пул,
+    /// 1 — без параллельности. Работает в сборке с фичей `rayon`.
+    fn set_compression_threads<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        threads: usize,
+    ) -> PyRefMut<'py, Self> {
+        slf.editor.set_compression_threads(threads);
+        slf
+    }
+    #[pyo3(signature = (enabled = true))]
+    fn set_allow_macro_removal<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        enabled: bool,
+    ) -> PyRefMut<'py, Self> {
+        slf.editor.set_allow_macro_removal(enabled);
+        slf
+    }
+    fn has_macros(&mut self) -> PyResult<bool> {
+        self.editor.has_macros().map_err(py_err)
+    }
+    fn set_full_calc_on_load<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_full_calc_on_load().map_err(py_err)?;
+        Ok(slf)
+    }
+    fn evaluate_formulas<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.evaluate_formulas().map_err(py_err)?;
+        Ok(slf)
     }
     #[cfg(feature = "polars")]
     #[pyo3(signature = (py_df, start_cell = None, default_width = 15.0))]
@@ -169,9 +1230,7 @@ impl Editor {
     ) -> PyResult<()> {
         let df = py_df.into();
         let start = start_cell.as_deref();
-        self.editor
-            .with_polars(&df, start)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        self.editor.with_polars(&df, start).map_err(py_err)?;
 
         // --- Вот тут автоприменяем ширину к столбцам ---
         // Определяем имена столбцов из DataFrame (через polars)
@@ -187,19 +1246,93 @@ impl Editor {
             let col_letter = index_to_excel_col(columns.iter().position(|c| c == col).unwrap());
             self.editor
                 .set_column_width(&col_letter, default_width)
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                .map_err(py_err)?;
         }
 
         Ok(())
     }
+    #[cfg(feature = "polars")]
+    #[pyo3(signature = (py_df, chunk_rows, start_cell = None))]
+    fn with_polars_chunked(
+        &mut self,
+        py_df: PyDataFrame,
+        chunk_rows: usize,
+        start_cell: Option<&str>,
+    ) -> PyResult<()> {
+        let df = py_df.into();
+        self.editor
+            .with_polars_chunked(&df, start_cell, chunk_rows)
+            .map_err(py_err)
+    }
+    /// Возвращает пары (имя столбца DataFrame, буква столбца листа).
+    #[cfg(feature = "polars")]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        py_df,
+        start_cell = None,
+        number_formats = None,
+        header_bold = false,
+        header_fill = None,
+        header_border = None,
+        autofit = false,
+        freeze_header = false,
+        table = None,
+        table_style = None,
+        autofilter = false,
+    ))]
+    fn write_polars(
+        &mut self,
+        py_df: PyDataFrame,
+        start_cell: Option<String>,
+        number_formats: Option<HashMap<String, String>>,
+        header_bold: bool,
+        header_fill: Option<String>,
+        header_border: Option<String>,
+        autofit: bool,
+        freeze_header: bool,
+        table: Option<String>,
+        table_style: Option<String>,
+        autofilter: bool,
+    ) -> PyResult<Vec<(String, String)>> {
+        let df = py_df.into();
+        let header_style = (header_bold || header_fill.is_some() || header_border.is_some())
+            .then_some(HeaderStyle {
+                bold: header_bold,
+                fill: header_fill,
+                border: header_border,
+            });
+        let opts = PolarsWriteOptions {
+            start_cell,
+            number_formats: number_formats.unwrap_or_default(),
+            header_style,
+            autofit,
+            freeze_header,
+            table,
+            table_style,
+            autofilter,
+        };
+        self.editor.write_polars(&df, &opts).map_err(py_err)
+    }
+    #[cfg(feature = "polars")]
+    #[pyo3(signature = (py_df, include_header = false))]
+    fn append_polars(&mut self, py_df: PyDataFrame, include_header: bool) -> PyResult<()> {
+        let df = py_df.into();
+        self.editor.append_polars(&df, include_header).map_err(py_err)
+    }
+    #[cfg(feature = "polars")]
+    #[pyo3(signature = (range = None, has_header = true))]
+    // &mut: чтение сбрасывает отложенные дозаписи и подгружает ленивые части
+    #[allow(clippy::wrong_self_convention)]
+    fn to_polars(&mut self, range: Option<&str>, has_header: bool) -> PyResult<PyDataFrame> {
+        let df = self.editor.to_polars(range, has_header).map_err(py_err)?;
+        Ok(PyDataFrame(df))
+    }
     fn set_number_format<'py>(
         mut slf: PyRefMut<'py, Self>,
         range: &str,
         fmt: &str,
     ) -> PyResult<PyRefMut<'py, Self>> {
-        slf.editor
-            .set_number_format(range, fmt)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        slf.editor.set_number_format(range, fmt).map_err(py_err)?;
         Ok(slf)
     }
 
@@ -207,13 +1340,27 @@ impl Editor {
         mut slf: PyRefMut<'py, Self>,
         range: &str,
         fmt: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_fill(range, fmt).map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (range, locked = true, hidden = false))]
+    fn set_cell_protection<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: &str,
+        locked: bool,
+        hidden: bool,
     ) -> PyResult<PyRefMut<'py, Self>> {
         slf.editor
-            .set_fill(range, fmt)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            .set_cell_protection(range, locked, hidden)
+            .map_err(py_err)?;
         Ok(slf)
     }
-    #[pyo3(signature = (range, name, size, bold = false, italic = false, align = None))]
+    #[pyo3(signature = (
+        range, name, size, bold = false, italic = false, align = None,
+        horiz = None, vert = None, wrap = false, color = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
     fn set_font<'py>(
         mut slf: PyRefMut<'py, Self>,
         range: &str,
@@ -222,51 +1369,305 @@ impl Editor {
         bold: bool,
         italic: bool,
         align: Option<PyAlignSpec>, // <--- ИЗМЕНЕНО: принимаем PyAlignSpec
+        horiz: Option<Bound<'py, PyAny>>,
+        vert: Option<Bound<'py, PyAny>>,
+        wrap: bool,
+        color: Option<&str>,
     ) -> PyResult<PyRefMut<'py, Self>> {
         let editor = &mut slf.editor;
+        let spec = to_align_spec(align, horiz.as_ref(), vert.as_ref(), wrap)?;
 
-        // Конвертируем PyAlignSpec в rust_core::AlignSpec вручную
-        if let Some(py_align_spec) = align {
+        // Конвертируем PyAlignSpec или kwargs в rust_core::AlignSpec вручную
+        if let Some(color) = color {
+            editor
+                .set_font_with_color(range, name, size, bold, italic, color)
+                .map_err(py_err)?;
+            if let Some(spec) = spec {
+                editor.set_alignment(range, &spec).map_err(py_err)?;
+            }
+        } else if let Some(spec) = spec {
             editor
-                .set_font_with_alignment(range, name, size, bold, italic, &py_align_spec.0) // <--- ИЗМЕНЕНО: используем .0
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                .set_font_with_alignment(range, name, size, bold, italic, &spec)
+                .map_err(py_err)?;
         } else {
             editor
                 .set_font(range, name, size, bold, italic)
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                .map_err(py_err)?;
         }
         Ok(slf)
     }
 
+    /// Выравнивание объектом `spec` или kwargs: `horiz`/`vert` — enum или строка.
+    #[pyo3(signature = (range, spec = None, horiz = None, vert = None, wrap = false))]
     fn set_alignment<'py>(
         mut slf: PyRefMut<'py, Self>,
         range: &str,
-        spec: PyAlignSpec, // <--- ИЗМЕНЕНО: принимаем PyAlignSpec
+        spec: Option<PyAlignSpec>,
+        horiz: Option<Bound<'py, PyAny>>,
+        vert: Option<Bound<'py, PyAny>>,
+        wrap: bool,
     ) -> PyResult<PyRefMut<'py, Self>> {
-        slf.editor
-            .set_alignment(range, &spec.0) // <--- ИЗМЕНЕНО: используем .0
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        let spec = to_align_spec(spec, horiz.as_ref(), vert.as_ref(), wrap)?
+            .ok_or_else(|| PyRuntimeError::new_err("no alignment given"))?;
+        slf.editor.set_alignment(range, &spec).map_err(py_err)?;
         Ok(slf)
     }
     fn merge_cells<'py>(
         mut slf: PyRefMut<'py, Self>,
         range: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.merge_cells(range).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn unmerge_cells<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.unmerge_cells(range).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn insert_rows<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        at_row: u32,
+        count: u32,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.insert_rows(at_row, count).map_err(py_err)?;
+        Ok(slf)
+    }
+    /// Вставляет строку так, чтобы столбец `column` остался упорядоченным;
+    /// значения — как в `append_row`. Возвращает номер новой строки.
+    fn insert_row_sorted(&mut self, column: &str, cells: Vec<Bound<'_, PyAny>>) -> PyResult<u32> {
+        let cells = cells
+            .iter()
+            .map(to_typed_cell)
+            .collect::<PyResult<Vec<_>>>()?;
+        self.editor.insert_row_sorted(column, &cells).map_err(py_err)
+    }
+    fn insert_columns<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        before: &str,
+        count: u32,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.insert_columns(before, count).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn delete_rows<'py>(mut slf: PyRefMut<'py, Self>, rows: &str) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.delete_rows(rows).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn delete_columns<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cols: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.delete_columns(cols).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn clear_rows<'py>(mut slf: PyRefMut<'py, Self>, rows: &str) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.clear_rows(rows).map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (rows, level = 1, collapsed = false))]
+    fn group_rows<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        rows: &str,
+        level: u8,
+        collapsed: bool,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .group_rows(rows, level, collapsed)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (cols, level = 1, collapsed = false))]
+    fn group_columns<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cols: &str,
+        level: u8,
+        collapsed: bool,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .group_columns(cols, level, collapsed)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    fn hide_columns<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cols: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.hide_columns(cols).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn show_columns<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cols: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.show_columns(cols).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_column_outline_level<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cols: &str,
+        level: u8,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .set_column_outline_level(cols, level)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (src, dst, adjust_formulas = true))]
+    fn copy_range<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        src: &str,
+        dst: &str,
+        adjust_formulas: bool,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .copy_range(src, dst, adjust_formulas)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (src, dst, adjust_formulas = true))]
+    fn move_range<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        src: &str,
+        dst: &str,
+        adjust_formulas: bool,
     ) -> PyResult<PyRefMut<'py, Self>> {
         slf.editor
-            .merge_cells(range)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            .move_range(src, dst, adjust_formulas)
+            .map_err(py_err)?;
         Ok(slf)
     }
+    /// mode: "contents" | "formats" | "all"
+    #[pyo3(signature = (range, mode = "all"))]
+    fn clear_range<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: &str,
+        mode: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let mode: ClearMode = mode.parse().map_err(py_err)?;
+        slf.editor.clear_range(range, mode).map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (pattern, match_case = false, whole_cell = false, regex = false, range = None))]
+    fn find(
+        &mut self,
+        pattern: &str,
+        match_case: bool,
+        whole_cell: bool,
+        regex: bool,
+        range: Option<String>,
+    ) -> PyResult<Vec<String>> {
+        let opts = FindOptions {
+            match_case,
+            whole_cell,
+            regex,
+            range,
+        };
+        self.editor.find_with(pattern, &opts).map_err(py_err)
+    }
+    /// Возвращает число изменённых ячеек.
+    #[pyo3(signature = (pattern, replacement, match_case = false, whole_cell = false, regex = false, range = None))]
+    fn replace_all(
+        &mut self,
+        pattern: &str,
+        replacement: &str,
+        match_case: bool,
+        whole_cell: bool,
+        regex: bool,
+        range: Option<String>,
+    ) -> PyResult<usize> {
+        let opts = FindOptions {
+            match_case,
+            whole_cell,
+            regex,
+            range,
+        };
+        self.editor
+            .replace_all(pattern, replacement, &opts)
+            .map_err(py_err)
+    }
+    /// Дописывает CSV-файл `path` под данные листа; возвращает число строк данных.
+    /// schema: типы столбцов по порядку — "auto" | "text" | "number" | "bool" | "date"
+    #[pyo3(signature = (path, delimiter = ",", has_header = true, write_header = false, schema = None))]
+    fn append_csv(
+        &mut self,
+        path: PathBuf,
+        delimiter: &str,
+        has_header: bool,
+        write_header: bool,
+        schema: Option<Vec<String>>,
+    ) -> PyResult<usize> {
+        let &[delimiter] = delimiter.as_bytes() else {
+            return Err(PyRuntimeError::new_err("delimiter must be a single ASCII character"));
+        };
+        let schema = schema
+            .unwrap_or_default()
+            .iter()
+            .map(|t| t.parse::<CsvType>())
+            .collect::<Result<Vec<_>, XlsxError>>()
+            .map_err(py_err)?;
+        let opts = CsvOptions {
+            delimiter,
+            has_header,
+            write_header,
+            schema,
+        };
+        let file = std::fs::File::open(&path)?;
+        self.editor.append_csv(file, &opts).map_err(py_err)
+    }
+    fn fill_template<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        values: HashMap<String, Bound<'py, PyAny>>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let values = to_record(values)?;
+        slf.editor.fill_template(&values).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn repeat_block<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: &str,
+        records: Vec<HashMap<String, Bound<'py, PyAny>>>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let records = records
+            .into_iter()
+            .map(to_record)
+            .collect::<PyResult<Vec<_>>>()?;
+        slf.editor.repeat_block(range, &records).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn get_merged_ranges(&self) -> PyResult<Vec<String>> {
+        self.editor.get_merged_ranges().map_err(py_err)
+    }
+    #[pyo3(signature = (range, style, color = None))]
     fn set_border<'py>(
         mut slf: PyRefMut<'py, Self>,
         range: &str,
         style: &str,
+        color: Option<&str>,
     ) -> PyResult<PyRefMut<'py, Self>> {
-        slf.editor
-            .set_border(range, style)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        match color {
+            Some(c) => slf.editor.set_border_with_color(range, style, c),
+            None => slf.editor.set_border(range, style),
+        }
+        .map_err(py_err)?;
         Ok(slf)
     }
+    /// Палитра темы книги: `[{"name": "lt1", "rgb": "FFFFFF"}, …]` в порядке
+    /// индексов `theme:N`.
+    fn get_theme_colors<'py>(&mut self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.editor
+            .get_theme_colors()
+            .map_err(py_err)?
+            .into_iter()
+            .map(|c| {
+                let d = PyDict::new(py);
+                d.set_item("name", c.name)?;
+                d.set_item("rgb", c.rgb)?;
+                Ok(d)
+            })
+            .collect()
+    }
     fn set_column_width<'py>(
         mut slf: PyRefMut<'py, Self>,
         col_letter: &str,
@@ -274,7 +1675,253 @@ impl Editor {
     ) -> PyResult<PyRefMut<'py, Self>> {
         slf.editor
             .set_column_width(col_letter, width)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (cols, enabled = true))]
+    fn set_column_best_fit<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cols: &str,
+        enabled: bool,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .set_column_best_fit(cols, enabled)
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_row_height<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        rows: &str,
+        height: f64,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_row_height(rows, height).map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (range = None))]
+    fn autofit_columns<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.autofit_columns(range).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn freeze_panes<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        cell: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.freeze_panes(cell).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn freeze_top_row<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.freeze_top_row().map_err(py_err)?;
+        Ok(slf)
+    }
+    fn freeze_first_column<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.freeze_first_column().map_err(py_err)?;
+        Ok(slf)
+    }
+    fn unfreeze_panes<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.unfreeze_panes().map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (show_grid_lines = None, zoom_scale = None, right_to_left = None, active_cell = None, selection = None))]
+    fn set_sheet_view<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        show_grid_lines: Option<bool>,
+        zoom_scale: Option<u16>,
+        right_to_left: Option<bool>,
+        active_cell: Option<String>,
+        selection: Option<String>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor
+            .set_sheet_view(SheetViewOptions {
+                show_grid_lines,
+                zoom_scale,
+                right_to_left,
+                active_cell,
+                selection,
+            })
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    /// orientation: "portrait" | "landscape";
+    /// margins: (left, right, top, bottom, header, footer) в дюймах
+    #[pyo3(signature = (
+        orientation = None,
+        paper_size = None,
+        scale = None,
+        fit_to_pages = None,
+        margins = None,
+        center_horizontally = None,
+        center_vertically = None,
+        print_grid_lines = None,
+        print_headings = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn set_page_setup<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        orientation: Option<&str>,
+        paper_size: Option<u32>,
+        scale: Option<u32>,
+        fit_to_pages: Option<(u32, u32)>,
+        margins: Option<(f64, f64, f64, f64, f64, f64)>,
+        center_horizontally: Option<bool>,
+        center_vertically: Option<bool>,
+        print_grid_lines: Option<bool>,
+        print_headings: Option<bool>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let orientation = orientation
+            .map(str::parse::<Orientation>)
+            .transpose()
+            .map_err(py_err)?;
+        let margins = margins.map(|(left, right, top, bottom, header, footer)| PageMargins {
+            left,
+            right,
+            top,
+            bottom,
+            header,
+            footer,
+        });
+        let setup = PageSetup {
+            orientation,
+            paper_size,
+            scale,
+            fit_to_pages,
+            margins,
+            center_horizontally,
+            center_vertically,
+            print_grid_lines,
+            print_headings,
+        };
+        slf.editor.set_page_setup(setup).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_print_area<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_print_area(range).map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (rows = None, cols = None))]
+    fn set_print_titles<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        rows: Option<&str>,
+        cols: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_print_titles(rows, cols).map_err(py_err)?;
+        Ok(slf)
+    }
+    /// Текст колонтитулов — в формате Excel: "&LСлева&CПо центру&RСтр. &P из &N"
+    #[pyo3(signature = (
+        header = None,
+        footer = None,
+        even_header = None,
+        even_footer = None,
+        first_header = None,
+        first_footer = None,
+        different_odd_even = false,
+        different_first = false
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn set_header_footer<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: Option<String>,
+        footer: Option<String>,
+        even_header: Option<String>,
+        even_footer: Option<String>,
+        first_header: Option<String>,
+        first_footer: Option<String>,
+        different_odd_even: bool,
+        different_first: bool,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let hf = HeaderFooter {
+            header,
+            footer,
+            even_header,
+            even_footer,
+            first_header,
+            first_footer,
+            different_odd_even,
+            different_first,
+        };
+        slf.editor.set_header_footer(hf).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn insert_page_break<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        after_row: u32,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.insert_page_break(after_row).map_err(py_err)?;
+        Ok(slf)
+    }
+    /// image: содержимое файла (bytes) или путь к нему (str / os.PathLike)
+    #[pyo3(signature = (coord, image, width = None, height = None, offset_x = 0, offset_y = 0, alt_text = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn insert_image<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        coord: &str,
+        image: &Bound<'py, PyAny>,
+        width: Option<u32>,
+        height: Option<u32>,
+        offset_x: u32,
+        offset_y: u32,
+        alt_text: Option<String>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let opts = ImageOptions {
+            width,
+            height,
+            offset_x,
+            offset_y,
+            alt_text,
+        };
+        let res = match image.downcast::<PyBytes>() {
+            Ok(bytes) => slf.editor.insert_image(coord, bytes.as_bytes(), opts),
+            Err(_) => {
+                let path: PathBuf = image.extract()?;
+                slf.editor.insert_image_file(coord, path, opts)
+            }
+        };
+        res.map_err(py_err)?;
+        Ok(slf)
+    }
+    fn set_auto_filter<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.set_auto_filter(range).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn remove_auto_filter<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.remove_auto_filter().map_err(py_err)?;
+        Ok(slf)
+    }
+    #[pyo3(signature = (range, name, style = None, columns = None))]
+    fn add_table<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        range: &str,
+        name: &str,
+        style: Option<&str>,
+        columns: Option<Vec<String>>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let cols: Option<Vec<&str>> = columns
+            .as_ref()
+            .map(|c| c.iter().map(String::as_str).collect());
+        slf.editor
+            .add_table(range, name, style, cols.as_deref())
+            .map_err(py_err)?;
+        Ok(slf)
+    }
+    fn extend_table<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        name: &str,
+        extra_rows: u32,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.extend_table(name, extra_rows).map_err(py_err)?;
+        Ok(slf)
+    }
+    fn mark_pivot_caches_dirty<'py>(mut slf: PyRefMut<'py, Self>) -> PyResult<PyRefMut<'py, Self>> {
+        slf.editor.mark_pivot_caches_dirty().map_err(py_err)?;
         Ok(slf)
     }
     fn set_columns_width<'py>(
@@ -285,11 +1932,61 @@ impl Editor {
         for col_letter in col_letters.iter() {
             slf.editor
                 .set_column_width(col_letter, width)
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                .map_err(py_err)?;
         }
         Ok(slf)
     }
 }
+/// Транзакция из `Editor.transaction()`: `begin()` на входе в `with`, на
+/// выходе `commit()`, а при исключении — `rollback()` (исключение не
+/// подавляется).
+#[pyclass]
+struct Transaction {
+    editor: Py<Editor>,
+}
+#[pymethods]
+impl Transaction {
+    fn __enter__(&self, py: Python<'_>) -> Py<Editor> {
+        self.editor.borrow_mut(py).editor.begin();
+        self.editor.clone_ref(py)
+    }
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> PyResult<bool> {
+        let mut ed = self.editor.borrow_mut(py);
+        match exc_type {
+            None => ed.editor.commit().map_err(py_err)?,
+            Some(_) => ed.editor.rollback().map_err(py_err)?,
+        };
+        Ok(false)
+    }
+}
+/// Итератор строк листа из `Editor.rows()`: кортежи значений, как у `get_cell`.
+#[pyclass]
+struct RowIterator {
+    rows: Rows,
+}
+#[pymethods]
+impl RowIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyTuple>>> {
+        let Some(row) = self.rows.next() else {
+            return Ok(None);
+        };
+        let values = row
+            .map_err(py_err)?
+            .iter()
+            .map(|v| read_to_py(py, v))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Some(PyTuple::new(py, values)?))
+    }
+}
 #[pyclass]
 struct Scanner {
     path: PathBuf,
@@ -301,12 +1998,14 @@ impl Scanner {
         Ok(Scanner { path })
     }
     fn get_sheets(&self) -> PyResult<Vec<String>> {
-        scan_excel(self.path.clone()).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        scan_excel(self.path.clone())
     }
     fn open_editor(&self, sheet_name: String) -> PyResult<Editor> {
-        let openned = XlsxEditor::open(self.path.clone(), &sheet_name)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-        Ok(Editor { editor: openned })
+        let openned = XlsxEditor::open(self.path.clone(), &sheet_name).map_err(py_err)?;
+        Ok(Editor {
+            editor: openned,
+            save_to: Some(self.path.clone()),
+        })
     }
 }
 
@@ -314,7 +2013,23 @@ impl Scanner {
 fn excelsior(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<Editor>()?;
     m.add_class::<Scanner>()?;
+    m.add_class::<RowIterator>()?;
+    m.add_class::<Transaction>()?;
+    m.add_class::<PyNumFmt>()?;
     m.add_function(wrap_pyfunction!(scan_excel, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_workbook, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_workbooks, m)?)?;
+    m.add_function(wrap_pyfunction!(adjust_formula, m)?)?;
+    m.add_function(wrap_pyfunction!(translate_r1c1_to_a1, m)?)?;
+    m.add("SheetNotFoundError", py.get_type::<SheetNotFoundError>())?;
+    m.add("XlsxCorruptError", py.get_type::<XlsxCorruptError>())?;
+    m.add("MalformedSheetError", py.get_type::<MalformedSheetError>())?;
+    m.add(
+        "RowLimitExceededError",
+        py.get_type::<RowLimitExceededError>(),
+    )?;
+    m.add("InvalidRangeError", py.get_type::<InvalidRangeError>())?;
+    m.add("ZipError", py.get_type::<ZipError>())?;
 
     // --- РЕГИСТРАЦИЯ НОВЫХ КЛАССОВ И ENUM-ОВ ---
 
@@ -0,0 +1,119 @@
+"""
+Сверяет excelsior/__init__.pyi с собранным модулем: те же классы, функции и
+методы с теми же именами параметров, что в `__text_signature__` из pyo3.
+
+Запуск после `maturin develop`:  python stubcheck.py [--docs]
+С `--docs` дополнительно печатает методы без doc-комментария в Rust.
+Код возврата 1, если заглушки расходятся с модулем.
+"""
+
+import ast
+import inspect
+import pathlib
+import sys
+
+import excelsior
+
+STUB = pathlib.Path(__file__).parent / "excelsior" / "__init__.pyi"
+
+
+def runtime_params(obj):
+    sig = getattr(obj, "__text_signature__", None)
+    if sig is None:
+        return None
+    sig = sig.replace("$self", "self").replace("$cls", "cls")
+    try:
+        params = inspect.signature(_stub_fn(sig)).parameters
+    except (ValueError, SyntaxError):
+        return None
+    return [p for p in params if p not in ("self", "cls", "/")]
+
+
+def _stub_fn(sig):
+    ns = {}
+    exec(f"def f{sig}: pass", ns)
+    return ns["f"]
+
+
+def stub_params(fn):
+    args = fn.args
+    names = [a.arg for a in args.posonlyargs + args.args + args.kwonlyargs]
+    return [n for n in names if n not in ("self", "cls")]
+
+
+def main():
+    tree = ast.parse(STUB.read_text(encoding="utf-8"))
+    stub_funcs = {}
+    stub_classes = {}
+    for node in tree.body:
+        if isinstance(node, ast.FunctionDef):
+            stub_funcs[node.name] = node
+        elif isinstance(node, ast.ClassDef):
+            methods = {}
+            for item in node.body:
+                if isinstance(item, ast.FunctionDef):
+                    # у перегрузок (@overload) параметры делятся между вариантами:
+                    # сравниваем их объединение в порядке появления
+                    if item.name in methods:
+                        merged = methods[item.name]
+                        seen = set(stub_params(merged))
+                        merged.args.kwonlyargs += [
+                            a for a in item.args.args + item.args.kwonlyargs if a.arg not in seen
+                        ]
+                    else:
+                        methods[item.name] = item
+            stub_classes[node.name] = methods
+
+    problems = []
+    undocumented = []
+    public = {n: getattr(excelsior, n) for n in dir(excelsior) if not n.startswith("_")}
+    for name, obj in public.items():
+        if inspect.isclass(obj):
+            if name not in stub_classes:
+                problems.append(f"class {name} is missing from the stub")
+                continue
+            if issubclass(obj, BaseException):
+                continue
+            methods = stub_classes[name]
+            for attr, member in vars(obj).items():
+                if attr.startswith("_") and attr not in ("__enter__", "__exit__"):
+                    continue
+                if not callable(member) and not isinstance(member, staticmethod):
+                    continue
+                fn = getattr(obj, attr)
+                if attr not in methods:
+                    problems.append(f"{name}.{attr} is missing from the stub")
+                    continue
+                want = runtime_params(fn)
+                got = stub_params(methods[attr])
+                if want is not None and want != got:
+                    problems.append(f"{name}.{attr}: stub has {got}, module has {want}")
+                if not (fn.__doc__ or "").strip():
+                    undocumented.append(f"{name}.{attr}")
+            for attr in methods:
+                # методы polars есть только в сборке с feature `polars`
+                if "polars" in attr and not hasattr(obj, "to_polars"):
+                    continue
+                if attr != "__init__" and not hasattr(obj, attr):
+                    problems.append(f"{name}.{attr} is in the stub but not in the module")
+        elif callable(obj):
+            if name not in stub_funcs:
+                problems.append(f"function {name} is missing from the stub")
+                continue
+            want = runtime_params(obj)
+            got = stub_params(stub_funcs[name])
+            if want is not None and want != got:
+                problems.append(f"{name}: stub has {got}, module has {want}")
+            if not (obj.__doc__ or "").strip():
+                undocumented.append(name)
+
+    for p in problems:
+        print(p)
+    if "--docs" in sys.argv:
+        for name in undocumented:
+            print(f"no docstring: {name}")
+    return 1 if problems else 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
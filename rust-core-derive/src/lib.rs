@@ -0,0 +1,199 @@
+//! `#[derive(XlsxRow)]` для `rust-core` (feature `serde`): столбцы
+//! `XlsxRow::columns` из именованных полей структуры.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{Data, DeriveInput, Error, Field, Fields, LitStr, Result, Token, parse_macro_input};
+
+/// Столбцы — поля структуры в порядке объявления, под теми же именами, что
+/// даёт `Serialize` (`#[serde(rename)]` и `rename_all` учитываются).
+///
+/// Атрибуты поля: `#[xlsx(header = "Итого")]`, `#[xlsx(number_format =
+/// "#,##0.00")]`, `#[xlsx(skip)]`. Поля с `#[serde(skip)]` и
+/// `skip_serializing` не пишутся.
+#[proc_macro_derive(XlsxRow, attributes(xlsx))]
+pub fn derive_xlsx_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => return Err(Error::new_spanned(&input.ident, NAMED_ONLY)),
+        },
+        _ => return Err(Error::new_spanned(&input.ident, NAMED_ONLY)),
+    };
+    let rename_all = container_rename_all(input)?;
+
+    let mut columns = Vec::new();
+    for f in fields {
+        let Some(col) = column(f, rename_all.as_ref())? else {
+            continue;
+        };
+        let Column {
+            field,
+            header,
+            number_format,
+        } = col;
+        let (header, number_format) = (opt_string(header), opt_string(number_format));
+        columns.push(quote! {
+            ::rust_core::serde_part::XlsxColumn {
+                field: ::std::string::String::from(#field),
+                header: #header,
+                number_format: #number_format,
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rust_core::serde_part::XlsxRow for #name #ty_generics #where_clause {
+            fn columns() -> ::std::vec::Vec<::rust_core::serde_part::XlsxColumn> {
+                ::std::vec![#(#columns),*]
+            }
+        }
+    })
+}
+
+const NAMED_ONLY: &str = "XlsxRow can only be derived for structs with named fields";
+
+struct Column {
+    field: String,
+    header: Option<LitStr>,
+    number_format: Option<LitStr>,
+}
+
+fn opt_string(s: Option<LitStr>) -> TokenStream2 {
+    match s {
+        Some(s) => quote!(::std::option::Option::Some(::std::string::String::from(#s))),
+        None => quote!(::std::option::Option::None),
+    }
+}
+
+/// Столбец поля `f`; `None` — поле не пишется.
+fn column(f: &Field, rename_all: Option<&LitStr>) -> Result<Option<Column>> {
+    let ident = f.ident.as_ref().expect("named field");
+    let mut col = Column {
+        field: rename(&ident.unraw().to_string(), rename_all)?,
+        header: None,
+        number_format: None,
+    };
+    let mut skip = false;
+    for attr in &f.attrs {
+        if attr.path().is_ident("xlsx") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("header") {
+                    col.header = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("number_format") {
+                    col.number_format = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else {
+                    return Err(meta.error("expected `header`, `number_format` or `skip`"));
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("serde") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                    skip = true;
+                } else if meta.path.is_ident("rename") {
+                    if meta.input.peek(Token![=]) {
+                        col.field = meta.value()?.parse::<LitStr>()?.value();
+                    } else {
+                        meta.parse_nested_meta(|m| {
+                            if m.path.is_ident("serialize") {
+                                col.field = m.value()?.parse::<LitStr>()?.value();
+                            } else {
+                                m.value()?.parse::<LitStr>()?;
+                            }
+                            Ok(())
+                        })?;
+                    }
+                } else if meta.path.is_ident("flatten") {
+                    return Err(meta.error("XlsxRow does not support #[serde(flatten)]"));
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok((!skip).then_some(col))
+}
+
+/// `rename_all` контейнера (или его `serialize = ...`).
+fn container_rename_all(input: &DeriveInput) -> Result<Option<LitStr>> {
+    let mut rule = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("rename_all") {
+                return skip_meta(&meta);
+            }
+            if meta.input.peek(Token![=]) {
+                rule = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+            meta.parse_nested_meta(|m| {
+                let value: LitStr = m.value()?.parse()?;
+                if m.path.is_ident("serialize") {
+                    rule = Some(value);
+                }
+                Ok(())
+            })
+        })?;
+    }
+    Ok(rule)
+}
+
+/// Пропускает чужой атрибут serde: `name`, `name = expr` или `name(...)`.
+fn skip_meta(meta: &syn::meta::ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let _content;
+        syn::parenthesized!(_content in meta.input);
+    }
+    Ok(())
+}
+
+/// Имя поля `snake_case` по правилу serde `rename_all`.
+fn rename(field: &str, rule: Option<&LitStr>) -> Result<String> {
+    let Some(rule) = rule else {
+        return Ok(field.to_owned());
+    };
+    let words = field.split('_').filter(|w| !w.is_empty());
+    let capitalize = |w: &str| {
+        let mut c = w.chars();
+        c.next()
+            .map(|f| f.to_uppercase().chain(c).collect::<String>())
+            .unwrap_or_default()
+    };
+    Ok(match rule.value().as_str() {
+        "lowercase" | "snake_case" => field.to_owned(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_uppercase(),
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.to_uppercase().replace('_', "-"),
+        "PascalCase" => words.map(capitalize).collect(),
+        "camelCase" => {
+            let pascal: String = words.map(capitalize).collect();
+            let mut c = pascal.chars();
+            c.next()
+                .map(|f| f.to_lowercase().chain(c).collect())
+                .unwrap_or_default()
+        }
+        other => {
+            return Err(Error::new(
+                rule.span(),
+                format!("unknown rename_all rule {other:?}"),
+            ));
+        }
+    })
+}
@@ -0,0 +1,203 @@
+//! Опорные замеры редактора на criterion: открытие большой книги, дозапись
+//! миллиона строк (смешанных и чисто числовых), стили на 100 тыс. ячеек и
+//! сохранение. Criterion хранит прошлые прогоны в `target/criterion` и
+//! показывает изменение относительно них; именованный срез —
+//! `-- --save-baseline NAME`, сравнение с ним — `-- --baseline NAME`.
+//!
+//! `cargo bench -p rust-core --bench editor [-- FILTER]`
+
+use criterion::{BatchSize, Criterion, SamplingMode, Throughput, criterion_group, criterion_main};
+use rust_core::{XlsxEditor, scan};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+// `--features alloc-mimalloc` — те же замеры на mimalloc
+#[cfg(feature = "alloc-mimalloc")]
+#[global_allocator]
+static GLOBAL: rust_core::MiMalloc = rust_core::MiMalloc;
+
+const TEMPLATE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/test.xlsx");
+/// строк в книге для открытия и сохранения (~50 МБ XML листа)
+const BIG_ROWS: u32 = 200_000;
+const APPEND_ROWS: u32 = 1_000_000;
+/// 20 000 строк × 5 столбцов = 100 000 ячеек под стили
+const STYLE_ROWS: u32 = 20_000;
+
+/// Строка только из чисел: целые и дроби.
+fn numeric_row(i: u32) -> [f64; 5] {
+    let x = f64::from(i);
+    [x, x * 7.0, x / 8.0, x * 0.001, 1e6 - x]
+}
+
+fn row(i: u32) -> [String; 5] {
+    [
+        format!("customer {i}"),
+        (i * 7).to_string(),
+        format!("{}.25", i % 1000),
+        "=B1*2".to_owned(),
+        format!("note for row {i} with some text"),
+    ]
+}
+
+/// Пустой лист "Bench" в книге из test.xlsx, в памяти.
+fn blank() -> XlsxEditor {
+    static TEMPLATE_BYTES: OnceLock<Arc<[u8]>> = OnceLock::new();
+    let data = TEMPLATE_BYTES.get_or_init(|| std::fs::read(TEMPLATE).unwrap().into());
+    let first = &scan(TEMPLATE).unwrap()[0];
+    let mut ed = XlsxEditor::open_bytes(data.clone(), first).unwrap();
+    ed.add_worksheet("Bench").unwrap();
+    ed
+}
+
+/// Книга с листом "Bench" из `rows` строк [`row`].
+fn filled(rows: u32) -> Vec<u8> {
+    let mut ed = blank();
+    ed.append_table((0..rows).map(row)).unwrap();
+    ed.save_to_bytes().unwrap()
+}
+
+/// Файл с [`BIG_ROWS`] строками; собирается один раз за прогон.
+fn big_book() -> &'static Path {
+    static BOOK: OnceLock<PathBuf> = OnceLock::new();
+    BOOK.get_or_init(|| {
+        let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("bench_editor.xlsx");
+        std::fs::write(&path, filled(BIG_ROWS)).unwrap();
+        path
+    })
+}
+
+/// Как [`big_book`], но за большим листом — пустой лист "Tail".
+fn big_book_with_tail() -> &'static Path {
+    static BOOK: OnceLock<PathBuf> = OnceLock::new();
+    BOOK.get_or_init(|| {
+        let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("bench_editor_tail.xlsx");
+        let mut ed = XlsxEditor::open(big_book(), "Bench").unwrap();
+        ed.add_worksheet("Tail").unwrap();
+        ed.save(&path).unwrap();
+        path
+    })
+}
+
+fn open(c: &mut Criterion) {
+    let book = big_book();
+    let mut g = c.benchmark_group("open");
+    g.sample_size(20);
+    g.bench_function("200k_rows", |b| {
+        b.iter(|| XlsxEditor::open(book, "Bench").unwrap())
+    });
+    g.finish();
+}
+
+fn append(c: &mut Criterion) {
+    let mut g = c.benchmark_group("append");
+    g.sample_size(10)
+        .sampling_mode(SamplingMode::Flat)
+        .measurement_time(Duration::from_secs(90))
+        .throughput(Throughput::Elements(APPEND_ROWS as u64));
+    g.bench_function("append_row_1m", |b| {
+        b.iter_batched(
+            blank,
+            |mut ed| {
+                for i in 0..APPEND_ROWS {
+                    ed.append_row(row(i)).unwrap();
+                }
+                ed
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    g.bench_function("append_table_1m", |b| {
+        b.iter_batched(
+            blank,
+            |mut ed| {
+                ed.append_table((0..APPEND_ROWS).map(row)).unwrap();
+                ed
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    g.bench_function("append_table_numeric_1m", |b| {
+        b.iter_batched(
+            blank,
+            |mut ed| {
+                ed.append_table((0..APPEND_ROWS).map(numeric_row)).unwrap();
+                ed
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    g.finish();
+}
+
+fn style(c: &mut Criterion) {
+    let book: Arc<[u8]> = filled(STYLE_ROWS).into();
+    let range = format!("A1:E{STYLE_ROWS}");
+    let fresh = || XlsxEditor::open_bytes(book.clone(), "Bench").unwrap();
+    let mut g = c.benchmark_group("style");
+    // итерация идёт секунды: время с запасом, чтобы criterion не ругался на 10 замеров
+    g.sample_size(10)
+        .sampling_mode(SamplingMode::Flat)
+        .measurement_time(Duration::from_secs(300))
+        .throughput(Throughput::Elements(STYLE_ROWS as u64 * 5));
+    g.bench_function("fill_100k", |b| {
+        b.iter_batched(
+            fresh,
+            |mut ed| {
+                ed.set_fill(&range, "FFFF00").unwrap();
+                ed
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    g.bench_function("fill_font_format_100k", |b| {
+        b.iter_batched(
+            fresh,
+            |mut ed| {
+                ed.set_fill(&range, "FFFF00").unwrap();
+                ed.set_font(&range, "Arial", 10.0, true, false).unwrap();
+                ed.set_number_format(&range, "0.00").unwrap();
+                ed
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    g.finish();
+}
+
+fn save(c: &mut Criterion) {
+    let book = big_book();
+    let mut g = c.benchmark_group("save");
+    g.sample_size(10)
+        .throughput(Throughput::Bytes(std::fs::metadata(book).unwrap().len()));
+    // одна дописанная строка: лист переписывается целиком, остальное копируется
+    g.bench_function("200k_rows_to_bytes", |b| {
+        b.iter_batched(
+            || {
+                let mut ed = XlsxEditor::open(book, "Bench").unwrap();
+                ed.append_row(["saved"]).unwrap();
+                ed
+            },
+            |mut ed| ed.save_to_bytes().unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+    // дописан маленький последний лист: большой лист перед ним не меняется
+    let tail_book = big_book_with_tail();
+    let dst = Path::new(env!("CARGO_TARGET_TMPDIR")).join("bench_editor_tail_out.xlsx");
+    g.bench_function("200k_rows_untouched_to_file", |b| {
+        b.iter_batched(
+            || {
+                let mut ed = XlsxEditor::open(tail_book, "Tail").unwrap();
+                ed.append_row(["saved"]).unwrap();
+                ed
+            },
+            |mut ed| ed.save(&dst).unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+    g.finish();
+}
+
+criterion_group!(benches, open, append, style, save);
+criterion_main!(benches);
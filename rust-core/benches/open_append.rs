@@ -0,0 +1,50 @@
+//! Открытие книги с большим листом (~50 МБ XML), дозапись строк и сохранение.
+//!
+//! `cargo bench -p rust-core --bench open_append [-- ROWS]`
+
+use rust_core::{XlsxEditor, scan};
+use std::time::Instant;
+
+fn main() -> anyhow::Result<()> {
+    let rows = std::env::args()
+        .skip(1)
+        .find(|a| a != "--bench")
+        .map(|a| a.parse::<u32>())
+        .transpose()?
+        .unwrap_or(200_000);
+
+    // ~250 байт XML на строку: 200 000 строк — около 50 МБ
+    let book = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("open_append.xlsx");
+    let src = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/test.xlsx");
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Big")?;
+    for i in 0..rows {
+        ed.append_row([
+            format!("customer {i}"),
+            (i * 7).to_string(),
+            format!("{}.25", i % 1000),
+            "=B1*2".to_owned(),
+            format!("note for row {i} with some text"),
+        ])?;
+    }
+    ed.save(&book)?;
+
+    for _ in 0..3 {
+        let t = Instant::now();
+        let mut ed = XlsxEditor::open(&book, "Big")?;
+        let open = t.elapsed();
+        let t = Instant::now();
+        for i in 0..1000 {
+            ed.append_row([format!("appended {i}"), i.to_string()])?;
+        }
+        ed.merge_cells(&format!("A{}:B{}", rows + 1, rows + 1))?;
+        let append = t.elapsed();
+        let t = Instant::now();
+        ed.save_to_bytes()?;
+        println!(
+            "{rows} rows: open {open:.1?}, append {append:.1?}, save {:.1?}",
+            t.elapsed()
+        );
+    }
+    Ok(())
+}
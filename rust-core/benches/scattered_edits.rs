@@ -0,0 +1,51 @@
+//! Разбросанные правки ячеек на большом листе: `set_cell` и заливка одной
+//! ячейки в случайных местах, затем сохранение.
+//!
+//! `cargo bench -p rust-core --bench scattered_edits [-- ROWS EDITS]`
+
+use rust_core::{XlsxEditor, scan};
+use std::time::Instant;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args()
+        .skip(1)
+        .filter(|a| a != "--bench")
+        .map(|a| a.parse::<u64>());
+    let rows = args.next().transpose()?.unwrap_or(50_000);
+    let edits = args.next().transpose()?.unwrap_or(100_000);
+
+    let src = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/test.xlsx");
+    let mut ed = XlsxEditor::open_bytes(std::fs::read(src)?, &scan(src)?[0])?;
+    ed.add_worksheet("Bench")?;
+    for i in 0..rows {
+        ed.append_row([format!("row {i}"), i.to_string(), (i * 3).to_string()])?;
+    }
+
+    let t = Instant::now();
+    let mut seed = 1u64;
+    for i in 0..edits {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let row = (seed >> 33) % (rows + rows / 10) + 1;
+        let col = b'A' + ((seed >> 20) % 8) as u8;
+        let coord = format!("{}{row}", col as char);
+        if i % 10 == 0 {
+            ed.set_fill(&coord, "FFFF00")?;
+        } else {
+            ed.set_cell(&coord, i)?;
+        }
+    }
+    let edit_time = t.elapsed();
+    let t = Instant::now();
+    let bytes = ed.save_to_bytes()?;
+    let save_time = t.elapsed();
+
+    println!(
+        "{rows} rows, {edits} edits: edits {:.1?}, save {:.1?}, {} KiB",
+        edit_time,
+        save_time,
+        bytes.len() / 1024
+    );
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! `with_polars` на DataFrame 1 000 000 × 20 (числа, целые и строки), на
+//! criterion, как и `benches/editor.rs`.
+//!
+//! `cargo bench -p rust-core --features polars --bench with_polars`
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use polars_core::prelude::*;
+use rust_core::{XlsxEditor, scan};
+use std::time::Duration;
+
+const TEMPLATE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/test.xlsx");
+const ROWS: usize = 1_000_000;
+
+/// 10 столбцов f64, 5 i64 и 5 строковых.
+fn frame() -> DataFrame {
+    let mut cols = Vec::with_capacity(20);
+    for c in 0..10 {
+        let v: Vec<f64> = (0..ROWS).map(|i| i as f64 * 0.5 + c as f64).collect();
+        cols.push(Column::new(format!("f{c}").into(), v));
+    }
+    for c in 0..5 {
+        let v: Vec<i64> = (0..ROWS).map(|i| (i * 7 + c) as i64).collect();
+        cols.push(Column::new(format!("i{c}").into(), v));
+    }
+    for c in 0..5 {
+        let v: Vec<String> = (0..ROWS).map(|i| format!("s{c} row {i}")).collect();
+        cols.push(Column::new(format!("s{c}").into(), v));
+    }
+    DataFrame::new(cols).unwrap()
+}
+
+fn with_polars(c: &mut Criterion) {
+    let df = frame();
+    let data = std::fs::read(TEMPLATE).unwrap();
+    let first = scan(TEMPLATE).unwrap()[0].clone();
+    let mut g = c.benchmark_group("with_polars");
+    g.sample_size(10)
+        .measurement_time(Duration::from_secs(60))
+        .throughput(Throughput::Elements((ROWS * 20) as u64));
+    g.bench_function("1m_x_20", |b| {
+        b.iter_batched(
+            || {
+                let mut ed = XlsxEditor::open_bytes(data.clone(), &first).unwrap();
+                ed.add_worksheet("Bench").unwrap();
+                ed
+            },
+            |mut ed| {
+                ed.with_polars(&df, None).unwrap();
+                ed
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    g.finish();
+}
+
+criterion_group!(benches, with_polars);
+criterion_main!(benches);
@@ -0,0 +1,177 @@
+//! arrow_part.rs – запись arrow `RecordBatch` на лист без конвертации в polars:
+//! DataFusion, читатели parquet и прочие arrow-источники пишут напрямую.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, check_row, ensure};
+use crate::range_part::CellMap;
+use crate::sheet_xml::refresh_dimension;
+use crate::style::{col_letter, parse_cell};
+use crate::template_part::CellValue;
+use anyhow::Result;
+use arrow_array::{Array, ArrayRef, RecordBatch, cast::AsArray, types::Float64Type};
+use arrow_cast::cast;
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_schema::{DataType, TimeUnit};
+
+/// Сериал Excel для 1970-01-01.
+const EPOCH: f64 = 25_569.0;
+
+impl XlsxEditor {
+    /// Пишет `batches` с ячейки `start_cell` (по умолчанию `A1`): строка имён
+    /// полей схемы, под ней строки всех батчей подряд. Ячейки прямоугольника под
+    /// данные очищаются, остальные ячейки этих строк остаются на месте.
+    ///
+    /// Схема берётся из первого батча, остальные должны с ней совпадать; без
+    /// батчей лист не меняется. Числа пишутся числами, `Boolean` — `TRUE`/`FALSE`,
+    /// даты, время и длительности — сериалами Excel с форматом на столбец, как в
+    /// [`Self::with_polars`], Decimal — всеми цифрами; прочие типы — текстом в
+    /// отображении arrow.
+    pub fn with_arrow<I>(&mut self, batches: I, start_cell: Option<&str>) -> Result<(), XlsxError>
+    where
+        I: IntoIterator<Item = RecordBatch>,
+    {
+        let (c0, r0) = parse_cell(start_cell.unwrap_or("A1"))?;
+        let mut batches = batches.into_iter().peekable();
+        let Some(first) = batches.peek() else {
+            return Ok(());
+        };
+        let schema = first.schema();
+        ensure!(!schema.fields().is_empty(), "RecordBatch has no columns");
+        let styles = schema
+            .fields()
+            .iter()
+            .map(|f| match arrow_format(f.data_type()) {
+                Some(fmt) => self
+                    .ensure_style(Some(&fmt), None, None, None, None)
+                    .map(Some),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut cells = CellMap::new();
+        let header = cells.entry(r0).or_default();
+        for (col, f) in (c0..).zip(schema.fields()) {
+            header.insert(
+                col,
+                CellValue::Text(f.name().clone()).cell_xml(col, r0, None),
+            );
+        }
+        let mut last = r0;
+        for batch in batches {
+            // nullable может отличаться: батч без пропусков — не другая схема
+            let fields = batch.schema_ref().fields();
+            let same = fields.len() == schema.fields().len()
+                && (fields.iter().zip(schema.fields()))
+                    .all(|(a, b)| a.name() == b.name() && a.data_type() == b.data_type());
+            ensure!(same, "RecordBatch schema differs from the first batch");
+            check_row(last as u64 + batch.num_rows() as u64)?;
+            for ((col, array), &s) in (c0..).zip(batch.columns()).zip(&styles) {
+                let values = ArrowValues::new(array)?;
+                for idx in (0..array.len()).filter(|&i| array.is_valid(i)) {
+                    let row = last + 1 + idx as u32;
+                    let xml = values.cell_xml(idx, col, row, s);
+                    cells.entry(row).or_default().insert(col, xml);
+                }
+            }
+            last += batch.num_rows() as u32;
+        }
+
+        let c1 = c0 + schema.fields().len() as u32 - 1;
+        self.put_block((c0, r0, c1, last), cells)?;
+        refresh_dimension(&mut self.sheet_xml)?;
+        Ok(())
+    }
+}
+
+/// Значения одного столбца батча в виде, готовом для ячеек.
+enum ArrowValues<'a> {
+    /// Числа, а также даты и время, уже пересчитанные в сериалы Excel.
+    Num(Vec<Option<f64>>),
+    Bool(&'a arrow_array::BooleanArray),
+    /// Decimal: текст числа идёт в `<v>` как есть.
+    Exact(ArrayFormatter<'a>),
+    Text(ArrayFormatter<'a>),
+}
+
+impl<'a> ArrowValues<'a> {
+    fn new(array: &'a ArrayRef) -> Result<Self> {
+        let day = |u: &TimeUnit| match u {
+            TimeUnit::Second => 86_400.0,
+            TimeUnit::Millisecond => 86_400e3,
+            TimeUnit::Microsecond => 86_400e6,
+            TimeUnit::Nanosecond => 86_400e9,
+        };
+        // (через какой тип читать, единиц в сутках, сдвиг)
+        let (via, per_day, shift) = match array.data_type() {
+            DataType::Boolean => return Ok(Self::Bool(array.as_boolean())),
+            DataType::Decimal32(..)
+            | DataType::Decimal64(..)
+            | DataType::Decimal128(..)
+            | DataType::Decimal256(..) => return Ok(Self::Exact(formatter(array)?)),
+            dt if dt.is_integer() || dt.is_floating() => (None, 1.0, 0.0),
+            DataType::Date32 => (Some(DataType::Int32), 1.0, EPOCH),
+            DataType::Date64 => (Some(DataType::Int64), 86_400e3, EPOCH),
+            DataType::Timestamp(u, _) => (Some(DataType::Int64), day(u), EPOCH),
+            DataType::Time32(u) => (Some(DataType::Int32), day(u), 0.0),
+            DataType::Time64(u) | DataType::Duration(u) => (Some(DataType::Int64), day(u), 0.0),
+            _ => return Ok(Self::Text(formatter(array)?)),
+        };
+        let ints = match via {
+            Some(t) => cast(array, &t)?,
+            None => array.clone(),
+        };
+        let floats = cast(&ints, &DataType::Float64)?;
+        let values = floats
+            .as_primitive::<Float64Type>()
+            .iter()
+            .map(|v| v.map(|v| v / per_day + shift))
+            .collect();
+        Ok(Self::Num(values))
+    }
+
+    /// `<c>` для непустого значения `idx`.
+    fn cell_xml(&self, idx: usize, col: u32, row: u32, s: Option<u32>) -> Vec<u8> {
+        let value = match self {
+            Self::Num(v) => CellValue::Number(v[idx].unwrap_or_default()),
+            Self::Bool(b) => CellValue::Bool(b.value(idx)),
+            Self::Exact(f) => {
+                let style = s.map(|s| format!(r#" s="{s}""#)).unwrap_or_default();
+                let r = format!("{}{row}", col_letter(col));
+                return format!(r#"<c r="{r}"{style}><v>{}</v></c>"#, f.value(idx)).into_bytes();
+            }
+            Self::Text(f) => CellValue::Text(f.value(idx).to_string()),
+        };
+        value.cell_xml(col, row, s)
+    }
+}
+
+fn formatter(array: &ArrayRef) -> Result<ArrayFormatter<'_>> {
+    Ok(ArrayFormatter::try_new(
+        array.as_ref(),
+        &FormatOptions::default(),
+    )?)
+}
+
+/// numFmt для столбца даты/времени или Decimal; `None` — формат не нужен.
+fn arrow_format(dtype: &DataType) -> Option<String> {
+    let fmt = match dtype {
+        DataType::Date32 | DataType::Date64 => "yyyy-mm-dd",
+        DataType::Timestamp(..) => "yyyy-mm-dd hh:mm:ss",
+        DataType::Duration(_) => "[h]:mm:ss",
+        DataType::Time32(_) | DataType::Time64(_) => "hh:mm:ss",
+        DataType::Decimal32(_, scale)
+        | DataType::Decimal64(_, scale)
+        | DataType::Decimal128(_, scale)
+        | DataType::Decimal256(_, scale)
+            if *scale > 0 =>
+        {
+            return Some(format!("0.{}", "0".repeat(*scale as usize)));
+        }
+        DataType::Decimal32(..)
+        | DataType::Decimal64(..)
+        | DataType::Decimal128(..)
+        | DataType::Decimal256(..) => "0",
+        _ => return None,
+    };
+    Some(fmt.to_owned())
+}
@@ -0,0 +1,355 @@
+//! audit_part.rs – сводка правок книги с открытия: какие части изменены,
+//! сколько строк дописано в листы, какие ячейки перезаписаны и какие стили
+//! заведены.
+//!
+//! Части, ячейки и стили сравниваются с исходным архивом в момент вызова
+//! [`XlsxEditor::change_summary`], так что сводку дают любые правки, какими
+//! бы методами они ни делались. Дописанные строки считает
+//! `grow_ranges_on_append`, через который проходит вся дозапись.
+//! [`XlsxEditor::write_audit_sheet`] кладёт сводку в скрытый лист книги.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, next_event};
+use crate::files_part::same_as_entry;
+use crate::lazy_part::{STYLES, WORKBOOK, WORKBOOK_RELS};
+use crate::read_part::{RawCell, read_cells};
+use crate::sheets_part::SheetVisibility;
+use crate::style::col_letter;
+use anyhow::Result;
+use quick_xml::{Reader, events::Event};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Read;
+
+/// Что стало с частью архива.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+impl fmt::Display for PartChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PartChangeKind::Added => "added",
+            PartChangeKind::Modified => "modified",
+            PartChangeKind::Removed => "removed",
+        })
+    }
+}
+
+/// Часть архива, которую save() запишет иначе, чем в исходной книге.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartChange {
+    pub path: String,
+    pub kind: PartChangeKind,
+}
+
+/// Изменённый или новый лист.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SheetChange {
+    pub name: String,
+    pub path: String,
+    /// Сколько строк дописано в конец листа (`append_*`, `with_polars`…).
+    pub rows_appended: u32,
+    /// Ячейки исходного листа (`"B7"`), где теперь другое значение или
+    /// формула, включая очищенные; по строкам. Сравнение по адресу: после
+    /// вставки или удаления строк сдвинутые ячейки тоже попадут сюда.
+    pub cells_overwritten: Vec<String>,
+}
+
+/// Что добавилось в styles.xml.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyleChanges {
+    /// Новые стили ячеек (`<xf>` в `<cellXfs>`).
+    pub cell_formats: u32,
+    pub fonts: u32,
+    pub fills: u32,
+    pub borders: u32,
+    /// Коды новых числовых форматов.
+    pub number_formats: Vec<String>,
+}
+
+/// Результат [`XlsxEditor::change_summary`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSummary {
+    /// Части в порядке исходного архива, затем новые.
+    pub parts: Vec<PartChange>,
+    /// Изменённые листы в порядке книги.
+    pub sheets: Vec<SheetChange>,
+    pub styles: StyleChanges,
+}
+
+impl ChangeSummary {
+    /// Книга не отличается от исходной.
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Сводка строками «изменение, что, подробности» — как в
+    /// [`XlsxEditor::write_audit_sheet`].
+    pub fn to_rows(&self) -> Vec<[String; 3]> {
+        let mut rows = Vec::new();
+        for p in &self.parts {
+            rows.push(["part".to_owned(), p.path.clone(), p.kind.to_string()]);
+        }
+        for s in &self.sheets {
+            if s.rows_appended > 0 {
+                rows.push([
+                    "rows appended".to_owned(),
+                    s.name.clone(),
+                    s.rows_appended.to_string(),
+                ]);
+            }
+            for cell in &s.cells_overwritten {
+                rows.push(["cell overwritten".to_owned(), s.name.clone(), cell.clone()]);
+            }
+        }
+        let st = &self.styles;
+        let counts = [
+            ("cell formats", st.cell_formats),
+            ("fonts", st.fonts),
+            ("fills", st.fills),
+            ("borders", st.borders),
+        ];
+        for (what, n) in counts.into_iter().filter(|&(_, n)| n > 0) {
+            rows.push(["styles created".to_owned(), what.to_owned(), n.to_string()]);
+        }
+        for code in &st.number_formats {
+            rows.push([
+                "number format created".to_owned(),
+                code.clone(),
+                String::new(),
+            ]);
+        }
+        rows
+    }
+}
+
+impl fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for [change, target, detail] in self.to_rows() {
+            match detail.is_empty() {
+                true => writeln!(f, "{change}: {target}")?,
+                false => writeln!(f, "{change}: {target} {detail}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl XlsxEditor {
+    /// Что изменилось в книге с открытия: части, которые save() запишет
+    /// иначе, дописанные строки и перезаписанные ячейки листов, новые стили.
+    /// Сводка считается по текущему состоянию: отменённые
+    /// [`Self::rollback`] правки в неё не попадают. Правки самого save()
+    /// (время изменения в docProps, `<dimension>`) сюда не входят.
+    ///
+    /// ```no_run
+    /// # use rust_core::XlsxEditor;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Sheet1")?;
+    /// xl.append_row(["a", "1"])?;
+    /// print!("{}", xl.change_summary()?); // part: xl/worksheets/sheet1.xml modified …
+    /// # Ok(()) }
+    /// ```
+    pub fn change_summary(&mut self) -> Result<ChangeSummary, XlsxError> {
+        self.flush_appended_ranges()?;
+        let mut zin = self.src.open()?;
+        let mut parts = Vec::new();
+        let mut in_source = HashSet::new();
+        for i in 0..zin.len() {
+            let name = zin.by_index_raw(i)?.name().to_owned();
+            in_source.insert(name.clone());
+            let kind = if self.removed_parts.contains(&name) {
+                Some(PartChangeKind::Removed)
+            } else {
+                self.pending_part(&name)
+                    .filter(|c| !same_as_entry(&mut zin, i, c))
+                    .map(|_| PartChangeKind::Modified)
+            };
+            if let Some(kind) = kind {
+                parts.push(PartChange { path: name, kind });
+            }
+        }
+        for (path, _) in &self.new_files {
+            if !in_source.contains(path) && !self.removed_parts.contains(path) {
+                parts.push(PartChange {
+                    path: path.clone(),
+                    kind: PartChangeKind::Added,
+                });
+            }
+        }
+
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let mut sheets = Vec::new();
+        for e in self.sheet_entries()? {
+            let changed = parts
+                .iter()
+                .any(|p| p.path == e.path && p.kind != PartChangeKind::Removed);
+            let rows_appended = self.appended_rows.get(&e.path).copied().unwrap_or(0);
+            if !changed && rows_appended == 0 {
+                continue;
+            }
+            let cells_overwritten =
+                match (self.pending_part(&e.path), read_entry(&mut zin, &e.path)?) {
+                    (Some(now), Some(was)) => overwritten_cells(&was, &now, sst)?,
+                    _ => Vec::new(),
+                };
+            sheets.push(SheetChange {
+                name: e.name,
+                path: e.path,
+                rows_appended,
+                cells_overwritten,
+            });
+        }
+
+        let styles = match (self.styles_xml.loaded(), read_entry(&mut zin, STYLES)?) {
+            (Some(now), Some(was)) => {
+                let (was, now) = (style_counts(&was)?, style_counts(now)?);
+                let diff = |k: usize| now.0[k].saturating_sub(was.0[k]);
+                StyleChanges {
+                    cell_formats: diff(0),
+                    fonts: diff(1),
+                    fills: diff(2),
+                    borders: diff(3),
+                    number_formats: now.1.into_iter().filter(|c| !was.1.contains(c)).collect(),
+                }
+            }
+            _ => StyleChanges::default(),
+        };
+        Ok(ChangeSummary {
+            parts,
+            sheets,
+            styles,
+        })
+    }
+
+    /// Дописывает [`Self::change_summary`] в лист `name` (столбцы Change,
+    /// Target, Detail — см. [`ChangeSummary::to_rows`]) и скрывает его. Нет
+    /// такого листа — он заводится с заголовком, есть — строки идут под
+    /// прежними, так что лист копит историю запусков. Сам лист аудита в
+    /// сводку не входит; текущим остаётся прежний лист.
+    pub fn write_audit_sheet(&mut self, name: &str) -> Result<&mut Self, XlsxError> {
+        let mut summary = self.change_summary()?;
+        let audit = self.sheet_entries()?.into_iter().find(|e| e.name == name);
+        if let Some(a) = &audit {
+            summary.parts.retain(|p| p.path != a.path);
+            summary.sheets.retain(|s| s.path != a.path);
+        }
+        let current = self.current_sheet_name()?;
+        if audit.is_some() {
+            self.with_worksheet(name)?;
+        } else {
+            self.add_worksheet(name)?;
+            self.append_row(["Change", "Target", "Detail"])?;
+        }
+        self.append_table(summary.to_rows())?;
+        self.hide_worksheet(name, SheetVisibility::Hidden)?;
+        if current != name {
+            self.with_worksheet(&current)?;
+        }
+        Ok(self)
+    }
+
+    /// Содержимое части, которое save() сравнит с исходным (как в
+    /// `write_package`); `None` — часть не читали и не меняли.
+    fn pending_part(&self, path: &str) -> Option<Cow<'_, [u8]>> {
+        if path == self.sheet_path {
+            return Some(self.current_sheet_xml());
+        }
+        if let Some((_, c)) = self.new_files.iter().find(|(p, _)| p == path) {
+            return Some(Cow::Borrowed(c));
+        }
+        match path {
+            WORKBOOK => self.workbook_xml.loaded(),
+            WORKBOOK_RELS => self.rels_xml.loaded(),
+            STYLES => self.styles_xml.loaded(),
+            _ => None,
+        }
+        .map(|x| Cow::Borrowed(&x[..]))
+    }
+}
+
+/// Часть `path` исходного архива; `None` — её там нет.
+fn read_entry<R: Read + std::io::Seek>(
+    zin: &mut zip::ZipArchive<R>,
+    path: &str,
+) -> Result<Option<Vec<u8>>> {
+    let Ok(mut f) = zin.by_name(path) else {
+        return Ok(None);
+    };
+    let mut buf = Vec::with_capacity(f.size() as usize);
+    f.read_to_end(&mut buf)?;
+    Ok(Some(buf))
+}
+
+/// Непустые ячейки `was`, у которых в `now` по тому же адресу другие
+/// значение или формула; значения сравниваются как текст ячейки, так что
+/// строка из sharedStrings и та же `inlineStr` — одно и то же.
+fn overwritten_cells(was: &[u8], now: &[u8], sst: &[String]) -> Result<Vec<String>> {
+    let key = |c: &RawCell| (c.f.clone(), c.display_text(sst));
+    let now: HashMap<(u32, u32), _> = read_cells(now)?
+        .iter()
+        .map(|c| ((c.row, c.col), key(c)))
+        .collect();
+    let mut out = Vec::new();
+    for c in read_cells(was)? {
+        let before = key(&c);
+        if before != (None, None) && now.get(&(c.row, c.col)) != Some(&before) {
+            out.push(format!("{}{}", col_letter(c.col), c.row));
+        }
+    }
+    Ok(out)
+}
+
+/// Число `<xf>` в `<cellXfs>`, шрифтов, заливок и границ styles.xml, и коды
+/// числовых форматов.
+fn style_counts(xml: &[u8]) -> Result<([u32; 4], Vec<String>)> {
+    const SECTIONS: [(&[u8], &[u8]); 4] = [
+        (b"cellXfs", b"xf"),
+        (b"fonts", b"font"),
+        (b"fills", b"fill"),
+        (b"borders", b"border"),
+    ];
+    let mut rdr = Reader::from_reader(xml);
+    let mut counts = [0; 4];
+    let mut codes = Vec::new();
+    let mut section: Option<usize> = None;
+    loop {
+        let ev = next_event(&mut rdr, STYLES)?;
+        match ev {
+            Event::End(ref e)
+                if section.is_some_and(|k| SECTIONS[k].0 == e.local_name().as_ref()) =>
+            {
+                section = None;
+            }
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let name = e.local_name();
+                let name = name.as_ref();
+                match section {
+                    Some(k) if SECTIONS[k].1 == name => counts[k] += 1,
+                    Some(_) => {}
+                    None if name == b"numFmt" => {
+                        for a in e.attributes().with_checks(false).flatten() {
+                            if a.key.local_name().as_ref() == b"formatCode" {
+                                codes.push(a.unescape_value()?.into_owned());
+                            }
+                        }
+                    }
+                    None => {
+                        if matches!(ev, Event::Start(_)) {
+                            section = SECTIONS.iter().position(|(s, _)| *s == name);
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((counts, codes))
+}
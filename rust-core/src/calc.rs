@@ -0,0 +1,861 @@
+//! calc.rs – вычисление практического подмножества формул по данным текущего
+//! листа, чтобы записать в ячейки кэшированные `<v>` (фича `calc`).
+//!
+//! Поддержаны числа, строки, логические значения, ссылки и диапазоны текущего
+//! листа (включая `A:A` и `1:1`), операторы `+ - * / ^ & %` и сравнения,
+//! функции SUM, AVERAGE, COUNT, COUNTA, MIN, MAX, IF, IFERROR, AND, OR, NOT,
+//! ROUND, ABS, VLOOKUP, CONCATENATE/CONCAT. Формулы с чем-то другим (ссылки на
+//! другие листы, имена, прочие функции, массивы) и зависящие от них не трогаются.
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::files_part::xml_escape;
+use crate::formula::{RefPart, adjust_formula, parse_part};
+use crate::read_part::{RawCell, read_cells, split_ref};
+use crate::sheet_xml::{find_child, find_elem, get_attr, set_attr, tag_prefix};
+use anyhow::Result;
+use quick_xml::escape::unescape;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// Ячейка: (столбец 0-based, строка 1-based).
+type Pos = (u32, u32);
+/// Прямоугольник `(c0, r0, c1, r1)`.
+type Rect = (u32, u32, u32, u32);
+
+/// Предел вложенности при вычислении ссылок на другие формулы (защита стека).
+const MAX_DEPTH: usize = 256;
+
+const ERRORS: [&str; 7] = [
+    "#DIV/0!", "#N/A", "#NAME?", "#NULL!", "#NUM!", "#REF!", "#VALUE!",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Empty,
+    Num(f64),
+    Text(String),
+    Bool(bool),
+}
+
+/// Почему значение не получено.
+#[derive(Debug, Clone)]
+enum Fail {
+    /// ошибка Excel (`#DIV/0!`, `#N/A`…) — это тоже результат формулы
+    Xl(&'static str),
+    /// формулу вычислить нельзя: она остаётся с прежним `<v>`
+    Unsupported,
+}
+
+type Eval<T> = std::result::Result<T, Fail>;
+/// Результат формулы для записи: значение или код ошибки Excel.
+type Outcome = std::result::Result<Value, &'static str>;
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Lit(Value),
+    Error(&'static str),
+    Area(Rect),
+    Neg(Box<Expr>),
+    Percent(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+/// Результат подвыражения: значение или диапазон (для функций).
+enum Val {
+    One(Value),
+    Area(Rect),
+}
+
+impl XlsxEditor {
+    /// Вычисляет формулы текущего листа и записывает результаты в их `<v>`
+    /// (`t="str"`/`"b"`/`"e"` для строк, логических значений и ошибок), чтобы
+    /// файл показывал верные значения и в программах, которые не пересчитывают
+    /// формулы. Неподдержанные формулы сохраняют прежнее значение.
+    pub fn evaluate_formulas(&mut self) -> Result<&mut Self, XlsxError> {
+        let Some(sd) = find_child(&self.sheet_xml, "sheetData")? else {
+            return Ok(self);
+        };
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let data = &self.sheet_xml[sd.clone()];
+
+        let formulas = collect_formulas(data)?;
+        if formulas.is_empty() {
+            return Ok(self);
+        }
+        let cells: HashMap<Pos, RawCell> = read_cells(data)?
+            .into_iter()
+            .map(|c| ((c.col, c.row), c))
+            .collect();
+        let bounds = cells
+            .keys()
+            .fold((0, 1), |(mc, mr), &(c, r)| (mc.max(c), mr.max(r)));
+
+        let mut order: Vec<Pos> = formulas.keys().copied().collect();
+        order.sort_by_key(|&(c, r)| (r, c));
+        let mut ctx = Sheet {
+            cells: &cells,
+            formulas: &formulas,
+            sst,
+            bounds,
+            memo: HashMap::new(),
+            active: HashSet::new(),
+        };
+        let mut results = HashMap::new();
+        for pos in order {
+            match ctx.cell(pos, 0) {
+                Ok(v) => results.insert(pos, Ok(v)),
+                Err(Fail::Xl(e)) => results.insert(pos, Err(e)),
+                Err(Fail::Unsupported) => None,
+            };
+        }
+
+        let data = write_values(data, &results);
+        self.sheet_xml.splice(sd, data);
+        Ok(self)
+    }
+}
+
+/* ============================ РАЗБОР ЛИСТА ================================ */
+
+/// Формулы ячеек (без `=`); у зависимых ячеек общей формулы (`t="shared"`)
+/// текст восстанавливается из главной. Формулы массивов пропускаются.
+fn collect_formulas(data: &[u8]) -> Result<HashMap<Pos, String>> {
+    let p = tag_prefix(data);
+    let (qc, qf) = (format!("{p}c"), format!("{p}f"));
+    let mut out = HashMap::new();
+    let mut masters: HashMap<String, (Pos, String)> = HashMap::new();
+    let mut shared: Vec<(Pos, String)> = Vec::new();
+
+    let mut from = 0;
+    while let Some(c) = find_elem(data, &qc, from) {
+        from = c.end;
+        let cell = &data[c];
+        let Some(f) = find_elem(cell, &qf, 0) else {
+            continue;
+        };
+        let Some(r) = get_attr(cell, "r") else {
+            continue;
+        };
+        let pos = split_ref(r.as_bytes());
+        let f = &cell[f];
+        let gt = f.iter().position(|&b| b == b'>').unwrap_or(0);
+        let text = if f[gt - 1] == b'/' {
+            String::new()
+        } else {
+            let inner = &f[gt + 1..f.len() - qf.len() - 3];
+            unescape(&String::from_utf8_lossy(inner))?.into_owned()
+        };
+        match get_attr(&f[..=gt], "t").as_deref() {
+            Some("shared") => {
+                let si = get_attr(&f[..=gt], "si").unwrap_or_default();
+                if text.is_empty() {
+                    shared.push((pos, si));
+                } else {
+                    masters.insert(si, (pos, text.clone()));
+                    out.insert(pos, text);
+                }
+            }
+            Some(_) => {}
+            None if !text.is_empty() => {
+                out.insert(pos, text);
+            }
+            None => {}
+        }
+    }
+    for (pos, si) in shared {
+        if let Some(((mc, mr), text)) = masters.get(&si) {
+            let (dc, dr) = (pos.0 as i64 - *mc as i64, pos.1 as i64 - *mr as i64);
+            out.insert(pos, adjust_formula(text, dr, dc));
+        }
+    }
+    Ok(out)
+}
+
+/// Переписывает `<v>` вычисленных ячеек; формула и прочие атрибуты сохраняются.
+fn write_values(data: &[u8], results: &HashMap<Pos, Outcome>) -> Vec<u8> {
+    let p = tag_prefix(data);
+    let (qc, qf) = (format!("{p}c"), format!("{p}f"));
+    let mut out = Vec::with_capacity(data.len());
+    let mut from = 0;
+    while let Some(c) = find_elem(data, &qc, from) {
+        let cell = &data[c.clone()];
+        let res = get_attr(cell, "r").and_then(|r| results.get(&split_ref(r.as_bytes())));
+        let (Some(res), Some(f)) = (res, find_elem(cell, &qf, 0)) else {
+            out.extend_from_slice(&data[from..c.end]);
+            from = c.end;
+            continue;
+        };
+        out.extend_from_slice(&data[from..c.start]);
+        let gt = cell.iter().position(|&b| b == b'>').unwrap_or(0);
+        let mut tag = cell[..=gt].to_vec();
+        let (t, v) = match res {
+            Ok(Value::Num(n)) => (None, fmt_num(*n)),
+            Ok(Value::Empty) => (None, "0".to_owned()),
+            Ok(Value::Text(s)) => (Some("str"), xml_escape(s)),
+            Ok(Value::Bool(b)) => (Some("b"), u8::from(*b).to_string()),
+            Err(e) => (Some("e"), xml_escape(e)),
+        };
+        set_attr(&mut tag, "t", t);
+        out.extend_from_slice(&tag);
+        out.extend_from_slice(&cell[f]);
+        out.extend_from_slice(format!("<{p}v>{v}</{p}v></{p}c>").as_bytes());
+        from = c.end;
+    }
+    out.extend_from_slice(&data[from..]);
+    out
+}
+
+/* ============================== ВЫЧИСЛЕНИЕ ================================ */
+
+struct Sheet<'a> {
+    cells: &'a HashMap<Pos, RawCell>,
+    formulas: &'a HashMap<Pos, String>,
+    sst: &'a [String],
+    /// наибольшие столбец и строка с данными — границы для `A:A` и `1:1`
+    bounds: Pos,
+    /// вычисленные формулы; `None` — вычислить не удалось
+    memo: HashMap<Pos, Option<Outcome>>,
+    /// формулы в процессе вычисления (циклические ссылки)
+    active: HashSet<Pos>,
+}
+
+impl Sheet<'_> {
+    /// Значение ячейки: формулы вычисляются, остальное берётся как есть.
+    fn cell(&mut self, pos: Pos, depth: usize) -> Eval<Value> {
+        let Some(formula) = self.formulas.get(&pos) else {
+            return self.stored(pos);
+        };
+        if let Some(done) = self.memo.get(&pos) {
+            return match done {
+                Some(Ok(v)) => Ok(v.clone()),
+                Some(Err(e)) => Err(Fail::Xl(e)),
+                None => Err(Fail::Unsupported),
+            };
+        }
+        if depth > MAX_DEPTH || !self.active.insert(pos) {
+            return Err(Fail::Unsupported);
+        }
+        let res = Parser::parse(formula, self.bounds)
+            .ok_or(Fail::Unsupported)
+            .and_then(|e| self.scalar(&e, depth + 1))
+            .map(|v| {
+                if v == Value::Empty {
+                    Value::Num(0.0)
+                } else {
+                    v
+                }
+            });
+        self.active.remove(&pos);
+        let saved = match &res {
+            Ok(v) => Some(Ok(v.clone())),
+            Err(Fail::Xl(e)) => Some(Err(*e)),
+            Err(Fail::Unsupported) => None,
+        };
+        self.memo.insert(pos, saved);
+        res
+    }
+
+    /// Значение ячейки без формулы (или кэш формулы, которую не вычисляем).
+    fn stored(&self, pos: Pos) -> Eval<Value> {
+        let Some(c) = self.cells.get(&pos) else {
+            return Ok(Value::Empty);
+        };
+        let v = c.v.as_deref();
+        Ok(match c.t.as_deref() {
+            Some("e") => {
+                let code = v.unwrap_or_default();
+                return Err(Fail::Xl(
+                    ERRORS.iter().find(|e| **e == code).unwrap_or(&"#VALUE!"),
+                ));
+            }
+            Some("b") => Value::Bool(v == Some("1")),
+            Some("s" | "inlineStr" | "str") => {
+                Value::Text(c.display_text(self.sst).unwrap_or_default())
+            }
+            _ => match v.map(|v| v.trim().parse::<f64>()) {
+                Some(Ok(n)) => Value::Num(n),
+                Some(Err(_)) => Value::Text(v.unwrap_or_default().to_owned()),
+                None => Value::Empty,
+            },
+        })
+    }
+
+    fn eval(&mut self, e: &Expr, d: usize) -> Eval<Val> {
+        Ok(match e {
+            Expr::Area(r) => Val::Area(*r),
+            Expr::Call(name, args) => Val::One(self.call(name, args, d)?),
+            _ => Val::One(self.scalar(e, d)?),
+        })
+    }
+
+    /// Значение выражения; диапазон из одной ячейки даёт её значение.
+    fn scalar(&mut self, e: &Expr, d: usize) -> Eval<Value> {
+        match e {
+            Expr::Lit(v) => Ok(v.clone()),
+            Expr::Error(code) => Err(Fail::Xl(code)),
+            Expr::Area((c0, r0, c1, r1)) => {
+                if c0 == c1 && r0 == r1 {
+                    self.cell((*c0, *r0), d)
+                } else {
+                    Err(Fail::Xl("#VALUE!"))
+                }
+            }
+            Expr::Neg(x) => Ok(Value::Num(-num(self.scalar(x, d)?)?)),
+            Expr::Percent(x) => Ok(Value::Num(num(self.scalar(x, d)?)? / 100.0)),
+            Expr::Bin(op, a, b) => {
+                let a = self.scalar(a, d)?;
+                let b = self.scalar(b, d)?;
+                binary(*op, a, b)
+            }
+            Expr::Call(name, args) => self.call(name, args, d),
+        }
+    }
+
+    /// Значения всех аргументов: ячейки диапазонов и отдельные значения
+    /// (второе поле `true` — значение задано напрямую, а не диапазоном).
+    fn values(&mut self, args: &[Expr], d: usize) -> Eval<Vec<(Eval<Value>, bool)>> {
+        let mut out = Vec::new();
+        for a in args {
+            match self.eval(a, d)? {
+                Val::One(v) => out.push((Ok(v), true)),
+                Val::Area((c0, r0, c1, r1)) => {
+                    for r in r0..=r1 {
+                        for c in c0..=c1 {
+                            match self.cell((c, r), d) {
+                                Err(Fail::Unsupported) => return Err(Fail::Unsupported),
+                                v => out.push((v, false)),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Числа аргументов как у SUM: в диапазонах только числа, прямые значения
+    /// приводятся к числу; ошибки передаются дальше.
+    fn numbers(&mut self, args: &[Expr], d: usize) -> Eval<Vec<f64>> {
+        let mut out = Vec::new();
+        for (v, direct) in self.values(args, d)? {
+            match v? {
+                Value::Num(n) => out.push(n),
+                Value::Empty => {}
+                v if direct => out.push(num(v)?),
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], d: usize) -> Eval<Value> {
+        let argc = |lo: usize, hi: usize| {
+            if (lo..=hi).contains(&args.len()) {
+                Ok(())
+            } else {
+                Err(Fail::Unsupported)
+            }
+        };
+        match name {
+            "SUM" => Ok(Value::Num(self.numbers(args, d)?.iter().sum())),
+            "AVERAGE" => {
+                let n = self.numbers(args, d)?;
+                if n.is_empty() {
+                    return Err(Fail::Xl("#DIV/0!"));
+                }
+                Ok(Value::Num(n.iter().sum::<f64>() / n.len() as f64))
+            }
+            "MIN" | "MAX" => {
+                let n = self.numbers(args, d)?;
+                let pick = if name == "MIN" { f64::min } else { f64::max };
+                Ok(Value::Num(n.into_iter().reduce(pick).unwrap_or(0.0)))
+            }
+            "COUNT" => {
+                let n = self
+                    .values(args, d)?
+                    .into_iter()
+                    .filter(|(v, direct)| match v {
+                        Ok(Value::Num(_)) => true,
+                        Ok(v @ (Value::Text(_) | Value::Bool(_))) if *direct => {
+                            num(v.clone()).is_ok()
+                        }
+                        _ => false,
+                    });
+                Ok(Value::Num(n.count() as f64))
+            }
+            "COUNTA" => {
+                let n = self.values(args, d)?;
+                Ok(Value::Num(
+                    n.iter()
+                        .filter(|(v, _)| !matches!(v, Ok(Value::Empty)))
+                        .count() as f64,
+                ))
+            }
+            "IF" => {
+                argc(2, 3)?;
+                if truth(self.scalar(&args[0], d)?)? {
+                    self.scalar(&args[1], d)
+                } else {
+                    args.get(2)
+                        .map_or(Ok(Value::Bool(false)), |e| self.scalar(e, d))
+                }
+            }
+            "IFERROR" => {
+                argc(2, 2)?;
+                match self.scalar(&args[0], d) {
+                    Err(Fail::Xl(_)) => self.scalar(&args[1], d),
+                    other => other,
+                }
+            }
+            "AND" | "OR" => {
+                let mut flags = Vec::new();
+                for (v, direct) in self.values(args, d)? {
+                    match v? {
+                        v @ (Value::Num(_) | Value::Bool(_)) => flags.push(truth(v)?),
+                        v @ Value::Text(_) if direct => flags.push(truth(v)?),
+                        _ => {}
+                    }
+                }
+                if flags.is_empty() {
+                    return Err(Fail::Xl("#VALUE!"));
+                }
+                Ok(Value::Bool(if name == "AND" {
+                    flags.iter().all(|f| *f)
+                } else {
+                    flags.iter().any(|f| *f)
+                }))
+            }
+            "NOT" => {
+                argc(1, 1)?;
+                Ok(Value::Bool(!truth(self.scalar(&args[0], d)?)?))
+            }
+            "ABS" => {
+                argc(1, 1)?;
+                Ok(Value::Num(num(self.scalar(&args[0], d)?)?.abs()))
+            }
+            "ROUND" => {
+                argc(2, 2)?;
+                let x = num(self.scalar(&args[0], d)?)?;
+                let k = 10f64.powi(num(self.scalar(&args[1], d)?)?.trunc() as i32);
+                // f64::round округляет половину от нуля, как Excel
+                Ok(Value::Num((x * k).round() / k))
+            }
+            "CONCATENATE" | "CONCAT" => {
+                let mut s = String::new();
+                for (v, _) in self.values(args, d)? {
+                    s.push_str(&text(&v?));
+                }
+                Ok(Value::Text(s))
+            }
+            "VLOOKUP" => {
+                argc(3, 4)?;
+                self.vlookup(args, d)
+            }
+            _ => Err(Fail::Unsupported),
+        }
+    }
+
+    fn vlookup(&mut self, args: &[Expr], d: usize) -> Eval<Value> {
+        let key = self.scalar(&args[0], d)?;
+        let Expr::Area((c0, r0, c1, r1)) = args[1] else {
+            return Err(Fail::Unsupported);
+        };
+        let col = num(self.scalar(&args[2], d)?)?.trunc();
+        let approx = match args.get(3) {
+            Some(e) => truth(self.scalar(e, d)?)?,
+            None => true,
+        };
+        if col < 1.0 {
+            return Err(Fail::Xl("#VALUE!"));
+        }
+        if col > f64::from(c1 - c0 + 1) {
+            return Err(Fail::Xl("#REF!"));
+        }
+        if let Value::Text(t) = &key
+            && !approx
+            && t.contains(['*', '?', '~'])
+        {
+            return Err(Fail::Unsupported);
+        }
+
+        let mut found = None;
+        for r in r0..=r1 {
+            let v = match self.cell((c0, r), d) {
+                Ok(v) => v,
+                Err(Fail::Xl(_)) => continue,
+                Err(Fail::Unsupported) => return Err(Fail::Unsupported),
+            };
+            if v == Value::Empty || rank(&v) != rank(&key) {
+                continue;
+            }
+            match compare(&v, &key) {
+                Ordering::Equal => {
+                    found = Some(r);
+                    if !approx {
+                        break;
+                    }
+                }
+                Ordering::Less if approx => found = Some(r),
+                Ordering::Greater if approx => break,
+                _ => {}
+            }
+        }
+        match found {
+            Some(r) => self.cell((c0 + col as u32 - 1, r), d),
+            None => Err(Fail::Xl("#N/A")),
+        }
+    }
+}
+
+fn binary(op: Op, a: Value, b: Value) -> Eval<Value> {
+    let arith = |f: fn(f64, f64) -> f64| -> Eval<Value> {
+        let n = f(num(a.clone())?, num(b.clone())?);
+        if n.is_finite() {
+            Ok(Value::Num(n))
+        } else {
+            Err(Fail::Xl("#NUM!"))
+        }
+    };
+    match op {
+        Op::Add => arith(|x, y| x + y),
+        Op::Sub => arith(|x, y| x - y),
+        Op::Mul => arith(|x, y| x * y),
+        Op::Div => {
+            if num(b.clone())? == 0.0 {
+                num(a)?;
+                return Err(Fail::Xl("#DIV/0!"));
+            }
+            arith(|x, y| x / y)
+        }
+        Op::Pow => arith(f64::powf),
+        Op::Concat => Ok(Value::Text(text(&a) + &text(&b))),
+        _ => {
+            let o = compare(&a, &b);
+            Ok(Value::Bool(match op {
+                Op::Eq => o == Ordering::Equal,
+                Op::Ne => o != Ordering::Equal,
+                Op::Lt => o == Ordering::Less,
+                Op::Le => o != Ordering::Greater,
+                Op::Gt => o == Ordering::Greater,
+                _ => o != Ordering::Less,
+            }))
+        }
+    }
+}
+
+fn num(v: Value) -> Eval<f64> {
+    match v {
+        Value::Num(n) => Ok(n),
+        Value::Bool(b) => Ok(f64::from(u8::from(b))),
+        Value::Empty => Ok(0.0),
+        Value::Text(s) => s.trim().parse().map_err(|_| Fail::Xl("#VALUE!")),
+    }
+}
+
+fn truth(v: Value) -> Eval<bool> {
+    match v {
+        Value::Bool(b) => Ok(b),
+        Value::Num(n) => Ok(n != 0.0),
+        Value::Empty => Ok(false),
+        Value::Text(s) if s.eq_ignore_ascii_case("TRUE") => Ok(true),
+        Value::Text(s) if s.eq_ignore_ascii_case("FALSE") => Ok(false),
+        Value::Text(_) => Err(Fail::Xl("#VALUE!")),
+    }
+}
+
+fn text(v: &Value) -> String {
+    match v {
+        Value::Empty => String::new(),
+        Value::Num(n) => fmt_num(*n),
+        Value::Text(s) => s.clone(),
+        Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_owned(),
+    }
+}
+
+/// Число так, как его пишет Excel в `<v>`: без `.0` у целых и без `-0`.
+fn fmt_num(n: f64) -> String {
+    (n + 0.0).to_string()
+}
+
+/// Порядок типов при сравнении в Excel: числа < строки < логические.
+fn rank(v: &Value) -> u8 {
+    match v {
+        Value::Empty | Value::Num(_) => 0,
+        Value::Text(_) => 1,
+        Value::Bool(_) => 2,
+    }
+}
+
+/// Сравнение как в Excel: пустая ячейка равна 0, "" или FALSE, строки без учёта регистра.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    let blank = |other: &Value| match other {
+        Value::Text(_) => Value::Text(String::new()),
+        Value::Bool(_) => Value::Bool(false),
+        _ => Value::Num(0.0),
+    };
+    match (a, b) {
+        (Value::Empty, Value::Empty) => Ordering::Equal,
+        (Value::Empty, _) => compare(&blank(b), b),
+        (_, Value::Empty) => compare(a, &blank(a)),
+        (Value::Num(x), Value::Num(y)) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+        (Value::Text(x), Value::Text(y)) => x.to_lowercase().cmp(&y.to_lowercase()),
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/* ================================= РАЗБОР ================================= */
+
+/// Разбор формулы в дерево; `None` — синтаксис вне поддержанного подмножества.
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+    bounds: Pos,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(formula: &'a str, bounds: Pos) -> Option<Expr> {
+        let mut p = Parser {
+            s: formula.as_bytes(),
+            i: 0,
+            bounds,
+        };
+        let e = p.expr(0)?;
+        (p.peek().is_none()).then_some(e)
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while self.s.get(self.i).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.i += 1;
+        }
+        self.s.get(self.i).copied()
+    }
+
+    /// Бинарный оператор в текущей позиции: (оператор, приоритет, длина).
+    fn binop(&mut self) -> Option<(Op, u8, usize)> {
+        let two = self.s.get(self.i..self.i + 2);
+        Some(match (self.peek()?, two) {
+            (_, Some(b"<>")) => (Op::Ne, 1, 2),
+            (_, Some(b"<=")) => (Op::Le, 1, 2),
+            (_, Some(b">=")) => (Op::Ge, 1, 2),
+            (b'=', _) => (Op::Eq, 1, 1),
+            (b'<', _) => (Op::Lt, 1, 1),
+            (b'>', _) => (Op::Gt, 1, 1),
+            (b'&', _) => (Op::Concat, 2, 1),
+            (b'+', _) => (Op::Add, 3, 1),
+            (b'-', _) => (Op::Sub, 3, 1),
+            (b'*', _) => (Op::Mul, 4, 1),
+            (b'/', _) => (Op::Div, 4, 1),
+            (b'^', _) => (Op::Pow, 5, 1),
+            _ => return None,
+        })
+    }
+
+    /// Выражение с операторами приоритета не ниже `min` (все левоассоциативны).
+    fn expr(&mut self, min: u8) -> Option<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some((op, prec, len)) = self.binop() {
+            if prec < min {
+                break;
+            }
+            self.i += len;
+            let rhs = self.expr(prec + 1)?;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    /// Унарные `-`/`+` (в Excel связывают сильнее `^`) и постфиксный `%`.
+    fn unary(&mut self) -> Option<Expr> {
+        match self.peek()? {
+            b'-' => {
+                self.i += 1;
+                Some(Expr::Neg(Box::new(self.unary()?)))
+            }
+            b'+' => {
+                self.i += 1;
+                self.unary()
+            }
+            _ => {
+                let mut e = self.primary()?;
+                while self.peek() == Some(b'%') {
+                    self.i += 1;
+                    e = Expr::Percent(Box::new(e));
+                }
+                Some(e)
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Option<Expr> {
+        if let Some(area) = self.area() {
+            return Some(Expr::Area(area));
+        }
+        match self.peek()? {
+            b'(' => {
+                self.i += 1;
+                let e = self.expr(0)?;
+                (self.peek() == Some(b')')).then(|| self.i += 1)?;
+                Some(e)
+            }
+            b'"' => self.string(),
+            b'#' => {
+                let rest = &self.s[self.i..];
+                let code = ERRORS.iter().find(|e| rest.starts_with(e.as_bytes()))?;
+                self.i += code.len();
+                Some(Expr::Error(code))
+            }
+            b'0'..=b'9' | b'.' => self.number(),
+            b if b.is_ascii_alphabetic() || b == b'_' => self.word(),
+            _ => None,
+        }
+    }
+
+    /// Ссылка или диапазон текущего листа: `A1`, `$A$1:B2`, `A:C`, `2:5`.
+    fn area(&mut self) -> Option<Rect> {
+        self.peek()?;
+        let (a, mut j) = parse_part(self.s, self.i)?;
+        let b = if self.s.get(j) == Some(&b':') {
+            let (b, k) = parse_part(self.s, j + 1)?;
+            j = k;
+            Some(b)
+        } else {
+            None
+        };
+        // имя функции, листа или определённое имя, а не ссылка
+        if self
+            .s
+            .get(j)
+            .is_some_and(|&c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'.' | b'(' | b'!'))
+        {
+            return None;
+        }
+        let (mc, mr) = self.bounds;
+        let rect = |a: RefPart, b: RefPart| -> Option<Rect> {
+            match (a.col, a.row, b.col, b.row) {
+                (Some((c0, _)), Some((r0, _)), Some((c1, _)), Some((r1, _))) => {
+                    Some((c0.min(c1), r0.min(r1), c0.max(c1), r0.max(r1)))
+                }
+                (Some((c0, _)), None, Some((c1, _)), None) => Some((c0.min(c1), 1, c0.max(c1), mr)),
+                (None, Some((r0, _)), None, Some((r1, _))) => Some((0, r0.min(r1), mc, r0.max(r1))),
+                _ => None,
+            }
+        };
+        let r = match b {
+            Some(b) => rect(a, b)?,
+            None if a.col.is_some() && a.row.is_some() => rect(a, a)?,
+            None => return None,
+        };
+        self.i = j;
+        Some(r)
+    }
+
+    fn string(&mut self) -> Option<Expr> {
+        let mut out = Vec::new();
+        let mut j = self.i + 1;
+        loop {
+            match *self.s.get(j)? {
+                b'"' if self.s.get(j + 1) == Some(&b'"') => {
+                    out.push(b'"');
+                    j += 2;
+                }
+                b'"' => break,
+                b => {
+                    out.push(b);
+                    j += 1;
+                }
+            }
+        }
+        self.i = j + 1;
+        Some(Expr::Lit(Value::Text(String::from_utf8(out).ok()?)))
+    }
+
+    fn number(&mut self) -> Option<Expr> {
+        let start = self.i;
+        let mut j = self.i;
+        while self
+            .s
+            .get(j)
+            .is_some_and(|b| b.is_ascii_digit() || *b == b'.')
+        {
+            j += 1;
+        }
+        if self.s.get(j).is_some_and(|b| matches!(b, b'e' | b'E')) {
+            j += 1;
+            if self.s.get(j).is_some_and(|b| matches!(b, b'+' | b'-')) {
+                j += 1;
+            }
+            while self.s.get(j).is_some_and(|b| b.is_ascii_digit()) {
+                j += 1;
+            }
+        }
+        let n = std::str::from_utf8(&self.s[start..j]).ok()?.parse().ok()?;
+        self.i = j;
+        Some(Expr::Lit(Value::Num(n)))
+    }
+
+    /// `TRUE`/`FALSE` или вызов функции; прочие имена не поддержаны.
+    fn word(&mut self) -> Option<Expr> {
+        let start = self.i;
+        while self
+            .s
+            .get(self.i)
+            .is_some_and(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.'))
+        {
+            self.i += 1;
+        }
+        let word = std::str::from_utf8(&self.s[start..self.i])
+            .ok()?
+            .to_ascii_uppercase();
+        if self.s.get(self.i) != Some(&b'(') {
+            return match word.as_str() {
+                "TRUE" => Some(Expr::Lit(Value::Bool(true))),
+                "FALSE" => Some(Expr::Lit(Value::Bool(false))),
+                _ => None,
+            };
+        }
+        self.i += 1;
+        let mut args = Vec::new();
+        if self.peek() == Some(b')') {
+            self.i += 1;
+        } else {
+            loop {
+                // пропущенный аргумент: IF(A1,,1)
+                if matches!(self.peek(), Some(b',' | b')')) {
+                    args.push(Expr::Lit(Value::Empty));
+                } else {
+                    args.push(self.expr(0)?);
+                }
+                match self.peek()? {
+                    b',' => self.i += 1,
+                    b')' => {
+                        self.i += 1;
+                        break;
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        let name = word.strip_prefix("_XLFN.").unwrap_or(&word).to_owned();
+        Some(Expr::Call(name, args))
+    }
+}
@@ -0,0 +1,81 @@
+//! calc_part.rs – пересчёт формул при открытии книги: `<calcPr fullCalcOnLoad="1"/>`
+//! и удаление calcChain.xml, когда правки затронули листы с формулами.
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::sheet_xml::{find_wb_child, insert_wb_child, root_prefix, set_attr};
+use anyhow::Result;
+use memchr::memmem;
+use std::borrow::Cow;
+
+impl XlsxEditor {
+    /// Включает (`true`) автоматический пересчёт: если к моменту save() значения
+    /// на листах изменились, а в книге есть формулы, calcChain.xml удаляется и
+    /// в `<calcPr>` ставится `fullCalcOnLoad="1"` — Excel пересчитает формулы
+    /// при открытии вместо показа устаревших кэшированных `<v>`.
+    pub fn set_recalc_on_change(&mut self, enabled: bool) -> &mut Self {
+        self.recalc_on_change = enabled;
+        self
+    }
+
+    /// Сразу помечает книгу к полному пересчёту при открытии (без проверки правок).
+    pub fn set_full_calc_on_load(&mut self) -> Result<&mut Self, XlsxError> {
+        self.drop_calc_chain()?;
+        let xml = self.workbook_xml.get_mut(&self.src)?;
+        match find_wb_child(xml, "calcPr")? {
+            Some(r) => {
+                let end = r.start + xml[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+                let mut tag = xml[r.start..=end].to_vec();
+                set_attr(&mut tag, "fullCalcOnLoad", Some("1"));
+                xml.splice(r.start..=end, tag);
+            }
+            None => {
+                let tag = format!(r#"<{}calcPr fullCalcOnLoad="1"/>"#, root_prefix(xml));
+                insert_wb_child(xml, "calcPr", tag.as_bytes())?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Для save(): включает пересчёт, если изменился хотя бы один лист,
+    /// а формулы в книге есть (calcChain.xml или `<f>` на изменённых листах).
+    pub(crate) fn recalc_if_values_changed(&mut self) -> Result<()> {
+        let (mut changed, mut formulas) = (false, false);
+        for entry in self.sheet_entries()? {
+            let current = if entry.path == self.sheet_path {
+                self.current_sheet_xml()
+            } else if let Some((_, xml)) = self.new_files.iter().find(|(p, _)| *p == entry.path) {
+                Cow::Borrowed(xml.as_slice())
+            } else {
+                continue;
+            };
+            // новые листы (копии, добавленные) не меняют значений под старыми формулами
+            if self.same_as_source(&entry.path, &current)? == Some(false) {
+                changed = true;
+                formulas |= has_formulas(&current);
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+
+        let has_chain = self
+            .rels_of("xl/workbook.xml")?
+            .of_kind("calcChain")
+            .next()
+            .is_some();
+        if !has_chain && !formulas {
+            return Ok(());
+        }
+        self.set_full_calc_on_load()?;
+        Ok(())
+    }
+}
+
+/// Есть ли на листе хотя бы одна формула (`<f>`/`<f …>`).
+fn has_formulas(xml: &[u8]) -> bool {
+    let p = root_prefix(xml);
+    [format!("<{p}f>"), format!("<{p}f ")]
+        .iter()
+        .any(|q| memmem::find(xml, q.as_bytes()).is_some())
+}
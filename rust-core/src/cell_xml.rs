@@ -0,0 +1,115 @@
+//! cell_xml.rs – ячейки и числа пишутся прямо в буфер XML листа.
+//!
+//! В горячих путях дозаписи (`append_row`, `append_table`, `with_polars`)
+//! на каждую ячейку раньше собирались строки: адрес через `format!`, число
+//! через `to_string()`, тег через `quick_xml::Writer`. Здесь то же самое
+//! дописывается в готовый `Vec<u8>`: числа — через `lexical_core` в буфер
+//! на стеке, буквы столбца — по байту.
+
+use lexical_core::{FormattedSize, ToLexical, WriteFloatOptions};
+use quick_xml::escape::escape;
+use std::fmt::{Display, Write};
+use std::num::NonZeroI32;
+
+/// Как `Display` у f64 (`1`, `0.25`, `1234567.5`), но очень большие и очень
+/// маленькие числа — с экспонентой (`1e21`, `1e-7`), а не сотней цифр.
+const FLOAT: WriteFloatOptions = match WriteFloatOptions::builder()
+    .trim_floats(true)
+    .positive_exponent_break(NonZeroI32::new(20))
+    .negative_exponent_break(NonZeroI32::new(-6))
+    .build()
+{
+    Ok(opts) => opts,
+    Err(_) => panic!("invalid float format options"),
+};
+
+/// Конечное `x` десятичной записью (см. [`FLOAT`]).
+pub(crate) fn push_f64(out: &mut Vec<u8>, x: f64) {
+    let mut buf = [0u8; f64::FORMATTED_SIZE_DECIMAL];
+    out.extend_from_slice(lexical_core::write_with_options::<
+        _,
+        { lexical_core::format::STANDARD },
+    >(x, &mut buf, &FLOAT));
+}
+
+/// Как [`push_f64`], кратчайшая запись f32 (`0.1`, а не `0.10000000149011612`).
+#[cfg(any(feature = "polars", test))]
+pub(crate) fn push_f32(out: &mut Vec<u8>, x: f32) {
+    let mut buf = [0u8; f32::FORMATTED_SIZE_DECIMAL];
+    out.extend_from_slice(lexical_core::write_with_options::<
+        _,
+        { lexical_core::format::STANDARD },
+    >(x, &mut buf, &FLOAT));
+}
+
+/// Целое число.
+pub(crate) fn push_int<N: ToLexical>(out: &mut Vec<u8>, n: N) {
+    let mut buf = [0u8; 40];
+    out.extend_from_slice(n.to_lexical(&mut buf));
+}
+
+/// Буквы столбца `col` (с нуля): `A`, `Z`, `AA`, … `XFD`.
+pub(crate) fn push_col(out: &mut Vec<u8>, col: u32) {
+    let mut letters = [0u8; 4];
+    let mut i = letters.len();
+    let mut n = col + 1;
+    while n > 0 {
+        i -= 1;
+        letters[i] = b'A' + ((n - 1) % 26) as u8;
+        n = (n - 1) / 26;
+    }
+    out.extend_from_slice(&letters[i..]);
+}
+
+/// Текст — конечное число? Не выделяет память; текст, который не может быть
+/// числом, отсеивается по первому байту без разбора.
+pub(crate) fn is_number(s: &str) -> bool {
+    matches!(s.as_bytes().first(), Some(b'0'..=b'9' | b'-' | b'+' | b'.'))
+        && lexical_core::parse::<f64>(s.as_bytes()).is_ok_and(f64::is_finite)
+}
+
+/// `<c>` ячейки (`col` с нуля) со значением `val` в разборе `append_row` и
+/// `set_cell`: ведущий `=` — формула, конечное число — число (как написано),
+/// остальное — строка `inlineStr`.
+pub(crate) fn push_text_cell(out: &mut Vec<u8>, col: u32, row: u32, val: &str) {
+    out.extend_from_slice(b"<c r=\"");
+    push_col(out, col);
+    push_int(out, row);
+    if let Some(formula) = val.strip_prefix('=') {
+        out.extend_from_slice(b"\"><f>");
+        out.extend_from_slice(escape(formula).as_bytes());
+        out.extend_from_slice(b"</f></c>");
+    } else if is_number(val) {
+        out.extend_from_slice(b"\"><v>");
+        out.extend_from_slice(val.as_bytes());
+        out.extend_from_slice(b"</v></c>");
+    } else {
+        out.extend_from_slice(b"\" t=\"inlineStr\"><is><t>");
+        out.extend_from_slice(escape(val).as_bytes());
+        out.extend_from_slice(b"</t></is></c>");
+    }
+}
+
+/// `<row r="row">` с ячейками [`push_text_cell`] подряд от столбца A; число
+/// записанных ячеек. Значения форматируются в один общий буфер, без
+/// `String` на каждую ячейку.
+pub(crate) fn push_text_row<I, S>(out: &mut Vec<u8>, row: u32, cells: I) -> u32
+where
+    I: IntoIterator<Item = S>,
+    S: Display,
+{
+    out.extend_from_slice(b"<row r=\"");
+    push_int(out, row);
+    out.extend_from_slice(b"\">");
+    let mut width = 0;
+    let mut val = String::new();
+    for cell in cells {
+        val.clear();
+        // запись в String не отказывает
+        let _ = write!(val, "{cell}");
+        push_text_cell(out, width, row, &val);
+        width += 1;
+    }
+    out.extend_from_slice(b"</row>");
+    width
+}
@@ -0,0 +1,121 @@
+//! columns_part.rs – запись данных по столбцам без polars: тип и стиль
+//! выбираются один раз на столбец, а не для каждой ячейки.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, check_row, ensure};
+use crate::range_part::CellMap;
+use crate::style::parse_cell;
+use crate::template_part::{CellValue, DATE_FORMAT, DATETIME_FORMAT};
+use anyhow::Result;
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+pub(crate) const MAX_COL: u32 = 16_384;
+
+/// Значения столбца для [`XlsxEditor::append_columns`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnData {
+    /// Числа; `NaN` — пустая ячейка.
+    F64(Vec<f64>),
+    I64(Vec<i64>),
+    Bool(Vec<bool>),
+    /// Текст; пустая строка — пустая ячейка.
+    Str(Vec<String>),
+    /// Даты: дни от 1970-01-01, формат `yyyy-mm-dd`.
+    Date(Vec<i32>),
+    /// Дата и время: миллисекунды от 1970-01-01 00:00, формат
+    /// `yyyy-mm-dd hh:mm:ss`.
+    DateTime(Vec<i64>),
+}
+
+impl ColumnData {
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnData::F64(v) => v.len(),
+            ColumnData::I64(v) => v.len(),
+            ColumnData::Bool(v) => v.len(),
+            ColumnData::Str(v) => v.len(),
+            ColumnData::Date(v) => v.len(),
+            ColumnData::DateTime(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl XlsxEditor {
+    /// Пишет столбцы `columns` с ячейки `start_cell`: строка имён, под ней
+    /// значения, каждый столбец — со своей длиной. Ячейки прямоугольника под
+    /// таблицу заменяются; блок целиком ниже данных листа просто дописывается.
+    pub fn append_columns(
+        &mut self,
+        start_cell: &str,
+        columns: &[(&str, ColumnData)],
+    ) -> Result<(), XlsxError> {
+        ensure!(!columns.is_empty(), "no columns to write");
+        let (c0, r0) = parse_cell(start_cell)?;
+        let height = columns.iter().map(|(_, d)| d.len()).max().unwrap_or(0);
+        check_row(r0 as u64 + height as u64)?;
+        ensure!(
+            c0 as usize + columns.len() <= MAX_COL as usize,
+            "{start_cell} is too close to the sheet edge for {} columns",
+            columns.len()
+        );
+        let r1 = r0 + height as u32;
+        let c1 = c0 + columns.len() as u32 - 1;
+
+        let mut cells = CellMap::new();
+        for (col, (name, data)) in (c0..).zip(columns) {
+            cells.entry(r0).or_default().insert(
+                col,
+                CellValue::Text(name.to_string()).cell_xml(col, r0, None),
+            );
+            let fmt = match data {
+                ColumnData::Date(_) => Some(DATE_FORMAT),
+                ColumnData::DateTime(_) => Some(DATETIME_FORMAT),
+                _ => None,
+            };
+            let s = fmt
+                .map(|f| self.ensure_style(Some(f), None, None, None, None))
+                .transpose()?;
+            let mut put = |i: usize, v: CellValue| {
+                let row = r0 + 1 + i as u32;
+                cells
+                    .entry(row)
+                    .or_default()
+                    .insert(col, v.cell_xml(col, row, s));
+            };
+            match data {
+                ColumnData::F64(v) => v
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, x)| !x.is_nan())
+                    .for_each(|(i, &x)| put(i, CellValue::Number(x))),
+                ColumnData::I64(v) => v
+                    .iter()
+                    .enumerate()
+                    .for_each(|(i, &x)| put(i, CellValue::Number(x as f64))),
+                ColumnData::Bool(v) => v
+                    .iter()
+                    .enumerate()
+                    .for_each(|(i, &x)| put(i, CellValue::Bool(x))),
+                ColumnData::Str(v) => v
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, x)| !x.is_empty())
+                    .for_each(|(i, x)| put(i, CellValue::Text(x.clone()))),
+                ColumnData::Date(v) => v
+                    .iter()
+                    .enumerate()
+                    .for_each(|(i, &d)| put(i, CellValue::unix_days(f64::from(d)))),
+                ColumnData::DateTime(v) => v
+                    .iter()
+                    .map(|&ms| ms as f64 / MS_PER_DAY)
+                    .enumerate()
+                    .for_each(|(i, d)| put(i, CellValue::unix_days(d))),
+            }
+        }
+        Ok(self.put_block((c0, r0, c1, r1), cells)?)
+    }
+}
@@ -0,0 +1,334 @@
+//! conditional.rs – условное форматирование (`<conditionalFormatting>` + `<dxfs>`).
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::files_part::xml_escape;
+use crate::format_part::{FontSpec, StyleSpec};
+use crate::sheet_xml::{insert_child, worksheet_children};
+use crate::theme_part::Color;
+use crate::view_part::normalize_sqref;
+use anyhow::Result;
+use memchr::memmem;
+use std::{fmt, str::FromStr};
+
+/* ========================== ПРАВИЛА ======================================= */
+
+/// Оператор сравнения для правила `cellIs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CfOperator {
+    LessThan,
+    LessThanOrEqual,
+    Equal,
+    NotEqual,
+    GreaterThanOrEqual,
+    GreaterThan,
+    Between,
+    NotBetween,
+}
+impl fmt::Display for CfOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CfOperator::LessThan => "lessThan",
+            CfOperator::LessThanOrEqual => "lessThanOrEqual",
+            CfOperator::Equal => "equal",
+            CfOperator::NotEqual => "notEqual",
+            CfOperator::GreaterThanOrEqual => "greaterThanOrEqual",
+            CfOperator::GreaterThan => "greaterThan",
+            CfOperator::Between => "between",
+            CfOperator::NotBetween => "notBetween",
+        })
+    }
+}
+impl FromStr for CfOperator {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        Ok(match s {
+            "lessThan" | "<" => CfOperator::LessThan,
+            "lessThanOrEqual" | "<=" => CfOperator::LessThanOrEqual,
+            "equal" | "=" | "==" => CfOperator::Equal,
+            "notEqual" | "<>" | "!=" => CfOperator::NotEqual,
+            "greaterThanOrEqual" | ">=" => CfOperator::GreaterThanOrEqual,
+            "greaterThan" | ">" => CfOperator::GreaterThan,
+            "between" => CfOperator::Between,
+            "notBetween" => CfOperator::NotBetween,
+            _ => bail!("Unknown conditional format operator: {s}"),
+        })
+    }
+}
+
+/// Оформление ячеек, подходящих под правило (пишется в `<dxf>`).
+/// Цвета — `RRGGBB`, `#RRGGBB` или `AARRGGBB`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CfStyle {
+    pub font_color: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub fill: Option<String>,
+}
+
+impl CfStyle {
+    /// То же оформление для [`XlsxEditor::ensure_dxf`]; цвета — в ARGB.
+    fn to_spec(&self) -> Result<StyleSpec> {
+        let rgb = |c: &String| argb(c).map(Color::Rgb);
+        Ok(StyleSpec {
+            font: FontSpec {
+                bold: self.bold,
+                italic: self.italic,
+                color: self.font_color.as_ref().map(rgb).transpose()?,
+                ..FontSpec::default()
+            },
+            fill: self.fill.as_ref().map(rgb).transpose()?,
+            ..StyleSpec::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CfRule {
+    /// Сравнение значения ячейки; для `Between`/`NotBetween` нужны две формулы.
+    CellIs {
+        operator: CfOperator,
+        formulas: Vec<String>,
+        style: CfStyle,
+    },
+    /// Формула относительно левой верхней ячейки диапазона (`MOD(ROW(),2)=0`).
+    Expression { formula: String, style: CfStyle },
+    /// Двух- или трёхцветная шкала от минимума к максимуму (середина — 50-й перцентиль).
+    ColorScale {
+        min_color: String,
+        mid_color: Option<String>,
+        max_color: String,
+    },
+    DataBar { color: String },
+    /// Набор значков (`3TrafficLights1`, `4Arrows`, `5Rating`, …) с равными долями.
+    IconSet {
+        icon_set: String,
+        reverse: bool,
+        show_value: bool,
+    },
+}
+
+/// Набор правил для одного диапазона.
+///
+/// ```no_run
+/// # use rust_core::{XlsxEditor, conditional::{CfOperator, CfStyle, ConditionalFormat}};
+/// # fn main() -> anyhow::Result<()> {
+/// # let mut xl = XlsxEditor::open("in.xlsx", "Sheet1")?;
+/// let red = CfStyle { font_color: Some("9C0006".into()), fill: Some("FFC7CE".into()), ..Default::default() };
+/// xl.add_conditional_format(
+///     ConditionalFormat::new("B2:B100")
+///         .cell_is(CfOperator::LessThan, "0", red)
+///         .data_bar("638EC6"),
+/// )?;
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalFormat {
+    pub range: String,
+    pub rules: Vec<CfRule>,
+}
+
+impl ConditionalFormat {
+    pub fn new(range: &str) -> Self {
+        Self {
+            range: range.to_owned(),
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn cell_is(mut self, operator: CfOperator, formula: &str, style: CfStyle) -> Self {
+        self.rules.push(CfRule::CellIs {
+            operator,
+            formulas: vec![formula.to_owned()],
+            style,
+        });
+        self
+    }
+
+    pub fn between(mut self, low: &str, high: &str, style: CfStyle) -> Self {
+        self.rules.push(CfRule::CellIs {
+            operator: CfOperator::Between,
+            formulas: vec![low.to_owned(), high.to_owned()],
+            style,
+        });
+        self
+    }
+
+    pub fn expression(mut self, formula: &str, style: CfStyle) -> Self {
+        self.rules.push(CfRule::Expression {
+            formula: formula.to_owned(),
+            style,
+        });
+        self
+    }
+
+    pub fn color_scale(mut self, min_color: &str, mid_color: Option<&str>, max_color: &str) -> Self {
+        self.rules.push(CfRule::ColorScale {
+            min_color: min_color.to_owned(),
+            mid_color: mid_color.map(str::to_owned),
+            max_color: max_color.to_owned(),
+        });
+        self
+    }
+
+    pub fn data_bar(mut self, color: &str) -> Self {
+        self.rules.push(CfRule::DataBar {
+            color: color.to_owned(),
+        });
+        self
+    }
+
+    pub fn icon_set(mut self, icon_set: &str, reverse: bool, show_value: bool) -> Self {
+        self.rules.push(CfRule::IconSet {
+            icon_set: icon_set.to_owned(),
+            reverse,
+            show_value,
+        });
+        self
+    }
+}
+
+/* ========================== ЗАПИСЬ ======================================== */
+
+impl XlsxEditor {
+    /// Добавляет на текущий лист блок `<conditionalFormatting>` с правилами `cf`.
+    /// Приоритеты продолжают уже существующие на листе (первое правило — самое важное).
+    pub fn add_conditional_format(
+        &mut self,
+        cf: ConditionalFormat,
+    ) -> Result<&mut Self, XlsxError> {
+        if cf.rules.is_empty() {
+            bail!("conditional format for {} has no rules", cf.range);
+        }
+        let sqref = normalize_sqref(&cf.range)?;
+        let mut priority = self.max_cf_priority()?;
+
+        let mut xml = format!(r#"<conditionalFormatting sqref="{sqref}">"#);
+        for rule in &cf.rules {
+            priority += 1;
+            match rule {
+                CfRule::CellIs {
+                    operator,
+                    formulas,
+                    style,
+                } => {
+                    let need = match operator {
+                        CfOperator::Between | CfOperator::NotBetween => 2,
+                        _ => 1,
+                    };
+                    if formulas.len() != need {
+                        bail!("operator {operator} needs {need} formula(s)");
+                    }
+                    let dxf = self.ensure_dxf(&style.to_spec()?)?;
+                    xml.push_str(&format!(
+                        r#"<cfRule type="cellIs" dxfId="{dxf}" priority="{priority}" operator="{operator}">"#
+                    ));
+                    for f in formulas {
+                        push_formula(&mut xml, f);
+                    }
+                    xml.push_str("</cfRule>");
+                }
+                CfRule::Expression { formula, style } => {
+                    let dxf = self.ensure_dxf(&style.to_spec()?)?;
+                    xml.push_str(&format!(
+                        r#"<cfRule type="expression" dxfId="{dxf}" priority="{priority}">"#
+                    ));
+                    push_formula(&mut xml, formula);
+                    xml.push_str("</cfRule>");
+                }
+                CfRule::ColorScale {
+                    min_color,
+                    mid_color,
+                    max_color,
+                } => {
+                    xml.push_str(&format!(
+                        r#"<cfRule type="colorScale" priority="{priority}"><colorScale><cfvo type="min"/>"#
+                    ));
+                    if mid_color.is_some() {
+                        xml.push_str(r#"<cfvo type="percentile" val="50"/>"#);
+                    }
+                    xml.push_str(r#"<cfvo type="max"/>"#);
+                    xml.push_str(&format!(r#"<color rgb="{}"/>"#, argb(min_color)?));
+                    if let Some(mid) = mid_color {
+                        xml.push_str(&format!(r#"<color rgb="{}"/>"#, argb(mid)?));
+                    }
+                    xml.push_str(&format!(r#"<color rgb="{}"/>"#, argb(max_color)?));
+                    xml.push_str("</colorScale></cfRule>");
+                }
+                CfRule::DataBar { color } => {
+                    xml.push_str(&format!(
+                        r#"<cfRule type="dataBar" priority="{priority}"><dataBar><cfvo type="min"/><cfvo type="max"/><color rgb="{}"/></dataBar></cfRule>"#,
+                        argb(color)?
+                    ));
+                }
+                CfRule::IconSet {
+                    icon_set,
+                    reverse,
+                    show_value,
+                } => {
+                    let n: u32 = match icon_set.chars().next() {
+                        Some(c @ '3'..='5') => c.to_digit(10).unwrap_or(3),
+                        _ => bail!("unknown icon set: {icon_set}"),
+                    };
+                    xml.push_str(&format!(
+                        r#"<cfRule type="iconSet" priority="{priority}"><iconSet iconSet="{}""#,
+                        xml_escape(icon_set)
+                    ));
+                    if !show_value {
+                        xml.push_str(r#" showValue="0""#);
+                    }
+                    if *reverse {
+                        xml.push_str(r#" reverse="1""#);
+                    }
+                    xml.push('>');
+                    for i in 0..n {
+                        xml.push_str(&format!(r#"<cfvo type="percent" val="{}"/>"#, i * 100 / n));
+                    }
+                    xml.push_str("</iconSet></cfRule>");
+                }
+            }
+        }
+        xml.push_str("</conditionalFormatting>");
+        insert_child(&mut self.sheet_xml, "conditionalFormatting", xml.as_bytes())?;
+        Ok(self)
+    }
+
+    /// Наибольший `priority` среди правил условного форматирования листа.
+    fn max_cf_priority(&self) -> Result<u32> {
+        let mut max = 0;
+        for c in worksheet_children(&self.sheet_xml)? {
+            if c.name != "conditionalFormatting" {
+                continue;
+            }
+            let block = &self.sheet_xml[c.range];
+            for p in memmem::find_iter(block, b" priority=\"") {
+                let v0 = p + 11;
+                let v: String = block[v0..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .map(|&b| b as char)
+                    .collect();
+                max = max.max(v.parse().unwrap_or(0));
+            }
+        }
+        Ok(max)
+    }
+}
+
+fn push_formula(xml: &mut String, formula: &str) {
+    let f = formula.strip_prefix('=').unwrap_or(formula);
+    xml.push_str(&format!("<formula>{}</formula>", xml_escape(f)));
+}
+
+/// `RRGGBB` / `#RRGGBB` / `AARRGGBB` → `AARRGGBB` в верхнем регистре.
+pub(crate) fn argb(color: &str) -> Result<String> {
+    let c = color.trim_start_matches('#');
+    if !c.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("invalid color: {color}");
+    }
+    Ok(match c.len() {
+        6 => format!("FF{}", c.to_ascii_uppercase()),
+        8 => c.to_ascii_uppercase(),
+        _ => bail!("invalid color: {color}"),
+    })
+}
@@ -0,0 +1,473 @@
+//! crypto_part.rs – книги под паролем (ECMA-376 agile encryption, MS-OFFCRYPTO):
+//! архив книги шифруется AES-256-CBC сегментами по 4096 байт и кладётся
+//! в составной файл (CFB) вместе с описанием ключа `EncryptionInfo`.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, next_event};
+use aes::{Aes128, Aes192, Aes256};
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use cbc::cipher::{
+    BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyInit, KeyIvInit, block_padding::NoPadding,
+};
+use quick_xml::{Reader, events::Event};
+use sha2::{Digest, Sha512};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Cursor, Read, Write},
+    path::Path,
+};
+
+/// Число итераций SHA-512 при выводе ключа из пароля, как пишет Excel.
+const SPIN_COUNT: u32 = 100_000;
+/// Длина ключа AES-256 в байтах.
+const KEY_LEN: usize = 32;
+/// Длина соли и блока AES.
+const BLOCK_LEN: usize = 16;
+/// Длина хэша SHA-512.
+const HASH_LEN: usize = 64;
+/// Размер сегмента `EncryptedPackage`, шифруемого со своим IV.
+const SEGMENT_LEN: usize = 4096;
+
+/// Константы `blockKey` из MS-OFFCRYPTO 2.3.4.11–2.3.4.14.
+const VERIFIER_INPUT_BLOCK: [u8; 8] = [0xfe, 0xa7, 0xd2, 0x76, 0x3b, 0x4b, 0x9e, 0x79];
+const VERIFIER_VALUE_BLOCK: [u8; 8] = [0xd7, 0xaa, 0x0f, 0x6d, 0x30, 0x61, 0x34, 0x4e];
+const KEY_VALUE_BLOCK: [u8; 8] = [0x14, 0x6e, 0x0b, 0xe7, 0xab, 0xac, 0xd0, 0xd6];
+const HMAC_KEY_BLOCK: [u8; 8] = [0x5f, 0xb2, 0xad, 0x01, 0x0c, 0xb9, 0xe1, 0xf6];
+const HMAC_VALUE_BLOCK: [u8; 8] = [0xa0, 0x67, 0x7f, 0x02, 0xb2, 0x2c, 0x84, 0x33];
+
+const ENCRYPTION_INFO: &str = "EncryptionInfo";
+const ENCRYPTED_PACKAGE: &str = "EncryptedPackage";
+
+impl XlsxEditor {
+    /// Открывает зашифрованную паролем книгу и лист `sheet_name`, как
+    /// [`XlsxEditor::open`]. Расшифрованный архив остаётся только в памяти.
+    /// Неверный пароль или повреждённый файл — ошибка.
+    pub fn open_encrypted<P: AsRef<Path>>(
+        src: P,
+        password: &str,
+        sheet_name: &str,
+    ) -> Result<Self, XlsxError> {
+        let src = src.as_ref();
+        let mut cfb = cfb::open(src)
+            .with_context(|| format!("{} is not a password-protected workbook", src.display()))?;
+        let mut read = |name: &str| -> Result<Vec<u8>> {
+            let mut buf = Vec::new();
+            cfb.open_stream(name)
+                .with_context(|| format!("{name} stream not found"))?
+                .read_to_end(&mut buf)?;
+            Ok(buf)
+        };
+        let info = read(ENCRYPTION_INFO)?;
+        let data = read(ENCRYPTED_PACKAGE)?;
+        let package = decrypt_package(&info, &data, password)?;
+        Self::open_bytes(package, sheet_name)
+    }
+
+    /// Как [`XlsxEditor::save`], но файл шифруется паролем `password`
+    /// (AES-256, SHA-512, 100 000 итераций) — Excel спросит пароль при открытии.
+    /// Незашифрованный архив собирается в памяти и на диск не попадает.
+    pub fn save_encrypted<P: AsRef<Path>>(
+        &mut self,
+        dst: P,
+        password: &str,
+    ) -> Result<(), XlsxError> {
+        let dst = dst.as_ref();
+        self.prepare_macro_target(dst)?;
+        let mut package = Cursor::new(Vec::new());
+        self.write_package(&mut package)?;
+        let (info, data) = encrypt_package(package.get_ref(), password)?;
+
+        // версия 3 (сектора по 512 байт), как у Excel
+        let mut cfb = cfb::CompoundFile::create_with_version(cfb::Version::V3, File::create(dst)?)?;
+        for (path, content) in data_spaces() {
+            if let Some(parent) = Path::new(path).parent() {
+                cfb.create_storage_all(parent)?;
+            }
+            cfb.create_stream(path)?.write_all(&content)?;
+        }
+        cfb.create_stream(ENCRYPTION_INFO)?.write_all(&info)?;
+        cfb.create_stream(ENCRYPTED_PACKAGE)?.write_all(&data)?;
+        cfb.flush()?;
+        Ok(())
+    }
+}
+
+/// Шифрует архив: `(EncryptionInfo, EncryptedPackage)`.
+fn encrypt_package(package: &[u8], password: &str) -> Result<(Vec<u8>, Vec<u8>)> {
+    let key_salt = random(BLOCK_LEN)?;
+    let pw_salt = random(BLOCK_LEN)?;
+    let secret = random(KEY_LEN)?;
+    let verifier = random(BLOCK_LEN)?;
+    let hmac_key = random(HASH_LEN)?;
+
+    // ключ книги, зашифрованный ключами из пароля
+    let h = password_hash(password, &pw_salt, SPIN_COUNT);
+    let key = |block: &[u8]| fit(sha512(&[&h, block]), KEY_LEN, 0x36);
+    let enc_verifier_input = aes_cbc(&key(&VERIFIER_INPUT_BLOCK), &pw_salt, &verifier, true)?;
+    let enc_verifier_value = aes_cbc(
+        &key(&VERIFIER_VALUE_BLOCK),
+        &pw_salt,
+        &sha512(&[&verifier]),
+        true,
+    )?;
+    let enc_key_value = aes_cbc(&key(&KEY_VALUE_BLOCK), &pw_salt, &secret, true)?;
+
+    // сам архив: размер и сегменты по 4096 байт, у каждого свой IV
+    let mut data = Vec::with_capacity(package.len() + BLOCK_LEN + 8);
+    data.extend((package.len() as u64).to_le_bytes());
+    for (i, segment) in package.chunks(SEGMENT_LEN).enumerate() {
+        let iv = block_iv(&key_salt, &(i as u32).to_le_bytes());
+        data.extend(aes_cbc(&secret, &iv, segment, true)?);
+    }
+
+    // целостность: HMAC всего потока EncryptedPackage
+    let hmac_value = hmac_sha512(&hmac_key, &data);
+    let enc_hmac_key = aes_cbc(
+        &secret,
+        &block_iv(&key_salt, &HMAC_KEY_BLOCK),
+        &hmac_key,
+        true,
+    )?;
+    let enc_hmac_value = aes_cbc(
+        &secret,
+        &block_iv(&key_salt, &HMAC_VALUE_BLOCK),
+        &hmac_value,
+        true,
+    )?;
+
+    let params = format!(
+        r#"saltSize="{BLOCK_LEN}" blockSize="{BLOCK_LEN}" keyBits="{}" hashSize="{HASH_LEN}" cipherAlgorithm="AES" cipherChaining="ChainingModeCBC" hashAlgorithm="SHA512""#,
+        KEY_LEN * 8
+    );
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\r\n\
+         <encryption xmlns=\"http://schemas.microsoft.com/office/2006/encryption\" \
+         xmlns:p=\"http://schemas.microsoft.com/office/2006/keyEncryptor/password\">\
+         <keyData {params} saltValue=\"{}\"/>\
+         <dataIntegrity encryptedHmacKey=\"{}\" encryptedHmacValue=\"{}\"/>\
+         <keyEncryptors><keyEncryptor uri=\"http://schemas.microsoft.com/office/2006/keyEncryptor/password\">\
+         <p:encryptedKey spinCount=\"{SPIN_COUNT}\" {params} saltValue=\"{}\" \
+         encryptedVerifierHashInput=\"{}\" encryptedVerifierHashValue=\"{}\" encryptedKeyValue=\"{}\"/>\
+         </keyEncryptor></keyEncryptors></encryption>",
+        STANDARD.encode(&key_salt),
+        STANDARD.encode(&enc_hmac_key),
+        STANDARD.encode(&enc_hmac_value),
+        STANDARD.encode(&pw_salt),
+        STANDARD.encode(&enc_verifier_input),
+        STANDARD.encode(&enc_verifier_value),
+        STANDARD.encode(&enc_key_value),
+    );
+    // версия 4.4 (agile) и флаги 0x40
+    let mut info = vec![4, 0, 4, 0, 0x40, 0, 0, 0];
+    info.extend(xml.as_bytes());
+    Ok((info, data))
+}
+
+/// Расшифровывает `EncryptedPackage` по `EncryptionInfo`: проверяет пароль
+/// и HMAC потока, возвращает исходный ZIP-архив.
+fn decrypt_package(info: &[u8], data: &[u8], password: &str) -> Result<Vec<u8>> {
+    if info.len() < 8 || info[..4] != [4, 0, 4, 0] {
+        let version = info.get(..4).map(|v| (v[0], v[2])).unwrap_or_default();
+        bail!(
+            "unsupported encryption version {}.{} (only agile encryption 4.4 is supported)",
+            version.0,
+            version.1
+        );
+    }
+    let elems = encryption_elems(&info[8..])?;
+    let elem = |name: &str| {
+        elems
+            .get(name)
+            .ok_or_else(|| anyhow!("EncryptionInfo: <{name}> not found"))
+    };
+    let key_data = KeyParams::parse(elem("keyData")?)?;
+    let encryptor = elem("encryptedKey")?;
+    let pw = KeyParams::parse(encryptor)?;
+    let spin: u32 = attr(encryptor, "spinCount")?
+        .parse()
+        .context("EncryptionInfo: bad spinCount")?;
+
+    // пароль: расшифрованный verifier должен совпасть со своим хэшем
+    let h = password_hash(password, &pw.salt, spin);
+    let key = |block: &[u8]| fit(sha512(&[&h, block]), pw.key_len, 0x36);
+    let iv = fit(pw.salt.clone(), BLOCK_LEN, 0x36);
+    let decrypt = |block: &[u8], name: &str| -> Result<Vec<u8>> {
+        aes_cbc(&key(block), &iv, &base64_attr(encryptor, name)?, false)
+    };
+    let mut verifier = decrypt(&VERIFIER_INPUT_BLOCK, "encryptedVerifierHashInput")?;
+    verifier.truncate(pw.salt.len());
+    let mut expected = decrypt(&VERIFIER_VALUE_BLOCK, "encryptedVerifierHashValue")?;
+    expected.truncate(HASH_LEN);
+    if sha512(&[&verifier]) != expected {
+        bail!("wrong password");
+    }
+    let mut secret = decrypt(&KEY_VALUE_BLOCK, "encryptedKeyValue")?;
+    secret.truncate(key_data.key_len);
+
+    if let Some(integrity) = elems.get("dataIntegrity") {
+        let unwrap = |block: &[u8], name: &str| -> Result<Vec<u8>> {
+            let iv = block_iv(&key_data.salt, block);
+            let mut v = aes_cbc(&secret, &iv, &base64_attr(integrity, name)?, false)?;
+            v.truncate(HASH_LEN);
+            Ok(v)
+        };
+        let hmac_key = unwrap(&HMAC_KEY_BLOCK, "encryptedHmacKey")?;
+        let hmac_value = unwrap(&HMAC_VALUE_BLOCK, "encryptedHmacValue")?;
+        if hmac_sha512(&hmac_key, data) != hmac_value {
+            bail!("encrypted workbook is damaged (integrity check failed)");
+        }
+    }
+
+    let Some((size, body)) = data.split_first_chunk::<8>() else {
+        bail!("{ENCRYPTED_PACKAGE} stream is truncated");
+    };
+    let size = u64::from_le_bytes(*size) as usize;
+    let mut package = Vec::with_capacity(body.len());
+    for (i, segment) in body.chunks(SEGMENT_LEN).enumerate() {
+        let iv = block_iv(&key_data.salt, &(i as u32).to_le_bytes());
+        package.extend(aes_cbc(&secret, &iv, segment, false)?);
+    }
+    if package.len() < size {
+        bail!("{ENCRYPTED_PACKAGE} stream is truncated");
+    }
+    package.truncate(size);
+    Ok(package)
+}
+
+/// Параметры шифра `<keyData>` или `<p:encryptedKey>`.
+struct KeyParams {
+    salt: Vec<u8>,
+    key_len: usize,
+}
+
+impl KeyParams {
+    /// Поддерживается то, что пишут Excel и LibreOffice: AES-CBC с SHA-512.
+    fn parse(attrs: &HashMap<String, String>) -> Result<Self> {
+        let algorithm = (
+            attr(attrs, "cipherAlgorithm")?,
+            attr(attrs, "cipherChaining")?,
+            attr(attrs, "hashAlgorithm")?,
+        );
+        if algorithm != ("AES", "ChainingModeCBC", "SHA512") {
+            bail!(
+                "unsupported encryption {} / {} / {} (only AES-CBC with SHA512)",
+                algorithm.0,
+                algorithm.1,
+                algorithm.2
+            );
+        }
+        let bits: usize = attr(attrs, "keyBits")?
+            .parse()
+            .context("EncryptionInfo: bad keyBits")?;
+        Ok(Self {
+            salt: base64_attr(attrs, "saltValue")?,
+            key_len: bits / 8,
+        })
+    }
+}
+
+/// Атрибуты элементов `EncryptionInfo` по локальному имени элемента.
+/// Из ключей берётся парольный (`<p:encryptedKey spinCount>`), не сертификатный.
+fn encryption_elems(xml: &[u8]) -> Result<HashMap<String, HashMap<String, String>>> {
+    let mut rdr = Reader::from_reader(xml);
+    let mut elems = HashMap::new();
+    loop {
+        match next_event(&mut rdr, ENCRYPTION_INFO)? {
+            Event::Start(e) | Event::Empty(e) => {
+                let attrs: HashMap<String, String> = e
+                    .attributes()
+                    .with_checks(false)
+                    .flatten()
+                    .map(|a| {
+                        (
+                            String::from_utf8_lossy(a.key.local_name().as_ref()).into_owned(),
+                            String::from_utf8_lossy(&a.value).into_owned(),
+                        )
+                    })
+                    .collect();
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                if name != "encryptedKey" || attrs.contains_key("spinCount") {
+                    elems.insert(name, attrs);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(elems)
+}
+
+fn attr<'a>(attrs: &'a HashMap<String, String>, name: &str) -> Result<&'a str> {
+    attrs
+        .get(name)
+        .map(String::as_str)
+        .ok_or_else(|| anyhow!("EncryptionInfo: attribute {name} not found"))
+}
+
+fn base64_attr(attrs: &HashMap<String, String>, name: &str) -> Result<Vec<u8>> {
+    STANDARD
+        .decode(attr(attrs, name)?)
+        .with_context(|| format!("EncryptionInfo: bad base64 in {name}"))
+}
+
+/// Хэш пароля: `H0 = sha(соль + пароль в UTF-16LE)`, `Hn = sha(n как u32 LE + Hn-1)`.
+fn password_hash(password: &str, salt: &[u8], spin: u32) -> Vec<u8> {
+    let pw: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut h = Sha512::new()
+        .chain_update(salt)
+        .chain_update(&pw)
+        .finalize();
+    for i in 0..spin {
+        h = Sha512::new()
+            .chain_update(i.to_le_bytes())
+            .chain_update(h)
+            .finalize();
+    }
+    h.to_vec()
+}
+
+/// IV сегмента или ключа целостности: `sha(соль + blockKey)`, обрезанный до блока.
+fn block_iv(salt: &[u8], block: &[u8]) -> Vec<u8> {
+    fit(sha512(&[salt, block]), BLOCK_LEN, 0x36)
+}
+
+fn sha512(parts: &[&[u8]]) -> Vec<u8> {
+    parts
+        .iter()
+        .fold(Sha512::new(), |h, p| h.chain_update(p))
+        .finalize()
+        .to_vec()
+}
+
+/// Обрезает `v` до `len` или дополняет байтами `pad`.
+fn fit(mut v: Vec<u8>, len: usize, pad: u8) -> Vec<u8> {
+    v.resize(len, pad);
+    v
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> Vec<u8> {
+    const SHA512_BLOCK: usize = 128;
+    let key = if key.len() > SHA512_BLOCK {
+        sha512(&[key])
+    } else {
+        key.to_vec()
+    };
+    let pad = |b: u8| -> Vec<u8> {
+        fit(key.clone(), SHA512_BLOCK, 0)
+            .into_iter()
+            .map(|k| k ^ b)
+            .collect()
+    };
+    sha512(&[&pad(0x5c), &sha512(&[&pad(0x36), data])])
+}
+
+fn random(len: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    getrandom::fill(&mut buf).map_err(|e| anyhow!("cannot generate random bytes: {e}"))?;
+    Ok(buf)
+}
+
+/// AES-CBC без дополнения; данные добиваются нулями до кратной блоку длины.
+/// Длина ключа (16/24/32 байта) выбирает AES-128/192/256.
+fn aes_cbc(key: &[u8], iv: &[u8], data: &[u8], encrypt: bool) -> Result<Vec<u8>> {
+    let mut buf = fit(data.to_vec(), data.len().div_ceil(BLOCK_LEN) * BLOCK_LEN, 0);
+    match (key.len(), encrypt) {
+        (16, true) => encrypt_cbc::<Aes128>(key, iv, &mut buf)?,
+        (24, true) => encrypt_cbc::<Aes192>(key, iv, &mut buf)?,
+        (32, true) => encrypt_cbc::<Aes256>(key, iv, &mut buf)?,
+        (16, false) => decrypt_cbc::<Aes128>(key, iv, &mut buf)?,
+        (24, false) => decrypt_cbc::<Aes192>(key, iv, &mut buf)?,
+        (32, false) => decrypt_cbc::<Aes256>(key, iv, &mut buf)?,
+        (n, _) => bail!("unsupported AES key length: {} bits", n * 8),
+    }
+    Ok(buf)
+}
+
+fn encrypt_cbc<C: BlockEncryptMut + BlockCipher + KeyInit>(
+    key: &[u8],
+    iv: &[u8],
+    buf: &mut [u8],
+) -> Result<()> {
+    let len = buf.len();
+    cbc::Encryptor::<C>::new_from_slices(key, iv)
+        .map_err(|_| anyhow!("bad AES key or IV length"))?
+        .encrypt_padded_mut::<NoPadding>(buf, len)
+        .map_err(|_| anyhow!("AES-CBC: data is not a whole number of blocks"))?;
+    Ok(())
+}
+
+fn decrypt_cbc<C: BlockDecryptMut + BlockCipher + KeyInit>(
+    key: &[u8],
+    iv: &[u8],
+    buf: &mut [u8],
+) -> Result<()> {
+    cbc::Decryptor::<C>::new_from_slices(key, iv)
+        .map_err(|_| anyhow!("bad AES key or IV length"))?
+        .decrypt_padded_mut::<NoPadding>(buf)
+        .map_err(|_| anyhow!("AES-CBC: data is not a whole number of blocks"))?;
+    Ok(())
+}
+
+/// Служебные потоки `\x06DataSpaces` (MS-OFFCRYPTO 2.2): говорят Excel, что
+/// `EncryptedPackage` обработан преобразованием StrongEncryptionTransform.
+fn data_spaces() -> [(&'static str, Vec<u8>); 4] {
+    let mut version = Vec::new();
+    lp_p4(&mut version, "Microsoft.Container.DataSpaces");
+    u16s(&mut version, &[1, 0, 1, 0, 1, 0]);
+
+    let mut entry = Vec::new();
+    entry.extend(1u32.to_le_bytes()); // одна ссылка
+    entry.extend(0u32.to_le_bytes()); // на поток
+    lp_p4(&mut entry, ENCRYPTED_PACKAGE);
+    lp_p4(&mut entry, "StrongEncryptionDataSpace");
+    let mut map = Vec::new();
+    map.extend(8u32.to_le_bytes());
+    map.extend(1u32.to_le_bytes());
+    map.extend((entry.len() as u32 + 4).to_le_bytes());
+    map.extend(entry);
+
+    let mut space = Vec::new();
+    space.extend(8u32.to_le_bytes());
+    space.extend(1u32.to_le_bytes());
+    lp_p4(&mut space, "StrongEncryptionTransform");
+
+    let mut id = Vec::new();
+    lp_p4(&mut id, "{FF9A3F03-56EF-4613-BDD5-5A41C1D07246}");
+    let mut primary = Vec::new();
+    primary.extend((id.len() as u32 + 8).to_le_bytes());
+    primary.extend(1u32.to_le_bytes());
+    primary.extend(id);
+    lp_p4(&mut primary, "Microsoft.Container.EncryptionTransform");
+    u16s(&mut primary, &[1, 0, 1, 0, 1, 0]);
+    // EncryptionTransformInfo: пустое имя, размер блока, режим, reserved = 4
+    for v in [0u32, 0, 0, 4] {
+        primary.extend(v.to_le_bytes());
+    }
+
+    [
+        ("\u{6}DataSpaces/Version", version),
+        ("\u{6}DataSpaces/DataSpaceMap", map),
+        (
+            "\u{6}DataSpaces/DataSpaceInfo/StrongEncryptionDataSpace",
+            space,
+        ),
+        (
+            "\u{6}DataSpaces/TransformInfo/StrongEncryptionTransform/\u{6}Primary",
+            primary,
+        ),
+    ]
+}
+
+/// Строка UNICODE-LP-P4: длина в байтах, UTF-16LE, выравнивание до 4 байт.
+fn lp_p4(out: &mut Vec<u8>, s: &str) {
+    let utf16: Vec<u8> = s.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    out.extend((utf16.len() as u32).to_le_bytes());
+    out.extend(utf16);
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+fn u16s(out: &mut Vec<u8>, values: &[u16]) {
+    out.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+}
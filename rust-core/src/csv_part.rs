@@ -0,0 +1,238 @@
+//! csv_part.rs – дозапись CSV/TSV под данные листа без polars: записи читаются
+//! потоком, тип каждой ячейки выводится по значению или задаётся схемой.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, check_row};
+use crate::sheet_xml::open_sheet_data;
+use crate::template_part::CellValue;
+use anyhow::{Context, Result};
+use std::{fmt, io::Read, str::FromStr};
+
+/// Тип значений столбца CSV в [`CsvOptions::schema`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvType {
+    /// По значению: число, `true`/`false`, дата `yyyy-mm-dd[ hh:mm[:ss]]`,
+    /// иначе текст.
+    #[default]
+    Auto,
+    /// Всегда текст: коды с ведущими нулями, телефоны.
+    Text,
+    Number,
+    Bool,
+    /// Дата или дата со временем — сериалом Excel в формате даты.
+    Date,
+}
+
+impl fmt::Display for CsvType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CsvType::Auto => "auto",
+            CsvType::Text => "text",
+            CsvType::Number => "number",
+            CsvType::Bool => "bool",
+            CsvType::Date => "date",
+        })
+    }
+}
+
+impl FromStr for CsvType {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        Ok(match s {
+            "auto" => CsvType::Auto,
+            "text" => CsvType::Text,
+            "number" => CsvType::Number,
+            "bool" => CsvType::Bool,
+            "date" => CsvType::Date,
+            _ => bail!("unknown CSV column type: {s}"),
+        })
+    }
+}
+
+/// Параметры [`XlsxEditor::append_csv`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// Разделитель полей: `b','`, для TSV — `b'\t'`.
+    pub delimiter: u8,
+    /// Первая запись — имена столбцов, а не данные.
+    pub has_header: bool,
+    /// Записать имена столбцов строкой перед данными (при `has_header`).
+    pub write_header: bool,
+    /// Типы столбцов по порядку; недостающие — [`CsvType::Auto`].
+    pub schema: Vec<CsvType>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            has_header: true,
+            write_header: false,
+            schema: Vec::new(),
+        }
+    }
+}
+
+impl XlsxEditor {
+    /// Дописывает записи CSV из `reader` под данными листа, с `last_row + 1`,
+    /// начиная со столбца A. Записи могут быть разной длины, пустые поля дают
+    /// пустые ячейки. Значения, начинающиеся с `=`, пишутся текстом, а не
+    /// формулой. Возвращает число записанных строк данных.
+    ///
+    /// Если значение не подходит к типу из схемы, ничего не пишется, а ошибка
+    /// называет строку и столбец CSV.
+    pub fn append_csv<R: Read>(
+        &mut self,
+        reader: R,
+        opts: &CsvOptions,
+    ) -> Result<usize, XlsxError> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(opts.delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        let old_last = self.last_row;
+        let mut row = old_last;
+        let (mut width, mut n) = (0u32, 0usize);
+        // стили даты и даты со временем создаются при первой встрече
+        let mut date_styles: [Option<u32>; 2] = [None; 2];
+        let mut bulk_rows_xml = Vec::new();
+        let mut record = csv::StringRecord::new();
+        let mut first = true;
+        while rdr.read_record(&mut record).map_err(anyhow::Error::from)? {
+            let header = std::mem::take(&mut first) && opts.has_header;
+            if header && !opts.write_header {
+                continue;
+            }
+            check_row(row as u64 + 1)?;
+            row += 1;
+            let line = record.position().map_or(0, |p| p.line());
+            let mut xml = format!(r#"<row r="{row}">"#).into_bytes();
+            for (col, field) in (0u32..).zip(record.iter()) {
+                let ty = if header {
+                    CsvType::Text
+                } else {
+                    opts.schema.get(col as usize).copied().unwrap_or_default()
+                };
+                let (value, date) = csv_value(field, ty)
+                    .with_context(|| format!("CSV line {line}, column {}", col + 1))?;
+                if value == CellValue::Empty {
+                    continue;
+                }
+                let s = match date {
+                    Some(with_time) => {
+                        let slot = &mut date_styles[usize::from(with_time)];
+                        if slot.is_none() {
+                            let fmt = ["yyyy-mm-dd", "yyyy-mm-dd hh:mm:ss"][usize::from(with_time)];
+                            *slot = Some(self.ensure_style(Some(fmt), None, None, None, None)?);
+                        }
+                        *slot
+                    }
+                    None => None,
+                };
+                xml.extend(value.cell_xml(col, row, s));
+            }
+            xml.extend_from_slice(b"</row>");
+            bulk_rows_xml.extend(xml);
+            width = width.max(record.len() as u32);
+            n += usize::from(!header);
+        }
+
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, bulk_rows_xml);
+        self.last_row = row;
+        let written = (width > 0 && row > old_last).then(|| (0, old_last + 1, width - 1, row));
+        self.grow_ranges_on_append(old_last, written)?;
+        Ok(n)
+    }
+}
+
+/// Значение поля по типу; вторым — `Some(есть ли время)` для дат.
+fn csv_value(field: &str, ty: CsvType) -> Result<(CellValue, Option<bool>)> {
+    if field.is_empty() {
+        return Ok((CellValue::Empty, None));
+    }
+    let number = || field.parse::<f64>().ok().filter(|n| n.is_finite());
+    let boolean = || match field.to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    };
+    Ok(match ty {
+        CsvType::Text => (CellValue::Text(field.to_owned()), None),
+        CsvType::Number => match number() {
+            Some(n) => (CellValue::Number(n), None),
+            None => bail!("`{field}` is not a number"),
+        },
+        CsvType::Bool => match boolean() {
+            Some(b) => (CellValue::Bool(b), None),
+            None => bail!("`{field}` is not true/false"),
+        },
+        CsvType::Date => match date_serial(field) {
+            Some((serial, time)) => (CellValue::Number(serial), Some(time)),
+            None => bail!("`{field}` is not a yyyy-mm-dd[ hh:mm[:ss]] date"),
+        },
+        CsvType::Auto => {
+            if let Some(n) = number() {
+                (CellValue::Number(n), None)
+            } else if let Some(b) = boolean() {
+                (CellValue::Bool(b), None)
+            } else if let Some((serial, time)) = date_serial(field) {
+                (CellValue::Number(serial), Some(time))
+            } else {
+                (CellValue::Text(field.to_owned()), None)
+            }
+        }
+    })
+}
+
+/// `yyyy-mm-dd`, `yyyy-mm-dd hh:mm[:ss]` (или через `T`, с `Z` в конце) →
+/// сериал Excel и есть ли в значении время.
+fn date_serial(s: &str) -> Option<(f64, bool)> {
+    let (date, time) = match s.split_once([' ', 'T']) {
+        Some((d, t)) => (d, Some(t.strip_suffix('Z').unwrap_or(t))),
+        None => (s, None),
+    };
+    let mut p = date.split('-');
+    let (y, m, d) = (p.next()?, p.next()?, p.next()?);
+    if p.next().is_some() || (y.len(), m.len(), d.len()) != (4, 2, 2) {
+        return None;
+    }
+    let (y, m, d): (i64, u32, u32) = (y.parse().ok()?, m.parse().ok()?, d.parse().ok()?);
+    let leap = y % 4 == 0 && (y % 100 != 0 || y % 400 == 0);
+    let month_days = match m {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if d == 0 || d > month_days {
+        return None;
+    }
+    let mut serial = days_from_civil(y, m, d) as f64 + 25_569.0;
+    if let Some(t) = time {
+        let mut p = t.split(':');
+        let (h, mi) = (
+            p.next()?.parse::<u32>().ok()?,
+            p.next()?.parse::<u32>().ok()?,
+        );
+        let sec = p.next().map_or(Some(0.0), |x| x.parse::<f64>().ok())?;
+        if p.next().is_some() || h > 23 || mi > 59 || !(0.0..60.0).contains(&sec) {
+            return None;
+        }
+        serial += (f64::from(h * 3600 + mi * 60) + sec) / 86_400.0;
+    }
+    Some((serial, time.is_some()))
+}
+
+/// Дни от 1970-01-01 до даты григорианского календаря.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (i64::from(m) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(d) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
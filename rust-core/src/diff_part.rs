@@ -0,0 +1,424 @@
+//! diff_part.rs – сравнение двух книг по содержимому ячеек.
+//!
+//! Побайтовое сравнение xlsx бесполезно в тестах генерации отчётов: время в
+//! docProps, порядок sharedStrings и номера стилей меняются от запуска к
+//! запуску. [`diff`] сравнивает листы по ячейкам — значение, формулу и стиль,
+//! раскрытый до числового формата, шрифта, заливки, границ, выравнивания и
+//! защиты, — так что одинаковые на вид книги не различаются.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, next_event};
+use crate::numfmt::builtin_code;
+use crate::read_part::{ReadValue, read_cells, read_value};
+use crate::scan;
+use crate::sheet_xml::find_child;
+use crate::style::col_letter;
+use anyhow::{Context, Result};
+use quick_xml::{Reader, Writer, events::Event};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// Результат [`diff`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkbookDiff {
+    /// Листы второй книги, которых нет в первой.
+    pub added_sheets: Vec<String>,
+    /// Листы первой книги, которых нет во второй.
+    pub removed_sheets: Vec<String>,
+    /// Листы обеих книг с различиями, в порядке первой книги.
+    pub sheets: Vec<SheetDiff>,
+}
+
+/// Различающиеся ячейки листа, по строкам.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SheetDiff {
+    pub name: String,
+    pub cells: Vec<CellDiff>,
+}
+
+/// Ячейка, которая в двух книгах различается значением, формулой или стилем.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellDiff {
+    /// Адрес: `"B4"`.
+    pub cell: String,
+    /// Ячейка в первой книге.
+    pub before: CellState,
+    /// Ячейка во второй книге.
+    pub after: CellState,
+}
+
+/// Ячейка одной из книг; нет ячейки — пустое значение и стиль `cellXfs` 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellState {
+    /// Значение как его хранит лист: даты — числами, их формат — в `style`.
+    pub value: ReadValue,
+    /// Формула без `=`.
+    pub formula: Option<String>,
+    pub style: CellStyle,
+}
+
+/// Стиль ячейки, раскрытый из `styles.xml`: шрифт, заливка, границы,
+/// выравнивание и защита — их XML без пробелов между тегами; нет элемента —
+/// пустая строка.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellStyle {
+    /// Код числового формата (`"0.00%"`, `"General"`).
+    pub number_format: String,
+    pub font: String,
+    pub fill: String,
+    pub border: String,
+    pub alignment: String,
+    pub protection: String,
+}
+
+impl WorkbookDiff {
+    /// Книги не различаются.
+    pub fn is_empty(&self) -> bool {
+        self.added_sheets.is_empty() && self.removed_sheets.is_empty() && self.sheets.is_empty()
+    }
+
+    /// Различия листа `name`; `None` — лист в обеих книгах одинаков.
+    pub fn sheet(&self, name: &str) -> Option<&SheetDiff> {
+        self.sheets.iter().find(|s| s.name == name)
+    }
+}
+
+impl CellDiff {
+    /// Различаются значение или формула.
+    pub fn value_changed(&self) -> bool {
+        (&self.before.value, &self.before.formula) != (&self.after.value, &self.after.formula)
+    }
+
+    pub fn style_changed(&self) -> bool {
+        self.before.style != self.after.style
+    }
+}
+
+impl fmt::Display for WorkbookDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.added_sheets {
+            writeln!(f, "sheet added: {name}")?;
+        }
+        for name in &self.removed_sheets {
+            writeln!(f, "sheet removed: {name}")?;
+        }
+        for s in &self.sheets {
+            for c in &s.cells {
+                let (a, b) = (&c.before, &c.after);
+                if c.value_changed() {
+                    writeln!(
+                        f,
+                        "{}!{}: {} -> {}",
+                        s.name,
+                        c.cell,
+                        a.content(),
+                        b.content()
+                    )?;
+                }
+                let fields = [
+                    (
+                        "number format",
+                        &a.style.number_format,
+                        &b.style.number_format,
+                    ),
+                    ("font", &a.style.font, &b.style.font),
+                    ("fill", &a.style.fill, &b.style.fill),
+                    ("border", &a.style.border, &b.style.border),
+                    ("alignment", &a.style.alignment, &b.style.alignment),
+                    ("protection", &a.style.protection, &b.style.protection),
+                ];
+                for (what, x, y) in fields.into_iter().filter(|(_, x, y)| x != y) {
+                    writeln!(f, "{}!{} {what}: {x} -> {y}", s.name, c.cell)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CellState {
+    /// Значение или формула для [`WorkbookDiff`] в тексте.
+    fn content(&self) -> String {
+        match (&self.formula, &self.value) {
+            (Some(f), _) => format!("={f}"),
+            (None, ReadValue::Empty) => "(empty)".to_owned(),
+            (None, v) => v.to_text().unwrap_or_default(),
+        }
+    }
+}
+
+/// Сравнивает книги `path_a` и `path_b` по ячейкам: листы с одинаковыми
+/// именами — ячейка в ячейку, по значению, формуле и стилю (см.
+/// [`CellStyle`]). Пустая ячейка со стилем по умолчанию равна отсутствующей.
+/// Остальное (ширины столбцов, объединения, docProps) не сравнивается.
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// let d = rust_core::diff("expected.xlsx", "report.xlsx")?;
+/// assert!(d.is_empty(), "{d}");
+/// # Ok(()) }
+/// ```
+pub fn diff<P: AsRef<Path>, Q: AsRef<Path>>(
+    path_a: P,
+    path_b: Q,
+) -> Result<WorkbookDiff, XlsxError> {
+    let mut a = Book::open(path_a.as_ref())?;
+    let mut b = Book::open(path_b.as_ref())?;
+    let mut out = WorkbookDiff {
+        added_sheets: b
+            .sheets
+            .iter()
+            .filter(|n| !a.sheets.contains(n))
+            .cloned()
+            .collect(),
+        removed_sheets: a
+            .sheets
+            .iter()
+            .filter(|n| !b.sheets.contains(n))
+            .cloned()
+            .collect(),
+        sheets: Vec::new(),
+    };
+    for name in a.sheets.clone() {
+        if !b.sheets.contains(&name) {
+            continue;
+        }
+        let (was, now) = (a.cells(&name)?, b.cells(&name)?);
+        let addrs: BTreeSet<(u32, u32)> = was.keys().chain(now.keys()).copied().collect();
+        let mut cells = Vec::new();
+        for (row, col) in addrs {
+            let before = was.get(&(row, col)).cloned().unwrap_or_else(|| a.blank());
+            let after = now.get(&(row, col)).cloned().unwrap_or_else(|| b.blank());
+            if before != after {
+                cells.push(CellDiff {
+                    cell: format!("{}{row}", col_letter(col)),
+                    before,
+                    after,
+                });
+            }
+        }
+        if !cells.is_empty() {
+            out.sheets.push(SheetDiff { name, cells });
+        }
+    }
+    Ok(out)
+}
+
+/// Одна из сравниваемых книг.
+struct Book {
+    xl: XlsxEditor,
+    sheets: Vec<String>,
+    styles: Vec<CellStyle>,
+}
+
+impl Book {
+    fn open(path: &Path) -> Result<Self> {
+        let sheets = scan(path)?;
+        let first = sheets
+            .first()
+            .with_context(|| format!("{} has no sheets", path.display()))?;
+        let mut xl = XlsxEditor::open(path, first)?;
+        xl.ensure_shared_strings()?;
+        // книга без styles.xml — у всех ячеек стиль по умолчанию
+        let styles = match xl.styles_xml.get(&xl.src) {
+            Ok(xml) => cell_styles(xml)?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Book { xl, sheets, styles })
+    }
+
+    /// Ячейка, которой в листе нет: пустая, со стилем по умолчанию.
+    fn blank(&self) -> CellState {
+        CellState {
+            value: ReadValue::Empty,
+            formula: None,
+            style: self.styles.first().cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Ячейки листа `name` по `(строка, столбец)`.
+    fn cells(&mut self, name: &str) -> Result<HashMap<(u32, u32), CellState>> {
+        self.xl.with_worksheet(name)?;
+        let sst = self.xl.shared_strings.as_deref().unwrap_or_default();
+        let Some(sd) = find_child(&self.xl.sheet_xml, "sheetData")? else {
+            return Ok(HashMap::new());
+        };
+        let mut out = HashMap::new();
+        for c in read_cells(&self.xl.sheet_xml[sd])? {
+            let style = c.s.unwrap_or(0) as usize;
+            let state = CellState {
+                value: read_value(&c, sst, &[]),
+                formula: c.f.clone(),
+                style: self.styles.get(style).cloned().unwrap_or_default(),
+            };
+            out.insert((c.row, c.col), state);
+        }
+        Ok(out)
+    }
+}
+
+/// Куда пишется перехваченный элемент `styles.xml`.
+#[derive(Clone, Copy)]
+enum Slot {
+    Font,
+    Fill,
+    Border,
+    Alignment,
+    Protection,
+}
+
+/// Ссылки `<xf>` из `<cellXfs>`.
+#[derive(Default)]
+struct Xf {
+    num_fmt: u32,
+    font: usize,
+    fill: usize,
+    border: usize,
+    alignment: String,
+    protection: String,
+}
+
+/// Стили ячеек `<cellXfs>` по номерам, раскрытые до [`CellStyle`].
+fn cell_styles(xml: &[u8]) -> Result<Vec<CellStyle>> {
+    const PART: &str = "xl/styles.xml";
+    let mut rdr = Reader::from_reader(xml);
+    rdr.config_mut().trim_text(true);
+    let mut codes: HashMap<u32, String> = HashMap::new();
+    let (mut fonts, mut fills, mut borders) = (Vec::new(), Vec::new(), Vec::new());
+    let mut xfs: Vec<Xf> = Vec::new();
+    let mut section: Option<Vec<u8>> = None; // fonts, fills, borders или cellXfs
+    // элемент, который сейчас переписывается без пробелов, и его глубина
+    let mut capture: Option<(Slot, Writer<Vec<u8>>, usize)> = None;
+    loop {
+        let ev = next_event(&mut rdr, PART)?;
+        if let Some((slot, w, depth)) = &mut capture {
+            match ev {
+                Event::Start(_) => *depth += 1,
+                Event::End(_) => *depth -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            w.write_event(ev)?;
+            if *depth == 0 {
+                let slot = *slot;
+                let xml = capture
+                    .take()
+                    .map(|(_, w, _)| w.into_inner())
+                    .unwrap_or_default();
+                store(
+                    slot,
+                    String::from_utf8_lossy(&xml).into_owned(),
+                    &mut fonts,
+                    &mut fills,
+                    &mut borders,
+                    &mut xfs,
+                );
+            }
+            continue;
+        }
+        let (e, empty) = match &ev {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) => {
+                if section.as_deref() == Some(e.local_name().as_ref()) {
+                    section = None;
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let name = e.local_name();
+        let slot = match (section.as_deref(), name.as_ref()) {
+            (None, b"fonts" | b"fills" | b"borders" | b"cellXfs") if !empty => {
+                section = Some(name.as_ref().to_vec());
+                None
+            }
+            (_, b"numFmt") => {
+                let (mut id, mut code) = (None, String::new());
+                for a in e.attributes().with_checks(false).flatten() {
+                    match a.key.as_ref() {
+                        b"numFmtId" => id = lexical_core::parse::<u32>(&a.value).ok(),
+                        b"formatCode" => code = a.unescape_value()?.into_owned(),
+                        _ => {}
+                    }
+                }
+                codes.extend(id.map(|id| (id, code)));
+                None
+            }
+            (Some(b"fonts"), b"font") => Some(Slot::Font),
+            (Some(b"fills"), b"fill") => Some(Slot::Fill),
+            (Some(b"borders"), b"border") => Some(Slot::Border),
+            (Some(b"cellXfs"), b"xf") => {
+                let mut xf = Xf::default();
+                for a in e.attributes().with_checks(false).flatten() {
+                    let n = lexical_core::parse::<u32>(&a.value).unwrap_or(0);
+                    match a.key.as_ref() {
+                        b"numFmtId" => xf.num_fmt = n,
+                        b"fontId" => xf.font = n as usize,
+                        b"fillId" => xf.fill = n as usize,
+                        b"borderId" => xf.border = n as usize,
+                        _ => {}
+                    }
+                }
+                xfs.push(xf);
+                None
+            }
+            (Some(b"cellXfs"), b"alignment") => Some(Slot::Alignment),
+            (Some(b"cellXfs"), b"protection") => Some(Slot::Protection),
+            _ => None,
+        };
+        if let Some(slot) = slot {
+            let mut w = Writer::new(Vec::new());
+            w.write_event(ev.clone())?;
+            if empty {
+                store(
+                    slot,
+                    String::from_utf8_lossy(&w.into_inner()).into_owned(),
+                    &mut fonts,
+                    &mut fills,
+                    &mut borders,
+                    &mut xfs,
+                );
+            } else {
+                capture = Some((slot, w, 1));
+            }
+        }
+    }
+
+    let pick = |v: &[String], i: usize| v.get(i).cloned().unwrap_or_default();
+    Ok(xfs
+        .into_iter()
+        .map(|xf| CellStyle {
+            number_format: match codes.get(&xf.num_fmt) {
+                Some(code) => code.clone(),
+                None => builtin_code(xf.num_fmt)
+                    .map_or_else(|| format!("numFmtId {}", xf.num_fmt), str::to_owned),
+            },
+            font: pick(&fonts, xf.font),
+            fill: pick(&fills, xf.fill),
+            border: pick(&borders, xf.border),
+            alignment: xf.alignment,
+            protection: xf.protection,
+        })
+        .collect())
+}
+
+fn store(
+    slot: Slot,
+    xml: String,
+    fonts: &mut Vec<String>,
+    fills: &mut Vec<String>,
+    borders: &mut Vec<String>,
+    xfs: &mut [Xf],
+) {
+    match (slot, xfs.last_mut()) {
+        (Slot::Font, _) => fonts.push(xml),
+        (Slot::Fill, _) => fills.push(xml),
+        (Slot::Border, _) => borders.push(xml),
+        (Slot::Alignment, Some(xf)) => xf.alignment = xml,
+        (Slot::Protection, Some(xf)) => xf.protection = xml,
+        _ => {}
+    }
+}
@@ -0,0 +1,342 @@
+//! docprops_part.rs – свойства документа: docProps/core.xml (название, автор,
+//! даты), docProps/app.xml (организация, руководитель) и пользовательские
+//! свойства docProps/custom.xml. Отсутствующие части создаются.
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::files_part::xml_escape;
+use crate::sheet_xml::{ensure_root_ns, find_elem, get_attr, root_prefix, root_tag_range};
+use anyhow::{Context, Result};
+use memchr::memmem;
+use quick_xml::escape::unescape;
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const CORE_KIND: &str = "core-properties";
+const APP_KIND: &str = "extended-properties";
+const CUSTOM_KIND: &str = "custom-properties";
+
+const NS_CP: &str = "http://schemas.openxmlformats.org/package/2006/metadata/core-properties";
+const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
+const NS_DCTERMS: &str = "http://purl.org/dc/terms/";
+const NS_VT: &str = "http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes";
+const NS_XSI: &str = "http://www.w3.org/2001/XMLSchema-instance";
+/// `fmtid` пользовательских свойств (FMTID_UserDefinedProperties).
+const CUSTOM_FMTID: &str = "{D5CDD505-2E9C-101B-9397-08002B2CF9AE}";
+
+const CORE_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:dcmitype="http://purl.org/dc/dcmitype/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"></cp:coreProperties>"#;
+const APP_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"></Properties>"#;
+const CUSTOM_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"></Properties>"#;
+
+/// Свойства для [`XlsxEditor::set_doc_properties`] («Файл → Сведения»).
+/// `None` — оставить как есть в файле.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocProperties {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    /// Автор (`dc:creator`).
+    pub author: Option<String>,
+    pub keywords: Option<String>,
+    /// Примечания (`dc:description`).
+    pub description: Option<String>,
+    pub category: Option<String>,
+    /// Состояние («Черновик», «Окончательный»).
+    pub status: Option<String>,
+    /// Организация (docProps/app.xml).
+    pub company: Option<String>,
+    /// Руководитель (docProps/app.xml).
+    pub manager: Option<String>,
+}
+
+/// Значение пользовательского свойства ([`XlsxEditor::set_custom_property`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    /// Дата и время в формате ISO 8601 (`2025-01-31T00:00:00Z`).
+    Date(String),
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::Text(s) | PropertyValue::Date(s) => f.write_str(s),
+            PropertyValue::Number(n) => write!(f, "{n}"),
+            PropertyValue::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(s: &str) -> Self {
+        PropertyValue::Text(s.to_owned())
+    }
+}
+impl From<String> for PropertyValue {
+    fn from(s: String) -> Self {
+        PropertyValue::Text(s)
+    }
+}
+impl From<f64> for PropertyValue {
+    fn from(n: f64) -> Self {
+        PropertyValue::Number(n)
+    }
+}
+impl From<i32> for PropertyValue {
+    fn from(n: i32) -> Self {
+        PropertyValue::Number(n.into())
+    }
+}
+impl From<bool> for PropertyValue {
+    fn from(b: bool) -> Self {
+        PropertyValue::Bool(b)
+    }
+}
+
+impl PropertyValue {
+    /// Значение в `vt:`-элементе: целые числа — `vt:i4`, прочие — `vt:r8`.
+    fn vt_xml(&self) -> String {
+        let (vt, v) = match self {
+            PropertyValue::Text(s) => ("lpwstr", xml_escape(s)),
+            PropertyValue::Number(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => {
+                ("i4", (*n as i32).to_string())
+            }
+            PropertyValue::Number(n) => ("r8", n.to_string()),
+            PropertyValue::Bool(b) => ("bool", b.to_string()),
+            PropertyValue::Date(s) => ("filetime", xml_escape(s)),
+        };
+        format!("<vt:{vt}>{v}</vt:{vt}>")
+    }
+}
+
+impl XlsxEditor {
+    /// Меняет свойства документа: название, тему, автора, ключевые слова,
+    /// примечания, категорию и состояние (docProps/core.xml), организацию
+    /// и руководителя (docProps/app.xml).
+    pub fn set_doc_properties(&mut self, props: DocProperties) -> Result<&mut Self, XlsxError> {
+        let core = [
+            ("dc:title", &props.title),
+            ("dc:subject", &props.subject),
+            ("dc:creator", &props.author),
+            ("cp:keywords", &props.keywords),
+            ("dc:description", &props.description),
+            ("cp:category", &props.category),
+            ("cp:contentStatus", &props.status),
+        ];
+        if core.iter().any(|(_, v)| v.is_some()) {
+            self.edit_props_part(CORE_KIND, CORE_XML, |xml| {
+                ensure_core_ns(xml)?;
+                for (name, value) in core {
+                    if let Some(v) = value {
+                        set_elem_text(xml, name, "", v)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        let app = [("Company", &props.company), ("Manager", &props.manager)];
+        if app.iter().any(|(_, v)| v.is_some()) {
+            self.edit_props_part(APP_KIND, APP_XML, |xml| {
+                let p = root_prefix(xml);
+                for (name, value) in app {
+                    if let Some(v) = value {
+                        set_elem_text(xml, &format!("{p}{name}"), "", v)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// Ставит пользовательское свойство `name` («Файл → Свойства → Прочие»);
+    /// свойство с тем же именем заменяется.
+    pub fn set_custom_property(
+        &mut self,
+        name: &str,
+        value: impl Into<PropertyValue>,
+    ) -> Result<&mut Self, XlsxError> {
+        let value = value.into();
+        self.edit_props_part(CUSTOM_KIND, CUSTOM_XML, |xml| {
+            ensure_root_ns(xml, "vt", NS_VT)?;
+            let q = format!("{}property", root_prefix(xml));
+            let (mut found, mut max_pid, mut from) = (None, 1, 0);
+            while let Some(r) = find_elem(xml, &q, from) {
+                from = r.end;
+                let gt = r.start + memchr::memchr(b'>', &xml[r.clone()]).unwrap_or(0);
+                let tag = &xml[r.start..=gt];
+                let pid: u32 = get_attr(tag, "pid")
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(0);
+                max_pid = max_pid.max(pid);
+                let same = get_attr(tag, "name")
+                    .is_some_and(|n| unescape(&n).is_ok_and(|n| n.to_lowercase() == name.to_lowercase()));
+                if same {
+                    found = Some((r, pid));
+                }
+            }
+            let pid = found.as_ref().map_or(max_pid + 1, |(_, pid)| *pid);
+            let elem = format!(
+                r#"<{q} fmtid="{CUSTOM_FMTID}" pid="{pid}" name="{}">{}</{q}>"#,
+                xml_escape(name),
+                value.vt_xml()
+            );
+            match found {
+                Some((r, _)) => {
+                    xml.splice(r, elem.into_bytes());
+                }
+                None => {
+                    let pos = root_end(xml)?;
+                    xml.splice(pos..pos, elem.into_bytes());
+                }
+            }
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Кто сохраняет книгу: save() пишет это имя в `cp:lastModifiedBy`
+    /// вместе с отметкой времени (см. [`XlsxEditor::set_stamp_modified`]).
+    pub fn set_last_modified_by(&mut self, name: &str) -> &mut Self {
+        self.modified_by = Some(name.to_owned());
+        self
+    }
+
+    /// Включает (`true`, по умолчанию) или выключает отметку save() в
+    /// docProps/core.xml: `dcterms:modified` — текущее время UTC, и
+    /// `cp:lastModifiedBy`, если задан [`XlsxEditor::set_last_modified_by`].
+    /// Выключают для воспроизводимых файлов (одинаковый вход — одинаковый архив).
+    pub fn set_stamp_modified(&mut self, enabled: bool) -> &mut Self {
+        self.stamp_modified = enabled;
+        self
+    }
+
+    /// Отметка save(): время изменения и автор правки. Книги без core.xml
+    /// не трогаются.
+    pub(crate) fn stamp_doc_modified(&mut self) -> Result<()> {
+        if !self.stamp_modified {
+            return Ok(());
+        }
+        let Some(path) = self.props_path(CORE_KIND)? else {
+            return Ok(());
+        };
+        let Some(mut xml) = self.read_part(&path)? else {
+            return Ok(());
+        };
+        ensure_core_ns(&mut xml)?;
+        if let Some(name) = &self.modified_by {
+            set_elem_text(&mut xml, "cp:lastModifiedBy", "", name)?;
+        }
+        set_w3cdtf(&mut xml, "dcterms:modified", &now_w3cdtf())?;
+        self.write_part(&path, xml);
+        Ok(())
+    }
+
+    /// Путь части свойств вида `kind` по связям пакета (`_rels/.rels`).
+    fn props_path(&mut self, kind: &str) -> Result<Option<String>> {
+        Ok(self
+            .rels_of("")?
+            .of_kind(kind)
+            .next()
+            .map(|r| r.target.clone()))
+    }
+
+    /// Правит часть свойств вида `kind`; если её нет — создаёт из `template`
+    /// со связью от пакета и ContentType.
+    fn edit_props_part(
+        &mut self,
+        kind: &str,
+        template: &str,
+        edit: impl FnOnce(&mut Vec<u8>) -> Result<()>,
+    ) -> Result<()> {
+        if let Some(path) = self.props_path(kind)?
+            && let Some(mut xml) = self.read_part(&path)?
+        {
+            edit(&mut xml)?;
+            self.write_part(&path, xml);
+            return Ok(());
+        }
+        let mut xml = template.as_bytes().to_vec();
+        if kind == CORE_KIND {
+            set_w3cdtf(&mut xml, "dcterms:created", &now_w3cdtf())?;
+        }
+        edit(&mut xml)?;
+        self.create_part("", kind, xml)?;
+        Ok(())
+    }
+}
+
+/// Ставит текст элемента `qname` (заменяя элемент целиком) или дописывает
+/// его в конец корня — порядок детей в частях свойств не важен.
+fn set_elem_text(xml: &mut Vec<u8>, qname: &str, attrs: &str, value: &str) -> Result<()> {
+    let elem = format!("<{qname}{attrs}>{}</{qname}>", xml_escape(value));
+    match find_elem(xml, qname, 0) {
+        Some(r) => {
+            xml.splice(r, elem.into_bytes());
+        }
+        None => {
+            let pos = root_end(xml)?;
+            xml.splice(pos..pos, elem.into_bytes());
+        }
+    }
+    Ok(())
+}
+
+/// Префиксы `cp:`, `dc:`, `dcterms:` объявлены на корне core.xml.
+fn ensure_core_ns(xml: &mut Vec<u8>) -> Result<()> {
+    ensure_root_ns(xml, "cp", NS_CP)?;
+    ensure_root_ns(xml, "dc", NS_DC)?;
+    ensure_root_ns(xml, "dcterms", NS_DCTERMS)
+}
+
+/// Дата `dcterms:created`/`dcterms:modified` с `xsi:type="dcterms:W3CDTF"`.
+fn set_w3cdtf(xml: &mut Vec<u8>, qname: &str, value: &str) -> Result<()> {
+    ensure_root_ns(xml, "xsi", NS_XSI)?;
+    set_elem_text(xml, qname, r#" xsi:type="dcterms:W3CDTF""#, value)
+}
+
+/// Позиция закрывающего тега корня; `<root/>` раскрывается.
+fn root_end(xml: &mut Vec<u8>) -> Result<usize> {
+    let r = root_tag_range(xml).context("root element not found")?;
+    if xml[r.end - 2] == b'/' {
+        let tag = &xml[r.start + 1..r.end - 2];
+        let name_len = tag
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(tag.len());
+        let close = format!("></{}>", String::from_utf8_lossy(&tag[..name_len]));
+        xml.splice(r.end - 2..r.end, close.into_bytes());
+        return Ok(r.end - 1);
+    }
+    memmem::rfind(xml, b"</").context("root end tag not found")
+}
+
+/// Текущее время UTC в W3CDTF: `2025-07-22T15:07:01Z`.
+fn now_w3cdtf() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // дни от 1970-01-01 → гражданская дата (алгоритм Х. Хиннанта)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!(
+        "{y:04}-{m:02}-{d:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
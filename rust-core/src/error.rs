@@ -0,0 +1,214 @@
+//! error.rs – типизированные ошибки библиотеки.
+//!
+//! Публичные методы возвращают `Result<_, XlsxError>`: основные случаи —
+//! отдельные варианты, всё прочее — [`XlsxError::Other`]. `anyhow` остаётся
+//! внутри крейта и наружу выходит только в этом варианте.
+
+use quick_xml::{Reader, events::Event};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, Cursor, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use zip::{ZipArchive, result::ZipError};
+
+/// Последняя строка листа Excel.
+pub(crate) const MAX_ROW: u32 = 1_048_576;
+
+/// Вид ошибки, по которому вызывающий код может ветвиться.
+#[derive(Debug)]
+pub enum XlsxError {
+    /// Листа с таким именем нет в workbook.xml.
+    SheetNotFound(String),
+    /// XML части не разбирается: `offset` — позиция в байтах.
+    MalformedSheet {
+        part: String,
+        offset: u64,
+        message: String,
+    },
+    /// Запись вышла бы за строку 1 048 576.
+    RowLimitExceeded { row: u64 },
+    /// Неверная ссылка на ячейку или диапазон.
+    InvalidRange(String),
+    /// Архив не открывается/не читается (включая ошибки ввода-вывода).
+    Zip(ZipError),
+    /// Остальные ошибки с цепочкой контекста.
+    Other(anyhow::Error),
+}
+
+impl fmt::Display for XlsxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XlsxError::SheetNotFound(name) => write!(f, "Sheet `{name}` not found in workbook.xml"),
+            XlsxError::MalformedSheet {
+                part,
+                offset,
+                message,
+            } => write!(f, "{part}: malformed XML at byte {offset}: {message}"),
+            XlsxError::RowLimitExceeded { row } => {
+                write!(f, "row {row} is past the sheet limit of {MAX_ROW} rows")
+            }
+            XlsxError::InvalidRange(r) => write!(f, "invalid cell reference or range: {r}"),
+            XlsxError::Zip(e) => write!(f, "zip error: {e}"),
+            // `{:#}` печатает и цепочку контекста, как у самого anyhow
+            XlsxError::Other(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for XlsxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XlsxError::Zip(e) => Some(e),
+            XlsxError::Other(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<ZipError> for XlsxError {
+    fn from(e: ZipError) -> Self {
+        XlsxError::Zip(e)
+    }
+}
+
+/// Внутренние ошибки крейта: типизированная ошибка внутри `anyhow` (в том
+/// числе под контекстом) достаётся обратно своим вариантом.
+impl From<anyhow::Error> for XlsxError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<XlsxError>() {
+            Ok(kind) => kind,
+            Err(e) => XlsxError::Other(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for XlsxError {
+    fn from(e: std::io::Error) -> Self {
+        XlsxError::Zip(ZipError::Io(e))
+    }
+}
+
+/// `anyhow::bail!` с `.into()`: годится и внутри крейта (`anyhow::Result`),
+/// и в публичных методах (`Result<_, XlsxError>`).
+macro_rules! bail {
+    ($($arg:tt)+) => {
+        return Err(anyhow::anyhow!($($arg)+).into())
+    };
+}
+pub(crate) use bail;
+
+/// `anyhow::ensure!` с тем же преобразованием ошибки, что у [`bail!`].
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::error::bail!($($arg)+);
+        }
+    };
+}
+pub(crate) use ensure;
+
+/// Открывает архив книги; ошибки — [`XlsxError::Zip`].
+pub(crate) fn open_zip(path: impl AsRef<Path>) -> Result<ZipArchive<File>, XlsxError> {
+    Ok(ZipArchive::new(File::open(path)?)?)
+}
+
+/// Исходный архив редактора: файл на диске или байты в памяти
+/// ([`crate::XlsxEditor::open_bytes`], расшифрованная книга).
+#[derive(Debug, Clone)]
+pub(crate) enum Source {
+    Path(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+pub(crate) enum SourceReader {
+    File(File),
+    Bytes(Cursor<Arc<[u8]>>),
+}
+
+impl Source {
+    /// Открывает архив заново; файл на диске не держится между вызовами.
+    pub(crate) fn open(&self) -> Result<ZipArchive<SourceReader>, XlsxError> {
+        Ok(ZipArchive::new(self.reader()?)?)
+    }
+
+    /// Байты архива с начала, без разбора.
+    pub(crate) fn reader(&self) -> io::Result<SourceReader> {
+        Ok(match self {
+            Source::Path(p) => SourceReader::File(File::open(p)?),
+            Source::Bytes(b) => SourceReader::Bytes(Cursor::new(b.clone())),
+        })
+    }
+
+    /// Пишет в `out` первые `len` байт архива. Файл в файл копирует ОС
+    /// (`copy_file_range` на Linux), без прохода через память процесса.
+    pub(crate) fn copy_head<W: Write>(&self, len: u64, out: &mut W) -> io::Result<()> {
+        let copied = match self {
+            Source::Path(p) => io::copy(&mut File::open(p)?.take(len), out)?,
+            Source::Bytes(b) => {
+                let head = &b[..b.len().min(len as usize)];
+                out.write_all(head)?;
+                head.len() as u64
+            }
+        };
+        if copied != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+impl Read for SourceReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            SourceReader::File(f) => f.read(buf),
+            SourceReader::Bytes(c) => c.read(buf),
+        }
+    }
+}
+
+impl Seek for SourceReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            SourceReader::File(f) => f.seek(pos),
+            SourceReader::Bytes(c) => c.seek(pos),
+        }
+    }
+}
+
+/// Проверяет, что номер строки не выходит за предел листа.
+pub(crate) fn check_row(row: u64) -> Result<(), XlsxError> {
+    if row > MAX_ROW as u64 {
+        return Err(XlsxError::RowLimitExceeded { row });
+    }
+    Ok(())
+}
+
+/// Следующее событие разбора части `part`. Битый XML — [`XlsxError::MalformedSheet`]
+/// с позицией ошибки, а не молчаливый конец цикла.
+pub(crate) fn next_event<'a>(
+    rdr: &mut Reader<&'a [u8]>,
+    part: &str,
+) -> Result<Event<'a>, XlsxError> {
+    rdr.read_event().map_err(|e| XlsxError::MalformedSheet {
+        part: part.to_owned(),
+        offset: rdr.error_position(),
+        message: e.to_string(),
+    })
+}
+
+/// То же, что [`next_event`], для потокового чтения части из архива.
+pub(crate) fn next_event_into<'b, R: BufRead>(
+    rdr: &mut Reader<R>,
+    buf: &'b mut Vec<u8>,
+    part: &str,
+) -> Result<Event<'b>, XlsxError> {
+    rdr.read_event_into(buf)
+        .map_err(|e| XlsxError::MalformedSheet {
+            part: part.to_owned(),
+            offset: rdr.error_position(),
+            message: e.to_string(),
+        })
+}
@@ -1,28 +1,41 @@
 /// files_part.rs
-use crate::{find_bytes_from, scan, XlsxEditor};
+use crate::error::{Source, SourceReader, XlsxError, bail, ensure, next_event};
+use crate::lazy_part::{LazyPart, STYLES, WORKBOOK, WORKBOOK_RELS};
+use crate::sheet_xml::{restore_prefix, strip_prefix};
+use crate::search::find_from;
+use crate::XlsxEditor;
 use ::zip as zip_crate;
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result};
 use memchr::memmem;
 use quick_xml::{Reader, events::Event};
 use std::{
+    borrow::Cow,
     fs::File,
-    io::{Read, Write},
+    io::{Cursor, Read, Seek, Write},
     path::Path,
+    sync::Arc,
 };
 
 /// Work with files
 impl XlsxEditor {
     /// Открывает книгу и подготавливает лист `sheet_id` (1‑based).
-    pub fn open_sheet<P: AsRef<Path>>(src: P, sheet_id: usize) -> Result<Self> {
-        let src_path = src.as_ref().to_path_buf();
-        let mut zip = zip_crate::ZipArchive::new(File::open(&src_path)?)?;
+    pub fn open_sheet<P: AsRef<Path>>(src: P, sheet_id: usize) -> Result<Self, XlsxError> {
+        let src = Source::Path(src.as_ref().to_path_buf());
+        Ok(Self::open_part(
+            src,
+            format!("xl/worksheets/sheet{sheet_id}.xml"),
+        )?)
+    }
+
+    /// Открывает книгу и подготавливает лист из части `sheet_path`.
+    pub(crate) fn open_part(src: Source, sheet_path: String) -> Result<Self> {
+        let mut zip = src.open()?;
 
         // ── sheet#.xml ───────────────────────────────────────────────
-        let sheet_path = format!("xl/worksheets/sheet{sheet_id}.xml");
 
         // читаем XML листа в отдельном блоке, чтобы `sheet` дропнулся,
         // и эксклюзивный займ `zip` освободился
-        let sheet_xml: Vec<u8> = {
+        let mut sheet_xml: Vec<u8> = {
             let mut sheet = zip
                 .by_name(&sheet_path)
                 .with_context(|| format!("{sheet_path} not found"))?;
@@ -31,196 +44,562 @@ impl XlsxEditor {
             buf
         };
 
-        // ── styles.xml ───────────────────────────────────────────────
-        let styles_xml: Vec<u8> = {
-            let mut styles = zip
-                .by_name("xl/styles.xml")
-                .context("styles.xml not found")?;
-            let mut buf = Vec::with_capacity(styles.size() as usize);
-            styles.read_to_end(&mut buf)?;
-            buf
-        };
-
-        // ── workbook.xml ───────────────────────────────────────────────
-        let workbook_xml: Vec<u8> = {
-            let mut wb = zip
-                .by_name("xl/workbook.xml")
-                .context("xl/workbook.xml not found")?;
-            let mut buf = Vec::with_capacity(wb.size() as usize);
-            wb.read_to_end(&mut buf)?;
-            buf
-        };
+        let sheet_prefix = strip_prefix(&mut sheet_xml);
 
-        // ── workbook.xml.rels ──────────────────────────────────────────
-        let rels_xml: Vec<u8> = {
-            let mut rels = zip
-                .by_name("xl/_rels/workbook.xml.rels")
-                .context("xl/_rels/workbook.xml.rels not found")?;
-            let mut buf = Vec::with_capacity(rels.size() as usize);
-            rels.read_to_end(&mut buf)?;
-            buf
-        };
+        // styles.xml, workbook.xml и его связи читаются при первом обращении
+        // (см. [`LazyPart`]); здесь только проверяем, что они есть в архиве
+        for part in [STYLES, WORKBOOK, WORKBOOK_RELS] {
+            ensure!(zip.index_for_name(part).is_some(), "{part} not found");
+        }
+        let source_pivots = zip.file_names().any(|n| n.starts_with("xl/pivotCache/"));
 
         // ── вычисляем last_row ───────────────────────────────────────
-        let mut reader = Reader::from_reader(sheet_xml.as_slice());
-        // check_utf8(&mut reader)?;
-        reader.config_mut().trim_text(true);
-
-        let mut last_row = 0;
-        while let Ok(ev) = reader.read_event() {
-            match ev {
-                Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"row" => {
-                    if let Some(r) = e.attributes().with_checks(false).flatten().find_map(|a| {
-                        (a.key.as_ref() == b"r")
-                            .then(|| String::from_utf8_lossy(&a.value).into_owned())
-                    }) {
-                        last_row = r.parse::<u32>().unwrap_or(last_row);
-                    }
-                }
-                Event::Eof => break,
-                _ => {}
-            }
-        }
+        let last_row = calc_last_row(&sheet_path, &sheet_xml)?;
 
         Ok(Self {
-            src_path,
+            src,
             sheet_path,
-            sheet_xml,
+            sheet_xml: sheet_xml.into(),
             last_row,
-            styles_xml,
-            workbook_xml,
-            rels_xml,
+            styles_xml: LazyPart::new(STYLES),
+            workbook_xml: LazyPart::new(WORKBOOK),
+            rels_xml: LazyPart::new(WORKBOOK_RELS),
+            source_pivots,
             new_files: Vec::new(),
             styles_index: None,
             loaded_files: std::collections::HashMap::new(), // ← добавлено
+            shared_strings: None,
+            removed_parts: std::collections::HashSet::new(),
+            recalc_on_change: false,
+            row_spans: false,
+            inherit_col_styles: false,
+            preserve_zip_metadata: false,
+            compression_threads: 0,
+            allow_macro_removal: false,
+            stamp_modified: true,
+            modified_by: None,
+            sheet_prefix,
+            xml_layout: Default::default(),
+            savepoints: Vec::new(),
+            appended_rows: Default::default(),
+            pending_growth: None,
         })
     }
 
-    fn flush_current_sheet(&mut self) {
-        let cur_path = self.sheet_path.clone();
-        let cur_xml = self.sheet_xml.clone();
-        if let Some((_, c)) = self.new_files.iter_mut().find(|(p, _)| p == &cur_path) {
-            *c = cur_xml;
+    /// Возвращает содержимое части архива `path` с учётом несохранённых правок:
+    /// сперва `new_files`, потом кэш `loaded_files`, иначе читает из ZIP и кэширует.
+    /// `Ok(None)`, если такой части нет.
+    pub(crate) fn read_part(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        if self.removed_parts.contains(path) {
+            return Ok(None);
+        }
+        if path == self.sheet_path {
+            return Ok(Some(self.current_sheet_xml().into_owned()));
+        }
+        if let Some((_, content)) = self.new_files.iter().find(|(p, _)| p == path) {
+            return Ok(Some(content.to_vec()));
+        }
+        self.source_part(path)
+    }
+
+    /// Исходное содержимое части `path` в архиве, без несохранённых правок (кэшируется).
+    pub(crate) fn source_part(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(buf) = self.loaded_files.get(path) {
+            return Ok(Some(buf.clone()));
+        }
+        let mut zin = self.src.open()?;
+        let mut f = match zin.by_name(path) {
+            Ok(f) => f,
+            Err(zip_crate::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut buf = Vec::with_capacity(f.size() as usize);
+        f.read_to_end(&mut buf)?;
+        self.loaded_files.insert(path.to_owned(), buf.clone());
+        Ok(Some(buf))
+    }
+
+    /// Совпадает ли часть `path` исходного архива с `content`; `None` — такой
+    /// части в исходном архиве нет. Часть не кэшируется и не копируется.
+    pub(crate) fn same_as_source(&self, path: &str, content: &[u8]) -> Result<Option<bool>> {
+        let mut zin = self.src.open()?;
+        let Some(i) = zin.index_for_name(path) else {
+            return Ok(None);
+        };
+        Ok(Some(same_as_entry(&mut zin, i, content)))
+    }
+
+    /// Кладёт новую версию части `path` в `new_files` (заменяя прежнюю).
+    pub(crate) fn write_part(&mut self, path: &str, content: Vec<u8>) {
+        self.removed_parts.remove(path);
+        if let Some((_, c)) = self.new_files.iter_mut().find(|(p, _)| p == path) {
+            *c = content.into();
+        } else {
+            self.new_files.push((path.to_owned(), content.into()));
+        }
+    }
+
+    /// Имена всех частей книги: исходный архив плюс ещё не сохранённые новые части.
+    pub(crate) fn part_names(&self) -> Result<Vec<String>> {
+        let zin = self.src.open()?;
+        let mut names: Vec<String> = zin.file_names().map(str::to_owned).collect();
+        for (p, _) in &self.new_files {
+            if !names.contains(p) {
+                names.push(p.clone());
+            }
+        }
+        names.retain(|n| !self.removed_parts.contains(n));
+        Ok(names)
+    }
+
+    /// Убирает часть `path` из книги: при save() она не будет записана.
+    pub(crate) fn remove_part(&mut self, path: &str) {
+        self.new_files.retain(|(p, _)| p != path);
+        self.loaded_files.remove(path);
+        self.removed_parts.insert(path.to_owned());
+    }
+
+    /// XML текущего листа в том виде, в каком он ляжет в архив: с исходным
+    /// префиксом элементов (см. [`strip_prefix`]). Копия только для листа
+    /// с префиксом.
+    pub(crate) fn current_sheet_xml(&self) -> Cow<'_, [u8]> {
+        if self.sheet_prefix.is_empty() {
+            return Cow::Borrowed(&self.sheet_xml);
+        }
+        let mut xml = self.sheet_xml.clone();
+        restore_prefix(&mut xml, &self.sheet_prefix);
+        Cow::Owned(xml)
+    }
+
+    /// Перед переключением листа: буфер текущего листа переезжает в
+    /// `new_files` без копии, `sheet_xml` остаётся пустым. Запись в
+    /// `new_files` для текущего листа — только место под него: содержимое
+    /// берётся из `sheet_xml`.
+    fn park_current_sheet(&mut self) {
+        if self.sheet_path.is_empty() {
+            return;
+        }
+        let mut xml = self.sheet_xml.take();
+        restore_prefix(&mut xml, &self.sheet_prefix);
+        if let Some((_, c)) = self.new_files.iter_mut().find(|(p, _)| *p == self.sheet_path) {
+            *c = xml.into();
         } else {
-            self.new_files.push((cur_path, cur_xml));
+            self.new_files.push((self.sheet_path.clone(), xml.into()));
         }
     }
 
-    pub fn save<P: AsRef<Path>>(&mut self, dst: P) -> Result<()> {
-        
-        self.flush_current_sheet();
-        let mut zin = zip_crate::ZipArchive::new(File::open(&self.src_path)?)?;
-        let mut zout = zip_crate::ZipWriter::new(File::create(dst)?);
+    /// Включает (`true`) сохранение метаданных архива при save(): переписанные
+    /// части получают время изменения, права и метод сжатия исходной записи,
+    /// новые — время первой записи архива, комментарий архива переносится.
+    /// Так diff сгенерированных файлов показывает только правки. Части, которые
+    /// редактор не менял (макросы, customXml, кэши сводных, внешние ссылки,
+    /// printerSettings…), копируются байт в байт всегда.
+    pub fn set_preserve_zip_metadata(&mut self, enabled: bool) -> &mut Self {
+        self.preserve_zip_metadata = enabled;
+        self
+    }
+
+    /// Сколько потоков сжимают переписанные части при save() (фича `rayon`):
+    /// `0` — общий пул rayon, `1` — по очереди в текущем потоке, больше —
+    /// отдельный пул на время сохранения. Нетронутые части копируются без
+    /// пересжатия в любом случае. Без `rayon` сжатие всегда однопоточное.
+    pub fn set_compression_threads(&mut self, threads: usize) -> &mut Self {
+        self.compression_threads = threads;
+        self
+    }
+
+    pub fn save<P: AsRef<Path>>(&mut self, dst: P) -> Result<(), XlsxError> {
+        self.prepare_macro_target(dst.as_ref())?;
+        // на чтение тоже: после перенесённого начала архива zip дочитывает его каталог
+        let out = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dst)?;
+        Ok(self.write_package(out)?)
+    }
 
-        let deflated: zip_crate::write::FileOptions<'_, ()> =
-            zip_crate::write::FileOptions::default()
-                .compression_method(zip_crate::CompressionMethod::Deflated)
-                .compression_level(Some(1));
+    /// Как [`Self::save`], но архив собирается в памяти; тип книги (xlsx/xlsm)
+    /// остаётся как у исходного файла.
+    pub fn save_to_bytes(&mut self) -> Result<Vec<u8>, XlsxError> {
+        let mut package = std::io::Cursor::new(Vec::new());
+        self.write_package(&mut package)?;
+        Ok(package.into_inner())
+    }
 
-        let stored: zip_crate::write::FileOptions<'_, ()> =
-            zip_crate::write::FileOptions::default()
-                .compression_method(zip_crate::CompressionMethod::Stored);
+    /// Пишет архив книги со всеми правками в `out` (файл save() или буфер
+    /// в памяти, который потом шифруется). Нетронутые части в начале архива
+    /// переносятся одним куском (см. [`leading_entries`]), остальное пишется
+    /// по частям.
+    pub(crate) fn write_package<W: Read + Write + Seek>(&mut self, mut out: W) -> Result<()> {
+        self.finish_modified_sheets()?;
+        self.stamp_doc_modified()?;
+        if self.recalc_on_change {
+            self.recalc_if_values_changed()?;
+        }
+        let mut zin = self.src.open()?;
+        // время для частей, которых не было в исходном архиве
+        let new_part_time = match zin.len() {
+            0 => None,
+            _ => zin.by_index_raw(0)?.last_modified(),
+        };
 
         use std::collections::HashSet;
         let mut written: HashSet<String> = HashSet::new();
+        let mut steps: Vec<Step<'_>> = Vec::with_capacity(zin.len() + self.new_files.len());
 
         for i in 0..zin.len() {
-            let file = zin.by_index_raw(i)?;
-            let name = file.name();
-
-            // Если есть новая версия файла — пишем её
-            if let Some((_, content)) = self.new_files.iter().find(|(p, _)| p == name) {
-                let opt = if should_store_uncompressed(name, content.len()) {
-                    stored
-                } else {
-                    deflated
-                };
-                zout.start_file(name, opt)?;
-                zout.write_all(content)?;
-                written.insert(name.to_string());
+            let name = zin.by_index_raw(i)?.name().to_owned();
+            if self.removed_parts.contains(&name) {
                 continue;
             }
 
-            match name {
-                "xl/workbook.xml" => {
-                    let content = &self.workbook_xml;
-                    let opt = if should_store_uncompressed(name, content.len()) {
-                        stored
-                    } else {
-                        deflated
-                    };
-                    zout.start_file(name, opt)?;
-                    zout.write_all(content)?;
-                }
-                "xl/_rels/workbook.xml.rels" => {
-                    let content = &self.rels_xml;
-                    let opt = if should_store_uncompressed(name, content.len()) {
-                        stored
-                    } else {
-                        deflated
-                    };
-                    zout.start_file(name, opt)?;
-                    zout.write_all(content)?;
-                }
-                _ if name == self.sheet_path => {
-                    let content = &self.sheet_xml;
-                    let opt = if should_store_uncompressed(name, content.len()) {
-                        stored
-                    } else {
-                        deflated
-                    };
-                    zout.start_file(name, opt)?;
-                    zout.write_all(content)?;
-                }
-                "xl/styles.xml" => {
-                    let mut content = self.styles_xml.clone();
-                    normalize_styles_root(&mut content);
-
-                    let opt = if should_store_uncompressed(name, content.len()) {
-                        stored
-                    } else {
-                        deflated
-                    };
-                    zout.start_file(name, opt)?;
-                    zout.write_all(&content)?;
+            // Если есть новая версия файла — пишем её
+            let content: Option<Cow<'_, [u8]>> = if name == self.sheet_path {
+                written.insert(name.clone());
+                Some(self.current_sheet_xml())
+            } else if let Some((_, content)) = self.new_files.iter().find(|(p, _)| *p == name) {
+                written.insert(name.clone());
+                Some(Cow::Borrowed(content))
+            } else {
+                // непрочитанные части книги не менялись и уходят как есть
+                match name.as_str() {
+                    WORKBOOK => self.workbook_xml.loaded().map(|x| Cow::Borrowed(&x[..])),
+                    WORKBOOK_RELS => self.rels_xml.loaded().map(|x| Cow::Borrowed(&x[..])),
+                    STYLES => self.styles_xml.loaded().map(|x| {
+                        let mut content = x.clone();
+                        normalize_styles_root(&mut content);
+                        Cow::Owned(content)
+                    }),
+                    "xl/calcChain.xml" => continue,
+                    _ => None,
                 }
-                "xl/calcChain.xml" => {
-                    continue;
+            };
+
+            // неизменённая часть уходит как есть, с исходным сжатием
+            let Some(content) = content.filter(|c| !same_as_entry(&mut zin, i, c)) else {
+                steps.push(Step::Copy(i));
+                continue;
+            };
+            let meta = if self.preserve_zip_metadata {
+                let file = zin.by_index_raw(i)?;
+                EntryMeta {
+                    time: file.last_modified(),
+                    mode: file.unix_mode(),
+                    stored: Some(file.compression() == zip_crate::CompressionMethod::Stored),
                 }
-                _ => zout.raw_copy_file(file)?,
-            }
+            } else {
+                EntryMeta::default()
+            };
+            let (opt, deflate) = entry_options(&name, &content, meta);
+            steps.push(Step::Write(Part {
+                name,
+                content,
+                opt,
+                deflate,
+            }));
         }
 
         // дозапись новых файлов, которых не было в исходном архиве
         for (path, content) in &self.new_files {
             if !written.contains(path) {
-                let opt = if should_store_uncompressed(path, content.len()) {
-                    stored
-                } else {
-                    deflated
+                let meta = EntryMeta {
+                    time: new_part_time.filter(|_| self.preserve_zip_metadata),
+                    ..Default::default()
                 };
-                zout.start_file(path, opt)?;
-                if path == &self.sheet_path {
-                    zout.write_all(&self.sheet_xml)?;
+                let content = if path == &self.sheet_path {
+                    self.current_sheet_xml()
                 } else {
-                    zout.write_all(content)?;
-                }
+                    Cow::Borrowed(content.as_slice())
+                };
+                let (opt, deflate) = entry_options(path, &content, meta);
+                steps.push(Step::Write(Part {
+                    name: path.clone(),
+                    content,
+                    opt,
+                    deflate,
+                }));
                 written.insert(path.clone());
             }
         }
 
+        // начало архива без правок копируется как есть, вместе с его каталогом
+        let same_head = steps
+            .iter()
+            .enumerate()
+            .take_while(|(k, s)| matches!(s, Step::Copy(i) if i == k))
+            .count();
+        let (mut zout, copied) = match leading_entries(&self.src, &mut zin, same_head)? {
+            Some(head) => {
+                self.src.copy_head(head.len, &mut out)?;
+                out.write_all(&head.central)?;
+                (zip_crate::ZipWriter::new_append(out)?, same_head)
+            }
+            None => (zip_crate::ZipWriter::new(out), 0),
+        };
+        if self.preserve_zip_metadata {
+            zout.set_raw_comment(zin.comment().into());
+        }
+
+        let mut packed = self.deflate_parts(&steps)?.into_iter();
+        for step in &steps[copied..] {
+            match step {
+                Step::Copy(i) => zout.raw_copy_file(zin.by_index_raw(*i)?)?,
+                Step::Write(part) => match packed.next().flatten() {
+                    Some(archive) => {
+                        let mut single = zip_crate::ZipArchive::new(Cursor::new(archive))?;
+                        zout.raw_copy_file(single.by_index_raw(0)?)?;
+                    }
+                    None => {
+                        zout.start_file(part.name.as_str(), part.opt)?;
+                        zout.write_all(&part.content)?;
+                    }
+                },
+            }
+        }
+
         zout.finish()?;
         Ok(())
     }
+
+    /// Для каждой `Step::Write` из `steps` — архив из одной этой части, уже
+    /// сжатой, или `None`, если часть пишется прямо в выходной архив. Сжатие
+    /// идёт параллельно, когда это имеет смысл (см. [`Self::set_compression_threads`]).
+    #[cfg(feature = "rayon")]
+    fn deflate_parts(&self, steps: &[Step<'_>]) -> Result<Vec<Option<Vec<u8>>>> {
+        use rayon::prelude::*;
+        let parts: Vec<&Part<'_>> = steps
+            .iter()
+            .filter_map(|s| match s {
+                Step::Write(p) => Some(p),
+                Step::Copy(_) => None,
+            })
+            .collect();
+        if self.compression_threads == 1 || parts.iter().filter(|p| p.deflate).count() < 2 {
+            return Ok(vec![None; parts.len()]);
+        }
+        let run = || {
+            parts
+                .par_iter()
+                .map(|p| p.deflate.then(|| single_part_archive(p)).transpose())
+                .collect::<Result<Vec<_>>>()
+        };
+        match self.compression_threads {
+            0 => run(),
+            n => rayon::ThreadPoolBuilder::new()
+                .num_threads(n)
+                .build()?
+                .install(run),
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn deflate_parts(&self, steps: &[Step<'_>]) -> Result<Vec<Option<Vec<u8>>>> {
+        Ok(vec![None; steps.iter().filter(|s| matches!(s, Step::Write(_))).count()])
+    }
+}
+
+/// Запись выходного архива в порядке исходного.
+enum Step<'a> {
+    /// Запись `i` исходного архива байт в байт.
+    Copy(usize),
+    Write(Part<'a>),
+}
+
+/// Переписанная или новая часть с параметрами записи.
+struct Part<'a> {
+    name: String,
+    content: Cow<'a, [u8]>,
+    opt: zip_crate::write::FileOptions<'static, ()>,
+    /// Сжимается Deflate (иначе пишется как есть).
+    #[cfg_attr(not(feature = "rayon"), allow(dead_code))]
+    deflate: bool,
+}
+
+/// Начало исходного архива, которое [`XlsxEditor::write_package`] переносит
+/// в выходной без разбора.
+struct LeadingEntries {
+    /// Сколько байт с начала файла занимают перенесённые записи.
+    len: u64,
+    /// Их записи центрального каталога байт в байт и конец каталога (EOCD)
+    /// для них одних: с ним начало — уже целый архив для дозаписи.
+    central: Vec<u8>,
+}
+
+/// Первые `n` записей `zin`, если их можно перенести в новый архив одним
+/// куском: данные лежат подряд с начала файла, раньше всех остальных
+/// записей, а записи каталога идут в том же порядке. `None` — нельзя
+/// (архив с префиксом, zip64 — в EOCD или в самих записях каталога, записи
+/// вразнобой) или нечего переносить.
+fn leading_entries(
+    src: &Source,
+    zin: &mut zip_crate::ZipArchive<SourceReader>,
+    n: usize,
+) -> Result<Option<LeadingEntries>> {
+    if n == 0 || zin.offset() != 0 || zin.zip64_comment().is_some() || n > u16::MAX as usize {
+        return Ok(None);
+    }
+    let mut local = Vec::with_capacity(n);
+    let mut central_at = Vec::with_capacity(n);
+    let mut len = u64::MAX;
+    for i in 0..zin.len() {
+        let f = zin.by_index_raw(i)?;
+        if i < n {
+            local.push(f.header_start());
+            central_at.push(f.central_header_start());
+        } else {
+            len = len.min(f.header_start());
+        }
+    }
+    if len == u64::MAX {
+        // все записи переносятся: кончаются там, где начинается каталог
+        len = central_at[0];
+    }
+    let in_order = local[0] == 0
+        && local.windows(2).all(|w| w[0] < w[1])
+        && local[n - 1] < len
+        && len < u32::MAX as u64;
+    if !in_order {
+        return Ok(None);
+    }
+
+    let mut reader = src.reader()?;
+    reader.seek(std::io::SeekFrom::Start(central_at[0]))?;
+    let mut central = Vec::new();
+    for &at in &central_at {
+        const FIXED: usize = 46;
+        if central.len() as u64 != at - central_at[0] {
+            return Ok(None);
+        }
+        let start = central.len();
+        central.resize(start + FIXED, 0);
+        reader.read_exact(&mut central[start..])?;
+        let rec = &central[start..];
+        let field = |at: usize| u16::from_le_bytes([rec[at], rec[at + 1]]) as usize;
+        ensure!(rec[..4] == *b"PK\x01\x02", "broken central directory");
+        let tail = field(28) + field(30) + field(32);
+        central.resize(start + FIXED + tail, 0);
+        reader.read_exact(&mut central[start + FIXED..])?;
+        // смещения zip64 в записи не сходятся с EOCD, который пишется ниже
+        if has_zip64_fields(&central[start..]) {
+            return Ok(None);
+        }
+    }
+
+    let size = central.len() as u32;
+    central.extend_from_slice(b"PK\x05\x06");
+    central.extend_from_slice(&[0; 4]); // номер диска и диска с каталогом
+    central.extend_from_slice(&(n as u16).to_le_bytes());
+    central.extend_from_slice(&(n as u16).to_le_bytes());
+    central.extend_from_slice(&size.to_le_bytes());
+    central.extend_from_slice(&(len as u32).to_le_bytes());
+    central.extend_from_slice(&0u16.to_le_bytes()); // без комментария
+    Ok(Some(LeadingEntries { len, central }))
+}
+
+/// Запись центрального каталога `rec` с полями zip64: размер, смещение или
+/// номер диска-заглушка (`0xFFFFFFFF`/`0xFFFF`) либо дополнительное поле
+/// `0x0001`. Такие записи бывают и без zip64 EOCD.
+fn has_zip64_fields(rec: &[u8]) -> bool {
+    let u16_at = |at: usize| u16::from_le_bytes([rec[at], rec[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes([rec[at], rec[at + 1], rec[at + 2], rec[at + 3]]);
+    if [20, 24, 42].into_iter().any(|at| u32_at(at) == u32::MAX) || u16_at(34) == u16::MAX {
+        return true;
+    }
+    let extra_at = 46 + u16_at(28) as usize;
+    let mut extra = &rec[extra_at..extra_at + u16_at(30) as usize];
+    while let [a, b, c, d, rest @ ..] = extra {
+        if u16::from_le_bytes([*a, *b]) == 0x0001 {
+            return true;
+        }
+        let len = u16::from_le_bytes([*c, *d]) as usize;
+        extra = rest.get(len..).unwrap_or_default();
+    }
+    false
+}
+
+/// Архив в памяти с одной частью `p`: сжатая запись потом копируется в
+/// выходной архив через `raw_copy_file`, без повторного сжатия.
+#[cfg(feature = "rayon")]
+fn single_part_archive(p: &Part<'_>) -> Result<Vec<u8>> {
+    let mut zip = zip_crate::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(p.name.as_str(), p.opt)?;
+    zip.write_all(&p.content)?;
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Метаданные исходной записи, переносимые при `preserve_zip_metadata`.
+#[derive(Default)]
+struct EntryMeta {
+    time: Option<zip_crate::DateTime>,
+    mode: Option<u32>,
+    /// Метод сжатия исходной записи: `Some(true)` — без сжатия.
+    stored: Option<bool>,
+}
+
+/// Параметры записи переписанной части и сжимается ли она. Без исходного
+/// метода маленький XML пишется без сжатия, остальное — быстрым Deflate.
+fn entry_options(
+    name: &str,
+    content: &[u8],
+    meta: EntryMeta,
+) -> (zip_crate::write::FileOptions<'static, ()>, bool) {
+    let stored = meta
+        .stored
+        .unwrap_or_else(|| should_store_uncompressed(name, content.len()));
+    let mut opt = if stored {
+        zip_crate::write::FileOptions::default()
+            .compression_method(zip_crate::CompressionMethod::Stored)
+    } else {
+        zip_crate::write::FileOptions::default()
+            .compression_method(zip_crate::CompressionMethod::Deflated)
+            .compression_level(Some(1))
+    };
+    if let Some(time) = meta.time {
+        opt = opt.last_modified_time(time);
+    }
+    if let Some(mode) = meta.mode {
+        opt = opt.unix_permissions(mode);
+    }
+    (opt, !stored)
+}
+
+/// Совпадает ли `content` с распакованной записью `i` исходного архива.
+pub(crate) fn same_as_entry(zin: &mut zip_crate::ZipArchive<SourceReader>, i: usize, content: &[u8]) -> bool {
+    let Ok(file) = zin.by_index(i) else {
+        return false;
+    };
+    file.size() == content.len() as u64 && reads_equal(file, content)
+}
+
+/// Читает `r` кусками и сравнивает с `content`, не собирая часть в память
+/// (лист может весить сотни мегабайт).
+fn reads_equal(mut r: impl Read, mut content: &[u8]) -> bool {
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        match r.read(&mut buf) {
+            Ok(0) => return content.is_empty(),
+            Ok(n) => {
+                if n > content.len() || buf[..n] != content[..n] {
+                    return false;
+                }
+                content = &content[n..];
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return false,
+        }
+    }
 }
 
 impl XlsxEditor {
     /// Считает количество листов по текущему состоянию `workbook_xml`
-    fn sheet_count(&self) -> usize {
-        let mut rdr = Reader::from_reader(self.workbook_xml.as_slice());
+    fn sheet_count(&self) -> Result<usize> {
+        let mut rdr = Reader::from_reader(self.workbook_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
         let mut n = 0usize;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, "xl/workbook.xml")?;
             match ev {
                 Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"sheet" => {
                     n += 1;
@@ -229,7 +608,7 @@ impl XlsxEditor {
                 _ => {}
             }
         }
-        n
+        Ok(n)
     }
 
     /// Возвращает (позиция_начала_контента, позиция_конца_контента) для содержимого между
@@ -255,32 +634,37 @@ impl XlsxEditor {
 
     /// Добавляет новый пустой лист c именем `sheet_name` **на позицию `index` (0‑based)**,
     /// пересобирая порядок `<sheet/>` в workbook.xml.
-    pub fn add_worksheet_at(&mut self, sheet_name: &str, mut index: usize) -> Result<&mut Self> {
+    pub fn add_worksheet_at(
+        &mut self,
+        sheet_name: &str,
+        mut index: usize,
+    ) -> Result<&mut Self, XlsxError> {
         // -------- 0) валидации / подготовка ----------
         // 0.1) имя уже существует?
-        let sheet_names = scan(&self.src_path)?;
-        if sheet_names.contains(&sheet_name.to_owned()) {
+        let sheet_names = self.sheet_entries()?;
+        if sheet_names.iter().any(|s| s.name == sheet_name) {
             bail!("Sheet {} already exists", sheet_name);
         }
 
         // 0.2) текущее количество листов
-        let cur_cnt = self.sheet_count();
+        let cur_cnt = self.sheet_count()?;
         if index > cur_cnt {
             index = cur_cnt; // кладём в конец
         }
 
         // 0.3) читаем исходный архив (для поиска свободного sheet#.xml)
-        let mut zin = zip::ZipArchive::new(File::open(&self.src_path)?)?;
+        let mut zin = self.src.open()?;
 
         // 0.4) локальные (редактируемые) копии XML
-        let mut wb_xml = self.workbook_xml.clone();
-        let mut rels_xml = self.rels_xml.clone();
+        let mut wb_xml = self.workbook_xml.get(&self.src)?.clone();
+        let mut rels_xml = self.rels_xml.get(&self.src)?.clone();
 
         // -------- 1) найдём max sheetId и max rId ----------
         let mut max_sheet_id = 0u32;
         let mut rdr = Reader::from_reader(wb_xml.as_slice());
         rdr.config_mut().trim_text(true);
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, "xl/workbook.xml")?;
             if let Event::Empty(ref e) | Event::Start(ref e) = ev {
                 if e.name().as_ref() == b"sheet" {
                     if let Some(id) = e.attributes().with_checks(false).flatten().find_map(|a| {
@@ -301,7 +685,8 @@ impl XlsxEditor {
         let mut max_rid = 0u32;
         let mut rdr = Reader::from_reader(rels_xml.as_slice());
         rdr.config_mut().trim_text(true);
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, "xl/_rels/workbook.xml.rels")?;
             if let Event::Empty(ref e) | Event::Start(ref e) = ev {
                 if e.name().as_ref() == b"Relationship" {
                     if let Some(id) = e.attributes().with_checks(false).flatten().find_map(|a| {
@@ -351,6 +736,7 @@ impl XlsxEditor {
         struct SheetTag {
             name: String,
             rid: String,  // "rIdNN"
+            state: Option<String>, // hidden / veryHidden
             path: String, // worksheets/sheet#.xml (нам нужно только для инфы; можно не хранить)
         }
         let (sheets_content_start, sheets_content_end) = Self::find_sheets_section(&wb_xml)?;
@@ -360,25 +746,34 @@ impl XlsxEditor {
         rdr.config_mut().trim_text(true);
         let mut sheets: Vec<SheetTag> = Vec::new();
 
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, "xl/workbook.xml")?;
             match ev {
                 Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"sheet" => {
                     let mut name = None;
                     let mut rid = None;
+                    let mut state = None;
                     // Target пути тут нет — он в rels, так что просто пустим.
                     for a in e.attributes().with_checks(false).flatten() {
                         let k = a.key.as_ref();
-                        let v = String::from_utf8_lossy(&a.value).into_owned();
+                        let v = a
+                            .unescape_value()
+                            .map_err(anyhow::Error::from)?
+                            .into_owned();
                         if k == b"name" {
                             name = Some(v.clone());
                         }
                         if k == b"r:id" {
-                            rid = Some(v);
+                            rid = Some(v.clone());
+                        }
+                        if k == b"state" {
+                            state = Some(v);
                         }
                     }
                     sheets.push(SheetTag {
                         name: name.unwrap_or_default(),
                         rid: rid.unwrap_or_default(),
+                        state,
                         path: String::new(),
                     });
                 }
@@ -391,6 +786,7 @@ impl XlsxEditor {
         let new_sheet = SheetTag {
             name: sheet_name.to_string(),
             rid: format!("rId{}", new_rid),
+            state: None,
             path: new_sheet_target.clone(),
         };
 
@@ -406,10 +802,16 @@ impl XlsxEditor {
         // Сохраним форматирование: перенос строки + два пробела
         for (i, sh) in sheets.iter().enumerate() {
             let sheet_id = (i as u32) + 1; // «естественная» нумерация
+            let state = sh
+                .state
+                .as_ref()
+                .map(|s| format!(" state=\"{}\"", xml_escape(s)))
+                .unwrap_or_default();
             let line = format!(
-                "\n  <sheet name=\"{}\" sheetId=\"{}\" r:id=\"{}\"/>",
+                "\n  <sheet name=\"{}\" sheetId=\"{}\"{} r:id=\"{}\"/>",
                 xml_escape(&sh.name),
                 sheet_id,
+                state,
                 sh.rid
             );
             new_inner.extend_from_slice(line.as_bytes());
@@ -439,71 +841,52 @@ impl XlsxEditor {
         </worksheet>"#;
 
         // Обновляем внутреннее состояние
-        self.workbook_xml = wb_xml;
-        self.rels_xml = rels_xml;
+        self.workbook_xml.set(wb_xml);
+        self.rels_xml.set(rels_xml);
+        self.register_part(&new_sheet_path, "worksheet")?;
 
         // кладём текущий редактируемый лист в new_files (если ещё не лежит)
-        {
-            let cur_path = self.sheet_path.clone();
-            let cur_xml = self.sheet_xml.clone();
-            if let Some(pair) = self.new_files.iter_mut().find(|(p, _)| p == &cur_path) {
-                pair.1 = cur_xml;
-            } else {
-                self.new_files.push((cur_path, cur_xml));
-            }
-        }
+        self.flush_appended_ranges()?;
+        self.park_current_sheet();
 
-        // создаём запись для нового листа
+        // место в new_files для нового листа; сам XML — в sheet_xml
         if let Some(pair) = self
             .new_files
             .iter_mut()
             .find(|(p, _)| p == &new_sheet_path)
         {
-            pair.1 = EMPTY_SHEET.as_bytes().to_vec();
+            pair.1 = Default::default();
         } else {
-            self.new_files
-                .push((new_sheet_path.clone(), EMPTY_SHEET.as_bytes().to_vec()));
+            self.new_files.push((new_sheet_path.clone(), Default::default()));
         }
 
         // переключаем редактор на новый лист
         self.sheet_path = new_sheet_path;
-        self.sheet_xml = EMPTY_SHEET.as_bytes().to_vec();
+        self.sheet_xml = EMPTY_SHEET.as_bytes().to_vec().into();
+        self.sheet_prefix = String::new();
         self.last_row = 0;
+        let path = self.sheet_path.clone();
+        self.ensure_code_name(&path)?;
 
         Ok(self)
     }
 
     /// Старый API: просто добавляет в конец.
-    pub fn add_worksheet(&mut self, sheet_name: &str) -> Result<&mut Self> {
-        let last_idx = self.sheet_count(); // вставка в конец
+    pub fn add_worksheet(&mut self, sheet_name: &str) -> Result<&mut Self, XlsxError> {
+        let last_idx = self.sheet_count()?; // вставка в конец
         self.add_worksheet_at(sheet_name, last_idx)
     }
 }
 
 impl XlsxEditor {
-    pub fn with_worksheet(&mut self, sheet_name: &str) -> Result<&mut Self> {
-        // 0) Если уже на этом листе — просто вернуть себя (опционально).
-        // У нас нет текущего имени, так что пропустим эту оптимизацию.
-
-        // 1) Сохраним текущий лист в new_files (как в add_worksheet_at)
-        {
-            let cur_path = self.sheet_path.clone();
-            let cur_xml = self.sheet_xml.clone();
-            if !cur_path.is_empty() {
-                if let Some(pair) = self.new_files.iter_mut().find(|(p, _)| p == &cur_path) {
-                    pair.1 = cur_xml;
-                } else {
-                    self.new_files.push((cur_path, cur_xml));
-                }
-            }
-        }
-
-        // 2) Найти r:id по имени листа в workbook.xml
-        let mut rdr = Reader::from_reader(self.workbook_xml.as_slice());
+    pub fn with_worksheet(&mut self, sheet_name: &str) -> Result<&mut Self, XlsxError> {
+        // 1) Найти r:id по имени листа в workbook.xml
+        let mut rdr = Reader::from_reader(self.workbook_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
 
         let mut target_rid: Option<String> = None;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, "xl/workbook.xml")?;
             match ev {
                 Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"sheet" => {
                     let mut name: Option<String> = None;
@@ -532,15 +915,16 @@ impl XlsxEditor {
             }
         }
 
-        let target_rid = target_rid
-            .with_context(|| format!("Sheet `{}` not found in workbook.xml", sheet_name))?;
+        let target_rid =
+            target_rid.ok_or_else(|| XlsxError::SheetNotFound(sheet_name.to_owned()))?;
 
-        // 3) По r:id найти Target в workbook.xml.rels
-        let mut rdr = Reader::from_reader(self.rels_xml.as_slice());
+        // 2) По r:id найти Target в workbook.xml.rels
+        let mut rdr = Reader::from_reader(self.rels_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
 
         let mut target_rel: Option<String> = None;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, "xl/_rels/workbook.xml.rels")?;
             match ev {
                 Event::Empty(ref e) | Event::Start(ref e)
                     if e.name().as_ref() == b"Relationship" =>
@@ -585,46 +969,68 @@ impl XlsxEditor {
             format!("xl/{}", target_rel)
         };
 
-        // 4) Достаём XML листа: сперва смотрим в new_files, иначе читаем из ZIP
-        // 4) Достаём XML листа: сперва new_files, потом кэш, иначе из ZIP
-        let sheet_xml: Vec<u8> =
-            if let Some((_, content)) = self.new_files.iter().find(|(p, _)| p == &new_sheet_path) {
-                content.clone()
-            } else if let Some(buf) = self.loaded_files.get(&new_sheet_path) {
-                buf.clone()
-            } else {
-                let mut zin = zip_crate::ZipArchive::new(File::open(&self.src_path)?)?;
-                let mut f = zin
-                    .by_name(&new_sheet_path)
-                    .with_context(|| format!("{} not found in zip", new_sheet_path))?;
-                let mut buf = Vec::with_capacity(f.size() as usize);
-                f.read_to_end(&mut buf)?;
-                self.loaded_files
-                    .insert(new_sheet_path.clone(), buf.clone()); // ← кэшируем
-                buf
-            };
+        // уже на этом листе: буфер на месте
+        if new_sheet_path == self.sheet_path {
+            self.last_row = calc_last_row(&self.sheet_path, &self.sheet_xml)?;
+            return Ok(self);
+        }
 
-        // 5) Пересчитываем last_row
-        let last_row = calc_last_row(&sheet_xml);
+        // 3) Достаём XML листа без копий: из места в new_files (оно остаётся
+        // за листом), потом из кэша, иначе из ZIP. Лист потом ляжет в
+        // new_files, так что в кэше его не держим.
+        let parked = self
+            .new_files
+            .iter()
+            .position(|(p, _)| p == &new_sheet_path);
+        let mut sheet_xml: Vec<u8> = if let Some(i) = parked {
+            Arc::unwrap_or_clone(std::mem::take(&mut self.new_files[i].1))
+        } else if let Some(buf) = self.loaded_files.remove(&new_sheet_path) {
+            buf
+        } else {
+            let mut zin = self.src.open()?;
+            let mut f = zin
+                .by_name(&new_sheet_path)
+                .with_context(|| format!("{} not found in zip", new_sheet_path))?;
+            let mut buf = Vec::with_capacity(f.size() as usize);
+            f.read_to_end(&mut buf)?;
+            buf
+        };
+
+        // 4) Пересчитываем last_row; битый лист возвращается на место
+        let sheet_prefix = strip_prefix(&mut sheet_xml);
+        let last_row = match calc_last_row(&new_sheet_path, &sheet_xml) {
+            Ok(n) => n,
+            Err(e) => {
+                if let Some(i) = parked {
+                    restore_prefix(&mut sheet_xml, &sheet_prefix);
+                    self.new_files[i].1 = sheet_xml.into();
+                }
+                return Err(e);
+            }
+        };
 
-        // 6) Переключаемся
+        // 5) Текущий лист — в new_files, переключаемся
+        self.flush_appended_ranges()?;
+        self.park_current_sheet();
         self.sheet_path = new_sheet_path;
-        self.sheet_xml = sheet_xml;
+        self.sheet_xml = sheet_xml.into();
+        self.sheet_prefix = sheet_prefix;
         self.last_row = last_row;
 
         Ok(self)
     }
 }
 
-// маленький хелпер
-fn calc_last_row(sheet_xml: &[u8]) -> u32 {
+/// Номер последней строки листа; битый XML — [`XlsxError::MalformedSheet`].
+pub(crate) fn calc_last_row(part: &str, sheet_xml: &[u8]) -> Result<u32, XlsxError> {
     let mut rdr = Reader::from_reader(sheet_xml);
     rdr.config_mut().trim_text(true);
 
     let mut last_row = 0u32;
-    while let Ok(ev) = rdr.read_event() {
+    loop {
+        let ev = next_event(&mut rdr, part)?;
         match ev {
-            Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"row" => {
+            Event::Empty(ref e) | Event::Start(ref e) if e.local_name().as_ref() == b"row" => {
                 if let Some(r) = e.attributes().with_checks(false).flatten().find_map(|a| {
                     (a.key.as_ref() == b"r").then(|| String::from_utf8_lossy(&a.value).into_owned())
                 }) {
@@ -635,11 +1041,11 @@ fn calc_last_row(sheet_xml: &[u8]) -> u32 {
             _ => {}
         }
     }
-    last_row
+    Ok(last_row)
 }
 
 // Простейший экранировщик для XML-атрибутов.
-fn xml_escape(s: &str) -> String {
+pub(crate) fn xml_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('"', "&quot;")
         .replace('<', "&lt;")
@@ -663,7 +1069,7 @@ fn normalize_styles_root(xml: &mut Vec<u8>) {
                     xml.splice(nf_abs..nf_end, std::iter::empty());
                     // вставляем внутрь корня (см. логику из пункта 1/2)
                     let root = memmem::find(&xml, b"<styleSheet").unwrap();
-                    let insert = find_bytes_from(&xml, b">", root).unwrap() + 1;
+                    let insert = find_from(&xml, b">", root).unwrap() + 1;
                     xml.splice(insert..insert, chunk.into_iter());
                 }
             }
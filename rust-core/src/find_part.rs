@@ -0,0 +1,216 @@
+//! find_part.rs – поиск и замена по тексту ячеек листа (inline и shared strings).
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::range_part::{CellMap, ClearMode, patch_sheet_data};
+use crate::read_part::{RawCell, read_cells};
+use crate::sheet_xml::find_child;
+use crate::style::col_letter;
+use crate::table_part::{Rect, parse_range, rects_overlap};
+use anyhow::Result;
+use quick_xml::escape::partial_escape;
+
+/// Параметры [`XlsxEditor::find_with`] и [`XlsxEditor::replace_all`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    /// Учитывать регистр (по умолчанию, как в Excel, — нет).
+    pub match_case: bool,
+    /// Совпадение должно занимать весь текст ячейки.
+    pub whole_cell: bool,
+    /// `pattern` — регулярное выражение; нужна фича `regex`.
+    /// В замене доступны группы: `$1`, `${name}`.
+    pub regex: bool,
+    /// Искать только в этом диапазоне (`"A1:F100"`); `None` — весь лист.
+    pub range: Option<String>,
+}
+
+impl FindOptions {
+    fn limit(&self) -> Result<Option<Rect>> {
+        self.range.as_deref().map(parse_range).transpose()
+    }
+}
+
+enum Matcher {
+    Plain {
+        needle: String,
+        match_case: bool,
+    },
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Matcher {
+    fn new(pattern: &str, opts: &FindOptions) -> Result<Self> {
+        if pattern.is_empty() {
+            bail!("empty search pattern");
+        }
+        if !opts.regex {
+            return Ok(Matcher::Plain {
+                needle: pattern.to_owned(),
+                match_case: opts.match_case,
+            });
+        }
+        #[cfg(feature = "regex")]
+        {
+            let re = regex::RegexBuilder::new(pattern)
+                .case_insensitive(!opts.match_case)
+                .build()?;
+            Ok(Matcher::Regex(re))
+        }
+        #[cfg(not(feature = "regex"))]
+        bail!("regex search requires the `regex` feature of rust-core")
+    }
+
+    /// Текст после замены или `None`, если совпадений нет.
+    fn replace(&self, text: &str, replacement: &str, whole_cell: bool) -> Option<String> {
+        match self {
+            Matcher::Plain { needle, match_case } => {
+                let mut out = String::new();
+                let mut rest = text;
+                let mut hit = false;
+                while let Some((a, b)) = find_plain(rest, needle, *match_case) {
+                    if whole_cell && !(a == 0 && b == rest.len() && !hit) {
+                        return None;
+                    }
+                    hit = true;
+                    out.push_str(&rest[..a]);
+                    out.push_str(replacement);
+                    rest = &rest[b..];
+                }
+                hit.then(|| {
+                    out.push_str(rest);
+                    out
+                })
+            }
+            #[cfg(feature = "regex")]
+            Matcher::Regex(re) => {
+                if whole_cell {
+                    let caps = re.captures(text)?;
+                    let m = caps.get(0)?;
+                    if m.start() != 0 || m.end() != text.len() {
+                        return None;
+                    }
+                    let mut out = String::new();
+                    caps.expand(replacement, &mut out);
+                    return Some(out);
+                }
+                re.is_match(text)
+                    .then(|| re.replace_all(text, replacement).into_owned())
+            }
+        }
+    }
+}
+
+/// Первое вхождение `needle` в `hay` (байтовые границы); без учёта регистра — посимвольно.
+fn find_plain(hay: &str, needle: &str, match_case: bool) -> Option<(usize, usize)> {
+    if match_case {
+        return hay.find(needle).map(|a| (a, a + needle.len()));
+    }
+    let eq = |a: char, b: char| a == b || a.to_lowercase().eq(b.to_lowercase());
+    for (start, _) in hay.char_indices() {
+        let mut h = hay[start..].char_indices();
+        let mut ok = true;
+        let mut end = start;
+        for n in needle.chars() {
+            match h.next() {
+                Some((i, c)) if eq(c, n) => end = start + i + c.len_utf8(),
+                _ => {
+                    ok = false;
+                    break;
+                }
+            }
+        }
+        if ok {
+            return Some((start, end));
+        }
+    }
+    None
+}
+
+impl XlsxEditor {
+    /// Адреса ячеек (`"B7"`, …), текст которых содержит `pattern`
+    /// (без учёта регистра). См. [`Self::find_with`].
+    pub fn find(&mut self, pattern: &str) -> Result<Vec<String>, XlsxError> {
+        self.find_with(pattern, &FindOptions::default())
+    }
+
+    /// Адреса ячеек, подходящих под `pattern`, в порядке строк.
+    /// Просматриваются строки (inline и shared) и значения чисел; формулы — нет.
+    pub fn find_with(
+        &mut self,
+        pattern: &str,
+        opts: &FindOptions,
+    ) -> Result<Vec<String>, XlsxError> {
+        let matcher = Matcher::new(pattern, opts)?;
+        Ok(self
+            .text_cells(opts.limit()?)?
+            .into_iter()
+            .filter(|(_, text)| matcher.replace(text, "", opts.whole_cell).is_some())
+            .map(|(c, _)| format!("{}{}", col_letter(c.col), c.row))
+            .collect())
+    }
+
+    /// Заменяет `pattern` на `replacement` во всех текстовых ячейках и возвращает
+    /// число изменённых ячеек. Изменённая ячейка записывается как inline-строка
+    /// с прежним стилем; общая строка в sharedStrings.xml не трогается, так что
+    /// другие ячейки с тем же текстом не меняются.
+    pub fn replace_all(
+        &mut self,
+        pattern: &str,
+        replacement: &str,
+        opts: &FindOptions,
+    ) -> Result<usize, XlsxError> {
+        let matcher = Matcher::new(pattern, opts)?;
+        let mut cells = CellMap::new();
+        let mut n = 0;
+        for (c, text) in self.text_cells(opts.limit()?)? {
+            if !matches!(c.t.as_deref(), Some("s" | "inlineStr")) {
+                continue;
+            }
+            let Some(new) = matcher.replace(&text, replacement, opts.whole_cell) else {
+                continue;
+            };
+            if new == text {
+                continue;
+            }
+            cells
+                .entry(c.row)
+                .or_default()
+                .insert(c.col, inline_cell(c.col, c.row, c.s, &new));
+            n += 1;
+        }
+        if n > 0
+            && let Some(sd) = find_child(&self.sheet_xml, "sheetData")?
+        {
+            let data = patch_sheet_data(&self.sheet_xml[sd.clone()], &[], ClearMode::All, cells)?;
+            self.sheet_xml.splice(sd, data);
+        }
+        Ok(n)
+    }
+
+    /// Ячейки листа (в пределах `limit`) без формул с их видимым текстом.
+    pub(crate) fn text_cells(&mut self, limit: Option<Rect>) -> Result<Vec<(RawCell, String)>> {
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        Ok(read_cells(&self.sheet_xml)?
+            .into_iter()
+            .filter(|c| limit.is_none_or(|r| rects_overlap(r, (c.col, c.row, c.col, c.row))))
+            .filter(|c| c.f.is_none() && c.t.as_deref() != Some("b"))
+            .filter_map(|c| {
+                let text = c.display_text(sst)?;
+                Some((c, text))
+            })
+            .collect())
+    }
+}
+
+/// `<c t="inlineStr">` с текстом `text` и стилем `s`.
+pub(crate) fn inline_cell(col: u32, row: u32, s: Option<u32>, text: &str) -> Vec<u8> {
+    let style = s.map(|s| format!(r#" s="{s}""#)).unwrap_or_default();
+    format!(
+        r#"<c r="{}{row}"{style} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+        col_letter(col),
+        partial_escape(text)
+    )
+    .into_bytes()
+}
@@ -0,0 +1,302 @@
+//! format_part.rs – чтение оформления ячейки.
+//!
+//! Чтобы дописать строки «как строка N», нужно знать, как она оформлена.
+//! [`XlsxEditor::get_number_format`] и [`XlsxEditor::get_cell_style`]
+//! раскрывают стиль ячейки из `styles.xml` в значения, которые принимают
+//! `set_number_format`, `set_font_with_color`, `set_fill`,
+//! `set_border_with_color` и `set_alignment`. У ячейки без своего стиля
+//! действует стиль строки (`customFormat`), затем столбца (`<col style>`).
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, next_event};
+use crate::numfmt::builtin_code;
+use crate::sheet_xml::{find_child, get_attr};
+use crate::structure_part::parse_span;
+use crate::style::{AlignSpec, parse_cell, protection_attrs};
+use crate::theme_part::Color;
+use anyhow::Result;
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+use std::collections::HashMap;
+
+/// Оформление ячейки из [`XlsxEditor::get_cell_style`]; по умолчанию —
+/// стиль 0 пустой книги (Calibri 11, «Общий», ячейка защищена).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleSpec {
+    /// Код числового формата; `None` — «Общий».
+    pub number_format: Option<String>,
+    pub font: FontSpec,
+    /// Цвет заливки; `None` — без заливки.
+    pub fill: Option<Color>,
+    pub border: BorderSpec,
+    pub alignment: AlignSpec,
+    pub locked: bool,
+    pub hidden: bool,
+}
+
+/// Шрифт; `color: None` — цвет по умолчанию (авто).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSpec {
+    pub name: String,
+    pub size: f32,
+    pub bold: bool,
+    pub italic: bool,
+    pub color: Option<Color>,
+}
+
+/// Рамка по сторонам; `None` — стороны нет.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BorderSpec {
+    pub left: Option<BorderSide>,
+    pub right: Option<BorderSide>,
+    pub top: Option<BorderSide>,
+    pub bottom: Option<BorderSide>,
+}
+
+/// Сторона рамки: `style` — `"thin"`, `"medium"`, ….
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorderSide {
+    pub style: String,
+    pub color: Option<Color>,
+}
+
+impl Default for StyleSpec {
+    fn default() -> Self {
+        StyleSpec {
+            number_format: None,
+            font: FontSpec::default(),
+            fill: None,
+            border: BorderSpec::default(),
+            alignment: AlignSpec::default(),
+            locked: true,
+            hidden: false,
+        }
+    }
+}
+
+impl Default for FontSpec {
+    fn default() -> Self {
+        FontSpec {
+            name: "Calibri".into(),
+            size: 11.0,
+            bold: false,
+            italic: false,
+            color: None,
+        }
+    }
+}
+
+impl BorderSpec {
+    fn side_mut(&mut self, name: &[u8]) -> Option<&mut Option<BorderSide>> {
+        Some(match name {
+            b"left" | b"start" => &mut self.left,
+            b"right" | b"end" => &mut self.right,
+            b"top" => &mut self.top,
+            b"bottom" => &mut self.bottom,
+            _ => return None,
+        })
+    }
+}
+
+impl XlsxEditor {
+    /// Код числового формата ячейки `coord` (`"B4"`) или столбца (`"B"`):
+    /// `"0.00%"`, `"yyyy-mm-dd"`. `None` — «Общий», а также встроенные
+    /// форматы, зависящие от языка Excel (id 27–36, 50–58).
+    pub fn get_number_format(&mut self, coord: &str) -> Result<Option<String>, XlsxError> {
+        Ok(self.get_cell_style(coord)?.number_format)
+    }
+
+    /// Оформление ячейки `coord` (`"B4"`) или столбца (`"B"`), раскрытое из
+    /// `styles.xml`. Цвета — как их принимают сеттеры: RGB или `theme:N:tint`.
+    ///
+    /// ```no_run
+    /// # use rust_core::XlsxEditor;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Sheet1")?;
+    /// let like = xl.get_cell_style("A5")?;
+    /// if let Some(fill) = &like.fill {
+    ///     xl.set_fill("A6:D6", &fill.to_string())?;
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn get_cell_style(&mut self, coord: &str) -> Result<StyleSpec, XlsxError> {
+        let style = self.style_at(coord)?;
+        let styles = self.styles_xml.get(&self.src)?;
+        Ok(resolve_style(styles, style)?)
+    }
+
+    /// Номер `cellXfs` ячейки или столбца: своя ячейка, иначе стиль строки
+    /// с `customFormat`, иначе стиль столбца, иначе 0.
+    fn style_at(&self, coord: &str) -> Result<u32> {
+        let (col, row) = if coord.bytes().all(|b| b.is_ascii_alphabetic()) {
+            let (c0, c1) = parse_span(coord, true)?;
+            if c0 != c1 {
+                bail!("expected one column, got {coord}");
+            }
+            (c0, None)
+        } else {
+            let (col, row) = parse_cell(coord)?;
+            (col, Some(row))
+        };
+        if let Some(row) = row {
+            if let Some(s) = self.cell_style_attr(row, col) {
+                return Ok(s.unwrap_or(0));
+            }
+            let row_style = self.sheet_xml.base_row_tag(row).and_then(|tag| {
+                let custom = get_attr(tag, "customFormat");
+                matches!(custom.as_deref(), Some("1" | "true")).then(|| get_attr(tag, "s"))?
+            });
+            if let Some(s) = row_style {
+                return Ok(s.parse().unwrap_or(0));
+            }
+        }
+        let col_style = match find_child(&self.sheet_xml, "cols")? {
+            Some(cols) => self
+                .read_cols_map(cols.start, cols.end)?
+                .get(&(col + 1))
+                .and_then(|p| p.style),
+            None => None,
+        };
+        Ok(col_style.unwrap_or(0))
+    }
+}
+
+/// Раскрывает стиль `cellXfs[style]`; нет такого `<xf>` — стиль по умолчанию.
+fn resolve_style(styles: &[u8], style: u32) -> Result<StyleSpec> {
+    const PART: &str = "xl/styles.xml";
+    let mut rdr = Reader::from_reader(styles);
+    let mut codes: HashMap<u32, String> = HashMap::new();
+    let mut fonts: Vec<FontSpec> = Vec::new();
+    let mut fills: Vec<Option<Color>> = Vec::new();
+    let mut borders: Vec<BorderSpec> = Vec::new();
+    // ссылки нужного <xf>: numFmtId, fontId, fillId, borderId
+    let mut xf: Option<[usize; 4]> = None;
+    let mut spec = StyleSpec::default();
+    let mut section: Option<Vec<u8>> = None; // fonts, fills, borders или cellXfs
+    let mut side: Option<Vec<u8>> = None; // открытая сторона рамки
+    let mut solid = false; // у открытой <patternFill> есть узор
+    let mut xf_idx = 0u32;
+    let mut in_xf = false; // внутри нужного <xf>
+    loop {
+        let (e, empty) = match next_event(&mut rdr, PART)? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) => {
+                let name = e.local_name();
+                if section.as_deref() == Some(name.as_ref()) {
+                    section = None;
+                } else if side.as_deref() == Some(name.as_ref()) {
+                    side = None;
+                } else if name.as_ref() == b"xf" {
+                    in_xf = false;
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let name = e.local_name();
+        match (section.as_deref(), name.as_ref()) {
+            (None, b"fonts" | b"fills" | b"borders" | b"cellXfs") if !empty => {
+                section = Some(name.as_ref().to_vec());
+            }
+            (_, b"numFmt") => {
+                if let (Some(id), Some(code)) = (attr(&e, b"numFmtId"), attr(&e, b"formatCode")) {
+                    codes.extend(id.parse().ok().map(|id| (id, code)));
+                }
+            }
+            (Some(b"fonts"), b"font") => fonts.push(FontSpec::default()),
+            (Some(b"fonts"), tag) => {
+                let Some(font) = fonts.last_mut() else {
+                    continue;
+                };
+                let val = attr(&e, b"val");
+                let on = !matches!(val.as_deref(), Some("0" | "false"));
+                match tag {
+                    b"b" => font.bold = on,
+                    b"i" => font.italic = on,
+                    b"sz" => font.size = val.and_then(|v| v.parse().ok()).unwrap_or(11.0),
+                    b"name" => font.name = val.unwrap_or_default(),
+                    b"color" => font.color = Color::from_attrs(&e),
+                    _ => {}
+                }
+            }
+            (Some(b"fills"), b"fill") => fills.push(None),
+            (Some(b"fills"), b"patternFill") => {
+                solid = !matches!(attr(&e, b"patternType").as_deref(), None | Some("none"));
+            }
+            (Some(b"fills"), b"fgColor") if solid => {
+                if let Some(fill) = fills.last_mut() {
+                    *fill = Color::from_attrs(&e);
+                }
+            }
+            (Some(b"borders"), b"border") => borders.push(BorderSpec::default()),
+            (Some(b"borders"), b"color") => {
+                let target = side
+                    .as_deref()
+                    .zip(borders.last_mut())
+                    .and_then(|(s, b)| b.side_mut(s)?.as_mut());
+                if let Some(target) = target {
+                    target.color = Color::from_attrs(&e);
+                }
+            }
+            (Some(b"borders"), tag) => {
+                let Some(slot) = borders.last_mut().and_then(|b| b.side_mut(tag)) else {
+                    continue;
+                };
+                *slot = attr(&e, b"style").map(|style| BorderSide { style, color: None });
+                if !empty {
+                    side = Some(tag.to_vec());
+                }
+            }
+            (Some(b"cellXfs"), b"xf") => {
+                if xf_idx == style {
+                    let id = |key: &[u8]| attr(&e, key).and_then(|v| v.parse().ok()).unwrap_or(0);
+                    xf = Some([
+                        id(b"numFmtId"),
+                        id(b"fontId"),
+                        id(b"fillId"),
+                        id(b"borderId"),
+                    ]);
+                    in_xf = !empty;
+                }
+                xf_idx += 1;
+            }
+            (Some(b"cellXfs"), b"alignment") if in_xf => {
+                let a = &mut spec.alignment;
+                a.horiz = attr(&e, b"horizontal").and_then(|v| v.parse().ok());
+                a.vert = attr(&e, b"vertical").and_then(|v| v.parse().ok());
+                a.wrap = matches!(attr(&e, b"wrapText").as_deref(), Some("1" | "true"));
+            }
+            (Some(b"cellXfs"), b"protection") if in_xf => {
+                (spec.locked, spec.hidden) = protection_attrs(&e);
+            }
+            _ => {}
+        }
+    }
+
+    let Some([num_fmt, font, fill, border]) = xf else {
+        return Ok(spec);
+    };
+    spec.number_format = match codes.remove(&(num_fmt as u32)) {
+        Some(code) => Some(code),
+        None if num_fmt == 0 => None,
+        None => builtin_code(num_fmt as u32).map(str::to_owned),
+    };
+    spec.font = fonts.into_iter().nth(font).unwrap_or_default();
+    spec.fill = fills.into_iter().nth(fill).flatten();
+    spec.border = borders.into_iter().nth(border).unwrap_or_default();
+    Ok(spec)
+}
+
+/// Значение атрибута `key` с раскрытыми сущностями.
+fn attr(e: &BytesStart, key: &[u8]) -> Option<String> {
+    e.attributes()
+        .with_checks(false)
+        .flatten()
+        .find(|a| a.key.as_ref() == key)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
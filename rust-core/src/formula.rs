@@ -0,0 +1,698 @@
+//! formula.rs – разбор ссылок в формулах A1 и их сдвиг при вставке/удалении
+//! строк и столбцов, перенос формул при копировании и перевод из R1C1 в A1.
+
+use crate::error::XlsxError;
+use crate::style::{col_letter, parse_cell};
+use crate::workbook_part::quote_sheet_name;
+use anyhow::Result;
+
+/// Сдвиг по одной оси: вставка `count` позиций перед `at` либо удаление
+/// `count` позиций начиная с `at`. Строки 1-based, колонки 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Shift {
+    pub at: u32,
+    pub count: u32,
+    pub delete: bool,
+}
+
+impl Shift {
+    /// Новые границы отрезка `[lo, hi]` или `None`, если он удалён целиком.
+    /// Вставка внутри отрезка его растягивает, удаление части — сжимает.
+    pub fn map_span(&self, lo: u32, hi: u32) -> Option<(u32, u32)> {
+        if self.count == 0 {
+            return Some((lo, hi));
+        }
+        if !self.delete {
+            let f = |x: u32| if x >= self.at { x + self.count } else { x };
+            return Some((f(lo), f(hi)));
+        }
+        let (a, b) = (self.at, self.at + self.count - 1);
+        let nlo = if lo < a {
+            lo
+        } else if lo > b {
+            lo - self.count
+        } else {
+            a
+        };
+        let nhi = if hi < a {
+            hi
+        } else if hi > b {
+            hi - self.count
+        } else {
+            a.checked_sub(1)?
+        };
+        (nlo <= nhi).then_some((nlo, nhi))
+    }
+}
+
+/// Структурная правка листа: сдвиг строк и/или столбцов.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RefEdit {
+    pub rows: Option<Shift>,
+    pub cols: Option<Shift>,
+}
+
+impl RefEdit {
+    pub fn map_rows(&self, lo: u32, hi: u32) -> Option<(u32, u32)> {
+        match self.rows {
+            Some(s) => s.map_span(lo, hi),
+            None => Some((lo, hi)),
+        }
+    }
+    pub fn map_cols(&self, lo: u32, hi: u32) -> Option<(u32, u32)> {
+        match self.cols {
+            Some(s) => s.map_span(lo, hi),
+            None => Some((lo, hi)),
+        }
+    }
+
+    /// Прямоугольник `(c0, r0, c1, r1)` после правки.
+    pub fn map_rect(&self, (c0, r0, c1, r1): (u32, u32, u32, u32)) -> Option<(u32, u32, u32, u32)> {
+        let (nc0, nc1) = self.map_cols(c0, c1)?;
+        let (nr0, nr1) = self.map_rows(r0, r1)?;
+        Some((nc0, nr0, nc1, nr1))
+    }
+}
+
+/* ========================== ССЫЛКИ В ФОРМУЛЕ ============================== */
+
+/// Часть ссылки: `$A$1`, `A` (для `A:C`) или `1` (для `1:3`).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RefPart {
+    pub col: Option<(u32, bool)>, // (0-based, абсолютная)
+    pub row: Option<(u32, bool)>,
+}
+
+const MAX_COL: u32 = 16_384;
+const MAX_ROW: u32 = 1_048_576;
+
+fn is_word(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'\\' | b'$' | b'?')
+}
+
+/// Байт имени листа/определённого имени (включая не-ASCII символы).
+fn in_name(b: u8) -> bool {
+    is_word(b) || !b.is_ascii()
+}
+
+/// Разбирает часть ссылки с позиции `i`; возвращает её и позицию за ней.
+pub(crate) fn parse_part(s: &[u8], mut i: usize) -> Option<(RefPart, usize)> {
+    let col_abs = s.get(i) == Some(&b'$');
+    if col_abs {
+        i += 1;
+    }
+    let l0 = i;
+    while i < s.len() && s[i].is_ascii_alphabetic() && i - l0 < 4 {
+        i += 1;
+    }
+    let letters = &s[l0..i];
+    let row_abs = !letters.is_empty() && s.get(i) == Some(&b'$');
+    if row_abs {
+        i += 1;
+    }
+    let d0 = i;
+    while i < s.len() && s[i].is_ascii_digit() {
+        i += 1;
+    }
+    let digits = &s[d0..i];
+
+    let col = if letters.is_empty() {
+        if col_abs && digits.is_empty() {
+            return None;
+        }
+        None
+    } else {
+        if letters.len() > 3 {
+            return None;
+        }
+        let c = letters.iter().fold(0u32, |acc, b| {
+            acc * 26 + (b.to_ascii_uppercase() - b'A') as u32 + 1
+        });
+        if c > MAX_COL {
+            return None;
+        }
+        Some((c - 1, col_abs))
+    };
+    let row = if digits.is_empty() {
+        if row_abs {
+            return None;
+        }
+        None
+    } else {
+        let r: u32 = std::str::from_utf8(digits).ok()?.parse().ok()?;
+        if r == 0 || r > MAX_ROW {
+            return None;
+        }
+        // у `$1` (строка целиком) `$` стоит перед цифрами
+        Some((r, if letters.is_empty() { col_abs } else { row_abs }))
+    };
+    if col.is_none() && row.is_none() {
+        return None;
+    }
+    Some((RefPart { col, row }, i))
+}
+
+fn write_part(out: &mut String, p: &RefPart) {
+    if let Some((c, abs)) = p.col {
+        if abs {
+            out.push('$');
+        }
+        out.push_str(&col_letter(c));
+    }
+    if let Some((r, abs)) = p.row {
+        if abs {
+            out.push('$');
+        }
+        out.push_str(&r.to_string());
+    }
+}
+
+/// Применяет `edit` к ссылке `a` (или диапазону `a:b`); `None` — ссылка удалена.
+fn shift_ref(a: RefPart, b: Option<RefPart>, edit: &RefEdit) -> Option<(RefPart, Option<RefPart>)> {
+    let b0 = b.unwrap_or(a);
+    let (mut na, mut nb) = (a, b0);
+    if let (Some((c0, _)), Some((c1, _))) = (a.col, b0.col) {
+        let (x0, x1) = edit.map_cols(c0.min(c1), c0.max(c1))?;
+        let (x0, x1) = if c0 <= c1 { (x0, x1) } else { (x1, x0) };
+        na.col = a.col.map(|(_, abs)| (x0, abs));
+        nb.col = b0.col.map(|(_, abs)| (x1, abs));
+    }
+    if let (Some((r0, _)), Some((r1, _))) = (a.row, b0.row) {
+        let (y0, y1) = edit.map_rows(r0.min(r1), r0.max(r1))?;
+        let (y0, y1) = if r0 <= r1 { (y0, y1) } else { (y1, y0) };
+        na.row = a.row.map(|(_, abs)| (y0, abs));
+        nb.row = b0.row.map(|(_, abs)| (y1, abs));
+    }
+    Some((na, b.map(|_| nb)))
+}
+
+/// Сдвигает ссылки формулы `formula` (без ведущего `=`) согласно `edit`.
+///
+/// * `sheet` – имя листа, который правится; ссылки с этим листом в квалификаторе
+///   сдвигаются всегда, на другие листы — никогда;
+/// * `local` – сдвигать ли ссылки без квалификатора (формула лежит на этом листе).
+///
+/// Удалённые ссылки превращаются в `#REF!`, как это делает Excel.
+pub(crate) fn shift_formula(formula: &str, edit: &RefEdit, sheet: &str, local: bool) -> String {
+    let applies = |t: Target| match t {
+        Target::Local => local,
+        Target::Sheet(name) => name.eq_ignore_ascii_case(sheet),
+        Target::Other => false,
+    };
+    map_refs(formula, &applies, &|a, b| shift_ref(a, b, edit))
+}
+
+/// Переносит формулу (без ведущего `=`) на `row_delta` строк и `col_delta`
+/// столбцов, как при копировании ячейки: относительные части ссылок смещаются,
+/// абсолютные (`$`) остаются на месте. Ссылки, ушедшие за границы листа,
+/// становятся `#REF!`.
+///
+/// `adjust_formula("SUM(A1:B$2)*$C3", 2, 1)` → `"SUM(B3:C$2)*$C5"`.
+pub fn adjust_formula(formula: &str, row_delta: i64, col_delta: i64) -> String {
+    let (dr, dc) = (row_delta, col_delta);
+    let applies = |t: Target| !matches!(t, Target::Other);
+    let step = |p: RefPart| -> Option<RefPart> {
+        let mv = |v: Option<(u32, bool)>, d: i64, lo: i64, hi: i64| match v {
+            Some((x, false)) => {
+                let n = x as i64 + d;
+                (lo..=hi).contains(&n).then_some(Some((n as u32, false)))
+            }
+            other => Some(other),
+        };
+        Some(RefPart {
+            col: mv(p.col, dc, 0, MAX_COL as i64 - 1)?,
+            row: mv(p.row, dr, 1, MAX_ROW as i64)?,
+        })
+    };
+    map_refs(formula, &applies, &|a, b| {
+        let na = step(a)?;
+        let nb = match b {
+            Some(b) => Some(step(b)?),
+            None => None,
+        };
+        Some((na, nb))
+    })
+}
+
+/// Переводит формулу из нотации R1C1 в A1 для ячейки `cell` (`"B5"`), где она
+/// будет лежать: `R[-1]C` → `B4`, `RC[-1]` → `A5`, `R1C1` → `$A$1`,
+/// `R[1]` → `6:6`, `C2:C[1]` → `$B:C`. Ссылки за границами листа становятся
+/// `#REF!`; строки, имена листов и прочие слова переносятся как есть.
+pub fn translate_r1c1_to_a1(formula: &str, cell: &str) -> Result<String, XlsxError> {
+    let (col, row) = parse_cell(cell)?;
+    let s = formula.as_bytes();
+    let mut out = String::with_capacity(formula.len() + 8);
+    let mut i = 0;
+
+    while i < s.len() {
+        let b = s[i];
+        match b {
+            b'"' | b'\'' => {
+                // строковый литерал или 'Имя листа'; удвоенная кавычка — экранирование
+                let start = i;
+                i += 1;
+                while i < s.len() {
+                    if s[i] == b {
+                        if s.get(i + 1) == Some(&b) {
+                            i += 2;
+                            continue;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    i += 1;
+                }
+                out.push_str(&formula[start..i]);
+            }
+            _ if is_word(b) && (i == 0 || !in_name(s[i - 1])) => {
+                if let Some((part, end)) = parse_r1c1(s, i, col, row) {
+                    let in_span = out.ends_with(':');
+                    match part {
+                        Some(p) => write_part(&mut out, &p),
+                        None => out.push_str("#REF!"),
+                    }
+                    let whole = part.is_some_and(|p| p.col.is_none() || p.row.is_none());
+                    // одиночные `R[1]`/`C2` в A1 пишутся диапазоном `6:6`/`$B:$B`
+                    if whole && !in_span && s.get(end) != Some(&b':') {
+                        let start = out.rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '$'));
+                        let token = out[start.map_or(0, |p| p + 1)..].to_owned();
+                        out.push(':');
+                        out.push_str(&token);
+                    }
+                    i = end;
+                } else {
+                    let start = i;
+                    while i < s.len() && in_name(s[i]) {
+                        i += 1;
+                    }
+                    out.push_str(&formula[start..i]);
+                }
+            }
+            _ => {
+                let ch = formula[i..].chars().next().unwrap_or('\0');
+                out.push(ch);
+                i += ch.len_utf8();
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Ссылка R1C1 (`R[1]C`, `R2C[-3]`, `RC`, `R[1]`, `C2`) с позиции `i` для ячейки
+/// (`col`, `row`). Возвращает часть ссылки A1 (`None` — вне листа) и позицию за ней;
+/// `None` — в этой позиции не ссылка (имя функции, слово).
+fn parse_r1c1(s: &[u8], i: usize, col: u32, row: u32) -> Option<(Option<RefPart>, usize)> {
+    // `R`/`C`, затем номер `5` или смещение `[-1]`
+    let axis = |j: usize, letter: u8| -> Option<(Option<(i64, bool)>, usize)> {
+        if !s.get(j)?.eq_ignore_ascii_case(&letter) {
+            return None;
+        }
+        let mut k = j + 1;
+        if s.get(k) == Some(&b'[') {
+            let end = k + s[k..].iter().position(|&b| b == b']')?;
+            let d: i64 = std::str::from_utf8(&s[k + 1..end]).ok()?.parse().ok()?;
+            return Some((Some((d, false)), end + 1));
+        }
+        let d0 = k;
+        while s.get(k).is_some_and(|b| b.is_ascii_digit()) {
+            k += 1;
+        }
+        if k == d0 {
+            return Some((Some((0, false)), k));
+        }
+        let n: i64 = std::str::from_utf8(&s[d0..k]).ok()?.parse().ok()?;
+        (n > 0).then_some((Some((n, true)), k))
+    };
+    let (r, j) = axis(i, b'R').unwrap_or((None, i));
+    let (c, j) = axis(j, b'C').unwrap_or((None, j));
+    if (r.is_none() && c.is_none()) || s.get(j).is_some_and(|&b| in_name(b) || b == b'(') {
+        return None;
+    }
+    let resolve = |v: Option<(i64, bool)>, base: u32, lo: i64, hi: i64, one_based: bool| match v {
+        None => Some(None),
+        Some((n, true)) => {
+            let x = if one_based { n } else { n - 1 };
+            (lo..=hi).contains(&x).then_some(Some((x as u32, true)))
+        }
+        Some((d, false)) => {
+            let x = base as i64 + d;
+            (lo..=hi).contains(&x).then_some(Some((x as u32, false)))
+        }
+    };
+    let part = (|| {
+        Some(RefPart {
+            row: resolve(r, row, 1, MAX_ROW as i64, true)?,
+            col: resolve(c, col, 0, MAX_COL as i64 - 1, false)?,
+        })
+    })();
+    Some((part, j))
+}
+
+/// На что указывает ссылка формулы.
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    /// без квалификатора — лист, где лежит формула
+    Local,
+    Sheet(&'a str),
+    /// 3D-ссылка или лист внешней книги
+    Other,
+}
+
+type RefFn<'a> = &'a dyn Fn(RefPart, Option<RefPart>) -> Option<(RefPart, Option<RefPart>)>;
+
+/// Проходит по формуле, пропуская строки и структурированные ссылки, и заменяет
+/// каждую ссылку, для которой `applies` вернула `true`, результатом `f`
+/// (`None` — `#REF!`).
+fn map_refs(formula: &str, applies: &dyn Fn(Target) -> bool, f: RefFn) -> String {
+    let s = formula.as_bytes();
+    let mut out = String::with_capacity(formula.len() + 8);
+    let mut i = 0;
+
+    while i < s.len() {
+        let b = s[i];
+        match b {
+            b'"' => {
+                // строковый литерал, "" внутри — экранированная кавычка
+                let start = i;
+                i += 1;
+                while i < s.len() {
+                    if s[i] == b'"' {
+                        if s.get(i + 1) == Some(&b'"') {
+                            i += 2;
+                            continue;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    i += 1;
+                }
+                out.push_str(&formula[start..i]);
+            }
+            b'[' => {
+                // структурированная ссылка или внешняя книга — не трогаем
+                let start = i;
+                let mut depth = 0;
+                while i < s.len() {
+                    match s[i] {
+                        b'[' => depth += 1,
+                        b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                i += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                out.push_str(&formula[start..i]);
+            }
+            b'\'' => {
+                // 'Имя листа'!ссылка
+                let start = i;
+                i += 1;
+                let mut name = String::new();
+                while i < s.len() {
+                    if s[i] == b'\'' {
+                        if s.get(i + 1) == Some(&b'\'') {
+                            name.push('\'');
+                            i += 2;
+                            continue;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    let ch = formula[i..].chars().next().unwrap_or('\0');
+                    name.push(ch);
+                    i += ch.len_utf8();
+                }
+                out.push_str(&formula[start..i]);
+                if s.get(i) == Some(&b'!') {
+                    out.push('!');
+                    i += 1;
+                    let t = if name.contains(':') {
+                        Target::Other
+                    } else {
+                        Target::Sheet(&name)
+                    };
+                    i = emit_ref_or_word(formula, i, applies(t), f, &mut out);
+                }
+            }
+            _ if is_word(b) && (i == 0 || !in_name(s[i - 1])) => {
+                if i > 0 && s[i - 1] == b'#' {
+                    // #REF!, #N/A и т.п.
+                    let start = i;
+                    while i < s.len() && is_word(s[i]) {
+                        i += 1;
+                    }
+                    out.push_str(&formula[start..i]);
+                    continue;
+                }
+                // Sheet1!A1 или Sheet1:Sheet3!A1
+                let mut j = i;
+                while j < s.len() && in_name(s[j]) {
+                    j += 1;
+                }
+                let mut k = j;
+                if s.get(k) == Some(&b':') {
+                    let mut m = k + 1;
+                    while m < s.len() && in_name(s[m]) {
+                        m += 1;
+                    }
+                    if s.get(m) == Some(&b'!') && m > k + 1 {
+                        k = m;
+                    }
+                }
+                if s.get(k) == Some(&b'!') {
+                    let name = &formula[i..k];
+                    out.push_str(name);
+                    out.push('!');
+                    // после `[1]` — лист внешней книги
+                    let external = i > 0 && s[i - 1] == b']';
+                    let t = if external || name.contains(':') {
+                        Target::Other
+                    } else {
+                        Target::Sheet(name)
+                    };
+                    i = emit_ref_or_word(formula, k + 1, applies(t), f, &mut out);
+                } else {
+                    i = emit_ref_or_word(formula, i, applies(Target::Local), f, &mut out);
+                }
+            }
+            _ => {
+                let ch = formula[i..].chars().next().unwrap_or('\0');
+                out.push(ch);
+                i += ch.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// Пишет в `out` ссылку с позиции `i` (преобразованную `map`, если `apply`) или,
+/// если там не ссылка, слово целиком. Возвращает позицию за ними.
+fn emit_ref_or_word(f: &str, i: usize, apply: bool, map: RefFn, out: &mut String) -> usize {
+    let s = f.as_bytes();
+    let word_end = |mut j: usize| {
+        while j < s.len() && in_name(s[j]) {
+            j += 1;
+        }
+        j
+    };
+    let boundary_ok = |j: usize| match s.get(j) {
+        None => true,
+        Some(&c) => !(is_word(c) || c == b'(' || c == b'!' || !c.is_ascii()),
+    };
+
+    if let Some((a, mut end)) = parse_part(s, i) {
+        // вторая часть диапазона должна быть того же вида: A1:B2, A:C, 1:3
+        let b = (s.get(end) == Some(&b':'))
+            .then(|| parse_part(s, end + 1))
+            .flatten()
+            .filter(|(p2, e2)| {
+                a.col.is_some() == p2.col.is_some()
+                    && a.row.is_some() == p2.row.is_some()
+                    && boundary_ok(*e2)
+            })
+            .map(|(p2, e2)| {
+                end = e2;
+                p2
+            });
+        let is_cell = a.col.is_some() && a.row.is_some();
+        if (is_cell || b.is_some()) && boundary_ok(end) {
+            if !apply {
+                out.push_str(&f[i..end]);
+                return end;
+            }
+            match map(a, b) {
+                Some((na, nb)) => {
+                    write_part(out, &na);
+                    if let Some(nb) = nb {
+                        out.push(':');
+                        write_part(out, &nb);
+                    }
+                }
+                None => out.push_str("#REF!"),
+            }
+            return end;
+        }
+    }
+    let end = word_end(i).max(i + 1).min(s.len());
+    out.push_str(&f[i..end]);
+    end
+}
+
+/// Сдвигает каждый диапазон в sqref (`"A1:B2 D5"`); удалённые выбрасываются.
+pub(crate) fn shift_sqref(sqref: &str, edit: &RefEdit) -> Option<String> {
+    let parts: Vec<String> = sqref
+        .split_whitespace()
+        .filter_map(|p| {
+            let (c0, r0, c1, r1) = crate::table_part::parse_range(p).ok()?;
+            let rect = edit.map_rect((c0, r0, c1, r1))?;
+            Some(crate::table_part::format_range(rect))
+        })
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// Заменяет квалификатор листа `old` в ссылках формулы на `new` (в кавычках,
+/// если нужно). `new = None` — лист удалён: ссылка целиком становится `#REF!`.
+/// Ссылки на листы внешних книг не трогаются.
+pub(crate) fn rename_sheet_refs(formula: &str, old: &str, new: Option<&str>) -> String {
+    let s = formula.as_bytes();
+    let mut out = String::with_capacity(formula.len() + 8);
+    let mut i = 0;
+    let no_map: RefFn = &|a, b| Some((a, b));
+    // Квалификатор `names` (1 или 2 листа для 3D) перед `!` на позиции `bang`.
+    let emit = |out: &mut String, raw: &str, names: &[&str], bang: usize| -> usize {
+        if !names.iter().any(|n| n.eq_ignore_ascii_case(old)) {
+            out.push_str(raw);
+            out.push('!');
+            return bang + 1;
+        }
+        let Some(new) = new else {
+            out.push_str("#REF!");
+            let mut skipped = String::new();
+            return emit_ref_or_word(formula, bang + 1, false, no_map, &mut skipped);
+        };
+        let renamed: Vec<&str> = names
+            .iter()
+            .map(|n| if n.eq_ignore_ascii_case(old) { new } else { n })
+            .collect();
+        let joined = renamed.join(":");
+        let plain = renamed.iter().all(|n| quote_sheet_name(n) == *n);
+        if plain {
+            out.push_str(&joined);
+        } else {
+            out.push_str(&format!("'{}'", joined.replace('\'', "''")));
+        }
+        out.push('!');
+        bang + 1
+    };
+
+    while i < s.len() {
+        let b = s[i];
+        match b {
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < s.len() {
+                    if s[i] == b'"' {
+                        if s.get(i + 1) == Some(&b'"') {
+                            i += 2;
+                            continue;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    i += 1;
+                }
+                out.push_str(&formula[start..i]);
+            }
+            b'[' => {
+                let start = i;
+                let mut depth = 0;
+                while i < s.len() {
+                    match s[i] {
+                        b'[' => depth += 1,
+                        b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                i += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                // лист внешней книги после `[1]` копируем как есть
+                while i < s.len() && in_name(s[i]) {
+                    i += 1;
+                }
+                out.push_str(&formula[start..i]);
+            }
+            b'\'' => {
+                let start = i;
+                i += 1;
+                let mut name = String::new();
+                while i < s.len() {
+                    if s[i] == b'\'' {
+                        if s.get(i + 1) == Some(&b'\'') {
+                            name.push('\'');
+                            i += 2;
+                            continue;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    let ch = formula[i..].chars().next().unwrap_or('\0');
+                    name.push(ch);
+                    i += ch.len_utf8();
+                }
+                if s.get(i) == Some(&b'!') && !name.contains('[') {
+                    let names: Vec<&str> = name.splitn(2, ':').collect();
+                    i = emit(&mut out, &formula[start..i], &names, i);
+                } else {
+                    out.push_str(&formula[start..i]);
+                }
+            }
+            _ if in_name(b) && (i == 0 || !in_name(s[i - 1])) => {
+                let mut j = i;
+                while j < s.len() && in_name(s[j]) {
+                    j += 1;
+                }
+                if i > 0 && s[i - 1] == b'#' {
+                    // #REF!, #N/A и т.п.
+                    out.push_str(&formula[i..j]);
+                    i = j;
+                    continue;
+                }
+                let mut k = j;
+                if s.get(k) == Some(&b':') {
+                    let mut m = k + 1;
+                    while m < s.len() && in_name(s[m]) {
+                        m += 1;
+                    }
+                    if s.get(m) == Some(&b'!') && m > k + 1 {
+                        k = m;
+                    }
+                }
+                if s.get(k) == Some(&b'!') {
+                    let names: Vec<&str> = formula[i..k].splitn(2, ':').collect();
+                    i = emit(&mut out, &formula[i..k], &names, k);
+                } else {
+                    out.push_str(&formula[i..j]);
+                    i = j;
+                }
+            }
+            _ => {
+                let ch = formula[i..].chars().next().unwrap_or('\0');
+                out.push(ch);
+                i += ch.len_utf8();
+            }
+        }
+    }
+    out
+}
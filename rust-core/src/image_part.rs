@@ -0,0 +1,317 @@
+//! image_part.rs – картинки на листе: `xl/media/*`, часть рисунка
+//! `xl/drawings/drawingN.xml` с привязкой `<xdr:twoCellAnchor>` и связи между ними.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::files_part::xml_escape;
+use crate::rels_part::NS_R;
+use crate::sheet_xml::{
+    ensure_root_ns, find_child, find_elem, get_attr, insert_child, root_prefix,
+};
+use crate::style::parse_cell;
+use anyhow::{Context, Result};
+use memchr::memmem;
+use std::collections::HashMap;
+use std::path::Path;
+
+const REL_DRAWING: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing";
+
+/// EMU в одном пикселе (96 dpi).
+const EMU_PER_PX: u64 = 9525;
+/// Ширина столбца и высота строки Excel по умолчанию, в пикселях.
+const DEFAULT_COL_PX: u32 = 64;
+const DEFAULT_ROW_PX: u32 = 20;
+
+const EMPTY_DRAWING: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<xdr:wsDr xmlns:xdr="http://schemas.openxmlformats.org/drawingml/2006/spreadsheetDrawing" xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main"></xdr:wsDr>"#;
+
+/// Размер и подпись картинки для [`XlsxEditor::insert_image`].
+/// Без `width`/`height` картинка вставляется в натуральную величину;
+/// если задана только одна сторона, вторая считается по пропорциям.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageOptions {
+    /// Ширина в пикселях.
+    pub width: Option<u32>,
+    /// Высота в пикселях.
+    pub height: Option<u32>,
+    /// Смещение от левого верхнего угла ячейки, в пикселях.
+    pub offset_x: u32,
+    pub offset_y: u32,
+    /// Замещающий текст (`descr`).
+    pub alt_text: Option<String>,
+}
+
+/// Формат картинки: расширение файла, ContentType и размер в пикселях.
+struct ImageInfo {
+    ext: &'static str,
+    content_type: &'static str,
+    width: u32,
+    height: u32,
+}
+
+impl XlsxEditor {
+    /// Вставляет картинку (PNG, JPEG или GIF) в текущий лист левым верхним углом
+    /// в ячейку `coord`. Если у листа уже есть рисунок, картинка добавляется в него.
+    pub fn insert_image(
+        &mut self,
+        coord: &str,
+        image: &[u8],
+        opts: ImageOptions,
+    ) -> Result<&mut Self, XlsxError> {
+        let info = image_info(image)?;
+        let (col0, row) = parse_cell(coord)?;
+        let (w, h) = match (opts.width, opts.height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, scale(info.height, w, info.width)),
+            (None, Some(h)) => (scale(info.width, h, info.height), h),
+            (None, None) => (info.width, info.height),
+        };
+        if w == 0 || h == 0 {
+            bail!("image size must be positive, got {w}x{h}");
+        }
+
+        // ---- xl/media/imageN.ext
+        let names = self.part_names()?;
+        let media = (1..)
+            .map(|n| format!("xl/media/image{n}.{}", info.ext))
+            .find(|p| !names.contains(p))
+            .context("no free media name")?;
+        self.write_part(&media, image.to_vec());
+        self.add_content_type_default(info.ext, info.content_type)?;
+
+        // ---- рисунок листа: существующий или новый
+        let drawing = self
+            .sheet_rels()?
+            .into_iter()
+            .find(|r| r.typ == REL_DRAWING)
+            .map(|r| r.target);
+        let drawing = match drawing {
+            Some(path) => path,
+            None => {
+                let sheet_path = self.sheet_path.clone();
+                let (path, rid) =
+                    self.create_part(&sheet_path, "drawing", EMPTY_DRAWING.as_bytes().to_vec())?;
+                ensure_root_ns(&mut self.sheet_xml, "r", NS_R)?;
+                let tag = format!(r#"<{}drawing r:id="{rid}"/>"#, root_prefix(&self.sheet_xml));
+                insert_child(&mut self.sheet_xml, "drawing", tag.as_bytes())?;
+                path
+            }
+        };
+        let rid = self.add_rel(&drawing, "image", &media)?;
+
+        // ---- привязка к ячейкам
+        let (cols, default_col) = self.col_widths_px()?;
+        let (rows, default_row) = self.row_heights_px()?;
+        let col_px = |c| cols.get(&c).copied().unwrap_or(default_col);
+        let row_px = |r| rows.get(&r).copied().unwrap_or(default_row);
+        let (from_col, from_col_off) = advance(col0, opts.offset_x, col_px);
+        let (from_row, from_row_off) = advance(row - 1, opts.offset_y, row_px);
+        let (to_col, to_col_off) = advance(from_col, from_col_off + w, col_px);
+        let (to_row, to_row_off) = advance(from_row, from_row_off + h, row_px);
+
+        let mut xml = self
+            .read_part(&drawing)?
+            .with_context(|| format!("{drawing} not found"))?;
+        let id = max_shape_id(&xml) + 1;
+        let anchor = format!(
+            concat!(
+                r#"<xdr:twoCellAnchor editAs="oneCell">"#,
+                "<xdr:from><xdr:col>{}</xdr:col><xdr:colOff>{}</xdr:colOff><xdr:row>{}</xdr:row><xdr:rowOff>{}</xdr:rowOff></xdr:from>",
+                "<xdr:to><xdr:col>{}</xdr:col><xdr:colOff>{}</xdr:colOff><xdr:row>{}</xdr:row><xdr:rowOff>{}</xdr:rowOff></xdr:to>",
+                r#"<xdr:pic><xdr:nvPicPr><xdr:cNvPr id="{id}" name="Picture {n}" descr="{descr}"/>"#,
+                r#"<xdr:cNvPicPr><a:picLocks noChangeAspect="1"/></xdr:cNvPicPr></xdr:nvPicPr>"#,
+                r#"<xdr:blipFill><a:blip xmlns:r="{ns}" r:embed="{rid}"/><a:stretch><a:fillRect/></a:stretch></xdr:blipFill>"#,
+                r#"<xdr:spPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="{cx}" cy="{cy}"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></xdr:spPr>"#,
+                "</xdr:pic><xdr:clientData/></xdr:twoCellAnchor>",
+            ),
+            from_col,
+            emu(from_col_off),
+            from_row,
+            emu(from_row_off),
+            to_col,
+            emu(to_col_off),
+            to_row,
+            emu(to_row_off),
+            id = id,
+            n = id - 1,
+            descr = xml_escape(opts.alt_text.as_deref().unwrap_or_default()),
+            ns = NS_R,
+            rid = rid,
+            cx = emu(w),
+            cy = emu(h),
+        );
+        let close = memmem::rfind(&xml, b"</xdr:wsDr>")
+            .with_context(|| format!("</xdr:wsDr> not found in {drawing}"))?;
+        xml.splice(close..close, anchor.into_bytes());
+        self.write_part(&drawing, xml);
+        Ok(self)
+    }
+
+    /// То же, что [`Self::insert_image`], но картинка читается из файла `path`.
+    pub fn insert_image_file<P: AsRef<Path>>(
+        &mut self,
+        coord: &str,
+        path: P,
+        opts: ImageOptions,
+    ) -> Result<&mut Self, XlsxError> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+        self.insert_image(coord, &bytes, opts)
+    }
+
+    /* ----------------------------- helpers ------------------------------ */
+
+    /// Ширины столбцов из `<cols>` (0-based столбец → пиксели) и ширина по умолчанию.
+    fn col_widths_px(&self) -> Result<(HashMap<u32, u32>, u32)> {
+        let xml = &self.sheet_xml;
+        let default = self
+            .sheet_format_attr("defaultColWidth")?
+            .map_or(DEFAULT_COL_PX, col_px);
+        let mut out = HashMap::new();
+        let Some(cols) = find_child(xml, "cols")? else {
+            return Ok((out, default));
+        };
+        let q = format!("{}col", root_prefix(xml));
+        let mut from = cols.start + 1;
+        while let Some(r) = find_elem(&xml[..cols.end], &q, from) {
+            from = r.end;
+            let tag = &xml[r];
+            let num = |a| get_attr(tag, a).and_then(|v| v.parse::<u32>().ok());
+            let (Some(min), Some(max)) = (num("min"), num("max")) else {
+                continue;
+            };
+            let hidden = get_attr(tag, "hidden").is_some_and(|v| v == "1" || v == "true");
+            let width = get_attr(tag, "width").and_then(|v| v.parse::<f64>().ok());
+            let px = match (hidden, width) {
+                (true, _) => 0,
+                (false, Some(w)) => col_px(w),
+                (false, None) => continue,
+            };
+            // последний <col> часто тянется до 16384 — картинке столько не нужно
+            for c in min..=max.min(min + 1024) {
+                out.insert(c - 1, px);
+            }
+        }
+        Ok((out, default))
+    }
+
+    /// Высоты строк с атрибутом `ht` (0-based строка → пиксели) и высота по умолчанию.
+    fn row_heights_px(&mut self) -> Result<(HashMap<u32, u32>, u32)> {
+        let default = self
+            .sheet_format_attr("defaultRowHeight")?
+            .map_or(DEFAULT_ROW_PX, row_px);
+        let mut out = HashMap::new();
+        self.for_each_row_tag(|r, tag| {
+            let hidden = get_attr(tag, "hidden").is_some_and(|v| v == "1" || v == "true");
+            let ht = get_attr(tag, "ht").and_then(|v| v.parse::<f64>().ok());
+            match (hidden, ht) {
+                (true, _) => {
+                    out.insert(r - 1, 0);
+                }
+                (false, Some(pt)) => {
+                    out.insert(r - 1, row_px(pt));
+                }
+                (false, None) => {}
+            }
+            None
+        })?;
+        Ok((out, default))
+    }
+
+    /// Числовой атрибут `<sheetFormatPr>`.
+    fn sheet_format_attr(&self, name: &str) -> Result<Option<f64>> {
+        Ok(find_child(&self.sheet_xml, "sheetFormatPr")?
+            .and_then(|r| get_attr(&self.sheet_xml[r], name))
+            .and_then(|v| v.parse().ok()))
+    }
+}
+
+/// Сдвигается от столбца/строки `i` на `px` пикселей: возвращает номер
+/// ячейки, в которую попали, и остаток смещения внутри неё.
+fn advance(mut i: u32, mut px: u32, size: impl Fn(u32) -> u32) -> (u32, u32) {
+    // скрытые (нулевые) ячейки просто пропускаются
+    while px >= size(i) && i < 1_048_576 {
+        px -= size(i);
+        i += 1;
+    }
+    (i, px)
+}
+
+/// Ширина столбца в символах → пиксели (шрифт по умолчанию, вместе с полями ячейки).
+fn col_px(width: f64) -> u32 {
+    (width * 7.0 + 5.0).trunc() as u32
+}
+
+/// Пункты → пиксели при 96 dpi.
+fn row_px(pt: f64) -> u32 {
+    (pt * 4.0 / 3.0).round() as u32
+}
+
+fn emu(px: u32) -> u64 {
+    px as u64 * EMU_PER_PX
+}
+
+fn scale(side: u32, num: u32, den: u32) -> u32 {
+    (side as u64 * num as u64 / den.max(1) as u64) as u32
+}
+
+/// Наибольший `id` фигур в рисунке (`<xdr:cNvPr id>`).
+fn max_shape_id(xml: &[u8]) -> u32 {
+    let mut max = 1;
+    let mut from = 0;
+    while let Some(r) = find_elem(xml, "xdr:cNvPr", from) {
+        from = r.end;
+        if let Some(id) = get_attr(&xml[r], "id").and_then(|v| v.parse().ok()) {
+            max = max.max(id);
+        }
+    }
+    max
+}
+
+/// Определяет формат и размер картинки по заголовку.
+fn image_info(b: &[u8]) -> Result<ImageInfo> {
+    let be16 = |i: usize| u16::from_be_bytes([b[i], b[i + 1]]) as u32;
+    let be32 = |i: usize| u32::from_be_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+    let le16 = |i: usize| u16::from_le_bytes([b[i], b[i + 1]]) as u32;
+
+    if b.len() >= 24 && b.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Ok(ImageInfo {
+            ext: "png",
+            content_type: "image/png",
+            width: be32(16),
+            height: be32(20),
+        });
+    }
+    if b.len() >= 10 && (b.starts_with(b"GIF87a") || b.starts_with(b"GIF89a")) {
+        return Ok(ImageInfo {
+            ext: "gif",
+            content_type: "image/gif",
+            width: le16(6),
+            height: le16(8),
+        });
+    }
+    if b.starts_with(&[0xFF, 0xD8]) {
+        // ищем маркер SOFn (кроме DHT, JPG и DAC)
+        let mut i = 2;
+        while i + 9 < b.len() {
+            if b[i] != 0xFF {
+                i += 1;
+                continue;
+            }
+            let marker = b[i + 1];
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Ok(ImageInfo {
+                    ext: "jpeg",
+                    content_type: "image/jpeg",
+                    width: be16(i + 7),
+                    height: be16(i + 5),
+                });
+            }
+            i += 2 + be16(i + 2) as usize;
+        }
+        bail!("JPEG image without a frame header");
+    }
+    bail!("unsupported image format (expected PNG, JPEG or GIF)")
+}
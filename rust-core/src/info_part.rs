@@ -0,0 +1,223 @@
+//! info_part.rs – сведения о книге без открытия редактора: листы, их
+//! видимость и размеры, определённые имена, макросы и программа-генератор.
+//! Листы читаются из архива потоком, целиком в память не загружаются.
+
+use crate::error::{XlsxError, next_event, next_event_into, open_zip};
+use crate::read_part::push_entity;
+use crate::rels_part::{parse_rels, rels_path_of};
+use crate::sheets_part::SheetVisibility;
+use crate::workbook_part::parse_sheet_entries;
+use anyhow::{Context, Result};
+use quick_xml::{Reader, events::Event};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+use zip::ZipArchive;
+
+const WORKBOOK: &str = "xl/workbook.xml";
+
+/// Результат [`scan_info`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkbookInfo {
+    /// Листы в порядке `<sheets>`.
+    pub sheets: Vec<SheetInfo>,
+    pub defined_names: Vec<DefinedNameInfo>,
+    /// Есть ли проект VBA (`xl/vbaProject.bin`).
+    pub has_macros: bool,
+    /// `<Application>` из свойств документа: «Microsoft Excel», «LibreOffice/…».
+    pub application: Option<String>,
+    /// `<AppVersion>`: «16.0300» и т.п.
+    pub app_version: Option<String>,
+}
+
+/// Лист книги.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SheetInfo {
+    pub name: String,
+    pub visibility: SheetVisibility,
+    /// `<dimension ref>` как его записал генератор (может быть устаревшим).
+    pub dimension: Option<String>,
+    /// Число строк `<row>` в `<sheetData>`.
+    pub rows: u32,
+    /// Номер последней строки; `0` — лист пуст.
+    pub last_row: u32,
+}
+
+/// Определённое имя из `<definedNames>`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DefinedNameInfo {
+    pub name: String,
+    /// Формула имени: `Sheet1!$A$1:$C$10`.
+    pub value: String,
+    /// Лист области видимости (0-based), `None` — вся книга.
+    pub local_sheet_id: Option<usize>,
+    pub hidden: bool,
+}
+
+/// Сведения о книге `src` для выбора, что с ней делать, до открытия
+/// [`XlsxEditor`](crate::XlsxEditor): листы с видимостью, `<dimension>`
+/// и числом строк, определённые имена, наличие макросов и генератор файла.
+pub fn scan_info<P: AsRef<Path>>(src: P) -> Result<WorkbookInfo, XlsxError> {
+    let mut zip = open_zip(src)?;
+    let wb = read_entry(&mut zip, WORKBOOK)?.context("workbook.xml not found")?;
+    let wb_rels_path = rels_path_of(WORKBOOK);
+    let wb_rels = read_entry(&mut zip, &wb_rels_path)?.context("workbook.xml.rels not found")?;
+
+    let entries = parse_sheet_entries(&wb, &wb_rels)?;
+    let (states, defined_names) = parse_workbook(&wb)?;
+    let mut sheets = Vec::with_capacity(entries.len());
+    for (entry, visibility) in entries.into_iter().zip(states) {
+        let mut sheet = SheetInfo {
+            name: entry.name,
+            visibility,
+            ..Default::default()
+        };
+        if let Ok(file) = zip.by_name(&entry.path) {
+            scan_sheet(&entry.path, BufReader::new(file), &mut sheet)?;
+        }
+        sheets.push(sheet);
+    }
+
+    let has_macros = parse_rels(&wb_rels, WORKBOOK)?
+        .iter()
+        .any(|r| r.kind() == "vbaProject");
+    let mut info = WorkbookInfo {
+        sheets,
+        defined_names,
+        has_macros,
+        ..Default::default()
+    };
+    let root_rels = read_entry(&mut zip, "_rels/.rels")?.unwrap_or_default();
+    let app = parse_rels(&root_rels, "")?
+        .into_iter()
+        .find(|r| r.kind() == "extended-properties")
+        .map(|r| r.target);
+    if let Some(xml) = app.map(|p| read_entry(&mut zip, &p)).transpose()?.flatten() {
+        (info.application, info.app_version) = parse_app(&xml)?;
+    }
+    Ok(info)
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Option<Vec<u8>>, XlsxError> {
+    let Ok(mut f) = zip.by_name(name) else {
+        return Ok(None);
+    };
+    let mut buf = Vec::with_capacity(f.size() as usize);
+    f.read_to_end(&mut buf)?;
+    Ok(Some(buf))
+}
+
+/// Видимость листов по порядку `<sheets>` и определённые имена.
+fn parse_workbook(xml: &[u8]) -> Result<(Vec<SheetVisibility>, Vec<DefinedNameInfo>)> {
+    let mut rdr = Reader::from_reader(xml);
+    let (mut states, mut names) = (Vec::new(), Vec::new());
+    let mut cur: Option<DefinedNameInfo> = None;
+    loop {
+        match next_event(&mut rdr, WORKBOOK)? {
+            Event::Start(ref e) | Event::Empty(ref e) if e.local_name().as_ref() == b"sheet" => {
+                let state = e
+                    .attributes()
+                    .with_checks(false)
+                    .flatten()
+                    .find(|a| a.key.as_ref() == b"state")
+                    .map(|a| String::from_utf8_lossy(&a.value).parse())
+                    .transpose()?;
+                states.push(state.unwrap_or_default());
+            }
+            Event::Start(ref e) if e.local_name().as_ref() == b"definedName" => {
+                let mut name = DefinedNameInfo::default();
+                for a in e.attributes().with_checks(false).flatten() {
+                    match a.key.as_ref() {
+                        b"name" => name.name = a.unescape_value()?.into_owned(),
+                        b"localSheetId" => name.local_sheet_id = a.unescape_value()?.parse().ok(),
+                        b"hidden" => name.hidden = matches!(&*a.value, b"1" | b"true"),
+                        _ => {}
+                    }
+                }
+                cur = Some(name);
+            }
+            Event::Text(ref t) => {
+                if let Some(n) = cur.as_mut() {
+                    n.value.push_str(&t.decode()?);
+                }
+            }
+            Event::GeneralRef(ref r) => {
+                if let Some(n) = cur.as_mut() {
+                    push_entity(&mut n.value, r)?;
+                }
+            }
+            Event::End(ref e) if e.local_name().as_ref() == b"definedName" => {
+                names.extend(cur.take());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((states, names))
+}
+
+/// `<dimension>`, число и номер последней строки — потоком, без загрузки листа.
+fn scan_sheet<R: Read>(part: &str, src: BufReader<R>, sheet: &mut SheetInfo) -> Result<()> {
+    let mut rdr = Reader::from_reader(src);
+    let mut buf = Vec::new();
+    loop {
+        match next_event_into(&mut rdr, &mut buf, part)? {
+            Event::Start(ref e) | Event::Empty(ref e) => match e.local_name().as_ref() {
+                b"dimension" => {
+                    sheet.dimension = e
+                        .attributes()
+                        .with_checks(false)
+                        .flatten()
+                        .find(|a| a.key.as_ref() == b"ref")
+                        .map(|a| String::from_utf8_lossy(&a.value).into_owned());
+                }
+                b"row" => {
+                    sheet.rows += 1;
+                    let r = e
+                        .attributes()
+                        .with_checks(false)
+                        .flatten()
+                        .find(|a| a.key.as_ref() == b"r")
+                        .and_then(|a| std::str::from_utf8(&a.value).ok()?.parse().ok());
+                    // строка без `r` идёт следом за предыдущей
+                    sheet.last_row = r.unwrap_or(sheet.last_row + 1).max(sheet.last_row);
+                }
+                _ => {}
+            },
+            Event::End(ref e) if e.local_name().as_ref() == b"sheetData" => break,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+/// `<Application>` и `<AppVersion>` из docProps/app.xml.
+fn parse_app(xml: &[u8]) -> Result<(Option<String>, Option<String>)> {
+    let mut rdr = Reader::from_reader(xml);
+    let (mut app, mut version) = (None, None);
+    let mut field: Option<&mut Option<String>> = None;
+    loop {
+        match next_event(&mut rdr, "docProps/app.xml")? {
+            Event::Start(ref e) => {
+                field = match e.local_name().as_ref() {
+                    b"Application" => Some(&mut app),
+                    b"AppVersion" => Some(&mut version),
+                    _ => None,
+                }
+            }
+            Event::Text(ref t) => {
+                if let Some(f) = field.as_mut() {
+                    f.get_or_insert_default().push_str(&t.decode()?);
+                }
+            }
+            Event::End(_) => field = None,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((app, version))
+}
@@ -0,0 +1,199 @@
+//! json_part.rs – значения ячеек из JSON для C API и CLI: массив скаляров
+//! (строка) или объект «ключ → скаляр» (шаблон), без вложенных массивов и
+//! объектов. `null` — пустая ячейка, числа и `true`/`false` — как есть,
+//! строки — через [`CellValue::parse`].
+
+use crate::error::{XlsxError, bail, ensure};
+use crate::template_part::CellValue;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+/// `[1, "a", true, null, "=A1*2"]` → значения ячеек строки.
+pub fn parse_row(src: &str) -> Result<Vec<CellValue>, XlsxError> {
+    let mut p = Parser::new(src);
+    p.expect(b'[')?;
+    let mut out = Vec::new();
+    if !p.eat(b']') {
+        loop {
+            out.push(p.value()?);
+            if p.eat(b']') {
+                break;
+            }
+            p.expect(b',')?;
+        }
+    }
+    p.end()?;
+    Ok(out)
+}
+
+/// `{"name": "Bob", "total": 42}` → значения для
+/// [`crate::XlsxEditor::fill_template`]; у повторённого ключа — последнее.
+pub fn parse_map(src: &str) -> Result<HashMap<String, CellValue>, XlsxError> {
+    let mut p = Parser::new(src);
+    p.expect(b'{')?;
+    let mut out = HashMap::new();
+    if !p.eat(b'}') {
+        loop {
+            p.skip_ws();
+            ensure!(
+                p.s.get(p.pos) == Some(&b'"'),
+                "expected a key at byte {}",
+                p.pos
+            );
+            let key = p.string()?;
+            p.expect(b':')?;
+            out.insert(key, p.value()?);
+            if p.eat(b'}') {
+                break;
+            }
+            p.expect(b',')?;
+        }
+    }
+    p.end()?;
+    Ok(out)
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser {
+            s: src.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.skip_ws();
+        ensure!(
+            self.pos == self.s.len(),
+            "trailing data at byte {}",
+            self.pos
+        );
+        Ok(())
+    }
+
+    fn skip_ws(&mut self) {
+        while self.s.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_ws();
+        let hit = self.s.get(self.pos) == Some(&c);
+        self.pos += hit as usize;
+        hit
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        ensure!(self.eat(c), "expected '{}' at byte {}", c as char, self.pos);
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<CellValue> {
+        self.skip_ws();
+        let rest = &self.s[self.pos..];
+        for (lit, v) in [
+            (&b"null"[..], CellValue::Empty),
+            (b"true", CellValue::Bool(true)),
+            (b"false", CellValue::Bool(false)),
+        ] {
+            if rest.starts_with(lit) {
+                self.pos += lit.len();
+                return Ok(v);
+            }
+        }
+        match rest.first() {
+            Some(b'"') => Ok(CellValue::parse(&self.string()?)),
+            Some(b'-' | b'0'..=b'9') => {
+                let len = rest
+                    .iter()
+                    .take_while(|c| matches!(c, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+                    .count();
+                let text = std::str::from_utf8(&rest[..len])?;
+                let n: f64 = text.parse().with_context(|| format!("bad number {text}"))?;
+                self.pos += len;
+                Ok(CellValue::Number(n))
+            }
+            Some(c) => bail!("unexpected '{}' at byte {}", *c as char, self.pos),
+            None => bail!("unexpected end of input"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.pos += 1; // открывающая кавычка
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while self
+                .s
+                .get(self.pos)
+                .is_some_and(|&c| c != b'"' && c != b'\\')
+            {
+                self.pos += 1;
+            }
+            out.push_str(std::str::from_utf8(&self.s[start..self.pos])?);
+            match self.s.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(_) => {
+                    let esc = *self.s.get(self.pos + 1).context("unterminated string")?;
+                    self.pos += 2;
+                    match esc {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => out.push(self.unicode()?),
+                        c => bail!("bad escape \\{} at byte {}", c as char, self.pos - 1),
+                    }
+                }
+                None => bail!("unterminated string"),
+            }
+        }
+    }
+
+    /// Код `\uXXXX` после `\u`, суррогатные пары склеиваются.
+    fn unicode(&mut self) -> Result<char> {
+        let hi = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&hi) {
+            ensure!(
+                self.s[self.pos..].starts_with(b"\\u"),
+                "lone surrogate at byte {}",
+                self.pos
+            );
+            self.pos += 2;
+            let lo = self.hex4()?;
+            ensure!(
+                (0xDC00..0xE000).contains(&lo),
+                "bad surrogate pair at byte {}",
+                self.pos
+            );
+            0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00)
+        } else {
+            hi
+        };
+        char::from_u32(code).with_context(|| format!("bad \\u escape at byte {}", self.pos))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self
+            .s
+            .get(self.pos..self.pos + 4)
+            .context("short \\u escape")?;
+        let n = u32::from_str_radix(std::str::from_utf8(digits)?, 16)
+            .with_context(|| format!("bad \\u escape at byte {}", self.pos))?;
+        self.pos += 4;
+        Ok(n)
+    }
+}
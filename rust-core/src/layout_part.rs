@@ -0,0 +1,274 @@
+//! layout_part.rs – переводы строк и отступы внутри `<sheetData>` при save().
+//!
+//! Новые строки редактор пишет в одну строку XML, а шаблоны часто бывают
+//! отформатированы с отступами: лист выходит разнородным, его неудобно
+//! сравнивать как текст, и некоторые разборщики на это жалуются.
+//! [`XlsxEditor::set_xml_layout`] выравнивает `<sheetData>` изменённых
+//! листов под отступы исходного листа или убирает их совсем. Пробелы
+//! меняются только между тегами, где они ничего не значат: текст ячеек
+//! (`<t>`, `<v>`, `<f>`) не трогается.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, next_event_into};
+use anyhow::Result;
+use quick_xml::{Reader, events::Event};
+use std::io::{BufRead, BufReader};
+use std::ops::{ControlFlow, Range};
+use std::str::FromStr;
+
+/// Как save() расставляет переводы строк и отступы внутри `<sheetData>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum XmlLayout {
+    /// Как записано: у исходных строк остаются их отступы, новые строки
+    /// идут одной строкой XML.
+    #[default]
+    AsWritten,
+    /// Как в исходном листе: на каждом уровне (`<row>`, `<c>`, `<v>`…) —
+    /// те же перевод строки и отступ, что у исходных элементов этого уровня.
+    /// Уровни, которых в исходных строках нет, продолжают отступы остального
+    /// листа. Лист, записанный в одну строку, так и остаётся.
+    MatchSource,
+    /// Без пробелов между тегами: всё `<sheetData>` в одну строку.
+    Compact,
+}
+
+impl FromStr for XmlLayout {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        Ok(match s {
+            "as_written" => XmlLayout::AsWritten,
+            "match_source" => XmlLayout::MatchSource,
+            "compact" => XmlLayout::Compact,
+            _ => bail!("Unknown xml layout: {s}"),
+        })
+    }
+}
+
+impl XlsxEditor {
+    /// Как save() расставит отступы внутри `<sheetData>` изменённых листов
+    /// (см. [`XmlLayout`]). По умолчанию — [`XmlLayout::AsWritten`], лист
+    /// пишется как есть.
+    pub fn set_xml_layout(&mut self, layout: XmlLayout) -> &mut Self {
+        self.xml_layout = layout;
+        self
+    }
+
+    /// Выравнивает пробелы в `<sheetData>` листа `path` по
+    /// [`Self::set_xml_layout`]; `true` — XML изменён. Отступы для
+    /// [`XmlLayout::MatchSource`] берутся из листа в исходном архиве, а у
+    /// нового листа — из него самого (копия шаблонного листа их сохранила).
+    pub(crate) fn apply_xml_layout(&self, path: &str, xml: &mut Vec<u8>) -> Result<bool> {
+        let indents = match self.xml_layout {
+            XmlLayout::AsWritten => return Ok(false),
+            XmlLayout::Compact => Indents::default(),
+            XmlLayout::MatchSource => {
+                let mut zip = self.src.open()?;
+                match zip.by_name(path) {
+                    Ok(part) => {
+                        Indents::learn(&mut Reader::from_reader(BufReader::new(part)), path)?
+                    }
+                    Err(_) => Indents::learn(&mut Reader::from_reader(&xml[..]), path)?,
+                }
+            }
+        };
+
+        let mut out = Vec::new();
+        let mut copied = 0;
+        let mut wanted: Vec<Vec<u8>> = Vec::new(); // пробелы по уровню, пустые — без отступа
+        walk_gaps(&mut Reader::from_reader(&xml[..]), path, |gap| {
+            if let Some(level) = gap.data {
+                while wanted.len() <= level {
+                    wanted.push(indents.at(wanted.len()).unwrap_or_default());
+                }
+                let want = &wanted[level][..];
+                if gap.ws != want {
+                    out.extend_from_slice(&xml[copied..gap.at.start]);
+                    out.extend_from_slice(want);
+                    copied = gap.at.end;
+                }
+            }
+            ControlFlow::Continue(())
+        })?;
+        if copied == 0 && out.is_empty() {
+            return Ok(false);
+        }
+        out.extend_from_slice(&xml[copied..]);
+        *xml = out;
+        Ok(true)
+    }
+}
+
+/// Промежуток перед тегом, где пробелы — только форматирование.
+struct Gap<'a> {
+    /// Где лежат пробелы (пустой диапазон — их нет).
+    at: Range<usize>,
+    ws: &'a [u8],
+    /// Уровень элемента тега в документе: корень — 0.
+    depth: usize,
+    /// Уровень внутри `<sheetData>`: 0 — `</sheetData>`, 1 — `<row>`, 2 —
+    /// `<c>`…; `None` — вне `<sheetData>`.
+    data: Option<usize>,
+}
+
+/// Что стояло перед очередным событием (не считая пробелов).
+#[derive(PartialEq)]
+enum After {
+    Open,
+    /// Закрытый или пустой дочерний элемент.
+    Child,
+    Text,
+    Other,
+}
+
+/// Вызывает `f` для каждого [`Gap`] листа по порядку, пока она не вернёт
+/// `Break`. Пробелы перед закрывающим тегом считаются форматированием,
+/// только если перед ними дочерний элемент: в `<t>  </t>` это текст.
+fn walk_gaps<R: BufRead>(
+    rdr: &mut Reader<R>,
+    part: &str,
+    mut f: impl FnMut(Gap<'_>) -> ControlFlow<()>,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut ws = Vec::new();
+    let mut ws_at = 0..0;
+    let mut depth = 0;
+    let mut data: Option<usize> = None; // уровень `<sheetData>`, пока внутри него
+    let mut after = After::Other;
+    loop {
+        let start = rdr.buffer_position() as usize;
+        buf.clear();
+        let ev = next_event_into(rdr, &mut buf, part)?;
+        let end = rdr.buffer_position() as usize;
+        let gap = |ws, depth: usize, data: Option<usize>| Gap {
+            at: ws_at.clone(),
+            ws,
+            depth,
+            data: data.map(|d| depth - d),
+        };
+        let flow = match &ev {
+            Event::Text(t) if t.iter().all(u8::is_ascii_whitespace) => {
+                ws.clear();
+                ws.extend_from_slice(t);
+                ws_at = start..end;
+                continue;
+            }
+            Event::Start(e) => {
+                let flow = match after {
+                    After::Text => ControlFlow::Continue(()),
+                    _ => f(gap(&ws[..], depth, data)),
+                };
+                if depth == 1 && data.is_none() && e.local_name().as_ref() == b"sheetData" {
+                    data = Some(depth);
+                }
+                depth += 1;
+                after = After::Open;
+                flow
+            }
+            Event::Empty(_) => {
+                let flow = match after {
+                    After::Text => ControlFlow::Continue(()),
+                    _ => f(gap(&ws[..], depth, data)),
+                };
+                after = After::Child;
+                flow
+            }
+            Event::End(_) => {
+                depth = depth.saturating_sub(1);
+                let flow = match after {
+                    After::Child => f(gap(&ws[..], depth, data)),
+                    _ => ControlFlow::Continue(()),
+                };
+                if data == Some(depth) {
+                    data = None;
+                }
+                after = After::Child;
+                flow
+            }
+            Event::Eof => break,
+            Event::Text(_) | Event::GeneralRef(_) | Event::CData(_) => {
+                after = After::Text;
+                ControlFlow::Continue(())
+            }
+            _ => {
+                after = After::Other;
+                ControlFlow::Continue(())
+            }
+        };
+        ws.clear();
+        ws_at = end..end;
+        if flow.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Отступы исходного листа по уровням.
+#[derive(Default)]
+struct Indents {
+    /// Внутри `<sheetData>` по уровню [`Gap::data`]: `Some(ws)` — так было
+    /// в исходных строках (пустой `ws` — без отступа), `None` — уровня там нет.
+    data: Vec<Option<Vec<u8>>>,
+    /// Во всём листе по уровню [`Gap::depth`], первый встреченный.
+    doc: Vec<Option<Vec<u8>>>,
+}
+
+impl Indents {
+    /// Собирает отступы листа; внутри `<sheetData>` хватает первой строки с
+    /// ячейками, дальше лист не читается.
+    fn learn<R: BufRead>(rdr: &mut Reader<R>, part: &str) -> Result<Self> {
+        let mut me = Indents::default();
+        let mut cells = false;
+        walk_gaps(rdr, part, |gap| {
+            let slot = |v: &mut Vec<Option<Vec<u8>>>, i: usize| {
+                if v.len() <= i {
+                    v.resize(i + 1, None);
+                }
+                v[i].get_or_insert_with(|| gap.ws.to_vec());
+            };
+            slot(&mut me.doc, gap.depth);
+            match gap.data {
+                Some(1) if cells => return ControlFlow::Break(()),
+                Some(level) => {
+                    cells |= level >= 2;
+                    slot(&mut me.data, level);
+                }
+                None => {}
+            }
+            ControlFlow::Continue(())
+        })?;
+        Ok(me)
+    }
+
+    /// Пробелы перед тегом уровня `level` внутри `<sheetData>`; `None` — без
+    /// отступа. Уровень, которого в исходных строках нет, берёт отступ
+    /// элементов той же глубины вне `<sheetData>` или продолжает предыдущий.
+    fn at(&self, level: usize) -> Option<Vec<u8>> {
+        if let Some(Some(ws)) = self.data.get(level) {
+            return Some(ws.clone()).filter(|ws| !ws.is_empty());
+        }
+        // `<sheetData>` — ребёнок корня: его уровни в документе на один глубже
+        if let Some(Some(ws)) = self.doc.get(level + 1) {
+            return Some(ws.clone()).filter(|ws| !ws.is_empty());
+        }
+        let prev = self.at(level.checked_sub(1)?)?;
+        let mut ws = prev;
+        ws.extend_from_slice(self.unit()?);
+        Some(ws)
+    }
+
+    /// Шаг отступа: на сколько следующий уровень глубже предыдущего.
+    fn unit(&self) -> Option<&[u8]> {
+        indent_step(&self.data).or_else(|| indent_step(&self.doc))
+    }
+}
+
+/// Разница между отступами двух соседних уровней, начиная с глубоких.
+fn indent_step(levels: &[Option<Vec<u8>>]) -> Option<&[u8]> {
+    levels.windows(2).rev().find_map(|w| match w {
+        [Some(a), Some(b)] if !a.is_empty() && b.len() > a.len() && b.starts_with(a) => {
+            Some(&b[a.len()..])
+        }
+        _ => None,
+    })
+}
@@ -0,0 +1,71 @@
+//! lazy_part.rs – части книги, которые читаются из архива при первом обращении.
+//!
+//! Для дозаписи значений styles.xml, workbook.xml и его связи не нужны: пока
+//! их никто не спросил, они не читаются, а `save()` копирует исходные записи
+//! как есть.
+//!
+//! Прочитанное содержимое лежит в `Arc`: снимок части для отката его не
+//! копирует, копия делается при первой правке после снимка.
+
+use crate::error::Source;
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::sync::{Arc, OnceLock};
+
+pub(crate) const STYLES: &str = "xl/styles.xml";
+pub(crate) const WORKBOOK: &str = "xl/workbook.xml";
+pub(crate) const WORKBOOK_RELS: &str = "xl/_rels/workbook.xml.rels";
+
+#[derive(Clone)]
+pub(crate) struct LazyPart {
+    path: &'static str,
+    data: OnceLock<Arc<Vec<u8>>>,
+}
+
+impl LazyPart {
+    /// Часть `path`, ещё не прочитанная.
+    pub(crate) fn new(path: &'static str) -> Self {
+        LazyPart {
+            path,
+            data: OnceLock::new(),
+        }
+    }
+
+    /// Часть `path` с уже прочитанным содержимым.
+    pub(crate) fn with(path: &'static str, data: Vec<u8>) -> Self {
+        LazyPart {
+            path,
+            data: OnceLock::from(Arc::new(data)),
+        }
+    }
+
+    /// Содержимое части; при первом обращении читается из `src`.
+    pub(crate) fn get(&self, src: &Source) -> Result<&Vec<u8>> {
+        if let Some(data) = self.data.get() {
+            return Ok(data.as_ref());
+        }
+        let mut zip = src.open()?;
+        let mut f = zip
+            .by_name(self.path)
+            .with_context(|| format!("{} not found", self.path))?;
+        let mut buf = Vec::with_capacity(f.size() as usize);
+        f.read_to_end(&mut buf)?;
+        Ok(self.data.get_or_init(|| Arc::new(buf)))
+    }
+
+    /// Как [`Self::get`], для правки.
+    pub(crate) fn get_mut(&mut self, src: &Source) -> Result<&mut Vec<u8>> {
+        self.get(src)?;
+        Ok(Arc::make_mut(self.data.get_mut().expect("part is loaded above")))
+    }
+
+    /// Заменяет содержимое части целиком.
+    pub(crate) fn set(&mut self, data: Vec<u8>) {
+        self.data = OnceLock::from(Arc::new(data));
+    }
+
+    /// Содержимое, если часть уже читали; `None` — она как в исходном архиве.
+    pub(crate) fn loaded(&self) -> Option<&Vec<u8>> {
+        self.data.get().map(Arc::as_ref)
+    }
+}
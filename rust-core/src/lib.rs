@@ -1,23 +1,93 @@
-// use mimalloc::MiMalloc;
-
-// #[global_allocator]
-// static GLOBAL: MiMalloc = MiMalloc;
+#[cfg(feature = "arrow")]
+mod arrow_part;
+pub mod audit_part;
+#[cfg(feature = "calc")]
+mod calc;
+mod calc_part;
+mod cell_xml;
+pub mod columns_part;
+pub mod conditional;
+#[cfg(feature = "encryption")]
+mod crypto_part;
+#[cfg(feature = "csv")]
+pub mod csv_part;
+pub mod diff_part;
+pub use diff_part::{WorkbookDiff, diff};
+pub mod docprops_part;
+pub mod error;
+pub use error::XlsxError;
+/// Аллокатор для `#[global_allocator]` в бинарниках (cli, замеры). Сама
+/// библиотека его не ставит: иначе он попал бы и в модули Python/Node/C.
+#[cfg(feature = "alloc-mimalloc")]
+pub use mimalloc::MiMalloc;
 pub mod files_part;
-mod polars_part;
-mod read_part;
+pub mod find_part;
+pub mod format_part;
+pub mod formula;
+pub mod image_part;
+pub mod info_part;
+pub mod json_part;
+pub use info_part::{WorkbookInfo, scan_info};
+mod lazy_part;
+pub mod layout_part;
+pub mod lint_part;
+mod macro_part;
+#[cfg(feature = "ndarray")]
+mod matrix_part;
+pub mod multi_part;
+pub mod numfmt;
+mod outline_part;
+mod pivot_part;
+pub mod polars_part;
+pub mod print_part;
+pub mod protection_part;
+pub mod range_part;
+pub mod read_part;
+pub mod records_part;
+mod registry_part;
+mod repair_part;
+mod rels_part;
+mod search;
+mod sheet_buf;
+mod sheet_xml;
+#[cfg(feature = "serde")]
+pub mod serde_part;
+// `#[derive(XlsxRow)]` ссылается на `::rust_core`, в том числе в тестах крейта
+#[cfg(feature = "serde")]
+extern crate self as rust_core;
+pub mod sheets_part;
+mod sorted_part;
+mod structure_part;
 pub mod style;
+mod table_part;
+pub mod template_part;
 mod test;
+pub mod theme_part;
+mod transaction_part;
+pub mod validation;
+pub mod view_part;
+#[cfg(feature = "wasm")]
+pub mod wasm_part;
+mod workbook_part;
 use std::{
     collections::HashMap,
-    fs::File,
+    fmt::Display,
     io::Read,
-    path::{Path, PathBuf},
+    path::Path,
+    sync::Arc,
 };
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result};
 use quick_xml::{Reader, Writer, events::Event};
 
-use crate::style::{AlignSpec, HorizAlignment, VertAlignment};
+use crate::cell_xml::{push_text_cell, push_text_row};
+use crate::error::{Source, bail, check_row, next_event, open_zip};
+use crate::lazy_part::LazyPart;
+use crate::sheet_buf::SheetBuf;
+use crate::sheet_xml::open_sheet_data;
+use crate::style::{AlignSpec, HorizAlignment, VertAlignment, parse_cell};
+use crate::table_part::{format_range, parse_range, rects_overlap};
+use crate::template_part::CellValue;
 // use tempfile::NamedTempFile;
 // use zip::{ZipArchive, ZipWriter, write::FileOptions};
 
@@ -30,6 +100,7 @@ struct FontKey {
     size_100: u32,
     bold: bool,
     italic: bool,
+    color: Option<String>, // Color в каноничной записи
 }
 #[derive(Hash, Eq, PartialEq, Clone)]
 struct StyleKey {
@@ -38,6 +109,7 @@ struct StyleKey {
     fill_id: Option<u32>,
     border_id: Option<u32>,
     align: Option<(Option<HorizAlignment>, Option<VertAlignment>, bool)>, // wrap
+    protection: Option<(bool, bool)>, // locked, hidden
 }
 #[allow(dead_code)]
 struct XfParts {
@@ -46,6 +118,7 @@ struct XfParts {
     fill_id: Option<u32>,
     border_id: Option<u32>,
     align: Option<AlignSpec>,
+    protection: Option<(bool, bool)>,
 }
 
 struct StyleIndex {
@@ -54,28 +127,47 @@ struct StyleIndex {
     numfmt_by_code: HashMap<String, u32>,
     next_custom_numfmt: u32, // >=164
 
+    fonts: Vec<FontKey>, // index == font_id
     font_by_key: HashMap<FontKey, u32>,
-    fill_by_rgb: HashMap<String, u32>,   // RGB в верхнем регистре
-    border_by_key: HashMap<String, u32>, // единый style для всех сторон
+    fill_by_color: HashMap<String, u32>, // Color в каноничной записи
+    border_by_key: HashMap<String, u32>, // единый style (и цвет) для всех сторон
 
     xf_by_key: HashMap<StyleKey, u32>,
+    dxf_by_xml: HashMap<String, u32>, // готовый <dxf>…</dxf> → dxfId
 
     fonts_count: u32,
     fills_count: u32,
     borders_count: u32,
+    dxfs_count: u32,
 }
 
 pub struct XlsxEditor {
-    src_path: PathBuf,
+    src: Source,
     sheet_path: String,
-    sheet_xml: Vec<u8>,
+    sheet_xml: SheetBuf, // XML текущего листа с отложенными правками ячеек
     last_row: u32,
-    styles_xml: Vec<u8>,               // содержимое styles.xml
-    workbook_xml: Vec<u8>,             // содержимое workbook.xml (может изменяться)
-    rels_xml: Vec<u8>,                 // содержимое workbook.xml.rels
-    new_files: Vec<(String, Vec<u8>)>, // новые или изменённые файлы для записи при save()
+    styles_xml: LazyPart,              // содержимое styles.xml, читается при первом обращении
+    workbook_xml: LazyPart,            // содержимое workbook.xml (может изменяться), тоже лениво
+    rels_xml: LazyPart,                // содержимое workbook.xml.rels, тоже лениво
+    source_pivots: bool,               // в исходном архиве есть кэши сводных таблиц
+    new_files: Vec<(String, Arc<Vec<u8>>)>, // новые или изменённые файлы для save(), общие со снимками
     styles_index: Option<StyleIndex>,
     loaded_files: std::collections::HashMap<String, Vec<u8>>,
+    shared_strings: Option<Vec<String>>, // xl/sharedStrings.xml, читается лениво
+    removed_parts: std::collections::HashSet<String>, // части, которые save() не запишет
+    recalc_on_change: bool, // save() включает пересчёт, если изменились листы с формулами
+    row_spans: bool,        // save() проставляет spans всем строкам изменённых листов
+    inherit_col_styles: bool, // дописанные ячейки без s получают стиль столбца
+    preserve_zip_metadata: bool, // save() переносит время/права/сжатие исходных записей
+    compression_threads: usize, // потоки для Deflate переписанных частей (rayon), 0 — пул rayon
+    allow_macro_removal: bool, // save() в .xlsx может выбросить проект VBA
+    stamp_modified: bool,   // save() ставит dcterms:modified в docProps/core.xml
+    modified_by: Option<String>, // cp:lastModifiedBy, который ставит save()
+    sheet_prefix: String,   // префикс элементов текущего листа в файле ("x:"), снят на время правок
+    xml_layout: layout_part::XmlLayout, // save() выравнивает отступы в sheetData изменённых листов
+    savepoints: Vec<transaction_part::Snapshot>, // снимки открытых транзакций, последний — внутренний
+    appended_rows: HashMap<String, u32>, // дописано строк с открытия по путям листов, для change_summary()
+    pending_growth: Option<table_part::PendingGrowth>, // дозапись текущего листа, ещё не отражённая в таблицах и автофильтре
 }
 
 /// Polars
@@ -84,24 +176,51 @@ pub struct XlsxEditor {
 impl XlsxEditor {
     /// Opens an XLSX file and prepares a specific sheet for editing by its name.
     ///
-    /// This function first scans the workbook to find the sheet ID corresponding to the given sheet name,
-    /// then calls `open_sheet` with the found ID.
+    /// This function first looks the sheet up in workbook.xml and resolves its part
+    /// through workbook.xml.rels (sheets may be reordered, copied or deleted,
+    /// so the part name does not have to match the sheet position).
     ///
     /// # Arguments
     /// * `src` - The path to the XLSX file.
     /// * `sheet_name` - The name of the sheet to open (e.g., "Sheet1").
     ///
     /// # Returns
-    /// A `Result` containing an `XlsxEditor` instance if successful, or an `anyhow::Error` otherwise.
-    pub fn open<P: AsRef<Path>>(src: P, sheet_name: &str) -> Result<Self> {
-        let sheet_names = scan(src.as_ref())?;
-        let sheet_id = sheet_names
-            .iter()
-            .position(|n| n == sheet_name)
-            .context(format!("Sheet '{}' not found", sheet_name))?
-            + 1;
-        println!("Sheet ID: {} with name {}", sheet_id, sheet_name);
-        Self::open_sheet(src, sheet_id)
+    /// A `Result` containing an `XlsxEditor` instance if successful, or an `XlsxError` otherwise.
+    pub fn open<P: AsRef<Path>>(src: P, sheet_name: &str) -> Result<Self, XlsxError> {
+        Ok(Self::open_source(
+            Source::Path(src.as_ref().to_path_buf()),
+            sheet_name,
+        )?)
+    }
+
+    /// Opens a workbook held in memory, e.g. a template fetched over the network.
+    ///
+    /// Nothing is read from or written to disk: parts are read from `data`, and
+    /// the result can be taken with [`XlsxEditor::save_to_bytes`].
+    pub fn open_bytes(data: impl Into<Arc<[u8]>>, sheet_name: &str) -> Result<Self, XlsxError> {
+        Ok(Self::open_source(Source::Bytes(data.into()), sheet_name)?)
+    }
+
+    pub(crate) fn open_source(src: Source, sheet_name: &str) -> Result<Self> {
+        let mut zip = src.open()?;
+        let mut read = |name: &str| -> Result<Vec<u8>> {
+            let mut f = zip.by_name(name).with_context(|| format!("{name} not found"))?;
+            let mut buf = Vec::with_capacity(f.size() as usize);
+            f.read_to_end(&mut buf)?;
+            Ok(buf)
+        };
+        let wb = read(lazy_part::WORKBOOK)?;
+        let rels = read(lazy_part::WORKBOOK_RELS)?;
+        let entries = workbook_part::parse_sheet_entries(&wb, &rels)?;
+        let entry = entries
+            .into_iter()
+            .find(|e| e.name == sheet_name)
+            .ok_or_else(|| XlsxError::SheetNotFound(sheet_name.to_owned()))?;
+        let mut editor = Self::open_part(src, entry.path)?;
+        // книга и её связи уже прочитаны ради пути листа — второй раз не читаем
+        editor.workbook_xml = LazyPart::with(lazy_part::WORKBOOK, wb);
+        editor.rels_xml = LazyPart::with(lazy_part::WORKBOOK_RELS, rels);
+        Ok(editor)
     }
 
     /// Appends a single row of cells to the end of the current sheet.
@@ -113,70 +232,65 @@ impl XlsxEditor {
     /// * `cells` - An iterator over values that can be converted to strings, representing the cells in the new row.
     ///
     /// # Returns
-    /// A `Result` indicating success or an `anyhow::Error` if the operation fails.
-    pub fn append_row<I, S>(&mut self, cells: I) -> Result<()>
+    /// A `Result` indicating success or an `XlsxError` if the operation fails.
+    pub fn append_row<I, S>(&mut self, cells: I) -> Result<(), XlsxError>
     where
         I: IntoIterator<Item = S>,
-        S: ToString,
+        S: Display,
     {
+        check_row(self.last_row as u64 + 1)?;
+        let old_last = self.last_row;
         self.last_row += 1;
         let row_num = self.last_row;
-        let mut writer = Writer::new(Vec::new());
-
-        // Create a new XML row element with the appropriate row number attribute.
-        writer
-            .create_element("row")
-            .with_attribute(("r", row_num.to_string().as_str()))
-            .write_inner_content(|w| {
-                let mut col = b'A';
-                for val in cells {
-                    let coord = format!("{}{}", col as char, row_num);
-                    let val_str = val.to_string();
-                    let is_formula = val_str.starts_with('=');
-                    let is_number = !is_formula && val_str.parse::<f64>().is_ok();
-
-                    {
-                        let mut c_elem =
-                            w.create_element("c").with_attribute(("r", coord.as_str()));
-                        if !is_number && !is_formula {
-                            c_elem = c_elem.with_attribute(("t", "inlineStr"));
-                        }
-                        c_elem.write_inner_content(|w2| {
-                            use quick_xml::events::BytesText;
-                            if is_formula {
-                                w2.create_element("f")
-                                    .write_text_content(BytesText::new(&val_str[1..]))?;
-                            } else if !is_number {
-                                w2.create_element("is").write_inner_content(|w3| {
-                                    w3.create_element("t")
-                                        .write_text_content(BytesText::new(&val_str))?;
-                                    Ok(())
-                                })?;
-                            } else {
-                                w2.create_element("v")
-                                    .write_text_content(BytesText::new(&val_str))?;
-                            }
-                            Ok(())
-                        })?;
-                    }
-                    col += 1;
-                }
-                Ok(())
-            })?;
-
-        let new_row_xml = writer.into_inner();
+        let mut new_row_xml = Vec::new();
+        let width = push_text_row(&mut new_row_xml, row_num, cells);
+
+        // Insert the new row right before the closing </sheetData> tag.
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, new_row_xml);
+        let written = (width > 0).then(|| (0, row_num, width - 1, row_num));
+        Ok(self.grow_ranges_on_append(old_last, written)?)
+    }
 
-        // Find the closing </sheetData> tag and insert the new row before it.
-        if let Some(pos) = self
-            .sheet_xml
-            .windows(12)
-            .rposition(|w| w == b"</sheetData>")
-        {
-            self.sheet_xml.splice(pos..pos, new_row_xml);
-            Ok(())
-        } else {
-            bail!("</sheetData> tag not found");
+    /// Appends a row of typed values to the end of the current sheet.
+    ///
+    /// Unlike [`append_row`](Self::append_row), values are not converted to strings and
+    /// parsed back: numbers, booleans and formulas are written as such, and text stays text
+    /// even when it looks like a number. `CellValue::Empty` leaves the cell blank.
+    ///
+    /// # Arguments
+    /// * `cells` - Values paired with an optional number format, e.g. `yyyy-mm-dd` for a
+    ///   date serial. Cells with the same format share one style.
+    pub fn append_values(&mut self, cells: &[(CellValue, Option<&str>)]) -> Result<(), XlsxError> {
+        check_row(self.last_row as u64 + 1)?;
+        let row = self.last_row + 1;
+        let mut styles: HashMap<&str, u32> = HashMap::new();
+        let mut xml = format!(r#"<row r="{row}">"#).into_bytes();
+        for (col, (value, fmt)) in (0u32..).zip(cells) {
+            if *value == CellValue::Empty {
+                continue;
+            }
+            let s = match fmt {
+                Some(f) => Some(match styles.get(f) {
+                    Some(&s) => s,
+                    None => {
+                        let s = self.ensure_style(Some(f), None, None, None, None)?;
+                        styles.insert(f, s);
+                        s
+                    }
+                }),
+                None => None,
+            };
+            xml.extend(value.cell_xml(col, row, s));
         }
+        xml.extend_from_slice(b"</row>");
+
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, xml);
+        let old_last = std::mem::replace(&mut self.last_row, row);
+        let width = cells.len() as u32;
+        let written = (width > 0).then(|| (0, row, width - 1, row));
+        Ok(self.grow_ranges_on_append(old_last, written)?)
     }
 
     /// Appends multiple rows (a table) to the end of the current sheet.
@@ -189,74 +303,25 @@ impl XlsxEditor {
     /// * `rows` - An iterator over iterators of values that can be converted to strings, representing the rows and cells of the table.
     ///
     /// # Returns
-    /// A `Result` indicating success or an `anyhow::Error` if the operation fails.
-    pub fn append_table<R, I, S>(&mut self, rows: R) -> Result<()>
+    /// A `Result` indicating success or an `XlsxError` if the operation fails.
+    pub fn append_table<R, I, S>(&mut self, rows: R) -> Result<(), XlsxError>
     where
         R: IntoIterator<Item = I>,
         I: IntoIterator<Item = S>,
-        S: ToString,
+        S: Display,
     {
-        ensure_sheetdata_open_close(&mut self.sheet_xml)?;
-
-        // Helper function to convert a 0-based column index to Excel column letters (e.g., 0 -> "A", 26 -> "AA").
-        fn col_idx_to_letters(mut idx: usize) -> String {
-            let mut s = String::new();
-            loop {
-                let rem = idx % 26;
-                s.insert(0, (b'A' + rem as u8) as char);
-                if idx < 26 {
-                    break;
-                }
-                idx = idx / 26 - 1;
-            }
-            s
-        }
-
         // Buffer to accumulate XML for all new rows.
         let mut bulk_rows_xml = Vec::<u8>::new();
+        let old_last = self.last_row;
+        let mut width = 0u32;
 
         for row in rows {
+            if let Err(e) = check_row(self.last_row as u64 + 1) {
+                self.last_row = old_last;
+                return Err(e);
+            }
             self.last_row += 1;
-            let row_num = self.last_row;
-
-            let mut writer = Writer::new(Vec::new());
-            writer
-                .create_element("row")
-                .with_attribute(("r", row_num.to_string().as_str()))
-                .write_inner_content(|w| {
-                    for (col_idx, val) in row.into_iter().enumerate() {
-                        let coord = format!("{}{}", col_idx_to_letters(col_idx), row_num);
-                        let val_str = val.to_string();
-                        let is_formula = val_str.starts_with('=');
-                        let is_number = !is_formula && val_str.parse::<f64>().is_ok();
-
-                        let mut c_elem =
-                            w.create_element("c").with_attribute(("r", coord.as_str()));
-                        if !is_number && !is_formula {
-                            c_elem = c_elem.with_attribute(("t", "inlineStr"));
-                        }
-                        c_elem.write_inner_content(|w2| {
-                            use quick_xml::events::BytesText;
-                            if is_formula {
-                                w2.create_element("f")
-                                    .write_text_content(BytesText::new(&val_str[1..]))?;
-                            } else if !is_number {
-                                w2.create_element("is").write_inner_content(|w3| {
-                                    w3.create_element("t")
-                                        .write_text_content(BytesText::new(&val_str))?;
-                                    Ok(())
-                                })?;
-                            } else {
-                                w2.create_element("v")
-                                    .write_text_content(BytesText::new(&val_str))?;
-                            }
-                            Ok(())
-                        })?;
-                    }
-                    Ok(())
-                })?;
-
-            bulk_rows_xml.extend_from_slice(&writer.into_inner());
+            width = width.max(push_text_row(&mut bulk_rows_xml, self.last_row, row));
         }
 
         // eprintln!(
@@ -269,17 +334,12 @@ impl XlsxEditor {
         //     self.sheet_path
         // );
 
-        // Find the closing </sheetData> tag and insert the new rows before it.
-        if let Some(pos) = self
-            .sheet_xml
-            .windows(12)
-            .rposition(|w| w == b"</sheetData>")
-        {
-            self.sheet_xml.splice(pos..pos, bulk_rows_xml);
-            Ok(())
-        } else {
-            bail!("</sheetData> tag not found");
-        }
+        // Insert the new rows right before the closing </sheetData> tag.
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, bulk_rows_xml);
+        let written =
+            (width > 0 && self.last_row > old_last).then(|| (0, old_last + 1, width - 1, self.last_row));
+        Ok(self.grow_ranges_on_append(old_last, written)?)
     }
 
     /// Appends multiple rows (a table) starting at a specified coordinate in the current sheet.
@@ -293,15 +353,13 @@ impl XlsxEditor {
     /// * `rows` - An iterator over iterators of values that can be converted to strings, representing the rows and cells of the table.
     ///
     /// # Returns
-    /// A `Result` indicating success or an `anyhow::Error` if the operation fails.
-    pub fn append_table_at<R, I, S>(&mut self, start_coord: &str, rows: R) -> Result<()>
+    /// A `Result` indicating success or an `XlsxError` if the operation fails.
+    pub fn append_table_at<R, I, S>(&mut self, start_coord: &str, rows: R) -> Result<(), XlsxError>
     where
         R: IntoIterator<Item = I>,
         I: IntoIterator<Item = S>,
         S: ToString,
     {
-        ensure_sheetdata_open_close(&mut self.sheet_xml)?;
-
         // Helper function to convert a 0-based column index to Excel column letters (e.g., 0 -> "A", 26 -> "AA").
         fn col_idx_to_letters(mut idx: usize) -> String {
             let mut s = String::new();
@@ -335,12 +393,19 @@ impl XlsxEditor {
         // Buffer to accumulate XML for new rows that need to be appended.
         let mut bulk_rows_xml = Vec::<u8>::new();
         let mut row_offset: usize = 0;
+        let old_last = self.last_row;
+        let mut width = 0usize;
 
         for row in rows {
+            if let Err(e) = check_row(current_row_num as u64 + row_offset as u64) {
+                self.last_row = old_last;
+                return Err(e);
+            }
             let abs_row = current_row_num + row_offset as u32;
             if abs_row <= self.last_row {
                 // If the row already exists, update cells within that row.
                 for (col_offset, val) in row.into_iter().enumerate() {
+                    width = width.max(col_offset + 1);
                     let coord = format!(
                         "{}{}",
                         col_idx_to_letters(start_col_idx + col_offset),
@@ -357,6 +422,7 @@ impl XlsxEditor {
                     .with_attribute(("r", abs_row.to_string().as_str()))
                     .write_inner_content(|w| {
                         for (col_offset, val) in row.into_iter().enumerate() {
+                            width = width.max(col_offset + 1);
                             let coord = format!(
                                 "{}{}",
                                 col_idx_to_letters(start_col_idx + col_offset),
@@ -364,7 +430,7 @@ impl XlsxEditor {
                             );
                             let val_str = val.to_string();
                             let is_formula = val_str.starts_with('=');
-                            let is_number = !is_formula && val_str.parse::<f64>().is_ok();
+                            let is_number = !is_formula && val_str.parse::<f64>().is_ok_and(f64::is_finite);
 
                             let mut c_elem =
                                 w.create_element("c").with_attribute(("r", coord.as_str()));
@@ -408,17 +474,15 @@ impl XlsxEditor {
         //     self.sheet_path
         // );
 
-        // Find the closing </sheetData> tag and insert the new rows before it.
-        if let Some(pos) = self
-            .sheet_xml
-            .windows(12)
-            .rposition(|w| w == b"</sheetData>")
-        {
-            self.sheet_xml.splice(pos..pos, bulk_rows_xml);
-            Ok(())
-        } else {
-            bail!("</sheetData> tag not found");
-        }
+        // Insert the new rows right before the closing </sheetData> tag.
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, bulk_rows_xml);
+        let written = (width > 0 && row_offset > 0).then(|| {
+            let c0 = start_col_idx as u32;
+            let r1 = current_row_num + row_offset as u32 - 1;
+            (c0, current_row_num, c0 + width as u32 - 1, r1)
+        });
+        Ok(self.grow_ranges_on_append(old_last, written)?)
     }
 
     /// Sets the value of a specific cell in the sheet.
@@ -431,174 +495,20 @@ impl XlsxEditor {
     /// * `value` - The value to set for the cell, which can be converted to a string.
     ///
     /// # Returns
-    /// A `Result` indicating success or an `anyhow::Error` if the operation fails.
-    pub fn set_cell<S: ToString>(&mut self, coord: &str, value: S) -> Result<()> {
-        // Extract row number from coordinate.
-        let row_start = coord
-            .find(|c: char| c.is_ascii_digit())
-            .context("invalid cell coordinate – no digits found")?;
-        let row_num: u32 = coord[row_start..]
-            .parse()
-            .context("invalid row number in cell coordinate")?;
-
-        let val_str = value.to_string();
-        let is_formula = val_str.starts_with('=');
-        let is_number = !is_formula && val_str.parse::<f64>().is_ok();
-
-        // Generate XML for the new cell.
-        let mut cell_writer = Writer::new(Vec::new());
-        // Create cell element with coordinate and type attributes.
-        let mut c_elem = cell_writer.create_element("c").with_attribute(("r", coord));
-        if !is_number && !is_formula {
-            c_elem = c_elem.with_attribute(("t", "inlineStr"));
-        }
-        c_elem.write_inner_content(|w2| {
-            use quick_xml::events::BytesText;
-            if is_formula {
-                w2.create_element("f")
-                    .write_text_content(BytesText::new(&val_str[1..]))?;
-            } else if !is_number {
-                // For strings, use <is><t> tags.
-                w2.create_element("is").write_inner_content(|w3| {
-                    w3.create_element("t")
-                        .write_text_content(BytesText::new(&val_str))?;
-                    Ok(())
-                })?;
-            } else {
-                // For numbers, use <v> tag.
-                w2.create_element("v")
-                    .write_text_content(BytesText::new(&val_str))?;
-            }
-            Ok(())
-        })?;
-        let cell_xml = cell_writer.into_inner();
-
-        // Find the row containing the target cell.
-        let row_marker = format!("<row r=\"{}\"", row_num);
-        if let Some(row_start) = self
-            .sheet_xml
-            .windows(row_marker.len())
-            .position(|w| w == row_marker.as_bytes())
-        {
-            // Find the end of the row.
-            if let Some(rel_end) = self.sheet_xml[row_start..]
-                .windows(6)
-                .position(|w| w == b"</row>")
-            {
-                let row_end = row_start + rel_end + 6; // 6 is the length of "</row>"
-                let mut row_slice = self.sheet_xml[row_start..row_end].to_vec();
-
-                // Find the cell within the row and replace it.
-                let cell_marker = format!("<c r=\"{}\"", coord);
-                if let Some(cell_pos) = row_slice
-                    .windows(cell_marker.len())
-                    .position(|w| w == cell_marker.as_bytes())
-                {
-                    if let Some(cell_end_rel) =
-                        row_slice[cell_pos..].windows(4).position(|w| w == b"</c>")
-                    {
-                        let cell_end = cell_pos + cell_end_rel + 4;
-                        row_slice.drain(cell_pos..cell_end);
-                    } else if let Some(cell_end_rel) =
-                        row_slice[cell_pos..].windows(2).position(|w| w == b"/>")
-                    {
-                        let cell_end = cell_pos + cell_end_rel + 2;
-                        row_slice.drain(cell_pos..cell_end);
-                    }
-                }
-
-                // Insert the new cell at the correct position within the row.
-                fn col_to_index(s: &str) -> u32 {
-                    s.bytes()
-                        .take_while(|b| b.is_ascii_alphabetic())
-                        .fold(0, |acc, b| {
-                            acc * 26 + (b.to_ascii_uppercase() - b'A' + 1) as u32
-                        })
-                }
-                let target_col = col_to_index(coord);
-                // Find the correct position to insert the new cell.
-                let mut insert_pos = row_slice.len() - 6; // 6 is the length of "</row>"
-                let mut i = 0;
-                while let Some(c_pos) = row_slice[i..].windows(6).position(|w| w == b"<c r=\"") {
-                    let abs = i + c_pos;
-                    // Find the end of the cell's coordinate attribute.
-                    if let Some(end_quote) = row_slice[abs + 6..].iter().position(|&b| b == b'"') {
-                        let coord_bytes = &row_slice[abs + 6..abs + 6 + end_quote];
-                        if let Ok(coord_str) = std::str::from_utf8(coord_bytes) {
-                            let col_idx = col_to_index(coord_str);
-                            if col_idx > target_col {
-                                insert_pos = abs;
-                                break;
-                            }
-                        }
-                        i = abs + 6 + end_quote;
-                    } else {
-                        break;
-                    }
-                }
-                row_slice.splice(insert_pos..insert_pos, cell_xml);
-
-                // Replace the original row with the updated one.
-                self.sheet_xml.splice(row_start..row_end, row_slice);
-            }
-        } else {
-            // If the row does not exist, create a new row and insert it in the correct order so that
-            // the `<row>` elements remain sorted by the `r` attribute.  Keeping the rows ordered
-            // avoids Excel "recovered records" errors that occur when rows are out of sequence.
-            let mut new_row_xml = Vec::new();
-            new_row_xml.extend_from_slice(b"<row r=\"");
-            new_row_xml.extend_from_slice(row_num.to_string().as_bytes());
-            new_row_xml.extend_from_slice(b"\">");
-            new_row_xml.extend_from_slice(&cell_xml);
-            new_row_xml.extend_from_slice(b"</row>");
-
-            // Try to find the first existing row whose `r` value is greater than the new row.
-            // If found, we will insert the new row *before* it, otherwise we fall back to
-            // inserting just before `</sheetData>` (the previous behaviour).
-            let mut insert_pos: Option<usize> = None;
-            let mut search_idx = 0;
-            while let Some(rel) = self.sheet_xml[search_idx..]
-                .windows(7)
-                .position(|w| w == b"<row r=")
-            {
-                let abs = search_idx + rel;
-                // Find the opening quote for the `r` attribute.
-                if let Some(first_quote) = self.sheet_xml[abs..].iter().position(|&b| b == b'"') {
-                    let num_start = abs + first_quote + 1;
-                    // Find the closing quote for the `r` attribute.
-                    if let Some(end_quote) =
-                        self.sheet_xml[num_start..].iter().position(|&b| b == b'"')
-                    {
-                        let num_bytes = &self.sheet_xml[num_start..num_start + end_quote];
-                        if let Ok(num_str) = std::str::from_utf8(num_bytes) {
-                            if let Ok(existing_r) = num_str.parse::<u32>() {
-                                if existing_r > row_num {
-                                    insert_pos = Some(abs);
-                                    break;
-                                }
-                            }
-                        }
-                        // Continue searching after this row tag.
-                        search_idx = num_start + end_quote;
-                    } else {
-                        break; // Malformed XML (should not happen)
-                    }
-                } else {
-                    break; // Malformed XML (should not happen)
-                }
-            }
-
-            let pos = match insert_pos {
-                Some(p) => p,
-                None => self
-                    .sheet_xml
-                    .windows(12)
-                    .rposition(|w| w == b"</sheetData>")
-                    .context("</sheetData> tag not found")?,
-            };
-
-            self.sheet_xml.splice(pos..pos, new_row_xml);
+    /// A `Result` indicating success or an `XlsxError` if the operation fails.
+    pub fn set_cell<S: ToString>(&mut self, coord: &str, value: S) -> Result<(), XlsxError> {
+        let (col, row_num) = parse_cell(coord)?;
+        check_row(row_num as u64)?;
+        let mut cell_xml = Vec::new();
+        push_text_cell(&mut cell_xml, col, row_num, &value.to_string());
+
+        // The cell is not spliced into the sheet right away: edits are collected in
+        // `sheet_xml` and merged in one pass (rows in `r` order, cells in column order)
+        // the next time the sheet XML is read or rewritten.
+        if !self.sheet_xml.has_edits() {
+            open_sheet_data(&mut self.sheet_xml)?;
         }
+        self.sheet_xml.set_cell(row_num, col, cell_xml);
 
         if row_num > self.last_row {
             self.last_row = row_num;
@@ -607,8 +517,17 @@ impl XlsxEditor {
     }
 }
 
-pub fn scan<P: AsRef<Path>>(src: P) -> Result<Vec<String>> {
-    let mut zip = zip::ZipArchive::new(File::open(src)?)?;
+pub fn scan<P: AsRef<Path>>(src: P) -> Result<Vec<String>, XlsxError> {
+    Ok(sheet_names(&mut open_zip(src)?)?)
+}
+
+/// Как [`scan`], но для книги в памяти.
+pub fn scan_bytes(data: &[u8]) -> Result<Vec<String>, XlsxError> {
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(data)).map_err(XlsxError::from)?;
+    Ok(sheet_names(&mut zip)?)
+}
+
+fn sheet_names<R: Read + std::io::Seek>(zip: &mut zip::ZipArchive<R>) -> Result<Vec<String>> {
     let mut wb = zip
         .by_name("xl/workbook.xml")
         .context("workbook.xml not found")?;
@@ -621,7 +540,8 @@ pub fn scan<P: AsRef<Path>>(src: P) -> Result<Vec<String>> {
 
     let mut names = Vec::new();
 
-    while let Ok(ev) = reader.read_event() {
+    loop {
+        let ev = next_event(&mut reader, "xl/workbook.xml")?;
         match ev {
             Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"sheet" => {
                 if let Some(n) = e.attributes().with_checks(false).flatten().find_map(|a| {
@@ -639,68 +559,47 @@ pub fn scan<P: AsRef<Path>>(src: P) -> Result<Vec<String>> {
 }
 
 impl XlsxEditor {
-    pub fn merge_cells(&mut self, range: &str) -> Result<()> {
-        // 1. позиция после </sheetData>
-        let sd_end = find_bytes(&self.sheet_xml, b"</sheetData>")
-            .context("</sheetData> not found")?
-            + "</sheetData>".len();
-
-        let (insert_pos, created) = if let Some(pos) = find_bytes(&self.sheet_xml, b"<mergeCells") {
-            // уже есть блок
-            bump_count(&mut self.sheet_xml, b"<mergeCells", b"count=\"")?;
-            let end = find_bytes_from(&self.sheet_xml, b"</mergeCells>", pos)
-                .context("</mergeCells> not found")?;
-            (end, false)
-        } else {
-            // нет блока – создаём
-            let tpl = br#"<mergeCells count="0"></mergeCells>"#;
-            self.sheet_xml.splice(sd_end..sd_end, tpl.iter().copied());
-            (sd_end + tpl.len() - "</mergeCells>".len(), true)
-        };
-
-        // 2. сам <mergeCell>
-        let tag = format!(r#"<mergeCell ref="{}"/>"#, range);
-        self.sheet_xml
-            .splice(insert_pos..insert_pos, tag.as_bytes().iter().copied());
-
-        // 3. правим count (если блок создан только что)
-        if created {
-            bump_count(&mut self.sheet_xml, b"<mergeCells", b"count=\"")?;
+    /// Объединяет ячейки `range`. Повторное объединение того же диапазона ничего
+    /// не меняет, а пересечение с уже объединённой областью — ошибка
+    /// (иначе Excel «восстанавливает» файл). Правится список объединений
+    /// листа; блок `<mergeCells>` пересобирается по нему целиком.
+    pub fn merge_cells(&mut self, range: &str) -> Result<(), XlsxError> {
+        let rect = parse_range(range)?;
+        for &other in self.sheet_xml.merges()? {
+            if other == rect {
+                return Ok(());
+            }
+            if rects_overlap(rect, other) {
+                bail!(
+                    "merge {} overlaps existing merged range {}",
+                    format_range(rect),
+                    format_range(other)
+                );
+            }
         }
+        self.sheet_xml.merges_mut()?.push(rect);
         Ok(())
     }
-}
 
-fn find_bytes(hay: &[u8], needle: &[u8]) -> Option<usize> {
-    hay.windows(needle.len()).position(|w| w == needle)
-}
-fn find_bytes_from(hay: &[u8], needle: &[u8], start: usize) -> Option<usize> {
-    hay[start..]
-        .windows(needle.len())
-        .position(|w| w == needle)
-        .map(|p| p + start)
-}
-
-fn bump_count(xml: &mut Vec<u8>, tag: &[u8], attr: &[u8]) -> Result<()> {
-    if let Some(pos) = find_bytes(xml, tag) {
-        if let Some(a) = find_bytes_from(xml, attr, pos) {
-            let start = a + attr.len();
-            let end = find_bytes_from(xml, b"\"", start).unwrap();
-            let mut num: u32 = std::str::from_utf8(&xml[start..end])?.parse()?;
-            num += 1;
-            xml.splice(start..end, num.to_string().as_bytes().iter().copied());
-            return Ok(());
+    /// Разъединяет все объединённые области, пересекающие `range`.
+    pub fn unmerge_cells(&mut self, range: &str) -> Result<&mut Self, XlsxError> {
+        let rect = parse_range(range)?;
+        let merges = self.sheet_xml.merges()?;
+        if merges.iter().any(|&m| rects_overlap(rect, m)) {
+            let merges = self.sheet_xml.merges_mut()?;
+            merges.retain(|&m| !rects_overlap(rect, m));
         }
+        Ok(self)
     }
-    Err(anyhow::anyhow!("attribute count not found"))
-}
 
-fn ensure_sheetdata_open_close(xml: &mut Vec<u8>) -> Result<()> {
-    const SELF_CLOSING: &[u8] = b"<sheetData/>";
-    if let Some(pos) = memchr::memmem::find(xml, SELF_CLOSING) {
-        // заменяем на <sheetData></sheetData>
-        let replacement = b"<sheetData></sheetData>";
-        xml.splice(pos..pos + SELF_CLOSING.len(), replacement.iter().copied());
+    /// Объединённые диапазоны текущего листа (`"A1:C1"`, …) в порядке файла,
+    /// затем новые.
+    pub fn get_merged_ranges(&self) -> Result<Vec<String>, XlsxError> {
+        Ok(self
+            .sheet_xml
+            .merges()?
+            .iter()
+            .map(|&m| format_range(m))
+            .collect())
     }
-    Ok(())
 }
@@ -0,0 +1,339 @@
+//! lint_part.rs – проверка изменённых частей книги до save(): то, из-за чего
+//! Excel сообщает «обнаружено содержимое, которое не удалось прочитать».
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::read_part::split_ref;
+use anyhow::Result;
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+use std::{collections::HashSet, fmt, str::FromStr};
+
+/// Атрибуты со ссылкой на связь части (`r:id`, `r:embed`, …).
+const REL_ATTRS: [&str; 4] = ["id", "embed", "link", "pict"];
+
+/// Вид проблемы, найденной [`XlsxEditor::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssueKind {
+    /// Часть не является корректным XML.
+    Malformed,
+    /// Две ячейки с одним адресом в строке.
+    DuplicateCell,
+    /// Строки не по возрастанию `r`, ячейки не по возрастанию столбца
+    /// или ячейка не в своей строке.
+    OutOfOrder,
+    /// `count` у `<mergeCells>` не совпадает с числом `<mergeCell>`.
+    CountMismatch,
+    /// Индекс стиля `s` за пределами `<cellXfs>`.
+    StyleIndex,
+    /// Ссылка `r:id` на связь, которой нет в `.rels` части.
+    DanglingRel,
+}
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IssueKind::Malformed => "malformed",
+            IssueKind::DuplicateCell => "duplicate-cell",
+            IssueKind::OutOfOrder => "out-of-order",
+            IssueKind::CountMismatch => "count-mismatch",
+            IssueKind::StyleIndex => "style-index",
+            IssueKind::DanglingRel => "dangling-rel",
+        })
+    }
+}
+impl FromStr for IssueKind {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        Ok(match s {
+            "malformed" => IssueKind::Malformed,
+            "duplicate-cell" => IssueKind::DuplicateCell,
+            "out-of-order" => IssueKind::OutOfOrder,
+            "count-mismatch" => IssueKind::CountMismatch,
+            "style-index" => IssueKind::StyleIndex,
+            "dangling-rel" => IssueKind::DanglingRel,
+            _ => bail!("Unknown issue kind: {s}"),
+        })
+    }
+}
+
+/// Одна проблема: часть архива, вид и описание.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub part: String,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+/// Результат [`XlsxEditor::validate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Проблем не найдено.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Проблемы одного вида.
+    pub fn of_kind(&self, kind: IssueKind) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(move |i| i.kind == kind)
+    }
+
+    fn push(&mut self, part: &str, kind: IssueKind, message: String) {
+        self.issues.push(Issue {
+            part: part.to_owned(),
+            kind,
+            message,
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return f.write_str("ok");
+        }
+        for (n, i) in self.issues.iter().enumerate() {
+            if n > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {}: {}", i.part, i.kind, i.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl XlsxEditor {
+    /// Проверяет части, которые запишет save(): workbook.xml, styles.xml, связи
+    /// книги, текущий лист и все изменённые/добавленные части. Ищет битый XML,
+    /// повторы и беспорядок строк/ячеек, неверный `count` у `<mergeCells>`,
+    /// индексы стилей вне `<cellXfs>` и ссылки на несуществующие связи.
+    /// Книга не меняется; пустой отчёт — [`ValidationReport::is_ok`].
+    pub fn validate(&mut self) -> Result<ValidationReport, XlsxError> {
+        self.flush_appended_ranges()?;
+        let mut parts: Vec<(String, Vec<u8>)> = vec![
+            ("xl/workbook.xml".into(), self.workbook_xml.get(&self.src)?.clone()),
+            ("xl/_rels/workbook.xml.rels".into(), self.rels_xml.get(&self.src)?.clone()),
+            ("xl/styles.xml".into(), self.styles_xml.get(&self.src)?.clone()),
+            (self.sheet_path.clone(), self.current_sheet_xml().into_owned()),
+        ];
+        for (path, xml) in &self.new_files {
+            let known = parts.iter().any(|(p, _)| p == path);
+            if !known && !self.removed_parts.contains(path) && is_xml_part(path) {
+                parts.push((path.clone(), xml.to_vec()));
+            }
+        }
+        let sheets: HashSet<String> = self.sheet_entries()?.into_iter().map(|e| e.path).collect();
+        let xf_count = cell_xf_count(self.styles_xml.get(&self.src)?);
+
+        let mut report = ValidationReport::default();
+        for (path, xml) in &parts {
+            let is_sheet = sheets.contains(path);
+            let Some(rel_ids) = lint_part(path, xml, is_sheet, xf_count, &mut report) else {
+                continue;
+            };
+            if rel_ids.is_empty() || path.ends_with(".rels") {
+                continue;
+            }
+            let rels = self.rels_of(path)?;
+            for id in rel_ids {
+                if rels.get(&id).is_none() {
+                    report.push(
+                        path,
+                        IssueKind::DanglingRel,
+                        format!("r:id `{id}` has no relationship"),
+                    );
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// XML-части архива (VML часто не является корректным XML — не проверяем).
+fn is_xml_part(path: &str) -> bool {
+    path.ends_with(".xml") || path.ends_with(".rels")
+}
+
+/// Число `<xf>` в `<cellXfs>`; `None`, если блока нет или styles.xml не разбирается.
+fn cell_xf_count(styles: &[u8]) -> Option<u32> {
+    let mut rdr = Reader::from_reader(styles);
+    let (mut inside, mut found, mut n) = (false, false, 0);
+    loop {
+        match rdr.read_event().ok()? {
+            Event::Start(e) if e.local_name().as_ref() == b"cellXfs" => {
+                (inside, found) = (true, true)
+            }
+            Event::End(e) if e.local_name().as_ref() == b"cellXfs" => inside = false,
+            Event::Start(e) | Event::Empty(e) if inside && e.local_name().as_ref() == b"xf" => {
+                n += 1
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    found.then_some(n)
+}
+
+/// Значение атрибута по локальному имени (без раскрытия сущностей).
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .with_checks(false)
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+}
+
+/// Состояние разбора `<sheetData>` и `<mergeCells>` листа.
+#[derive(Default)]
+struct SheetScan {
+    row: Option<u32>,
+    cols: HashSet<u32>,
+    last_col: Option<u32>,
+    merge_count: Option<u32>,
+    merges: u32,
+}
+
+/// Один проход по части: корректность XML, а для листа — порядок строк и ячеек,
+/// `<mergeCells count>` и индексы стилей (если известен размер `<cellXfs>`). Возвращает
+/// `r:id`-ссылки части или `None`, если XML битый.
+fn lint_part(
+    part: &str,
+    xml: &[u8],
+    is_sheet: bool,
+    xf_count: Option<u32>,
+    report: &mut ValidationReport,
+) -> Option<Vec<String>> {
+    let mut rdr = Reader::from_reader(xml);
+    let mut ids = Vec::new();
+    let mut sheet = SheetScan::default();
+    let mut issue = |kind, message| report.push(part, kind, message);
+    loop {
+        let (e, empty) = match rdr.read_event() {
+            Ok(Event::Start(e)) => (e, false),
+            Ok(Event::Empty(e)) => (e, true),
+            Ok(Event::End(e)) => {
+                if is_sheet && e.local_name().as_ref() == b"mergeCells" {
+                    check_merge_count(&sheet, &mut issue);
+                }
+                continue;
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => continue,
+            Err(err) => {
+                let at = rdr.error_position();
+                issue(IssueKind::Malformed, format!("at byte {at}: {err}"));
+                return None;
+            }
+        };
+        for a in e.attributes().with_checks(false).flatten() {
+            let key = a.key;
+            if key.prefix().is_some_and(|p| p.as_ref() != b"xml")
+                && REL_ATTRS
+                    .iter()
+                    .any(|n| key.local_name().as_ref() == n.as_bytes())
+            {
+                ids.push(String::from_utf8_lossy(&a.value).into_owned());
+            }
+        }
+        if !is_sheet {
+            continue;
+        }
+        match e.local_name().as_ref() {
+            b"row" => {
+                let r = attr(&e, b"r").and_then(|r| r.parse::<u32>().ok());
+                if let (Some(r), Some(prev)) = (r, sheet.row)
+                    && r <= prev
+                {
+                    let kind = if r == prev {
+                        "duplicate"
+                    } else {
+                        "out of order"
+                    };
+                    issue(
+                        IssueKind::OutOfOrder,
+                        format!("row {r} is {kind} after row {prev}"),
+                    );
+                }
+                sheet.row = r.or(sheet.row.map(|p| p + 1)).or(Some(1));
+                sheet.cols.clear();
+                sheet.last_col = None;
+                check_style(&e, "row", b"s", xf_count, &mut issue);
+            }
+            b"c" => {
+                if let Some(r) = attr(&e, b"r") {
+                    let (col, row) = split_ref(r.as_bytes());
+                    if sheet.row.is_some_and(|cur| cur != row) {
+                        issue(
+                            IssueKind::OutOfOrder,
+                            format!("cell {r} inside row {}", sheet.row.unwrap_or(0)),
+                        );
+                    } else if !sheet.cols.insert(col) {
+                        issue(IssueKind::DuplicateCell, format!("cell {r} appears twice"));
+                    } else if sheet.last_col.is_some_and(|last| col < last) {
+                        issue(
+                            IssueKind::OutOfOrder,
+                            format!("cell {r} is out of column order"),
+                        );
+                    }
+                    sheet.last_col = Some(col);
+                }
+                check_style(&e, "cell", b"s", xf_count, &mut issue);
+            }
+            b"col" => check_style(&e, "column", b"style", xf_count, &mut issue),
+            b"mergeCells" => {
+                sheet.merge_count = attr(&e, b"count").and_then(|c| c.parse().ok());
+                sheet.merges = 0;
+                if empty {
+                    check_merge_count(&sheet, &mut issue);
+                }
+            }
+            b"mergeCell" => sheet.merges += 1,
+            _ => {}
+        }
+    }
+    Some(ids)
+}
+
+/// Индекс стиля из атрибута `name` (`s` у строк и ячеек, `style` у `<col>`).
+fn check_style(
+    e: &BytesStart,
+    what: &str,
+    name: &[u8],
+    xf_count: Option<u32>,
+    issue: &mut impl FnMut(IssueKind, String),
+) {
+    let Some(xf_count) = xf_count else {
+        return;
+    };
+    let Some(s) = attr(e, name).and_then(|s| s.parse::<u32>().ok()) else {
+        return;
+    };
+    if s >= xf_count {
+        let r = attr(e, b"r")
+            .or_else(|| attr(e, b"min"))
+            .unwrap_or_default();
+        issue(
+            IssueKind::StyleIndex,
+            format!("{what} {r} uses style {s}, but cellXfs has {xf_count} entries"),
+        );
+    }
+}
+
+fn check_merge_count(sheet: &SheetScan, issue: &mut impl FnMut(IssueKind, String)) {
+    if let Some(count) = sheet.merge_count
+        && count != sheet.merges
+    {
+        issue(
+            IssueKind::CountMismatch,
+            format!(
+                "mergeCells count=\"{count}\", but {} mergeCell elements",
+                sheet.merges
+            ),
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! macro_part.rs – книги с макросами (.xlsm/.xltm): проект VBA переносится как
+//! есть, новые листы получают собственный `codeName`, ContentType книги
+//! соответствует расширению файла, а запись в формат без макросов требует
+//! явного разрешения.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::rels_part::PartRels;
+use crate::sheet_xml::{find_elem, find_wb_child, get_attr, insert_child, root_prefix, set_attr};
+use anyhow::Result;
+use std::{collections::HashSet, io::Read, path::Path};
+
+const WORKBOOK: &str = "xl/workbook.xml";
+
+/// ContentType `xl/workbook.xml` по расширению файла.
+const MAIN_TYPES: [(&str, &str); 4] = [
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml",
+    ),
+    ("xlsm", "application/vnd.ms-excel.sheet.macroEnabled.main+xml"),
+    (
+        "xltx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.template.main+xml",
+    ),
+    ("xltm", "application/vnd.ms-excel.template.macroEnabled.main+xml"),
+];
+
+/// Сколько байт начала листа читается в поисках `<sheetPr codeName>`.
+const HEAD_LEN: u64 = 8 * 1024;
+
+impl XlsxEditor {
+    /// Есть ли в книге проект VBA (`xl/vbaProject.bin`).
+    pub fn has_macros(&mut self) -> Result<bool, XlsxError> {
+        Ok(self.rels_of(WORKBOOK)?.of_kind("vbaProject").next().is_some())
+    }
+
+    /// Разрешает (`true`) save() в файл без поддержки макросов (`.xlsx`, `.xltx`):
+    /// проект VBA и его связи удаляются из книги. Без разрешения такая запись —
+    /// ошибка, чтобы макросы не пропадали незаметно.
+    pub fn set_allow_macro_removal(&mut self, enabled: bool) -> &mut Self {
+        self.allow_macro_removal = enabled;
+        self
+    }
+
+    /// Перед записью в `dst`: ContentType книги под расширение файла и проверка,
+    /// что макросы не теряются без разрешения. Неизвестные расширения не трогаются.
+    pub(crate) fn prepare_macro_target(&mut self, dst: &Path) -> Result<()> {
+        let ext = dst
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let Some((_, main_type)) = MAIN_TYPES.iter().find(|(e, _)| *e == ext) else {
+            return Ok(());
+        };
+        if !ext.ends_with('m') && self.has_macros()? {
+            if !self.allow_macro_removal {
+                bail!(
+                    "workbook has macros; saving it as .{ext} would drop them \
+                     (save as .xlsm or call set_allow_macro_removal(true))"
+                );
+            }
+            self.remove_macros()?;
+        }
+        if self.content_type_override(WORKBOOK)?.as_deref() != Some(main_type) {
+            self.remove_content_type_override(WORKBOOK)?;
+            self.add_content_type_override(WORKBOOK, main_type)?;
+        }
+        Ok(())
+    }
+
+    /// Удаляет проект VBA, его собственные связи (подпись) и ContentType.
+    fn remove_macros(&mut self) -> Result<()> {
+        let mut wb_rels = self.rels_of(WORKBOOK)?;
+        let vba: Vec<_> = wb_rels.of_kind("vbaProject").cloned().collect();
+        for rel in vba {
+            let rels = self.rels_of(&rel.target)?;
+            for child in rels.rels.iter().filter(|r| !r.external) {
+                self.remove_part(&child.target);
+                self.remove_content_type_override(&child.target)?;
+            }
+            if !rels.rels.is_empty() {
+                self.store_rels(PartRels {
+                    rels: Vec::new(),
+                    ..rels
+                });
+            }
+            self.remove_part(&rel.target);
+            self.remove_content_type_override(&rel.target)?;
+            wb_rels.remove(&rel.id);
+        }
+        self.store_rels(wb_rels);
+        Ok(())
+    }
+
+    /// Даёт листу `path` `codeName`, под которым его видит VBA: если его имя
+    /// совпадает с именем другого листа (копия) или если в книге есть макросы,
+    /// а имени нет. Новое имя — первое свободное `SheetN`.
+    pub(crate) fn ensure_code_name(&mut self, path: &str) -> Result<()> {
+        let mut xml = self.sheet_part(path)?;
+        let current = code_name(&xml);
+        if current.is_none() && !self.has_macros()? {
+            return Ok(());
+        }
+
+        let mut used = HashSet::new();
+        let wb = self.workbook_xml.get(&self.src)?;
+        if let Some(pr) = find_wb_child(wb, "workbookPr")? {
+            used.extend(get_attr(&wb[pr], "codeName"));
+        }
+        for entry in self.sheet_entries()? {
+            if entry.path != path {
+                used.extend(code_name(&self.sheet_head(&entry.path)?));
+            }
+        }
+        let used: HashSet<String> = used.iter().map(|n| n.to_lowercase()).collect();
+        if current
+            .as_ref()
+            .is_some_and(|c| !used.contains(&c.to_lowercase()))
+        {
+            return Ok(());
+        }
+        let fresh = (1..)
+            .map(|n| format!("Sheet{n}"))
+            .find(|n| !used.contains(&n.to_lowercase()))
+            .unwrap_or_default();
+
+        let p = root_prefix(&xml);
+        match find_elem(&xml, &format!("{p}sheetPr"), 0) {
+            Some(r) => {
+                let end = r.start + xml[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+                let mut tag = xml[r.start..=end].to_vec();
+                set_attr(&mut tag, "codeName", Some(&fresh));
+                xml.splice(r.start..=end, tag);
+            }
+            None => {
+                let block = format!(r#"<{p}sheetPr codeName="{fresh}"/>"#);
+                insert_child(&mut xml, "sheetPr", block.as_bytes())?;
+            }
+        }
+        self.store_sheet_part(path, xml);
+        Ok(())
+    }
+
+    /// Начало XML листа — `<sheetPr>` идёт первым, так что большие листы
+    /// из архива целиком не распаковываются.
+    fn sheet_head(&mut self, path: &str) -> Result<Vec<u8>> {
+        let edited = path == self.sheet_path
+            || self.loaded_files.contains_key(path)
+            || self.new_files.iter().any(|(p, _)| p == path);
+        if edited {
+            return self.sheet_part(path);
+        }
+        let mut zin = self.src.open()?;
+        let Ok(file) = zin.by_name(path) else {
+            return Ok(Vec::new());
+        };
+        let mut head = Vec::new();
+        file.take(HEAD_LEN).read_to_end(&mut head)?;
+        Ok(head)
+    }
+}
+
+/// `codeName` из `<sheetPr>` (достаточно начала листа).
+fn code_name(xml: &[u8]) -> Option<String> {
+    let p = root_prefix(xml);
+    let r = find_elem(xml, &format!("{p}sheetPr"), 0).or_else(|| {
+        // обрезанное начало: элемент мог не закрыться — хватит открывающего тега
+        let at = memchr::memmem::find(xml, format!("<{p}sheetPr").as_bytes())?;
+        Some(at..xml.len())
+    })?;
+    let end = r.start + memchr::memchr(b'>', &xml[r.clone()])?;
+    get_attr(&xml[r.start..=end], "codeName")
+}
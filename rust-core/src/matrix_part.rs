@@ -0,0 +1,93 @@
+//! matrix_part.rs – запись числовых матриц ndarray блоком: числа идут в `<v>`
+//! напрямую, без промежуточных строк и разбора значений.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, check_row, ensure};
+use crate::range_part::{CellMap, ClearMode, patch_sheet_data};
+use crate::sheet_xml::{extend_dimension, find_child, open_sheet_data};
+use crate::style::{col_letter, parse_cell};
+use anyhow::{Context, Result};
+use ndarray::{ArrayBase, Data, Ix2};
+
+const MAX_COL: u32 = 16_384;
+
+impl XlsxEditor {
+    /// Пишет матрицу `m` числами, левый верхний элемент — в `start_cell`.
+    /// Ячейки под матрицей заменяются; `NaN` и бесконечности оставляют ячейку
+    /// пустой. Если блок целиком ниже данных листа, строки просто дописываются
+    /// в конец `<sheetData>`, без перестройки существующих.
+    pub fn write_matrix<S>(
+        &mut self,
+        start_cell: &str,
+        m: &ArrayBase<S, Ix2>,
+    ) -> Result<(), XlsxError>
+    where
+        S: Data<Elem = f64>,
+    {
+        let (c0, r0) = parse_cell(start_cell)?;
+        let (rows, cols) = m.dim();
+        if rows == 0 || cols == 0 {
+            return Ok(());
+        }
+        check_row(r0 as u64 + rows as u64 - 1)?;
+        ensure!(
+            c0 as u64 + (cols as u64) <= MAX_COL as u64,
+            "{start_cell} is too close to the sheet edge for a {rows}x{cols} matrix"
+        );
+        let (r1, c1) = (r0 + rows as u32 - 1, c0 + cols as u32 - 1);
+        let letters: Vec<String> = (c0..=c1).map(col_letter).collect();
+
+        let mut num = [0u8; lexical_core::BUFFER_SIZE];
+        let mut cell = |xml: &mut Vec<u8>, col: usize, row: u32, v: f64| {
+            if !v.is_finite() {
+                return;
+            }
+            xml.extend_from_slice(b"<c r=\"");
+            xml.extend_from_slice(letters[col].as_bytes());
+            xml.extend_from_slice(row.to_string().as_bytes());
+            xml.extend_from_slice(b"\"><v>");
+            xml.extend_from_slice(lexical_core::write(v, &mut num));
+            xml.extend_from_slice(b"</v></c>");
+        };
+
+        if r0 > self.last_row {
+            let mut bulk_rows_xml = Vec::with_capacity(rows * cols * 24);
+            for (i, line) in m.outer_iter().enumerate() {
+                let row = r0 + i as u32;
+                bulk_rows_xml.extend_from_slice(format!(r#"<row r="{row}">"#).as_bytes());
+                for (j, &v) in line.iter().enumerate() {
+                    cell(&mut bulk_rows_xml, j, row, v);
+                }
+                bulk_rows_xml.extend_from_slice(b"</row>");
+            }
+            let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+            self.sheet_xml.splice(pos..pos, bulk_rows_xml);
+        } else {
+            let mut cells = CellMap::new();
+            for (i, line) in m.outer_iter().enumerate() {
+                let row = r0 + i as u32;
+                let slot = cells.entry(row).or_default();
+                for (j, &v) in line.iter().enumerate() {
+                    let mut xml = Vec::new();
+                    cell(&mut xml, j, row, v);
+                    if !xml.is_empty() {
+                        slot.insert(c0 + j as u32, xml);
+                    }
+                }
+            }
+            open_sheet_data(&mut self.sheet_xml)?;
+            let sd =
+                find_child(&self.sheet_xml, "sheetData")?.context("no <sheetData> in sheet")?;
+            let data = patch_sheet_data(
+                &self.sheet_xml[sd.clone()],
+                &[(c0, r0, c1, r1)],
+                ClearMode::All,
+                cells,
+            )?;
+            self.sheet_xml.splice(sd, data);
+        }
+        self.last_row = self.last_row.max(r1);
+        extend_dimension(&mut self.sheet_xml, (c0, r0, c1, r1))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+//! multi_part.rs – несколько листов, открытых для правки одновременно.
+//!
+//! [`XlsxEditor::sheets_mut`] вынимает XML нужных листов в отдельные буферы
+//! ([`SheetMut`]), отдаёт их замыканию и возвращает в книгу — без
+//! переключения `with_worksheet` и повторного чтения листов. Листы — разные
+//! части архива, поэтому с фичей `rayon` их можно дописывать параллельно
+//! ([`XlsxEditor::par_sheets_mut`]). Сохраняется всё одним `save()`.
+
+use crate::XlsxEditor;
+use crate::cell_xml::{push_text_cell, push_text_row};
+use crate::error::{XlsxError, check_row, ensure};
+use crate::files_part::calc_last_row;
+use crate::sheet_buf::SheetBuf;
+use crate::sheet_xml::{open_sheet_data, restore_prefix, strip_prefix};
+use crate::style::parse_cell;
+use crate::template_part::CellValue;
+use anyhow::{Context, Result};
+use std::fmt::Display;
+use std::io::Read;
+use std::sync::Arc;
+
+/// Лист, вынутый из книги для правки (см. [`XlsxEditor::sheets_mut`]).
+/// Умеет то, что не трогает общие части книги: дозапись строк и значения
+/// ячеек. Стили, таблицы и структура листа — через сам редактор.
+pub struct SheetMut {
+    name: String,
+    path: String,
+    xml: SheetBuf, // без префикса элементов, как у текущего листа
+    prefix: String,
+    last_row: u32,
+    /// `last_row` при открытии: от него дотягиваются таблицы, фильтр и
+    /// источники сводных, когда лист возвращается в книгу
+    opened_last: u32,
+    /// лист уже был среди правок (текущий или в `new_files`)
+    held: bool,
+    changed: bool,
+}
+
+impl SheetMut {
+    /// Имя листа, как его передали в [`XlsxEditor::sheets_mut`].
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Номер последней строки листа.
+    pub fn last_row(&self) -> u32 {
+        self.last_row
+    }
+
+    /// Дописывает строку, как [`XlsxEditor::append_row`]: `=` — формула,
+    /// числовой текст — число, остальное — строка.
+    pub fn append_row<I, S>(&mut self, cells: I) -> Result<(), XlsxError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        self.append_table([cells])
+    }
+
+    /// Дописывает строки одной вставкой, как [`XlsxEditor::append_table`].
+    pub fn append_table<R, I, S>(&mut self, rows: R) -> Result<(), XlsxError>
+    where
+        R: IntoIterator<Item = I>,
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        let mut xml = Vec::new();
+        let mut row = self.last_row;
+        for cells in rows {
+            check_row(row as u64 + 1)?;
+            row += 1;
+            push_text_row(&mut xml, row, cells);
+        }
+        Ok(self.push_rows(xml, row)?)
+    }
+
+    /// Дописывает строку типизированных значений, как
+    /// [`XlsxEditor::append_values`], но без числовых форматов (стили общие
+    /// для всей книги). `CellValue::Empty` — пустая ячейка.
+    pub fn append_values(&mut self, cells: &[CellValue]) -> Result<(), XlsxError> {
+        check_row(self.last_row as u64 + 1)?;
+        let row = self.last_row + 1;
+        let mut xml = format!(r#"<row r="{row}">"#).into_bytes();
+        for (col, value) in (0u32..).zip(cells) {
+            if *value != CellValue::Empty {
+                xml.extend(value.cell_xml(col, row, None));
+            }
+        }
+        xml.extend_from_slice(b"</row>");
+        Ok(self.push_rows(xml, row)?)
+    }
+
+    /// Записывает значение в ячейку `coord`, как [`XlsxEditor::set_cell`].
+    pub fn set_cell<S: ToString>(&mut self, coord: &str, value: S) -> Result<(), XlsxError> {
+        let (col, row) = parse_cell(coord)?;
+        check_row(row as u64)?;
+        let mut cell_xml = Vec::new();
+        push_text_cell(&mut cell_xml, col, row, &value.to_string());
+        if !self.xml.has_edits() {
+            open_sheet_data(&mut self.xml)?;
+        }
+        self.xml.set_cell(row, col, cell_xml);
+        self.last_row = self.last_row.max(row);
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Вставляет готовые строки перед `</sheetData>`; `last` — номер последней.
+    fn push_rows(&mut self, rows: Vec<u8>, last: u32) -> Result<()> {
+        let pos = open_sheet_data(&mut self.xml)?.end;
+        self.xml.splice(pos..pos, rows);
+        self.last_row = last;
+        self.changed = true;
+        Ok(())
+    }
+}
+
+impl XlsxEditor {
+    /// Открывает листы `names` для правки одновременно и передаёт их `f`.
+    /// Каждый лист — свой буфер: их можно держать и менять вперемешку, не
+    /// переключаясь через `with_worksheet`. После `f` (и при её ошибке) листы
+    /// возвращаются в книгу; у дописанных листов, как после `append_row`,
+    /// дотягиваются таблицы, автофильтр и источники сводных.
+    ///
+    /// ```no_run
+    /// # use rust_core::XlsxEditor;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Jan")?;
+    /// xl.sheets_mut(&["Jan", "Feb"], |sheets| {
+    ///     let [jan, feb] = sheets else { unreachable!() };
+    ///     jan.append_row(["a", "1"])?;
+    ///     feb.append_row(["b", "2"])?;
+    ///     Ok(())
+    /// })?;
+    /// xl.save("out.xlsx")?;
+    /// # Ok(()) }
+    /// ```
+    pub fn sheets_mut<R>(
+        &mut self,
+        names: &[&str],
+        f: impl FnOnce(&mut [SheetMut]) -> Result<R, XlsxError>,
+    ) -> Result<R, XlsxError> {
+        let mut sheets = Vec::with_capacity(names.len());
+        for name in names {
+            match self.take_sheet(name, &sheets) {
+                Ok(s) => sheets.push(s),
+                Err(e) => {
+                    self.put_back_sheets(sheets)?;
+                    return Err(e.into());
+                }
+            }
+        }
+        let res = f(&mut sheets);
+        let back = self.put_back_sheets(sheets);
+        let out = res?;
+        back?;
+        Ok(out)
+    }
+
+    /// [`Self::sheets_mut`], где `f` вызывается для каждого листа в пуле
+    /// rayon: листы не пересекаются, так что дозапись идёт параллельно.
+    #[cfg(feature = "rayon")]
+    pub fn par_sheets_mut(
+        &mut self,
+        names: &[&str],
+        f: impl Fn(&mut SheetMut) -> Result<(), XlsxError> + Sync,
+    ) -> Result<(), XlsxError> {
+        use rayon::prelude::*;
+        self.sheets_mut(names, |sheets| sheets.par_iter_mut().try_for_each(&f))
+    }
+
+    /// Вынимает XML листа `name` без копии: текущий лист, отложенный в
+    /// `new_files`, из кэша или из архива.
+    fn take_sheet(&mut self, name: &str, taken: &[SheetMut]) -> Result<SheetMut> {
+        let path = self.sheet_path_of(name)?;
+        ensure!(
+            !taken.iter().any(|s| s.path == path),
+            "sheet {name} is listed twice"
+        );
+        if path == self.sheet_path {
+            return Ok(SheetMut {
+                name: name.to_owned(),
+                xml: std::mem::take(&mut self.sheet_xml),
+                prefix: std::mem::take(&mut self.sheet_prefix),
+                last_row: self.last_row,
+                opened_last: self.last_row,
+                held: true,
+                changed: false,
+                path,
+            });
+        }
+
+        let parked = self.new_files.iter().position(|(p, _)| *p == path);
+        let mut xml = if let Some(i) = parked {
+            Arc::unwrap_or_clone(std::mem::take(&mut self.new_files[i].1))
+        } else if let Some(buf) = self.loaded_files.remove(&path) {
+            buf
+        } else {
+            let mut zin = self.src.open()?;
+            let mut f = zin
+                .by_name(&path)
+                .with_context(|| format!("{path} not found in zip"))?;
+            let mut buf = Vec::with_capacity(f.size() as usize);
+            f.read_to_end(&mut buf)?;
+            buf
+        };
+        let prefix = strip_prefix(&mut xml);
+        let last_row = match calc_last_row(&path, &xml) {
+            Ok(n) => n,
+            Err(e) => {
+                // битый лист возвращается на место
+                if let Some(i) = parked {
+                    restore_prefix(&mut xml, &prefix);
+                    self.new_files[i].1 = xml.into();
+                }
+                return Err(e.into());
+            }
+        };
+        Ok(SheetMut {
+            name: name.to_owned(),
+            xml: xml.into(),
+            prefix,
+            last_row,
+            opened_last: last_row,
+            held: parked.is_some(),
+            changed: false,
+            path,
+        })
+    }
+
+    /// Возвращает листы в книгу: текущий — на место, остальные — в
+    /// `new_files` (нетронутые листы из архива просто отпускаются).
+    fn put_back_sheets(&mut self, sheets: Vec<SheetMut>) -> Result<()> {
+        let mut res = Ok(());
+        for mut s in sheets {
+            if res.is_ok() && s.last_row > s.opened_last {
+                res = self.as_current(&mut s, |ed, old| {
+                    ed.grow_ranges_on_append(old, None)?;
+                    ed.flush_appended_ranges()
+                });
+            }
+            if s.path == self.sheet_path {
+                self.sheet_xml = s.xml;
+                self.sheet_prefix = s.prefix;
+                self.last_row = s.last_row;
+            } else if s.held || s.changed {
+                let mut xml = s.xml.take();
+                restore_prefix(&mut xml, &s.prefix);
+                self.write_part(&s.path, xml);
+            }
+        }
+        res
+    }
+
+    /// Выполняет `f` так, будто `sheet` — текущий лист (второй аргумент —
+    /// `last_row` листа при открытии).
+    fn as_current<T>(
+        &mut self,
+        sheet: &mut SheetMut,
+        f: impl FnOnce(&mut Self, u32) -> Result<T>,
+    ) -> Result<T> {
+        let swap = |ed: &mut Self, s: &mut SheetMut| {
+            std::mem::swap(&mut ed.sheet_path, &mut s.path);
+            std::mem::swap(&mut ed.sheet_xml, &mut s.xml);
+            std::mem::swap(&mut ed.sheet_prefix, &mut s.prefix);
+            std::mem::swap(&mut ed.last_row, &mut s.last_row);
+        };
+        // отложенная дозапись самого редактора относится к его листу
+        let pending = self.pending_growth.take();
+        swap(self, sheet);
+        let res = f(self, sheet.opened_last);
+        swap(self, sheet);
+        self.pending_growth = pending;
+        res
+    }
+}
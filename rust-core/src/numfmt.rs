@@ -0,0 +1,240 @@
+//! numfmt.rs – числовые форматы: встроенные коды Excel и сборка типовых.
+//!
+//! У Excel есть встроенные форматы с номерами 0–49: они не пишутся в
+//! `<numFmts>`, а Excel показывает их в списке «Числовой формат» по имени.
+//! Код, набранный вручную с лишним пробелом, становится пользовательским
+//! форматом, и ячейка уже не «Процентный», а «(все форматы)». Константы
+//! [`NumFmt`] — точные коды встроенных форматов; `set_number_format` отдаёт
+//! такому коду встроенный номер. Помощники (`NumFmt::currency("EUR")`,
+//! `NumFmt::percent(1)`) собирают коды, которые Excel понимает одинаково в
+//! любой локали.
+
+use crate::error::{XlsxError, bail};
+use crate::template_part::{DATE_FORMAT, DATETIME_FORMAT};
+use anyhow::Result;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Deref;
+
+/// Встроенные форматы (ECMA-376, 18.8.30): номер и код. Остальные номера
+/// до 163 зависят от языка Excel и кода не имеют.
+pub(crate) const BUILTIN: [(u32, &str); 28] = [
+    (0, "General"),
+    (1, "0"),
+    (2, "0.00"),
+    (3, "#,##0"),
+    (4, "#,##0.00"),
+    (9, "0%"),
+    (10, "0.00%"),
+    (11, "0.00E+00"),
+    (12, "# ?/?"),
+    (13, "# ??/??"),
+    (14, "mm-dd-yy"),
+    (15, "d-mmm-yy"),
+    (16, "d-mmm"),
+    (17, "mmm-yy"),
+    (18, "h:mm AM/PM"),
+    (19, "h:mm:ss AM/PM"),
+    (20, "h:mm"),
+    (21, "h:mm:ss"),
+    (22, "m/d/yy h:mm"),
+    (37, "#,##0 ;(#,##0)"),
+    (38, "#,##0 ;[Red](#,##0)"),
+    (39, "#,##0.00;(#,##0.00)"),
+    (40, "#,##0.00;[Red](#,##0.00)"),
+    (45, "mm:ss"),
+    (46, "[h]:mm:ss"),
+    (47, "mmss.0"),
+    (48, "##0.0E+0"),
+    (49, "@"),
+];
+
+/// Код встроенного формата `id`; `None` — номер без кода.
+pub fn builtin_code(id: u32) -> Option<&'static str> {
+    BUILTIN.iter().find(|(i, _)| *i == id).map(|(_, c)| *c)
+}
+
+/// Номер встроенного формата с кодом `code` (регистр важен, как в Excel).
+pub fn builtin_id(code: &str) -> Option<u32> {
+    BUILTIN.iter().find(|(_, c)| *c == code).map(|(i, _)| *i)
+}
+
+/// Код числового формата. Разыменовывается в `&str`, так что подходит
+/// везде, где ждут код: `xl.set_number_format("B2:B9", &NumFmt::PERCENT)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NumFmt(Cow<'static, str>);
+
+impl NumFmt {
+    /// «Общий» (0).
+    pub const GENERAL: NumFmt = NumFmt::borrowed("General");
+    /// `0` (1).
+    pub const INTEGER: NumFmt = NumFmt::borrowed("0");
+    /// `0.00` (2).
+    pub const DECIMAL: NumFmt = NumFmt::borrowed("0.00");
+    /// `#,##0` (3).
+    pub const THOUSANDS: NumFmt = NumFmt::borrowed("#,##0");
+    /// `#,##0.00` (4).
+    pub const THOUSANDS_DECIMAL: NumFmt = NumFmt::borrowed("#,##0.00");
+    /// `0%` (9).
+    pub const PERCENT: NumFmt = NumFmt::borrowed("0%");
+    /// `0.00%` (10).
+    pub const PERCENT_DECIMAL: NumFmt = NumFmt::borrowed("0.00%");
+    /// `0.00E+00` (11).
+    pub const SCIENTIFIC: NumFmt = NumFmt::borrowed("0.00E+00");
+    /// `# ?/?` (12).
+    pub const FRACTION: NumFmt = NumFmt::borrowed("# ?/?");
+    /// Краткая дата (14): Excel показывает её в формате даты системы.
+    pub const DATE: NumFmt = NumFmt::borrowed("mm-dd-yy");
+    /// `d-mmm-yy` (15).
+    pub const DATE_LONG: NumFmt = NumFmt::borrowed("d-mmm-yy");
+    /// `h:mm` (20).
+    pub const TIME: NumFmt = NumFmt::borrowed("h:mm");
+    /// `h:mm:ss` (21).
+    pub const TIME_SECONDS: NumFmt = NumFmt::borrowed("h:mm:ss");
+    /// `h:mm AM/PM` (18).
+    pub const TIME_AM_PM: NumFmt = NumFmt::borrowed("h:mm AM/PM");
+    /// Дата и время (22), как [`Self::DATE`] — в формате системы.
+    pub const DATETIME: NumFmt = NumFmt::borrowed("m/d/yy h:mm");
+    /// Длительность больше суток: `[h]:mm:ss` (46).
+    pub const DURATION: NumFmt = NumFmt::borrowed("[h]:mm:ss");
+    /// Отрицательные — в скобках: `#,##0 ;(#,##0)` (37).
+    pub const ACCOUNTING: NumFmt = NumFmt::borrowed("#,##0 ;(#,##0)");
+    /// То же, отрицательные красным (38).
+    pub const ACCOUNTING_RED: NumFmt = NumFmt::borrowed("#,##0 ;[Red](#,##0)");
+    /// `#,##0.00;(#,##0.00)` (39).
+    pub const ACCOUNTING_DECIMAL: NumFmt = NumFmt::borrowed("#,##0.00;(#,##0.00)");
+    /// То же, отрицательные красным (40).
+    pub const ACCOUNTING_DECIMAL_RED: NumFmt = NumFmt::borrowed("#,##0.00;[Red](#,##0.00)");
+    /// Текст: `@` (49).
+    pub const TEXT: NumFmt = NumFmt::borrowed("@");
+
+    const fn borrowed(code: &'static str) -> Self {
+        NumFmt(Cow::Borrowed(code))
+    }
+
+    /// Произвольный код формата.
+    pub fn custom(code: impl Into<String>) -> Self {
+        NumFmt(Cow::Owned(code.into()))
+    }
+
+    /// Дата ISO 8601: `yyyy-mm-dd` — одинаково в любой локали.
+    pub fn date_iso() -> Self {
+        NumFmt::borrowed(DATE_FORMAT)
+    }
+
+    /// Дата и время ISO 8601: `yyyy-mm-dd hh:mm:ss`.
+    pub fn datetime_iso() -> Self {
+        NumFmt::borrowed(DATETIME_FORMAT)
+    }
+
+    /// Число с `places` знаками после запятой: `0.00`.
+    pub fn decimal(places: u8) -> Self {
+        NumFmt::custom(format!("0{}", fraction(places)))
+    }
+
+    /// Число с разделителем тысяч: `#,##0.00`.
+    pub fn thousands(places: u8) -> Self {
+        NumFmt::custom(format!("#,##0{}", fraction(places)))
+    }
+
+    /// Проценты: `0.0%`.
+    pub fn percent(places: u8) -> Self {
+        NumFmt::custom(format!("0{}%", fraction(places)))
+    }
+
+    /// Экспоненциальный: `0.00E+00`.
+    pub fn scientific(places: u8) -> Self {
+        NumFmt::custom(format!("0{}E+00", fraction(places)))
+    }
+
+    /// Денежный формат валюты `iso` (ISO 4217: `"EUR"`, `"USD"`) со знаком
+    /// и языковым тегом её страны: `[$€-407]` показывает «€» в Excel с
+    /// любым языком системы. Валюты без знака в таблице получают код
+    /// (`[$NOK]`). Иены и воны — без копеек.
+    pub fn currency(iso: &str) -> Result<Self, XlsxError> {
+        if iso.len() != 3 || !iso.bytes().all(|b| b.is_ascii_uppercase()) {
+            bail!("expected an ISO 4217 currency code like \"EUR\", got {iso:?}");
+        }
+        let Some(&(_, symbol, lcid, before, places)) = CURRENCIES.iter().find(|c| c.0 == iso)
+        else {
+            return Ok(NumFmt::custom(format!("#,##0.00 [${iso}]")));
+        };
+        let number = format!("#,##0{}", fraction(places));
+        Ok(NumFmt::custom(if before {
+            // многобуквенный знак (CHF, R$) отделяется пробелом, «$» — нет
+            let gap = if symbol.chars().count() > 1 { " " } else { "" };
+            format!("[${symbol}-{lcid}]{gap}{number}")
+        } else {
+            format!("{number} [${symbol}-{lcid}]")
+        }))
+    }
+
+    /// Код формата.
+    pub fn code(&self) -> &str {
+        &self.0
+    }
+
+    /// Номер встроенного формата с этим кодом; `None` — пользовательский.
+    pub fn builtin_id(&self) -> Option<u32> {
+        builtin_id(&self.0)
+    }
+}
+
+/// Валюта: код ISO, знак, языковой тег (LCID) страны, знак перед числом,
+/// знаков после запятой.
+const CURRENCIES: [(&str, &str, &str, bool, u8); 14] = [
+    ("USD", "$", "409", true, 2),
+    ("EUR", "€", "407", false, 2),
+    ("GBP", "£", "809", true, 2),
+    ("JPY", "¥", "411", true, 0),
+    ("CNY", "¥", "804", true, 2),
+    ("RUB", "₽", "419", false, 2),
+    ("CHF", "CHF", "807", true, 2),
+    ("INR", "₹", "4009", true, 2),
+    ("KRW", "₩", "412", true, 0),
+    ("CAD", "$", "1009", true, 2),
+    ("AUD", "$", "C09", true, 2),
+    ("BRL", "R$", "416", true, 2),
+    ("PLN", "zł", "415", false, 2),
+    ("SEK", "kr", "41D", false, 2),
+];
+
+/// Дробная часть кода: `""`, `".0"`, `".00"`, ….
+fn fraction(places: u8) -> String {
+    if places == 0 {
+        String::new()
+    } else {
+        format!(".{}", "0".repeat(places as usize))
+    }
+}
+
+impl Deref for NumFmt {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for NumFmt {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for NumFmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for NumFmt {
+    fn from(code: &str) -> Self {
+        NumFmt::custom(code)
+    }
+}
+
+impl From<String> for NumFmt {
+    fn from(code: String) -> Self {
+        NumFmt::custom(code)
+    }
+}
@@ -0,0 +1,217 @@
+//! outline_part.rs – группировка строк и столбцов (структура листа):
+//! `outlineLevel`/`hidden`/`collapsed` у `<row>` и `<col>` и уровни в `<sheetFormatPr>`.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::sheet_xml::{
+    edit_empty_child, find_child, find_elem, get_attr, insert_child, open_sheet_data, root_prefix,
+    set_attr,
+};
+use crate::structure_part::parse_span;
+use crate::style::ColProp;
+use anyhow::Result;
+
+/// Наибольший уровень группировки в Excel.
+const MAX_OUTLINE_LEVEL: u8 = 7;
+
+impl XlsxEditor {
+    /// Группирует строки `rows` (`"5:10"`) на уровне `level` (1..=7; 0 снимает
+    /// группировку). `collapsed` сворачивает группу: строки скрываются, а у итоговой
+    /// строки (под группой или над ней — см. `summary_below`) ставится `collapsed`.
+    /// Без `collapsed` скрытые строки остаются скрытыми (их могла свернуть внешняя группа).
+    pub fn group_rows(
+        &mut self,
+        rows: &str,
+        level: u8,
+        collapsed: bool,
+    ) -> Result<&mut Self, XlsxError> {
+        check_level(level)?;
+        let (lo, hi) = parse_span(rows, false)?;
+        let summary = match self.summary_before("summaryBelow")? {
+            false => Some(hi + 1),
+            true => lo.checked_sub(1).filter(|r| *r >= 1),
+        };
+        self.ensure_rows(lo, hi)?;
+        if let Some(s) = summary.filter(|_| collapsed) {
+            self.ensure_rows(s, s)?;
+        }
+
+        let mut max_level = 0u8;
+        self.for_each_row_tag(|r, tag| {
+            let mut new = tag.to_vec();
+            if (lo..=hi).contains(&r) {
+                let lvl = level.to_string();
+                set_attr(&mut new, "outlineLevel", (level > 0).then_some(&*lvl));
+                if collapsed && level > 0 {
+                    set_attr(&mut new, "hidden", Some("1"));
+                }
+            } else if Some(r) == summary {
+                set_attr(
+                    &mut new,
+                    "collapsed",
+                    (collapsed && level > 0).then_some("1"),
+                );
+            }
+            let lvl = get_attr(&new, "outlineLevel").and_then(|v| v.parse().ok());
+            max_level = max_level.max(lvl.unwrap_or(0));
+            (new != tag).then_some(new)
+        })?;
+        self.set_outline_levels("outlineLevelRow", max_level)?;
+        Ok(self)
+    }
+
+    /// Группирует столбцы `cols` (`"B:D"`) — как [`Self::group_rows`]; итоговый
+    /// столбец — справа от группы или слева (см. `summary_right`).
+    pub fn group_columns(
+        &mut self,
+        cols: &str,
+        level: u8,
+        collapsed: bool,
+    ) -> Result<&mut Self, XlsxError> {
+        check_level(level)?;
+        let (lo, hi) = parse_span(cols, true)?;
+        // в <cols> столбцы нумеруются с 1
+        let (lo, hi) = (lo + 1, hi + 1);
+        let summary = match self.summary_before("summaryRight")? {
+            false => Some(hi + 1),
+            true => lo.checked_sub(1).filter(|c| *c >= 1),
+        };
+
+        let mut max_level = 0u8;
+        self.edit_cols(|map| {
+            for c in lo..=hi {
+                let prop = map.entry(c).or_default();
+                prop.outline_level = level;
+                prop.hidden |= collapsed && level > 0;
+            }
+            if let Some(s) = summary {
+                if collapsed && level > 0 {
+                    map.entry(s).or_default().collapsed = true;
+                } else if let Some(prop) = map.get_mut(&s) {
+                    prop.collapsed = false;
+                }
+            }
+            // столбцы без свойств не нужны в <cols>
+            map.retain(|_, p| *p != Default::default());
+            max_level = map.values().map(|p| p.outline_level).max().unwrap_or(0);
+        })?;
+        self.set_outline_levels("outlineLevelCol", max_level)?;
+        Ok(self)
+    }
+
+    /// Скрывает столбцы `cols` (`"D:F"`).
+    pub fn hide_columns(&mut self, cols: &str) -> Result<&mut Self, XlsxError> {
+        self.edit_col_span(cols, |p| p.hidden = true)?;
+        Ok(self)
+    }
+
+    /// Показывает скрытые столбцы `cols`, в том числе свёрнутые в группу.
+    pub fn show_columns(&mut self, cols: &str) -> Result<&mut Self, XlsxError> {
+        self.edit_col_span(cols, |p| p.hidden = false)?;
+        Ok(self)
+    }
+
+    /// Уровень группировки столбцов `cols` (0..=7) без сворачивания и
+    /// итогового столбца — как `outlineLevel` в самом файле.
+    pub fn set_column_outline_level(
+        &mut self,
+        cols: &str,
+        level: u8,
+    ) -> Result<&mut Self, XlsxError> {
+        check_level(level)?;
+        let max_level = self.edit_col_span(cols, |p| p.outline_level = level)?;
+        self.set_outline_levels("outlineLevelCol", max_level)?;
+        Ok(self)
+    }
+
+    /* ----------------------------- helpers ------------------------------ */
+
+    /// Применяет `f` к свойствам столбцов `cols` (`"B:D"`); возвращает
+    /// наибольший уровень группировки столбцов после правки.
+    pub(crate) fn edit_col_span(&mut self, cols: &str, f: impl Fn(&mut ColProp)) -> Result<u8> {
+        let (lo, hi) = parse_span(cols, true)?;
+        let mut max_level = 0u8;
+        self.edit_cols(|map| {
+            // в <cols> столбцы нумеруются с 1
+            for c in lo + 1..=hi + 1 {
+                f(map.entry(c).or_default());
+            }
+            // столбцы без свойств не нужны в <cols>
+            map.retain(|_, p| *p != Default::default());
+            max_level = map.values().map(|p| p.outline_level).max().unwrap_or(0);
+        })?;
+        Ok(max_level)
+    }
+
+    /// Итоги стоят перед группой (`<outlinePr summaryBelow|summaryRight="0">`).
+    fn summary_before(&self, attr: &str) -> Result<bool> {
+        let xml = &self.sheet_xml;
+        let Some(pr) = find_child(xml, "sheetPr")? else {
+            return Ok(false);
+        };
+        let q = format!("{}outlinePr", root_prefix(xml));
+        Ok(find_elem(&xml[..pr.end], &q, pr.start)
+            .and_then(|r| get_attr(&xml[r], attr))
+            .is_some_and(|v| v == "0" || v == "false"))
+    }
+
+    /// Пишет наибольший уровень группировки в `<sheetFormatPr>` (`outlineLevelRow`/`Col`).
+    fn set_outline_levels(&mut self, attr: &str, level: u8) -> Result<()> {
+        let xml = &mut self.sheet_xml;
+        if find_child(xml, "sheetFormatPr")?.is_none() {
+            if level == 0 {
+                return Ok(());
+            }
+            let block = format!(
+                r#"<{}sheetFormatPr defaultRowHeight="15"/>"#,
+                root_prefix(xml)
+            );
+            insert_child(xml, "sheetFormatPr", block.as_bytes())?;
+        }
+        let lvl = level.to_string();
+        edit_empty_child(xml, "sheetFormatPr", |tag| {
+            set_attr(tag, attr, (level > 0).then_some(&*lvl));
+        })
+    }
+
+    /// Создаёт пустые `<row>` для строк `lo..=hi`, которых ещё нет в `<sheetData>`.
+    pub(crate) fn ensure_rows(&mut self, lo: u32, hi: u32) -> Result<()> {
+        let sd = open_sheet_data(&mut self.sheet_xml)?;
+        let p = root_prefix(&self.sheet_xml);
+        let mut data = self.sheet_xml[sd.clone()].to_vec();
+
+        // начала существующих строк по номерам
+        let q = format!("{p}row");
+        let mut existing: Vec<(u32, usize)> = Vec::new();
+        let mut from = 0;
+        while let Some(e) = find_elem(&data, &q, from) {
+            from = e.end;
+            let gt = e.start + memchr::memchr(b'>', &data[e.start..]).unwrap_or(0);
+            if let Some(r) = get_attr(&data[e.start..=gt], "r").and_then(|r| r.parse().ok()) {
+                existing.push((r, e.start));
+            }
+        }
+        let close = data.len();
+        for r in (lo..=hi).rev() {
+            if existing.iter().any(|(n, _)| *n == r) {
+                continue;
+            }
+            let at = existing
+                .iter()
+                .find(|(n, _)| *n > r)
+                .map_or(close, |(_, pos)| *pos);
+            // без самозакрывающегося тега: патчер стилей ищет `</row>`
+            let row = format!(r#"<{q} r="{r}"></{q}>"#);
+            data.splice(at..at, row.bytes());
+        }
+        self.sheet_xml.splice(sd, data);
+        Ok(())
+    }
+}
+
+fn check_level(level: u8) -> Result<()> {
+    if level > MAX_OUTLINE_LEVEL {
+        bail!("outline level must be within 0..={MAX_OUTLINE_LEVEL}, got {level}");
+    }
+    Ok(())
+}
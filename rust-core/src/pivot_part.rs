@@ -0,0 +1,126 @@
+//! pivot_part.rs – кэши сводных таблиц, построенных на данных текущего листа:
+//! `refreshOnLoad` у `<pivotCacheDefinition>` и расширение `<worksheetSource ref>`.
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::search::find;
+use crate::sheet_xml::{find_elem, get_attr, root_prefix, root_tag_range, set_attr};
+use crate::table_part::{format_range, parse_range};
+use anyhow::Result;
+use quick_xml::escape::unescape;
+use std::ops::Range;
+
+/// Кэш сводной таблицы, источник которого — текущий лист.
+struct PivotCache {
+    path: String,
+    xml: Vec<u8>,
+    /// открывающий тег `<worksheetSource>` в `xml`
+    source: Range<usize>,
+}
+
+impl XlsxEditor {
+    /// Помечает кэши сводных таблиц, построенных на текущем листе (на диапазоне
+    /// или на таблице листа), как устаревшие: `refreshOnLoad="1"` — Excel обновит
+    /// сводные таблицы при открытии книги.
+    pub fn mark_pivot_caches_dirty(&mut self) -> Result<&mut Self, XlsxError> {
+        self.flush_appended_ranges()?;
+        for mut cache in self.sheet_pivot_caches()? {
+            mark_dirty(&mut cache.xml);
+            self.write_part(&cache.path, cache.xml);
+        }
+        Ok(self)
+    }
+
+    /// После дописывания строк: источники кэшей, заканчивающиеся на строке
+    /// `old_last`, дотягиваются до `new_last` и помечаются к обновлению.
+    pub(crate) fn grow_pivot_sources(&mut self, old_last: u32, new_last: u32) -> Result<()> {
+        for mut cache in self.sheet_pivot_caches()? {
+            let mut tag = cache.xml[cache.source.clone()].to_vec();
+            let Some((c0, r0, c1, r1)) = get_attr(&tag, "ref").and_then(|r| parse_range(&r).ok())
+            else {
+                continue;
+            };
+            if r1 != old_last {
+                continue;
+            }
+            set_attr(&mut tag, "ref", Some(&format_range((c0, r0, c1, new_last))));
+            cache.xml.splice(cache.source, tag);
+            mark_dirty(&mut cache.xml);
+            self.write_part(&cache.path, cache.xml);
+        }
+        Ok(())
+    }
+
+    /* ----------------------------- helpers ------------------------------ */
+
+    /// Кэши сводных таблиц книги с источником на текущем листе.
+    fn sheet_pivot_caches(&mut self) -> Result<Vec<PivotCache>> {
+        // большинство книг без сводных таблиц: не читаем связи зря. Пока
+        // workbook.xml не читали, он как в архиве — смотрим на части архива.
+        let has_caches = match self.workbook_xml.loaded() {
+            Some(wb) => find(wb, b"pivotCache").is_some(),
+            None => self.source_pivots,
+        };
+        if !has_caches {
+            return Ok(Vec::new());
+        }
+        let sheet = self.current_sheet_name()?.to_lowercase();
+        let tables: Vec<String> = self
+            .sheet_tables()?
+            .into_iter()
+            .map(|t| t.name.to_lowercase())
+            .collect();
+
+        let targets: Vec<String> = self
+            .rels_of("xl/workbook.xml")?
+            .of_kind("pivotCacheDefinition")
+            .map(|r| r.target.clone())
+            .collect();
+        let mut out = Vec::new();
+        for path in targets {
+            let Some(xml) = self.read_part(&path)? else {
+                continue;
+            };
+            let q = format!("{}worksheetSource", root_prefix(&xml));
+            let Some(elem) = find_elem(&xml, &q, 0) else {
+                continue;
+            };
+            let gt = elem.start + memchr::memchr(b'>', &xml[elem.start..]).unwrap_or(0);
+            let tag = &xml[elem.start..=gt];
+            let attr = |name: &str| {
+                get_attr(tag, name).map(|v| {
+                    unescape(&v)
+                        .map_or(v.clone(), |s| s.into_owned())
+                        .to_lowercase()
+                })
+            };
+            // источник во внешней книге
+            if get_attr(tag, "r:id").is_some() {
+                continue;
+            }
+            let ours = match (attr("sheet"), attr("name")) {
+                (Some(s), _) => s == sheet,
+                (None, Some(n)) => tables.contains(&n),
+                (None, None) => false,
+            };
+            if ours {
+                out.push(PivotCache {
+                    path,
+                    source: elem.start..gt + 1,
+                    xml,
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// `refreshOnLoad="1"` на корне `<pivotCacheDefinition>`.
+fn mark_dirty(xml: &mut Vec<u8>) {
+    let Some(r) = root_tag_range(xml) else {
+        return;
+    };
+    let mut tag = xml[r.clone()].to_vec();
+    set_attr(&mut tag, "refreshOnLoad", Some("1"));
+    xml.splice(r, tag);
+}
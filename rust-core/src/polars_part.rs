@@ -1,18 +1,121 @@
 use crate::XlsxEditor;
 #[cfg(feature = "polars")]
-use crate::style::{col_letter, split_coord};
+use crate::cell_xml::{push_col, push_f32, push_f64, push_int};
 #[cfg(feature = "polars")]
-use anyhow::{Result, bail};
+use crate::error::{XlsxError, check_row, ensure};
+#[cfg(feature = "polars")]
+use crate::read_part::{ReadValue, date_styles, read_cells, read_value};
+#[cfg(feature = "polars")]
+use crate::search::{ROW_CLOSE, ROW_OPEN, find, find_markup};
+#[cfg(feature = "polars")]
+use crate::sheet_xml::{get_attr, open_sheet_data, refresh_dimension, set_attr};
+#[cfg(feature = "polars")]
+use crate::style::{col_letter, parse_cell, split_coord};
+#[cfg(feature = "polars")]
+use crate::table_part::{format_range, parse_range};
+#[cfg(feature = "polars")]
+use crate::template_part::CellValue;
+#[cfg(feature = "polars")]
+use anyhow::Result;
 #[cfg(feature = "polars")]
 use polars_core::prelude::*;
 #[cfg(feature = "polars")]
 use quick_xml::Writer;
 #[cfg(feature = "polars")]
+use quick_xml::escape::escape;
+#[cfg(feature = "polars")]
 use quick_xml::events::BytesText;
+#[cfg(feature = "polars")]
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Атрибуты `<row>`, переносимые при пересборке строки (всё, кроме `r`).
+#[cfg(feature = "polars")]
+const ROW_ATTRS: [&str; 11] = [
+    "spans",
+    "s",
+    "customFormat",
+    "ht",
+    "hidden",
+    "customHeight",
+    "outlineLevel",
+    "collapsed",
+    "thickTop",
+    "thickBot",
+    "x14ac:dyDescent",
+];
+
+/// Оформление листа для [`XlsxEditor::write_polars`]: один вызов даёт готовую
+/// к показу таблицу, без отдельных `set_*` по вычисленным диапазонам.
+#[cfg(feature = "polars")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PolarsWriteOptions {
+    /// Левый верхний угол (ячейка заголовка первого столбца), по умолчанию `A1`.
+    pub start_cell: Option<String>,
+    /// Числовые форматы по именам столбцов (`"price" → "#,##0.00"`); заменяют
+    /// форматы, которые столбец получил бы по типу (даты, Decimal).
+    pub number_formats: HashMap<String, String>,
+    /// Оформление строки заголовка; `None` — заголовок без стиля.
+    pub header_style: Option<HeaderStyle>,
+    /// Ширина столбцов по содержимому, см. [`XlsxEditor::autofit_columns`].
+    pub autofit: bool,
+    /// Закрепить строки до заголовка включительно.
+    pub freeze_header: bool,
+    /// Имя таблицы Excel на записанном диапазоне; `None` — без таблицы.
+    pub table: Option<String>,
+    /// Стиль таблицы, по умолчанию `TableStyleMedium2`.
+    pub table_style: Option<String>,
+    /// Автофильтр по заголовку; у таблицы фильтр свой, так что вместе с
+    /// `table` не используется.
+    pub autofilter: bool,
+}
+
+/// Стиль строки заголовка в [`PolarsWriteOptions`].
+#[cfg(feature = "polars")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderStyle {
+    /// Жирный шрифт (Calibri 11).
+    pub bold: bool,
+    /// Цвет заливки RGB: `"D9E1F2"`.
+    pub fill: Option<String>,
+    /// Граница ячеек: `"thin"`, `"medium"`…, см. [`XlsxEditor::set_border`].
+    pub border: Option<String>,
+}
 
 impl XlsxEditor {
     #[cfg(feature = "polars")]
-    pub fn with_polars(&mut self, df: &DataFrame, start_cell: Option<&str>) -> Result<()> {
+    pub fn with_polars(
+        &mut self,
+        df: &DataFrame,
+        start_cell: Option<&str>,
+    ) -> Result<(), XlsxError> {
+        self.with_polars_chunked(df, start_cell, df.height().max(1))
+    }
+
+    /// Как [`Self::with_polars`], но строки `df` превращаются в XML и
+    /// вставляются в лист блоками по `chunk_rows`: кроме XML листа в памяти
+    /// лежит один блок, а не весь DataFrame. С фичей `rayon` строки блока
+    /// сериализуются параллельно.
+    #[cfg(feature = "polars")]
+    pub fn with_polars_chunked(
+        &mut self,
+        df: &DataFrame,
+        start_cell: Option<&str>,
+        chunk_rows: usize,
+    ) -> Result<(), XlsxError> {
+        Ok(self.write_frame(df, start_cell, chunk_rows, &HashMap::new())?)
+    }
+
+    /// Общая часть [`Self::with_polars_chunked`] и [`Self::write_polars`]:
+    /// `formats` — числовые форматы по именам столбцов поверх форматов по типу.
+    #[cfg(feature = "polars")]
+    fn write_frame(
+        &mut self,
+        df: &DataFrame,
+        start_cell: Option<&str>,
+        chunk_rows: usize,
+        formats: &HashMap<String, String>,
+    ) -> Result<()> {
+        ensure!(chunk_rows > 0, "chunk_rows must be positive");
         // ---------- 0.  Координаты ----------
         let start_coord = start_cell.unwrap_or("A1");
         let (base_col, first_row) = {
@@ -27,9 +130,9 @@ impl XlsxEditor {
         let last_row = first_row + df.height() as u32; // header + N строк данных
 
         // ---------- 0‑bis.  Сносим старые строки в диапазоне ----------
+        let mut old_rows = BTreeMap::<u32, Vec<u8>>::new();
         let mut i = 0;
-        while let Some(beg_rel) = self.sheet_xml[i..].windows(4).position(|w| w == b"<row") {
-            let beg = i + beg_rel;
+        while let Some(beg) = find_markup(&ROW_OPEN, &self.sheet_xml, i) {
 
             // следующий символ после "<row" должен быть пробел или '>'
             let after = beg + 4;
@@ -48,22 +151,20 @@ impl XlsxEditor {
             };
             let open_end = after + open_end_rel + 1; // позиция сразу после '>'
 
-            // конец всего блока </row>
-            let Some(close_rel) = self.sheet_xml[open_end..]
-                .windows(6)
-                .position(|w| w == b"</row>")
-            else {
-                break;
+            // конец всего блока </row> (у пустой строки `<row …/>` — конец тега)
+            let row_end = if self.sheet_xml[open_end - 2] == b'/' {
+                open_end
+            } else {
+                let Some(close) = find_markup(&ROW_CLOSE, &self.sheet_xml, open_end) else {
+                    break;
+                };
+                close + 6 // позиция сразу после "</row>"
             };
-            let row_end = open_end + close_rel + 6; // позиция сразу после "</row>"
 
             // 1) пробуем достать r="N" только из открывающего тега <row ...>
             let mut row_num_opt = None;
-            if let Some(r_pos_rel) = self.sheet_xml[beg..open_end]
-                .windows(3)
-                .position(|w| w == b"r=\"")
-            {
-                let r_pos = beg + r_pos_rel + 3;
+            if let Some(r_pos) = find(&self.sheet_xml[beg..open_end], b"r=\"") {
+                let r_pos = beg + r_pos + 3;
                 if let Some(q_end_rel) = self.sheet_xml[r_pos..open_end]
                     .iter()
                     .position(|&b| b == b'"')
@@ -77,11 +178,8 @@ impl XlsxEditor {
 
             // 2) fallback: берем номер строки по первой ячейке внутри этого <row>
             if row_num_opt.is_none() {
-                if let Some(c_r_rel) = self.sheet_xml[open_end..row_end]
-                    .windows(3)
-                    .position(|w| w == b"r=\"")
-                {
-                    let r_pos = open_end + c_r_rel + 3;
+                if let Some(c_r) = find(&self.sheet_xml[open_end..row_end], b"r=\"") {
+                    let r_pos = open_end + c_r + 3;
                     if let Some(q_end_rel) = self.sheet_xml[r_pos..row_end]
                         .iter()
                         .position(|&b| b == b'"')
@@ -103,6 +201,8 @@ impl XlsxEditor {
 
             if let Some(row_num) = row_num_opt {
                 if row_num >= first_row && row_num <= last_row {
+                    // высоту, стиль, уровень группировки строки переносим на новую
+                    old_rows.insert(row_num, self.sheet_xml[beg..open_end].to_vec());
                     // вырезаем весь <row>...</row>, чтобы точно не было дублей
                     self.sheet_xml.splice(beg..row_end, std::iter::empty());
                     i = 0; // начинаем поиск заново с начала буфера
@@ -115,14 +215,9 @@ impl XlsxEditor {
         }
 
         // ---------- 1.  Метаданные столбцов ----------
-        struct ColMeta {
-            is_number: bool,
-            style_id: Option<u32>,
-            conv: Box<dyn Fn(AnyValue) -> String>,
-        }
-
+        let columns = plain_columns(df)?;
         let mut cols = Vec::<ColMeta>::with_capacity(df.width());
-        for s in df.get_columns() {
+        for s in &columns {
             match s.dtype() {
                 DataType::String => cols.push(ColMeta {
                     is_number: false,
@@ -153,143 +248,43 @@ impl XlsxEditor {
                     style_id: None,
                     conv: Box::new(|v| v.to_string()),
                 }),
+                // значения — `t="b"`, см. Kind::Bool
+                DataType::Boolean => cols.push(ColMeta {
+                    is_number: false,
+                    style_id: None,
+                    conv: Box::new(|v| v.to_string()),
+                }),
+                // даты, время и Decimal — числами, формат один на столбец
+                dt if column_format(dt).is_some() => cols.push(ColMeta {
+                    is_number: true,
+                    style_id: Some(self.ensure_style(
+                        column_format(dt).as_deref(),
+                        None,
+                        None,
+                        None,
+                        None,
+                    )?),
+                    conv: Box::new(|v| v.to_string()),
+                }),
                 _ => cols.push(ColMeta {
                     is_number: false,
                     style_id: None,
                     conv: Box::new(|v| v.to_string()),
                 }),
             }
+            if let (Some(fmt), Some(meta)) = (formats.get(s.name().as_str()), cols.last_mut()) {
+                meta.style_id = Some(self.ensure_style(Some(fmt), None, None, None, None)?);
+            }
         }
 
-        // ---------- 2.  Генерим XML: сначала заголовок, потом данные ----------
-        let mut bulk_rows_xml = Vec::<u8>::new();
-
-        // 2.1 Хедер
-        let mut cur_row = first_row;
-        {
-            let mut w = Writer::new(Vec::new());
-            w.create_element("row")
-                .with_attribute(("r", cur_row.to_string().as_str()))
-                .write_inner_content(|wr| {
-                    for (col_idx, s) in df.get_columns().iter().enumerate() {
-                        let coord =
-                            format!("{}{}", col_letter(base_col.0 + col_idx as u32), cur_row);
-                        let c = wr
-                            .create_element("c")
-                            .with_attribute(("r", coord.as_str()))
-                            .with_attribute(("t", "inlineStr")); // всегда текст
-
-                        c.write_inner_content(|w2| {
-                            w2.create_element("is").write_inner_content(|w3| {
-                                w3.create_element("t")
-                                    .write_text_content(BytesText::new(s.name()))?;
-                                Ok(())
-                            })?;
-                            Ok(())
-                        })?;
-                    }
-                    Ok(())
-                })?;
-            bulk_rows_xml.extend_from_slice(&w.into_inner());
-            cur_row += 1;
-        }
-
-        // 2.2 Данные
-        for idx in 0..df.height() {
-            let mut w = Writer::new(Vec::new());
-            w.create_element("row")
-                .with_attribute(("r", cur_row.to_string().as_str()))
-                .write_inner_content(|wr| {
-                    for (col_idx, s) in df.get_columns().iter().enumerate() {
-                        let coord =
-                            format!("{}{}", col_letter(base_col.0 + col_idx as u32), cur_row);
-                        let val = s.get(idx).unwrap_or(AnyValue::Null);
-                        let meta = &cols[col_idx];
-
-                        enum Kind {
-                            Blank,
-                            Num(String),
-                            Str(String),
-                        }
-                        let kind = match val {
-                            AnyValue::Null => Kind::Blank,
-                            AnyValue::Float64(x) => {
-                                if x.is_finite() {
-                                    Kind::Num(x.to_string())
-                                } else {
-                                    Kind::Blank
-                                }
-                            }
-                            AnyValue::Float32(x) => {
-                                if x.is_finite() {
-                                    Kind::Num(x.to_string())
-                                } else {
-                                    Kind::Blank
-                                }
-                            }
-                            _ => {
-                                if meta.is_number {
-                                    Kind::Num(val.to_string())
-                                } else {
-                                    Kind::Str((meta.conv)(val))
-                                }
-                            }
-                        };
-
-                        let is_text = matches!(kind, Kind::Str(_));
-                        let mut c = wr.create_element("c").with_attribute(("r", coord.as_str()));
-                        if let Some(sid) = meta.style_id {
-                            c = c.with_attribute(("s", sid.to_string().as_str()));
-                        }
-                        if is_text {
-                            c = c.with_attribute(("t", "inlineStr"));
-                        }
-
-                        c.write_inner_content(|w2| {
-                            match kind {
-                                Kind::Blank => { /* пустая ячейка — ничего не пишем */
-                                }
-                                Kind::Num(txt) => {
-                                    w2.create_element("v")
-                                        .write_text_content(BytesText::new(&txt))?;
-                                }
-                                Kind::Str(txt) => {
-                                    w2.create_element("is").write_inner_content(|w3| {
-                                        w3.create_element("t")
-                                            .write_text_content(BytesText::new(&txt))?;
-                                        Ok(())
-                                    })?;
-                                }
-                            }
-                            Ok(())
-                        })?;
-                    }
-                    Ok(())
-                })?;
-            bulk_rows_xml.extend_from_slice(&w.into_inner());
-            cur_row += 1;
-        }
-
-        // ---------- 3.  Вставляем новые строки ----------
-        // 3. Вставляем новые строки в правильное место (сортировка по r)
-        let sd_open = if let Some(p) = self.sheet_xml.windows(11).position(|w| w == b"<sheetData>")
-        {
-            p + 11
-        } else {
-            bail!("<sheetData> tag not found");
-        };
-
+        // ---------- 2.  Место вставки (сортировка по r) ----------
         // по умолчанию — перед </sheetData>
-        let mut insert_pos = self
-            .sheet_xml
-            .windows(12)
-            .rposition(|w| w == b"</sheetData>")
-            .ok_or_else(|| anyhow::anyhow!("</sheetData> tag not found"))?;
+        let sd = open_sheet_data(&mut self.sheet_xml)?;
+        let (sd_open, mut insert_pos) = (sd.start, sd.end);
 
         // ищем первую <row> с r >= first_row
         let mut j = sd_open;
-        while let Some(beg_rel) = self.sheet_xml[j..].windows(4).position(|w| w == b"<row") {
-            let beg = j + beg_rel;
+        while let Some(beg) = find_markup(&ROW_OPEN, &self.sheet_xml, j) {
             let after = beg + 4;
             if after >= self.sheet_xml.len() {
                 break;
@@ -305,20 +300,18 @@ impl XlsxEditor {
             };
             let open_end = after + open_end_rel + 1;
 
-            let Some(close_rel) = self.sheet_xml[open_end..]
-                .windows(6)
-                .position(|w| w == b"</row>")
-            else {
-                break;
+            let row_end = if self.sheet_xml[open_end - 2] == b'/' {
+                open_end
+            } else {
+                let Some(close) = find_markup(&ROW_CLOSE, &self.sheet_xml, open_end) else {
+                    break;
+                };
+                close + 6
             };
-            let row_end = open_end + close_rel + 6;
 
             let mut row_num_opt = None;
-            if let Some(r_pos_rel) = self.sheet_xml[beg..open_end]
-                .windows(3)
-                .position(|w| w == b"r=\"")
-            {
-                let r_pos = beg + r_pos_rel + 3;
+            if let Some(r_pos) = find(&self.sheet_xml[beg..open_end], b"r=\"") {
+                let r_pos = beg + r_pos + 3;
                 if let Some(q_end_rel) = self.sheet_xml[r_pos..open_end]
                     .iter()
                     .position(|&b| b == b'"')
@@ -340,28 +333,523 @@ impl XlsxEditor {
             j = row_end;
         }
 
-        self.sheet_xml.splice(insert_pos..insert_pos, bulk_rows_xml);
-        self.last_row = last_row;
-        if let Some(dim_beg) = self
-            .sheet_xml
-            .windows(16)
-            .position(|w| w == b"<dimension ref=\"")
+        // ---------- 3.  Заголовок, затем данные блоками ----------
         {
-            let start = dim_beg + 16;
-            if let Some(q_end_rel) = self.sheet_xml[start..].iter().position(|&b| b == b'"') {
-                let end = start + q_end_rel;
-                let last_col = col_letter(base_col.0 + (df.width().saturating_sub(1) as u32));
-                let dim = format!(
-                    "{}{}:{}{}",
-                    col_letter(base_col.0),
-                    first_row,
-                    last_col,
-                    last_row
-                );
-                self.sheet_xml.splice(start..end, dim.into_bytes());
+            let mut w = Writer::new(Vec::new());
+            w.create_element("row")
+                .with_attribute(("r", first_row.to_string().as_str()))
+                .write_inner_content(|wr| {
+                    for (col_idx, s) in columns.iter().enumerate() {
+                        let coord =
+                            format!("{}{}", col_letter(base_col.0 + col_idx as u32), first_row);
+                        let c = wr
+                            .create_element("c")
+                            .with_attribute(("r", coord.as_str()))
+                            .with_attribute(("t", "inlineStr")); // всегда текст
+
+                        c.write_inner_content(|w2| {
+                            w2.create_element("is").write_inner_content(|w3| {
+                                w3.create_element("t")
+                                    .write_text_content(BytesText::new(s.name()))?;
+                                Ok(())
+                            })?;
+                            Ok(())
+                        })?;
+                    }
+                    Ok(())
+                })?;
+            let header = keep_row_attrs(w.into_inner(), old_rows.get(&first_row));
+            let n = header.len();
+            self.sheet_xml.splice(insert_pos..insert_pos, header);
+            insert_pos += n;
+        }
+
+        let mut buf = Vec::new();
+        for start in (0..df.height()).step_by(chunk_rows) {
+            let rows = start..(start + chunk_rows).min(df.height());
+            let xml_of = |idx: usize| {
+                let row = first_row + 1 + idx as u32;
+                data_row_xml(&columns, &cols, idx, row, base_col.0, old_rows.get(&row))
+            };
+            #[cfg(feature = "rayon")]
+            {
+                use rayon::prelude::*;
+                let parts = rows
+                    .into_par_iter()
+                    .map(xml_of)
+                    .collect::<Vec<_>>();
+                parts.iter().for_each(|p| buf.extend_from_slice(p));
             }
+            #[cfg(not(feature = "rayon"))]
+            for idx in rows {
+                buf.extend(xml_of(idx));
+            }
+            let n = buf.len();
+            self.sheet_xml.splice(insert_pos..insert_pos, buf.drain(..));
+            insert_pos += n;
         }
+        self.last_row = last_row;
+        refresh_dimension(&mut self.sheet_xml)?;
 
         Ok(())
     }
+
+    /// Пишет `df` как [`Self::with_polars`] и оформляет результат по `opts`:
+    /// форматы столбцов, стиль заголовка, ширина столбцов, закрепление,
+    /// таблица или автофильтр. Возвращает буквы столбцов листа по именам
+    /// столбцов `df` (в их порядке) — для формул и дальнейшего оформления.
+    #[cfg(feature = "polars")]
+    pub fn write_polars(
+        &mut self,
+        df: &DataFrame,
+        opts: &PolarsWriteOptions,
+    ) -> Result<Vec<(String, String)>, XlsxError> {
+        ensure!(df.width() > 0, "DataFrame has no columns");
+        for name in opts.number_formats.keys() {
+            ensure!(df.column(name).is_ok(), "no column {name} in DataFrame");
+        }
+        let start = opts.start_cell.as_deref().unwrap_or("A1");
+        let (c0, r0) = parse_cell(start)?;
+        let (c1, r1) = (c0 + df.width() as u32 - 1, r0 + df.height() as u32);
+        self.write_frame(df, Some(start), df.height().max(1), &opts.number_formats)?;
+
+        let header = format_range((c0, r0, c1, r0));
+        if let Some(h) = &opts.header_style {
+            if h.bold {
+                self.set_font(&header, "Calibri", 11.0, true, false)?;
+            }
+            if let Some(rgb) = &h.fill {
+                self.set_fill(&header, rgb)?;
+            }
+            if let Some(b) = &h.border {
+                self.set_border(&header, b)?;
+            }
+        }
+        let names: Vec<String> = df
+            .get_column_names()
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        let whole = format_range((c0, r0, c1, r1));
+        if let Some(name) = &opts.table {
+            let headers: Vec<&str> = names.iter().map(String::as_str).collect();
+            self.add_table(&whole, name, opts.table_style.as_deref(), Some(&headers))?;
+        } else if opts.autofilter {
+            self.set_auto_filter(&whole)?;
+        }
+        if opts.autofit {
+            self.autofit_columns(Some(&whole))?;
+        }
+        if opts.freeze_header {
+            self.freeze_panes(&format!("A{}", r0 + 1))?;
+        }
+
+        Ok(names
+            .into_iter()
+            .zip(c0..)
+            .map(|(n, c)| (n, col_letter(c)))
+            .collect())
+    }
+
+    /// Читает диапазон `range` текущего листа (`None` — все данные листа, см.
+    /// [`Self::used_range`]) в DataFrame. При `has_header` первая строка даёт
+    /// имена столбцов (пустые — `column_N`, повторы — с суффиксом `_N`).
+    ///
+    /// Тип столбца выводится по его непустым ячейкам: только целые числа —
+    /// `Int64`, числа — `Float64`, числа в формате даты — `Date` (или
+    /// `Datetime[ms]`, если есть время), `TRUE`/`FALSE` — `Boolean`, иначе
+    /// `String` с текстом ячеек как он записан в файле.
+    #[cfg(feature = "polars")]
+    pub fn to_polars(
+        &mut self,
+        range: Option<&str>,
+        has_header: bool,
+    ) -> Result<DataFrame, XlsxError> {
+        let (c0, r0, c1, r1) = match range {
+            Some(r) => parse_range(r)?,
+            None => {
+                let used = self.used_range()?;
+                if used.is_empty() {
+                    return Ok(DataFrame::empty());
+                }
+                (used.first_col, used.first_row, used.last_col, used.last_row)
+            }
+        };
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let dates = date_styles(self.styles_xml.get(&self.src)?)?;
+
+        // значения по столбцам
+        let height = (r1 - r0 + 1) as usize;
+        let mut grid = vec![vec![ReadValue::Empty; height]; (c1 - c0 + 1) as usize];
+        for c in read_cells(&self.sheet_xml)? {
+            if (c0..=c1).contains(&c.col) && (r0..=r1).contains(&c.row) {
+                grid[(c.col - c0) as usize][(c.row - r0) as usize] = read_value(&c, sst, &dates);
+            }
+        }
+
+        let mut used_names = HashSet::new();
+        let mut columns = Vec::with_capacity(grid.len());
+        for (i, mut values) in grid.into_iter().enumerate() {
+            let mut name = if has_header {
+                values.remove(0).to_text().unwrap_or_default()
+            } else {
+                String::new()
+            };
+            if name.is_empty() {
+                name = format!("column_{}", i + 1);
+            }
+            let base = name.clone();
+            let mut n = 1;
+            while !used_names.insert(name.clone()) {
+                name = format!("{base}_{n}");
+                n += 1;
+            }
+            columns.push(build_column(&name, values)?);
+        }
+        Ok(DataFrame::new(columns).map_err(anyhow::Error::from)?)
+    }
+
+    /// Дописывает `df` под данными листа, с `last_row + 1`, без расчёта
+    /// начальной ячейки. Если в первой строке данных листа (заголовке) есть
+    /// имена столбцов `df`, значения попадают в одноимённые столбцы, а столбцы
+    /// без пары — правее заголовка (их имена дописываются в него); иначе столбцы идут подряд от первого столбца
+    /// данных (`A` у пустого листа). При `include_header` первой пишется строка
+    /// имён. Даты и время получают формат `yyyy-mm-dd` / `yyyy-mm-dd hh:mm:ss`.
+    #[cfg(feature = "polars")]
+    pub fn append_polars(&mut self, df: &DataFrame, include_header: bool) -> Result<(), XlsxError> {
+        let targets = self.polars_targets(df)?;
+        let columns = plain_columns(df)?;
+        let old_last = self.last_row;
+        check_row(old_last as u64 + df.height() as u64 + u64::from(include_header))?;
+
+        let mut bulk_rows_xml = Vec::new();
+        let mut row = old_last;
+        if include_header {
+            row += 1;
+            let cells = df
+                .get_columns()
+                .iter()
+                .zip(&targets)
+                .map(|(s, &col)| (col, CellValue::Text(s.name().to_string()), None));
+            bulk_rows_xml.extend(row_xml(row, cells.collect()));
+        }
+        let styles = columns
+            .iter()
+            .map(|s| match column_format(s.dtype()) {
+                Some(fmt) => self
+                    .ensure_style(Some(&fmt), None, None, None, None)
+                    .map(Some),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for idx in 0..df.height() {
+            row += 1;
+            let cells = columns
+                .iter()
+                .zip(&targets)
+                .zip(&styles)
+                .map(|((s, &col), &sid)| {
+                    (col, polars_cell(s.get(idx).unwrap_or(AnyValue::Null)), sid)
+                });
+            bulk_rows_xml.extend(row_xml(row, cells.collect()));
+        }
+
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, bulk_rows_xml);
+        self.last_row = row;
+        let (lo, hi) = (targets.iter().min(), targets.iter().max());
+        let written = lo
+            .zip(hi)
+            .filter(|_| row > old_last)
+            .map(|(&lo, &hi)| (lo, old_last + 1, hi, row));
+        Ok(self.grow_ranges_on_append(old_last, written)?)
+    }
+
+    /// Столбцы листа (0-based) для столбцов `df` — см. [`Self::append_polars`].
+    #[cfg(feature = "polars")]
+    fn polars_targets(&mut self, df: &DataFrame) -> Result<Vec<u32>> {
+        let used = self.used_range()?;
+        let (row, header) = match self.header_columns()? {
+            Some(h) => (h.row, h.cols),
+            None => (0, HashMap::new()),
+        };
+
+        let names: Vec<String> = df
+            .get_column_names()
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        if !names.iter().any(|n| header.contains_key(n)) {
+            return Ok((used.first_col..).take(names.len()).collect());
+        }
+        let mut next = header.values().max().map_or(0, |c| c + 1);
+        let mut targets = Vec::with_capacity(names.len());
+        for name in &names {
+            match header.get(name) {
+                Some(&col) => targets.push(col),
+                None => {
+                    // новый столбец продолжает заголовок
+                    self.set_cell(&format!("{}{row}", col_letter(next)), name)?;
+                    targets.push(next);
+                    next += 1;
+                }
+            }
+        }
+        Ok(targets)
+    }
+}
+
+/// `<row>` с ячейками `(столбец, значение, стиль)`; пустые значения не пишутся.
+#[cfg(feature = "polars")]
+fn row_xml(row: u32, mut cells: Vec<(u32, CellValue, Option<u32>)>) -> Vec<u8> {
+    cells.sort_by_key(|(col, ..)| *col);
+    let mut xml = format!(r#"<row r="{row}">"#).into_bytes();
+    for (col, value, s) in cells.iter().filter(|(_, v, _)| *v != CellValue::Empty) {
+        xml.extend(value.cell_xml(*col, row, *s));
+    }
+    xml.extend_from_slice(b"</row>");
+    xml
+}
+
+/// Значение polars → ячейка: даты и время — серийным номером Excel.
+#[cfg(feature = "polars")]
+fn polars_cell(v: AnyValue) -> CellValue {
+    if let Some(x) = temporal_serial(&v) {
+        return CellValue::Number(x);
+    }
+    match v {
+        AnyValue::Null => CellValue::Empty,
+        AnyValue::Boolean(b) => CellValue::Bool(b),
+        AnyValue::String(s) => CellValue::Text(s.to_owned()),
+        AnyValue::StringOwned(s) => CellValue::Text(s.to_string()),
+        AnyValue::Decimal(v, scale) => CellValue::Number(v as f64 / 10f64.powi(scale as i32)),
+        v => match v.extract::<f64>() {
+            Some(x) if x.is_finite() => CellValue::Number(x),
+            Some(_) => CellValue::Empty,
+            None => CellValue::Text(v.to_string()),
+        },
+    }
+}
+
+/// Столбец по значениям: тип — общий для всех непустых ячеек, иначе `String`.
+#[cfg(feature = "polars")]
+fn build_column(name: &str, values: Vec<ReadValue>) -> Result<Column> {
+    let kinds: HashSet<_> = values
+        .iter()
+        .filter(|v| **v != ReadValue::Empty)
+        .map(std::mem::discriminant)
+        .collect();
+    let first = values.iter().find(|v| **v != ReadValue::Empty);
+    let column = match (kinds.len(), first) {
+        (1, Some(ReadValue::Number(_))) => {
+            let nums: Vec<Option<f64>> = values.iter().map(ReadValue::as_f64).collect();
+            if nums
+                .iter()
+                .flatten()
+                .all(|x| x.fract() == 0.0 && x.abs() < 9e15)
+            {
+                let ints: Vec<Option<i64>> = nums.iter().map(|x| x.map(|x| x as i64)).collect();
+                Column::new(name.into(), ints)
+            } else {
+                Column::new(name.into(), nums)
+            }
+        }
+        (1, Some(ReadValue::Date(_))) => {
+            // серийный номер → дни от 1970-01-01
+            let days: Vec<Option<f64>> = values
+                .iter()
+                .map(|v| {
+                    v.as_f64()
+                        .map(|x| if x < 60.0 { x + 1.0 } else { x } - 25_569.0)
+                })
+                .collect();
+            if days.iter().flatten().all(|d| d.fract() == 0.0) {
+                let days: Vec<Option<i32>> = days.iter().map(|d| d.map(|d| d as i32)).collect();
+                Column::new(name.into(), days).cast(&DataType::Date)?
+            } else {
+                let ms: Vec<Option<i64>> = days
+                    .iter()
+                    .map(|d| d.map(|d| (d * 86_400_000.0).round() as i64))
+                    .collect();
+                Column::new(name.into(), ms)
+                    .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            }
+        }
+        (1, Some(ReadValue::Bool(_))) => {
+            let bools: Vec<Option<bool>> = values
+                .iter()
+                .map(|v| match v {
+                    ReadValue::Bool(b) => Some(*b),
+                    _ => None,
+                })
+                .collect();
+            Column::new(name.into(), bools)
+        }
+        _ => {
+            let texts: Vec<Option<String>> = values.iter().map(ReadValue::to_text).collect();
+            Column::new(name.into(), texts)
+        }
+    };
+    Ok(column)
+}
+
+/// numFmt для столбца даты/времени или Decimal (знаков после запятой — по
+/// `scale`); `None` — формат не нужен.
+#[cfg(feature = "polars")]
+fn column_format(dtype: &DataType) -> Option<String> {
+    let fmt = match dtype {
+        DataType::Date => "yyyy-mm-dd",
+        DataType::Datetime(..) => "yyyy-mm-dd hh:mm:ss",
+        DataType::Duration(_) => "[h]:mm:ss",
+        DataType::Time => "hh:mm:ss",
+        DataType::Decimal(_, Some(scale)) if *scale > 0 => {
+            return Some(format!("0.{}", "0".repeat(*scale)));
+        }
+        DataType::Decimal(..) => "0",
+        _ => return None,
+    };
+    Some(fmt.to_owned())
+}
+
+/// Decimal как текст `<v>`: все цифры, без округления до f64.
+#[cfg(feature = "polars")]
+fn decimal_text(v: i128, scale: usize) -> String {
+    let sign = if v < 0 { "-" } else { "" };
+    let digits = format!("{:0>width$}", v.unsigned_abs(), width = scale + 1);
+    if scale == 0 {
+        return format!("{sign}{digits}");
+    }
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    format!("{sign}{int}.{frac}")
+}
+
+/// Столбцы `df` для записи: Categorical и Enum — строками своих категорий.
+#[cfg(feature = "polars")]
+fn plain_columns(df: &DataFrame) -> Result<Vec<Column>> {
+    df.get_columns()
+        .iter()
+        .map(|s| match s.dtype() {
+            DataType::Categorical(..) | DataType::Enum(..) => Ok(s.cast(&DataType::String)?),
+            _ => Ok(s.clone()),
+        })
+        .collect()
+}
+
+/// Серийный номер Excel: дни от 1899-12-30 с долей суток; у длительности и
+/// времени суток — просто число суток.
+#[cfg(feature = "polars")]
+fn temporal_serial(v: &AnyValue) -> Option<f64> {
+    let per_day = |unit: &TimeUnit| match unit {
+        TimeUnit::Nanoseconds => 86_400e9,
+        TimeUnit::Microseconds => 86_400e6,
+        TimeUnit::Milliseconds => 86_400e3,
+    };
+    let days = match v {
+        AnyValue::Date(d) => f64::from(*d),
+        AnyValue::Datetime(t, unit, _) => *t as f64 / per_day(unit),
+        AnyValue::Duration(t, unit) => return Some(*t as f64 / per_day(unit)),
+        AnyValue::Time(ns) => return Some(*ns as f64 / 86_400e9),
+        _ => return None,
+    };
+    // 1900-02-29, которого не было, Excel всё равно считает
+    let s = days + 25_569.0;
+    Some(if s < 61.0 { s - 1.0 } else { s })
+}
+
+/// Как писать значения столбца в [`XlsxEditor::with_polars_chunked`].
+#[cfg(feature = "polars")]
+struct ColMeta {
+    is_number: bool,
+    style_id: Option<u32>,
+    conv: Box<dyn Fn(AnyValue) -> String + Send + Sync>,
+}
+
+/// `<row>` с данными строки `idx` DataFrame (см. [`XlsxEditor::with_polars_chunked`]).
+/// Числа пишутся прямо в буфер строки, без промежуточных `String`.
+#[cfg(feature = "polars")]
+fn data_row_xml(
+    columns: &[Column],
+    cols: &[ColMeta],
+    idx: usize,
+    row: u32,
+    base_col: u32,
+    old: Option<&Vec<u8>>,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(32 * columns.len());
+    out.extend_from_slice(b"<row r=\"");
+    push_int(&mut out, row);
+    out.extend_from_slice(b"\">");
+    for (col_idx, s) in columns.iter().enumerate() {
+        let val = s.get(idx).unwrap_or(AnyValue::Null);
+        let meta = &cols[col_idx];
+        out.extend_from_slice(b"<c r=\"");
+        push_col(&mut out, base_col + col_idx as u32);
+        push_int(&mut out, row);
+        out.push(b'"');
+        if let Some(sid) = meta.style_id {
+            out.extend_from_slice(b" s=\"");
+            push_int(&mut out, sid);
+            out.push(b'"');
+        }
+
+        let start = out.len();
+        out.extend_from_slice(b"><v>");
+        match val {
+            AnyValue::Null => out.truncate(start),
+            AnyValue::Boolean(b) => {
+                out.splice(start..start, *b" t=\"b\"");
+                out.push(if b { b'1' } else { b'0' });
+            }
+            // точно, без округления до f64
+            AnyValue::Decimal(v, scale) => out.extend_from_slice(decimal_text(v, scale).as_bytes()),
+            AnyValue::Float64(x) if x.is_finite() => push_f64(&mut out, x),
+            AnyValue::Float32(x) if x.is_finite() => push_f32(&mut out, x),
+            AnyValue::Float64(_) | AnyValue::Float32(_) => out.truncate(start),
+            AnyValue::Int8(n) => push_int(&mut out, n),
+            AnyValue::Int16(n) => push_int(&mut out, n),
+            AnyValue::Int32(n) => push_int(&mut out, n),
+            AnyValue::Int64(n) => push_int(&mut out, n),
+            AnyValue::UInt8(n) => push_int(&mut out, n),
+            AnyValue::UInt16(n) => push_int(&mut out, n),
+            AnyValue::UInt32(n) => push_int(&mut out, n),
+            AnyValue::UInt64(n) => push_int(&mut out, n),
+            _ => match temporal_serial(&val) {
+                Some(x) => push_f64(&mut out, x),
+                None if meta.is_number => out.extend_from_slice(val.to_string().as_bytes()),
+                None => {
+                    out.truncate(start);
+                    out.extend_from_slice(b" t=\"inlineStr\"><is><t>");
+                    out.extend_from_slice(escape((meta.conv)(val)).as_bytes());
+                    out.extend_from_slice(b"</t></is></c>");
+                    continue;
+                }
+            },
+        }
+        if out.len() == start {
+            // пустая ячейка: только стиль
+            out.extend_from_slice(b"></c>");
+        } else {
+            out.extend_from_slice(b"</v></c>");
+        }
+    }
+    out.extend_from_slice(b"</row>");
+    keep_row_attrs(out, old)
+}
+
+/// Переносит атрибуты старого тега `<row>` (если строка была) на сгенерированную.
+#[cfg(feature = "polars")]
+fn keep_row_attrs(mut row: Vec<u8>, old: Option<&Vec<u8>>) -> Vec<u8> {
+    let Some(old) = old else {
+        return row;
+    };
+    let gt = memchr::memchr(b'>', &row).map_or(row.len(), |p| p + 1);
+    let mut tag = row[..gt].to_vec();
+    for a in ROW_ATTRS {
+        if let Some(v) = get_attr(old, a) {
+            set_attr(&mut tag, a, Some(&v));
+        }
+    }
+    row.splice(..gt, tag);
+    row
 }
@@ -0,0 +1,348 @@
+//! print_part.rs – параметры печати текущего листа: `<pageSetup>`, `<pageMargins>`,
+//! `<printOptions>`, колонтитулы, разрывы страниц и имена
+//! `_xlnm.Print_Area` / `_xlnm.Print_Titles`.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::files_part::xml_escape;
+use crate::sheet_xml::{
+    edit_empty_child, find_child, find_elem, get_attr, remove_children, root_prefix, set_attr,
+    upsert_child,
+};
+use crate::structure_part::parse_span;
+use crate::style::col_letter;
+use crate::table_part::normalize_range;
+use crate::workbook_part::{absolute_ref, quote_sheet_name};
+use anyhow::Result;
+use std::fmt;
+use std::str::FromStr;
+
+/// Ориентация страницы.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Orientation {
+    #[default]
+    Portrait,
+    Landscape,
+}
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Orientation::Portrait => "portrait",
+            Orientation::Landscape => "landscape",
+        })
+    }
+}
+impl FromStr for Orientation {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        Ok(match s {
+            "portrait" => Orientation::Portrait,
+            "landscape" => Orientation::Landscape,
+            _ => bail!("Unknown orientation: {s}"),
+        })
+    }
+}
+
+/// Поля страницы в дюймах. `Default` — «Обычные» поля Excel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageMargins {
+    pub left: f64,
+    pub right: f64,
+    pub top: f64,
+    pub bottom: f64,
+    pub header: f64,
+    pub footer: f64,
+}
+
+impl Default for PageMargins {
+    fn default() -> Self {
+        Self {
+            left: 0.7,
+            right: 0.7,
+            top: 0.75,
+            bottom: 0.75,
+            header: 0.3,
+            footer: 0.3,
+        }
+    }
+}
+
+/// Параметры печати для [`XlsxEditor::set_page_setup`].
+/// `None` — оставить как есть в файле.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageSetup {
+    pub orientation: Option<Orientation>,
+    /// Код формата бумаги (`paperSize`): 1 — Letter, 8 — A3, 9 — A4.
+    pub paper_size: Option<u32>,
+    /// Масштаб в процентах, 10..=400; отключает «вписать в страницы».
+    pub scale: Option<u32>,
+    /// Вписать в `(ширина, высота)` страниц; 0 — без ограничения по этой оси.
+    pub fit_to_pages: Option<(u32, u32)>,
+    pub margins: Option<PageMargins>,
+    pub center_horizontally: Option<bool>,
+    pub center_vertically: Option<bool>,
+    pub print_grid_lines: Option<bool>,
+    /// Печатать заголовки строк и столбцов (1, 2, … / A, B, …).
+    pub print_headings: Option<bool>,
+}
+
+/// Колонтитулы для [`XlsxEditor::set_header_footer`]. Текст — в формате Excel:
+/// секции `&L`/`&C`/`&R` и поля вроде `&P` (см. [`HeaderFooter::sections`]).
+/// `None` — колонтитула нет.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderFooter {
+    pub header: Option<String>,
+    pub footer: Option<String>,
+    /// Колонтитулы чётных страниц; задаются вместе с `different_odd_even`.
+    pub even_header: Option<String>,
+    pub even_footer: Option<String>,
+    /// Колонтитулы первой страницы; задаются вместе с `different_first`.
+    pub first_header: Option<String>,
+    pub first_footer: Option<String>,
+    pub different_odd_even: bool,
+    pub different_first: bool,
+}
+
+impl HeaderFooter {
+    /// Номер страницы.
+    pub const PAGE: &'static str = "&P";
+    /// Число страниц.
+    pub const PAGES: &'static str = "&N";
+    pub const DATE: &'static str = "&D";
+    pub const TIME: &'static str = "&T";
+    /// Имя файла.
+    pub const FILE: &'static str = "&F";
+    /// Имя листа.
+    pub const SHEET: &'static str = "&A";
+
+    /// Собирает колонтитул из левой, центральной и правой секций;
+    /// пустые секции пропускаются. Литеральный `&` в тексте пишется как `&&`.
+    pub fn sections(left: &str, center: &str, right: &str) -> String {
+        [("&L", left), ("&C", center), ("&R", right)]
+            .iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(code, text)| format!("{code}{text}"))
+            .collect()
+    }
+}
+
+impl XlsxEditor {
+    /// Меняет параметры печати текущего листа: ориентацию, бумагу, масштаб
+    /// или «вписать в страницы», поля и центрирование.
+    pub fn set_page_setup(&mut self, setup: PageSetup) -> Result<&mut Self, XlsxError> {
+        if setup.scale.is_some() && setup.fit_to_pages.is_some() {
+            bail!("scale and fit_to_pages are mutually exclusive");
+        }
+        if let Some(s) = setup.scale.filter(|s| !(10..=400).contains(s)) {
+            bail!("scale must be within 10..=400, got {s}");
+        }
+        if let Some(m) = &setup.margins {
+            let all = [m.left, m.right, m.top, m.bottom, m.header, m.footer];
+            if all.iter().any(|v| !v.is_finite() || *v < 0.0) {
+                bail!("page margins must be non-negative");
+            }
+        }
+
+        // «вписать в страницы» включается флагом в <sheetPr>
+        let fit = match (setup.scale, setup.fit_to_pages) {
+            (Some(_), _) => Some(false),
+            (_, Some(_)) => Some(true),
+            _ => None,
+        };
+        if let Some(fit) = fit {
+            let sheet = self.current_sheet_name()?;
+            self.edit_sheet_pr(&sheet, "pageSetUpPr", |tag| {
+                set_attr(tag, "fitToPage", fit.then_some("1"));
+            })?;
+        }
+
+        let xml = &mut self.sheet_xml;
+        let bit = |b: Option<bool>| b.map(|b| b.then_some("1"));
+        let opts = [
+            ("horizontalCentered", bit(setup.center_horizontally)),
+            ("verticalCentered", bit(setup.center_vertically)),
+            ("headings", bit(setup.print_headings)),
+            ("gridLines", bit(setup.print_grid_lines)),
+        ];
+        if opts.iter().any(|(_, v)| v.is_some()) {
+            edit_empty_child(xml, "printOptions", |tag| {
+                for (name, v) in opts {
+                    if let Some(v) = v {
+                        set_attr(tag, name, v);
+                    }
+                }
+            })?;
+        }
+
+        if let Some(m) = &setup.margins {
+            edit_empty_child(xml, "pageMargins", |tag| {
+                let sides = [
+                    ("left", m.left),
+                    ("right", m.right),
+                    ("top", m.top),
+                    ("bottom", m.bottom),
+                    ("header", m.header),
+                    ("footer", m.footer),
+                ];
+                for (name, v) in sides {
+                    set_attr(tag, name, Some(&v.to_string()));
+                }
+            })?;
+        }
+
+        let page = [
+            setup.paper_size.is_some(),
+            setup.scale.is_some(),
+            setup.fit_to_pages.is_some(),
+            setup.orientation.is_some(),
+        ];
+        if page.contains(&true) {
+            edit_empty_child(xml, "pageSetup", |tag| {
+                if let Some(p) = setup.paper_size {
+                    set_attr(tag, "paperSize", Some(&p.to_string()));
+                }
+                if let Some(s) = setup.scale {
+                    set_attr(tag, "scale", Some(&s.to_string()));
+                }
+                if let Some((w, h)) = setup.fit_to_pages {
+                    // по умолчанию обе величины равны 1
+                    set_attr(
+                        tag,
+                        "fitToWidth",
+                        (w != 1).then(|| w.to_string()).as_deref(),
+                    );
+                    set_attr(
+                        tag,
+                        "fitToHeight",
+                        (h != 1).then(|| h.to_string()).as_deref(),
+                    );
+                }
+                if let Some(o) = setup.orientation {
+                    set_attr(tag, "orientation", Some(&o.to_string()));
+                }
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// Задаёт область печати текущего листа (`"A1:F40"`).
+    pub fn set_print_area(&mut self, range: &str) -> Result<&mut Self, XlsxError> {
+        let range = normalize_range(range)?;
+        let value = format!(
+            "{}!{}",
+            quote_sheet_name(&self.current_sheet_name()?),
+            absolute_ref(&range)
+        );
+        let idx = self.current_sheet_index()?;
+        self.set_defined_name("_xlnm.Print_Area", Some(idx), Some(&value), false)?;
+        Ok(self)
+    }
+
+    /// Сквозные строки (`"1:2"`) и столбцы (`"A:B"`), повторяемые на каждой
+    /// печатной странице. Оба `None` убирают их.
+    pub fn set_print_titles(
+        &mut self,
+        rows: Option<&str>,
+        cols: Option<&str>,
+    ) -> Result<&mut Self, XlsxError> {
+        let sheet = quote_sheet_name(&self.current_sheet_name()?);
+        let mut parts = Vec::new();
+        // Excel пишет столбцы первыми
+        if let Some(c) = cols {
+            let (lo, hi) = parse_span(c, true)?;
+            parts.push(format!("{sheet}!${}:${}", col_letter(lo), col_letter(hi)));
+        }
+        if let Some(r) = rows {
+            let (lo, hi) = parse_span(r, false)?;
+            parts.push(format!("{sheet}!${lo}:${hi}"));
+        }
+        let value = (!parts.is_empty()).then(|| parts.join(","));
+        let idx = self.current_sheet_index()?;
+        self.set_defined_name("_xlnm.Print_Titles", Some(idx), value.as_deref(), false)?;
+        Ok(self)
+    }
+
+    /// Заменяет колонтитулы текущего листа; `HeaderFooter::default()` убирает их.
+    pub fn set_header_footer(&mut self, hf: HeaderFooter) -> Result<&mut Self, XlsxError> {
+        let xml = &mut self.sheet_xml;
+        let parts = [
+            ("oddHeader", &hf.header),
+            ("oddFooter", &hf.footer),
+            ("evenHeader", &hf.even_header),
+            ("evenFooter", &hf.even_footer),
+            ("firstHeader", &hf.first_header),
+            ("firstFooter", &hf.first_footer),
+        ];
+        if parts.iter().all(|(_, t)| t.is_none()) {
+            remove_children(xml, "headerFooter")?;
+            return Ok(self);
+        }
+        for (name, text) in parts {
+            if text.as_ref().is_some_and(|t| t.chars().count() > 255) {
+                bail!("{name} is longer than 255 characters");
+            }
+        }
+
+        let p = root_prefix(xml);
+        let mut block = format!("<{p}headerFooter");
+        if hf.different_odd_even {
+            block.push_str(r#" differentOddEven="1""#);
+        }
+        if hf.different_first {
+            block.push_str(r#" differentFirst="1""#);
+        }
+        block.push('>');
+        for (name, text) in parts {
+            if let Some(t) = text {
+                block.push_str(&format!("<{p}{name}>{}</{p}{name}>", xml_escape(t)));
+            }
+        }
+        block.push_str(&format!("</{p}headerFooter>"));
+        upsert_child(xml, "headerFooter", block.as_bytes())?;
+        Ok(self)
+    }
+
+    /// Ставит ручной разрыв страницы после строки `after_row` (1-based).
+    /// Повторный вызов для той же строки ничего не меняет.
+    pub fn insert_page_break(&mut self, after_row: u32) -> Result<&mut Self, XlsxError> {
+        if !(1..1_048_576).contains(&after_row) {
+            bail!("invalid row for a page break: {after_row}");
+        }
+        let xml = &mut self.sheet_xml;
+        let p = root_prefix(xml);
+        let q = format!("{p}brk");
+
+        // существующие разрывы: (строка, тег) — чужие атрибуты сохраняем
+        let mut breaks: Vec<(u32, Vec<u8>)> = Vec::new();
+        if let Some(r) = find_child(xml, "rowBreaks")? {
+            let mut from = r.start + 1;
+            while let Some(b) = find_elem(&xml[..r.end], &q, from) {
+                from = b.end;
+                let id = get_attr(&xml[b.clone()], "id").and_then(|v| v.parse().ok());
+                breaks.push((id.unwrap_or(0), xml[b].to_vec()));
+            }
+        }
+        if breaks.iter().any(|(id, _)| *id == after_row) {
+            return Ok(self);
+        }
+        let tag = format!(r#"<{q} id="{after_row}" max="16383" man="1"/>"#);
+        breaks.push((after_row, tag.into_bytes()));
+        breaks.sort_by_key(|(id, _)| *id);
+
+        let manual = breaks
+            .iter()
+            .filter(|(_, t)| get_attr(t, "man").as_deref() == Some("1"))
+            .count();
+        let mut block = format!(
+            r#"<{p}rowBreaks count="{}" manualBreakCount="{manual}">"#,
+            breaks.len()
+        )
+        .into_bytes();
+        for (_, t) in breaks {
+            block.extend(t);
+        }
+        block.extend(format!("</{p}rowBreaks>").bytes());
+        upsert_child(xml, "rowBreaks", &block)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,226 @@
+//! protection_part.rs – защита листов (`<sheetProtection>`) и структуры книги
+//! (`<workbookProtection>`): старый 16-битный хэш пароля и SHA-512 с солью.
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::sheet_xml::{
+    find_wb_child, insert_wb_child, remove_children, root_tag_range, tag_prefix, upsert_child,
+};
+use anyhow::{Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha2::{Digest, Sha512};
+
+/// Число итераций SHA-512, как пишет Excel.
+const SPIN_COUNT: u32 = 100_000;
+
+/// Что разрешено пользователю на защищённом листе ([`XlsxEditor::protect_sheet`]).
+/// `Default` совпадает с Excel: можно только выделять ячейки.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SheetPermissions {
+    pub select_locked_cells: bool,
+    pub select_unlocked_cells: bool,
+    pub format_cells: bool,
+    pub format_columns: bool,
+    pub format_rows: bool,
+    pub insert_columns: bool,
+    pub insert_rows: bool,
+    pub insert_hyperlinks: bool,
+    pub delete_columns: bool,
+    pub delete_rows: bool,
+    pub sort: bool,
+    pub auto_filter: bool,
+    pub pivot_tables: bool,
+    /// Правка фигур, диаграмм и примечаний.
+    pub objects: bool,
+    pub scenarios: bool,
+}
+
+impl Default for SheetPermissions {
+    fn default() -> Self {
+        Self {
+            select_locked_cells: true,
+            select_unlocked_cells: true,
+            format_cells: false,
+            format_columns: false,
+            format_rows: false,
+            insert_columns: false,
+            insert_rows: false,
+            insert_hyperlinks: false,
+            delete_columns: false,
+            delete_rows: false,
+            sort: false,
+            auto_filter: false,
+            pivot_tables: false,
+            objects: false,
+            scenarios: false,
+        }
+    }
+}
+
+impl SheetPermissions {
+    /// Атрибуты `<sheetProtection>`: `(имя, разрешено, запрещено ли по умолчанию)`.
+    /// В XML `1` означает «действие запрещено».
+    fn attrs(&self) -> [(&'static str, bool, bool); 15] {
+        [
+            ("objects", self.objects, false),
+            ("scenarios", self.scenarios, false),
+            ("formatCells", self.format_cells, true),
+            ("formatColumns", self.format_columns, true),
+            ("formatRows", self.format_rows, true),
+            ("insertColumns", self.insert_columns, true),
+            ("insertRows", self.insert_rows, true),
+            ("insertHyperlinks", self.insert_hyperlinks, true),
+            ("deleteColumns", self.delete_columns, true),
+            ("deleteRows", self.delete_rows, true),
+            ("selectLockedCells", self.select_locked_cells, false),
+            ("sort", self.sort, true),
+            ("autoFilter", self.auto_filter, true),
+            ("pivotTables", self.pivot_tables, true),
+            ("selectUnlockedCells", self.select_unlocked_cells, false),
+        ]
+    }
+}
+
+/// Хэши пароля защиты (base64 для SHA-512, hex для старого).
+pub(crate) struct PasswordHash {
+    pub legacy: String,
+    pub salt: String,
+    pub hash: String,
+}
+
+impl PasswordHash {
+    fn new(password: &str) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        getrandom::fill(&mut salt).map_err(|e| anyhow!("cannot generate salt: {e}"))?;
+        Ok(Self::with_salt(password, &salt))
+    }
+
+    /// SHA-512 по ECMA-376: `H0 = sha(salt + пароль в UTF-16LE)`,
+    /// `Hn = sha(Hn-1 + n как u32 LE)`.
+    pub(crate) fn with_salt(password: &str, salt: &[u8]) -> Self {
+        let pw: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let mut h = Sha512::new()
+            .chain_update(salt)
+            .chain_update(&pw)
+            .finalize();
+        for i in 0..SPIN_COUNT {
+            h = Sha512::new()
+                .chain_update(h)
+                .chain_update(i.to_le_bytes())
+                .finalize();
+        }
+        Self {
+            legacy: legacy_hash(password),
+            salt: STANDARD.encode(salt),
+            hash: STANDARD.encode(h),
+        }
+    }
+
+    /// Атрибуты хэша; `prefix` — `""` для листа, `"workbook"` для книги.
+    fn attrs(&self, prefix: &str) -> String {
+        let name = |n: &str| match prefix {
+            "" => n.to_owned(),
+            p => format!("{p}{}{}", n[..1].to_ascii_uppercase(), &n[1..]),
+        };
+        format!(
+            r#" {}="SHA-512" {}="{}" {}="{}" {}="{SPIN_COUNT}" {}="{}""#,
+            name("algorithmName"),
+            name("hashValue"),
+            self.hash,
+            name("saltValue"),
+            self.salt,
+            name("spinCount"),
+            name("password"),
+            self.legacy,
+        )
+    }
+}
+
+/// Старый хэш пароля (атрибут `password`) — 16 бит, для совместимости.
+fn legacy_hash(password: &str) -> String {
+    let chars: Vec<u16> = password.encode_utf16().collect();
+    let rotate = |h: u16| ((h >> 14) & 1) | ((h << 1) & 0x7fff);
+    let mut h = 0u16;
+    for &c in chars.iter().rev() {
+        h = rotate(h) ^ c;
+    }
+    h = rotate(h) ^ chars.len() as u16 ^ 0xCE4B;
+    format!("{h:04X}")
+}
+
+impl XlsxEditor {
+    /// Защищает лист `sheet`. `password` — `None` для защиты без пароля;
+    /// пишутся и SHA-512-хэш, и старый 16-битный.
+    pub fn protect_sheet(
+        &mut self,
+        sheet: &str,
+        password: Option<&str>,
+        permissions: SheetPermissions,
+    ) -> Result<&mut Self, XlsxError> {
+        let hash = password.map(PasswordHash::new).transpose()?;
+        let path = self.sheet_path_of(sheet)?;
+        let mut xml = self.sheet_part(&path)?;
+        let p = root_tag_range(&xml)
+            .map(|r| tag_prefix(&xml[r]))
+            .unwrap_or_default();
+
+        let mut tag = format!("<{p}sheetProtection");
+        if let Some(h) = &hash {
+            tag.push_str(&h.attrs(""));
+        }
+        tag.push_str(r#" sheet="1""#);
+        for (name, allowed, locked_by_default) in permissions.attrs() {
+            let locked = !allowed;
+            if locked != locked_by_default {
+                tag.push_str(&format!(r#" {name}="{}""#, u8::from(locked)));
+            }
+        }
+        tag.push_str("/>");
+        upsert_child(&mut xml, "sheetProtection", tag.as_bytes())?;
+        self.store_sheet_part(&path, xml);
+        Ok(self)
+    }
+
+    /// Снимает защиту с листа `sheet` (пароль не нужен).
+    pub fn unprotect_sheet(&mut self, sheet: &str) -> Result<&mut Self, XlsxError> {
+        let path = self.sheet_path_of(sheet)?;
+        let mut xml = self.sheet_part(&path)?;
+        if remove_children(&mut xml, "sheetProtection")? {
+            self.store_sheet_part(&path, xml);
+        }
+        Ok(self)
+    }
+
+    /// Защищает структуру книги: листы нельзя добавлять, удалять,
+    /// переименовывать, перемещать и показывать.
+    pub fn protect_workbook(&mut self, password: Option<&str>) -> Result<&mut Self, XlsxError> {
+        let hash = password.map(PasswordHash::new).transpose()?;
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let p = root_tag_range(wb)
+            .map(|r| tag_prefix(&wb[r]))
+            .unwrap_or_default();
+        let mut tag = format!("<{p}workbookProtection");
+        if let Some(h) = &hash {
+            tag.push_str(&h.attrs("workbook"));
+        }
+        tag.push_str(r#" lockStructure="1"/>"#);
+        match find_wb_child(wb, "workbookProtection")? {
+            Some(r) => {
+                wb.splice(r, tag.into_bytes());
+            }
+            None => {
+                insert_wb_child(wb, "workbookProtection", tag.as_bytes())?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Снимает защиту структуры книги.
+    pub fn unprotect_workbook(&mut self) -> Result<&mut Self, XlsxError> {
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        if let Some(r) = find_wb_child(wb, "workbookProtection")? {
+            wb.splice(r, std::iter::empty());
+        }
+        Ok(self)
+    }
+}
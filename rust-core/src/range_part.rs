@@ -0,0 +1,516 @@
+//! range_part.rs – операции над прямоугольными диапазонами ячеек:
+//! копирование и перенос со стилями и формулами, очистка.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::files_part::calc_last_row;
+use crate::formula::adjust_formula;
+use crate::sheet_xml::{
+    extend_dimension, find_child, find_elem, get_attr, open_sheet_data, set_attr, tag_prefix,
+};
+use crate::style::{col_letter, parse_cell};
+use crate::table_part::{Rect, format_range, parse_range, rects_overlap};
+use anyhow::{Context, Result};
+use quick_xml::{Reader, escape, events::Event, name::QName};
+use std::collections::{BTreeMap, HashMap};
+use std::{fmt, str::FromStr};
+
+const MAX_ROW: u32 = 1_048_576;
+const MAX_COL: u32 = 16_384;
+
+/// Что стирает [`XlsxEditor::clear_range`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClearMode {
+    /// Значения и формулы; стиль ячеек остаётся.
+    Contents,
+    /// Стили ячеек и объединения; значения остаются.
+    Formats,
+    /// Всё: ячейки удаляются целиком.
+    All,
+}
+impl fmt::Display for ClearMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ClearMode::Contents => "contents",
+            ClearMode::Formats => "formats",
+            ClearMode::All => "all",
+        })
+    }
+}
+impl FromStr for ClearMode {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        Ok(match s {
+            "contents" => ClearMode::Contents,
+            "formats" => ClearMode::Formats,
+            "all" => ClearMode::All,
+            _ => bail!("Unknown clear mode: {s}"),
+        })
+    }
+}
+
+/// Ячейка `<c>` из `<sheetData>` в исходном виде.
+pub(crate) struct XmlCell {
+    pub col: u32,
+    pub row: u32,
+    pub xml: Vec<u8>,
+}
+
+/// Общие формулы листа: `si → (формула, колонка, строка)` ведущей ячейки.
+type SharedFormulas = HashMap<String, (String, u32, u32)>;
+
+/// Новые ячейки по строкам и колонкам: `row → col → <c …>`.
+pub(crate) type CellMap = BTreeMap<u32, BTreeMap<u32, Vec<u8>>>;
+
+impl XlsxEditor {
+    /// Копирует ячейки `src` (значения, стили, формулы) так, чтобы левый верхний
+    /// угол оказался в `dst` (`"F1"`). При `adjust_formulas` относительные ссылки
+    /// в формулах смещаются, как при копировании в Excel, иначе формулы
+    /// переносятся дословно. Область назначения предварительно очищается;
+    /// объединения, целиком лежащие в `src`, копируются вместе с ячейками.
+    pub fn copy_range(
+        &mut self,
+        src: &str,
+        dst: &str,
+        adjust_formulas: bool,
+    ) -> Result<&mut Self, XlsxError> {
+        Ok(self.copy_cells(src, dst, adjust_formulas, false)?)
+    }
+
+    /// Как [`Self::copy_range`], но ячейки `src` вне области назначения очищаются,
+    /// а объединения переносятся, а не дублируются.
+    pub fn move_range(
+        &mut self,
+        src: &str,
+        dst: &str,
+        adjust_formulas: bool,
+    ) -> Result<&mut Self, XlsxError> {
+        Ok(self.copy_cells(src, dst, adjust_formulas, true)?)
+    }
+
+    /// Очищает `range` (`"A2:F100"`, можно несколько через пробел) согласно `mode`.
+    /// Опустевшие строки без собственного оформления удаляются, так что сброс
+    /// шаблона перед новой дозаписью не оставляет в файле пустых `<row>`.
+    pub fn clear_range(&mut self, range: &str, mode: ClearMode) -> Result<&mut Self, XlsxError> {
+        let rects = range
+            .split_whitespace()
+            .map(parse_range)
+            .collect::<Result<Vec<_>>>()?;
+        if rects.is_empty() {
+            bail!("empty range");
+        }
+        if let Some(sd) = find_child(&self.sheet_xml, "sheetData")? {
+            let data = patch_sheet_data(&self.sheet_xml[sd.clone()], &rects, mode, CellMap::new())?;
+            self.sheet_xml.splice(sd, data);
+        }
+        if mode != ClearMode::Contents {
+            for r in &rects {
+                self.unmerge_cells(&format_range(*r))?;
+            }
+        }
+        self.last_row = calc_last_row(&self.sheet_path, &self.sheet_xml)?;
+        Ok(self)
+    }
+
+    /// Ставит готовые ячейки `cells` в прямоугольник `rect`, прежние ячейки
+    /// которого стираются. Блок целиком ниже данных листа дописывается в конец
+    /// `<sheetData>` без перестройки существующих строк.
+    pub(crate) fn put_block(&mut self, rect: Rect, cells: CellMap) -> Result<()> {
+        let bottom = cells.keys().next_back().copied().unwrap_or(0);
+        let sd = open_sheet_data(&mut self.sheet_xml)?;
+        if rect.1 > self.last_row {
+            let mut xml = Vec::new();
+            for (r, row) in cells {
+                xml.extend_from_slice(format!(r#"<row r="{r}">"#).as_bytes());
+                row.into_values().for_each(|c| xml.extend(c));
+                xml.extend_from_slice(b"</row>");
+            }
+            self.sheet_xml.splice(sd.end..sd.end, xml);
+        } else {
+            let sd =
+                find_child(&self.sheet_xml, "sheetData")?.context("no <sheetData> in sheet")?;
+            let data =
+                patch_sheet_data(&self.sheet_xml[sd.clone()], &[rect], ClearMode::All, cells)?;
+            self.sheet_xml.splice(sd, data);
+        }
+        self.last_row = self.last_row.max(bottom);
+        extend_dimension(&mut self.sheet_xml, rect)?;
+        Ok(())
+    }
+
+    fn copy_cells(
+        &mut self,
+        src: &str,
+        dst: &str,
+        adjust: bool,
+        move_cells: bool,
+    ) -> Result<&mut Self> {
+        let from = parse_range(src)?;
+        let (c0, r0, c1, r1) = from;
+        let top_left = dst.split(':').next().unwrap_or(dst).replace('$', "");
+        let (dc0, dr0) = parse_cell(&top_left)?;
+        let to = (dc0, dr0, dc0 + (c1 - c0), dr0 + (r1 - r0));
+        if to.2 >= MAX_COL || to.3 > MAX_ROW {
+            bail!(
+                "{dst} is too close to the sheet edge for a {}x{} block",
+                c1 - c0 + 1,
+                r1 - r0 + 1
+            );
+        }
+        let (dc, dr) = (dc0 as i64 - c0 as i64, dr0 as i64 - r0 as i64);
+
+        let sd = find_child(&self.sheet_xml, "sheetData")?.context("<sheetData> not found")?;
+        let (cells, shared) = collect_cells(&self.sheet_xml[sd.clone()], from)?;
+        let mut new_cells = CellMap::new();
+        for cell in &cells {
+            let xml = relocate_cell(cell, dc, dr, adjust, &shared)?;
+            new_cells
+                .entry((cell.row as i64 + dr) as u32)
+                .or_default()
+                .insert((cell.col as i64 + dc) as u32, xml);
+        }
+        let clear = if move_cells { vec![from, to] } else { vec![to] };
+        let data = patch_sheet_data(
+            &self.sheet_xml[sd.clone()],
+            &clear,
+            ClearMode::All,
+            new_cells,
+        )?;
+        self.sheet_xml.splice(sd, data);
+
+        // объединения внутри исходного блока едут вместе с ним
+        let mut merges = Vec::new();
+        for m in self.get_merged_ranges()? {
+            let m = parse_range(&m)?;
+            if m.0 >= c0 && m.2 <= c1 && m.1 >= r0 && m.3 <= r1 {
+                merges.push(m);
+            }
+        }
+        if move_cells {
+            for m in &merges {
+                self.unmerge_cells(&format_range(*m))?;
+            }
+        }
+        self.unmerge_cells(&format_range(to))?;
+        for (mc0, mr0, mc1, mr1) in merges {
+            let m = (
+                (mc0 as i64 + dc) as u32,
+                (mr0 as i64 + dr) as u32,
+                (mc1 as i64 + dc) as u32,
+                (mr1 as i64 + dr) as u32,
+            );
+            self.merge_cells(&format_range(m))?;
+        }
+
+        extend_dimension(&mut self.sheet_xml, to)?;
+        self.last_row = calc_last_row(&self.sheet_path, &self.sheet_xml)?;
+        Ok(self)
+    }
+}
+
+/// Ячейки `<sheetData>` внутри `rect` и все общие формулы листа.
+fn collect_cells(xml: &[u8], rect: Rect) -> Result<(Vec<XmlCell>, SharedFormulas)> {
+    let mut cells = Vec::new();
+    let mut shared = HashMap::new();
+    let mut rdr = Reader::from_reader(xml);
+    rdr.config_mut().check_end_names = false;
+    loop {
+        let before = rdr.buffer_position() as usize;
+        match rdr.read_event()? {
+            ref ev @ (Event::Start(ref e) | Event::Empty(ref e))
+                if e.local_name().as_ref() == b"c" =>
+            {
+                let tag_end = rdr.buffer_position() as usize;
+                let end = if matches!(ev, Event::Empty(_)) {
+                    tag_end
+                } else {
+                    let name = e.name().as_ref().to_vec();
+                    rdr.read_to_end(QName(&name))?;
+                    rdr.buffer_position() as usize
+                };
+                let raw = &xml[before..end];
+                let Some((col, row)) =
+                    get_attr(&xml[before..tag_end], "r").and_then(|r| parse_cell(&r).ok())
+                else {
+                    continue;
+                };
+                if let Some((f_tag, Some(text))) = formula_of(raw)
+                    && get_attr(&f_tag, "t").as_deref() == Some("shared")
+                    && !text.is_empty()
+                    && let Some(si) = get_attr(&f_tag, "si")
+                {
+                    shared.insert(si, (text, col, row));
+                }
+                if (rect.0..=rect.2).contains(&col) && (rect.1..=rect.3).contains(&row) {
+                    cells.push(XmlCell {
+                        col,
+                        row,
+                        xml: raw.to_vec(),
+                    });
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((cells, shared))
+}
+
+/// Открывающий тег `<f>` ячейки и её формула (`None` у `<f …/>`).
+fn formula_of(cell: &[u8]) -> Option<(Vec<u8>, Option<String>)> {
+    let p = tag_prefix(cell);
+    let qname = format!("{p}f");
+    let r = find_elem(cell, &qname, 1)?;
+    let gt = r.start + cell[r.clone()].iter().position(|&b| b == b'>')?;
+    let tag = cell[r.start..=gt].to_vec();
+    if cell[gt - 1] == b'/' {
+        return Some((tag, None));
+    }
+    let body = &cell[gt + 1..r.end - qname.len() - 3];
+    let text = escape::unescape(&String::from_utf8_lossy(body))
+        .ok()?
+        .into_owned();
+    Some((tag, Some(text)))
+}
+
+/// Ячейка `cell`, перенесённая на `dc`/`dr`: новый `r`, формула (общие раскрываются
+/// в обычные, при `adjust` ссылки смещаются).
+fn relocate_cell(
+    cell: &XmlCell,
+    dc: i64,
+    dr: i64,
+    adjust: bool,
+    shared: &SharedFormulas,
+) -> Result<Vec<u8>> {
+    let (col, row) = ((cell.col as i64 + dc) as u32, (cell.row as i64 + dr) as u32);
+    let mut xml = cell.xml.clone();
+    let tag_end = xml.iter().position(|&b| b == b'>').context("broken <c>")?;
+    let mut tag = xml[..=tag_end].to_vec();
+    set_attr(&mut tag, "r", Some(&format!("{}{row}", col_letter(col))));
+    xml.splice(..=tag_end, tag);
+
+    let p = tag_prefix(&xml);
+    let Some((f_tag, text)) = formula_of(&xml) else {
+        return Ok(xml);
+    };
+    let typ = get_attr(&f_tag, "t");
+    let formula = match (typ.as_deref(), text.filter(|t| !t.is_empty())) {
+        (_, Some(t)) => Some(t),
+        // ведомая ячейка общей формулы: берём формулу ведущей со смещением
+        (Some("shared"), None) => {
+            get_attr(&f_tag, "si")
+                .and_then(|si| shared.get(&si))
+                .map(|(f, mc, mr)| {
+                    adjust_formula(
+                        f,
+                        cell.row as i64 - *mr as i64,
+                        cell.col as i64 - *mc as i64,
+                    )
+                })
+        }
+        _ => None,
+    };
+    let formula = formula.map(|f| {
+        if adjust {
+            adjust_formula(&f, dr, dc)
+        } else {
+            f
+        }
+    });
+
+    let new_f = match (typ.as_deref(), formula) {
+        (Some("array"), Some(f)) => {
+            let rect = get_attr(&f_tag, "ref")
+                .and_then(|r| parse_range(&r).ok())
+                .unwrap_or((cell.col, cell.row, cell.col, cell.row));
+            let moved = (
+                (rect.0 as i64 + dc) as u32,
+                (rect.1 as i64 + dr) as u32,
+                (rect.2 as i64 + dc) as u32,
+                (rect.3 as i64 + dr) as u32,
+            );
+            format!(
+                r#"<{p}f t="array" ref="{}">{}</{p}f>"#,
+                format_range(moved),
+                escape::partial_escape(&f)
+            )
+        }
+        (_, Some(f)) => format!("<{p}f>{}</{p}f>", escape::partial_escape(&f)),
+        // формулу восстановить не из чего — остаётся только значение
+        (_, None) => String::new(),
+    };
+    let f_range = find_elem(&xml, &format!("{p}f"), 1).context("<f> vanished")?;
+    xml.splice(f_range, new_f.into_bytes());
+    Ok(xml)
+}
+
+/// Очищает ячейку согласно `mode`; `None` — ячейка больше не нужна.
+fn clear_cell(cell: &[u8], mode: ClearMode) -> Option<Vec<u8>> {
+    let tag_end = cell.iter().position(|&b| b == b'>')?;
+    let mut tag = cell[..=tag_end].to_vec();
+    match mode {
+        ClearMode::All => None,
+        ClearMode::Contents => {
+            // остаётся пустая ячейка со стилем
+            get_attr(&tag, "s").filter(|s| s != "0")?;
+            for attr in ["t", "cm", "vm"] {
+                set_attr(&mut tag, attr, None);
+            }
+            if tag[tag.len() - 2] != b'/' {
+                tag.insert(tag.len() - 1, b'/');
+            }
+            Some(tag)
+        }
+        ClearMode::Formats => {
+            if tag[tag.len() - 2] == b'/' {
+                return None;
+            }
+            set_attr(&mut tag, "s", None);
+            let mut out = tag;
+            out.extend_from_slice(&cell[tag_end + 1..]);
+            Some(out)
+        }
+    }
+}
+
+/// Переписывает `<sheetData>` за один проход: ячейки внутри `clear` очищаются
+/// согласно `mode`, затем `cells` встают на свои места (заменяя существующие).
+/// Недостающие строки создаются, строки без ячеек и атрибутов выбрасываются,
+/// нетронутые строки копируются байт в байт.
+pub(crate) fn patch_sheet_data(
+    xml: &[u8],
+    clear: &[Rect],
+    mode: ClearMode,
+    cells: CellMap,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(xml.len() + cells.len() * 64);
+    let p = tag_prefix(xml);
+    let mut pending = cells.into_iter().peekable();
+    let affected = |r: u32| clear.iter().any(|c| (c.1..=c.3).contains(&r));
+    let cleared = |col: u32, r: u32| clear.iter().any(|c| rects_overlap(*c, (col, r, col, r)));
+    let new_row = |out: &mut Vec<u8>, r: u32, cells: BTreeMap<u32, Vec<u8>>| {
+        out.extend_from_slice(format!(r#"<{p}row r="{r}">"#).as_bytes());
+        for c in cells.into_values() {
+            out.extend_from_slice(&c);
+        }
+        out.extend_from_slice(format!("</{p}row>").as_bytes());
+    };
+
+    let mut rdr = Reader::from_reader(xml);
+    rdr.config_mut().check_end_names = false;
+    loop {
+        let before = rdr.buffer_position() as usize;
+        let ev = rdr.read_event()?;
+        let after = rdr.buffer_position() as usize;
+        match ev {
+            Event::Empty(ref e) if e.local_name().as_ref() == b"sheetData" => {
+                // <sheetData/> → <sheetData>…</sheetData>
+                let mut tag = xml[before..after - 2].to_vec();
+                tag.push(b'>');
+                out.extend_from_slice(&tag);
+                for (r, cells) in pending.by_ref() {
+                    new_row(&mut out, r, cells);
+                }
+                out.extend_from_slice(format!("</{p}sheetData>").as_bytes());
+            }
+            Event::End(ref e) if e.local_name().as_ref() == b"sheetData" => {
+                for (r, cells) in pending.by_ref() {
+                    new_row(&mut out, r, cells);
+                }
+                out.extend_from_slice(&xml[before..after]);
+            }
+            Event::Start(ref e) | Event::Empty(ref e) if e.local_name().as_ref() == b"row" => {
+                let empty = matches!(ev, Event::Empty(_));
+                let end = if empty {
+                    after
+                } else {
+                    let name = e.name().as_ref().to_vec();
+                    rdr.read_to_end(QName(&name))?;
+                    rdr.buffer_position() as usize
+                };
+                let raw = &xml[before..end];
+                let Some(r) =
+                    get_attr(&xml[before..after], "r").and_then(|r| r.parse::<u32>().ok())
+                else {
+                    out.extend_from_slice(raw);
+                    continue;
+                };
+                while let Some((pr, cells)) = pending.next_if(|(pr, _)| *pr < r) {
+                    new_row(&mut out, pr, cells);
+                }
+                let mut add = pending
+                    .next_if(|(pr, _)| *pr == r)
+                    .map(|x| x.1)
+                    .unwrap_or_default();
+                if add.is_empty() && !affected(r) {
+                    out.extend_from_slice(raw);
+                    continue;
+                }
+
+                let mut tag = xml[before..after].to_vec();
+                if empty {
+                    tag.truncate(tag.len() - 2);
+                    tag.push(b'>');
+                }
+                set_attr(&mut tag, "spans", None);
+                let rp = tag_prefix(&tag);
+                let mut row_cells: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+                // комментарии и PI между ячейками остаются перед той же ячейкой
+                let mut notes: Vec<(u32, &[u8])> = Vec::new();
+                let qname = format!("{rp}c");
+                let mut from = after - before;
+                while let Some(cr) = find_elem(raw, &qname, from) {
+                    let gap = &raw[from..cr.start];
+                    from = cr.end;
+                    let Some((col, _)) =
+                        get_attr(&raw[cr.clone()], "r").and_then(|v| parse_cell(&v).ok())
+                    else {
+                        continue;
+                    };
+                    if !gap.trim_ascii().is_empty() {
+                        notes.push((col, gap));
+                    }
+                    if add.contains_key(&col) {
+                        continue;
+                    }
+                    let cell = &raw[cr];
+                    if !cleared(col, r) {
+                        row_cells.insert(col, cell.to_vec());
+                    } else if let Some(c) = clear_cell(cell, mode) {
+                        row_cells.insert(col, c);
+                    }
+                }
+                row_cells.append(&mut add);
+                let body_end = match empty {
+                    true => raw.len(),
+                    false => memchr::memrchr(b'<', raw).unwrap_or(raw.len()),
+                };
+                let tail = raw.get(from..body_end).unwrap_or_default();
+                let tail = if tail.trim_ascii().is_empty() { &[][..] } else { tail };
+
+                let mut attrs = tag.clone();
+                set_attr(&mut attrs, "r", None);
+                let bare = notes.is_empty() && tail.is_empty() && !attrs.contains(&b'=');
+                if row_cells.is_empty() && bare {
+                    continue;
+                }
+                out.extend_from_slice(&tag);
+                let mut notes = notes.into_iter().peekable();
+                for (col, c) in row_cells {
+                    while let Some((_, note)) = notes.next_if(|(nc, _)| *nc <= col) {
+                        out.extend_from_slice(note);
+                    }
+                    out.extend_from_slice(&c);
+                }
+                for (_, note) in notes {
+                    out.extend_from_slice(note);
+                }
+                out.extend_from_slice(tail);
+                out.extend_from_slice(format!("</{rp}row>").as_bytes());
+            }
+            Event::Eof => break,
+            _ => out.extend_from_slice(&xml[before..after]),
+        }
+    }
+    Ok(out)
+}
@@ -1,120 +1,532 @@
 use crate::XlsxEditor;
-use anyhow::{Result, bail};
-use quick_xml::{Reader, events::Event};
+use crate::error::{XlsxError, bail, next_event};
+use crate::sheet_xml::{find_child, find_elem, get_attr, tag_prefix};
+use crate::style::{col_letter, parse_cell};
+use crate::table_part::parse_range;
+use anyhow::Result;
+use quick_xml::{
+    Reader,
+    events::{BytesRef, Event},
+};
+use std::collections::{BTreeMap, HashMap};
 
-impl XlsxEditor {
-    /// Returns the last non-empty row index for the specified column or columns.
-    ///
-    /// The `columns` argument can be a single column such as "B" or multiple comma–separated
-    /// columns such as "B,D". The function scans the sheet for the highest populated row
-    /// across all specified columns and returns that 1-based row index. If no data is found
-    /// in those columns, `Ok(0)` is returned.
-    pub fn get_last_row_index(&self, columns: &str) -> Result<u32> {
-        // Local helper to split coordinate like "C12" -> ("C", 12)
-        fn split_coord(coord: &str) -> (String, u32) {
-            let pos = coord
-                .find(|c: char| c.is_ascii_digit())
-                .unwrap_or(coord.len());
-            let col = coord[..pos].to_ascii_uppercase();
-            let row: u32 = coord[pos..].parse().unwrap_or(0);
-            (col, row)
-        }
-
-        let targets: std::collections::HashSet<String> = columns
-            .split(',')
-            .map(|s| s.trim().to_ascii_uppercase())
-            .collect();
-        if targets.is_empty() {
-            bail!("no columns supplied")
-        }
-
-        let mut reader = Reader::from_reader(self.sheet_xml.as_slice());
-        reader.config_mut().trim_text(true);
-        let mut last_row: u32 = 0;
-
-        while let Ok(ev) = reader.read_event() {
-            match ev {
-                Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"c" => {
-                    // locate the coordinate attribute r="A1"
-                    if let Some(coord) = e.attributes().with_checks(false).flatten().find_map(|a| {
-                        (a.key.as_ref() == b"r")
-                            .then(|| String::from_utf8_lossy(&a.value).into_owned())
-                    }) {
-                        let (col, row) = split_coord(&coord);
-                        if targets.contains(&col) {
-                            if row > last_row {
-                                last_row = row;
-                            }
+/// Сырое содержимое `<c>` как оно лежит в XML листа (без интерпретации стиля).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RawCell {
+    pub col: u32, // 0-based
+    pub row: u32, // 1-based
+    pub t: Option<String>,
+    pub s: Option<u32>,
+    pub v: Option<String>,
+    pub f: Option<String>,
+    pub text: Option<String>, // <is><t>..</t></is>, runs склеены
+}
+
+impl RawCell {
+    /// Текст ячейки «как видит пользователь» (без применения numFmt).
+    pub fn display_text(&self, sst: &[String]) -> Option<String> {
+        match self.t.as_deref() {
+            Some("inlineStr") => self.text.clone(),
+            Some("s") => {
+                let idx: usize = self.v.as_deref()?.trim().parse().ok()?;
+                sst.get(idx).cloned()
+            }
+            Some("b") => Some(if self.v.as_deref() == Some("1") {
+                "TRUE".to_owned()
+            } else {
+                "FALSE".to_owned()
+            }),
+            _ => self.v.clone(),
+        }
+    }
+}
+
+/// Дописывает в `buf` символ для `&amp;`/`&#123;` и т.п.
+pub(crate) fn push_entity(buf: &mut String, r: &BytesRef) -> Result<()> {
+    if let Some(ch) = r.resolve_char_ref()? {
+        buf.push(ch);
+    } else {
+        let name = r.decode()?;
+        match quick_xml::escape::resolve_predefined_entity(&name) {
+            Some(s) => buf.push_str(s),
+            None => {
+                buf.push('&');
+                buf.push_str(&name);
+                buf.push(';');
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `b"AB12"` → (27, 12); колонка 0-based, строка 1-based.
+pub(crate) fn split_ref(r: &[u8]) -> (u32, u32) {
+    let mut col = 0u32;
+    let mut row = 0u32;
+    for &b in r {
+        if b.is_ascii_alphabetic() {
+            col = col * 26 + (b.to_ascii_uppercase() - b'A') as u32 + 1;
+        } else if b.is_ascii_digit() {
+            row = row * 10 + (b - b'0') as u32;
+        }
+    }
+    (col.saturating_sub(1), row)
+}
+
+/// Значение ячейки при чтении ([`XlsxEditor::get_cell`], [`XlsxEditor::get_range`]).
+/// У формул — кэшированный результат.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadValue {
+    Empty,
+    Number(f64),
+    /// Серийный номер даты Excel (число в формате даты).
+    Date(f64),
+    Bool(bool),
+    /// Текст, в том числе коды ошибок (`#DIV/0!`).
+    Text(String),
+}
+
+impl ReadValue {
+    /// Текст значения; целые числа — без `.0`.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            ReadValue::Empty => None,
+            ReadValue::Number(x) | ReadValue::Date(x) => {
+                Some(if x.fract() == 0.0 && x.abs() < 1e15 {
+                    (*x as i64).to_string()
+                } else {
+                    x.to_string()
+                })
+            }
+            ReadValue::Bool(b) => Some(if *b { "TRUE" } else { "FALSE" }.to_owned()),
+            ReadValue::Text(t) => Some(t.clone()),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ReadValue::Number(x) | ReadValue::Date(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+/// Значение ячейки; `dates` — см. [`date_styles`].
+pub(crate) fn read_value(c: &RawCell, sst: &[String], dates: &[bool]) -> ReadValue {
+    match c.t.as_deref() {
+        None | Some("n") => match c.v.as_deref().and_then(|v| v.trim().parse::<f64>().ok()) {
+            Some(x) if c.s.is_some_and(|s| dates.get(s as usize) == Some(&true)) => {
+                ReadValue::Date(x)
+            }
+            Some(x) => ReadValue::Number(x),
+            // формула без кэшированного значения
+            None => ReadValue::Empty,
+        },
+        Some("b") => ReadValue::Bool(c.v.as_deref().map(str::trim) == Some("1")),
+        _ => c.display_text(sst).map_or(ReadValue::Empty, ReadValue::Text),
+    }
+}
+
+/// Для каждого `<xf>` из `<cellXfs>`: задаёт ли его numFmt дату/время.
+pub(crate) fn date_styles(styles: &[u8]) -> Result<Vec<bool>> {
+    let mut rdr = Reader::from_reader(styles);
+    let mut codes = HashMap::new();
+    let mut fmt_ids = Vec::new();
+    let mut in_xfs = false;
+    loop {
+        match next_event(&mut rdr, "xl/styles.xml")? {
+            Event::Start(ref e) if e.local_name().as_ref() == b"cellXfs" => in_xfs = true,
+            Event::Start(ref e) | Event::Empty(ref e) => match e.local_name().as_ref() {
+                b"numFmt" => {
+                    let (mut id, mut code) = (None, String::new());
+                    for a in e.attributes().with_checks(false).flatten() {
+                        match a.key.as_ref() {
+                            b"numFmtId" => id = lexical_core::parse::<u32>(&a.value).ok(),
+                            b"formatCode" => code = a.unescape_value()?.into_owned(),
+                            _ => {}
                         }
                     }
+                    codes.extend(id.map(|id| (id, code)));
                 }
-                Event::Eof => break,
+                b"xf" if in_xfs => fmt_ids.push(
+                    e.attributes()
+                        .with_checks(false)
+                        .flatten()
+                        .find(|a| a.key.as_ref() == b"numFmtId")
+                        .and_then(|a| lexical_core::parse(&a.value).ok())
+                        .unwrap_or(0),
+                ),
                 _ => {}
+            },
+            Event::End(ref e) if e.local_name().as_ref() == b"cellXfs" => in_xfs = false,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(fmt_ids
+        .into_iter()
+        .map(|id| match codes.get(&id) {
+            Some(code) => is_date_code(code),
+            None => matches!(id, 14..=22 | 27..=36 | 45..=47 | 50..=58),
+        })
+        .collect())
+}
+
+/// Эвристика: в коде формата (вне кавычек, `[...]`, `\x`, `_x`, `*x`) есть
+/// d, m, y, h или s. `General` и числовые форматы этих букв не содержат.
+fn is_date_code(code: &str) -> bool {
+    // секция для положительных чисел
+    let section = code.split(';').next().unwrap_or_default();
+    let mut chars = section.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                chars.by_ref().find(|&c| c == '"');
             }
+            '[' => {
+                chars.by_ref().find(|&c| c == ']');
+            }
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            _ if "dmyhsDMYHS".contains(ch) => return true,
+            _ => {}
         }
-        Ok(last_row)
     }
+    false
+}
 
-    /// Returns a vector with the last non-empty row indices for every column in the inclusive
-    /// range like "A:E". The resulting vector has the same length as the number of columns in
-    /// the range and is ordered left-to-right.
-    ///
-    /// Example: `get_last_roww_index("A:C")` might return `[10, 12, 7]`.
-    pub fn get_last_roww_index(&self, range: &str) -> Result<Vec<u32>> {
-        let parts: Vec<&str> = range.split(':').collect();
-        if parts.len() != 2 {
-            bail!("range must be in the form A:E")
+/// Полный проход по `<sheetData>`: возвращает все `<c>` в порядке документа.
+pub(crate) fn read_cells(sheet_xml: &[u8]) -> Result<Vec<RawCell>> {
+    let mut rdr = Reader::from_reader(sheet_xml);
+    let mut cells = Vec::new();
+
+    let mut cur: Option<RawCell> = None;
+    // в какой текстовый узел сейчас пишем: b'v', b'f' или b't' (внутри <is>)
+    let mut text_target: Option<u8> = None;
+    let mut in_rph = false; // фонетика <rPh><t> не входит в текст
+    let mut buf = String::new();
+
+    loop {
+        let ev = rdr.read_event()?;
+        match ev {
+            Event::Start(ref e) | Event::Empty(ref e) if e.local_name().as_ref() == b"c" => {
+                let mut cell = RawCell::default();
+                for a in e.attributes().with_checks(false).flatten() {
+                    match a.key.local_name().as_ref() {
+                        b"r" => {
+                            let (col, row) = split_ref(&a.value);
+                            cell.col = col;
+                            cell.row = row;
+                        }
+                        b"t" => cell.t = Some(String::from_utf8_lossy(&a.value).into_owned()),
+                        b"s" => cell.s = lexical_core::parse(&a.value).ok(),
+                        _ => {}
+                    }
+                }
+                if matches!(ev, Event::Empty(_)) {
+                    cells.push(cell);
+                } else {
+                    cur = Some(cell);
+                }
+            }
+            Event::End(ref e) if e.local_name().as_ref() == b"c" => {
+                if let Some(c) = cur.take() {
+                    cells.push(c);
+                }
+            }
+            Event::Start(ref e) if cur.is_some() => match e.local_name().as_ref() {
+                b"v" => text_target = Some(b'v'),
+                b"f" => text_target = Some(b'f'),
+                b"t" if !in_rph => text_target = Some(b't'),
+                b"rPh" => in_rph = true,
+                _ => {}
+            },
+            Event::Text(ref t) if text_target.is_some() => buf.push_str(&t.decode()?),
+            Event::CData(ref t) if text_target.is_some() => buf.push_str(&t.decode()?),
+            Event::GeneralRef(ref r) if text_target.is_some() => push_entity(&mut buf, r)?,
+            Event::End(ref e) if cur.is_some() => {
+                let name = e.local_name();
+                match (text_target, name.as_ref()) {
+                    (Some(b'v'), b"v") | (Some(b'f'), b"f") | (Some(b't'), b"t") => {
+                        let c = cur.as_mut().unwrap();
+                        let val = std::mem::take(&mut buf);
+                        match text_target {
+                            Some(b'v') => c.v = Some(val),
+                            Some(b'f') => c.f = Some(val),
+                            _ => c.text.get_or_insert_with(String::new).push_str(&val),
+                        }
+                        text_target = None;
+                    }
+                    (_, b"rPh") => in_rph = false,
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
         }
-        // Reuse helpers from outer function
-        fn letters_to_col_idx(s: &str) -> usize {
-            s.bytes().fold(0, |acc, b| {
-                acc * 26 + (b.to_ascii_uppercase() - b'A' + 1) as usize
-            }) - 1
+    }
+    Ok(cells)
+}
+
+/// Разбирает `xl/sharedStrings.xml` в вектор строк (индекс == номер `<si>`).
+pub(crate) fn parse_shared_strings(xml: &[u8]) -> Result<Vec<String>> {
+    let mut rdr = Reader::from_reader(xml);
+    let mut out = Vec::new();
+    let mut cur: Option<String> = None;
+    let mut in_t = false;
+    let mut in_rph = false;
+
+    loop {
+        match next_event(&mut rdr, "xl/sharedStrings.xml")? {
+            Event::Start(ref e) => match e.local_name().as_ref() {
+                b"si" => cur = Some(String::new()),
+                b"rPh" => in_rph = true,
+                b"t" if !in_rph => in_t = true,
+                _ => {}
+            },
+            Event::Empty(ref e) if e.local_name().as_ref() == b"si" => out.push(String::new()),
+            Event::Text(ref t) if in_t => {
+                if let Some(c) = cur.as_mut() {
+                    c.push_str(&t.decode()?);
+                }
+            }
+            Event::GeneralRef(ref r) if in_t => {
+                if let Some(c) = cur.as_mut() {
+                    push_entity(c, r)?;
+                }
+            }
+            Event::End(ref e) => match e.local_name().as_ref() {
+                b"si" => out.push(cur.take().unwrap_or_default()),
+                b"rPh" => in_rph = false,
+                b"t" => in_t = false,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+impl XlsxEditor {
+    /// Лениво загружает таблицу sharedStrings в `self.shared_strings`.
+    pub(crate) fn ensure_shared_strings(&mut self) -> Result<()> {
+        if self.shared_strings.is_none() {
+            let sst = match self.read_part("xl/sharedStrings.xml")? {
+                Some(xml) => parse_shared_strings(&xml)?,
+                None => Vec::new(),
+            };
+            self.shared_strings = Some(sst);
+        }
+        Ok(())
+    }
+
+    /// Значение ячейки `coord` (`"B7"`); пустая или отсутствующая —
+    /// [`ReadValue::Empty`].
+    pub fn get_cell(&mut self, coord: &str) -> Result<ReadValue, XlsxError> {
+        parse_cell(coord)?;
+        Ok(self.get_range(&format!("{coord}:{coord}"))?.remove(0).remove(0))
+    }
+
+    /// Значения диапазона `"A1:D10"` по строкам; каждая строка — на всю ширину
+    /// диапазона, недостающие ячейки — [`ReadValue::Empty`].
+    pub fn get_range(&mut self, range: &str) -> Result<Vec<Vec<ReadValue>>, XlsxError> {
+        let (c0, r0, c1, r1) = parse_range(range)?;
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let dates = date_styles(self.styles_xml.get(&self.src)?)?;
+        let width = (c1 - c0 + 1) as usize;
+        let mut rows = vec![vec![ReadValue::Empty; width]; (r1 - r0 + 1) as usize];
+        for c in read_cells(&self.sheet_xml)? {
+            if (c0..=c1).contains(&c.col) && (r0..=r1).contains(&c.row) {
+                rows[(c.row - r0) as usize][(c.col - c0) as usize] = read_value(&c, sst, &dates);
+            }
         }
-        fn split_coord(coord: &str) -> (String, u32) {
-            let pos = coord
-                .find(|c: char| c.is_ascii_digit())
-                .unwrap_or(coord.len());
-            let col = coord[..pos].to_ascii_uppercase();
-            let row: u32 = coord[pos..].parse().unwrap_or(0);
-            (col, row)
+        Ok(rows)
+    }
+
+    /// Построчный обход листа `sheet` (по умолчанию текущего) с учётом
+    /// несохранённых правок. Копируется только `<sheetData>` листа; ячейки
+    /// разбираются по мере обхода, без списка ячеек всего листа.
+    pub fn rows(&mut self, sheet: Option<&str>) -> Result<Rows, XlsxError> {
+        let xml = match sheet {
+            Some(name) => {
+                let path = self.sheet_path_of(name)?;
+                self.sheet_part(&path)?
+            }
+            None => self.sheet_xml.clone(),
+        };
+        let xml = match find_child(&xml, "sheetData")? {
+            Some(sd) => xml[sd].to_vec(),
+            None => Vec::new(),
+        };
+        self.ensure_shared_strings()?;
+        Ok(Rows {
+            qname: format!("{}row", tag_prefix(&xml)),
+            xml,
+            from: 0,
+            row: 0,
+            pending: None,
+            sst: self.shared_strings.clone().unwrap_or_default(),
+            dates: date_styles(self.styles_xml.get(&self.src)?)?,
+        })
+    }
+
+    /// Границы данных текущего листа за один проход по `<sheetData>`: первая и
+    /// последняя строка/столбец с непустыми ячейками (значение, текст или формула),
+    /// последняя строка каждого столбца и последний столбец каждой строки.
+    /// Пустые ячейки, оставшиеся только ради стиля, не учитываются.
+    pub fn used_range(&self) -> Result<UsedRange, XlsxError> {
+        let mut used = UsedRange::default();
+        for c in read_cells(&self.sheet_xml)? {
+            if c.v.is_none() && c.f.is_none() && c.text.is_none() {
+                continue;
+            }
+            if used.is_empty() {
+                (used.first_row, used.first_col) = (c.row, c.col);
+            }
+            used.first_row = used.first_row.min(c.row);
+            used.last_row = used.last_row.max(c.row);
+            used.first_col = used.first_col.min(c.col);
+            used.last_col = used.last_col.max(c.col);
+            let r = used.last_row_by_col.entry(c.col).or_default();
+            *r = (*r).max(c.row);
+            let col = used.last_col_by_row.entry(c.row).or_default();
+            *col = (*col).max(c.col);
         }
+        Ok(used)
+    }
 
-        let start = parts[0].trim().to_ascii_uppercase();
-        let end = parts[1].trim().to_ascii_uppercase();
+    /// Последняя непустая строка среди столбцов `columns` (`"B"` или `"B,D"`);
+    /// `0`, если данных в них нет. Частный случай [`Self::used_range`].
+    pub fn get_last_row_index(&self, columns: &str) -> Result<u32, XlsxError> {
+        let cols = columns
+            .split(',')
+            .map(|c| parse_col(c.trim()))
+            .collect::<Result<Vec<u32>>>()?;
+        let used = self.used_range()?;
+        Ok(cols.iter().map(|&c| used.last_row_in(c)).max().unwrap_or(0))
+    }
 
-        let start_idx = letters_to_col_idx(&start);
-        let end_idx = letters_to_col_idx(&end);
-        if start_idx > end_idx {
+    /// Последние непустые строки столбцов диапазона `"A:E"` слева направо.
+    /// Частный случай [`Self::used_range`].
+    pub fn get_last_roww_index(&self, range: &str) -> Result<Vec<u32>, XlsxError> {
+        let Some((a, b)) = range.split_once(':') else {
+            bail!("range must be in the form A:E")
+        };
+        let (lo, hi) = (parse_col(a.trim())?, parse_col(b.trim())?);
+        if lo > hi {
             bail!("invalid range order")
         }
-        let mut per_col_last: Vec<u32> = vec![0; end_idx - start_idx + 1];
-
-        let mut reader = Reader::from_reader(self.sheet_xml.as_slice());
-        reader.config_mut().trim_text(true);
-
-        while let Ok(ev) = reader.read_event() {
-            match ev {
-                Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"c" => {
-                    if let Some(coord) = e.attributes().with_checks(false).flatten().find_map(|a| {
-                        (a.key.as_ref() == b"r")
-                            .then(|| String::from_utf8_lossy(&a.value).into_owned())
-                    }) {
-                        let (col, row) = split_coord(&coord);
-                        let idx = letters_to_col_idx(&col);
-                        if idx >= start_idx && idx <= end_idx {
-                            let vec_idx = idx - start_idx;
-                            if row > per_col_last[vec_idx] {
-                                per_col_last[vec_idx] = row;
-                            }
-                        }
-                    }
-                }
-                Event::Eof => break,
-                _ => {}
+        let used = self.used_range()?;
+        Ok((lo..=hi).map(|c| used.last_row_in(c)).collect())
+    }
+}
+
+/// Строки листа по порядку, с первой (см. [`XlsxEditor::rows`]). Каждая
+/// `<row>` разбирается, когда до неё доходит обход; значения — от столбца A до
+/// последней ячейки строки, пропущенные строки — пустые.
+pub struct Rows {
+    /// `<sheetData>` листа
+    xml: Vec<u8>,
+    qname: String,
+    from: usize,
+    /// номер последней выданной строки
+    row: u32,
+    pending: Option<(u32, Vec<ReadValue>)>,
+    sst: Vec<String>,
+    dates: Vec<bool>,
+}
+
+impl Rows {
+    /// Следующая `<row>` листа: номер и значения.
+    fn read_row(&mut self) -> Option<Result<(u32, Vec<ReadValue>)>> {
+        let e = find_elem(&self.xml, &self.qname, self.from)?;
+        self.from = e.end;
+        let gt = e.start + memchr::memchr(b'>', &self.xml[e.start..]).unwrap_or(0);
+        let r = get_attr(&self.xml[e.start..=gt], "r")
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(self.row + 1);
+        let cells = match read_cells(&self.xml[e]) {
+            Ok(cells) => cells,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut values = Vec::new();
+        for c in cells {
+            let col = c.col as usize;
+            if col >= values.len() {
+                values.resize(col + 1, ReadValue::Empty);
+            }
+            values[col] = read_value(&c, &self.sst, &self.dates);
+        }
+        Some(Ok((r, values)))
+    }
+}
+
+impl Iterator for Rows {
+    type Item = Result<Vec<ReadValue>, XlsxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_none() {
+            match self.read_row()? {
+                Ok(row) => self.pending = Some(row),
+                Err(err) => return Some(Err(err.into())),
             }
         }
-        Ok(per_col_last)
+        let (r, _) = self.pending.as_ref()?;
+        if *r > self.row + 1 {
+            self.row += 1;
+            return Some(Ok(Vec::new()));
+        }
+        let (r, values) = self.pending.take()?;
+        self.row = r;
+        Some(Ok(values))
+    }
+}
+
+/// Границы данных листа (см. [`XlsxEditor::used_range`]). Строки 1-based,
+/// столбцы 0-based; у пустого листа все границы нулевые.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsedRange {
+    pub first_row: u32,
+    pub last_row: u32,
+    pub first_col: u32,
+    pub last_col: u32,
+    /// столбец → последняя непустая строка в нём
+    pub last_row_by_col: BTreeMap<u32, u32>,
+    /// строка → последний непустой столбец в ней
+    pub last_col_by_row: BTreeMap<u32, u32>,
+}
+
+impl UsedRange {
+    pub fn is_empty(&self) -> bool {
+        self.last_row == 0
+    }
+
+    /// Диапазон данных в нотации A1 (`"B2:F40"`); `None` у пустого листа.
+    pub fn to_ref(&self) -> Option<String> {
+        (!self.is_empty()).then(|| {
+            format!(
+                "{}{}:{}{}",
+                col_letter(self.first_col),
+                self.first_row,
+                col_letter(self.last_col),
+                self.last_row
+            )
+        })
+    }
+
+    /// Последняя непустая строка столбца `col` (0-based); `0`, если он пуст.
+    pub fn last_row_in(&self, col: u32) -> u32 {
+        self.last_row_by_col.get(&col).copied().unwrap_or(0)
     }
 }
 
+/// `"AB"` → 27.
+fn parse_col(letters: &str) -> Result<u32> {
+    if letters.is_empty() || letters.len() > 3 || !letters.bytes().all(|b| b.is_ascii_alphabetic()) {
+        bail!("invalid column: {letters:?}");
+    }
+    Ok(split_ref(letters.as_bytes()).0)
+}
@@ -0,0 +1,308 @@
+//! records_part.rs – дозапись записей «имя столбца → значение» под заголовок
+//! листа.
+//!
+//! [`XlsxEditor::append_records`] один раз читает строку заголовка и кладёт
+//! каждое значение в столбец с тем же именем: код не зависит от порядка
+//! столбцов в шаблоне, и переставленные в нём столбцы не ломают дозапись.
+//! [`XlsxEditor::upsert_rows`] так же раскладывает записи по столбцам, но
+//! сначала ищет строку с тем же ключом и обновляет её на месте.
+
+use crate::XlsxEditor;
+use crate::cell_xml::push_int;
+use crate::error::{XlsxError, bail, check_row};
+use crate::range_part::{CellMap, ClearMode, patch_sheet_data};
+use crate::read_part::{RawCell, read_cells};
+use crate::sheet_xml::{find_child, find_elem, open_sheet_data, tag_prefix};
+use crate::style::col_letter;
+use crate::template_part::CellValue;
+use anyhow::{Context, Result};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+/// Значение ключевого столбца [`XlsxEditor::upsert_rows`]: число, текст
+/// или логическое, без приведения между ними.
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Number(u64), // биты f64, `-0` приведён к `0`
+    Text(String),
+    Bool(bool),
+}
+
+impl Key {
+    fn number(x: f64) -> Self {
+        Key::Number(if x == 0.0 { 0.0f64 } else { x }.to_bits())
+    }
+
+    /// Ключ ячейки листа; `None` — ячейка пустая.
+    fn of_cell(c: &RawCell, sst: &[String]) -> Option<Self> {
+        match c.t.as_deref() {
+            None | Some("n") => c.v.as_deref()?.trim().parse().ok().map(Key::number),
+            Some("b") => Some(Key::Bool(c.v.as_deref().map(str::trim) == Some("1"))),
+            _ => c.display_text(sst).map(Key::Text),
+        }
+    }
+
+    fn of_value(v: &CellValue) -> Option<Self> {
+        match v {
+            CellValue::Number(x) => Some(Key::number(*x)),
+            CellValue::Text(t) => Some(Key::Text(t.clone())),
+            CellValue::Bool(b) => Some(Key::Bool(*b)),
+            CellValue::Empty | CellValue::Formula(_) => None,
+        }
+    }
+}
+
+/// Строка заголовка листа: номер и столбцы (с нуля) по именам.
+pub(crate) struct Header {
+    pub row: u32,
+    pub cols: HashMap<String, u32>,
+}
+
+impl XlsxEditor {
+    /// Дописывает `records` под данными листа, с `last_row + 1`, по одной
+    /// строке на запись. Заголовок — первая строка листа со значениями: ключ
+    /// записи — имя столбца (без пробелов по краям, с учётом регистра), и
+    /// значение встаёт в этот столбец, где бы он ни был. `CellValue::Empty` —
+    /// пустая ячейка; у повторённого ключа — последнее значение.
+    ///
+    /// Ключ, которого в заголовке нет, — ошибка, а при `add_columns` — новый
+    /// столбец справа от заголовка (имя дописывается в заголовок). У пустого
+    /// листа заголовок тогда пишется первой строкой. Новые столбцы идут в
+    /// порядке первой встречи ключей; у `HashMap` он случаен, так что для
+    /// предсказуемого порядка подойдут `BTreeMap` или вектор пар.
+    ///
+    /// ```no_run
+    /// # use rust_core::{XlsxEditor, template_part::CellValue};
+    /// # use std::collections::HashMap;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Orders")?;
+    /// let order = HashMap::from([
+    ///     ("Customer", CellValue::from("Bob")),
+    ///     ("Total", CellValue::from(42.5)),
+    /// ]);
+    /// xl.append_records([order], false)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn append_records<R, K>(
+        &mut self,
+        records: impl IntoIterator<Item = R>,
+        add_columns: bool,
+    ) -> Result<(), XlsxError>
+    where
+        R: IntoIterator<Item = (K, CellValue)>,
+        K: AsRef<str>,
+    {
+        let mut header = self.header_columns()?;
+        let new_header = header.is_none();
+        let mut next = header
+            .as_ref()
+            .map_or(0, |h| h.cols.values().max().map_or(0, |c| c + 1));
+        let mut added: Vec<(u32, String)> = Vec::new();
+        let mut rows: Vec<BTreeMap<u32, CellValue>> = Vec::new();
+        for record in records {
+            let mut cells = BTreeMap::new();
+            for (key, value) in record {
+                let name = key.as_ref().trim();
+                let col = match header.as_ref().and_then(|h| h.cols.get(name)) {
+                    Some(&col) => col,
+                    None if add_columns => {
+                        let h = header.get_or_insert_with(|| Header {
+                            row: self.last_row + 1,
+                            cols: HashMap::new(),
+                        });
+                        h.cols.insert(name.to_owned(), next);
+                        added.push((next, name.to_owned()));
+                        next += 1;
+                        next - 1
+                    }
+                    None => match &header {
+                        Some(h) => bail!("no column {name:?} in the header row {}", h.row),
+                        None => bail!("sheet has no header row for column {name:?}"),
+                    },
+                };
+                cells.insert(col, value);
+            }
+            rows.push(cells);
+        }
+
+        let header_rows = u64::from(new_header && !added.is_empty());
+        check_row(self.last_row as u64 + header_rows + rows.len() as u64)?;
+        if let Some(h) = header.as_ref().filter(|_| !added.is_empty()) {
+            let row = h.row;
+            for (col, name) in added {
+                self.set_cell(&format!("{}{row}", col_letter(col)), name)?;
+            }
+        }
+        let old_last = self.last_row;
+        let (mut lo, mut hi) = (u32::MAX, 0);
+        let mut xml = Vec::new();
+        let mut row = old_last;
+        for cells in rows {
+            row += 1;
+            xml.extend_from_slice(b"<row r=\"");
+            push_int(&mut xml, row);
+            xml.extend_from_slice(b"\">");
+            for (col, value) in cells.into_iter().filter(|(_, v)| *v != CellValue::Empty) {
+                xml.extend(value.cell_xml(col, row, None));
+                (lo, hi) = (lo.min(col), hi.max(col));
+            }
+            xml.extend_from_slice(b"</row>");
+        }
+
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, xml);
+        self.last_row = row;
+        let written = (lo <= hi).then_some((lo, old_last + 1, hi, row));
+        Ok(self.grow_ranges_on_append(old_last, written)?)
+    }
+
+    /// Обновляет или дописывает строки по ключу: запись раскладывается по
+    /// столбцам заголовка, как в [`Self::append_records`], и ищется строка
+    /// под заголовком, где в столбце `key_column` то же значение. Найденная
+    /// строка обновляется на месте — меняются только ячейки из записи, стили
+    /// ячеек остаются, `CellValue::Empty` очищает значение. Запись с новым
+    /// ключом дописывается в конец листа; если ключ повторится в следующих
+    /// записях, обновится уже эта строка.
+    ///
+    /// Ключи сравниваются с учётом типа: число `7` и текст `"7"` — разные
+    /// ключи. Если ключ стоит в нескольких строках листа, обновляется первая.
+    /// Запись без ключа (или с пустым ключом либо формулой) и имя, которого
+    /// нет в заголовке, — ошибка; тогда лист не меняется.
+    ///
+    /// ```no_run
+    /// # use rust_core::{XlsxEditor, template_part::CellValue};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Orders")?;
+    /// let order = [
+    ///     ("Id", CellValue::from(1017)),
+    ///     ("Status", CellValue::from("shipped")),
+    /// ];
+    /// xl.upsert_rows("Id", [order])?;
+    /// # Ok(()) }
+    /// ```
+    pub fn upsert_rows<R, K>(
+        &mut self,
+        key_column: &str,
+        rows: impl IntoIterator<Item = R>,
+    ) -> Result<(), XlsxError>
+    where
+        R: IntoIterator<Item = (K, CellValue)>,
+        K: AsRef<str>,
+    {
+        let key_name = key_column.trim();
+        let Some(header) = self.header_columns()? else {
+            bail!("sheet has no header row for column {key_name:?}");
+        };
+        let Some(&key_col) = header.cols.get(key_name) else {
+            bail!("no column {key_name:?} in the header row {}", header.row);
+        };
+        let sd = find_child(&self.sheet_xml, "sheetData")?.context("sheetData not found")?;
+
+        // строки по ключу и стили ячеек под заголовком — за один проход
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let mut index: HashMap<Key, u32> = HashMap::new();
+        let mut styles: HashMap<(u32, u32), u32> = HashMap::new();
+        for c in read_cells(&self.sheet_xml[sd.clone()])? {
+            if c.row <= header.row {
+                continue;
+            }
+            if let Some(s) = c.s {
+                styles.insert((c.row, c.col), s);
+            }
+            if c.col == key_col
+                && let Some(key) = Key::of_cell(&c, sst)
+            {
+                index.entry(key).or_insert(c.row);
+            }
+        }
+
+        let old_last = self.last_row;
+        let mut last = old_last;
+        let mut cells = CellMap::new();
+        for record in rows {
+            let mut values = BTreeMap::new();
+            for (key, value) in record {
+                let name = key.as_ref().trim();
+                let Some(&col) = header.cols.get(name) else {
+                    bail!("no column {name:?} in the header row {}", header.row);
+                };
+                values.insert(col, value);
+            }
+            let key = match values.get(&key_col) {
+                Some(v) => match Key::of_value(v) {
+                    Some(key) => key,
+                    None => bail!("key column {key_name:?} needs a value, got {v:?}"),
+                },
+                None => bail!("record has no key column {key_name:?}"),
+            };
+            let row = match index.entry(key) {
+                Entry::Occupied(e) => *e.get(),
+                Entry::Vacant(e) => {
+                    check_row(last as u64 + 1)?;
+                    last += 1;
+                    *e.insert(last)
+                }
+            };
+            let row_cells = cells.entry(row).or_default();
+            for (col, value) in values {
+                if row > old_last && value == CellValue::Empty {
+                    // в новой строке пустой ячейке незачем быть
+                    row_cells.remove(&col);
+                } else {
+                    let s = styles.get(&(row, col)).copied();
+                    row_cells.insert(col, value.cell_xml(col, row, s));
+                }
+            }
+        }
+        if cells.is_empty() {
+            return Ok(());
+        }
+
+        let (mut lo, mut hi) = (u32::MAX, 0);
+        for row_cells in cells.range(old_last + 1..).map(|(_, c)| c) {
+            for &col in row_cells.keys() {
+                (lo, hi) = (lo.min(col), hi.max(col));
+            }
+        }
+        let data = patch_sheet_data(&self.sheet_xml[sd.clone()], &[], ClearMode::All, cells)?;
+        self.sheet_xml.splice(sd, data);
+        self.last_row = last;
+        if last == old_last {
+            return Ok(());
+        }
+        let written = (lo <= hi).then_some((lo, old_last + 1, hi, last));
+        Ok(self.grow_ranges_on_append(old_last, written)?)
+    }
+
+    /// Строка заголовка: первая `<row>` листа, где есть значения; `None` —
+    /// лист пуст. Остальной лист не разбирается. У повторённого имени —
+    /// первый столбец.
+    pub(crate) fn header_columns(&mut self) -> Result<Option<Header>> {
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let Some(sd) = find_child(&self.sheet_xml, "sheetData")? else {
+            return Ok(None);
+        };
+        let data = &self.sheet_xml[sd];
+        let qname = format!("{}row", tag_prefix(data));
+        let mut from = 0;
+        while let Some(e) = find_elem(data, &qname, from) {
+            from = e.end;
+            let mut cols = HashMap::new();
+            let mut row = 0;
+            for c in read_cells(&data[e])? {
+                if c.v.is_none() && c.f.is_none() && c.text.is_none() {
+                    continue;
+                }
+                row = c.row;
+                if let Some(text) = c.display_text(sst) {
+                    cols.entry(text.trim().to_owned()).or_insert(c.col);
+                }
+            }
+            if row > 0 {
+                return Ok(Some(Header { row, cols }));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,241 @@
+//! registry_part.rs – реестр видов частей пакета: где лежит новая часть,
+//! какой у неё ContentType и тип связи. Все новые части книги создаются через
+//! него, чтобы `[Content_Types].xml` и `.rels` всегда оставались согласованными.
+
+use crate::XlsxEditor;
+use crate::rels_part::REL_TABLE;
+use crate::sheet_xml::{find_elem, get_attr};
+use anyhow::{Context, Result};
+use memchr::memmem;
+
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Вид части пакета.
+pub(crate) struct PartSpec {
+    /// Последний сегмент типа связи (`"table"`, `"comments"`, …).
+    pub kind: &'static str,
+    pub rel_type: &'static str,
+    pub content_type: &'static str,
+    /// Шаблон пути новой части; `{n}` — номер. Без `{n}` часть единственная.
+    pub path: &'static str,
+    /// ContentType задаётся через `<Default Extension>` (vml), а не `<Override>`.
+    pub by_extension: bool,
+}
+
+/// Известные виды частей.
+pub(crate) struct PartRegistry;
+
+impl PartRegistry {
+    const SPECS: [PartSpec; 10] = [
+        PartSpec {
+            kind: "worksheet",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet",
+            content_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml",
+            path: "xl/worksheets/sheet{n}.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "table",
+            rel_type: REL_TABLE,
+            content_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.table+xml",
+            path: "xl/tables/table{n}.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "comments",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/comments",
+            content_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.comments+xml",
+            path: "xl/comments{n}.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "vmlDrawing",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/vmlDrawing",
+            content_type: "application/vnd.openxmlformats-officedocument.vmlDrawing",
+            path: "xl/drawings/vmlDrawing{n}.vml",
+            by_extension: true,
+        },
+        PartSpec {
+            kind: "drawing",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing",
+            content_type: "application/vnd.openxmlformats-officedocument.drawing+xml",
+            path: "xl/drawings/drawing{n}.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "sharedStrings",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/sharedStrings",
+            content_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sharedStrings+xml",
+            path: "xl/sharedStrings.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "styles",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles",
+            content_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml",
+            path: "xl/styles.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "core-properties",
+            rel_type: "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties",
+            content_type: "application/vnd.openxmlformats-package.core-properties+xml",
+            path: "docProps/core.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "extended-properties",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties",
+            content_type: "application/vnd.openxmlformats-officedocument.extended-properties+xml",
+            path: "docProps/app.xml",
+            by_extension: false,
+        },
+        PartSpec {
+            kind: "custom-properties",
+            rel_type: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties",
+            content_type: "application/vnd.openxmlformats-officedocument.custom-properties+xml",
+            path: "docProps/custom.xml",
+            by_extension: false,
+        },
+    ];
+
+    /// Описание вида `kind`.
+    pub(crate) fn get(kind: &str) -> Result<&'static PartSpec> {
+        Self::SPECS
+            .iter()
+            .find(|s| s.kind == kind)
+            .with_context(|| format!("unknown part kind `{kind}`"))
+    }
+}
+
+impl XlsxEditor {
+    /// Создаёт часть вида `kind` с содержимым `content`, регистрирует её
+    /// ContentType и связь от части `owner` (листа или `xl/workbook.xml`).
+    /// Возвращает путь новой части и `r:id` связи.
+    pub(crate) fn create_part(
+        &mut self,
+        owner: &str,
+        kind: &str,
+        content: Vec<u8>,
+    ) -> Result<(String, String)> {
+        let path = self.free_part_path(kind)?;
+        self.write_part(&path, content);
+        self.register_part(&path, kind)?;
+        let rid = self.add_rel(owner, kind, &path)?;
+        Ok((path, rid))
+    }
+
+    /// Первый свободный путь для части вида `kind`.
+    pub(crate) fn free_part_path(&self, kind: &str) -> Result<String> {
+        let spec = PartRegistry::get(kind)?;
+        if !spec.path.contains("{n}") {
+            return Ok(spec.path.to_owned());
+        }
+        let names = self.part_names()?;
+        (1..)
+            .map(|n| spec.path.replace("{n}", &n.to_string()))
+            .find(|p| !names.contains(p))
+            .context("no free part name")
+    }
+
+    /// Регистрирует ContentType части `path` вида `kind`.
+    pub(crate) fn register_part(&mut self, path: &str, kind: &str) -> Result<()> {
+        let spec = PartRegistry::get(kind)?;
+        match path.rsplit_once('.') {
+            Some((_, ext)) if spec.by_extension => {
+                self.add_content_type_default(ext, spec.content_type)
+            }
+            _ => self.add_content_type_override(path, spec.content_type),
+        }
+    }
+
+    /// Добавляет связь вида `kind` от части `from` на часть `to` и возвращает её `r:id`.
+    pub(crate) fn add_rel(&mut self, from: &str, kind: &str, to: &str) -> Result<String> {
+        let typ = match PartRegistry::get(kind) {
+            Ok(spec) => spec.rel_type.to_owned(),
+            Err(_) => format!("{REL_NS}/{kind}"),
+        };
+        let mut rels = self.rels_of(from)?;
+        let rid = rels.add(&typ, to);
+        self.store_rels(rels);
+        Ok(rid)
+    }
+
+    /// Регистрирует `<Override>` для новой части в `[Content_Types].xml`, если его ещё нет.
+    pub(crate) fn add_content_type_override(
+        &mut self,
+        part: &str,
+        content_type: &str,
+    ) -> Result<()> {
+        let mut ct = self
+            .read_part("[Content_Types].xml")?
+            .context("[Content_Types].xml not found")?;
+        let part_name = format!("/{part}");
+        if memmem::find(&ct, format!(r#"PartName="{part_name}""#).as_bytes()).is_some() {
+            return Ok(());
+        }
+        let pos = memmem::rfind(&ct, b"</Types>").context("</Types> not found")?;
+        let entry = format!(r#"<Override PartName="{part_name}" ContentType="{content_type}"/>"#);
+        ct.splice(pos..pos, entry.into_bytes());
+        self.write_part("[Content_Types].xml", ct);
+        Ok(())
+    }
+
+    /// Регистрирует `<Default>` для расширения `ext`, если его ещё нет.
+    pub(crate) fn add_content_type_default(&mut self, ext: &str, content_type: &str) -> Result<()> {
+        let mut ct = self
+            .read_part("[Content_Types].xml")?
+            .context("[Content_Types].xml not found")?;
+        let mut from = 0;
+        while let Some(r) = find_elem(&ct, "Default", from) {
+            from = r.end;
+            let e = get_attr(&ct[r], "Extension").unwrap_or_default();
+            if e.eq_ignore_ascii_case(ext) {
+                return Ok(());
+            }
+        }
+        // <Default> по схеме идут перед <Override>
+        let pos = match memmem::find(&ct, b"<Override") {
+            Some(p) => p,
+            None => memmem::rfind(&ct, b"</Types>").context("</Types> not found")?,
+        };
+        let entry = format!(r#"<Default Extension="{ext}" ContentType="{content_type}"/>"#);
+        ct.splice(pos..pos, entry.into_bytes());
+        self.write_part("[Content_Types].xml", ct);
+        Ok(())
+    }
+
+    /// ContentType из `<Override>` части `part`, если он есть.
+    pub(crate) fn content_type_override(&mut self, part: &str) -> Result<Option<String>> {
+        let ct = self
+            .read_part("[Content_Types].xml")?
+            .context("[Content_Types].xml not found")?;
+        let part_name = format!("/{part}");
+        let mut from = 0;
+        while let Some(r) = find_elem(&ct, "Override", from) {
+            from = r.end;
+            if get_attr(&ct[r.clone()], "PartName").as_deref() == Some(part_name.as_str()) {
+                return Ok(get_attr(&ct[r], "ContentType"));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Удаляет `<Override>` части `part` из `[Content_Types].xml`.
+    pub(crate) fn remove_content_type_override(&mut self, part: &str) -> Result<()> {
+        let mut ct = self
+            .read_part("[Content_Types].xml")?
+            .context("[Content_Types].xml not found")?;
+        let part_name = format!("/{part}");
+        let mut from = 0;
+        while let Some(r) = find_elem(&ct, "Override", from) {
+            if get_attr(&ct[r.clone()], "PartName").as_deref() == Some(part_name.as_str()) {
+                ct.splice(r, std::iter::empty());
+                self.write_part("[Content_Types].xml", ct);
+                return Ok(());
+            }
+            from = r.end;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! rels_part.rs – связи частей пакета (`_rels/*.rels`): чтение, правка в памяти
+//! ([`PartRels`]) и запись обратно; связи книги живут в `rels_xml`.
+
+use crate::XlsxEditor;
+use crate::error::next_event;
+use crate::files_part::xml_escape;
+use anyhow::Result;
+use quick_xml::{Reader, events::Event};
+
+pub(crate) const REL_TABLE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/table";
+
+const WORKBOOK: &str = "xl/workbook.xml";
+/// Пространство имён атрибутов `r:id` в частях документа.
+pub(crate) const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+pub(crate) const EMPTY_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"></Relationships>"#;
+
+/// Связь из `.rels`: `target` уже разрешён в путь внутри архива
+/// (для `TargetMode="External"` — как есть).
+#[derive(Debug, Clone)]
+pub(crate) struct Rel {
+    pub id: String,
+    pub typ: String,
+    pub target: String,
+    pub external: bool,
+}
+
+impl Rel {
+    /// Последний сегмент типа связи (`"drawing"`, `"hyperlink"`, …).
+    pub(crate) fn kind(&self) -> &str {
+        self.typ.rsplit('/').next().unwrap_or(&self.typ)
+    }
+}
+
+/// Связи части `owner`: правятся в памяти и записываются через
+/// [`XlsxEditor::store_rels`], так что `r:id` выдаются без повторов.
+#[derive(Debug, Clone)]
+pub(crate) struct PartRels {
+    pub owner: String,
+    pub rels: Vec<Rel>,
+}
+
+impl PartRels {
+    pub(crate) fn get(&self, id: &str) -> Option<&Rel> {
+        self.rels.iter().find(|r| r.id == id)
+    }
+
+    /// Связи вида `kind` (см. [`Rel::kind`]).
+    pub(crate) fn of_kind<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Rel> {
+        self.rels.iter().filter(move |r| r.kind() == kind)
+    }
+
+    /// Добавляет связь типа `typ` на часть `target` (путь в архиве)
+    /// и возвращает её новый `r:id`.
+    pub(crate) fn add(&mut self, typ: &str, target: &str) -> String {
+        let max = self
+            .rels
+            .iter()
+            .filter_map(|r| r.id.strip_prefix("rId")?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+        let id = format!("rId{}", max + 1);
+        self.rels.push(Rel {
+            id: id.clone(),
+            typ: typ.to_owned(),
+            target: target.to_owned(),
+            external: false,
+        });
+        id
+    }
+
+    /// Убирает связь `id` и возвращает её.
+    pub(crate) fn remove(&mut self, id: &str) -> Option<Rel> {
+        let pos = self.rels.iter().position(|r| r.id == id)?;
+        Some(self.rels.remove(pos))
+    }
+
+    fn to_xml(&self) -> Vec<u8> {
+        let mut xml = EMPTY_RELS.trim_end_matches("</Relationships>").to_owned();
+        for r in &self.rels {
+            let (target, mode) = match r.external {
+                true => (r.target.clone(), r#" TargetMode="External""#),
+                false => (relative_target(&self.owner, &r.target), ""),
+            };
+            xml.push_str(&format!(
+                r#"<Relationship Id="{}" Type="{}" Target="{}"{mode}/>"#,
+                xml_escape(&r.id),
+                xml_escape(&r.typ),
+                xml_escape(&target),
+            ));
+        }
+        xml.push_str("</Relationships>");
+        xml.into_bytes()
+    }
+}
+
+/// `xl/worksheets/sheet1.xml` → `xl/worksheets/_rels/sheet1.xml.rels`.
+pub(crate) fn rels_path_of(part: &str) -> String {
+    match part.rsplit_once('/') {
+        Some((dir, file)) => format!("{dir}/_rels/{file}.rels"),
+        None => format!("_rels/{part}.rels"),
+    }
+}
+
+/// Разрешает `Target` относительно части `base` (`../tables/table1.xml`).
+pub(crate) fn resolve_target(base: &str, target: &str) -> String {
+    if let Some(abs) = target.strip_prefix('/') {
+        return abs.to_owned();
+    }
+    let mut parts: Vec<&str> = base.split('/').collect();
+    parts.pop(); // имя файла
+    for seg in target.split('/') {
+        match seg {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            s => parts.push(s),
+        }
+    }
+    parts.join("/")
+}
+
+/// Путь `to` относительно каталога части `from` (для атрибута `Target`).
+pub(crate) fn relative_target(from: &str, to: &str) -> String {
+    let mut from_dir: Vec<&str> = from.split('/').collect();
+    from_dir.pop();
+    let to_parts: Vec<&str> = to.split('/').collect();
+    let common = from_dir
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut out: Vec<&str> = vec![".."; from_dir.len() - common];
+    out.extend(&to_parts[common..]);
+    out.join("/")
+}
+
+/// Разбирает `.rels` части `base`.
+pub(crate) fn parse_rels(xml: &[u8], base: &str) -> Result<Vec<Rel>> {
+    let mut out = Vec::new();
+    let mut rdr = Reader::from_reader(xml);
+    let part = rels_path_of(base);
+    loop {
+        match next_event(&mut rdr, &part)? {
+            Event::Empty(ref e) | Event::Start(ref e)
+                if e.local_name().as_ref() == b"Relationship" =>
+            {
+                let (mut id, mut typ, mut target, mut external) =
+                    (String::new(), String::new(), String::new(), false);
+                for a in e.attributes().with_checks(false).flatten() {
+                    let v = a.unescape_value()?.into_owned();
+                    match a.key.as_ref() {
+                        b"Id" => id = v,
+                        b"Type" => typ = v,
+                        b"Target" => target = v,
+                        b"TargetMode" => external = v == "External",
+                        _ => {}
+                    }
+                }
+                if !external {
+                    target = resolve_target(base, &target);
+                }
+                out.push(Rel {
+                    id,
+                    typ,
+                    target,
+                    external,
+                });
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+impl XlsxEditor {
+    /// Связи текущего листа (пусто, если у листа нет `.rels`).
+    pub(crate) fn sheet_rels(&mut self) -> Result<Vec<Rel>> {
+        let sheet_path = self.sheet_path.clone();
+        Ok(self.rels_of(&sheet_path)?.rels)
+    }
+
+    /// Связи части `part` (`xl/workbook.xml` — из `rels_xml`).
+    pub(crate) fn rels_of(&mut self, part: &str) -> Result<PartRels> {
+        let rels = if part == WORKBOOK {
+            parse_rels(self.rels_xml.get(&self.src)?, part)?
+        } else {
+            match self.read_part(&rels_path_of(part))? {
+                Some(xml) => parse_rels(&xml, part)?,
+                None => Vec::new(),
+            }
+        };
+        Ok(PartRels {
+            owner: part.to_owned(),
+            rels,
+        })
+    }
+
+    /// Записывает связи обратно; `.rels` без связей удаляется.
+    pub(crate) fn store_rels(&mut self, rels: PartRels) {
+        if rels.owner == WORKBOOK {
+            self.rels_xml.set(rels.to_xml());
+            return;
+        }
+        let path = rels_path_of(&rels.owner);
+        if rels.rels.is_empty() {
+            self.remove_part(&path);
+        } else {
+            self.write_part(&path, rels.to_xml());
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! repair_part.rs – починка мелких дефектов шаблонов при открытии: неверные
+//! `count`, повторяющиеся и перепутанные строки.
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::files_part::calc_last_row;
+use crate::sheet_xml::{find_child, find_elem, get_attr, root_prefix, set_attr, tag_prefix};
+use anyhow::Result;
+use quick_xml::{Reader, events::Event};
+use std::path::Path;
+
+/// Контейнеры листа с атрибутом `count`: `(элемент, дочерний элемент)`.
+const SHEET_COUNTS: [(&str, &str); 2] = [
+    ("mergeCells", "mergeCell"),
+    ("dataValidations", "dataValidation"),
+];
+
+/// Контейнеры styles.xml с атрибутом `count`.
+const STYLE_COUNTS: [(&str, &str); 8] = [
+    ("numFmts", "numFmt"),
+    ("fonts", "font"),
+    ("fills", "fill"),
+    ("borders", "border"),
+    ("cellStyleXfs", "xf"),
+    ("cellXfs", "xf"),
+    ("cellStyles", "cellStyle"),
+    ("dxfs", "dxf"),
+];
+
+impl XlsxEditor {
+    /// Открывает лист как [`XlsxEditor::open`] и сразу чинит его
+    /// ([`XlsxEditor::repair_sheet`]). Вторым значением — список исправлений.
+    pub fn open_with_repair<P: AsRef<Path>>(
+        src: P,
+        sheet_name: &str,
+    ) -> Result<(Self, Vec<String>), XlsxError> {
+        let mut editor = Self::open(src, sheet_name)?;
+        let fixed = editor.repair_sheet()?;
+        Ok((editor, fixed))
+    }
+
+    /// Чинит текущий лист и styles.xml: выставляет верные `count` у `<mergeCells>`,
+    /// `<dataValidations>` и блоков стилей, из повторяющихся строк оставляет
+    /// последнюю, сортирует строки по `r`. Возвращает описание каждого исправления
+    /// (пустой список — чинить было нечего).
+    pub fn repair_sheet(&mut self) -> Result<Vec<String>, XlsxError> {
+        let mut fixed = Vec::new();
+        let sheet = self.sheet_path.clone();
+        fix_rows(&mut self.sheet_xml, &mut |m| {
+            fixed.push(format!("{sheet}: {m}"))
+        })?;
+        for (parent, child) in SHEET_COUNTS {
+            fix_count(&mut self.sheet_xml, parent, child, &mut |m| {
+                fixed.push(format!("{sheet}: {m}"))
+            })?;
+        }
+        for (parent, child) in STYLE_COUNTS {
+            fix_count(self.styles_xml.get_mut(&self.src)?, parent, child, &mut |m| {
+                fixed.push(format!("xl/styles.xml: {m}"))
+            })?;
+        }
+        if !fixed.is_empty() {
+            self.last_row = calc_last_row(&self.sheet_path, &self.sheet_xml)?;
+            self.styles_index = None;
+        }
+        Ok(fixed)
+    }
+}
+
+/// Повторяющиеся строки: остаётся последняя; затем строки сортируются по `r`.
+/// Строки без `r` остаются на месте относительно соседей.
+fn fix_rows(xml: &mut Vec<u8>, log: &mut impl FnMut(String)) -> Result<()> {
+    let Some(sd) = find_child(xml, "sheetData")? else {
+        return Ok(());
+    };
+    let data = &xml[sd.clone()];
+    let q = format!("{}row", tag_prefix(data));
+    let mut rows: Vec<(Option<u32>, std::ops::Range<usize>)> = Vec::new();
+    let mut from = 0;
+    while let Some(e) = find_elem(data, &q, from) {
+        from = e.end;
+        let gt = e.start + memchr::memchr(b'>', &data[e.start..]).unwrap_or(0);
+        let r = get_attr(&data[e.start..=gt], "r").and_then(|r| r.parse().ok());
+        rows.push((r, e));
+    }
+    let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+        return Ok(());
+    };
+    let span = first.1.start..last.1.end;
+
+    let mut kept: Vec<(Option<u32>, std::ops::Range<usize>)> = Vec::with_capacity(rows.len());
+    for (i, (r, range)) in rows.iter().enumerate() {
+        if r.is_some() && rows[i + 1..].iter().any(|(n, _)| n == r) {
+            log(format!("dropped duplicate row {}", r.unwrap_or(0)));
+            continue;
+        }
+        kept.push((*r, range.clone()));
+    }
+    let unordered = kept
+        .windows(2)
+        .filter(|w| matches!((w[0].0, w[1].0), (Some(a), Some(b)) if a > b))
+        .count();
+    if unordered > 0 {
+        // стабильная сортировка: строки без `r` наследуют номер предыдущей
+        let mut prev = 0;
+        let mut keyed: Vec<_> = kept
+            .into_iter()
+            .map(|(r, range)| {
+                prev = r.unwrap_or(prev);
+                (prev, range)
+            })
+            .collect();
+        keyed.sort_by_key(|(r, _)| *r);
+        kept = keyed
+            .into_iter()
+            .map(|(r, range)| (Some(r), range))
+            .collect();
+        log(format!("sorted rows by number ({unordered} out of order)"));
+    }
+    if kept.len() == rows.len() && unordered == 0 {
+        return Ok(());
+    }
+
+    let mut body = Vec::with_capacity(span.len());
+    for (_, range) in &kept {
+        body.extend_from_slice(&data[range.clone()]);
+    }
+    xml.splice(sd.start + span.start..sd.start + span.end, body);
+    Ok(())
+}
+
+/// Выставляет `count` элемента `parent` по числу его прямых потомков `child`.
+fn fix_count(
+    xml: &mut Vec<u8>,
+    parent: &str,
+    child: &str,
+    log: &mut impl FnMut(String),
+) -> Result<()> {
+    let q = format!("{}{parent}", root_prefix(xml));
+    let Some(e) = find_elem(xml, &q, 0) else {
+        return Ok(());
+    };
+    let gt = e.start + memchr::memchr(b'>', &xml[e.start..]).unwrap_or(0) + 1;
+    let Some(old) = get_attr(&xml[e.start..gt], "count") else {
+        return Ok(());
+    };
+
+    let mut rdr = Reader::from_reader(&xml[e.clone()]);
+    let (mut depth, mut n) = (0usize, 0u32);
+    loop {
+        match rdr.read_event()? {
+            Event::Start(s) => {
+                if depth == 1 && s.local_name().as_ref() == child.as_bytes() {
+                    n += 1;
+                }
+                depth += 1;
+            }
+            Event::Empty(s) if depth == 1 && s.local_name().as_ref() == child.as_bytes() => n += 1,
+            Event::End(_) => depth -= 1,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if old.parse() == Ok(n) {
+        return Ok(());
+    }
+    let mut tag = xml[e.start..gt].to_vec();
+    set_attr(&mut tag, "count", Some(&n.to_string()));
+    xml.splice(e.start..gt, tag);
+    log(format!("{parent} count {old} -> {n}"));
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! search.rs – поиск подстрок в байтах XML на `memchr::memmem`.
+//!
+//! `memmem::find` каждый раз заново готовит искатель под иглу; для тегов, которые
+//! ищутся в цикле по всему листу (`<row`, `</row>`, `<c`, `</c>`), искатели
+//! собраны один раз и лежат в статиках.
+
+use memchr::memmem::Finder;
+use std::sync::LazyLock;
+
+pub(crate) static ROW_OPEN: LazyLock<Finder<'static>> = LazyLock::new(|| Finder::new(b"<row"));
+pub(crate) static ROW_CLOSE: LazyLock<Finder<'static>> = LazyLock::new(|| Finder::new(b"</row>"));
+pub(crate) static CELL_OPEN: LazyLock<Finder<'static>> = LazyLock::new(|| Finder::new(b"<c"));
+pub(crate) static CELL_CLOSE: LazyLock<Finder<'static>> = LazyLock::new(|| Finder::new(b"</c>"));
+
+/// Первое вхождение `needle` в `hay`.
+#[inline]
+pub(crate) fn find(hay: &[u8], needle: &[u8]) -> Option<usize> {
+    memchr::memmem::find(hay, needle)
+}
+
+/// Последнее вхождение `needle` в `hay`.
+#[inline]
+pub(crate) fn rfind(hay: &[u8], needle: &[u8]) -> Option<usize> {
+    memchr::memmem::rfind(hay, needle)
+}
+
+/// Первое вхождение `needle` не раньше `start` (индекс от начала `hay`).
+#[inline]
+pub(crate) fn find_from(hay: &[u8], needle: &[u8], start: usize) -> Option<usize> {
+    Some(start + find(hay.get(start..)?, needle)?)
+}
+
+/// Как [`find_from`], но готовым искателем (см. [`ROW_CLOSE`] и соседей).
+#[inline]
+pub(crate) fn find_with(finder: &Finder<'_>, hay: &[u8], start: usize) -> Option<usize> {
+    Some(start + finder.find(hay.get(start..)?)?)
+}
+
+/// Первое вхождение искателя не раньше `start`, но не внутри комментария
+/// (`<!--…-->`), инструкции обработки (`<?…?>`) или `<![CDATA[…]]>`: их
+/// содержимое — не разметка, даже если похоже на `<row>` или `</c>`. `start`
+/// должен стоять вне такой вставки.
+pub(crate) fn find_markup(finder: &Finder<'_>, hay: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    loop {
+        let p = find_with(finder, hay, i)?;
+        match opaque_start(&hay[i..p]) {
+            Some(q) => i = opaque_end(hay, i + q)?,
+            None => return Some(p),
+        }
+    }
+}
+
+/// Начало первой непрозрачной вставки (комментарий, PI, CDATA, `<!…>`) в `xml`.
+fn opaque_start(xml: &[u8]) -> Option<usize> {
+    memchr::memchr2_iter(b'!', b'?', xml)
+        .find(|&q| q > 0 && xml[q - 1] == b'<')
+        .map(|q| q - 1)
+}
+
+/// Конец (после `-->`, `?>`, `]]>` или `>`) вставки, начатой в `at`;
+/// `None` — она не закрыта.
+fn opaque_end(hay: &[u8], at: usize) -> Option<usize> {
+    let rest = &hay[at..];
+    let (open, close): (usize, &[u8]) = if rest.starts_with(b"<!--") {
+        (4, b"-->")
+    } else if rest.starts_with(b"<![CDATA[") {
+        (9, b"]]>")
+    } else if rest.starts_with(b"<?") {
+        (2, b"?>")
+    } else {
+        (2, b">")
+    };
+    Some(at + open + find(&rest[open..], close)? + close.len())
+}
+
+/// Есть ли в `xml` комментарии, PI или CDATA (объявление `<?xml …?>` в
+/// начале не в счёт). Без них быстрый поиск может начинать с любого места.
+pub(crate) fn has_opaque(xml: &[u8]) -> bool {
+    let body = match xml.starts_with(b"<?xml") {
+        true => memchr::memchr(b'>', xml).map_or(xml, |gt| &xml[gt + 1..]),
+        false => xml,
+    };
+    opaque_start(body).is_some()
+}
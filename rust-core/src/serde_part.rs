@@ -0,0 +1,450 @@
+//! serde_part.rs – дозапись строк из типов с `Serialize`: поля структуры (или
+//! ключи словаря) становятся столбцами, значения — ячейками.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, check_row};
+use crate::sheet_xml::open_sheet_data;
+use crate::template_part::CellValue;
+use anyhow::Result;
+use serde::ser::{self, Impossible, Serialize, SerializeMap, SerializeStruct, Serializer};
+use std::{collections::HashMap, fmt};
+
+pub use rust_core_derive::XlsxRow;
+
+/// Столбец в [`XlsxRow::columns`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XlsxColumn {
+    /// Имя поля (ключа словаря), из которого берётся значение.
+    pub field: String,
+    /// Заголовок столбца; `None` — имя поля.
+    pub header: Option<String>,
+    /// Числовой формат ячеек столбца: `"#,##0.00"`, `"yyyy-mm-dd"`.
+    pub number_format: Option<String>,
+}
+
+/// Порядок, заголовки и форматы столбцов для [`XlsxEditor::append_xlsx_rows`].
+/// Обычно выводится `#[derive(XlsxRow)]` рядом с `#[derive(Serialize)]`:
+/// поля по порядку, атрибуты `#[xlsx(header = "...", number_format = "...")]`
+/// и `#[xlsx(skip)]`.
+pub trait XlsxRow: Serialize {
+    /// Столбцы по порядку; поля, которых здесь нет, не пишутся.
+    fn columns() -> Vec<XlsxColumn>;
+}
+
+/// Поля одной строки: `(имя, значение)` в порядке сериализации.
+type Record = Vec<(String, CellValue)>;
+
+impl XlsxEditor {
+    /// Дописывает `rows` под данными листа, с `last_row + 1`, начиная со
+    /// столбца A. Столбцы — поля структуры в порядке объявления (у словарей —
+    /// ключи в порядке первой встречи по всем строкам). При `include_header`
+    /// первой пишется строка имён полей.
+    ///
+    /// Числа и `bool` пишутся своими типами, строки и варианты перечислений без
+    /// данных — текстом, `None` и `()` — пустой ячейкой. Вложенные структуры,
+    /// последовательности и словари в значениях — ошибка.
+    pub fn append_serialize<T: Serialize>(
+        &mut self,
+        rows: &[T],
+        include_header: bool,
+    ) -> Result<(), XlsxError> {
+        let records = rows.iter().map(to_record).collect::<Result<Vec<_>>>()?;
+        let mut columns: Vec<XlsxColumn> = Vec::new();
+        for (name, _) in records.iter().flatten() {
+            if !columns.iter().any(|c| c.field == *name) {
+                columns.push(XlsxColumn {
+                    field: name.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(self.write_records(&records, &columns, include_header)?)
+    }
+
+    /// Как [`Self::append_serialize`], но столбцы, их заголовки и числовые
+    /// форматы задаёт [`XlsxRow::columns`]. Поле столбца, которого нет в
+    /// какой-либо строке, — ошибка, и ничего не пишется.
+    pub fn append_xlsx_rows<T: XlsxRow>(
+        &mut self,
+        rows: &[T],
+        include_header: bool,
+    ) -> Result<(), XlsxError> {
+        let records = rows.iter().map(to_record).collect::<Result<Vec<_>>>()?;
+        let columns = T::columns();
+        for (i, record) in records.iter().enumerate() {
+            for c in &columns {
+                if !record.iter().any(|(n, _)| *n == c.field) {
+                    bail!(
+                        "no field {} in row {i} of {}",
+                        c.field,
+                        std::any::type_name::<T>()
+                    );
+                }
+            }
+        }
+        Ok(self.write_records(&records, &columns, include_header)?)
+    }
+
+    fn write_records(
+        &mut self,
+        records: &[Record],
+        columns: &[XlsxColumn],
+        include_header: bool,
+    ) -> Result<()> {
+        let old_last = self.last_row;
+        let rows = records.len() as u64 + u64::from(include_header);
+        check_row(old_last as u64 + rows)?;
+        let styles = columns
+            .iter()
+            .map(|c| match &c.number_format {
+                Some(fmt) => self
+                    .ensure_style(Some(fmt), None, None, None, None)
+                    .map(Some),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut bulk_rows_xml = Vec::new();
+        let mut row = old_last;
+        if include_header {
+            row += 1;
+            bulk_rows_xml.extend(format!(r#"<row r="{row}">"#).into_bytes());
+            for (col, c) in (0u32..).zip(columns) {
+                let text = c.header.as_ref().unwrap_or(&c.field);
+                bulk_rows_xml.extend(CellValue::Text(text.clone()).cell_xml(col, row, None));
+            }
+            bulk_rows_xml.extend_from_slice(b"</row>");
+        }
+        for record in records {
+            row += 1;
+            let values: HashMap<&str, &CellValue> =
+                record.iter().map(|(n, v)| (n.as_str(), v)).collect();
+            bulk_rows_xml.extend(format!(r#"<row r="{row}">"#).into_bytes());
+            for ((col, c), s) in (0u32..).zip(columns).zip(&styles) {
+                match values.get(c.field.as_str()) {
+                    Some(v) if **v != CellValue::Empty => {
+                        bulk_rows_xml.extend(v.cell_xml(col, row, *s));
+                    }
+                    _ => {}
+                }
+            }
+            bulk_rows_xml.extend_from_slice(b"</row>");
+        }
+
+        let pos = open_sheet_data(&mut self.sheet_xml)?.end;
+        self.sheet_xml.splice(pos..pos, bulk_rows_xml);
+        self.last_row = row;
+        let written = (!columns.is_empty() && row > old_last)
+            .then(|| (0, old_last + 1, columns.len() as u32 - 1, row));
+        self.grow_ranges_on_append(old_last, written)
+    }
+}
+
+fn to_record<T: Serialize>(row: &T) -> Result<Record> {
+    Ok(row.serialize(RowSerializer)?)
+}
+
+/// Ошибка сериализации строки.
+#[derive(Debug)]
+struct SerError(String);
+
+impl fmt::Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        SerError(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, SerError> {
+    Err(SerError(format!("{what} cannot be written to a cell")))
+}
+
+fn not_a_row<T>() -> Result<T, SerError> {
+    Err(SerError("a row must serialize as a struct or a map".into()))
+}
+
+/// Строка целиком: структура или словарь.
+struct RowSerializer;
+
+/// Поля строки, собранные [`RowSerializer`].
+struct RowFields {
+    fields: Record,
+    key: Option<String>,
+}
+
+impl SerializeStruct for RowFields {
+    type Ok = Record;
+    type Error = SerError;
+    fn serialize_field<V: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), SerError> {
+        let v = value
+            .serialize(CellSerializer)
+            .map_err(|e| SerError(format!("{key}: {e}")))?;
+        self.fields.push((key.to_owned(), v));
+        Ok(())
+    }
+    fn end(self) -> Result<Record, SerError> {
+        Ok(self.fields)
+    }
+}
+
+impl SerializeMap for RowFields {
+    type Ok = Record;
+    type Error = SerError;
+    fn serialize_key<K: Serialize + ?Sized>(&mut self, key: &K) -> Result<(), SerError> {
+        self.key = Some(key.serialize(CellSerializer)?.to_string());
+        Ok(())
+    }
+    fn serialize_value<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), SerError> {
+        let key = self.key.take().unwrap_or_default();
+        let v = value
+            .serialize(CellSerializer)
+            .map_err(|e| SerError(format!("{key}: {e}")))?;
+        self.fields.push((key, v));
+        Ok(())
+    }
+    fn end(self) -> Result<Record, SerError> {
+        Ok(self.fields)
+    }
+}
+
+/// Всё, кроме структуры и словаря, строкой быть не может.
+macro_rules! not_a_row {
+    ($($f:ident($($t:ty),*)),* $(,)?) => {
+        $(fn $f(self, $(_: $t),*) -> Result<Record, SerError> {
+            not_a_row()
+        })*
+    };
+}
+
+impl Serializer for RowSerializer {
+    type Ok = Record;
+    type Error = SerError;
+    type SerializeSeq = Impossible<Record, SerError>;
+    type SerializeTuple = Impossible<Record, SerError>;
+    type SerializeTupleStruct = Impossible<Record, SerError>;
+    type SerializeTupleVariant = Impossible<Record, SerError>;
+    type SerializeMap = RowFields;
+    type SerializeStruct = RowFields;
+    type SerializeStructVariant = Impossible<Record, SerError>;
+
+    not_a_row!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    );
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<Record, SerError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &V,
+    ) -> Result<Record, SerError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &V,
+    ) -> Result<Record, SerError> {
+        not_a_row()
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        not_a_row()
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, SerError> {
+        not_a_row()
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        not_a_row()
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        not_a_row()
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<RowFields, SerError> {
+        Ok(RowFields {
+            fields: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<RowFields, SerError> {
+        Ok(RowFields {
+            fields: Vec::with_capacity(len),
+            key: None,
+        })
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        not_a_row()
+    }
+}
+
+/// Значение одного поля.
+struct CellSerializer;
+
+macro_rules! number {
+    ($($f:ident($t:ty)),* $(,)?) => {
+        $(fn $f(self, v: $t) -> Result<CellValue, SerError> {
+            Ok(CellValue::Number(v as f64))
+        })*
+    };
+}
+
+impl Serializer for CellSerializer {
+    type Ok = CellValue;
+    type Error = SerError;
+    type SerializeSeq = Impossible<CellValue, SerError>;
+    type SerializeTuple = Impossible<CellValue, SerError>;
+    type SerializeTupleStruct = Impossible<CellValue, SerError>;
+    type SerializeTupleVariant = Impossible<CellValue, SerError>;
+    type SerializeMap = Impossible<CellValue, SerError>;
+    type SerializeStruct = Impossible<CellValue, SerError>;
+    type SerializeStructVariant = Impossible<CellValue, SerError>;
+
+    number!(
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+    );
+
+    fn serialize_bool(self, v: bool) -> Result<CellValue, SerError> {
+        Ok(CellValue::Bool(v))
+    }
+    fn serialize_char(self, v: char) -> Result<CellValue, SerError> {
+        Ok(CellValue::Text(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<CellValue, SerError> {
+        Ok(CellValue::Text(v.to_owned()))
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<CellValue, SerError> {
+        unsupported("bytes")
+    }
+    fn serialize_none(self) -> Result<CellValue, SerError> {
+        Ok(CellValue::Empty)
+    }
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<CellValue, SerError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<CellValue, SerError> {
+        Ok(CellValue::Empty)
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<CellValue, SerError> {
+        Ok(CellValue::Empty)
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<CellValue, SerError> {
+        Ok(CellValue::Text(variant.to_owned()))
+    }
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &V,
+    ) -> Result<CellValue, SerError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        value: &V,
+    ) -> Result<CellValue, SerError> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        unsupported("a sequence")
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, SerError> {
+        unsupported("a tuple")
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        unsupported("a tuple struct")
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        unsupported("an enum variant with fields")
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        unsupported("a map")
+    }
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        unsupported("a nested struct")
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        unsupported("an enum variant with fields")
+    }
+}
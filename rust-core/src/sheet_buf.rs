@@ -0,0 +1,498 @@
+//! sheet_buf.rs – буфер XML текущего листа: плоский `Vec<u8>` плюс отложенные
+//! правки отдельных ячеек (piece table по координатам `row → col`).
+//!
+//! `set_cell` и стиль одной ячейки не сдвигают хвост листа на каждую правку:
+//! правки копятся в [`SheetBuf`] и вливаются в XML одним линейным проходом,
+//! когда буфер читают (`Deref`) или меняют целиком (`DerefMut`). Остальной код
+//! работает с листом как с `Vec<u8>`, не зная о правках.
+//!
+//! Так же хранятся объединения ячеек: список диапазонов читается из
+//! `<mergeCells>` при первом обращении, `merge_cells`/`unmerge_cells` правят
+//! только его, а блок пересобирается целиком, с верным `count`, когда буфер
+//! читают — самое позднее при save().
+//!
+//! Основа листа лежит в `Arc`: снимок буфера для отката
+//! ([`XlsxEditor::begin`](crate::XlsxEditor::begin)) копирует только
+//! отложенные правки, а сам XML копируется при первой правке после снимка.
+
+use crate::search::{find, has_opaque, rfind};
+use crate::sheet_xml::{find_child, find_elem, get_attr, insert_child, set_attr, tag_prefix};
+use crate::style::{col_letter, parse_cell};
+use crate::table_part::{Rect, format_range, parse_range};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::ops::{Deref, DerefMut, Range};
+use std::sync::{Arc, OnceLock};
+
+/// Отложенная правка ячейки.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CellEdit {
+    /// Ячейка целиком заменяется готовым `<c …>`.
+    Replace(Vec<u8>),
+    /// У ячейки меняется только стиль `s`; нет ячейки — появится пустая
+    /// `<c r="…" s="…"/>`.
+    Restyle(u32),
+}
+
+type Edits = BTreeMap<u32, BTreeMap<u32, CellEdit>>;
+
+/// XML листа с отложенными правками ячеек.
+#[derive(Default)]
+pub(crate) struct SheetBuf {
+    base: Arc<Vec<u8>>, // общий со снимками, пока его не меняют
+    edits: Edits,
+    /// `base` с влитыми `edits`: собирается при чтении и становится новой
+    /// основой при следующей правке, чтобы не сливать дважды.
+    merged: OnceLock<Vec<u8>>,
+    /// В `base` есть комментарии или PI ([`has_opaque`]): строки тогда ищутся
+    /// только проходом от начала `<sheetData>`, а не с середины листа, где
+    /// можно попасть внутрь комментария. Правки ячеек их не добавляют, так
+    /// что флаг сбрасывается, только когда `base` меняют целиком.
+    opaque: OnceLock<bool>,
+    /// Объединённые диапазоны в порядке файла; читаются из `base` при первом
+    /// обращении и сбрасываются, когда `base` меняют целиком.
+    merges: OnceLock<Vec<Rect>>,
+    /// `merges` правили: `<mergeCells>` пересобирается при слиянии правок.
+    merges_dirty: bool,
+}
+
+impl From<Vec<u8>> for SheetBuf {
+    fn from(base: Vec<u8>) -> Self {
+        SheetBuf {
+            base: Arc::new(base),
+            ..Default::default()
+        }
+    }
+}
+
+impl Deref for SheetBuf {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        if self.edits.is_empty() && !self.merges_dirty {
+            return &self.base;
+        }
+        self.merged.get_or_init(|| self.render())
+    }
+}
+
+impl DerefMut for SheetBuf {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        self.flush();
+        self.opaque.take();
+        self.merges.take(); // XML правят как угодно — список перечитается
+        Arc::make_mut(&mut self.base)
+    }
+}
+
+impl SheetBuf {
+    /// Вливает отложенные правки в `base`.
+    fn flush(&mut self) {
+        if self.edits.is_empty() && !self.merges_dirty {
+            return;
+        }
+        self.base = Arc::new(self.merged.take().unwrap_or_else(|| self.render()));
+        self.edits.clear();
+        self.merges_dirty = false;
+    }
+
+    /// `base` с правками ячеек и пересобранным `<mergeCells>`.
+    fn render(&self) -> Vec<u8> {
+        let mut out = match self.edits.is_empty() {
+            true => self.base.to_vec(),
+            false => merge_edits(&self.base, &self.edits, self.opaque()),
+        };
+        if let Some(merges) = self.merges.get().filter(|_| self.merges_dirty) {
+            write_merges(&mut out, merges);
+        }
+        out
+    }
+
+    /// Забирает XML со всеми правками, оставляя буфер пустым (без копии).
+    pub(crate) fn take(&mut self) -> Vec<u8> {
+        self.flush();
+        self.opaque.take();
+        self.merges.take();
+        Arc::unwrap_or_clone(std::mem::take(&mut self.base))
+    }
+
+    /// Копия для отката: основа общая, копируются только отложенные правки.
+    pub(crate) fn snapshot(&self) -> Self {
+        SheetBuf {
+            base: Arc::clone(&self.base),
+            edits: self.edits.clone(),
+            merged: OnceLock::new(),
+            opaque: self.opaque.clone(),
+            merges: self.merges.clone(),
+            merges_dirty: self.merges_dirty,
+        }
+    }
+
+    fn opaque(&self) -> bool {
+        *self.opaque.get_or_init(|| has_opaque(&self.base))
+    }
+
+    fn edits_mut(&mut self) -> &mut Edits {
+        self.fold_merged();
+        &mut self.edits
+    }
+
+    /// Прочитанный вид уже содержит все правки — он и есть новая основа.
+    fn fold_merged(&mut self) {
+        if let Some(merged) = self.merged.take() {
+            self.base = Arc::new(merged);
+            self.edits.clear();
+            self.merges_dirty = false;
+        }
+    }
+
+    /// Объединённые диапазоны листа в порядке файла. Ссылки, которые не
+    /// разобрать, пропускаются (и пропадут из файла при пересборке блока).
+    pub(crate) fn merges(&self) -> Result<&[Rect]> {
+        if let Some(m) = self.merges.get() {
+            return Ok(m);
+        }
+        let list = read_merges(&self.base)?;
+        Ok(self.merges.get_or_init(|| list))
+    }
+
+    /// Список объединений для правки; `<mergeCells>` пересоберётся по нему.
+    pub(crate) fn merges_mut(&mut self) -> Result<&mut Vec<Rect>> {
+        self.merges()?;
+        self.fold_merged();
+        self.merges_dirty = true;
+        Ok(self.merges.get_mut().expect("merges are read above"))
+    }
+
+    /// Откладывает замену ячейки (`col` с нуля) готовым `<c …>`.
+    pub(crate) fn set_cell(&mut self, row: u32, col: u32, xml: Vec<u8>) {
+        self.edits_mut()
+            .entry(row)
+            .or_default()
+            .insert(col, CellEdit::Replace(xml));
+    }
+
+    /// Откладывает смену стиля ячейки на `style`.
+    pub(crate) fn restyle_cell(&mut self, row: u32, col: u32, style: u32) {
+        match self.edits_mut().entry(row).or_default().entry(col) {
+            Entry::Occupied(mut e) => match e.get_mut() {
+                CellEdit::Replace(xml) => set_cell_attr(xml, "s", &style.to_string()),
+                CellEdit::Restyle(s) => *s = style,
+            },
+            Entry::Vacant(e) => {
+                e.insert(CellEdit::Restyle(style));
+            }
+        }
+    }
+
+    /// Отложенная правка ячейки, если есть.
+    pub(crate) fn pending(&self, row: u32, col: u32) -> Option<&CellEdit> {
+        self.edits.get(&row)?.get(&col)
+    }
+
+    pub(crate) fn has_edits(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    /// Открывающий тег `<row …>` строки `row` из XML без отложенных правок:
+    /// правки ячеек атрибутов строк не меняют. `None` — строки нет или её
+    /// не найти по разметке.
+    pub(crate) fn base_row_tag(&self, row: u32) -> Option<&[u8]> {
+        let raw = &self.base[find_row(&self.base, row, self.opaque())??];
+        let gt = memchr::memchr(b'>', raw)?;
+        Some(&raw[..=gt])
+    }
+
+    /// `<c …>` ячейки (и всё после неё) из XML без отложенных правок: верно
+    /// для всех ячеек, кроме [`Self::pending`].
+    pub(crate) fn base_cell(&self, row: u32, col: u32) -> Option<&[u8]> {
+        let base = &self.base;
+        let tag = format!(r#"<c r="{}{row}""#, col_letter(col));
+        match find_row(base, row, self.opaque()) {
+            Some(found) => {
+                let raw = &base[found?];
+                find(raw, tag.as_bytes()).map(|pos| &raw[pos..])
+            }
+            // строки без `r` или необычная разметка — ищем по всему листу
+            None => rfind(base, tag.as_bytes()).map(|pos| &base[pos..]),
+        }
+    }
+}
+
+/// Строка `row` в `<sheetData>`: `Some(None)` — строки нет, `None` — по
+/// разметке не понять (у строки нет `r`). `opaque` — в листе есть
+/// комментарии или PI.
+fn find_row(base: &[u8], row: u32, opaque: bool) -> Option<Option<Range<usize>>> {
+    // find_elem дошёл бы до `</sheetData>` через весь лист — концы ищем с двух сторон
+    let open = find(base, b"<sheetData>")?;
+    let close = rfind(base, b"</sheetData>")?;
+    Some(seek_row(base, open + b"<sheetData>".len()..close, row, opaque)?.ok())
+}
+
+/// Ищет строку `row` среди строк `body`: строки идут по возрастанию `r`,
+/// поэтому поиск делением пополам, а не проходом по всему листу. `Err` —
+/// строки нет, в нём место, куда её вставить; `None` — у встреченной строки
+/// нет `r`. `linear` — сразу проходом подряд (лист с комментариями).
+fn seek_row(
+    base: &[u8],
+    body: Range<usize>,
+    row: u32,
+    mut linear: bool,
+) -> Option<Result<Range<usize>, usize>> {
+    let Range {
+        start: mut lo,
+        end: mut hi,
+    } = body;
+    // короткий остаток (или середина внутри длинной строки) проходим подряд
+    loop {
+        linear |= hi - lo <= 4096;
+        let from = if linear { lo } else { lo + (hi - lo) / 2 };
+        let Some(found) = find_elem(&base[..hi], "row", from) else {
+            if linear {
+                return Some(Err(hi));
+            }
+            linear = true;
+            continue;
+        };
+        let r = row_number(&base[found.clone()])?;
+        if r == row {
+            return Some(Ok(found));
+        }
+        if r < row {
+            lo = found.end;
+        } else if linear {
+            return Some(Err(found.start));
+        } else {
+            hi = found.start;
+        }
+    }
+}
+
+/// Объединённые диапазоны из `<mergeCells>` листа.
+fn read_merges(xml: &[u8]) -> Result<Vec<Rect>> {
+    let Some(block) = find_child(xml, "mergeCells")? else {
+        return Ok(Vec::new());
+    };
+    let xml = &xml[block];
+    let qname = format!("{}mergeCell", tag_prefix(xml));
+    let mut out = Vec::new();
+    let mut from = 1;
+    while let Some(r) = find_elem(xml, &qname, from) {
+        if let Some(rect) = get_attr(&xml[r.clone()], "ref").and_then(|v| parse_range(&v).ok()) {
+            out.push(rect);
+        }
+        from = r.end;
+    }
+    Ok(out)
+}
+
+/// Заменяет `<mergeCells>` листа блоком из `merges` (пустой список — блок
+/// убирается); нового блока место ищется по схеме.
+fn write_merges(xml: &mut Vec<u8>, merges: &[Rect]) {
+    let Ok(block) = find_child(xml, "mergeCells") else {
+        debug_assert!(false, "merges read from a sheet that no longer parses");
+        return;
+    };
+    let p = block
+        .as_ref()
+        .map(|b| tag_prefix(&xml[b.clone()]))
+        .unwrap_or_default();
+    let mut out = String::new();
+    if !merges.is_empty() {
+        out = format!(r#"<{p}mergeCells count="{}">"#, merges.len());
+        for &m in merges {
+            out.push_str(&format!(r#"<{p}mergeCell ref="{}"/>"#, format_range(m)));
+        }
+        out.push_str(&format!("</{p}mergeCells>"));
+    }
+    match block {
+        Some(b) => {
+            xml.splice(b, out.into_bytes());
+        }
+        None if !out.is_empty() => {
+            let _ = insert_child(xml, "mergeCells", out.as_bytes());
+        }
+        None => {}
+    }
+}
+
+/// Номер строки из открывающего тега `<row r="…">`.
+fn row_number(raw: &[u8]) -> Option<u32> {
+    let tag_end = memchr::memchr(b'>', raw).map_or(raw.len(), |p| p + 1);
+    get_attr(&raw[..tag_end], "r")?.parse().ok()
+}
+
+/// Ставит атрибут в открывающий тег элемента `xml`.
+fn set_cell_attr(xml: &mut Vec<u8>, name: &str, value: &str) {
+    let Some(gt) = memchr::memchr(b'>', xml) else {
+        return;
+    };
+    let mut tag = xml[..=gt].to_vec();
+    set_attr(&mut tag, name, Some(value));
+    xml.splice(..=gt, tag);
+}
+
+fn edited_cell(out: &mut Vec<u8>, row: u32, col: u32, edit: &CellEdit) {
+    match edit {
+        CellEdit::Replace(xml) => out.extend_from_slice(xml),
+        CellEdit::Restyle(s) => out
+            .extend_from_slice(format!(r#"<c r="{}{row}" s="{s}"/>"#, col_letter(col)).as_bytes()),
+    }
+}
+
+/// Новая строка `r` из одних правленых ячеек.
+fn new_row(out: &mut Vec<u8>, r: u32, cells: &BTreeMap<u32, CellEdit>) {
+    out.extend_from_slice(format!(r#"<row r="{r}">"#).as_bytes());
+    for (&col, edit) in cells {
+        edited_cell(out, r, col, edit);
+    }
+    out.extend_from_slice(b"</row>");
+}
+
+/// Строка `raw` (`<row …>…</row>` или `<row …/>`) с правками `cells`: ячейки
+/// идут по возрастанию столбца, остальное содержимое строки сохраняется.
+fn merge_row(out: &mut Vec<u8>, raw: &[u8], r: u32, cells: &BTreeMap<u32, CellEdit>) {
+    let gt = memchr::memchr(b'>', raw).unwrap_or(raw.len() - 1);
+    let self_closing = raw[gt - 1] == b'/';
+    if self_closing {
+        out.extend_from_slice(raw[..gt - 1].trim_ascii_end());
+        out.push(b'>');
+    } else {
+        out.extend_from_slice(&raw[..=gt]);
+    }
+    let body_end = if self_closing {
+        gt + 1
+    } else {
+        raw.len() - b"</row>".len()
+    };
+    let mut pending = cells.iter().peekable();
+    let mut at = gt + 1;
+    while let Some(c) = find_elem(&raw[..body_end], "c", at) {
+        out.extend_from_slice(&raw[at..c.start]);
+        at = c.end;
+        let cell = &raw[c];
+        let tag_end = memchr::memchr(b'>', cell).map_or(cell.len(), |p| p + 1);
+        let Some(col) = get_attr(&cell[..tag_end], "r").and_then(|v| parse_cell(&v).ok()) else {
+            out.extend_from_slice(cell);
+            continue;
+        };
+        let col = col.0;
+        while let Some((&pc, edit)) = pending.next_if(|(pc, _)| **pc < col) {
+            edited_cell(out, r, pc, edit);
+        }
+        match pending.next_if(|(pc, _)| **pc == col) {
+            Some((_, CellEdit::Replace(xml))) => out.extend_from_slice(xml),
+            Some((_, CellEdit::Restyle(s))) => {
+                let mut tag = cell[..tag_end].to_vec();
+                set_attr(&mut tag, "s", Some(&s.to_string()));
+                out.extend_from_slice(&tag);
+                out.extend_from_slice(&cell[tag_end..]);
+            }
+            None => out.extend_from_slice(cell),
+        }
+    }
+    out.extend_from_slice(&raw[at..body_end]);
+    for (&pc, edit) in pending {
+        edited_cell(out, r, pc, edit);
+    }
+    out.extend_from_slice(b"</row>");
+}
+
+/// Собирает XML листа с правками за один проход: правленые строки
+/// пересобираются, новые встают по порядку номеров, остальное копируется как
+/// есть. В листе с комментариями (`opaque`) строки не ищутся с середины.
+fn merge_edits(base: &[u8], edits: &Edits, opaque: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(base.len() + edits.len() * 64);
+    // быстрый поиск, а для необычной разметки (`</sheetData >`) — разбор XML
+    let sd = find_elem(base, "sheetData", 0).or_else(|| find_child(base, "sheetData").ok()?);
+    let Some(sd) = sd else {
+        // set_cell создаёт <sheetData> до первой правки, сюда не попадаем
+        debug_assert!(false, "cell edits on a sheet without <sheetData>");
+        return base.to_vec();
+    };
+    let gt = sd.start + memchr::memchr(b'>', &base[sd.clone()]).unwrap_or(0);
+    if base[gt - 1] == b'/' {
+        out.extend_from_slice(base[..gt - 1].trim_ascii_end());
+        out.push(b'>');
+        for (&r, cells) in edits {
+            new_row(&mut out, r, cells);
+        }
+        out.extend_from_slice(b"</sheetData>");
+        out.extend_from_slice(&base[sd.end..]);
+        return out;
+    }
+
+    // закрывающий тег может быть с пробелами (`</sheetData >`)
+    let body_end = sd.start + memchr::memrchr(b'<', &base[sd.clone()]).unwrap_or(0);
+    out.extend_from_slice(&base[..=gt]);
+    if opaque || !merge_seeking(&mut out, base, gt + 1..body_end, edits) {
+        out.truncate(gt + 1);
+        merge_scanning(&mut out, base, gt + 1..body_end, edits);
+    }
+    out.extend_from_slice(&base[body_end..]);
+    out
+}
+
+/// Слияние с поиском каждой правленой строки делением пополам: строки между
+/// правками копируются целыми кусками. `false` — у какой-то строки нет `r`,
+/// нужен [`merge_scanning`].
+fn merge_seeking(out: &mut Vec<u8>, base: &[u8], body: Range<usize>, edits: &Edits) -> bool {
+    let mut at = body.start;
+    for (&r, cells) in edits {
+        // шаг растёт вдвое, пока не перешагнём строку `r`: близкие правки
+        // ищутся за пару проб, далёкие — делением пополам внутри шага
+        let (mut lo, mut step) = (at, 4096);
+        let hi = loop {
+            let Some(probe) = find_elem(&base[..body.end], "row", lo + step) else {
+                break body.end;
+            };
+            match row_number(&base[probe.clone()]) {
+                Some(pr) if pr >= r => break probe.end,
+                Some(_) => (lo, step) = (probe.end, step * 2),
+                None => return false,
+            }
+        };
+        match seek_row(base, lo..hi, r, false) {
+            Some(Ok(row)) => {
+                out.extend_from_slice(&base[at..row.start]);
+                merge_row(out, &base[row.clone()], r, cells);
+                at = row.end;
+            }
+            Some(Err(pos)) => {
+                out.extend_from_slice(&base[at..pos]);
+                new_row(out, r, cells);
+                at = pos;
+            }
+            None => return false,
+        }
+    }
+    out.extend_from_slice(&base[at..body.end]);
+    true
+}
+
+/// Слияние проходом по всем строкам `body` по порядку.
+fn merge_scanning(out: &mut Vec<u8>, base: &[u8], body: Range<usize>, edits: &Edits) {
+    let mut pending = edits.iter().peekable();
+    let mut at = body.start;
+    while let Some(row) = find_elem(&base[..body.end], "row", at) {
+        out.extend_from_slice(&base[at..row.start]);
+        at = row.end;
+        let raw = &base[row];
+        let Some(r) = row_number(raw) else {
+            out.extend_from_slice(raw);
+            continue;
+        };
+        while let Some((&pr, cells)) = pending.next_if(|(pr, _)| **pr < r) {
+            new_row(out, pr, cells);
+        }
+        match pending.next_if(|(pr, _)| **pr == r) {
+            Some((_, cells)) => merge_row(out, raw, r, cells),
+            None => out.extend_from_slice(raw),
+        }
+    }
+    out.extend_from_slice(&base[at..body.end]);
+    for (&r, cells) in pending {
+        new_row(out, r, cells);
+    }
+}
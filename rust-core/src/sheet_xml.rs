@@ -0,0 +1,669 @@
+//! sheet_xml.rs – структура верхнего уровня `<worksheet>` и `<workbook>`: порядок
+//! дочерних элементов по схеме и точечная правка атрибутов в сыром теге.
+
+use crate::read_part::split_ref;
+use crate::table_part::{Rect, format_range, parse_range};
+use anyhow::{Context, Result};
+use crate::search::{CELL_CLOSE, CELL_OPEN, ROW_CLOSE, ROW_OPEN, find_markup};
+use memchr::memmem::{self, Finder};
+use quick_xml::{Reader, events::Event};
+use std::ops::Range;
+
+/// Порядок дочерних элементов `<worksheet>` (CT_Worksheet, ECMA-376 §18.3.1.99).
+/// Excel "чинит" файл, если элементы идут не в этом порядке.
+pub(crate) const WORKSHEET_ORDER: &[&str] = &[
+    "sheetPr",
+    "dimension",
+    "sheetViews",
+    "sheetFormatPr",
+    "cols",
+    "sheetData",
+    "sheetCalcPr",
+    "sheetProtection",
+    "protectedRanges",
+    "scenarios",
+    "autoFilter",
+    "sortState",
+    "dataConsolidate",
+    "customSheetViews",
+    "mergeCells",
+    "phoneticPr",
+    "conditionalFormatting",
+    "dataValidations",
+    "hyperlinks",
+    "printOptions",
+    "pageMargins",
+    "pageSetup",
+    "headerFooter",
+    "rowBreaks",
+    "colBreaks",
+    "customProperties",
+    "cellWatches",
+    "ignoredErrors",
+    "smartTags",
+    "drawing",
+    "legacyDrawing",
+    "legacyDrawingHF",
+    "drawingHF",
+    "picture",
+    "oleObjects",
+    "controls",
+    "webPublishItems",
+    "tableParts",
+    "extLst",
+];
+
+/// Порядок дочерних элементов `<workbook>` (CT_Workbook, ECMA-376 §18.2.27).
+pub(crate) const WORKBOOK_ORDER: &[&str] = &[
+    "fileVersion",
+    "fileSharing",
+    "workbookPr",
+    "workbookProtection",
+    "bookViews",
+    "sheets",
+    "functionGroups",
+    "externalReferences",
+    "definedNames",
+    "calcPr",
+    "oleSize",
+    "customWorkbookViews",
+    "pivotCaches",
+    "smartTagPr",
+    "smartTagTypes",
+    "webPublishing",
+    "fileRecoveryPr",
+    "webPublishObjects",
+    "extLst",
+];
+
+/// Дочерний элемент корня (`<worksheet>`/`<workbook>`): локальное имя и байтовый диапазон целиком
+/// (от `<` открывающего тега до `>` закрывающего).
+#[derive(Debug, Clone)]
+pub(crate) struct Child {
+    pub name: String,
+    pub range: Range<usize>,
+}
+
+fn order_of(order: &[&str], name: &str) -> Option<usize> {
+    order.iter().position(|n| *n == name)
+}
+
+/// Диапазон `<sheetData>…</sheetData>` (или `<sheetData/>`), найденный без разбора строк.
+fn sheet_data_range(xml: &[u8]) -> Option<Range<usize>> {
+    let p = root_prefix(xml);
+    let (open, close) = (format!("<{p}sheetData"), format!("</{p}sheetData>"));
+    let mut from = 0;
+    let start = loop {
+        let at = from + memmem::find(&xml[from..], open.as_bytes())?;
+        match xml.get(at + open.len()) {
+            Some(b'>' | b'/' | b' ' | b'\t' | b'\r' | b'\n') => break at,
+            _ => from = at + open.len(),
+        }
+    };
+    let tag_end = start + memmem::find(&xml[start..], b">")?;
+    if xml[tag_end - 1] == b'/' {
+        return Some(start..tag_end + 1);
+    }
+    let end = memmem::rfind(xml, close.as_bytes())?;
+    Some(start..end + close.len())
+}
+
+/// Собирает дочерние элементы верхнего уровня в куске `xml`, начиная с глубины `depth`.
+fn scan_children(xml: &[u8], offset: usize, mut depth: usize, out: &mut Vec<Child>) -> Result<()> {
+    let mut rdr = Reader::from_reader(xml);
+    rdr.config_mut().check_end_names = false;
+    let mut open: Option<(String, usize)> = None;
+
+    loop {
+        let before = rdr.buffer_position() as usize;
+        match rdr.read_event()? {
+            Event::Start(ref e) => {
+                if depth == 1 {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                    open = Some((name, offset + before));
+                }
+                depth += 1;
+            }
+            Event::Empty(ref e) if depth == 1 => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                let after = rdr.buffer_position() as usize;
+                out.push(Child {
+                    name,
+                    range: offset + before..offset + after,
+                });
+            }
+            Event::End(_) => {
+                depth = depth.saturating_sub(1);
+                if let Some((name, start)) = open.take_if(|_| depth == 1) {
+                    let after = rdr.buffer_position() as usize;
+                    out.push(Child {
+                        name,
+                        range: start..offset + after,
+                    });
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Все дочерние элементы `<worksheet>` в порядке документа.
+/// Содержимое `<sheetData>` не разбирается, поэтому проход дешёвый даже на больших листах.
+pub(crate) fn worksheet_children(xml: &[u8]) -> Result<Vec<Child>> {
+    let mut out = Vec::new();
+    match sheet_data_range(xml) {
+        Some(sd) => {
+            scan_children(&xml[..sd.start], 0, 0, &mut out)?;
+            out.push(Child {
+                name: "sheetData".to_owned(),
+                range: sd.clone(),
+            });
+            // хвост без закрывающего `</worksheet>`, иначе ридер споткнётся о непарный тег
+            let tail_end = root_close(xml, "worksheet").unwrap_or(xml.len()).max(sd.end);
+            scan_children(&xml[sd.end..tail_end], sd.end, 1, &mut out)?;
+        }
+        None => scan_children(xml, 0, 0, &mut out)?,
+    }
+    Ok(out)
+}
+
+/// Первый дочерний элемент `<worksheet>` с локальным именем `name`.
+pub(crate) fn find_child(xml: &[u8], name: &str) -> Result<Option<Range<usize>>> {
+    Ok(worksheet_children(xml)?
+        .into_iter()
+        .find(|c| c.name == name)
+        .map(|c| c.range))
+}
+
+/// Внутренность `<sheetData>` (между открывающим и закрывающим тегами) — сюда
+/// вставляются строки. `<sheetData/>` раскрывается в пару тегов. Границы ищутся
+/// через [`find_child`], поэтому необычная разметка (`</sheetData >`, атрибуты,
+/// префиксы) разбирается полным проходом XML, а не приводит к ошибке.
+pub(crate) fn open_sheet_data(xml: &mut Vec<u8>) -> Result<Range<usize>> {
+    let sd = find_child(xml, "sheetData")?.context("<sheetData> not found in the worksheet")?;
+    let gt = sd.start + memchr::memchr(b'>', &xml[sd.clone()]).context("malformed <sheetData> tag")?;
+    if xml[gt - 1] != b'/' {
+        let close = sd.start + memchr::memrchr(b'<', &xml[sd.clone()]).unwrap_or(0);
+        return Ok(gt + 1..close.max(gt + 1));
+    }
+    let p = tag_prefix(&xml[sd.clone()]);
+    let mut tag = xml[sd.start..gt - 1].trim_ascii_end().to_vec();
+    tag.push(b'>');
+    let inner = sd.start + tag.len();
+    tag.extend(format!("</{p}sheetData>").bytes());
+    xml.splice(sd, tag);
+    Ok(inner..inner)
+}
+
+/// Позиция, куда по схеме должен встать новый элемент `name`
+/// (после всех уже существующих элементов с тем же именем).
+pub(crate) fn insert_pos(xml: &[u8], name: &str) -> Result<usize> {
+    let children = worksheet_children(xml)?;
+    pos_by_order(xml, &children, WORKSHEET_ORDER, "worksheet", name)
+}
+
+fn pos_by_order(
+    xml: &[u8],
+    children: &[Child],
+    order: &[&str],
+    root: &str,
+    name: &str,
+) -> Result<usize> {
+    let idx = order_of(order, name).with_context(|| format!("unknown {root} element <{name}>"))?;
+    if let Some(next) = children
+        .iter()
+        .find(|c| order_of(order, &c.name).is_some_and(|i| i > idx))
+    {
+        return Ok(next.range.start);
+    }
+    root_close(xml, root).with_context(|| format!("</{root}> not found"))
+}
+
+/// Позиция закрывающего тега корня `</root>` (или `</x:root>`).
+fn root_close(xml: &[u8], root: &str) -> Option<usize> {
+    let close = memmem::rfind(xml, format!("{root}>").as_bytes())?;
+    xml[..close].iter().rposition(|&b| b == b'<')
+}
+
+/// Вставляет `content` как новый дочерний элемент `name` в положенное по схеме место.
+pub(crate) fn insert_child(xml: &mut Vec<u8>, name: &str, content: &[u8]) -> Result<usize> {
+    let pos = insert_pos(xml, name)?;
+    xml.splice(pos..pos, content.iter().copied());
+    Ok(pos)
+}
+
+/// Заменяет первый элемент `name` на `content` или вставляет его, если элемента нет.
+pub(crate) fn upsert_child(xml: &mut Vec<u8>, name: &str, content: &[u8]) -> Result<()> {
+    match find_child(xml, name)? {
+        Some(r) => {
+            xml.splice(r, content.iter().copied());
+        }
+        None => {
+            insert_child(xml, name, content)?;
+        }
+    }
+    Ok(())
+}
+
+/// Правит открывающий тег пустого дочернего элемента `name` (`<pageSetup …/>`),
+/// создавая `<name/>` в положенном по схеме месте.
+pub(crate) fn edit_empty_child(
+    xml: &mut Vec<u8>,
+    name: &str,
+    f: impl FnOnce(&mut Vec<u8>),
+) -> Result<()> {
+    let (range, mut tag) = match find_child(xml, name)? {
+        Some(r) => {
+            let end = r.start + xml[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+            (r.start..end + 1, xml[r.start..=end].to_vec())
+        }
+        None => {
+            let p = root_prefix(xml);
+            let pos = insert_child(xml, name, format!("<{p}{name}/>").as_bytes())?;
+            let len = p.len() + name.len() + 3;
+            (pos..pos + len, xml[pos..pos + len].to_vec())
+        }
+    };
+    f(&mut tag);
+    xml.splice(range, tag);
+    Ok(())
+}
+
+/// Удаляет все элементы `name`; возвращает `true`, если что-то было удалено.
+pub(crate) fn remove_children(xml: &mut Vec<u8>, name: &str) -> Result<bool> {
+    let ranges: Vec<Range<usize>> = worksheet_children(xml)?
+        .into_iter()
+        .filter(|c| c.name == name)
+        .map(|c| c.range)
+        .collect();
+    for r in ranges.iter().rev() {
+        xml.splice(r.clone(), std::iter::empty());
+    }
+    Ok(!ranges.is_empty())
+}
+
+/// Расширяет `<dimension ref>` листа так, чтобы он покрывал `rect`
+/// (создаёт элемент, если его нет).
+pub(crate) fn extend_dimension(xml: &mut Vec<u8>, rect: Rect) -> Result<()> {
+    let Some(r) = find_child(xml, "dimension")? else {
+        let dim = format!(r#"<dimension ref="{}"/>"#, format_range(rect));
+        insert_child(xml, "dimension", dim.as_bytes())?;
+        return Ok(());
+    };
+    let end = r.start + xml[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+    let mut tag = xml[r.start..=end].to_vec();
+    let merged = match get_attr(&tag, "ref").and_then(|v| parse_range(&v).ok()) {
+        Some(old) => (
+            old.0.min(rect.0),
+            old.1.min(rect.1),
+            old.2.max(rect.2),
+            old.3.max(rect.3),
+        ),
+        None => rect,
+    };
+    set_attr(&mut tag, "ref", Some(&format_range(merged)));
+    xml.splice(r.start..=end, tag);
+    Ok(())
+}
+
+/// Переписывает `<dimension ref>` по фактическим ячейкам `<sheetData>` (включая
+/// пустые со стилем, как Excel); у листа без ячеек — `A1`. `true` — XML изменён.
+pub(crate) fn refresh_dimension(xml: &mut Vec<u8>) -> Result<bool> {
+    let Some(sd) = find_child(xml, "sheetData")? else {
+        return Ok(false);
+    };
+    let data = &xml[sd];
+    let open = format!("<{}c", tag_prefix(data));
+    let finder = Finder::new(&open);
+    let mut rect: Option<Rect> = None;
+    let mut from = 0;
+    while let Some(p) = find_markup(&finder, data, from) {
+        from = p + open.len();
+        if !data.get(from).is_some_and(|b| b.is_ascii_whitespace()) {
+            continue;
+        }
+        let gt = from + memchr::memchr(b'>', &data[from..]).unwrap_or(data.len() - from);
+        let Some(r) = get_attr(&data[p..gt], "r") else {
+            continue;
+        };
+        let (c, r) = split_ref(r.as_bytes());
+        rect = Some(match rect {
+            Some((c0, r0, c1, r1)) => (c0.min(c), r0.min(r), c1.max(c), r1.max(r)),
+            None => (c, r, c, r),
+        });
+    }
+    let dim = rect.map_or_else(|| "A1".to_owned(), format_range);
+
+    match find_child(xml, "dimension")? {
+        Some(d) => {
+            let end = d.start + xml[d.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+            let mut tag = xml[d.start..=end].to_vec();
+            if get_attr(&tag, "ref").as_deref() == Some(dim.as_str()) {
+                return Ok(false);
+            }
+            set_attr(&mut tag, "ref", Some(&dim));
+            xml.splice(d.start..=end, tag);
+        }
+        None => {
+            let tag = format!(r#"<{}dimension ref="{dim}"/>"#, root_prefix(xml));
+            insert_child(xml, "dimension", tag.as_bytes())?;
+        }
+    }
+    Ok(true)
+}
+
+/// Пересчитывает `spans="first:last"` (номера столбцов с 1) по ячейкам каждой
+/// строки. `all_rows == false` — правятся только строки, где `spans` уже есть;
+/// `true` — атрибут ставится всем непустым строкам. У строки без ячеек `spans`
+/// убирается. `true` в результате — XML изменён.
+pub(crate) fn refresh_row_spans(xml: &mut Vec<u8>, all_rows: bool) -> Result<bool> {
+    let Some(sd) = find_child(xml, "sheetData")? else {
+        return Ok(false);
+    };
+    // правка на месте: лист бывает огромным, копия sheetData удвоила бы память
+    let p = tag_prefix(&xml[sd.clone()]);
+    let (row_q, open) = (format!("{p}row"), format!("<{p}c"));
+    let finder = Finder::new(&open);
+    let mut changed = false;
+    let (mut from, mut end) = (sd.start, sd.end);
+    while let Some(e) = find_elem(&xml[..end], &row_q, from) {
+        let data = &xml[..end];
+        let gt = e.start + memchr::memchr(b'>', &data[e.start..]).unwrap_or(0) + 1;
+        from = e.end;
+        let tag = &data[e.start..gt];
+        let old = get_attr(tag, "spans");
+        if old.is_none() && !all_rows {
+            continue;
+        }
+
+        let mut cols: Option<(u32, u32)> = None;
+        let mut at = gt;
+        while let Some(c) = find_markup(&finder, &data[..e.end], at) {
+            at = c + open.len();
+            if !data.get(at).is_some_and(|b| b.is_ascii_whitespace()) {
+                continue;
+            }
+            let end = at + memchr::memchr(b'>', &data[at..e.end]).unwrap_or(0);
+            let Some(r) = get_attr(&data[c..end], "r") else {
+                continue;
+            };
+            let col = split_ref(r.as_bytes()).0 + 1;
+            cols = Some(cols.map_or((col, col), |(lo, hi)| (lo.min(col), hi.max(col))));
+        }
+        let spans = cols.map(|(lo, hi)| format!("{lo}:{hi}"));
+        if spans == old {
+            continue;
+        }
+        let mut tag = tag.to_vec();
+        set_attr(&mut tag, "spans", spans.as_deref());
+        from = e.end - (gt - e.start) + tag.len();
+        end = end - (gt - e.start) + tag.len();
+        xml.splice(e.start..gt, tag);
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// Основное пространство имён SpreadsheetML.
+pub(crate) const NS_MAIN: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+
+/// Лист в стиле `<x:worksheet xmlns:x="…/main">` переводится в равнозначный вид
+/// без префикса (`xmlns` по умолчанию на корне), чтобы поиски `<row`,
+/// `</sheetData>` и т.п. работали как обычно. Возвращает снятый префикс
+/// (`"x:"`) для [`restore_prefix`]; `""` — лист не трогали.
+pub(crate) fn strip_prefix(xml: &mut Vec<u8>) -> String {
+    let Some(root) = root_tag_range(xml) else {
+        return String::new();
+    };
+    let p = tag_prefix(&xml[root.clone()]);
+    let Some(name) = p.strip_suffix(':') else {
+        return String::new();
+    };
+    let tag = &xml[root];
+    if get_attr(tag, "xmlns").is_some()
+        || get_attr(tag, &format!("xmlns:{name}")).as_deref() != Some(NS_MAIN)
+    {
+        return String::new();
+    }
+    *xml = map_elem_names(xml, |n| n.strip_prefix(p.as_bytes()).map(<[u8]>::to_vec));
+    if let Some(root) = root_tag_range(xml) {
+        let mut tag = xml[root.clone()].to_vec();
+        set_attr(&mut tag, "xmlns", Some(NS_MAIN));
+        xml.splice(root, tag);
+    }
+    p
+}
+
+/// Обратное к [`strip_prefix`]: элементы без префикса получают `prefix`,
+/// `xmlns` по умолчанию заменяется на `xmlns:{prefix}`. Так и добавленные
+/// редактором элементы пишутся в стиле исходного файла.
+pub(crate) fn restore_prefix(xml: &mut Vec<u8>, prefix: &str) {
+    let Some(name) = prefix.strip_suffix(':') else {
+        return;
+    };
+    *xml = map_elem_names(xml, |n| (!n.contains(&b':')).then(|| [prefix.as_bytes(), n].concat()));
+    if let Some(root) = root_tag_range(xml) {
+        let mut tag = xml[root.clone()].to_vec();
+        set_attr(&mut tag, "xmlns", None);
+        set_attr(&mut tag, &format!("xmlns:{name}"), Some(NS_MAIN));
+        xml.splice(root, tag);
+    }
+}
+
+/// Копия `xml`, где имена элементов (в открывающих и закрывающих тегах)
+/// заменены через `f`; комментарии, CDATA и инструкции не трогаются.
+fn map_elem_names(xml: &[u8], f: impl Fn(&[u8]) -> Option<Vec<u8>>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(xml.len() + xml.len() / 16);
+    let mut i = 0;
+    while let Some(lt) = memchr::memchr(b'<', &xml[i..]).map(|p| p + i) {
+        let skip = |end: &[u8]| memmem::find(&xml[lt..], end).map_or(xml.len(), |p| lt + p + end.len());
+        let skip_to = match xml.get(lt + 1) {
+            _ if xml[lt..].starts_with(b"<!--") => skip(b"-->"),
+            _ if xml[lt..].starts_with(b"<![CDATA[") => skip(b"]]>"),
+            Some(b'?' | b'!') => skip(b">"),
+            _ => {
+                let at = lt + 1 + usize::from(xml.get(lt + 1) == Some(&b'/'));
+                let end = xml[at..]
+                    .iter()
+                    .position(|&b| b == b'>' || b == b'/' || b.is_ascii_whitespace())
+                    .map_or(xml.len(), |e| at + e);
+                out.extend_from_slice(&xml[i..at]);
+                match f(&xml[at..end]) {
+                    Some(name) => out.extend_from_slice(&name),
+                    None => out.extend_from_slice(&xml[at..end]),
+                }
+                i = end;
+                continue;
+            }
+        };
+        out.extend_from_slice(&xml[i..skip_to]);
+        i = skip_to;
+    }
+    out.extend_from_slice(&xml[i..]);
+    out
+}
+
+/* ========================== WORKBOOK.XML ================================== */
+
+/// Все дочерние элементы `<workbook>` в порядке документа.
+pub(crate) fn workbook_children(xml: &[u8]) -> Result<Vec<Child>> {
+    let mut out = Vec::new();
+    let end = root_close(xml, "workbook").unwrap_or(xml.len());
+    scan_children(&xml[..end], 0, 0, &mut out)?;
+    Ok(out)
+}
+
+/// Первый дочерний элемент `<workbook>` с локальным именем `name`.
+pub(crate) fn find_wb_child(xml: &[u8], name: &str) -> Result<Option<Range<usize>>> {
+    Ok(workbook_children(xml)?
+        .into_iter()
+        .find(|c| c.name == name)
+        .map(|c| c.range))
+}
+
+/// Вставляет `content` в `<workbook>` в положенное по схеме место.
+pub(crate) fn insert_wb_child(xml: &mut Vec<u8>, name: &str, content: &[u8]) -> Result<usize> {
+    let children = workbook_children(xml)?;
+    let pos = pos_by_order(xml, &children, WORKBOOK_ORDER, "workbook", name)?;
+    xml.splice(pos..pos, content.iter().copied());
+    Ok(pos)
+}
+
+/* ========================== АТРИБУТЫ В СЫРОМ ТЕГЕ ========================= */
+
+/// Диапазон значения атрибута `name` (без кавычек) в открывающем теге `tag`.
+fn attr_value_range(tag: &[u8], name: &str) -> Option<Range<usize>> {
+    let n = name.as_bytes();
+    let mut from = 0;
+    while let Some(p) = memmem::find(&tag[from..], n).map(|p| p + from) {
+        from = p + n.len();
+        if p == 0 || !tag[p - 1].is_ascii_whitespace() {
+            continue;
+        }
+        let mut i = p + n.len();
+        while i < tag.len() && tag[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if tag.get(i) != Some(&b'=') {
+            continue;
+        }
+        i += 1;
+        while i < tag.len() && tag[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let quote = *tag.get(i)?;
+        if quote != b'"' && quote != b'\'' {
+            continue;
+        }
+        let v0 = i + 1;
+        let v1 = v0 + tag[v0..].iter().position(|&b| b == quote)?;
+        return Some(v0..v1);
+    }
+    None
+}
+
+/// Значение атрибута `name` в открывающем теге (без раскрытия сущностей).
+pub(crate) fn get_attr(tag: &[u8], name: &str) -> Option<String> {
+    attr_value_range(tag, name).map(|r| String::from_utf8_lossy(&tag[r]).into_owned())
+}
+
+/// Ставит (`Some`) или убирает (`None`) атрибут в открывающем теге `tag`,
+/// не трогая остальное форматирование. `value` должен быть уже экранирован.
+pub(crate) fn set_attr(tag: &mut Vec<u8>, name: &str, value: Option<&str>) {
+    match (attr_value_range(tag, name), value) {
+        (Some(r), Some(v)) => {
+            tag.splice(r, v.bytes());
+        }
+        (Some(r), None) => {
+            // ` name="value"` целиком, вместе с пробелом перед именем
+            let mut start = r.start;
+            while start > 0 && !tag[start - 1].is_ascii_whitespace() {
+                start -= 1;
+            }
+            start = start.saturating_sub(1);
+            tag.splice(start..r.end + 1, std::iter::empty());
+        }
+        (None, Some(v)) => {
+            let mut end = tag.len() - 1; // '>'
+            if end > 0 && tag[end - 1] == b'/' {
+                end -= 1;
+            }
+            while end > 0 && tag[end - 1].is_ascii_whitespace() {
+                end -= 1;
+            }
+            tag.splice(end..end, format!(r#" {name}="{v}""#).bytes());
+        }
+        (None, None) => {}
+    }
+}
+
+/// Диапазон открывающего тега корневого элемента (пропуская `<?xml…?>` и комментарии).
+pub(crate) fn root_tag_range(xml: &[u8]) -> Option<Range<usize>> {
+    let mut from = 0;
+    loop {
+        let p = from + memchr::memchr(b'<', &xml[from..])?;
+        if matches!(xml.get(p + 1), Some(b'?' | b'!')) {
+            from = p + 1;
+            continue;
+        }
+        let end = p + memchr::memchr(b'>', &xml[p..])?;
+        return Some(p..end + 1);
+    }
+}
+
+/// Префикс пространства имён корневого элемента (`"x:"` или `""`).
+pub(crate) fn root_prefix(xml: &[u8]) -> String {
+    root_tag_range(xml)
+        .map(|r| tag_prefix(&xml[r]))
+        .unwrap_or_default()
+}
+
+/// Объявляет `xmlns:{prefix}="{uri}"` на корневом элементе, если его там нет.
+pub(crate) fn ensure_root_ns(xml: &mut Vec<u8>, prefix: &str, uri: &str) -> Result<()> {
+    let r = root_tag_range(xml).context("root element not found")?;
+    let attr = format!("xmlns:{prefix}");
+    if get_attr(&xml[r.clone()], &attr).is_none() {
+        let mut tag = xml[r.clone()].to_vec();
+        set_attr(&mut tag, &attr, Some(uri));
+        xml.splice(r, tag);
+    }
+    Ok(())
+}
+
+/// Находит первое вхождение элемента `<qname …>` (с проверкой границы имени)
+/// в `xml[from..]` и возвращает его полный диапазон, включая содержимое.
+/// Теги внутри комментариев, PI и CDATA не в счёт (см. [`find_markup`]).
+pub(crate) fn find_elem(xml: &[u8], qname: &str, from: usize) -> Option<Range<usize>> {
+    match qname {
+        // строки и ячейки ищутся в цикле по всему листу — готовыми искателями
+        "row" => find_elem_with(xml, &ROW_OPEN, &ROW_CLOSE, from),
+        "c" => find_elem_with(xml, &CELL_OPEN, &CELL_CLOSE, from),
+        _ => {
+            let (open, close) = (format!("<{qname}"), format!("</{qname}>"));
+            find_elem_with(xml, &Finder::new(&open), &Finder::new(&close), from)
+        }
+    }
+}
+
+fn find_elem_with(xml: &[u8], open: &Finder, close: &Finder, from: usize) -> Option<Range<usize>> {
+    let mut i = from;
+    loop {
+        let p = find_markup(open, xml, i)?;
+        let after = p + open.needle().len();
+        match xml.get(after) {
+            Some(b'>' | b'/' | b' ' | b'\t' | b'\r' | b'\n') => {
+                let gt = after + memchr::memchr(b'>', &xml[after..])?;
+                if xml[gt - 1] == b'/' {
+                    return Some(p..gt + 1);
+                }
+                let c = find_markup(close, xml, gt + 1)?;
+                return Some(p..c + close.needle().len());
+            }
+            _ => i = after,
+        }
+    }
+}
+
+/// Удаляет все элементы `<qname …/>` / `<qname …>…</qname>` из `xml`.
+pub(crate) fn remove_elems(xml: &mut Vec<u8>, qname: &str) {
+    let mut from = 0;
+    while let Some(r) = find_elem(xml, qname, from) {
+        from = r.start;
+        xml.splice(r, std::iter::empty());
+    }
+}
+
+/// Префикс пространства имён в теге (`"x:"` для `<x:sheetViews>`, иначе `""`).
+pub(crate) fn tag_prefix(tag: &[u8]) -> String {
+    let end = tag
+        .iter()
+        .position(|&b| b == b'>' || b == b'/' || b.is_ascii_whitespace())
+        .unwrap_or(tag.len());
+    let name = &tag[1.min(end)..end];
+    match name.iter().position(|&b| b == b':') {
+        Some(c) => String::from_utf8_lossy(&name[..=c]).into_owned(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,1133 @@
+//! sheets_part.rs – управление листами книги: переименование, удаление,
+//! копирование, порядок и видимость; свойства листа (`<sheetPr>`,
+//! `<sheetFormatPr>`).
+
+use crate::XlsxEditor;
+use crate::conditional::argb;
+use crate::error::{XlsxError, bail};
+use crate::files_part::xml_escape;
+use crate::formula::rename_sheet_refs;
+use crate::registry_part::PartRegistry;
+use crate::rels_part::{NS_R, PartRels, Rel};
+use crate::sheet_xml::{
+    find_child, find_elem, find_wb_child, get_attr, insert_child, insert_wb_child, root_prefix,
+    refresh_dimension, refresh_row_spans, root_tag_range, set_attr, tag_prefix, worksheet_children,
+};
+use crate::structure_part::{rewrite_elems, rewrite_tags, rewrite_texts};
+use crate::table_part::parse_range;
+use anyhow::{Context, Result};
+use quick_xml::escape::{escape, unescape};
+use quick_xml::{Reader, events::Event};
+use std::ops::Range;
+use std::sync::Arc;
+use std::{fmt, str::FromStr};
+
+/// Части, которые принадлежат листу и удаляются/копируются вместе с ним
+/// (по последнему сегменту типа связи). Картинки и кэши сводных таблиц
+/// бывают общими, их не трогаем.
+const OWNED_PARTS: [&str; 9] = [
+    "table",
+    "comments",
+    "vmlDrawing",
+    "drawing",
+    "chart",
+    "chartStyle",
+    "chartColorStyle",
+    "threadedComment",
+    "printerSettings",
+];
+
+/// Связи листа, на которые в его XML нет `r:id`.
+const IMPLICIT_RELS: [&str; 4] = ["comments", "threadedComment", "pivotTable", "queryTable"];
+
+/// Элементы листа, которые ссылаются на связь через `r:id`; без связи они
+/// удаляются из листа (у `pageSetup` и `hyperlink` с `location` — только атрибут).
+const REL_REF_ELEMS: [&str; 7] = [
+    "drawing",
+    "legacyDrawing",
+    "legacyDrawingHF",
+    "picture",
+    "tablePart",
+    "hyperlink",
+    "pageSetup",
+];
+
+/// Элементы листа, которые [`XlsxEditor::duplicate_sheet_as_template`]
+/// переносит в заготовку.
+const TEMPLATE_PARTS: [&str; 12] = [
+    "sheetPr",
+    "dimension",
+    "sheetViews",
+    "sheetFormatPr",
+    "cols",
+    "sheetData",
+    "mergeCells",
+    "printOptions",
+    "pageMargins",
+    "pageSetup",
+    "headerFooter",
+    "colBreaks",
+];
+
+/// Видимость листа (атрибут `state` в `<sheet>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SheetVisibility {
+    #[default]
+    Visible,
+    /// Скрыт, пользователь может показать его через «Показать».
+    Hidden,
+    /// Скрыт так, что вернуть можно только из VBA или программно.
+    VeryHidden,
+}
+impl fmt::Display for SheetVisibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SheetVisibility::Visible => "visible",
+            SheetVisibility::Hidden => "hidden",
+            SheetVisibility::VeryHidden => "veryHidden",
+        })
+    }
+}
+impl FromStr for SheetVisibility {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        Ok(match s {
+            "visible" => SheetVisibility::Visible,
+            "hidden" => SheetVisibility::Hidden,
+            "veryHidden" => SheetVisibility::VeryHidden,
+            _ => bail!("Unknown sheet visibility: {s}"),
+        })
+    }
+}
+
+/// Порядок дочерних элементов `<sheetPr>` (CT_SheetPr).
+const SHEET_PR_ORDER: [&str; 3] = ["tabColor", "outlinePr", "pageSetUpPr"];
+
+/// Свойства листа для [`XlsxEditor::set_sheet_properties`].
+/// `None` — оставить как есть в файле.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SheetProperties {
+    /// Ширина столбцов по умолчанию (в символах), 0..=255.
+    pub default_col_width: Option<f64>,
+    /// Высота строк по умолчанию (в пунктах), 0..=409.
+    pub default_row_height: Option<f64>,
+    /// Итоговая строка группы под детальными строками (по умолчанию в Excel — да).
+    pub summary_below: Option<bool>,
+    /// Итоговый столбец группы справа от детальных (по умолчанию в Excel — да).
+    pub summary_right: Option<bool>,
+}
+
+/// `<sheet>` из workbook.xml.
+struct SheetTag {
+    range: Range<usize>,
+    name: String,
+    rid_attr: String,
+    rid: String,
+    hidden: bool,
+}
+
+/// `<sheet>`-элементы книги по порядку.
+fn sheet_tags(wb: &[u8]) -> Result<Vec<SheetTag>> {
+    let block = find_wb_child(wb, "sheets")?.context("<sheets> not found in workbook.xml")?;
+    let q = format!("{}sheet", tag_prefix(&wb[block.clone()]));
+    let mut out = Vec::new();
+    let mut from = block.start + 1;
+    while let Some(r) = find_elem(&wb[..block.end], &q, from) {
+        from = r.end;
+        let mut tag = SheetTag {
+            range: r.clone(),
+            name: String::new(),
+            rid_attr: "r:id".to_owned(),
+            rid: String::new(),
+            hidden: false,
+        };
+        let mut rdr = Reader::from_reader(&wb[r]);
+        if let Event::Empty(e) | Event::Start(e) = rdr.read_event()? {
+            for a in e.attributes().with_checks(false).flatten() {
+                let v = a.unescape_value()?.into_owned();
+                match a.key.as_ref() {
+                    b"name" => tag.name = v,
+                    b"state" => tag.hidden = v != "visible",
+                    k if k.ends_with(b":id") => {
+                        tag.rid_attr = String::from_utf8_lossy(k).into_owned();
+                        tag.rid = v;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        out.push(tag);
+    }
+    Ok(out)
+}
+
+/// Имена листов сравниваются без учёта регистра.
+fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+fn sheet_pos(tags: &[SheetTag], name: &str) -> Result<usize> {
+    tags.iter()
+        .position(|t| same_name(&t.name, name))
+        .ok_or_else(|| XlsxError::SheetNotFound(name.to_owned()).into())
+}
+
+/// Правила Excel: 1–31 символ, без `[]:*?/\`, не начинается и не кончается `'`.
+fn validate_sheet_name(name: &str) -> Result<()> {
+    let len = name.chars().count();
+    if len == 0 || len > 31 {
+        bail!("sheet name must be 1 to 31 characters long: `{name}`");
+    }
+    if let Some(c) = name.chars().find(|c| "[]:*?/\\".contains(*c)) {
+        bail!("sheet name `{name}` contains forbidden character `{c}`");
+    }
+    if name.starts_with('\'') || name.ends_with('\'') {
+        bail!("sheet name `{name}` must not start or end with an apostrophe");
+    }
+    if same_name(name, "History") {
+        bail!("`History` is a reserved sheet name");
+    }
+    Ok(())
+}
+
+
+/// Связь указывает на часть, принадлежащую только этой части (копируется и удаляется вместе с ней).
+fn owned(rel: &Rel) -> bool {
+    !rel.external && OWNED_PARTS.contains(&rel.kind())
+}
+
+/// Есть ли в `xml` атрибут с префиксом (`r:id`, `r:embed`, …) со значением `id`.
+fn references(xml: &[u8], id: &str) -> bool {
+    let needle = format!(r#"="{id}""#);
+    let mut from = 0;
+    while let Some(p) = memchr::memmem::find(&xml[from..], needle.as_bytes()).map(|p| p + from) {
+        from = p + needle.len();
+        let name_start = xml[..p]
+            .iter()
+            .rposition(|b| b.is_ascii_whitespace())
+            .map_or(0, |s| s + 1);
+        if xml[name_start..p].contains(&b':') {
+            return true;
+        }
+    }
+    false
+}
+
+/// Убирает из листа ссылки на связи, которых нет в `rels`. `true` — лист изменён.
+fn strip_dangling_refs(xml: &mut Vec<u8>, rels: &PartRels) -> Result<bool> {
+    let Some(root) = root_tag_range(xml) else {
+        return Ok(false);
+    };
+    // префикс пространства имён связей, объявленный на корне (обычно `r`)
+    let root_tag = String::from_utf8_lossy(&xml[root]).into_owned();
+    let Some(rp) = root_tag.split_whitespace().find_map(|a| {
+        let (name, value) = a.split_once('=')?;
+        let value = value.trim_end_matches(['>', '/']).trim_matches('"');
+        (value == NS_R).then(|| name.strip_prefix("xmlns:"))?
+    }) else {
+        return Ok(false);
+    };
+    let id_attr = format!("{rp}:id");
+    let p = root_prefix(xml);
+    let before = xml.len();
+    for name in REL_REF_ELEMS {
+        rewrite_tags(xml, &format!("{p}{name}"), |tag| {
+            let Some(id) = get_attr(tag, &id_attr) else {
+                return true;
+            };
+            if rels.get(&id).is_some() {
+                return true;
+            }
+            let keep = match name {
+                "pageSetup" => true,
+                "hyperlink" => get_attr(tag, "location").is_some(),
+                _ => false,
+            };
+            if keep {
+                set_attr(tag, &id_attr, None);
+            }
+            keep
+        });
+    }
+    if xml.len() == before {
+        return Ok(false);
+    }
+    // опустевшие контейнеры
+    for (block, item) in [("tableParts", "tablePart"), ("hyperlinks", "hyperlink")] {
+        let Some(r) = find_child(xml, block)? else {
+            continue;
+        };
+        let mut part = xml[r.clone()].to_vec();
+        let n = rewrite_tags(&mut part, &format!("{p}{item}"), |_| true);
+        if n == 0 {
+            xml.splice(r, std::iter::empty());
+        } else if block == "tableParts" {
+            let gt = part.iter().position(|&b| b == b'>').unwrap_or(0);
+            let mut tag = part[..=gt].to_vec();
+            set_attr(&mut tag, "count", Some(&n.to_string()));
+            part.splice(..=gt, tag);
+            xml.splice(r, part);
+        }
+    }
+    Ok(true)
+}
+
+/// Число строк заголовка: закреплённые строки, иначе первая строка с данными.
+fn header_rows(xml: &[u8]) -> Result<u32> {
+    let p = root_prefix(xml);
+    if let Some(r) = find_elem(xml, &format!("{p}pane"), 0) {
+        let tag = &xml[r];
+        let frozen = matches!(
+            get_attr(tag, "state").as_deref(),
+            Some("frozen" | "frozenSplit")
+        );
+        let rows = get_attr(tag, "ySplit").and_then(|v| v.parse::<f64>().ok());
+        if let Some(rows) = rows.filter(|&n| frozen && n >= 1.0) {
+            return Ok(rows as u32);
+        }
+    }
+    let Some(sd) = find_child(xml, "sheetData")? else {
+        return Ok(0);
+    };
+    Ok(find_elem(&xml[sd.clone()], &format!("{p}row"), 0)
+        .and_then(|r| get_attr(&xml[sd.start + r.start..sd.start + r.end], "r"))
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(0))
+}
+
+/// Префикс пространства имён корневого элемента.
+/// Применяет `f` ко всем формулам листа: ячейки, условное форматирование,
+/// проверка данных (и их x14-варианты), ссылки гиперссылок на место в книге.
+fn rewrite_sheet_formulas(xml: &mut Vec<u8>, f: &dyn Fn(&str) -> String) {
+    let p = root_prefix(xml);
+    for q in ["f", "formula", "formula1", "formula2"] {
+        rewrite_texts(xml, &format!("{p}{q}"), f);
+    }
+    rewrite_texts(xml, "xm:f", f);
+    rewrite_elems(xml, &format!("{p}hyperlink"), "location", |v| {
+        let plain = unescape(v).ok()?;
+        Some(escape(f(&plain)).into_owned())
+    });
+}
+
+impl XlsxEditor {
+    /// Переименовывает лист; ссылки на него в формулах всех листов, именах,
+    /// диаграммах и источниках сводных таблиц переписываются.
+    pub fn rename_worksheet(&mut self, old: &str, new: &str) -> Result<&mut Self, XlsxError> {
+        validate_sheet_name(new)?;
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let idx = sheet_pos(&tags, old)?;
+        if tags
+            .iter()
+            .enumerate()
+            .any(|(i, t)| i != idx && same_name(&t.name, new))
+        {
+            bail!("Sheet {new} already exists");
+        }
+        let old = tags[idx].name.clone();
+        self.edit_sheet_tag(idx, |tag| set_attr(tag, "name", Some(&xml_escape(new))))?;
+        self.rewrite_sheet_refs(&old, Some(new))?;
+
+        if let Some(mut app) = self.read_part("docProps/app.xml")? {
+            rewrite_texts(&mut app, "vt:lpstr", |t| {
+                if t == old {
+                    new.to_owned()
+                } else {
+                    t.to_owned()
+                }
+            });
+            self.write_part("docProps/app.xml", app);
+        }
+        Ok(self)
+    }
+
+    /// Удаляет лист вместе с его связями, таблицами, примечаниями и рисунками.
+    /// Ссылки на него в формулах превращаются в `#REF!`, локальные имена
+    /// удаляются, calcChain.xml выбрасывается. Если удаляется текущий лист,
+    /// редактор переключается на первый видимый.
+    pub fn delete_worksheet(&mut self, name: &str) -> Result<&mut Self, XlsxError> {
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let idx = sheet_pos(&tags, name)?;
+        let Some(next) = tags
+            .iter()
+            .enumerate()
+            .find(|(i, t)| *i != idx && !t.hidden)
+            .map(|(_, t)| t.name.clone())
+        else {
+            bail!("cannot delete `{name}`: a workbook needs at least one visible sheet");
+        };
+        let path = self.sheet_entries()?.swap_remove(idx).path;
+        if path == self.sheet_path {
+            self.with_worksheet(&next)?;
+        }
+
+        let tag = &tags[idx];
+        self.workbook_xml
+            .get_mut(&self.src)?
+            .splice(tag.range.clone(), std::iter::empty());
+        let mut wb_rels = self.rels_of("xl/workbook.xml")?;
+        wb_rels.remove(&tag.rid);
+        self.store_rels(wb_rels);
+        self.remove_part_tree(&path)?;
+
+        let left = tags.len() - 1;
+        self.remap_sheet_indices(
+            |i| match i.cmp(&idx) {
+                std::cmp::Ordering::Less => Some(i),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some(i - 1),
+            },
+            idx.min(left - 1),
+        )?;
+        self.rewrite_sheet_refs(&tag.name, None)?;
+        self.drop_calc_chain()?;
+        self.fix_active_tab()?;
+        Ok(self)
+    }
+
+    /// Копирует лист `src` в новый лист `new_name` в конце книги: XML листа,
+    /// его связи, таблицы (с новыми именами), примечания и рисунки с диаграммами.
+    /// Ссылки копии на сам `src` и локальные имена `src` переносятся на копию.
+    pub fn copy_worksheet(&mut self, src: &str, new_name: &str) -> Result<&mut Self, XlsxError> {
+        validate_sheet_name(new_name)?;
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let idx = sheet_pos(&tags, src)?;
+        if tags.iter().any(|t| same_name(&t.name, new_name)) {
+            bail!("Sheet {new_name} already exists");
+        }
+        let src_name = tags[idx].name.clone();
+        let src_path = self.sheet_entries()?.swap_remove(idx).path;
+        // копия берёт таблицы листа уже выросшими
+        self.flush_appended_ranges()?;
+
+        let mut xml = self.sheet_part(&src_path)?;
+        rewrite_sheet_formulas(&mut xml, &|f| {
+            rename_sheet_refs(f, &src_name, Some(new_name))
+        });
+        let p = root_prefix(&xml);
+        rewrite_tags(&mut xml, &format!("{p}sheetView"), |tag| {
+            set_attr(tag, "tabSelected", None);
+            true
+        });
+
+        let new_path = self.append_sheet(&tags, idx, new_name, xml, |_| true)?;
+        self.copy_rels(&src_path, &new_path)?;
+        self.reconcile_sheet_rels(&new_path)?;
+        Ok(self)
+    }
+
+    /// Создаёт в конце книги лист `new_name` — заготовку по образцу `src_sheet`:
+    /// строки заголовка (закреплённые строки, а без закрепления — первая строка
+    /// с данными) вместе со стилями и объединениями, ширины столбцов, вид листа
+    /// и параметры печати, включая сквозные строки. Данные, таблицы,
+    /// примечания и рисунки не переносятся.
+    pub fn duplicate_sheet_as_template(
+        &mut self,
+        src_sheet: &str,
+        new_name: &str,
+    ) -> Result<&mut Self, XlsxError> {
+        validate_sheet_name(new_name)?;
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let idx = sheet_pos(&tags, src_sheet)?;
+        if tags.iter().any(|t| same_name(&t.name, new_name)) {
+            bail!("Sheet {new_name} already exists");
+        }
+        let src_name = tags[idx].name.clone();
+        let src_path = self.sheet_entries()?.swap_remove(idx).path;
+
+        let mut xml = self.sheet_part(&src_path)?;
+        let header = header_rows(&xml)?;
+        let drop: Vec<Range<usize>> = worksheet_children(&xml)?
+            .into_iter()
+            .filter(|c| !TEMPLATE_PARTS.contains(&c.name.as_str()))
+            .map(|c| c.range)
+            .collect();
+        for r in drop.into_iter().rev() {
+            xml.splice(r, std::iter::empty());
+        }
+        let p = root_prefix(&xml);
+
+        if let Some(sd) = find_child(&xml, "sheetData")? {
+            let mut block = xml[sd.clone()].to_vec();
+            rewrite_tags(&mut block, &format!("{p}row"), |tag| {
+                get_attr(tag, "r")
+                    .and_then(|r| r.parse::<u32>().ok())
+                    .is_some_and(|r| r <= header)
+            });
+            xml.splice(sd, block);
+        }
+        if let Some(mc) = find_child(&xml, "mergeCells")? {
+            let mut block = xml[mc.clone()].to_vec();
+            let kept = rewrite_elems(&mut block, &format!("{p}mergeCell"), "ref", |r| {
+                let (_, _, _, r1) = parse_range(r).ok()?;
+                (r1 <= header).then(|| r.to_owned())
+            });
+            if kept == 0 {
+                block.clear();
+            } else {
+                set_attr(&mut block, "count", Some(&kept.to_string()));
+            }
+            xml.splice(mc, block);
+        }
+        // связи листа не копируются: ни принтера, ни картинок колонтитулов
+        rewrite_tags(&mut xml, &format!("{p}pageSetup"), |tag| {
+            set_attr(tag, "r:id", None);
+            true
+        });
+        rewrite_tags(&mut xml, &format!("{p}sheetView"), |tag| {
+            set_attr(tag, "tabSelected", None);
+            true
+        });
+        refresh_dimension(&mut xml)?;
+        rewrite_sheet_formulas(&mut xml, &|f| {
+            rename_sheet_refs(f, &src_name, Some(new_name))
+        });
+
+        self.append_sheet(&tags, idx, new_name, xml, |n| {
+            n.eq_ignore_ascii_case("_xlnm.Print_Titles")
+        })?;
+        Ok(self)
+    }
+
+    /// Переставляет лист `name` на позицию `index` (0-based; за концом — в конец).
+    pub fn move_worksheet(&mut self, name: &str, index: usize) -> Result<&mut Self, XlsxError> {
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let from = sheet_pos(&tags, name)?;
+        let to = index.min(tags.len() - 1);
+        if from == to {
+            return Ok(self);
+        }
+        let mut order: Vec<usize> = (0..tags.len()).collect();
+        let moved = order.remove(from);
+        order.insert(to, moved);
+
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let inner: Vec<u8> = order
+            .iter()
+            .flat_map(|&i| wb[tags[i].range.clone()].to_vec())
+            .collect();
+        let span = tags[0].range.start..tags[tags.len() - 1].range.end;
+        wb.splice(span, inner);
+
+        let mut new_pos = vec![0; order.len()];
+        for (k, &i) in order.iter().enumerate() {
+            new_pos[i] = k;
+        }
+        self.remap_sheet_indices(|i| new_pos.get(i).copied(), 0)?;
+        Ok(self)
+    }
+
+    /// Скрывает или показывает лист. Последний видимый лист скрыть нельзя.
+    pub fn hide_worksheet(
+        &mut self,
+        name: &str,
+        visibility: SheetVisibility,
+    ) -> Result<&mut Self, XlsxError> {
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let idx = sheet_pos(&tags, name)?;
+        let visible = visibility == SheetVisibility::Visible;
+        if !visible && !tags.iter().enumerate().any(|(i, t)| i != idx && !t.hidden) {
+            bail!("cannot hide `{name}`: a workbook needs at least one visible sheet");
+        }
+        let state = (!visible).then(|| visibility.to_string());
+        self.edit_sheet_tag(idx, |tag| set_attr(tag, "state", state.as_deref()))?;
+        if !visible {
+            let path = self.sheet_entries()?.swap_remove(idx).path;
+            self.set_tab_selected(&path, false)?;
+        }
+        self.fix_active_tab()?;
+        Ok(self)
+    }
+
+    /// Делает лист `name` активным: книга откроется на нём, его ярлычок
+    /// выбран, а выделение с остальных ярлычков снято.
+    pub fn set_active_sheet(&mut self, name: &str) -> Result<&mut Self, XlsxError> {
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let idx = sheet_pos(&tags, name)?;
+        if tags[idx].hidden {
+            bail!("Sheet {name} is hidden and cannot be active");
+        }
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let p = root_prefix(wb);
+        let q = format!("{p}workbookView");
+        if find_elem(wb, &q, 0).is_none() {
+            let block = format!("<{p}bookViews><{q}/></{p}bookViews>");
+            insert_wb_child(wb, "bookViews", block.as_bytes())?;
+        }
+        rewrite_tags(wb, &q, |tag| {
+            set_attr(tag, "activeTab", Some(&idx.to_string()));
+            let first_sheet: Option<usize> =
+                get_attr(tag, "firstSheet").and_then(|v| v.parse().ok());
+            if first_sheet.is_some_and(|f| f > idx) {
+                set_attr(tag, "firstSheet", Some(&idx.to_string()));
+            }
+            true
+        });
+        for (i, e) in self.sheet_entries()?.into_iter().enumerate() {
+            self.set_tab_selected(&e.path, i == idx)?;
+        }
+        Ok(self)
+    }
+
+    /// Цвет ярлычка листа `sheet` (`"FF0000"`, `"#FF0000"` или ARGB).
+    pub fn set_tab_color(&mut self, sheet: &str, rgb: &str) -> Result<&mut Self, XlsxError> {
+        let rgb = argb(rgb)?;
+        self.edit_sheet_pr(sheet, "tabColor", |tag| {
+            for a in ["auto", "indexed", "theme", "tint"] {
+                set_attr(tag, a, None);
+            }
+            set_attr(tag, "rgb", Some(&rgb));
+        })?;
+        Ok(self)
+    }
+
+    /// Меняет свойства листа `sheet`: размеры строк и столбцов по умолчанию
+    /// (`<sheetFormatPr>`) и положение итогов при группировке (`<outlinePr>`).
+    pub fn set_sheet_properties(
+        &mut self,
+        sheet: &str,
+        props: SheetProperties,
+    ) -> Result<&mut Self, XlsxError> {
+        if let Some(w) = props
+            .default_col_width
+            .filter(|w| !(0.0..=255.0).contains(w))
+        {
+            bail!("default_col_width must be within 0..=255, got {w}");
+        }
+        if let Some(h) = props
+            .default_row_height
+            .filter(|h| !(0.0..=409.0).contains(h))
+        {
+            bail!("default_row_height must be within 0..=409, got {h}");
+        }
+        if props.summary_below.is_some() || props.summary_right.is_some() {
+            self.edit_sheet_pr(sheet, "outlinePr", |tag| {
+                // по умолчанию итоги снизу и справа — атрибут нужен только для "0"
+                if let Some(b) = props.summary_below {
+                    set_attr(tag, "summaryBelow", (!b).then_some("0"));
+                }
+                if let Some(r) = props.summary_right {
+                    set_attr(tag, "summaryRight", (!r).then_some("0"));
+                }
+            })?;
+        }
+        if props.default_col_width.is_none() && props.default_row_height.is_none() {
+            return Ok(self);
+        }
+
+        let path = self.sheet_path_of(sheet)?;
+        let mut xml = self.sheet_part(&path)?;
+        let p = root_prefix(&xml);
+        let r = match find_child(&xml, "sheetFormatPr")? {
+            Some(r) => r,
+            None => {
+                let block = format!(r#"<{p}sheetFormatPr defaultRowHeight="15"/>"#);
+                let pos = insert_child(&mut xml, "sheetFormatPr", block.as_bytes())?;
+                pos..pos + block.len()
+            }
+        };
+        let end = r.start + xml[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+        let mut tag = xml[r.start..=end].to_vec();
+        if let Some(w) = props.default_col_width {
+            set_attr(&mut tag, "defaultColWidth", Some(&w.to_string()));
+        }
+        if let Some(h) = props.default_row_height {
+            set_attr(&mut tag, "defaultRowHeight", Some(&h.to_string()));
+            set_attr(&mut tag, "customHeight", Some("1"));
+        }
+        xml.splice(r.start..=end, tag);
+        self.store_sheet_part(&path, xml);
+        Ok(self)
+    }
+
+    /* ----------------------------- helpers ------------------------------ */
+
+    /// XML листа `path` с учётом правок (текущий лист — из `sheet_xml`).
+    pub(crate) fn sheet_part(&mut self, path: &str) -> Result<Vec<u8>> {
+        if path == self.sheet_path {
+            return Ok(self.sheet_xml.clone());
+        }
+        self.read_part(path)?
+            .with_context(|| format!("{path} not found"))
+    }
+
+    /// Правка XML листа `path` без копии буфера: текущий или отложенный в
+    /// `new_files` лист вынимается на время `f` и возвращается на место;
+    /// лист, которого нет среди правок, сохраняется, только если `f` его
+    /// изменила (второй элемент результата). `f` не должна трогать сам лист.
+    pub(crate) fn edit_sheet_part<T>(
+        &mut self,
+        path: &str,
+        f: impl FnOnce(&mut Self, &mut Vec<u8>) -> Result<(T, bool)>,
+    ) -> Result<T> {
+        let held = path == self.sheet_path || self.new_files.iter().any(|(p, _)| p == path);
+        let mut xml = if path == self.sheet_path {
+            self.sheet_xml.take()
+        } else if let Some((_, c)) = self.new_files.iter_mut().find(|(p, _)| p == path) {
+            Arc::unwrap_or_clone(std::mem::take(c))
+        } else {
+            self.sheet_part(path)?
+        };
+        let res = f(self, &mut xml);
+        if held || matches!(res, Ok((_, true))) {
+            self.store_sheet_part(path, xml);
+        }
+        res.map(|(v, _)| v)
+    }
+
+    pub(crate) fn store_sheet_part(&mut self, path: &str, xml: Vec<u8>) {
+        if path == self.sheet_path {
+            self.sheet_xml = xml.into();
+        } else {
+            self.write_part(path, xml);
+        }
+    }
+
+    pub(crate) fn sheet_path_of(&self, sheet: &str) -> Result<String> {
+        self.sheet_entries()?
+            .into_iter()
+            .find(|e| same_name(&e.name, sheet))
+            .map(|e| e.path)
+            .ok_or_else(|| XlsxError::SheetNotFound(sheet.to_owned()).into())
+    }
+
+    /// Правит открывающий тег элемента `name` внутри `<sheetPr>` листа `sheet`,
+    /// создавая недостающие `<sheetPr>` и сам элемент на своём месте.
+    pub(crate) fn edit_sheet_pr(
+        &mut self,
+        sheet: &str,
+        name: &str,
+        f: impl FnOnce(&mut Vec<u8>),
+    ) -> Result<()> {
+        let path = self.sheet_path_of(sheet)?;
+        let mut xml = self.sheet_part(&path)?;
+        let p = root_prefix(&xml);
+        let mut pr = match find_child(&xml, "sheetPr")? {
+            Some(r) => r,
+            None => {
+                let block = format!("<{p}sheetPr/>");
+                let pos = insert_child(&mut xml, "sheetPr", block.as_bytes())?;
+                pos..pos + block.len()
+            }
+        };
+        // <sheetPr/> → <sheetPr></sheetPr>
+        if xml[pr.end - 2] == b'/' {
+            let open = xml[pr.start..pr.end - 2].trim_ascii_end().to_vec();
+            let block = [open.as_slice(), b">", format!("</{p}sheetPr>").as_bytes()].concat();
+            let len = block.len();
+            xml.splice(pr.clone(), block);
+            pr = pr.start..pr.start + len;
+        }
+        let gt = pr.start + xml[pr.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+        let inner = gt + 1..pr.end - format!("</{p}sheetPr>").len();
+
+        let q = format!("{p}{name}");
+        let existing = find_elem(&xml[..inner.end], &q, inner.start);
+        let (at, mut tag, old_len) = match existing {
+            Some(r) => {
+                let end = r.start + xml[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+                (r.start, xml[r.start..=end].to_vec(), end + 1 - r.start)
+            }
+            None => {
+                // перед первым элементом, который по схеме идёт позже
+                let rank = SHEET_PR_ORDER.iter().position(|n| *n == name).unwrap_or(0);
+                let at = SHEET_PR_ORDER[rank + 1..]
+                    .iter()
+                    .filter_map(|n| find_elem(&xml[..inner.end], &format!("{p}{n}"), inner.start))
+                    .map(|r| r.start)
+                    .min()
+                    .unwrap_or(inner.end);
+                (at, format!("<{q}/>").into_bytes(), 0)
+            }
+        };
+        f(&mut tag);
+        xml.splice(at..at + old_len, tag);
+        self.store_sheet_part(&path, xml);
+        Ok(())
+    }
+
+    /// Правит открывающий тег `idx`-го `<sheet>`.
+    fn edit_sheet_tag(&mut self, idx: usize, f: impl FnOnce(&mut Vec<u8>)) -> Result<()> {
+        let tags = sheet_tags(self.workbook_xml.get(&self.src)?)?;
+        let r = tags
+            .get(idx)
+            .context("sheet index out of range")?
+            .range
+            .clone();
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let end = r.start + wb[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+        let mut tag = wb[r.start..=end].to_vec();
+        f(&mut tag);
+        wb.splice(r.start..=end, tag);
+        Ok(())
+    }
+
+    /// Переименовывает (`Some`) или обрывает в `#REF!` (`None`) ссылки на лист
+    /// `old` по всей книге.
+    fn rewrite_sheet_refs(&mut self, old: &str, new: Option<&str>) -> Result<()> {
+        let f = |s: &str| rename_sheet_refs(s, old, new);
+        for entry in self.sheet_entries()? {
+            let mut xml = self.sheet_part(&entry.path)?;
+            let before = xml.clone();
+            rewrite_sheet_formulas(&mut xml, &f);
+            if xml != before {
+                self.store_sheet_part(&entry.path, xml);
+            }
+        }
+
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let p = root_prefix(wb);
+        rewrite_texts(wb, &format!("{p}definedName"), f);
+
+        for path in self.part_names()? {
+            let chart = path.starts_with("xl/charts/chart") && path.ends_with(".xml");
+            let pivot = path.starts_with("xl/pivotCache/pivotCacheDefinition");
+            if !(chart || pivot) {
+                continue;
+            }
+            let Some(mut xml) = self.read_part(&path)? else {
+                continue;
+            };
+            let before = xml.clone();
+            if chart {
+                rewrite_texts(&mut xml, "c:f", f);
+            } else if let Some(new) = new {
+                rewrite_elems(&mut xml, "worksheetSource", "sheet", |v| {
+                    let name = unescape(v).ok()?;
+                    Some(if same_name(&name, old) {
+                        xml_escape(new)
+                    } else {
+                        v.to_owned()
+                    })
+                });
+            }
+            if xml != before {
+                self.write_part(&path, xml);
+            }
+        }
+        Ok(())
+    }
+
+    /// Перенумеровывает ссылки на позиции листов: `localSheetId` имён (`None` —
+    /// имя удаляется) и `activeTab`/`firstSheet` (`None` — `fallback`).
+    fn remap_sheet_indices(
+        &mut self,
+        map: impl Fn(usize) -> Option<usize>,
+        fallback: usize,
+    ) -> Result<()> {
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let p = root_prefix(wb);
+        if let Some(block) = find_wb_child(wb, "definedNames")? {
+            let mut names = wb[block.clone()].to_vec();
+            let kept = rewrite_tags(&mut names, &format!("{p}definedName"), |tag| {
+                let Some(i) = get_attr(tag, "localSheetId").and_then(|v| v.parse().ok()) else {
+                    return true;
+                };
+                match map(i) {
+                    Some(j) => {
+                        set_attr(tag, "localSheetId", Some(&j.to_string()));
+                        true
+                    }
+                    None => false,
+                }
+            });
+            if kept == 0 {
+                names.clear();
+            }
+            wb.splice(block, names);
+        }
+        rewrite_tags(wb, &format!("{p}workbookView"), |tag| {
+            for a in ["activeTab", "firstSheet"] {
+                if let Some(i) = get_attr(tag, a).and_then(|v| v.parse().ok()) {
+                    let j = map(i).unwrap_or(fallback);
+                    set_attr(tag, a, Some(&j.to_string()));
+                }
+            }
+            true
+        });
+        Ok(())
+    }
+
+    /// Если активная вкладка скрыта или вне диапазона, делает активной
+    /// первую видимую и выделяет её.
+    fn fix_active_tab(&mut self) -> Result<()> {
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let tags = sheet_tags(wb)?;
+        let p = root_prefix(wb);
+        let q = format!("{p}workbookView");
+        let Some(view) = find_elem(wb, &q, 0) else {
+            return Ok(());
+        };
+        let active: usize = get_attr(&wb[view], "activeTab")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        if tags.get(active).is_some_and(|t| !t.hidden) {
+            return Ok(());
+        }
+        let Some(first) = tags.iter().position(|t| !t.hidden) else {
+            return Ok(());
+        };
+        rewrite_tags(wb, &q, |tag| {
+            set_attr(tag, "activeTab", Some(&first.to_string()));
+            let first_sheet: Option<usize> =
+                get_attr(tag, "firstSheet").and_then(|v| v.parse().ok());
+            if first_sheet.is_some_and(|f| f > first) {
+                set_attr(tag, "firstSheet", Some(&first.to_string()));
+            }
+            true
+        });
+        let path = self.sheet_entries()?.swap_remove(first).path;
+        self.set_tab_selected(&path, true)
+    }
+
+    fn set_tab_selected(&mut self, path: &str, selected: bool) -> Result<()> {
+        let mut xml = self.sheet_part(path)?;
+        let p = root_prefix(&xml);
+        if selected && find_child(&xml, "sheetViews")?.is_none() {
+            let block = format!(
+                r#"<{p}sheetViews><{p}sheetView tabSelected="1" workbookViewId="0"/></{p}sheetViews>"#
+            );
+            insert_child(&mut xml, "sheetViews", block.as_bytes())?;
+        }
+        let before = xml.len();
+        rewrite_tags(&mut xml, &format!("{p}sheetView"), |tag| {
+            set_attr(tag, "tabSelected", selected.then_some("1"));
+            true
+        });
+        // нетронутые листы не попадают в new_files
+        if xml.len() != before || selected {
+            self.store_sheet_part(path, xml);
+        }
+        Ok(())
+    }
+
+    /// Добавляет `xml` в конец книги как лист `new_name` и переносит на него
+    /// локальные имена листа `idx`, имя которых проходит `keep`. Возвращает путь части.
+    fn append_sheet(
+        &mut self,
+        tags: &[SheetTag],
+        idx: usize,
+        new_name: &str,
+        xml: Vec<u8>,
+        keep: impl Fn(&str) -> bool,
+    ) -> Result<String> {
+        let (new_path, rid) = self.create_part("xl/workbook.xml", "worksheet", xml)?;
+
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let max_id = tags
+            .iter()
+            .filter_map(|t| get_attr(&wb[t.range.clone()], "sheetId"))
+            .filter_map(|v| v.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+        let last = tags.last().context("workbook has no sheets")?;
+        let q = tag_prefix(&wb[last.range.clone()]);
+        let tag = format!(
+            r#"<{q}sheet name="{}" sheetId="{}" {}="{rid}"/>"#,
+            xml_escape(new_name),
+            max_id + 1,
+            last.rid_attr
+        );
+        let at = last.range.end;
+        wb.splice(at..at, tag.into_bytes());
+
+        // локальные имена (область печати, фильтр и т.п.)
+        let src_name = &tags[idx].name;
+        for (name, value, hidden) in self.local_names(idx)? {
+            if !keep(&name) {
+                continue;
+            }
+            let value = rename_sheet_refs(&value, src_name, Some(new_name));
+            self.set_defined_name(&name, Some(tags.len()), Some(&value), hidden)?;
+        }
+        self.ensure_code_name(&new_path)?;
+        Ok(new_path)
+    }
+
+    /// Локальные имена листа `idx`: `(имя, формула, hidden)`.
+    fn local_names(&self, idx: usize) -> Result<Vec<(String, String, bool)>> {
+        let wb = self.workbook_xml.get(&self.src)?;
+        let Some(block) = find_wb_child(wb, "definedNames")? else {
+            return Ok(Vec::new());
+        };
+        let q = format!("{}definedName", tag_prefix(&wb[block.clone()]));
+        let close = format!("</{q}>");
+        let mut out = Vec::new();
+        let mut from = block.start + 1;
+        while let Some(r) = find_elem(&wb[..block.end], &q, from) {
+            from = r.end;
+            let gt = r.start + wb[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+            let tag = &wb[r.start..=gt];
+            if get_attr(tag, "localSheetId").and_then(|v| v.parse().ok()) != Some(idx)
+                || wb[gt - 1] == b'/'
+            {
+                continue;
+            }
+            let name = unescape(&get_attr(tag, "name").unwrap_or_default())?.into_owned();
+            let body = String::from_utf8_lossy(&wb[gt + 1..r.end - close.len()]);
+            let value = unescape(&body)?.into_owned();
+            out.push((name, value, get_attr(tag, "hidden").as_deref() == Some("1")));
+        }
+        Ok(out)
+    }
+
+    /// Удаляет часть, её `.rels` и все принадлежащие ей части.
+    fn remove_part_tree(&mut self, path: &str) -> Result<()> {
+        let rels = self.rels_of(path)?;
+        for rel in &rels.rels {
+            if owned(rel) {
+                self.remove_part_tree(&rel.target)?;
+            }
+        }
+        if !rels.rels.is_empty() {
+            self.store_rels(PartRels {
+                rels: Vec::new(),
+                ..rels
+            });
+        }
+        self.remove_part(path);
+        self.remove_content_type_override(path)
+    }
+
+    /// Копирует связи части `src` для части `dst`, дублируя принадлежащие части.
+    fn copy_rels(&mut self, src: &str, dst: &str) -> Result<()> {
+        let mut rels = self.rels_of(src)?;
+        for rel in &mut rels.rels {
+            if rel.kind() == "pivotTable" {
+                bail!("copying sheets with pivot tables is not supported");
+            }
+            if owned(rel) {
+                rel.target = self.duplicate_part(&rel.target, rel.kind())?;
+            }
+        }
+        rels.owner = dst.to_owned();
+        self.store_rels(rels);
+        Ok(())
+    }
+
+    /// Включает (`true`) `spans="first:last"` на всех строках изменённых листов
+    /// при save() — в том числе на дописанных. Некоторым читателям (старый Excel,
+    /// часть ETL) атрибут нужен для быстрого разбора. Уже имеющиеся `spans`
+    /// на изменённых листах исправляются всегда.
+    pub fn set_row_spans(&mut self, enabled: bool) -> &mut Self {
+        self.row_spans = enabled;
+        self
+    }
+
+    /// Перед записью книги: текущий лист и все изменённые листы сверяются со
+    /// связями, а их `<dimension>` и `spans` строк пересчитываются по фактическим
+    /// ячейкам (см. [`XlsxEditor::set_row_spans`]), отступы в `<sheetData>` —
+    /// по [`XlsxEditor::set_xml_layout`].
+    pub(crate) fn finish_modified_sheets(&mut self) -> Result<()> {
+        self.flush_appended_ranges()?;
+        // без чтения workbook.xml другие листы не открыть и не завести:
+        // изменён только текущий
+        let paths: Vec<String> = if self.workbook_xml.loaded().is_none() {
+            vec![self.sheet_path.clone()]
+        } else {
+            self.sheet_entries()?
+                .into_iter()
+                .map(|e| e.path)
+                .filter(|p| *p == self.sheet_path || self.new_files.iter().any(|(n, _)| n == p))
+                .collect()
+        };
+        let all_rows = self.row_spans;
+        for path in paths {
+            self.reconcile_sheet_rels(&path)?;
+            self.edit_sheet_part(&path, |ed, xml| {
+                let spans = refresh_row_spans(xml, all_rows)?;
+                let dim = refresh_dimension(xml)?;
+                Ok(((), ed.apply_xml_layout(&path, xml)? || dim || spans))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Сверяет лист `path` с его связями: связи, на которые лист больше не
+    /// ссылается, убираются вместе с принадлежащими им частями (рисунками,
+    /// таблицами…), а элементы листа со ссылкой на несуществующую связь —
+    /// из листа. Неявные связи (примечания, сводные таблицы) не трогаются.
+    pub(crate) fn reconcile_sheet_rels(&mut self, path: &str) -> Result<()> {
+        self.edit_sheet_part(path, |ed, xml| Ok(((), ed.reconcile_rels_with(path, xml)?)))
+    }
+
+    /// [`Self::reconcile_sheet_rels`] над уже вынутым XML листа; `true` —
+    /// из листа убраны ссылки.
+    fn reconcile_rels_with(&mut self, path: &str, xml: &mut Vec<u8>) -> Result<bool> {
+        let mut rels = self.rels_of(path)?;
+
+        let unused: Vec<String> = rels
+            .rels
+            .iter()
+            .filter(|r| !IMPLICIT_RELS.contains(&r.kind()) && !references(xml, &r.id))
+            .map(|r| r.id.clone())
+            .collect();
+        for id in &unused {
+            if let Some(rel) = rels.remove(id)
+                && owned(&rel)
+            {
+                self.remove_part_tree(&rel.target)?;
+            }
+        }
+        if !unused.is_empty() {
+            self.store_rels(rels.clone());
+        }
+
+        strip_dangling_refs(xml, &rels)
+    }
+
+    /// Копия части `path` под свободным именем того же вида (`table3.xml` →
+    /// `table7.xml`); у таблиц меняются `id` и имя. Возвращает путь копии.
+    fn duplicate_part(&mut self, path: &str, kind: &str) -> Result<String> {
+        let mut xml = self
+            .read_part(path)?
+            .with_context(|| format!("{path} not found"))?;
+        let (stem, ext) = path.rsplit_once('.').map_or((path, ""), |(s, e)| (s, e));
+        let stem = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+        let names = self.part_names()?;
+        let new_path = (1..)
+            .map(|n| format!("{stem}{n}.{ext}"))
+            .find(|p| !names.contains(p))
+            .context("no free part name")?;
+
+        if kind == "table" {
+            let all = self.all_tables()?;
+            let id = all.iter().map(|(id, _)| *id).max().unwrap_or(0) + 1;
+            let r = root_tag_range(&xml).context("table root not found")?;
+            let mut tag = xml[r.clone()].to_vec();
+            let base = get_attr(&tag, "displayName")
+                .or_else(|| get_attr(&tag, "name"))
+                .unwrap_or_else(|| "Table".to_owned());
+            let name = (2..)
+                .map(|k| format!("{base}_{k}"))
+                .find(|n| !all.iter().any(|(_, t)| t.eq_ignore_ascii_case(n)))
+                .context("no free table name")?;
+            set_attr(&mut tag, "id", Some(&id.to_string()));
+            set_attr(&mut tag, "name", Some(&name));
+            set_attr(&mut tag, "displayName", Some(&name));
+            xml.splice(r, tag);
+        }
+
+        let ct = self.content_type_override(path)?;
+        self.write_part(&new_path, xml);
+        match ct {
+            Some(ct) => self.add_content_type_override(&new_path, &ct)?,
+            None if PartRegistry::get(kind).is_ok() => self.register_part(&new_path, kind)?,
+            None => {}
+        }
+        self.copy_rels(path, &new_path)?;
+        Ok(new_path)
+    }
+
+    /// Убирает calcChain.xml: после удаления листа он ссылается на несуществующие ячейки.
+    pub(crate) fn drop_calc_chain(&mut self) -> Result<()> {
+        let mut rels = self.rels_of("xl/workbook.xml")?;
+        let Some(id) = rels.of_kind("calcChain").next().map(|r| r.id.clone()) else {
+            return Ok(());
+        };
+        let rel = rels.remove(&id).context("calcChain relationship vanished")?;
+        self.store_rels(rels);
+        self.remove_part(&rel.target);
+        self.remove_content_type_override(&rel.target)
+    }
+}
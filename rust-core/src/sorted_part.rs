@@ -0,0 +1,125 @@
+//! sorted_part.rs – вставка строки на своё место в упорядоченном листе.
+//!
+//! Листы временных рядов держат строки по возрастанию (или убыванию) даты
+//! или числа в одном столбце. [`XlsxEditor::insert_row_sorted`] находит место
+//! новой строки делением пополам по значениям этого столбца и вставляет её
+//! туда через [`XlsxEditor::insert_rows`], со сдвигом всех ссылок листа.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, check_row};
+use crate::read_part::read_cells;
+use crate::sheet_xml::{find_child, open_sheet_data};
+use crate::structure_part::parse_span;
+use crate::template_part::CellValue;
+use anyhow::Result;
+use std::collections::HashMap;
+
+impl XlsxEditor {
+    /// Вставляет строку `cells` (со столбца A, как [`Self::append_values`])
+    /// так, чтобы столбец `column` (`"A"`) остался упорядоченным, и
+    /// возвращает номер новой строки. Порядок — по числам (и датам) в этом
+    /// столбце: по возрастанию или, если первое больше последнего, по
+    /// убыванию; текст вроде заголовка и пустые ячейки не в счёт. Строка
+    /// встаёт после равных значений, а если идёт после всех — сразу под
+    /// последним числом. Строки ниже сдвигаются, как при `insert_rows`, и
+    /// ссылки тоже: строка над первой или под последней строкой диапазона
+    /// формулы в него не попадает.
+    ///
+    /// Ячейка без числового формата берёт стиль той же ячейки соседней
+    /// строки данных (выше, а у первой — ниже): даты остаются датами.
+    /// В столбце `column` новой строки должно быть число; неупорядоченный
+    /// столбец — ошибка, лист тогда не меняется.
+    ///
+    /// ```no_run
+    /// # use rust_core::{XlsxEditor, template_part::CellValue};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Rates")?;
+    /// let day = CellValue::unix_days(19_800.0);
+    /// let row = xl.insert_row_sorted("A", &[(day, None), (CellValue::from(1.07), None)])?;
+    /// # Ok(()) }
+    /// ```
+    pub fn insert_row_sorted(
+        &mut self,
+        column: &str,
+        cells: &[(CellValue, Option<&str>)],
+    ) -> Result<u32, XlsxError> {
+        let (col, last_col) = parse_span(column, true)?;
+        if col != last_col {
+            bail!("expected one column, got {column}");
+        }
+        let key = match cells.get(col as usize) {
+            Some((CellValue::Number(x), _)) if x.is_finite() => *x,
+            Some((v, _)) => bail!("column {column} of the new row needs a number, got {v:?}"),
+            None => bail!("the new row has no value in column {column}"),
+        };
+
+        // числа столбца и стили ячеек по строкам — за один проход
+        let mut keys: Vec<(u32, f64)> = Vec::new();
+        let mut styles: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+        if let Some(sd) = find_child(&self.sheet_xml, "sheetData")? {
+            for c in read_cells(&self.sheet_xml[sd])? {
+                if let Some(s) = c.s {
+                    styles.entry(c.row).or_default().push((c.col, s));
+                }
+                if c.col == col
+                    && matches!(c.t.as_deref(), None | Some("n"))
+                    && let Some(x) = c.v.as_deref().and_then(|v| v.trim().parse::<f64>().ok())
+                {
+                    keys.push((c.row, x));
+                }
+            }
+        }
+        let desc = matches!((keys.first(), keys.last()), (Some(a), Some(b)) if a.1 > b.1);
+        let before = |a: f64, b: f64| if desc { a >= b } else { a <= b };
+        if let Some(w) = keys.windows(2).find(|w| !before(w[0].1, w[1].1)) {
+            bail!("column {column} is not sorted at row {}", w[1].0);
+        }
+        let idx = keys.partition_point(|&(_, x)| before(x, key));
+        let at = match keys.get(idx) {
+            Some(&(row, _)) => row,
+            None => keys.last().map_or(self.last_row, |&(row, _)| row) + 1,
+        };
+        check_row(at.max(self.last_row + 1) as u64)?;
+        let neighbour = idx.checked_sub(1).or((!keys.is_empty()).then_some(0));
+        let like = neighbour
+            .and_then(|i| styles.remove(&keys[i].0))
+            .unwrap_or_default();
+
+        let mut fmt_styles: HashMap<&str, u32> = HashMap::new();
+        let mut xml = Vec::new();
+        for (c, (value, fmt)) in (0u32..).zip(cells) {
+            let s = match fmt {
+                Some(f) => Some(match fmt_styles.get(f) {
+                    Some(&s) => s,
+                    None => {
+                        let s = self.ensure_style(Some(f), None, None, None, None)?;
+                        fmt_styles.insert(f, s);
+                        s
+                    }
+                }),
+                None => like.iter().find(|&&(lc, _)| lc == c).map(|&(_, s)| s),
+            };
+            if *value != CellValue::Empty || s.is_some() {
+                xml.push((c, value.cell_xml(c, at, s)));
+            }
+        }
+
+        let old_last = self.last_row;
+        if at <= old_last {
+            self.insert_rows(at, 1)?;
+        }
+        if !self.sheet_xml.has_edits() {
+            open_sheet_data(&mut self.sheet_xml)?;
+        }
+        for (c, cell) in xml {
+            self.sheet_xml.set_cell(at, c, cell);
+        }
+        if at > old_last {
+            self.last_row = at;
+            let width = cells.len() as u32;
+            let written = (width > 0).then(|| (0, at, width - 1, at));
+            self.grow_ranges_on_append(old_last, written)?;
+        }
+        Ok(at)
+    }
+}
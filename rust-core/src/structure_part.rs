@@ -0,0 +1,506 @@
+//! structure_part.rs – вставка/удаление строк и столбцов со сдвигом всех ссылок листа:
+//! `<row r>`/`<c r>`, формулы, объединения, гиперссылки, условное
+//! форматирование, проверки данных, автофильтр, таблицы и определённые имена.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, check_row};
+use crate::files_part::calc_last_row;
+use crate::formula::{RefEdit, Shift, shift_formula, shift_sqref};
+use crate::sheet_xml::{
+    find_child, find_elem, find_wb_child, get_attr, set_attr, tag_prefix, worksheet_children,
+};
+use crate::style::parse_cell;
+use crate::table_part::parse_range;
+use anyhow::Result;
+use quick_xml::{Reader, escape, events::Event};
+
+const MAX_COL: u32 = 16_384;
+
+impl XlsxEditor {
+    /// Вставляет `count` пустых строк перед строкой `at_row` (1-based).
+    ///
+    /// Строки ниже сдвигаются, а вместе с ними — ссылки в формулах листа
+    /// (относительные и абсолютные), объединения, гиперссылки, условное
+    /// форматирование, проверки данных, автофильтр, таблицы и имена книги,
+    /// указывающие на этот лист. Формулы на других листах не переписываются.
+    pub fn insert_rows(&mut self, at_row: u32, count: u32) -> Result<&mut Self, XlsxError> {
+        if at_row == 0 {
+            bail!("row numbers start at 1");
+        }
+        if count == 0 {
+            return Ok(self);
+        }
+        if self.last_row >= at_row {
+            check_row(self.last_row as u64 + count as u64)?;
+        }
+        let edit = RefEdit {
+            rows: Some(Shift {
+                at: at_row,
+                count,
+                delete: false,
+            }),
+            cols: None,
+        };
+        self.apply_ref_edit(&edit)?;
+        Ok(self)
+    }
+
+    /// Вставляет `count` пустых столбцов перед столбцом `before` (`"C"`).
+    /// Ячейки и ссылки правее получают новые буквы, `<cols>` сдвигаются;
+    /// вставка внутрь таблицы не поддерживается.
+    pub fn insert_columns(&mut self, before: &str, count: u32) -> Result<&mut Self, XlsxError> {
+        let (at, _) = parse_span(before, true)?;
+        if count == 0 {
+            return Ok(self);
+        }
+        self.flush_appended_ranges()?;
+        let used = find_child(&self.sheet_xml, "dimension")?
+            .and_then(|r| root_attr(&self.sheet_xml[r], "ref"))
+            .and_then(|r| parse_range(&r).ok());
+        if used.is_some_and(|(_, _, c1, _)| c1 >= at && c1 + count >= MAX_COL) {
+            bail!("inserting {count} columns would push data past column XFD");
+        }
+        let edit = RefEdit {
+            rows: None,
+            cols: Some(Shift {
+                at,
+                count,
+                delete: false,
+            }),
+        };
+        self.apply_ref_edit(&edit)?;
+        Ok(self)
+    }
+
+    /// Удаляет строки `rows` (`"5"` или `"5:7"`) и сдвигает всё, что ниже, вверх.
+    /// Ссылки на удалённые ячейки превращаются в `#REF!`, объединения и
+    /// диапазоны правил сжимаются или пропадают. См. [`Self::insert_rows`].
+    pub fn delete_rows(&mut self, rows: &str) -> Result<&mut Self, XlsxError> {
+        let (lo, hi) = parse_span(rows, false)?;
+        let edit = RefEdit {
+            rows: Some(Shift {
+                at: lo,
+                count: hi - lo + 1,
+                delete: true,
+            }),
+            cols: None,
+        };
+        self.apply_ref_edit(&edit)?;
+        Ok(self)
+    }
+
+    /// Удаляет столбцы `cols` (`"C"` или `"B:D"`) и сдвигает всё, что правее, влево,
+    /// включая ширины в `<cols>`. Столбцы внутри таблиц удалять нельзя.
+    pub fn delete_columns(&mut self, cols: &str) -> Result<&mut Self, XlsxError> {
+        let (lo, hi) = parse_span(cols, true)?;
+        let edit = RefEdit {
+            rows: None,
+            cols: Some(Shift {
+                at: lo,
+                count: hi - lo + 1,
+                delete: true,
+            }),
+        };
+        self.apply_ref_edit(&edit)?;
+        Ok(self)
+    }
+
+    /// Убирает строки `rows` целиком (значения и оформление строк), не сдвигая остальные:
+    /// на их месте остаётся пустое место, ссылки не меняются.
+    pub fn clear_rows(&mut self, rows: &str) -> Result<&mut Self, XlsxError> {
+        let (lo, hi) = parse_span(rows, false)?;
+        self.flush_appended_ranges()?;
+        if let Some(sd) = find_child(&self.sheet_xml, "sheetData")? {
+            let data = drop_rows(&self.sheet_xml[sd.clone()], lo, hi)?;
+            self.sheet_xml.splice(sd, data);
+        }
+        self.last_row = calc_last_row(&self.sheet_path, &self.sheet_xml)?;
+        Ok(self)
+    }
+
+    /// Применяет структурную правку `edit` к текущему листу и всему, что на него ссылается.
+    pub(crate) fn apply_ref_edit(&mut self, edit: &RefEdit) -> Result<()> {
+        self.flush_appended_ranges()?;
+        let sheet = self.current_sheet_name()?;
+
+        // таблицы проверяем до любых изменений: удалять их целиком не даём
+        let mut tables = Vec::new();
+        if find_child(&self.sheet_xml, "tableParts")?.is_some() {
+            for t in self.sheet_tables()? {
+                let rect = parse_range(&t.range)?;
+                let header_gone = edit
+                    .rows
+                    .is_some_and(|s| s.delete && (s.at..s.at + s.count).contains(&rect.1));
+                // колонки таблицы описаны в её части (<tableColumns>), их не переписываем
+                let columns_hit = edit.cols.is_some_and(|s| {
+                    if s.delete {
+                        s.at <= rect.2 && rect.0 < s.at + s.count
+                    } else {
+                        rect.0 < s.at && s.at <= rect.2
+                    }
+                });
+                if columns_hit {
+                    bail!(
+                        "adding or removing columns inside table {} is not supported",
+                        t.name
+                    );
+                }
+                match edit.map_rect(rect).filter(|_| !header_gone) {
+                    Some(new) if new != rect => tables.push((t.path, new)),
+                    Some(_) => {}
+                    None => bail!("the edit would delete table {} or its header row", t.name),
+                }
+            }
+        }
+
+        if let Some(sd) = find_child(&self.sheet_xml, "sheetData")? {
+            let data = shift_sheet_data(&self.sheet_xml[sd.clone()], edit, &sheet)?;
+            self.sheet_xml.splice(sd, data);
+        }
+
+        let mut drop_filter = false;
+        let children = worksheet_children(&self.sheet_xml)?;
+        for c in children.iter().rev() {
+            let mut block = self.sheet_xml[c.range.clone()].to_vec();
+            let p = tag_prefix(&block);
+            let keep = match c.name.as_str() {
+                "dimension" => {
+                    let r = root_attr(&block, "ref").and_then(|r| shift_sqref(&r, edit));
+                    set_root_attr(&mut block, "ref", Some(r.as_deref().unwrap_or("A1")));
+                    true
+                }
+                "mergeCells" => {
+                    // объединение, сжавшееся до одной ячейки, теряет смысл
+                    let n = rewrite_elems(&mut block, &format!("{p}mergeCell"), "ref", |r| {
+                        shift_sqref(r, edit).filter(|r| r.contains(':'))
+                    });
+                    set_root_attr(&mut block, "count", Some(&n.to_string()));
+                    n > 0
+                }
+                "hyperlinks" => {
+                    rewrite_elems(&mut block, &format!("{p}hyperlink"), "ref", |r| {
+                        shift_sqref(r, edit)
+                    }) > 0
+                }
+                "conditionalFormatting" => {
+                    match root_attr(&block, "sqref").and_then(|r| shift_sqref(&r, edit)) {
+                        Some(sqref) => {
+                            set_root_attr(&mut block, "sqref", Some(&sqref));
+                            rewrite_texts(&mut block, &format!("{p}formula"), |f| {
+                                shift_formula(f, edit, &sheet, true)
+                            });
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                "dataValidations" => {
+                    let n =
+                        rewrite_elems(&mut block, &format!("{p}dataValidation"), "sqref", |r| {
+                            shift_sqref(r, edit)
+                        });
+                    for f in ["formula1", "formula2"] {
+                        rewrite_texts(&mut block, &format!("{p}{f}"), |f| {
+                            shift_formula(f, edit, &sheet, true)
+                        });
+                    }
+                    set_root_attr(&mut block, "count", Some(&n.to_string()));
+                    n > 0
+                }
+                "cols" if edit.cols.is_some() => {
+                    // <col min max> нумеруются с 1
+                    rewrite_tags(&mut block, &format!("{p}col"), |tag| {
+                        let span =
+                            get_attr(tag, "min")
+                                .zip(get_attr(tag, "max"))
+                                .and_then(|(a, b)| {
+                                    Some((a.parse::<u32>().ok()?, b.parse::<u32>().ok()?))
+                                });
+                        let Some((min, max)) = span.filter(|s| s.0 >= 1 && s.0 <= s.1) else {
+                            return true;
+                        };
+                        match edit.map_cols(min - 1, max - 1) {
+                            Some((a, b)) => {
+                                set_attr(tag, "min", Some(&(a + 1).to_string()));
+                                set_attr(tag, "max", Some(&(b + 1).to_string()));
+                                true
+                            }
+                            None => false,
+                        }
+                    }) > 0
+                }
+                "autoFilter" => {
+                    match root_attr(&block, "ref").and_then(|r| shift_sqref(&r, edit)) {
+                        Some(r) => {
+                            set_root_attr(&mut block, "ref", Some(&r));
+                            true
+                        }
+                        None => {
+                            drop_filter = true;
+                            false
+                        }
+                    }
+                }
+                _ => continue,
+            };
+            if keep {
+                self.sheet_xml.splice(c.range.clone(), block);
+            } else {
+                self.sheet_xml.splice(c.range.clone(), std::iter::empty());
+            }
+        }
+
+        for (path, rect) in tables {
+            self.set_table_ref(&path, rect)?;
+        }
+
+        // имена книги, ссылающиеся на этот лист (области печати, _FilterDatabase, …)
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        if let Some(dn) = find_wb_child(wb, "definedNames")? {
+            let mut block = wb[dn.clone()].to_vec();
+            let p = tag_prefix(&block);
+            rewrite_texts(&mut block, &format!("{p}definedName"), |f| {
+                shift_formula(f, edit, &sheet, false)
+            });
+            wb.splice(dn, block);
+        }
+        if drop_filter {
+            let idx = self.current_sheet_index()?;
+            self.set_defined_name("_xlnm._FilterDatabase", Some(idx), None, true)?;
+        }
+
+        self.last_row = calc_last_row(&self.sheet_path, &self.sheet_xml)?;
+        Ok(())
+    }
+}
+
+/// Переписывает строки и ячейки `<sheetData>`: номера, формулы и диапазоны общих формул.
+/// Строки и ячейки, попавшие в удаляемую область, выбрасываются.
+fn shift_sheet_data(xml: &[u8], edit: &RefEdit, sheet: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(xml.len());
+    let mut rdr = Reader::from_reader(xml);
+    rdr.config_mut().check_end_names = false;
+    let mut skip = 0usize; // глубина внутри выбрасываемого элемента
+    let mut formula: Option<usize> = None; // начало текста текущей <f>
+
+    loop {
+        let before = rdr.buffer_position() as usize;
+        let ev = rdr.read_event()?;
+        let after = rdr.buffer_position() as usize;
+        let raw = &xml[before..after];
+        if skip > 0 {
+            match ev {
+                Event::Start(_) => skip += 1,
+                Event::End(_) => skip -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+        match ev {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let empty = matches!(ev, Event::Empty(_));
+                let mut tag = raw.to_vec();
+                match e.local_name().as_ref() {
+                    b"row" => {
+                        if let Some(r) = get_attr(&tag, "r").and_then(|r| r.parse::<u32>().ok()) {
+                            let Some((nr, _)) = edit.map_rows(r, r) else {
+                                skip = usize::from(!empty);
+                                continue;
+                            };
+                            set_attr(&mut tag, "r", Some(&nr.to_string()));
+                        }
+                        // spans — лишь подсказка; после сдвига колонок проще её убрать
+                        if edit.cols.is_some() {
+                            set_attr(&mut tag, "spans", None);
+                        }
+                    }
+                    b"c" => {
+                        if let Some((c, r)) = get_attr(&tag, "r").and_then(|r| parse_cell(&r).ok())
+                        {
+                            let Some((nc, nr, _, _)) = edit.map_rect((c, r, c, r)) else {
+                                skip = usize::from(!empty);
+                                continue;
+                            };
+                            let coord = format!("{}{nr}", crate::style::col_letter(nc));
+                            set_attr(&mut tag, "r", Some(&coord));
+                        }
+                    }
+                    b"f" => {
+                        // диапазон общей/массивной формулы
+                        if let Some(r) = get_attr(&tag, "ref").and_then(|r| shift_sqref(&r, edit)) {
+                            set_attr(&mut tag, "ref", Some(&r));
+                        }
+                        if !empty {
+                            formula = Some(after);
+                        }
+                    }
+                    _ => {}
+                }
+                out.extend_from_slice(&tag);
+            }
+            Event::End(ref e) if e.local_name().as_ref() == b"f" => {
+                if let Some(start) = formula.take() {
+                    out.extend_from_slice(&shift_text(&xml[start..before], |f| {
+                        shift_formula(f, edit, sheet, true)
+                    }));
+                }
+                out.extend_from_slice(raw);
+            }
+            Event::Eof => break,
+            // текст формулы пишется целиком при её закрытии
+            _ if formula.is_some() => {}
+            _ => out.extend_from_slice(raw),
+        }
+    }
+    Ok(out)
+}
+
+/// Выбрасывает из `<sheetData>` строки с номерами `lo..=hi`.
+fn drop_rows(xml: &[u8], lo: u32, hi: u32) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(xml.len());
+    let mut rdr = Reader::from_reader(xml);
+    rdr.config_mut().check_end_names = false;
+    let mut skip = 0usize;
+    loop {
+        let before = rdr.buffer_position() as usize;
+        let ev = rdr.read_event()?;
+        let raw = &xml[before..rdr.buffer_position() as usize];
+        match ev {
+            Event::Eof => break,
+            Event::Start(_) if skip > 0 => skip += 1,
+            Event::End(_) if skip > 0 => skip -= 1,
+            _ if skip > 0 => {}
+            Event::Start(ref e) | Event::Empty(ref e)
+                if e.local_name().as_ref() == b"row"
+                    && get_attr(raw, "r")
+                        .and_then(|r| r.parse::<u32>().ok())
+                        .is_some_and(|r| (lo..=hi).contains(&r)) =>
+            {
+                skip = usize::from(matches!(ev, Event::Start(_)));
+            }
+            _ => out.extend_from_slice(raw),
+        }
+    }
+    Ok(out)
+}
+
+/// `"5:7"` → `(5, 7)`, `"B:D"` → `(1, 3)` (колонки 0-based); одиночное значение — отрезок из одного.
+pub(crate) fn parse_span(spec: &str, columns: bool) -> Result<(u32, u32)> {
+    let one = |s: &str| -> Result<u32> {
+        let s = s.trim().trim_start_matches('$');
+        if columns {
+            if s.is_empty() || !s.bytes().all(|b| b.is_ascii_alphabetic()) {
+                bail!("invalid column: {s}");
+            }
+            Ok(parse_cell(&format!("{s}1"))?.0)
+        } else {
+            match s.parse::<u32>() {
+                Ok(r) if r >= 1 => Ok(r),
+                _ => bail!("invalid row: {s}"),
+            }
+        }
+    };
+    let (a, b) = match spec.split_once(':') {
+        Some((a, b)) => (one(a)?, one(b)?),
+        None => (one(spec)?, one(spec)?),
+    };
+    Ok((a.min(b), a.max(b)))
+}
+
+/// Применяет `f` к экранированному тексту элемента; неизменённый текст возвращается как есть.
+fn shift_text(raw: &[u8], f: impl Fn(&str) -> String) -> Vec<u8> {
+    let text = String::from_utf8_lossy(raw);
+    let Ok(plain) = escape::unescape(&text) else {
+        return raw.to_vec();
+    };
+    let shifted = f(&plain);
+    if shifted == plain {
+        raw.to_vec()
+    } else {
+        escape::partial_escape(&shifted).into_owned().into_bytes()
+    }
+}
+
+/// Переписывает атрибут `attr` у всех элементов `qname` внутри `block`;
+/// если `f` вернула `None`, элемент удаляется. Возвращает число оставшихся элементов.
+pub(crate) fn rewrite_elems(
+    block: &mut Vec<u8>,
+    qname: &str,
+    attr: &str,
+    f: impl Fn(&str) -> Option<String>,
+) -> usize {
+    rewrite_tags(block, qname, |tag| {
+        let Some(old) = get_attr(tag, attr) else {
+            return true;
+        };
+        match f(&old) {
+            Some(v) => {
+                set_attr(tag, attr, Some(&v));
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Правит открывающий тег каждого элемента `qname` внутри `block`;
+/// `false` от `f` удаляет элемент целиком. Возвращает число оставшихся элементов.
+pub(crate) fn rewrite_tags(block: &mut Vec<u8>, qname: &str, f: impl Fn(&mut Vec<u8>) -> bool) -> usize {
+    let mut kept = 0;
+    let mut from = 0;
+    while let Some(r) = find_elem(block, qname, from) {
+        let end = r.start
+            + block[r.clone()]
+                .iter()
+                .position(|&b| b == b'>')
+                .unwrap_or(0);
+        let mut tag = block[r.start..=end].to_vec();
+        if f(&mut tag) {
+            let len = tag.len();
+            block.splice(r.start..=end, tag);
+            kept += 1;
+            from = r.end + len - (end + 1 - r.start);
+        } else {
+            block.splice(r.clone(), std::iter::empty());
+            from = r.start;
+        }
+    }
+    kept
+}
+
+/// Переписывает текст всех элементов `qname` внутри `block` функцией `f`.
+pub(crate) fn rewrite_texts(block: &mut Vec<u8>, qname: &str, f: impl Fn(&str) -> String) {
+    let close = format!("</{qname}>");
+    let mut from = 0;
+    while let Some(r) = find_elem(block, qname, from) {
+        let gt = r.start
+            + block[r.clone()]
+                .iter()
+                .position(|&b| b == b'>')
+                .unwrap_or(0);
+        if block[gt - 1] == b'/' {
+            from = r.end;
+            continue;
+        }
+        let body = gt + 1..r.end - close.len();
+        let new = shift_text(&block[body.clone()], &f);
+        let len = new.len();
+        block.splice(body.clone(), new);
+        from = body.start + len + close.len();
+    }
+}
+
+fn root_attr(block: &[u8], name: &str) -> Option<String> {
+    let end = block.iter().position(|&b| b == b'>')?;
+    get_attr(&block[..=end], name)
+}
+
+fn set_root_attr(block: &mut Vec<u8>, name: &str, value: Option<&str>) {
+    let Some(end) = block.iter().position(|&b| b == b'>') else {
+        return;
+    };
+    let mut tag = block[..=end].to_vec();
+    set_attr(&mut tag, name, value);
+    block.splice(..=end, tag);
+}
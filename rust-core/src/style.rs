@@ -1,13 +1,31 @@
 //! style.rs – универсальный слой стилей + нормализация <cols>
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result};
 use memchr::memmem;
-use quick_xml::{Reader, events::Event};
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
 use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 use std::{fmt, str::FromStr};
 
+use crate::columns_part::MAX_COL;
+use crate::error::{XlsxError, bail, check_row, next_event};
+use crate::files_part::xml_escape;
+use crate::format_part::{BorderSide, BorderSpec, FontSpec, StyleSpec};
+use crate::numfmt::BUILTIN;
+use crate::read_part::read_cells;
+use crate::search::{CELL_CLOSE, ROW_CLOSE, find_from, find_markup};
+use crate::sheet_buf::CellEdit;
+use crate::sheet_xml::{find_child, find_elem, get_attr, open_sheet_data, set_attr};
+use crate::structure_part::parse_span;
+use crate::theme_part::Color;
 use crate::{FontKey, StyleIndex, StyleKey, XfParts, XlsxEditor};
 
+/// Имя части стилей — для сообщений об ошибках разбора.
+const STYLES: &str = "xl/styles.xml";
+
 /* ========================== ALIGNMENT API ================================= */
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -30,8 +48,8 @@ impl fmt::Display for HorizAlignment {
     }
 }
 impl FromStr for HorizAlignment {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self> {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
         Ok(match s {
             "left" => HorizAlignment::Left,
             "center" => HorizAlignment::Center,
@@ -61,8 +79,8 @@ impl fmt::Display for VertAlignment {
     }
 }
 impl FromStr for VertAlignment {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self> {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
         Ok(match s {
             "top" => VertAlignment::Top,
             "center" => VertAlignment::Center,
@@ -89,6 +107,8 @@ struct StyleParts {
     pub fill: Option<u32>,
     pub border: Option<u32>,
     pub align: Option<AlignSpec>,
+    /// `(locked, hidden)` из `<protection>`.
+    pub protection: Option<(bool, bool)>,
 }
 
 /* ========================== TARGET PARSER ================================= */
@@ -139,23 +159,30 @@ impl StyleIndex {
             numfmt_by_code: HashMap::new(),
             next_custom_numfmt: 164,
 
+            fonts: Vec::new(),
             font_by_key: HashMap::new(),
-            fill_by_rgb: HashMap::new(),
+            fill_by_color: HashMap::new(),
             border_by_key: HashMap::new(),
             xf_by_key: HashMap::new(),
+            dxf_by_xml: HashMap::new(),
 
             fonts_count: 0,
             fills_count: 0,
             borders_count: 0,
+            dxfs_count: 0,
         };
 
         let mut rdr = Reader::from_reader(styles);
         rdr.config_mut().trim_text(true);
 
         // --- numFmts ---
+        // Встроенные коды получают свои номера без <numFmt>; коды из файла их перекрывают.
         // Сканируем блок <numFmts> и заполняем карту code->id, заодно поднимаем next_custom_numfmt
+        ix.numfmt_by_code
+            .extend(BUILTIN.iter().map(|&(id, code)| (code.to_owned(), id)));
         let mut max_custom = 163u32;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) | Event::Empty(ref e) if e.name().as_ref() == b"numFmt" => {
                     let mut id = None::<u32>;
@@ -163,7 +190,7 @@ impl StyleIndex {
                     for a in e.attributes().with_checks(false).flatten() {
                         match a.key.as_ref() {
                             b"numFmtId" => id = Some(lexical_core::parse(&a.value)?),
-                            b"formatCode" => code = Some(String::from_utf8_lossy(&a.value).into()),
+                            b"formatCode" => code = Some(a.unescape_value()?.into_owned()),
                             _ => {}
                         }
                     }
@@ -187,7 +214,8 @@ impl StyleIndex {
         let mut in_fonts = false;
         let mut font_id = 0u32;
 
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"fonts" => in_fonts = true,
                 Event::End(ref e) if e.name().as_ref() == b"fonts" => {
@@ -201,9 +229,10 @@ impl StyleIndex {
                     let mut italic = false;
                     let mut size: f32 = 11.0;
                     let mut name: String = "Calibri".into();
+                    let mut color = None;
 
                     while depth > 0 {
-                        match rdr.read_event()? {
+                        match next_event(&mut rdr, STYLES)? {
                             Event::Start(ref fe) => {
                                 depth += 1;
                                 match fe.name().as_ref() {
@@ -226,6 +255,7 @@ impl StyleIndex {
                                             }
                                         }
                                     }
+                                    b"color" => color = Color::from_attrs(fe),
                                     _ => {}
                                 }
                             }
@@ -248,6 +278,7 @@ impl StyleIndex {
                                         }
                                     }
                                 }
+                                b"color" => color = Color::from_attrs(fe),
                                 _ => {}
                             },
                             Event::End(_) => depth -= 1,
@@ -261,7 +292,9 @@ impl StyleIndex {
                         size_100: (size * 100.0).round() as u32,
                         bold,
                         italic,
+                        color: color.map(|c| c.to_string()),
                     };
+                    ix.fonts.push(key.clone());
                     ix.font_by_key.entry(key).or_insert(font_id);
                     font_id += 1;
                 }
@@ -276,7 +309,8 @@ impl StyleIndex {
         rdr.config_mut().trim_text(true);
         let mut in_fills = false;
         let mut fill_id = 0u32;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"fills" => in_fills = true,
                 Event::End(ref e) if e.name().as_ref() == b"fills" => {
@@ -284,35 +318,21 @@ impl StyleIndex {
                     break;
                 }
                 Event::Start(ref e) if in_fills && e.name().as_ref() == b"fill" => {
-                    // считываем <fill> целиком; учитываем только solid fgColor rgb
+                    // считываем <fill> целиком; учитываем только fgColor (rgb или theme)
                     let mut depth = 1;
-                    let mut rgb: Option<String> = None;
+                    let mut color: Option<Color> = None;
 
                     while depth > 0 {
-                        match rdr.read_event()? {
+                        match next_event(&mut rdr, STYLES)? {
                             Event::Start(ref fe) => {
                                 if fe.name().as_ref() == b"fgColor" {
-                                    for a in fe.attributes().with_checks(false).flatten() {
-                                        if a.key.as_ref() == b"rgb" {
-                                            let mut v =
-                                                String::from_utf8_lossy(&a.value).into_owned();
-                                            v.make_ascii_uppercase();
-                                            rgb = Some(v);
-                                        }
-                                    }
+                                    color = Color::from_attrs(fe);
                                 }
                                 depth += 1;
                             }
                             Event::Empty(ref fe) => {
                                 if fe.name().as_ref() == b"fgColor" {
-                                    for a in fe.attributes().with_checks(false).flatten() {
-                                        if a.key.as_ref() == b"rgb" {
-                                            let mut v =
-                                                String::from_utf8_lossy(&a.value).into_owned();
-                                            v.make_ascii_uppercase();
-                                            rgb = Some(v);
-                                        }
-                                    }
+                                    color = Color::from_attrs(fe);
                                 }
                                 // depth не меняем
                             }
@@ -322,8 +342,8 @@ impl StyleIndex {
                         }
                     }
 
-                    if let Some(k) = rgb {
-                        ix.fill_by_rgb.entry(k).or_insert(fill_id);
+                    if let Some(c) = color {
+                        ix.fill_by_color.entry(c.to_string()).or_insert(fill_id);
                     }
                     fill_id += 1;
                 }
@@ -338,7 +358,8 @@ impl StyleIndex {
         rdr.config_mut().trim_text(true);
         let mut in_borders = false;
         let mut border_id = 0u32;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"borders" => in_borders = true,
                 Event::End(ref e) if e.name().as_ref() == b"borders" => {
@@ -348,61 +369,39 @@ impl StyleIndex {
                 Event::Start(ref e) if in_borders && e.name().as_ref() == b"border" => {
                     let mut depth = 1;
                     let mut styles = [None, None, None, None]; // left,right,top,bottom
+                    let mut colors = [None, None, None, None];
+                    let mut side = None; // открытая сторона — для вложенного <color>
 
                     while depth > 0 {
-                        match rdr.read_event()? {
+                        match next_event(&mut rdr, STYLES)? {
                             Event::Start(ref be) => {
-                                let side = match be.name().as_ref() {
-                                    b"left" => Some(0),
-                                    b"right" => Some(1),
-                                    b"top" => Some(2),
-                                    b"bottom" => Some(3),
-                                    _ => None,
-                                };
+                                side = border_side(be.name().as_ref());
                                 if let Some(i) = side {
-                                    for a in be.attributes().with_checks(false).flatten() {
-                                        if a.key.as_ref() == b"style" {
-                                            styles[i] = Some(
-                                                String::from_utf8_lossy(&a.value).into_owned(),
-                                            );
-                                        }
-                                    }
+                                    styles[i] = border_style_attr(be);
                                 }
                                 depth += 1;
                             }
                             Event::Empty(ref be) => {
-                                let side = match be.name().as_ref() {
-                                    b"left" => Some(0),
-                                    b"right" => Some(1),
-                                    b"top" => Some(2),
-                                    b"bottom" => Some(3),
-                                    _ => None,
-                                };
-                                if let Some(i) = side {
-                                    for a in be.attributes().with_checks(false).flatten() {
-                                        if a.key.as_ref() == b"style" {
-                                            styles[i] = Some(
-                                                String::from_utf8_lossy(&a.value).into_owned(),
-                                            );
-                                        }
-                                    }
+                                if let Some(i) = border_side(be.name().as_ref()) {
+                                    styles[i] = border_style_attr(be);
+                                } else if be.name().as_ref() == b"color"
+                                    && let Some(i) = side
+                                {
+                                    colors[i] = Color::from_attrs(be).map(|c| c.to_string());
                                 }
                             }
 
-                            Event::End(_) => depth -= 1,
+                            Event::End(_) => {
+                                side = None;
+                                depth -= 1;
+                            }
                             Event::Eof => break,
                             _ => {}
                         }
                     }
 
-                    // ключ только для «ровных» рамок, иначе пропускаем (мы такие и создаём)
-                    if let (Some(l), Some(r), Some(t), Some(b)) =
-                        (&styles[0], &styles[1], &styles[2], &styles[3])
-                    {
-                        if l == r && r == t && t == b {
-                            ix.border_by_key.entry(l.clone()).or_insert(border_id);
-                        }
-                    }
+                    let key = sides_key(&styles, &colors);
+                    ix.border_by_key.entry(key).or_insert(border_id);
                     border_id += 1;
                 }
                 Event::Eof => break,
@@ -417,7 +416,8 @@ impl StyleIndex {
         let mut in_xfs = false;
         let mut xf_id = 0u32;
 
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = true,
                 Event::End(ref e) if e.name().as_ref() == b"cellXfs" => {
@@ -445,12 +445,22 @@ impl StyleIndex {
                         }
                     }
 
-                    // выцепим alignment (если есть)
+                    // выцепим alignment и protection (если есть)
                     let mut align: Option<AlignSpec> = None;
+                    let mut protection: Option<(bool, bool)> = None;
                     if matches!(ev, Event::Start(_)) {
                         let mut depth = 1;
                         while depth > 0 {
-                            match rdr.read_event()? {
+                            let child = next_event(&mut rdr, STYLES)?;
+                            match child {
+                                Event::Start(ref ae) | Event::Empty(ref ae)
+                                    if ae.name().as_ref() == b"protection" =>
+                                {
+                                    if matches!(child, Event::Start(_)) {
+                                        depth += 1;
+                                    }
+                                    protection = Some(protection_attrs(ae));
+                                }
                                 Event::Start(ref ae) => {
                                     depth += 1;
                                     if ae.name().as_ref() == b"alignment" {
@@ -484,6 +494,7 @@ impl StyleIndex {
                         fill_id,
                         border_id,
                         align: align.clone(),
+                        protection,
                     });
 
                     let sk = StyleKey {
@@ -494,6 +505,7 @@ impl StyleIndex {
                         align: align
                             .as_ref()
                             .map(|a| (a.horiz.clone(), a.vert.clone(), a.wrap)),
+                        protection,
                     };
                     ix.xf_by_key.entry(sk).or_insert(xf_id);
                     xf_id += 1;
@@ -503,6 +515,19 @@ impl StyleIndex {
             }
         }
 
+        // --- dxfs ---
+        // Разностные форматы сравниваются целиком, как их пишет ensure_dxf
+        if let Some(block) = find_elem(styles, "dxfs", 0) {
+            let block = &styles[block];
+            let mut from = 0;
+            while let Some(r) = find_elem(block, "dxf", from) {
+                let dxf = String::from_utf8_lossy(&block[r.clone()]).into_owned();
+                ix.dxf_by_xml.entry(dxf).or_insert(ix.dxfs_count);
+                ix.dxfs_count += 1;
+                from = r.end;
+            }
+        }
+
         Ok(ix)
     }
 }
@@ -510,7 +535,7 @@ impl StyleIndex {
 impl XlsxEditor {
     fn style_ix_mut(&mut self) -> Result<&mut StyleIndex> {
         if self.styles_index.is_none() {
-            let ix = StyleIndex::build(&self.styles_xml)?;
+            let ix = StyleIndex::build(self.styles_xml.get(&self.src)?)?;
             self.styles_index = Some(ix);
         }
         Ok(self.styles_index.as_mut().unwrap())
@@ -524,8 +549,27 @@ impl XlsxEditor {
 /* ========================== PUBLIC API ==================================== */
 
 impl XlsxEditor {
-    pub fn set_border(&mut self, range: &str, border_style: &str) -> Result<&mut Self> {
-        let border_id = self.ensure_border(border_style)?;
+    pub fn set_border(&mut self, range: &str, border_style: &str) -> Result<&mut Self, XlsxError> {
+        let border_id = self.ensure_border(border_style, None)?;
+        self.apply_patch(
+            range,
+            StyleParts {
+                border: Some(border_id),
+                ..Default::default()
+            },
+        )?;
+        Ok(self)
+    }
+
+    /// Рамка `border_style` (`"thin"`, `"medium"`, …) цвета `color` со всех
+    /// сторон каждой ячейки; `color` — RGB или цвет темы, см. [`Color`].
+    pub fn set_border_with_color(
+        &mut self,
+        range: &str,
+        border_style: &str,
+        color: &str,
+    ) -> Result<&mut Self, XlsxError> {
+        let border_id = self.ensure_border(border_style, Some(&color.parse()?))?;
         self.apply_patch(
             range,
             StyleParts {
@@ -543,8 +587,30 @@ impl XlsxEditor {
         size: f32,
         bold: bool,
         italic: bool,
-    ) -> Result<&mut Self> {
-        let font_id = self.ensure_font(name, size, bold, italic)?;
+    ) -> Result<&mut Self, XlsxError> {
+        let font_id = self.ensure_font(name, size, bold, italic, None)?;
+        self.apply_patch(
+            range,
+            StyleParts {
+                font: Some(font_id),
+                ..Default::default()
+            },
+        )?;
+        Ok(self)
+    }
+
+    /// Как [`Self::set_font`], но с цветом шрифта: RGB или цвет темы
+    /// (`"theme:1"` — основной тёмный), см. [`Color`].
+    pub fn set_font_with_color(
+        &mut self,
+        range: &str,
+        name: &str,
+        size: f32,
+        bold: bool,
+        italic: bool,
+        color: &str,
+    ) -> Result<&mut Self, XlsxError> {
+        let font_id = self.ensure_font(name, size, bold, italic, Some(&color.parse()?))?;
         self.apply_patch(
             range,
             StyleParts {
@@ -563,8 +629,8 @@ impl XlsxEditor {
         bold: bool,
         italic: bool,
         align: &AlignSpec,
-    ) -> Result<&mut Self> {
-        let font_id = self.ensure_font(name, size, bold, italic)?;
+    ) -> Result<&mut Self, XlsxError> {
+        let font_id = self.ensure_font(name, size, bold, italic, None)?;
         self.apply_patch(
             range,
             StyleParts {
@@ -576,8 +642,10 @@ impl XlsxEditor {
         Ok(self)
     }
 
-    pub fn set_fill(&mut self, range: &str, rgb: &str) -> Result<&mut Self> {
-        let fill_id = self.ensure_fill(rgb)?;
+    /// Сплошная заливка диапазона. `color` — RGB (`"FFFF00"`) или цвет
+    /// темы (`"theme:4:-0.25"`), см. [`Color`].
+    pub fn set_fill(&mut self, range: &str, color: &str) -> Result<&mut Self, XlsxError> {
+        let fill_id = self.ensure_fill(&color.parse()?)?;
         self.apply_patch(
             range,
             StyleParts {
@@ -588,7 +656,11 @@ impl XlsxEditor {
         Ok(self)
     }
 
-    pub fn set_alignment(&mut self, range: &str, align: &AlignSpec) -> Result<&mut Self> {
+    pub fn set_alignment(
+        &mut self,
+        range: &str,
+        align: &AlignSpec,
+    ) -> Result<&mut Self, XlsxError> {
         self.apply_patch(
             range,
             StyleParts {
@@ -599,8 +671,28 @@ impl XlsxEditor {
         Ok(self)
     }
 
+    /// Флаги защиты ячеек диапазона (`<protection>` в xf): `locked` — ячейку
+    /// нельзя менять на защищённом листе, `hidden` — формула не видна.
+    /// По умолчанию в Excel все ячейки заблокированы; снимите `locked`
+    /// с полей ввода перед [`Self::protect_sheet`].
+    pub fn set_cell_protection(
+        &mut self,
+        range: &str,
+        locked: bool,
+        hidden: bool,
+    ) -> Result<&mut Self, XlsxError> {
+        self.apply_patch(
+            range,
+            StyleParts {
+                protection: Some((locked, hidden)),
+                ..Default::default()
+            },
+        )?;
+        Ok(self)
+    }
+
     /// Публичный API для числового формата.
-    pub fn set_number_format(&mut self, range: &str, fmt: &str) -> Result<()> {
+    pub fn set_number_format(&mut self, range: &str, fmt: &str) -> Result<(), XlsxError> {
         let style_id = self.ensure_style(Some(fmt), None, None, None, None)?;
         match parse_target(range)? {
             Target::Cell(c) => self.apply_style_to_cell(&c, style_id)?,
@@ -613,16 +705,109 @@ impl XlsxEditor {
                 }
             }
             Target::Col(c0) => self.force_column_number_format(c0, style_id)?,
-            Target::Row(_row) => bail!("Row-level not implemented yet"),
+            Target::Row(row) => self.force_row_number_format(row, style_id)?,
         }
         Ok(())
     }
 
-    pub fn set_column_width(&mut self, col_letter: &str, width: f64) -> Result<&mut Self> {
+    /// Оформление листа по умолчанию, например другой шрифт для всего
+    /// листа: `spec` становится стилем всех столбцов без своего стиля
+    /// (`<col style>` — его получают новые ячейки) и всех ячеек без своего `s`.
+    /// Повторный вызов заменяет прежнее оформление по умолчанию.
+    ///
+    /// ```no_run
+    /// # use rust_core::{XlsxEditor, format_part::{FontSpec, StyleSpec}};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Sheet1")?;
+    /// let font = FontSpec { name: "Arial".into(), size: 10.0, ..FontSpec::default() };
+    /// xl.set_sheet_default_style(&StyleSpec { font, ..StyleSpec::default() })?;
+    /// # Ok(()) }
+    /// ```
+    pub fn set_sheet_default_style(&mut self, spec: &StyleSpec) -> Result<&mut Self, XlsxError> {
+        let style_id = self.ensure_style_spec(spec)?;
+        // прежний стиль по умолчанию узнаём по последнему столбцу листа
+        let mut old = None;
+        self.edit_cols(|cols| {
+            old = cols.get(&MAX_COL).and_then(|p| p.style);
+            for col in 1..=MAX_COL {
+                let p = cols.entry(col).or_default();
+                if p.style.is_none() || p.style == old {
+                    p.style = Some(style_id);
+                }
+            }
+        })?;
+        let xml: &mut Vec<u8> = &mut self.sheet_xml;
+        let body = open_sheet_data(xml)?;
+        restyle_cells(xml, body, |_, s| {
+            (s.is_none() || s == old).then_some(style_id)
+        })?;
+        Ok(self)
+    }
+
+    /// Стиль столбцов `cols` (`"B"` или `"B:D"`): один xf на все столбцы,
+    /// `<cols>` нормализуется. С `stamp_cells` стиль получают и уже
+    /// существующие ячейки этих столбцов, иначе — только новые.
+    pub fn set_column_style(
+        &mut self,
+        cols: &str,
+        spec: &StyleSpec,
+        stamp_cells: bool,
+    ) -> Result<&mut Self, XlsxError> {
+        let (lo, hi) = parse_span(cols, true)?;
+        let style_id = self.ensure_style_spec(spec)?;
+        self.edit_col_span(cols, |p| p.style = Some(style_id))?;
+        if stamp_cells {
+            let xml: &mut Vec<u8> = &mut self.sheet_xml;
+            let body = open_sheet_data(xml)?;
+            restyle_cells(xml, body, |c, _| {
+                c.is_some_and(|c| (lo..=hi).contains(&c))
+                    .then_some(style_id)
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// Отметка `bestFit` у столбцов `cols` (`"B:D"`): ширина подобрана по
+    /// содержимому. Саму ширину не меняет — для подбора есть
+    /// [`Self::autofit_columns`].
+    pub fn set_column_best_fit(
+        &mut self,
+        cols: &str,
+        enabled: bool,
+    ) -> Result<&mut Self, XlsxError> {
+        self.edit_col_span(cols, |p| p.best_fit = enabled)?;
+        Ok(self)
+    }
+
+    pub fn set_column_width(
+        &mut self,
+        col_letter: &str,
+        width: f64,
+    ) -> Result<&mut Self, XlsxError> {
         let col0 = col_index(col_letter) as u32; // 0-based
         self.set_column_properties(col0, Some(width), None)?;
         Ok(self)
     }
+
+    /// Высота строк `rows` (`"3"` или `"3:7"`) в пунктах, 0..=409; недостающие
+    /// `<row>` создаются пустыми.
+    pub fn set_row_height(&mut self, rows: &str, height: f64) -> Result<&mut Self, XlsxError> {
+        if !(0.0..=409.0).contains(&height) {
+            bail!("row height must be within 0..=409, got {height}");
+        }
+        let (lo, hi) = parse_span(rows, false)?;
+        self.ensure_rows(lo, hi)?;
+        let ht = height.to_string();
+        self.for_each_row_tag(|r, tag| {
+            (lo..=hi).contains(&r).then(|| {
+                let mut new = tag.to_vec();
+                set_attr(&mut new, "ht", Some(&ht));
+                set_attr(&mut new, "customHeight", Some("1"));
+                new
+            })
+        })?;
+        Ok(self)
+    }
 }
 
 /* ========================== CORE PATCH ENGINE ============================= */
@@ -657,7 +842,7 @@ impl XlsxEditor {
         let mut sid_cache: HashMap<Option<u32>, u32> = HashMap::new();
 
         // забираем исходный буфер, чтобы свободно писать новый
-        let src = std::mem::take(&mut self.sheet_xml);
+        let src = self.sheet_xml.take();
         let mut dst = Vec::with_capacity(src.len() + 512);
 
         let find_row = memmem::Finder::new(b"<row ");
@@ -667,8 +852,8 @@ impl XlsxEditor {
 
         let mut i = 0usize;
 
-        while let Some(off) = find_row.find(&src[i..]) {
-            let row_start = i + off;
+        // теги ищутся мимо комментариев и PI: в них разметка не настоящая
+        while let Some(row_start) = find_markup(&find_row, &src, i) {
             // всё до <row ...> — как есть
             dst.extend_from_slice(&src[i..row_start]);
 
@@ -678,10 +863,10 @@ impl XlsxEditor {
 
             // r="...":
             let mut row_r: Option<u32> = None;
-            if let Some(pos) = find_bytes_from(&src, b" r=\"", row_start) {
+            if let Some(pos) = find_from(&src, b" r=\"", row_start) {
                 if pos < row_tag_end {
                     let v0 = pos + 4;
-                    if let Some(v1) = find_bytes_from(&src, b"\"", v0) {
+                    if let Some(v1) = find_from(&src, b"\"", v0) {
                         row_r = lexical_core::parse::<u32>(&src[v0..v1]).ok();
                     }
                 }
@@ -689,7 +874,7 @@ impl XlsxEditor {
 
             // границы строки
             let row_end =
-                find_bytes_from(&src, b"</row>", row_tag_end).context("</row> not found")?;
+                find_markup(&ROW_CLOSE, &src, row_tag_end).context("</row> not found")?;
             let row_close_end = row_end + "</row>".len();
 
             let Some(cur_row) = row_r else {
@@ -713,8 +898,8 @@ impl XlsxEditor {
             let mut j = row_tag_end + 1;
             while j < row_end {
                 // следующий <c ...> (включая самозакрывающийся)
-                let next_open = find_cell_open.find(&src[j..]).map(|p| j + p);
-                let next_sc = find_cell_selfclose.find(&src[j..]).map(|p| j + p);
+                let next_open = find_markup(&find_cell_open, &src, j);
+                let next_sc = find_markup(&find_cell_selfclose, &src, j);
                 let next_cell = match (next_open, next_sc) {
                     (Some(a), Some(b)) => Some(a.min(b)),
                     (Some(a), None) => Some(a),
@@ -744,9 +929,9 @@ impl XlsxEditor {
 
                         // r="A12" → проверяем колонку
                         let mut col_in_range = false;
-                        if let Some(rpos) = find_bytes_from(&cell_tag, b" r=\"", 0) {
+                        if let Some(rpos) = find_from(&cell_tag, b" r=\"", 0) {
                             let v0 = rpos + 4;
-                            if let Some(v1) = find_bytes_from(&cell_tag, b"\"", v0) {
+                            if let Some(v1) = find_from(&cell_tag, b"\"", v0) {
                                 let val = &cell_tag[v0..v1];
                                 if let Some(p) = val.iter().position(|b| b.is_ascii_digit()) {
                                     // считаем индекс колонки
@@ -763,10 +948,10 @@ impl XlsxEditor {
 
                         if col_in_range {
                             // старый s=".."
-                            let old_sid = if let Some(sp) = find_bytes_from(&cell_tag, b" s=\"", 0)
+                            let old_sid = if let Some(sp) = find_from(&cell_tag, b" s=\"", 0)
                             {
                                 let s0 = sp + 4;
-                                let s1 = find_bytes_from(&cell_tag, b"\"", s0 + 1)
+                                let s1 = find_from(&cell_tag, b"\"", s0 + 1)
                                     .context("attr quote")?;
                                 lexical_core::parse::<u32>(&cell_tag[s0..s1]).ok()
                             } else {
@@ -776,9 +961,9 @@ impl XlsxEditor {
                             let new_sid = self.get_or_make_sid(&mut sid_cache, old_sid, patch);
 
                             // заменить/вставить s="..."
-                            if let Some(sp) = find_bytes_from(&cell_tag, b" s=\"", 0) {
+                            if let Some(sp) = find_from(&cell_tag, b" s=\"", 0) {
                                 let s0 = sp + 4;
-                                let s1 = find_bytes_from(&cell_tag, b"\"", s0 + 1)
+                                let s1 = find_from(&cell_tag, b"\"", s0 + 1)
                                     .context("attr quote")?;
                                 cell_tag.splice(s0..s1, new_sid.to_string().bytes());
                             } else {
@@ -798,7 +983,7 @@ impl XlsxEditor {
                             j = tag_end + 1;
                         } else {
                             // копируем содержимое ячейки до </c>
-                            let c_close = find_bytes_from(&src, b"</c>", tag_end + 1)
+                            let c_close = find_markup(&CELL_CLOSE, &src, tag_end + 1)
                                 .context("</c> missing")?;
                             dst.extend_from_slice(&src[tag_end + 1..=c_close + 3]);
                             j = c_close + 4;
@@ -814,7 +999,7 @@ impl XlsxEditor {
 
         // хвост документа
         dst.extend_from_slice(&src[i..]);
-        self.sheet_xml = dst;
+        self.sheet_xml = dst.into();
         Ok(())
     }
 }
@@ -846,12 +1031,14 @@ impl XlsxEditor {
             let (font, fill) = self.xf_components(sid)?;
             let border = self.xf_border(sid)?;
             let align = self.xf_alignment(sid)?;
+            let protection = self.xf_protection(sid)?;
             Ok(StyleParts {
                 num_fmt_code: None,
                 font,
                 fill,
                 border,
                 align,
+                protection,
             })
         } else {
             Ok(StyleParts::default())
@@ -881,6 +1068,7 @@ impl XlsxEditor {
             fill_id,
             border_id,
             align: align_key.clone(),
+            protection: parts.protection,
         };
 
         // 2) короткий мут-заимствование: проверяем кэш
@@ -898,6 +1086,7 @@ impl XlsxEditor {
             fill_id,
             border_id,
             parts.align.as_ref(),
+            parts.protection,
         )?;
 
         // 4) короткий мут-заимствование: обновляем индекс
@@ -909,6 +1098,7 @@ impl XlsxEditor {
                 fill_id,
                 border_id,
                 align: parts.align.clone(),
+                protection: parts.protection,
             });
             ix.xf_by_key.insert(sk, sid);
         }
@@ -923,6 +1113,7 @@ impl XlsxEditor {
         fill_id: Option<u32>,
         border_id: Option<u32>,
         align: Option<&AlignSpec>,
+        protection: Option<(bool, bool)>,
     ) -> Result<u32> {
         let mut xf = String::from("<xf xfId=\"0\" ");
 
@@ -948,6 +1139,9 @@ impl XlsxEditor {
         if align.is_some() {
             xf.push_str(r#"applyAlignment="1" "#);
         }
+        if protection.is_some() {
+            xf.push_str(r#"applyProtection="1" "#);
+        }
         xf.pop();
         xf.push('>');
 
@@ -966,12 +1160,23 @@ impl XlsxEditor {
                 xf.push_str("/>");
             }
         }
+        if let Some((locked, hidden)) = protection {
+            // по умолчанию locked="1" hidden="0"
+            xf.push_str("<protection");
+            if !locked {
+                xf.push_str(r#" locked="0""#);
+            }
+            if hidden {
+                xf.push_str(r#" hidden="1""#);
+            }
+            xf.push_str("/>");
+        }
         xf.push_str("</xf>");
 
-        let pos = memmem::rfind(&self.styles_xml, b"</cellXfs>")
+        let pos = memmem::rfind(self.styles_xml.get(&self.src)?, b"</cellXfs>")
             .context("styles.xml: </cellXfs> not found")?;
-        self.styles_xml.splice(pos..pos, xf.bytes());
-        bump_count(&mut self.styles_xml, b"<cellXfs", b"count=\"")?;
+        self.styles_xml.get_mut(&self.src)?.splice(pos..pos, xf.bytes());
+        bump_count(self.styles_xml.get_mut(&self.src)?, b"<cellXfs", b"count=\"")?;
 
         // индекс нового — это текущее количество <xf> до вставки
         let sid = {
@@ -983,6 +1188,20 @@ impl XlsxEditor {
     }
 }
 
+/// `(locked, hidden)` из `<protection>`; отсутствующие атрибуты — по умолчанию.
+pub(crate) fn protection_attrs(e: &quick_xml::events::BytesStart) -> (bool, bool) {
+    let mut out = (true, false);
+    for a in e.attributes().with_checks(false).flatten() {
+        let on = matches!(a.value.as_ref(), b"1" | b"true");
+        match a.key.as_ref() {
+            b"locked" => out.0 = on,
+            b"hidden" => out.1 = on,
+            _ => {}
+        }
+    }
+    out
+}
+
 fn merge_style_parts(mut base: StyleParts, patch: &StyleParts) -> StyleParts {
     if patch.num_fmt_code.is_some() {
         base.num_fmt_code = patch.num_fmt_code.clone();
@@ -999,13 +1218,16 @@ fn merge_style_parts(mut base: StyleParts, patch: &StyleParts) -> StyleParts {
     if patch.align.is_some() {
         base.align = patch.align.clone();
     }
+    if patch.protection.is_some() {
+        base.protection = patch.protection;
+    }
     base
 }
 
 /* ========================== LOW-LEVEL HELPERS ============================= */
 
 impl XlsxEditor {
-    fn ensure_style(
+    pub(crate) fn ensure_style(
         &mut self,
         num_fmt: Option<&str>,
         font_id: Option<u32>,
@@ -1028,6 +1250,34 @@ impl XlsxEditor {
         self.add_new_xf(fmt_id, font_id, fill_id, border_id, align)
     }
 
+    /// Номер `<xf>` с оформлением `spec` целиком (как его отдаёт
+    /// `get_cell_style`); такой же `<xf>` переиспользуется.
+    pub(crate) fn ensure_style_spec(&mut self, spec: &StyleSpec) -> Result<u32> {
+        let f = &spec.font;
+        let font = match *f != FontSpec::default() {
+            true => Some(self.ensure_font(&f.name, f.size, f.bold, f.italic, f.color.as_ref())?),
+            false => None,
+        };
+        let fill = spec
+            .fill
+            .as_ref()
+            .map(|c| self.ensure_fill(c))
+            .transpose()?;
+        let border = match spec.border != BorderSpec::default() {
+            true => Some(self.ensure_border_spec(&spec.border)?),
+            false => None,
+        };
+        let parts = StyleParts {
+            num_fmt_code: spec.number_format.clone(),
+            font,
+            fill,
+            border,
+            align: (spec.alignment != AlignSpec::default()).then(|| spec.alignment.clone()),
+            protection: (!spec.locked || spec.hidden).then_some((spec.locked, spec.hidden)),
+        };
+        self.ensure_style_from_parts(&parts)
+    }
+
     fn ensure_num_fmt(&mut self, code: &str) -> Result<u32> {
         // A) есть в кеше?
         if let Some(id) = self
@@ -1049,24 +1299,28 @@ impl XlsxEditor {
         };
 
         // C) правим XML
-        let tag = format!(r#"<numFmt numFmtId="{new_id}" formatCode="{code}"/>"#);
-        if let Some(end) = memmem::rfind(&self.styles_xml, b"</numFmts>") {
+        let tag = format!(
+            r#"<numFmt numFmtId="{new_id}" formatCode="{}"/>"#,
+            xml_escape(code)
+        );
+        if let Some(end) = memmem::rfind(self.styles_xml.get(&self.src)?, b"</numFmts>") {
             // блок уже есть → просто дописываем внутрь и бампим count
-            self.styles_xml.splice(end..end, tag.bytes());
-            bump_count(&mut self.styles_xml, b"<numFmts", b"count=\"")?;
+            self.styles_xml.get_mut(&self.src)?.splice(end..end, tag.bytes());
+            bump_count(self.styles_xml.get_mut(&self.src)?, b"<numFmts", b"count=\"")?;
         } else {
             // блока нет → создаём РОВНО один
-            let root = memmem::find(&self.styles_xml, b"<styleSheet")
+            let root = memmem::find(self.styles_xml.get(&self.src)?, b"<styleSheet")
                 .context("<styleSheet> root not found in styles.xml")?;
-            let after_root = find_bytes_from(&self.styles_xml, b">", root)
+            let after_root = find_from(self.styles_xml.get(&self.src)?, b">", root)
                 .context("<styleSheet> start tag '>' not found")?
                 + 1;
 
             // старайся соблюдать порядок: numFmts должен стоять до <fonts>
-            let before_fonts = memmem::find(&self.styles_xml, b"<fonts").unwrap_or(after_root);
+            let before_fonts = memmem::find(self.styles_xml.get(&self.src)?, b"<fonts").unwrap_or(after_root);
 
             let block = format!(r#"<numFmts count="1">{tag}</numFmts>"#);
             self.styles_xml
+                .get_mut(&self.src)?
                 .splice(before_fonts..before_fonts, block.bytes());
 
             // ← ВАЖНО: НЕ делать вторую вставку по insert..insert
@@ -1088,13 +1342,14 @@ impl XlsxEditor {
         fill_id: Option<u32>,
         border_id: Option<u32>,
     ) -> Result<Option<u32>> {
-        let mut rdr = Reader::from_reader(self.styles_xml.as_slice());
+        let mut rdr = Reader::from_reader(self.styles_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
 
         let mut in_xfs = false;
         let mut idx: u32 = 0;
 
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = true,
                 Event::End(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = false,
@@ -1102,15 +1357,18 @@ impl XlsxEditor {
                 Event::Start(ref e) | Event::Empty(ref e)
                     if in_xfs && e.name().as_ref() == b"xf" =>
                 {
-                    // С xf с alignment мы не сравниваем — пропускаем
+                    // С xf с alignment/protection мы не сравниваем — пропускаем
                     let mut has_alignment_child = false;
                     // Event::Start -> значит дальше внутри могут быть теги
                     if matches!(ev, Event::Start(_)) {
                         let mut depth = 1;
                         while depth > 0 {
-                            match rdr.read_event()? {
+                            match next_event(&mut rdr, STYLES)? {
+                                Event::Empty(ref ie) if ie.name().as_ref() == b"protection" => {
+                                    has_alignment_child = true;
+                                }
                                 Event::Start(ref ie) => {
-                                    if ie.name().as_ref() == b"alignment" {
+                                    if matches!(ie.name().as_ref(), b"alignment" | b"protection") {
                                         has_alignment_child = true;
                                     }
                                     depth += 1;
@@ -1207,17 +1465,18 @@ impl XlsxEditor {
         }
         xf.push_str("</xf>");
 
-        let pos = memmem::rfind(&self.styles_xml, b"</cellXfs>")
+        let pos = memmem::rfind(self.styles_xml.get(&self.src)?, b"</cellXfs>")
             .context("styles.xml: </cellXfs> not found")?;
-        self.styles_xml.splice(pos..pos, xf.bytes());
-        bump_count(&mut self.styles_xml, b"<cellXfs", b"count=\"")?;
+        self.styles_xml.get_mut(&self.src)?.splice(pos..pos, xf.bytes());
+        bump_count(self.styles_xml.get_mut(&self.src)?, b"<cellXfs", b"count=\"")?;
 
         // посчитать индекс нового
-        let mut rdr = Reader::from_reader(self.styles_xml.as_slice());
+        let mut rdr = Reader::from_reader(self.styles_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
         let mut in_xfs = false;
         let mut cnt = 0u32;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = true,
                 Event::End(ref e) if e.name().as_ref() == b"cellXfs" => break,
@@ -1235,12 +1494,20 @@ impl XlsxEditor {
         Ok(cnt - 1)
     }
 
-    fn ensure_font(&mut self, name: &str, size: f32, bold: bool, italic: bool) -> Result<u32> {
+    fn ensure_font(
+        &mut self,
+        name: &str,
+        size: f32,
+        bold: bool,
+        italic: bool,
+        color: Option<&Color>,
+    ) -> Result<u32> {
         let key = FontKey {
             name: name.to_string(),
             size_100: (size * 100.0).round() as u32,
             bold,
             italic,
+            color: color.map(|c| c.to_string()),
         };
 
         // 0) индекс/поиск
@@ -1261,7 +1528,7 @@ impl XlsxEditor {
         };
 
         // 2) XML
-        let insert = memmem::rfind(&self.styles_xml, b"</fonts>")
+        let insert = memmem::rfind(self.styles_xml.get(&self.src)?, b"</fonts>")
             .context("<fonts> block not found in styles.xml")?;
         let mut xml = String::from("<font>");
         if bold {
@@ -1271,14 +1538,18 @@ impl XlsxEditor {
             xml.push_str("<i/>");
         }
         xml.push_str(&format!(r#"<sz val="{}"/>"#, (key.size_100 as f32) / 100.0));
+        if let Some(c) = color {
+            xml.push_str(&format!("<color {}/>", c.attrs()));
+        }
         xml.push_str(&format!(r#"<name val="{}"/>"#, name));
         xml.push_str("</font>");
-        self.styles_xml.splice(insert..insert, xml.bytes());
-        bump_count(&mut self.styles_xml, b"<fonts", b"count=\"")?;
+        self.styles_xml.get_mut(&self.src)?.splice(insert..insert, xml.bytes());
+        bump_count(self.styles_xml.get_mut(&self.src)?, b"<fonts", b"count=\"")?;
 
         // 3) индекс
         {
             let ix = self.style_ix_mut()?;
+            ix.fonts.push(key.clone());
             ix.font_by_key.insert(key, new_id);
             ix.fonts_count = new_id + 1;
         }
@@ -1286,14 +1557,13 @@ impl XlsxEditor {
         Ok(new_id)
     }
 
-    fn ensure_fill(&mut self, rgb: &str) -> Result<u32> {
-        let mut key = rgb.to_string();
-        key.make_ascii_uppercase();
+    fn ensure_fill(&mut self, color: &Color) -> Result<u32> {
+        let key = color.to_string();
 
         // 0) индекс/поиск
         {
             let ix = self.style_ix_mut()?;
-            if let Some(&id) = ix.fill_by_rgb.get(&key) {
+            if let Some(&id) = ix.fill_by_color.get(&key) {
                 return Ok(id);
             }
         }
@@ -1301,36 +1571,61 @@ impl XlsxEditor {
         // 1) id до вставки
         let new_id = {
             let ix = self.style_ix_mut()?;
-            if let Some(&id) = ix.fill_by_rgb.get(&key) {
+            if let Some(&id) = ix.fill_by_color.get(&key) {
                 return Ok(id);
             }
             ix.fills_count
         };
 
         // 2) XML
-        let insert = memmem::rfind(&self.styles_xml, b"</fills>")
+        let insert = memmem::rfind(self.styles_xml.get(&self.src)?, b"</fills>")
             .context("<fills> block not found in styles.xml")?;
         let xml = format!(
-            r#"<fill><patternFill patternType="solid"><fgColor rgb="{key}"/><bgColor indexed="64"/></patternFill></fill>"#
+            r#"<fill><patternFill patternType="solid"><fgColor {}/><bgColor indexed="64"/></patternFill></fill>"#,
+            color.attrs()
         );
-        self.styles_xml.splice(insert..insert, xml.bytes());
-        bump_count(&mut self.styles_xml, b"<fills", b"count=\"")?;
+        self.styles_xml.get_mut(&self.src)?.splice(insert..insert, xml.bytes());
+        bump_count(self.styles_xml.get_mut(&self.src)?, b"<fills", b"count=\"")?;
 
         // 3) индекс
         {
             let ix = self.style_ix_mut()?;
-            ix.fill_by_rgb.insert(key, new_id);
+            ix.fill_by_color.insert(key, new_id);
             ix.fills_count = new_id + 1;
         }
 
         Ok(new_id)
     }
 
-    fn ensure_border(&mut self, style: &str) -> Result<u32> {
+    fn ensure_border(&mut self, style: &str, color: Option<&Color>) -> Result<u32> {
+        let side = Some(BorderSide {
+            style: style.to_owned(),
+            color: color.cloned(),
+        });
+        let spec = BorderSpec {
+            left: side.clone(),
+            right: side.clone(),
+            top: side.clone(),
+            bottom: side,
+        };
+        self.ensure_border_spec(&spec)
+    }
+
+    /// Рамка со сторонами `spec`; сторона `None` пишется пустой.
+    fn ensure_border_spec(&mut self, spec: &BorderSpec) -> Result<u32> {
+        let sides = [&spec.left, &spec.right, &spec.top, &spec.bottom];
+        let styles = sides.map(|s| s.as_ref().map(|s| s.style.clone()));
+        let colors = sides.map(|s| {
+            s.as_ref()
+                .and_then(|s| s.color.as_ref())
+                .map(|c| c.to_string())
+        });
+        let key = sides_key(&styles, &colors);
+
         // 0) Убедимся, что индекс инициализирован и попробуем найти готовый
         {
             let ix = self.style_ix_mut()?;
-            if let Some(&id) = ix.border_by_key.get(style) {
+            if let Some(&id) = ix.border_by_key.get(&key) {
                 return Ok(id);
             }
         }
@@ -1339,38 +1634,100 @@ impl XlsxEditor {
         let new_id = {
             let ix = self.style_ix_mut()?;
             // повторная проверка на случай гонки
-            if let Some(&id) = ix.border_by_key.get(style) {
+            if let Some(&id) = ix.border_by_key.get(&key) {
                 return Ok(id);
             }
             ix.borders_count
         };
 
         // 2) Вставляем XML
-        let end_pos = memmem::rfind(&self.styles_xml, b"</borders>")
+        let end_pos = memmem::rfind(self.styles_xml.get(&self.src)?, b"</borders>")
             .context("styles.xml: </borders> not found")?;
+        let side = |name: &str, side: &Option<BorderSide>| match side {
+            Some(BorderSide {
+                style,
+                color: Some(c),
+            }) => format!(r#"<{name} style="{style}"><color {}/></{name}>"#, c.attrs()),
+            Some(BorderSide { style, color: None }) => format!(r#"<{name} style="{style}"/>"#),
+            None => format!("<{name}/>"),
+        };
         let tag = format!(
-            r#"<border><left style="{s}"/><right style="{s}"/><top style="{s}"/><bottom style="{s}"/><diagonal/></border>"#,
-            s = style
+            "<border>{}{}{}{}<diagonal/></border>",
+            side("left", &spec.left),
+            side("right", &spec.right),
+            side("top", &spec.top),
+            side("bottom", &spec.bottom)
         );
-        self.styles_xml.splice(end_pos..end_pos, tag.bytes());
-        bump_count(&mut self.styles_xml, b"<borders", b"count=\"")?;
+        self.styles_xml.get_mut(&self.src)?.splice(end_pos..end_pos, tag.bytes());
+        bump_count(self.styles_xml.get_mut(&self.src)?, b"<borders", b"count=\"")?;
 
         // 3) Обновляем индекс ПОСЛЕ вставки, используя pre‑id
         {
             let ix = self.style_ix_mut()?;
-            ix.border_by_key.insert(style.to_string(), new_id);
+            ix.border_by_key.insert(key, new_id);
             ix.borders_count = new_id + 1;
         }
 
         Ok(new_id)
     }
 
+    /// Номер `<dxf>` (разностного формата условного форматирования и стилей
+    /// таблиц) с оформлением `spec`; такой же `<dxf>` переиспользуется, как
+    /// `<xf>` в `cellXfs`. Пишутся только отличия от [`StyleSpec::default`]:
+    /// шрифт Calibri 11, «Общий» формат и т. д. в dxf не попадают.
+    pub(crate) fn ensure_dxf(&mut self, spec: &StyleSpec) -> Result<u32> {
+        let num_fmt = match &spec.number_format {
+            Some(code) => Some((self.ensure_num_fmt(code)?, code.as_str())),
+            None => None,
+        };
+        let dxf = dxf_xml(spec, num_fmt);
+
+        // 0) индекс
+        let new_id = {
+            let ix = self.style_ix_mut()?;
+            if let Some(&id) = ix.dxf_by_xml.get(&dxf) {
+                return Ok(id);
+            }
+            ix.dxfs_count
+        };
+
+        // 1) XML
+        let xml = self.styles_xml.get_mut(&self.src)?;
+        match find_elem(xml, "dxfs", 0) {
+            Some(r) if xml[r.end - 2] == b'/' => {
+                let block = format!(r#"<dxfs count="1">{dxf}</dxfs>"#);
+                xml.splice(r, block.bytes());
+            }
+            Some(r) => {
+                let close = r.end - b"</dxfs>".len();
+                xml.splice(close..close, dxf.bytes());
+                bump_count(xml, b"<dxfs", b"count=\"")?;
+            }
+            None => {
+                // блока нет — ставим по схеме перед tableStyles/colors/extLst
+                let pos = [&b"<tableStyles"[..], b"<colors", b"<extLst", b"</styleSheet>"]
+                    .iter()
+                    .find_map(|t| memmem::find(xml, t))
+                    .context("styles.xml: </styleSheet> not found")?;
+                let block = format!(r#"<dxfs count="1">{dxf}</dxfs>"#);
+                xml.splice(pos..pos, block.bytes());
+            }
+        }
+
+        // 2) индекс
+        let ix = self.style_ix_mut()?;
+        ix.dxf_by_xml.insert(dxf, new_id);
+        ix.dxfs_count = new_id + 1;
+        Ok(new_id)
+    }
+
     fn xf_components(&self, style_id: u32) -> Result<(Option<u32>, Option<u32>)> {
-        let mut rdr = Reader::from_reader(self.styles_xml.as_slice());
+        let mut rdr = Reader::from_reader(self.styles_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
         let mut in_xfs = false;
         let mut idx = 0u32;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = true,
                 Event::End(ref e) if e.name().as_ref() == b"cellXfs" => break,
@@ -1399,11 +1756,12 @@ impl XlsxEditor {
     }
 
     fn xf_border(&self, style_id: u32) -> Result<Option<u32>> {
-        let mut rdr = Reader::from_reader(self.styles_xml.as_slice());
+        let mut rdr = Reader::from_reader(self.styles_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
         let mut in_xfs = false;
         let mut idx = 0u32;
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = true,
                 Event::End(ref e) if e.name().as_ref() == b"cellXfs" => break,
@@ -1429,13 +1787,14 @@ impl XlsxEditor {
     }
 
     fn xf_alignment(&self, style_id: u32) -> Result<Option<AlignSpec>> {
-        let mut rdr = Reader::from_reader(self.styles_xml.as_slice());
+        let mut rdr = Reader::from_reader(self.styles_xml.get(&self.src)?.as_slice());
         rdr.config_mut().trim_text(true);
         let mut in_xfs = false;
         let mut xf_idx = 0u32;
         let mut depth = 0;
 
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
             match ev {
                 Event::Start(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = true,
                 Event::End(ref e) if e.name().as_ref() == b"cellXfs" => break,
@@ -1444,7 +1803,7 @@ impl XlsxEditor {
                     if xf_idx == style_id {
                         depth = 1;
                         while depth > 0 {
-                            match rdr.read_event()? {
+                            match next_event(&mut rdr, STYLES)? {
                                 Event::Start(ref ie) => {
                                     depth += 1;
                                     if ie.name().as_ref() == b"alignment" {
@@ -1475,7 +1834,7 @@ impl XlsxEditor {
                     }
                     xf_idx += 1;
                 }
-                Event::Empty(ref _e) if in_xfs => {
+                Event::Empty(ref e) if in_xfs && e.name().as_ref() == b"xf" => {
                     if xf_idx == style_id {
                         return Ok(None);
                     }
@@ -1488,61 +1847,79 @@ impl XlsxEditor {
         Ok(None)
     }
 
-    fn cell_style_id(&self, coord: &str) -> Result<Option<u32>> {
-        let tag = format!(r#"<c r="{coord}""#);
-        if let Some(pos) = memmem::rfind(&self.sheet_xml, tag.as_bytes()) {
-            if let Some(spos) = find_bytes_from(&self.sheet_xml, b" s=\"", pos) {
-                let val_start = spos + 4;
-                let val_end =
-                    find_bytes_from(&self.sheet_xml, b"\"", val_start + 1).unwrap_or(val_start);
-                let id = std::str::from_utf8(&self.sheet_xml[val_start..val_end])?
-                    .parse::<u32>()
-                    .unwrap_or(0);
-                return Ok(Some(id));
+    fn xf_protection(&self, style_id: u32) -> Result<Option<(bool, bool)>> {
+        let mut rdr = Reader::from_reader(self.styles_xml.get(&self.src)?.as_slice());
+        rdr.config_mut().trim_text(true);
+        let mut in_xfs = false;
+        let mut xf_idx = 0u32;
+
+        loop {
+            let ev = next_event(&mut rdr, STYLES)?;
+            match ev {
+                Event::Start(ref e) if e.name().as_ref() == b"cellXfs" => in_xfs = true,
+                Event::End(ref e) if e.name().as_ref() == b"cellXfs" => break,
+
+                Event::Start(ref e) if in_xfs && e.name().as_ref() == b"xf" => {
+                    if xf_idx == style_id {
+                        let mut depth = 1;
+                        while depth > 0 {
+                            match next_event(&mut rdr, STYLES)? {
+                                Event::Empty(ref ie) | Event::Start(ref ie)
+                                    if ie.name().as_ref() == b"protection" =>
+                                {
+                                    return Ok(Some(protection_attrs(ie)));
+                                }
+                                Event::Start(_) => depth += 1,
+                                Event::End(_) => depth -= 1,
+                                Event::Eof => break,
+                                _ => {}
+                            }
+                        }
+                        return Ok(None);
+                    }
+                    xf_idx += 1;
+                }
+                Event::Empty(ref e) if in_xfs && e.name().as_ref() == b"xf" => {
+                    if xf_idx == style_id {
+                        return Ok(None);
+                    }
+                    xf_idx += 1;
+                }
+                Event::Eof => break,
+                _ => {}
             }
         }
         Ok(None)
     }
 
-    fn apply_style_to_cell(&mut self, coord: &str, style: u32) -> Result<()> {
-        let row_num = coord.trim_start_matches(|c: char| c.is_ascii_alphabetic());
-        let row_tag = format!(r#"<row r="{row_num}""#);
-
-        let row_pos = match memmem::rfind(&self.sheet_xml, row_tag.as_bytes()) {
-            Some(p) => p,
-            None => {
-                self.set_cell(coord, "")?;
-                return self.apply_style_to_cell(coord, style);
-            }
-        };
-
-        let row_end =
-            find_bytes_from(&self.sheet_xml, b"</row>", row_pos).context("</row> not found")?;
+    fn cell_style_id(&self, coord: &str) -> Result<Option<u32>> {
+        let (col, row) = parse_cell(coord)?;
+        Ok(self.cell_style_attr(row, col).flatten())
+    }
 
-        let cell_tag = format!(r#"<c r="{coord}""#);
-        let cpos = match find_bytes_from(&self.sheet_xml, cell_tag.as_bytes(), row_pos) {
-            Some(p) => p,
-            None => {
-                let new_cell = format!(r#"<c r="{coord}" s="{style}"/>"#);
-                self.sheet_xml.splice(row_end..row_end, new_cell.bytes());
-                return Ok(());
-            }
+    /// Атрибут `s` ячейки: `None` — ячейки нет, `Some(None)` — она есть, но
+    /// без стиля.
+    pub(crate) fn cell_style_attr(&self, row: u32, col: u32) -> Option<Option<u32>> {
+        let cell = match self.sheet_xml.pending(row, col) {
+            Some(CellEdit::Restyle(s)) => return Some(Some(*s)),
+            Some(CellEdit::Replace(xml)) => xml.as_slice(),
+            // ячейки без отложенных правок совпадают с основой, сливать не нужно
+            None => self.sheet_xml.base_cell(row, col)?,
         };
+        let tag_end = memchr::memchr(b'>', cell).map_or(cell.len(), |p| p + 1);
+        Some(get_attr(&cell[..tag_end], "s").map(|s| s.parse::<u32>().unwrap_or(0)))
+    }
 
-        let ctag_end = find_bytes_from(&self.sheet_xml, b">", cpos).context("malformed <c> tag")?;
-
-        if let Some(sattr) = find_bytes_from(&self.sheet_xml, b" s=\"", cpos) {
-            if sattr < ctag_end {
-                let val_start = sattr + 4;
-                let val_end = find_bytes_from(&self.sheet_xml, b"\"", val_start + 1)
-                    .context("attr closing '\"' not found")?;
-                self.sheet_xml
-                    .splice(val_start..val_end, style.to_string().bytes());
-                return Ok(());
-            }
+    /// Ставит ячейке `coord` стиль `style`; правка откладывается в `sheet_xml`
+    /// (см. [`crate::sheet_buf`]), недостающие строка и ячейка появятся при слиянии.
+    fn apply_style_to_cell(&mut self, coord: &str, style: u32) -> Result<()> {
+        let (col, row) = parse_cell(coord)?;
+        check_row(row as u64)?;
+        if !self.sheet_xml.has_edits() {
+            open_sheet_data(&mut self.sheet_xml)?;
         }
-        self.sheet_xml
-            .splice(ctag_end..ctag_end, format!(r#" s="{style}""#).bytes());
+        self.sheet_xml.restyle_cell(row, col, style);
+        self.last_row = self.last_row.max(row);
         Ok(())
     }
 }
@@ -1550,12 +1927,15 @@ impl XlsxEditor {
 /* ========================== НОРМАЛИЗАЦИЯ <cols> =========================== */
 
 #[derive(Clone, Debug, Default, PartialEq)]
-struct ColProp {
-    width: Option<f64>,
-    style: Option<u32>,
-    best_fit: bool,
-    custom_width: bool,
-    hidden: bool,
+pub(crate) struct ColProp {
+    pub width: Option<f64>,
+    pub style: Option<u32>,
+    pub best_fit: bool,
+    pub custom_width: bool,
+    pub hidden: bool,
+    /// Уровень группировки (`outlineLevel`), 0 — без группы.
+    pub outline_level: u8,
+    pub collapsed: bool,
 }
 
 fn equal_props(a: &ColProp, b: &ColProp) -> bool {
@@ -1564,6 +1944,8 @@ fn equal_props(a: &ColProp, b: &ColProp) -> bool {
         && a.best_fit == b.best_fit
         && a.custom_width == b.custom_width
         && a.hidden == b.hidden
+        && a.outline_level == b.outline_level
+        && a.collapsed == b.collapsed
 }
 
 impl XlsxEditor {
@@ -1591,6 +1973,14 @@ impl XlsxEditor {
         self.write_cols_map(cols_start, cols_end, &cols_map)
     }
 
+    /// Правит свойства столбцов `<cols>` целиком: ключ карты — номер столбца с 1.
+    pub(crate) fn edit_cols(&mut self, f: impl FnOnce(&mut BTreeMap<u32, ColProp>)) -> Result<()> {
+        let (cols_start, cols_end) = self.ensure_cols_block()?;
+        let mut cols_map = self.read_cols_map(cols_start, cols_end)?;
+        f(&mut cols_map);
+        self.write_cols_map(cols_start, cols_end, &cols_map)
+    }
+
     /// Более безопасный путь задания number format для столбца:
     /// 1) создаём style_id 1 раз
     /// 2) обновляем <cols> нормализованно
@@ -1603,15 +1993,14 @@ impl XlsxEditor {
         let col_bytes = col_up.as_bytes();
         let sid = style_id.to_string();
 
-        let src = std::mem::take(&mut self.sheet_xml);
+        let src = self.sheet_xml.take();
         let mut dst = Vec::with_capacity(src.len() + 512);
 
         let mut i = 0usize;
         // Быстрый поиск повторяющегося шаблона
         let finder = memmem::Finder::new(b"<c");
 
-        while let Some(off) = finder.find(&src[i..]) {
-            let start = i + off;
+        while let Some(start) = find_markup(&finder, &src, i) {
             // все, что до <c...> — как есть
             dst.extend_from_slice(&src[i..start]);
 
@@ -1619,7 +2008,7 @@ impl XlsxEditor {
             let next = *src.get(start + 2).unwrap_or(&b'>');
             let is_cell = matches!(next, b' ' | b'>' | b'/' | b'r' | b's' | b't');
             // границы тега
-            let tag_end = find_bytes_from(&src, b">", start).context("cell tag end")? + 1;
+            let tag_end = find_from(&src, b">", start).context("cell tag end")? + 1;
 
             if !is_cell {
                 // не <c ...> ячейки — просто копируем тег
@@ -1632,9 +2021,9 @@ impl XlsxEditor {
             let mut cell = src[start..tag_end].to_vec();
 
             // ищем r="..."
-            if let Some(rpos) = find_bytes_from(&cell, b" r=\"", 0) {
+            if let Some(rpos) = find_from(&cell, b" r=\"", 0) {
                 let v0 = rpos + 4;
-                if let Some(v1) = find_bytes_from(&cell, b"\"", v0) {
+                if let Some(v1) = find_from(&cell, b"\"", v0) {
                     let val = &cell[v0..v1];
                     // A..Z + цифры
                     let ok = val.len() > col_bytes.len()
@@ -1649,7 +2038,7 @@ impl XlsxEditor {
                         if let Some(sp) = memmem::rfind(&cell, b" s=\"") {
                             let s0 = sp + 4;
                             let s1 =
-                                find_bytes_from(&cell, b"\"", s0 + 1).context("closing quote")?;
+                                find_from(&cell, b"\"", s0 + 1).context("closing quote")?;
                             cell.splice(s0..s1, sid.bytes());
                         } else {
                             let ins = if cell.len() >= 2 && cell[cell.len() - 2] == b'/' {
@@ -1668,10 +2057,93 @@ impl XlsxEditor {
         }
         // хвост
         dst.extend_from_slice(&src[i..]);
-        self.sheet_xml = dst;
+        self.sheet_xml = dst.into();
         Ok(())
     }
 
+    /// Включает (`true`) стиль столбца у дозаписываемых ячеек: ячейка без
+    /// своего стиля получает `s` из `<col style>` своего столбца. Excel и так
+    /// показывает такую ячейку в формате столбца, но часть читателей (pandas,
+    /// старые ETL) смотрит только на `s` ячейки. Работает для всей дозаписи
+    /// (`append_row`, `append_table`, `with_polars`, …); ячейки со своим
+    /// форматом (`append_values`) не трогает.
+    pub fn set_inherit_column_styles(&mut self, enabled: bool) -> &mut Self {
+        self.inherit_col_styles = enabled;
+        self
+    }
+
+    /// Проставляет стиль столбца ячейкам без `s` в строках ниже `old_last`
+    /// (см. [`Self::set_inherit_column_styles`]). Дописанные строки своего
+    /// стиля не имеют, так что действует стиль столбца.
+    pub(crate) fn stamp_column_styles(&mut self, old_last: u32) -> Result<()> {
+        if !self.inherit_col_styles || self.last_row <= old_last {
+            return Ok(());
+        }
+        let styles: HashMap<u32, u32> = match find_child(&self.sheet_xml, "cols")? {
+            Some(cols) => self
+                .read_cols_map(cols.start, cols.end)?
+                .into_iter()
+                .filter_map(|(col, p)| Some((col - 1, p.style.filter(|&s| s != 0)?)))
+                .collect(),
+            None => return Ok(()),
+        };
+        if styles.is_empty() {
+            return Ok(());
+        }
+
+        let xml: &mut Vec<u8> = &mut self.sheet_xml;
+        let body = open_sheet_data(xml)?;
+        // дописанные строки — хвост <sheetData>: идём с конца, пока r > old_last
+        let mut start = body.end;
+        while let Some(p) = memmem::rfind(&xml[body.start..start], b"<row") {
+            let p = body.start + p;
+            let gt = find_from(xml, b">", p).context("row tag end")?;
+            match get_attr(&xml[p..=gt], "r").and_then(|r| r.parse::<u32>().ok()) {
+                Some(r) if r > old_last => start = p,
+                _ => break,
+            }
+        }
+
+        restyle_cells(xml, start..body.end, |col, s| match s {
+            None => col.and_then(|c| styles.get(&c).copied()),
+            Some(_) => None,
+        })
+    }
+
+    /// То же для строки: style_id ставится в `<row s customFormat="1">` (его
+    /// получат новые ячейки строки) и во все существующие `<c>` строки.
+    fn force_row_number_format(&mut self, row: u32, style_id: u32) -> Result<()> {
+        if row == 0 {
+            bail!("row numbers start at 1");
+        }
+        check_row(row as u64)?;
+        self.ensure_rows(row, row)?;
+        let sid = style_id.to_string();
+        self.for_each_row_tag(|r, tag| {
+            (r == row).then(|| {
+                let mut new = tag.to_vec();
+                set_attr(&mut new, "s", Some(&sid));
+                set_attr(&mut new, "customFormat", Some("1"));
+                new
+            })
+        })?;
+
+        let xml: &mut Vec<u8> = &mut self.sheet_xml;
+        let body = open_sheet_data(xml)?;
+        let mut from = body.start;
+        let found = loop {
+            let Some(e) = find_elem(&xml[..body.end], "row", from) else {
+                return Ok(());
+            };
+            let gt = find_from(xml, b">", e.start).context("row tag end")?;
+            if get_attr(&xml[e.start..=gt], "r").and_then(|r| r.parse().ok()) == Some(row) {
+                break gt + 1..e.end;
+            }
+            from = e.end;
+        };
+        restyle_cells(xml, found, |_, _| Some(style_id))
+    }
+
     fn ensure_cols_block(&mut self) -> Result<(usize, usize)> {
         if let (Some(start), Some(end)) = (
             memmem::rfind(&self.sheet_xml, b"<cols>"),
@@ -1684,8 +2156,9 @@ impl XlsxEditor {
         let anchor_end = if let Some(p) = memmem::rfind(&self.sheet_xml, b"</sheetFormatPr>") {
             p + "</sheetFormatPr>".len()
         } else {
-            memmem::rfind(&self.sheet_xml, b"<sheetData")
+            find_child(&self.sheet_xml, "sheetData")?
                 .context("<sheetData> not found on the current sheet")?
+                .start
         };
 
         let block = b"<cols></cols>";
@@ -1697,13 +2170,18 @@ impl XlsxEditor {
         Ok((start, end))
     }
 
-    fn read_cols_map(&self, cols_start: usize, cols_end: usize) -> Result<BTreeMap<u32, ColProp>> {
+    pub(crate) fn read_cols_map(
+        &self,
+        cols_start: usize,
+        cols_end: usize,
+    ) -> Result<BTreeMap<u32, ColProp>> {
         let mut map = BTreeMap::new();
         let slice = &self.sheet_xml[cols_start..cols_end];
         let mut rdr = Reader::from_reader(slice);
         rdr.config_mut().trim_text(true);
 
-        while let Ok(ev) = rdr.read_event() {
+        loop {
+            let ev = next_event(&mut rdr, &self.sheet_path)?;
             match ev {
                 Event::Empty(ref e) | Event::Start(ref e) if e.name().as_ref() == b"col" => {
                     let mut min = None;
@@ -1713,6 +2191,8 @@ impl XlsxEditor {
                     let mut best_fit = false;
                     let mut custom_width = false;
                     let mut hidden = false;
+                    let mut outline_level = 0;
+                    let mut collapsed = false;
 
                     for a in e.attributes().with_checks(false).flatten() {
                         let v = String::from_utf8_lossy(&a.value);
@@ -1724,6 +2204,8 @@ impl XlsxEditor {
                             b"bestFit" => best_fit = v == "1" || v == "true",
                             b"customWidth" => custom_width = v == "1" || v == "true",
                             b"hidden" => hidden = v == "1" || v == "true",
+                            b"outlineLevel" => outline_level = v.parse().unwrap_or(0),
+                            b"collapsed" => collapsed = v == "1" || v == "true",
                             _ => {}
                         }
                     }
@@ -1735,6 +2217,8 @@ impl XlsxEditor {
                         best_fit,
                         custom_width,
                         hidden,
+                        outline_level,
+                        collapsed,
                     };
                     for i in min..=max {
                         map.insert(i, p.clone());
@@ -1796,10 +2280,163 @@ fn build_one_col_tag(min: u32, max: u32, p: &ColProp) -> String {
     if p.hidden {
         s.push_str(r#" hidden="1""#);
     }
+    if p.outline_level > 0 {
+        s.push_str(&format!(r#" outlineLevel="{}""#, p.outline_level));
+    }
+    if p.collapsed {
+        s.push_str(r#" collapsed="1""#);
+    }
     s.push_str("/>");
     s
 }
 
+/* ========================== AUTOFIT <cols> ================================ */
+
+impl XlsxEditor {
+    /// Подбирает ширину столбцов по содержимому и пишет `<col width=.. bestFit="1">`.
+    ///
+    /// `range`:
+    /// * `None` — все столбцы, в которых есть данные;
+    /// * `"C"` / `"C:"` — один столбец;
+    /// * `"A:F"` — диапазон столбцов;
+    /// * `"A1:F20"` — столбцы A..F, учитываются только строки 1..20.
+    ///
+    /// Ширина считается приблизительно: по таблице относительных ширин символов,
+    /// масштабированной на размер (и жирность) шрифта ячейки.
+    pub fn autofit_columns(&mut self, range: Option<&str>) -> Result<&mut Self, XlsxError> {
+        let (c0, c1, rows) = match range {
+            Some(r) => parse_col_span(r)?,
+            None => (0, u32::MAX, None),
+        };
+
+        // (size, bold) для каждого xf; xf без fontId использует шрифт 0
+        let xf_fonts: Vec<(f64, bool)> = {
+            let ix = self.style_ix_mut()?;
+            let font_of = |id: u32| {
+                ix.fonts
+                    .get(id as usize)
+                    .map(|k| (k.size_100 as f64 / 100.0, k.bold))
+                    .unwrap_or((11.0, false))
+            };
+            ix.xfs
+                .iter()
+                .map(|x| font_of(x.font_id.unwrap_or(0)))
+                .collect()
+        };
+        let default_font = xf_fonts.first().copied().unwrap_or((11.0, false));
+
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let cells = read_cells(&self.sheet_xml)?;
+
+        let mut widths: BTreeMap<u32, f64> = BTreeMap::new();
+        for cell in &cells {
+            if cell.col < c0 || cell.col > c1 {
+                continue;
+            }
+            if rows.is_some_and(|(r0, r1)| cell.row < r0 || cell.row > r1) {
+                continue;
+            }
+            let Some(text) = cell.display_text(sst) else {
+                continue;
+            };
+            let (size, bold) = cell
+                .s
+                .and_then(|s| xf_fonts.get(s as usize).copied())
+                .unwrap_or(default_font);
+            let is_number = matches!(cell.t.as_deref(), None | Some("n"));
+            let w = text_width(&text, is_number, size, bold);
+            let slot = widths.entry(cell.col).or_insert(0.0);
+            if w > *slot {
+                *slot = w;
+            }
+        }
+
+        if widths.is_empty() {
+            return Ok(self);
+        }
+
+        let (cols_start, cols_end) = self.ensure_cols_block()?;
+        let mut cols_map = self.read_cols_map(cols_start, cols_end)?;
+        for (col0, w) in widths {
+            let prop = cols_map.entry(col0 + 1).or_default();
+            prop.width = Some(w);
+            prop.custom_width = true;
+            prop.best_fit = true;
+        }
+        self.write_cols_map(cols_start, cols_end, &cols_map)?;
+        Ok(self)
+    }
+}
+
+/// (c0, c1, Some((r0, r1)) если строки заданы); столбцы 0-based.
+type ColSpan = (u32, u32, Option<(u32, u32)>);
+
+/// `"C"`, `"C:"`, `"A:F"`, `"A1:F20"` → [`ColSpan`].
+fn parse_col_span(s: &str) -> Result<ColSpan> {
+    let s = s.trim();
+    let (a, b) = match s.split_once(':') {
+        Some((a, "")) => (a, a),
+        Some((a, b)) => (a, b),
+        None => (s, s),
+    };
+    let split = |p: &str| -> Result<(u32, Option<u32>)> {
+        let pos = p.find(|c: char| c.is_ascii_digit()).unwrap_or(p.len());
+        let (letters, digits) = p.split_at(pos);
+        if letters.is_empty() || !letters.bytes().all(|b| b.is_ascii_alphabetic()) {
+            bail!("invalid column range: {s}");
+        }
+        let row = if digits.is_empty() {
+            None
+        } else {
+            Some(digits.parse::<u32>()?)
+        };
+        Ok((col_index(letters) as u32, row))
+    };
+    let (c0, r0) = split(a)?;
+    let (c1, r1) = split(b)?;
+    let rows = match (r0, r1) {
+        (Some(r0), Some(r1)) => Some((r0.min(r1), r0.max(r1))),
+        (None, None) => None,
+        _ => bail!("invalid column range: {s}"),
+    };
+    Ok((c0.min(c1), c0.max(c1), rows))
+}
+
+/// Относительная ширина символа в «цифрах» шрифта по умолчанию.
+fn char_width(ch: char) -> f64 {
+    match ch {
+        'i' | 'j' | 'l' | 'I' | '.' | ',' | ':' | ';' | '\'' | '|' | '!' | ' ' => 0.5,
+        'f' | 'r' | 't' | '(' | ')' | '[' | ']' | '-' | '/' => 0.7,
+        'm' | 'w' | 'M' | 'W' | '@' | '%' => 1.5,
+        c if c.is_ascii_uppercase() => 1.2,
+        c if c.is_ascii() => 1.0,
+        // CJK, хангыль, полноширинные формы
+        '\u{1100}'..='\u{115F}'
+        | '\u{2E80}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}' => 2.0,
+        _ => 1.1,
+    }
+}
+
+/// Ширина столбца (в единицах Excel) под текст `text` шрифтом `size` pt.
+fn text_width(text: &str, is_number: bool, size: f64, bold: bool) -> f64 {
+    let units = text
+        .lines()
+        .map(|line| {
+            let w: f64 = line.chars().map(char_width).sum();
+            // General показывает не больше 11 знаков числа
+            if is_number { w.min(11.0) } else { w }
+        })
+        .fold(0.0, f64::max);
+    let scale = size / 11.0 * if bold { 1.1 } else { 1.0 };
+    let w = units * scale + 1.7;
+    ((w * 100.0).round() / 100.0).min(255.0)
+}
+
 /* ========================== BYTE/STRING HELPERS =========================== */
 
 pub fn col_letter(mut n: u32) -> String {
@@ -1825,19 +2462,180 @@ pub fn split_coord(coord: &str) -> (u32, u32) {
         coord[p..].parse::<u32>().unwrap(),
     )
 }
-#[inline]
-fn find_bytes_from(hay: &[u8], needle: &[u8], start: usize) -> Option<usize> {
-    if start >= hay.len() {
-        return None;
+/// Как [`split_coord`], но без паник: `"B7"` → (1, 7), мусор → ошибка.
+pub(crate) fn parse_cell(coord: &str) -> Result<(u32, u32)> {
+    let invalid = || XlsxError::InvalidRange(coord.to_owned());
+    let p = coord.find(|c: char| c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (letters, digits) = coord.split_at(p);
+    if letters.is_empty()
+        || letters.len() > 3
+        || !letters.bytes().all(|b| b.is_ascii_alphabetic())
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid().into());
+    }
+    match digits.parse::<u32>() {
+        Ok(row) if row > 0 => Ok((col_index(letters) as u32, row)),
+        _ => Err(invalid().into()),
+    }
+}
+/// Индекс стороны рамки (left, right, top, bottom) по имени тега.
+fn border_side(name: &[u8]) -> Option<usize> {
+    match name {
+        b"left" => Some(0),
+        b"right" => Some(1),
+        b"top" => Some(2),
+        b"bottom" => Some(3),
+        _ => None,
     }
-    // поищем в срезе с нужного оффсета и поправим индекс
-    memmem::find(&hay[start..], needle).map(|i| i + start)
 }
+
+fn border_style_attr(e: &BytesStart) -> Option<String> {
+    e.attributes()
+        .with_checks(false)
+        .flatten()
+        .find(|a| a.key.as_ref() == b"style")
+        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+}
+
+/// Ключ рамки в индексе: `style` или `style|цвет`.
+fn border_key(style: &str, color: Option<&str>) -> String {
+    match color {
+        Some(c) => format!("{style}|{c}"),
+        None => style.to_owned(),
+    }
+}
+
+/// Ключ рамки по сторонам (left, right, top, bottom): у «ровной» рамки —
+/// как [`border_key`], иначе ключи сторон через `;` (нет стороны — пусто).
+fn sides_key(styles: &[Option<String>; 4], colors: &[Option<String>; 4]) -> String {
+    let even = styles.iter().all(|s| s.is_some() && *s == styles[0])
+        && colors.iter().all(|c| *c == colors[0]);
+    if even && let Some(style) = &styles[0] {
+        return border_key(style, colors[0].as_deref());
+    }
+    let side = |i: usize| match &styles[i] {
+        Some(style) => border_key(style, colors[i].as_deref()),
+        None => String::new(),
+    };
+    (0..4).map(side).collect::<Vec<_>>().join(";")
+}
+
+/// Переписывает `s` у ячеек `xml[range]`: `f(столбец с 0, текущий s)`
+/// возвращает новый стиль или `None` — оставить ячейку как есть.
+fn restyle_cells(
+    xml: &mut Vec<u8>,
+    range: Range<usize>,
+    f: impl Fn(Option<u32>, Option<u32>) -> Option<u32>,
+) -> Result<()> {
+    let mut out = Vec::with_capacity(range.len() + 256);
+    let mut i = range.start;
+    while let Some(cell) = find_elem(&xml[..range.end], "c", i) {
+        let gt = find_from(xml, b">", cell.start).context("cell tag end")?;
+        out.extend_from_slice(&xml[i..cell.start]);
+        let mut tag = xml[cell.start..=gt].to_vec();
+        let col = get_attr(&tag, "r")
+            .and_then(|r| parse_cell(&r).ok())
+            .map(|(c, _)| c);
+        let s = get_attr(&tag, "s").and_then(|s| s.parse().ok());
+        if let Some(new) = f(col, s) {
+            set_attr(&mut tag, "s", Some(&new.to_string()));
+        }
+        out.extend(tag);
+        i = gt + 1;
+    }
+    out.extend_from_slice(&xml[i..range.end]);
+    xml.splice(range, out);
+    Ok(())
+}
+
+/// `<dxf>` для [`XlsxEditor::ensure_dxf`] в порядке схемы: font, numFmt,
+/// fill, alignment, protection, border. Заливка в dxf задаётся через `bgColor`.
+fn dxf_xml(spec: &StyleSpec, num_fmt: Option<(u32, &str)>) -> String {
+    let mut xml = String::from("<dxf>");
+    let (font, plain) = (&spec.font, FontSpec::default());
+    if *font != plain {
+        xml.push_str("<font>");
+        if font.bold {
+            xml.push_str("<b/>");
+        }
+        if font.italic {
+            xml.push_str("<i/>");
+        }
+        if font.size != plain.size {
+            xml.push_str(&format!(r#"<sz val="{}"/>"#, font.size));
+        }
+        if let Some(c) = &font.color {
+            xml.push_str(&format!("<color {}/>", c.attrs()));
+        }
+        if font.name != plain.name {
+            xml.push_str(&format!(r#"<name val="{}"/>"#, xml_escape(&font.name)));
+        }
+        xml.push_str("</font>");
+    }
+    if let Some((id, code)) = num_fmt {
+        xml.push_str(&format!(
+            r#"<numFmt numFmtId="{id}" formatCode="{}"/>"#,
+            xml_escape(code)
+        ));
+    }
+    if let Some(c) = &spec.fill {
+        xml.push_str(&format!(
+            "<fill><patternFill><bgColor {}/></patternFill></fill>",
+            c.attrs()
+        ));
+    }
+    let a = &spec.alignment;
+    if *a != AlignSpec::default() {
+        xml.push_str("<alignment");
+        if let Some(h) = &a.horiz {
+            xml.push_str(&format!(r#" horizontal="{h}""#));
+        }
+        if let Some(v) = &a.vert {
+            xml.push_str(&format!(r#" vertical="{v}""#));
+        }
+        if a.wrap {
+            xml.push_str(r#" wrapText="1""#);
+        }
+        xml.push_str("/>");
+    }
+    if !spec.locked || spec.hidden {
+        xml.push_str(&format!(
+            r#"<protection locked="{}" hidden="{}"/>"#,
+            spec.locked as u8, spec.hidden as u8
+        ));
+    }
+    let b = &spec.border;
+    let sides = [
+        ("left", &b.left),
+        ("right", &b.right),
+        ("top", &b.top),
+        ("bottom", &b.bottom),
+    ];
+    if sides.iter().any(|(_, s)| s.is_some()) {
+        xml.push_str("<border>");
+        for (name, side) in sides {
+            let Some(side) = side else { continue };
+            let style = xml_escape(&side.style);
+            match &side.color {
+                Some(c) => xml.push_str(&format!(
+                    r#"<{name} style="{style}"><color {}/></{name}>"#,
+                    c.attrs()
+                )),
+                None => xml.push_str(&format!(r#"<{name} style="{style}"/>"#)),
+            }
+        }
+        xml.push_str("</border>");
+    }
+    xml.push_str("</dxf>");
+    xml
+}
+
 fn bump_count(xml: &mut Vec<u8>, tag: &[u8], attr: &[u8]) -> Result<()> {
     if let Some(pos) = memmem::rfind(xml, tag) {
-        if let Some(a) = find_bytes_from(xml, attr, pos) {
+        if let Some(a) = find_from(xml, attr, pos) {
             let start = a + attr.len();
-            let end = find_bytes_from(xml, b"\"", start).context("closing quote not found")?;
+            let end = find_from(xml, b"\"", start).context("closing quote not found")?;
             let mut num: u32 = std::str::from_utf8(&xml[start..end])?.parse()?;
             num += 1;
             xml.splice(start..end, num.to_string().bytes());
@@ -0,0 +1,437 @@
+//! table_part.rs – автофильтр листа и «умные» таблицы Excel (ListObject).
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::files_part::xml_escape;
+use crate::read_part::read_cells;
+use crate::rels_part::REL_TABLE;
+use crate::sheet_xml::{
+    ensure_root_ns, extend_dimension, find_child, find_elem, get_attr, insert_child,
+    remove_children, root_tag_range, set_attr, tag_prefix, upsert_child,
+};
+use crate::style::{col_letter, parse_cell};
+use crate::workbook_part::{absolute_ref, quote_sheet_name};
+use anyhow::{Context, Result};
+
+const FILTER_DB: &str = "_xlnm._FilterDatabase";
+const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Дозапись текущего листа, ещё не отражённая в таблицах, автофильтре и
+/// `<dimension>` (см. [`XlsxEditor::flush_appended_ranges`]).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PendingGrowth {
+    /// последняя строка листа до первой из отложенных дозаписей
+    old_last: u32,
+    /// записанная область всех отложенных дозаписей
+    written: Option<Rect>,
+}
+
+/// Таблица, привязанная к текущему листу.
+#[derive(Debug, Clone)]
+pub(crate) struct TableInfo {
+    pub path: String,
+    pub name: String,
+    pub range: String,
+    pub totals: u32, // строк итогов внизу (`totalsRowCount`)
+}
+
+impl XlsxEditor {
+    /// Включает автофильтр на диапазоне `range` (`"A1:F1"` — строка заголовков,
+    /// либо вся таблица `"A1:F200"`). Существующий фильтр листа заменяется
+    /// вместе с условиями; в workbook.xml обновляется `_xlnm._FilterDatabase`.
+    pub fn set_auto_filter(&mut self, range: &str) -> Result<&mut Self, XlsxError> {
+        let range = normalize_range(range)?;
+        self.flush_appended_ranges()?;
+        let xml = format!(r#"<autoFilter ref="{range}"/>"#);
+        upsert_child(&mut self.sheet_xml, "autoFilter", xml.as_bytes())?;
+
+        self.set_filter_database(&range)?;
+        Ok(self)
+    }
+
+    /// Создаёт таблицу Excel на диапазоне `range` (первая строка — заголовки).
+    ///
+    /// * `name` – имя таблицы для структурированных ссылок (`=SUM(Sales[Qty])`);
+    /// * `style` – стиль оформления, по умолчанию `TableStyleMedium2`;
+    /// * `columns` – заголовки колонок; если заданы, записываются в первую
+    ///   строку диапазона, иначе берутся из уже заполненных ячеек.
+    pub fn add_table(
+        &mut self,
+        range: &str,
+        name: &str,
+        style: Option<&str>,
+        columns: Option<&[&str]>,
+    ) -> Result<&mut Self, XlsxError> {
+        validate_table_name(name)?;
+        let rect = parse_range(range)?;
+        let (c0, r0, c1, r1) = rect;
+        if r1 <= r0 {
+            bail!("table range {range} needs a header row and at least one data row");
+        }
+        let ncols = (c1 - c0 + 1) as usize;
+        self.flush_appended_ranges()?;
+
+        // ---- имя уникально в книге, диапазон не пересекается с таблицами/фильтром листа
+        let all = self.all_tables()?;
+        if all.iter().any(|(_, n)| n.eq_ignore_ascii_case(name)) {
+            bail!("table {name} already exists");
+        }
+        for t in self.sheet_tables()? {
+            if rects_overlap(rect, parse_range(&t.range)?) {
+                bail!("range {range} overlaps table {} ({})", t.name, t.range);
+            }
+        }
+        let sheet_filter = find_child(&self.sheet_xml, "autoFilter")?
+            .and_then(|r| get_attr(&self.sheet_xml[r], "ref"))
+            .filter(|f| parse_range(f).is_ok_and(|fr| rects_overlap(rect, fr)));
+        if let Some(f) = sheet_filter {
+            bail!("range {range} overlaps the sheet autoFilter {f}; remove it first");
+        }
+
+        // ---- заголовки
+        let current = self.header_texts(r0, c0, c1)?;
+        let headers = match columns {
+            Some(cols) => {
+                if cols.len() != ncols {
+                    bail!("{} column names given for {ncols} columns", cols.len());
+                }
+                cols.iter().map(|c| c.to_string()).collect()
+            }
+            None => current.iter().map(|h| h.clone().unwrap_or_default()).collect(),
+        };
+        let headers = unique_headers(headers);
+        for (i, h) in headers.iter().enumerate() {
+            if current[i].as_deref() != Some(h.as_str()) {
+                let coord = format!("{}{}", col_letter(c0 + i as u32), r0);
+                self.set_cell(&coord, h)?;
+            }
+        }
+
+        // ---- xl/tables/tableN.xml
+        let id = all.iter().map(|(id, _)| *id).max().unwrap_or(0) + 1;
+        let range = format_range(rect);
+        let mut xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<table xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" id="{id}" name="{n}" displayName="{n}" ref="{range}" totalsRowShown="0"><autoFilter ref="{range}"/><tableColumns count="{ncols}">"#,
+            n = xml_escape(name),
+        );
+        for (i, h) in headers.iter().enumerate() {
+            xml.push_str(&format!(
+                r#"<tableColumn id="{}" name="{}"/>"#,
+                i + 1,
+                xml_escape(h)
+            ));
+        }
+        xml.push_str(&format!(
+            r#"</tableColumns><tableStyleInfo name="{}" showFirstColumn="0" showLastColumn="0" showRowStripes="1" showColumnStripes="0"/></table>"#,
+            xml_escape(style.unwrap_or("TableStyleMedium2"))
+        ));
+
+        // ---- часть, связь листа и <tableParts>
+        let sheet_path = self.sheet_path.clone();
+        let (_, rid) = self.create_part(&sheet_path, "table", xml.into_bytes())?;
+        ensure_root_ns(&mut self.sheet_xml, "r", NS_R)?;
+        match find_child(&self.sheet_xml, "tableParts")? {
+            Some(r) => {
+                let p = tag_prefix(&self.sheet_xml[r.clone()]);
+                let mut block = self.sheet_xml[r.clone()].to_vec();
+                let part = format!(r#"<{p}tablePart r:id="{rid}"/>"#);
+                let close = format!("</{p}tableParts>");
+                match memchr::memmem::rfind(&block, close.as_bytes()) {
+                    Some(pos) => {
+                        block.splice(pos..pos, part.into_bytes());
+                    }
+                    None => {
+                        // <tableParts count="0"/>
+                        let gt = block.len() - 2;
+                        block.splice(gt.., format!(">{part}{close}").into_bytes());
+                    }
+                }
+                let count = memchr::memmem::find_iter(&block, format!("<{p}tablePart ").as_bytes())
+                    .count();
+                let tag_end = block.iter().position(|&b| b == b'>').unwrap_or(0);
+                let mut tag = block[..=tag_end].to_vec();
+                set_attr(&mut tag, "count", Some(&count.to_string()));
+                block.splice(..=tag_end, tag);
+                self.sheet_xml.splice(r, block);
+            }
+            None => {
+                let xml = format!(r#"<tableParts count="1"><tablePart r:id="{rid}"/></tableParts>"#);
+                insert_child(&mut self.sheet_xml, "tableParts", xml.as_bytes())?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Расширяет таблицу `name` текущего листа на `extra_rows` строк вниз
+    /// (например, после дозаписи строк под ней). Структурированные ссылки
+    /// вида `Sales[Qty]` после этого охватывают и новые строки.
+    pub fn extend_table(&mut self, name: &str, extra_rows: u32) -> Result<&mut Self, XlsxError> {
+        self.flush_appended_ranges()?;
+        let t = self
+            .sheet_tables()?
+            .into_iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+            .with_context(|| format!("table {name} not found on the current sheet"))?;
+        let (c0, r0, c1, r1) = parse_range(&t.range)?;
+        self.set_table_ref(&t.path, (c0, r0, c1, r1 + extra_rows))?;
+        Ok(self)
+    }
+
+    /// Снимает автофильтр с текущего листа (если он был).
+    pub fn remove_auto_filter(&mut self) -> Result<&mut Self, XlsxError> {
+        self.flush_appended_ranges()?;
+        if remove_children(&mut self.sheet_xml, "autoFilter")? {
+            let idx = self.current_sheet_index()?;
+            self.set_defined_name(FILTER_DB, Some(idx), None, false)?;
+        }
+        Ok(self)
+    }
+}
+
+/// Прямоугольник `(c0, r0, c1, r1)`: колонки 0-based, строки 1-based, включительно.
+pub(crate) type Rect = (u32, u32, u32, u32);
+
+/// Разбирает `A1` / `A1:F10` в прямоугольник (углы упорядочены).
+pub(crate) fn parse_range(range: &str) -> Result<Rect> {
+    let (a, b) = range.split_once(':').unwrap_or((range, range));
+    let invalid = |_| XlsxError::InvalidRange(range.to_owned());
+    let (c0, r0) = parse_cell(a.trim()).map_err(invalid)?;
+    let (c1, r1) = parse_cell(b.trim()).map_err(invalid)?;
+    Ok((c0.min(c1), r0.min(r1), c0.max(c1), r0.max(r1)))
+}
+
+/// Обратное к [`parse_range`]: `A1:F10` (или `A1` для одной ячейки).
+pub(crate) fn format_range((c0, r0, c1, r1): Rect) -> String {
+    let tl = format!("{}{}", col_letter(c0), r0);
+    if (c0, r0) == (c1, r1) {
+        return tl;
+    }
+    format!("{tl}:{}{}", col_letter(c1), r1)
+}
+
+/// Проверяет `A1` / `A1:F10` и приводит к виду `A1:F10` (верхний регистр,
+/// левый верхний угол первым).
+pub(crate) fn normalize_range(range: &str) -> Result<String> {
+    Ok(format_range(parse_range(range)?))
+}
+
+pub(crate) fn rects_overlap(a: Rect, b: Rect) -> bool {
+    a.0 <= b.2 && b.0 <= a.2 && a.1 <= b.3 && b.1 <= a.3
+}
+
+/// Имя таблицы по правилам Excel: буква/`_`/`\` в начале, дальше буквы,
+/// цифры, `_` и `.`; не похоже на ссылку на ячейку, не длиннее 255.
+fn validate_table_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let ok_first = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '\\');
+    let ok_rest = chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+    let upper = name.to_ascii_uppercase();
+    if !ok_first
+        || !ok_rest
+        || name.chars().count() > 255
+        || parse_cell(name).is_ok()
+        || matches!(upper.as_str(), "C" | "R")
+    {
+        bail!("invalid table name: {name:?}");
+    }
+    Ok(())
+}
+
+/// Уникализирует заголовки колонок как Excel: пустые → `ColumnN`,
+/// повторы получают числовой суффикс.
+fn unique_headers(raw: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(raw.len());
+    for (i, h) in raw.into_iter().enumerate() {
+        let base = if h.trim().is_empty() {
+            format!("Column{}", i + 1)
+        } else {
+            h
+        };
+        let mut cand = base.clone();
+        let mut n = 2;
+        while out.iter().any(|o| o.eq_ignore_ascii_case(&cand)) {
+            cand = format!("{base}{n}");
+            n += 1;
+        }
+        out.push(cand);
+    }
+    out
+}
+
+impl XlsxEditor {
+    /// Скрытое имя `_xlnm._FilterDatabase` текущего листа, указывающее на `range`.
+    fn set_filter_database(&mut self, range: &str) -> Result<()> {
+        let idx = self.current_sheet_index()?;
+        let value = format!(
+            "{}!{}",
+            quote_sheet_name(&self.current_sheet_name()?),
+            absolute_ref(range)
+        );
+        self.set_defined_name(FILTER_DB, Some(idx), Some(&value), true)
+    }
+
+    /// Вызывается после дозаписи строк ниже `old_last`. Дописанным ячейкам
+    /// сразу проставляется стиль столбца, если это включено; таблицы,
+    /// автофильтр, `<dimension>` и источники сводных только запоминаются и
+    /// растут один раз — в [`Self::flush_appended_ranges`], а не на каждой
+    /// дозаписи (в цикле из `append_row` иначе на каждую строку разбирался
+    /// бы лист, а при автофильтре — и workbook.xml).
+    pub(crate) fn grow_ranges_on_append(&mut self, old_last: u32, written: Option<Rect>) -> Result<()> {
+        self.stamp_column_styles(old_last)?;
+        let new_last = self.last_row;
+        if new_last > old_last {
+            *self.appended_rows.entry(self.sheet_path.clone()).or_default() += new_last - old_last;
+        }
+        let pending = self.pending_growth.get_or_insert(PendingGrowth {
+            old_last,
+            written: None,
+        });
+        pending.old_last = pending.old_last.min(old_last);
+        if let Some(r) = written {
+            pending.written = Some(match pending.written {
+                Some(w) => (w.0.min(r.0), w.1.min(r.1), w.2.max(r.2), w.3.max(r.3)),
+                None => r,
+            });
+        }
+        Ok(())
+    }
+
+    /// Доводит отложенную дозапись текущего листа: таблицы и автофильтр,
+    /// заканчивавшиеся на прежней последней строке, растут до `last_row`
+    /// (с `_xlnm._FilterDatabase` и источниками сводных), а `<dimension>`
+    /// охватывает записанную область. Вызывается перед сохранением, сменой
+    /// листа и правками, которые читают или двигают эти диапазоны.
+    pub(crate) fn flush_appended_ranges(&mut self) -> Result<()> {
+        let Some(PendingGrowth { old_last, written }) = self.pending_growth.take() else {
+            return Ok(());
+        };
+        if let Some(rect) = written {
+            extend_dimension(&mut self.sheet_xml, rect)?;
+        }
+        let new_last = self.last_row;
+        if new_last <= old_last || old_last == 0 {
+            return Ok(());
+        }
+
+        if find_child(&self.sheet_xml, "tableParts")?.is_some() {
+            for t in self.sheet_tables()? {
+                let (c0, r0, c1, r1) = parse_range(&t.range)?;
+                // таблицу со строкой итогов не трогаем: новые строки оказались под итогами
+                if t.totals == 0 && r1 == old_last {
+                    self.set_table_ref(&t.path, (c0, r0, c1, new_last))?;
+                }
+            }
+        }
+
+        if let Some(af) = find_child(&self.sheet_xml, "autoFilter")? {
+            let end = af.start + self.sheet_xml[af.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+            let mut tag = self.sheet_xml[af.start..=end].to_vec();
+            let rect = get_attr(&tag, "ref").map(|r| parse_range(&r)).transpose()?;
+            if let Some((c0, r0, c1, _)) = rect.filter(|r| r.3 == old_last) {
+                let range = format_range((c0, r0, c1, new_last));
+                set_attr(&mut tag, "ref", Some(&range));
+                self.sheet_xml.splice(af.start..=end, tag);
+                self.set_filter_database(&range)?;
+            }
+        }
+        self.grow_pivot_sources(old_last, new_last)
+    }
+
+    /// `(id, displayName)` всех таблиц книги.
+    pub(crate) fn all_tables(&mut self) -> Result<Vec<(u32, String)>> {
+        let mut out = Vec::new();
+        for path in self.part_names()? {
+            if !(path.starts_with("xl/tables/") && path.ends_with(".xml")) {
+                continue;
+            }
+            let Some(xml) = self.read_part(&path)? else {
+                continue;
+            };
+            let tag = table_tag(&xml)?;
+            let id = get_attr(tag, "id").and_then(|v| v.parse().ok()).unwrap_or(0);
+            let name = get_attr(tag, "displayName")
+                .or_else(|| get_attr(tag, "name"))
+                .unwrap_or_default();
+            out.push((id, name));
+        }
+        Ok(out)
+    }
+
+    /// Таблицы, подключённые к текущему листу через его `.rels`.
+    pub(crate) fn sheet_tables(&mut self) -> Result<Vec<TableInfo>> {
+        let mut out = Vec::new();
+        for rel in self.sheet_rels()? {
+            if rel.typ != REL_TABLE {
+                continue;
+            }
+            let xml = self
+                .read_part(&rel.target)?
+                .with_context(|| format!("{} not found", rel.target))?;
+            let tag = table_tag(&xml)?;
+            out.push(TableInfo {
+                name: get_attr(tag, "displayName")
+                    .or_else(|| get_attr(tag, "name"))
+                    .unwrap_or_default(),
+                range: get_attr(tag, "ref").context("table without ref")?,
+                totals: get_attr(tag, "totalsRowCount")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0),
+                path: rel.target,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Переписывает `ref` таблицы и её `<autoFilter>`.
+    pub(crate) fn set_table_ref(&mut self, path: &str, rect: Rect) -> Result<()> {
+        let mut xml = self
+            .read_part(path)?
+            .with_context(|| format!("{path} not found"))?;
+        let tag_range = table_tag_range(&xml)?;
+        let mut tag = xml[tag_range.clone()].to_vec();
+        let totals: u32 = get_attr(&tag, "totalsRowCount")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let (c0, r0, c1, r1) = rect;
+        set_attr(&mut tag, "ref", Some(&format_range(rect)));
+        xml.splice(tag_range, tag);
+
+        // автофильтр таблицы не включает строку итогов
+        let p = tag_prefix(&xml[table_tag_range(&xml)?]);
+        if let Some(af) = find_elem(&xml, &format!("{p}autoFilter"), 0) {
+            let end = af.start + xml[af.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+            let mut af_tag = xml[af.start..=end].to_vec();
+            let af_rect = (c0, r0, c1, r1 - totals);
+            set_attr(&mut af_tag, "ref", Some(&format_range(af_rect)));
+            xml.splice(af.start..=end, af_tag);
+        }
+        self.write_part(path, xml);
+        Ok(())
+    }
+
+    /// Тексты ячеек строки заголовка `row` в колонках `c0..=c1` (`None` — пусто).
+    fn header_texts(&mut self, row: u32, c0: u32, c1: u32) -> Result<Vec<Option<String>>> {
+        self.ensure_shared_strings()?;
+        let sst = self.shared_strings.as_deref().unwrap_or_default();
+        let mut out = vec![None; (c1 - c0 + 1) as usize];
+        for c in read_cells(&self.sheet_xml)?
+            .into_iter()
+            .filter(|c| c.row == row && (c0..=c1).contains(&c.col))
+        {
+            out[(c.col - c0) as usize] = c.display_text(sst);
+        }
+        Ok(out)
+    }
+}
+
+/// Диапазон открывающего тега `<table …>` в части таблицы.
+fn table_tag_range(xml: &[u8]) -> Result<std::ops::Range<usize>> {
+    root_tag_range(xml).context("<table> not found in table part")
+}
+
+fn table_tag(xml: &[u8]) -> Result<&[u8]> {
+    Ok(&xml[table_tag_range(xml)?])
+}
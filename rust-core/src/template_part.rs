@@ -0,0 +1,319 @@
+//! template_part.rs – заполнение шаблонов: плейсхолдеры `{{key}}` в тексте
+//! ячеек и размножение стилизованного блока строк по записям.
+
+use crate::XlsxEditor;
+use crate::cell_xml::{push_col, push_f64, push_int};
+use crate::error::XlsxError;
+use crate::find_part::inline_cell;
+use crate::range_part::{CellMap, ClearMode, patch_sheet_data};
+use crate::sheet_xml::{find_child, find_elem, get_attr, set_attr, tag_prefix};
+use crate::style::col_letter;
+use crate::table_part::{Rect, parse_range};
+use anyhow::Result;
+use quick_xml::escape::partial_escape;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hash;
+
+/// Атрибуты `<row>`, которые [`XlsxEditor::repeat_block`] переносит на копии.
+const ROW_FORMAT_ATTRS: [&str; 4] = ["ht", "customHeight", "s", "customFormat"];
+
+/// Формат ячеек с датой и с датой и временем у привязок (`append_row` в Python и JS).
+pub const DATE_FORMAT: &str = "yyyy-mm-dd";
+pub const DATETIME_FORMAT: &str = "yyyy-mm-dd hh:mm:ss";
+
+/// Значение, подставляемое в ячейку.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellValue {
+    /// Пустая ячейка (стиль сохраняется).
+    Empty,
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    /// Формула; ведущий `=` необязателен.
+    Formula(String),
+}
+
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValue::Empty => Ok(()),
+            CellValue::Text(s) => f.write_str(s),
+            CellValue::Number(n) => write!(f, "{n}"),
+            CellValue::Bool(b) => f.write_str(if *b { "TRUE" } else { "FALSE" }),
+            CellValue::Formula(s) => write!(f, "={}", s.trim_start_matches('=')),
+        }
+    }
+}
+
+impl From<&str> for CellValue {
+    fn from(s: &str) -> Self {
+        CellValue::Text(s.to_owned())
+    }
+}
+impl From<String> for CellValue {
+    fn from(s: String) -> Self {
+        CellValue::Text(s)
+    }
+}
+impl From<f64> for CellValue {
+    fn from(n: f64) -> Self {
+        CellValue::Number(n)
+    }
+}
+impl From<i64> for CellValue {
+    fn from(n: i64) -> Self {
+        CellValue::Number(n as f64)
+    }
+}
+impl From<i32> for CellValue {
+    fn from(n: i32) -> Self {
+        CellValue::Number(n.into())
+    }
+}
+impl From<bool> for CellValue {
+    fn from(b: bool) -> Self {
+        CellValue::Bool(b)
+    }
+}
+
+impl CellValue {
+    /// Значение по тексту, как в [`XlsxEditor::append_row`]: `=` — формула,
+    /// конечное число — число, иначе текст.
+    pub fn parse(s: &str) -> Self {
+        match s.strip_prefix('=') {
+            Some(f) => CellValue::Formula(f.to_owned()),
+            None => match s.parse::<f64>() {
+                Ok(n) if n.is_finite() => CellValue::Number(n),
+                _ => CellValue::Text(s.to_owned()),
+            },
+        }
+    }
+
+    /// Дата как сериал Excel: `days` — дни с долей суток от 1970-01-01.
+    pub fn unix_days(days: f64) -> Self {
+        CellValue::Number(days + 25_569.0)
+    }
+
+    /// `<c>` с этим значением и стилем `s`.
+    pub(crate) fn cell_xml(&self, col: u32, row: u32, s: Option<u32>) -> Vec<u8> {
+        match self {
+            CellValue::Text(t) => return inline_cell(col, row, s, t),
+            CellValue::Number(n) if !n.is_finite() => {
+                return inline_cell(col, row, s, &n.to_string());
+            }
+            _ => {}
+        }
+        let mut out = Vec::with_capacity(32);
+        out.extend_from_slice(b"<c r=\"");
+        push_col(&mut out, col);
+        push_int(&mut out, row);
+        out.push(b'"');
+        if let Some(s) = s {
+            out.extend_from_slice(b" s=\"");
+            push_int(&mut out, s);
+            out.push(b'"');
+        }
+        match self {
+            CellValue::Number(n) => {
+                out.extend_from_slice(b"><v>");
+                push_f64(&mut out, *n);
+                out.extend_from_slice(b"</v></c>");
+            }
+            CellValue::Bool(true) => out.extend_from_slice(b" t=\"b\"><v>1</v></c>"),
+            CellValue::Bool(false) => out.extend_from_slice(b" t=\"b\"><v>0</v></c>"),
+            CellValue::Formula(f) => {
+                out.extend_from_slice(b"><f>");
+                out.extend_from_slice(partial_escape(f.trim_start_matches('=')).as_bytes());
+                out.extend_from_slice(b"</f></c>");
+            }
+            CellValue::Empty | CellValue::Text(_) => out.extend_from_slice(b"/>"),
+        }
+        out
+    }
+}
+
+/// Подставляет значения в `{{key}}` (пробелы вокруг ключа допустимы).
+/// Если текст ячейки — ровно один известный плейсхолдер, значение
+/// возвращается как есть (число остаётся числом); иначе — текст с подстановками.
+/// `None` — известных плейсхолдеров нет.
+fn substitute<'a>(text: &str, lookup: impl Fn(&str) -> Option<&'a CellValue>) -> Option<CellValue> {
+    let mut out = String::new();
+    let mut rest = text;
+    let mut hit = false;
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open + 2..].find("}}").map(|c| open + 2 + c) else {
+            break;
+        };
+        let key = rest[open + 2..close].trim();
+        out.push_str(&rest[..open]);
+        match lookup(key) {
+            Some(v) => {
+                if !hit && open == 0 && close + 2 == rest.len() && rest.len() == text.len() {
+                    return Some(v.clone());
+                }
+                hit = true;
+                out.push_str(&v.to_string());
+            }
+            None => out.push_str(&rest[open..close + 2]),
+        }
+        rest = &rest[close + 2..];
+    }
+    hit.then(|| {
+        out.push_str(rest);
+        CellValue::Text(out)
+    })
+}
+
+impl XlsxEditor {
+    /// Заменяет плейсхолдеры `{{key}}` в текстовых ячейках листа значениями из
+    /// `values`, сохраняя стиль ячеек. Ячейка, целиком состоящая из одного
+    /// плейсхолдера, получает значение своего типа (число, логическое, формула);
+    /// в остальных случаях значение вставляется в текст. Неизвестные ключи
+    /// остаются нетронутыми.
+    pub fn fill_template<K>(
+        &mut self,
+        values: &HashMap<K, CellValue>,
+    ) -> Result<&mut Self, XlsxError>
+    where
+        K: Borrow<str> + Hash + Eq,
+    {
+        self.fill_placeholders(values, None)?;
+        Ok(self)
+    }
+
+    /// Размножает блок строк `range` (`"A5:F6"`) по одному разу на запись:
+    /// первая запись заполняет сам блок, для остальных под ним вставляются
+    /// строки (ссылки ниже сдвигаются, как при [`Self::insert_rows`]), куда
+    /// копируются ячейки, стили, объединения и высота строк блока; относительные
+    /// ссылки в формулах смещаются. Плейсхолдеры каждой копии заполняются из
+    /// своей записи. Без записей строки блока удаляются.
+    pub fn repeat_block<K, R, I>(&mut self, range: &str, records: I) -> Result<&mut Self, XlsxError>
+    where
+        K: Borrow<str> + Hash + Eq,
+        R: Borrow<HashMap<K, CellValue>>,
+        I: IntoIterator<Item = R>,
+    {
+        let (c0, r0, c1, r1) = parse_range(range)?;
+        let h = r1 - r0 + 1;
+        let records: Vec<R> = records.into_iter().collect();
+        let n = records.len() as u32;
+        if n == 0 {
+            return self.delete_rows(&format!("{r0}:{r1}"));
+        }
+        if n > 1 {
+            let row_attrs = self.row_format(r0, r1)?;
+            self.insert_rows(r1 + 1, h * (n - 1))?;
+            for i in 1..n {
+                let top = r0 + i * h;
+                self.copy_range(range, &format!("{}{top}", col_letter(c0)), true)?;
+            }
+            self.apply_row_format(&row_attrs, r0, h, n)?;
+        }
+        for (i, rec) in records.iter().enumerate() {
+            let top = r0 + i as u32 * h;
+            self.fill_placeholders(rec.borrow(), Some((c0, top, c1, top + h - 1)))?;
+        }
+        Ok(self)
+    }
+
+    /// Подстановка `{{key}}` в ячейках внутри `limit`; возвращает число изменённых.
+    fn fill_placeholders<K>(
+        &mut self,
+        values: &HashMap<K, CellValue>,
+        limit: Option<Rect>,
+    ) -> Result<usize>
+    where
+        K: Borrow<str> + Hash + Eq,
+    {
+        let mut cells = CellMap::new();
+        let mut n = 0;
+        for (c, text) in self.text_cells(limit)? {
+            if !matches!(c.t.as_deref(), Some("s" | "inlineStr")) {
+                continue;
+            }
+            if let Some(v) = substitute(&text, |k| values.get(k)) {
+                cells
+                    .entry(c.row)
+                    .or_default()
+                    .insert(c.col, v.cell_xml(c.col, c.row, c.s));
+                n += 1;
+            }
+        }
+        if n > 0
+            && let Some(sd) = find_child(&self.sheet_xml, "sheetData")?
+        {
+            let data = patch_sheet_data(&self.sheet_xml[sd.clone()], &[], ClearMode::All, cells)?;
+            self.sheet_xml.splice(sd, data);
+        }
+        Ok(n)
+    }
+
+    /// Форматные атрибуты строк `r0..=r1`: `row → [(attr, value)]`.
+    fn row_format(
+        &mut self,
+        r0: u32,
+        r1: u32,
+    ) -> Result<BTreeMap<u32, Vec<(&'static str, String)>>> {
+        let mut out = BTreeMap::new();
+        self.for_each_row_tag(|r, tag| {
+            if (r0..=r1).contains(&r) {
+                let attrs: Vec<_> = ROW_FORMAT_ATTRS
+                    .iter()
+                    .filter_map(|&a| Some((a, get_attr(tag, a)?)))
+                    .collect();
+                out.insert(r, attrs);
+            }
+            None
+        })?;
+        Ok(out)
+    }
+
+    /// Переносит атрибуты строк блока (начало `r0`, высота `h`) на его копии 1..n.
+    fn apply_row_format(
+        &mut self,
+        attrs: &BTreeMap<u32, Vec<(&'static str, String)>>,
+        r0: u32,
+        h: u32,
+        n: u32,
+    ) -> Result<()> {
+        if attrs.values().all(Vec::is_empty) {
+            return Ok(());
+        }
+        self.for_each_row_tag(|r, tag| {
+            if r < r0 + h || r >= r0 + n * h {
+                return None;
+            }
+            let src = attrs.get(&(r0 + (r - r0) % h))?;
+            let mut tag = tag.to_vec();
+            for (a, v) in src {
+                set_attr(&mut tag, a, Some(v));
+            }
+            Some(tag)
+        })
+    }
+
+    /// Обходит открывающие теги `<row>`; `f` может вернуть замену тега.
+    pub(crate) fn for_each_row_tag(&mut self, mut f: impl FnMut(u32, &[u8]) -> Option<Vec<u8>>) -> Result<()> {
+        let Some(sd) = find_child(&self.sheet_xml, "sheetData")? else {
+            return Ok(());
+        };
+        let mut xml = self.sheet_xml[sd.clone()].to_vec();
+        let q = format!("{}row", tag_prefix(&xml));
+        let mut from = 0;
+        while let Some(e) = find_elem(&xml, &q, from) {
+            let gt = e.start + memchr::memchr(b'>', &xml[e.start..]).unwrap_or(0) + 1;
+            from = e.end;
+            let Some(r) = get_attr(&xml[e.start..gt], "r").and_then(|r| r.parse().ok()) else {
+                continue;
+            };
+            if let Some(tag) = f(r, &xml[e.start..gt]) {
+                from = e.end - (gt - e.start) + tag.len();
+                xml.splice(e.start..gt, tag);
+            }
+        }
+        self.sheet_xml.splice(sd, xml);
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 #[cfg(test)]
 use crate::{
-    XlsxEditor, scan,
+    XlsxEditor, XlsxError, scan,
     style::{AlignSpec, HorizAlignment, VertAlignment},
 };
 #[cfg(test)]
@@ -203,6 +203,3413 @@ fn add_worksheet() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn autofit_columns() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_autofit.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.append_table_at("A10", [["short", "a considerably longer header"]])?;
+    xl.set_font("B10", "Calibri", 22.0, true, false)?;
+    xl.autofit_columns(None)?;
+    xl.save(file_name_out)?;
+
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    let width = |col: u32| -> f64 {
+        let tag = format!(r#"<col min="{col}" max="{col}" width=""#);
+        let start = xml.find(&tag).expect("col tag") + tag.len();
+        let end = start + xml[start..].find('"').unwrap();
+        xml[start..end].parse().unwrap()
+    };
+    assert!(width(2) > width(1) * 3.0);
+    assert!(xml.contains(r#"bestFit="1""#));
+
+    // ограничение по строкам: только A1:A2 (там числа) → столбец B не трогаем
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.autofit_columns(Some("A1:A2"))?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<col min="1" max="1""#));
+    assert!(!xml.contains(r#"<col min="2""#));
+    Ok(())
+}
+
+#[test]
+fn freeze_panes() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_freeze.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.freeze_panes("B3")?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(
+        r#"<pane xSplit="1" ySplit="2" topLeftCell="B3" activePane="bottomRight" state="frozen"/>"#
+    ));
+    // старое выделение H6 заменено, tabSelected сохранён
+    assert!(!xml.contains(r#"activeCell="H6""#));
+    assert!(xml.contains(r#"<sheetView tabSelected="1" workbookViewId="0">"#));
+
+    xl.freeze_top_row()?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert_eq!(xml.matches("<pane ").count(), 1);
+    assert!(xml.contains(r#"<pane ySplit="1" topLeftCell="A2" activePane="bottomLeft""#));
+
+    // новый лист без <sheetViews>: блок создаётся до <sheetData>
+    xl.add_worksheet("Frozen")?.freeze_first_column()?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    let views = xml.find("<sheetViews>").expect("sheetViews");
+    assert!(views < xml.find("<sheetData>").unwrap());
+
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn sheet_view_options() -> Result<()> {
+    use crate::view_part::SheetViewOptions;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_view.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_sheet_view(SheetViewOptions {
+        show_grid_lines: Some(false),
+        zoom_scale: Some(85),
+        right_to_left: Some(true),
+        selection: Some("c3:d4".into()),
+        ..Default::default()
+    })?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"showGridLines="0""#));
+    assert!(xml.contains(r#"zoomScale="85""#));
+    assert!(xml.contains(r#"rightToLeft="1""#));
+    assert!(xml.contains(r#"activeCell="C3" sqref="C3:D4""#));
+    assert_eq!(xml.matches("<selection").count(), 1);
+
+    // при закреплении выделение попадает в активную панель
+    xl.freeze_top_row()?;
+    xl.set_sheet_view(SheetViewOptions {
+        show_grid_lines: Some(true),
+        active_cell: Some("B10".into()),
+        ..Default::default()
+    })?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(!xml.contains("showGridLines"));
+    assert!(xml.contains(r#"<selection pane="bottomLeft" activeCell="B10" sqref="B10"/>"#));
+
+    assert!(
+        xl.set_sheet_view(SheetViewOptions {
+            zoom_scale: Some(5),
+            ..Default::default()
+        })
+        .is_err()
+    );
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn auto_filter() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_filter.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_auto_filter("A1:C1")?.set_auto_filter("a1:d20")?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert_eq!(xml.matches("<autoFilter").count(), 1);
+    let pos = xml.find(r#"<autoFilter ref="A1:D20"/>"#).expect("autoFilter");
+    assert!(pos > xml.find("</sheetData>").unwrap());
+    if let Some(merge) = xml.find("<mergeCells") {
+        assert!(pos < merge);
+    }
+    let wb = String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?;
+    assert_eq!(wb.matches("_xlnm._FilterDatabase").count(), 1);
+    assert!(wb.contains(
+        r#"<definedName name="_xlnm._FilterDatabase" localSheetId="0" hidden="1">Sheet1!$A$1:$D$20</definedName>"#
+    ));
+    xl.save(file_name_out)?;
+
+    xl.remove_auto_filter()?;
+    assert!(!String::from_utf8(xl.sheet_xml.clone())?.contains("<autoFilter"));
+    assert!(!String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains("_FilterDatabase"));
+    Ok(())
+}
+
+#[test]
+fn add_and_extend_table() -> Result<()> {
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_table.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, &scan(file_name)?[0])?;
+    xl.add_worksheet("Sales")?;
+    xl.append_table(vec![
+        vec!["Region", "Qty", ""],
+        vec!["North", "10", "a"],
+        vec!["South", "20", "b"],
+    ])?;
+    xl.add_table("A1:C3", "Sales", None, None)?;
+
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<tableParts count="1"><tablePart r:id="rId1"/></tableParts>"#));
+    assert!(sheet.contains("xmlns:r="));
+    // пустой заголовок получил имя, как это делает Excel
+    assert!(sheet.contains("Column3"));
+
+    let (path, table) = xl
+        .new_files
+        .iter()
+        .find(|(p, _)| p.starts_with("xl/tables/"))
+        .map(|(p, c)| (p.clone(), String::from_utf8_lossy(c).into_owned()))
+        .expect("table part");
+    assert!(table.contains(r#"name="Sales" displayName="Sales" ref="A1:C3""#));
+    assert!(table.contains(r#"<tableColumn id="3" name="Column3"/>"#));
+    let ct = xl.new_files.iter().find(|(p, _)| p == "[Content_Types].xml").unwrap();
+    assert!(String::from_utf8_lossy(&ct.1).contains(&format!("/{path}")));
+
+    // имена уникальны, диапазоны не пересекаются
+    assert!(xl.add_table("E1:F3", "sales", None, None).is_err());
+    assert!(xl.add_table("B2:D5", "Other", None, None).is_err());
+
+    // дозапись под таблицей растит её сама, extend_table добавляет строки явно
+    xl.append_row(vec!["West", "5", "c"])?;
+    xl.extend_table("Sales", 1)?;
+    let table = xl.new_files.iter().find(|(p, _)| *p == path).unwrap();
+    let table = String::from_utf8_lossy(&table.1);
+    assert!(table.contains(r#"ref="A1:C5""#));
+    assert!(table.contains(r#"<autoFilter ref="A1:C5"/>"#));
+
+    xl.add_table("E1:F2", "Second", Some("TableStyleLight9"), Some(&["Key", "Value"]))?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<tableParts count="2">"#));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn append_grows_table_filter_and_dimension() -> Result<()> {
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_table_grow.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, &scan(file_name)?[0])?;
+    xl.add_worksheet("Grow")?;
+    xl.append_table(vec![vec!["Name", "Qty"], vec!["a", "1"], vec!["b", "2"]])?;
+    xl.add_table("A1:B3", "Grow", None, None)?;
+    xl.append_table_at("E1", vec![vec!["Key"], vec!["x"]])?;
+    xl.set_auto_filter("E1:E2")?;
+
+    // строка итогов/фильтр на другой высоте не растут — здесь оба кончаются на 3 и 2
+    xl.append_table(vec![vec!["c", "3", "", "", "y"]])?;
+    xl.append_table(vec![vec!["d", "4", "", "", "z"]])?;
+    // до сохранения (или другой правки диапазонов) рост только запомнен
+    let grown = |xl: &XlsxEditor| xl.new_files.iter().any(|(p, c)| p.starts_with("xl/tables/") && String::from_utf8_lossy(c).contains(r#"ref="A1:B5""#));
+    assert!(!grown(&xl));
+    xl.flush_appended_ranges()?;
+    assert!(grown(&xl));
+    let table = xl
+        .new_files
+        .iter()
+        .find(|(p, _)| p.starts_with("xl/tables/"))
+        .map(|(_, c)| String::from_utf8_lossy(c).into_owned())
+        .unwrap();
+    assert!(table.contains(r#"ref="A1:B5""#));
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<autoFilter ref="E1:E2"/>"#));
+    assert!(sheet.contains(r#"<dimension ref="A1:E5"/>"#));
+
+    xl.append_row(vec!["e", "5"])?;
+    xl.flush_appended_ranges()?;
+    let table = xl
+        .new_files
+        .iter()
+        .find(|(p, _)| p.starts_with("xl/tables/"))
+        .map(|(_, c)| String::from_utf8_lossy(c).into_owned())
+        .unwrap();
+    assert!(table.contains(r#"ref="A1:B6""#));
+    assert!(table.contains(r#"<autoFilter ref="A1:B6"/>"#));
+    assert!(String::from_utf8(xl.sheet_xml.clone())?.contains(r#"<dimension ref="A1:E6"/>"#));
+
+    // фильтр, заканчивающийся на последней строке, тоже растёт
+    xl.set_auto_filter("E1:E6")?;
+    xl.append_row(vec!["f", "6"])?;
+    xl.flush_appended_ranges()?;
+    assert!(String::from_utf8(xl.sheet_xml.clone())?.contains(r#"<autoFilter ref="E1:E7"/>"#));
+    let wb = String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?;
+    assert!(wb.contains("Grow!$E$1:$E$7"));
+
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn conditional_formatting() -> Result<()> {
+    use crate::conditional::{CfOperator, CfStyle, ConditionalFormat};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_cf.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let red = CfStyle {
+        font_color: Some("9C0006".into()),
+        fill: Some("#FFC7CE".into()),
+        ..Default::default()
+    };
+    xl.add_conditional_format(
+        ConditionalFormat::new("B2:B20")
+            .cell_is(CfOperator::LessThan, "0", red.clone())
+            .between("10", "20", red.clone())
+            .data_bar("638EC6"),
+    )?;
+    xl.add_conditional_format(
+        ConditionalFormat::new("C2:C20")
+            .expression("=MOD(ROW(),2)=0", CfStyle { bold: true, ..Default::default() })
+            .color_scale("F8696B", Some("FFEB84"), "63BE7B")
+            .icon_set("3TrafficLights1", false, true),
+    )?;
+
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert_eq!(xml.matches("<conditionalFormatting ").count(), 2);
+    assert!(xml.contains(
+        r#"<cfRule type="cellIs" dxfId="0" priority="1" operator="lessThan"><formula>0</formula></cfRule>"#
+    ));
+    // одинаковое оформление → один и тот же dxf
+    assert!(xml.contains(r#"dxfId="0" priority="2" operator="between"><formula>10</formula><formula>20</formula>"#));
+    assert!(xml.contains(r#"<cfRule type="expression" dxfId="1" priority="4"><formula>MOD(ROW(),2)=0</formula>"#));
+    assert!(xml.contains(r#"<cfvo type="percent" val="33"/>"#));
+    assert!(xml.find("<conditionalFormatting").unwrap() > xml.find("</sheetData>").unwrap());
+
+    let styles = String::from_utf8(xl.styles_xml.get(&xl.src)?.clone())?;
+    assert!(styles.contains(r#"<dxfs count="2"><dxf><font><color rgb="FF9C0006"/></font><fill><patternFill><bgColor rgb="FFFFC7CE"/></patternFill></fill></dxf><dxf><font><b/></font></dxf></dxfs>"#));
+
+    assert!(xl.add_conditional_format(ConditionalFormat::new("A1")).is_err());
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn data_validation() -> Result<()> {
+    use crate::conditional::CfOperator;
+    use crate::validation::{ErrorStyle, ValidationRule, ValidationSpec};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_validation.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let mut list = ValidationSpec::new(ValidationRule::List(vec!["Yes".into(), "No".into()]));
+    list.prompt_message = Some("Pick one".into());
+    xl.set_data_validation("A2:A50", list)?;
+
+    let mut qty = ValidationSpec::new(ValidationRule::Whole {
+        operator: CfOperator::Between,
+        value1: "1".into(),
+        value2: Some("100".into()),
+    });
+    qty.error_style = ErrorStyle::Warning;
+    qty.error_message = Some("1..100 & whole".into());
+    xl.set_data_validation("B2:B50", qty)?;
+    xl.set_data_validation(
+        "C2:C50",
+        ValidationSpec::new(ValidationRule::Date {
+            operator: CfOperator::GreaterThanOrEqual,
+            value1: "2024-01-31".into(),
+            value2: None,
+        }),
+    )?;
+    // тот же диапазон — правило заменяется
+    xl.set_data_validation("A2:A50", ValidationSpec::new(ValidationRule::ListRange("=$Z$1:$Z$3".into())))?;
+
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<dataValidations count="3">"#));
+    assert!(xml.contains(r#"sqref="A2:A50"><formula1>$Z$1:$Z$3</formula1>"#));
+    assert!(!xml.contains("Pick one"));
+    assert!(xml.contains(r#"<dataValidation type="whole" errorStyle="warning" allowBlank="1""#));
+    assert!(xml.contains(r#"error="1..100 &amp; whole""#));
+    assert!(xml.contains("<formula1>1</formula1><formula2>100</formula2>"));
+    assert!(xml.contains(r#"operator="greaterThanOrEqual""#));
+    assert!(xml.contains("<formula1>DATE(2024,1,31)</formula1>"));
+    assert!(xml.find("<dataValidations").unwrap() > xml.find("</sheetData>").unwrap());
+
+    let bad = ValidationSpec::new(ValidationRule::Decimal {
+        operator: CfOperator::Between,
+        value1: "1".into(),
+        value2: None,
+    });
+    assert!(xl.set_data_validation("D1", bad).is_err());
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn merge_unmerge_and_overlap() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_merge.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.merge_cells("A20:C20")?;
+    xl.merge_cells("e20:f21")?;
+    xl.merge_cells("A20:C20")?; // повтор — без изменений
+    assert_eq!(xl.get_merged_ranges()?, vec!["A20:C20", "E20:F21"]);
+    assert!(xl.merge_cells("B19:B20").is_err());
+    assert!(xl.merge_cells("F21:G22").is_err());
+
+    xl.unmerge_cells("F21")?;
+    assert_eq!(xl.get_merged_ranges()?, vec!["A20:C20"]);
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<mergeCells count="1">"#));
+
+    xl.unmerge_cells("A1:Z100")?;
+    assert!(xl.get_merged_ranges()?.is_empty());
+    assert!(!String::from_utf8(xl.sheet_xml.clone())?.contains("<mergeCells"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn insert_rows_shifts_references() -> Result<()> {
+    use crate::conditional::{CfStyle, ConditionalFormat};
+    use crate::validation::{ValidationRule, ValidationSpec};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_insert_rows.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_cell("H30", "=SUM(H2:H4)+$A$3+Sheet1!A2+'Other'!A2&\"B3\"")?;
+    xl.set_cell("H31", 7)?;
+    xl.merge_cells("J3:K3")?;
+    xl.set_data_validation("J2:J4", ValidationSpec::new(ValidationRule::Custom("J2>$A$3".into())))?;
+    xl.add_conditional_format(ConditionalFormat::new("K2:K10").expression("K2>0", CfStyle::default()))?;
+    let last = xl.last_row;
+
+    xl.insert_rows(3, 2)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<c r="H32""#));
+    assert!(xml.contains("<f>SUM(H2:H6)+$A$5+Sheet1!A2+'Other'!A2&amp;\"B3\"</f>"));
+    assert!(xml.contains(r#"<c r="H33""#));
+    assert_eq!(xl.get_merged_ranges()?, vec!["J5:K5"]);
+    assert!(xml.contains(r#"sqref="J2:J6"><formula1>J2&gt;$A$5</formula1>"#));
+    assert!(xml.contains(r#"<conditionalFormatting sqref="K2:K12">"#));
+    assert_eq!(xl.last_row, last + 2);
+    assert!(xl.insert_rows(0, 1).is_err());
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn delete_and_clear_rows_and_columns() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_delete.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_cell("H30", "=SUM(H2:H10)+J5+$K$40")?;
+    xl.set_cell("J40", "=J5")?;
+    xl.merge_cells("J3:K6")?;
+    xl.set_column_width("K", 30.0)?;
+
+    xl.delete_rows("4:5")?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<c r="H28""#));
+    assert!(xml.contains("<f>SUM(H2:H8)+#REF!+$K$38</f>"));
+    assert!(xml.contains("<f>#REF!</f>"));
+    assert_eq!(xl.get_merged_ranges()?, vec!["J3:K4"]);
+
+    xl.delete_columns("I:J")?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains("<f>SUM(H2:H8)+#REF!+$I$38</f>"));
+    assert!(!xml.contains(r#"<c r="J38""#));
+    assert_eq!(xl.get_merged_ranges()?, vec!["I3:I4"]);
+    assert!(xml.contains(r#"<col min="9" max="9""#));
+
+    xl.clear_rows("28")?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(!xml.contains(r#"<row r="28""#));
+    assert!(xl.delete_rows("0").is_err());
+    assert!(xl.delete_columns("1:2").is_err());
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn insert_columns_shifts_references() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_insert_columns.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_cell("H30", "=SUM(A30:D30)+$C$5+B:C")?;
+    xl.set_column_width("D", 25.0)?;
+    xl.merge_cells("B40:C40")?;
+
+    xl.insert_columns("C", 2)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<c r="J30""#));
+    assert!(xml.contains("<f>SUM(A30:F30)+$E$5+B:E</f>"));
+    assert!(xml.contains(r#"<col min="6" max="6""#));
+    assert_eq!(xl.get_merged_ranges()?, vec!["B40:E40"]);
+
+    xl.add_table("A50:C52", "Cols", None, Some(&["a", "b", "c"]))?;
+    assert!(xl.insert_columns("B", 1).is_err());
+    xl.insert_columns("A", 1)?;
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn copy_and_move_range() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_copy_range.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_cell("H29", 3)?;
+    xl.set_cell("H30", "=H29*2+$A$1")?;
+    xl.set_cell("I29", "x")?;
+    xl.merge_cells("H31:I31")?;
+
+    xl.copy_range("H29:I31", "K29", true)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<c r="K30"><f>K29*2+$A$1</f></c>"#));
+    assert!(xml.contains(r#"<c r="L29" t="inlineStr"><is><t>x</t></is></c>"#));
+    assert!(xml.contains(r#"<c r="H30"><f>H29*2+$A$1</f></c>"#));
+    assert_eq!(xl.get_merged_ranges()?, vec!["H31:I31", "K31:L31"]);
+
+    xl.copy_range("H30", "N30", false)?;
+    assert!(String::from_utf8(xl.sheet_xml.clone())?.contains(r#"<c r="N30"><f>H29*2+$A$1</f></c>"#));
+
+    xl.move_range("H29:I31", "H40", true)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(!xml.contains(r#"<c r="H29""#));
+    assert!(xml.contains(r#"<c r="H41"><f>H40*2+$A$1</f></c>"#));
+    assert_eq!(xl.get_merged_ranges()?, vec!["K31:L31", "H42:I42"]);
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn clear_range_modes() -> Result<()> {
+    use crate::range_part::ClearMode;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_clear_range.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    for (c, v) in [("H29", "1"), ("I29", "2"), ("H30", "=H29"), ("H31", "x")] {
+        xl.set_cell(c, v)?;
+    }
+    xl.set_fill("H29:I29", "FFFF00")?;
+    xl.merge_cells("H32:I32")?;
+
+    xl.clear_range("H29:I29", ClearMode::Contents)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<c r="H29" s="1"/><c r="I29" s="1"/>"#));
+
+    xl.clear_range("H29:I29", ClearMode::Formats)?;
+    assert!(!String::from_utf8(xl.sheet_xml.clone())?.contains(r#"<c r="H29""#));
+
+    xl.clear_range("H30:I32", ClearMode::All)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(!xml.contains(r#"<c r="H30""#) && !xml.contains(r#"<row r="31""#));
+    assert!(xl.get_merged_ranges()?.is_empty());
+    assert_eq!("formats".parse::<ClearMode>()?, ClearMode::Formats);
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn find_and_replace_all() -> Result<()> {
+    use crate::find_part::FindOptions;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_find_replace.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    for (c, v) in [("H29", "Dear {{customer_name}}"), ("H30", "{{CUSTOMER_NAME}}"), ("H31", "=H29")] {
+        xl.set_cell(c, v)?;
+    }
+    xl.set_fill("H29", "FFFF00")?;
+    assert_eq!(xl.find("{{customer_name}}")?, ["H29", "H30"]);
+
+    let exact = FindOptions { match_case: true, ..Default::default() };
+    assert_eq!(xl.replace_all("{{customer_name}}", "Иван & Co", &exact)?, 1);
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<c r="H29" s="1" t="inlineStr"><is><t xml:space="preserve">Dear Иван &amp; Co</t></is></c>"#));
+
+    let whole = FindOptions { whole_cell: true, range: Some("H30:H31".into()), ..Default::default() };
+    assert!(xl.find_with("customer", &whole)?.is_empty());
+    assert_eq!(xl.replace_all("{{customer_name}}", "Петр", &whole)?, 1);
+    assert_eq!(xl.find("петр")?, ["H30"]);
+    #[cfg(feature = "regex")]
+    {
+        let re = FindOptions { regex: true, ..Default::default() };
+        assert_eq!(xl.replace_all(r"(\w+) & (\w+)", "$2 & $1", &re)?, 1);
+        assert_eq!(xl.find("co & иван")?, ["H29"]);
+    }
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn fill_template_and_repeat_block() -> Result<()> {
+    use crate::template_part::CellValue;
+    use std::collections::HashMap;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_template.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    for (c, v) in [
+        ("H20", "Dear {{ customer }}, {{unknown}}"),
+        ("I20", "{{total}}"),
+        ("H21", "{{item}}"),
+        ("I21", "{{qty}}"),
+        ("J21", "=I21*2"),
+        ("H23", "=SUM(I21:I22)"),
+    ] {
+        xl.set_cell(c, v)?;
+    }
+    xl.set_fill("H21:J21", "FFFF00")?;
+
+    let values = HashMap::from([("customer", CellValue::from("ACME")), ("total", 12.5.into())]);
+    xl.fill_template(&values)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains("Dear ACME, {{unknown}}"));
+    assert!(xml.contains(r#"<c r="I20"><v>12.5</v></c>"#));
+
+    let rows = [
+        HashMap::from([("item", CellValue::from("Pen")), ("qty", 2.into())]),
+        HashMap::from([("item", CellValue::from("Ink")), ("qty", 3.into())]),
+        HashMap::from([("item", CellValue::from("Pad")), ("qty", 5.into())]),
+    ];
+    xl.repeat_block("H21:J21", &rows)?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(xml.contains(r#"<c r="I23" s="1"><v>5</v></c>"#));
+    assert!(xml.contains(r#"<c r="J23" s="1"><f>I23*2</f></c>"#));
+    assert!(xml.contains(r#"<c r="H25"><f>SUM(I21:I24)</f></c>"#));
+
+    xl.repeat_block("H21:J21", Vec::<HashMap<&str, CellValue>>::new())?;
+    assert!(!String::from_utf8(xl.sheet_xml.clone())?.contains("Pen"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn manage_worksheets() -> Result<()> {
+    use crate::sheets_part::SheetVisibility;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_sheets.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.append_table_at("H40", [["Name", "Qty"], ["a", "1"]])?;
+    xl.add_table("H40:I41", "Items", None, None)?;
+    xl.set_auto_filter("A1:D7")?;
+    xl.add_worksheet("Data")?;
+    xl.set_cell("A1", "=Sheet1!D1*2+SUM('Sheet1'!D1:D7)")?;
+
+    xl.rename_worksheet("sheet1", "My Sheet")?;
+    assert!(String::from_utf8(xl.sheet_xml.clone())?.contains("<f>'My Sheet'!D1*2+SUM('My Sheet'!D1:D7)</f>"));
+    assert!(xl.rename_worksheet("Data", "my sheet").is_err());
+    assert!(xl.rename_worksheet("Data", "a/b").is_err());
+
+    xl.copy_worksheet("My Sheet", "Copy")?;
+    let names = xl.part_names()?;
+    assert!(names.contains(&"xl/tables/table2.xml".to_owned()));
+    let table = String::from_utf8(xl.read_part("xl/tables/table2.xml")?.unwrap())?;
+    assert!(table.contains(r#"displayName="Items_2""#));
+    let wb = String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?;
+    assert!(wb.contains(r#"localSheetId="2" hidden="1">Copy!$A$1:$D$7<"#));
+
+    xl.move_worksheet("Copy", 0)?;
+    let order: Vec<String> = xl.sheet_entries()?.into_iter().map(|s| s.name).collect();
+    assert_eq!(order, ["Copy", "My Sheet", "Data"]);
+    assert!(String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains(r#"localSheetId="0""#));
+
+    xl.hide_worksheet("Copy", SheetVisibility::VeryHidden)?;
+    assert!(String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains(r#"state="veryHidden""#));
+
+    xl.delete_worksheet("My Sheet")?;
+    assert!(!xl.part_names()?.contains(&"xl/tables/table1.xml".to_owned()));
+    xl.with_worksheet("Data")?;
+    assert!(String::from_utf8(xl.sheet_xml.clone())?.contains("<f>#REF!*2+SUM(#REF!)</f>"));
+    assert!(xl.delete_worksheet("Data").is_err());
+    xl.save(file_name_out)?;
+
+    let mut copy = XlsxEditor::open(file_name_out, "Copy")?;
+    assert_eq!(copy.sheet_tables()?[0].name, "Items_2");
+    Ok(())
+}
+
+#[test]
+fn tab_color_and_sheet_properties() -> Result<()> {
+    use crate::sheets_part::SheetProperties;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_sheet_props.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.add_worksheet("Data")?;
+    xl.with_worksheet("Sheet1")?;
+    xl.set_sheet_properties(
+        "Sheet1",
+        SheetProperties {
+            summary_below: Some(false),
+            ..Default::default()
+        },
+    )?;
+    xl.set_tab_color("Sheet1", "#00B050")?;
+    xl.set_sheet_properties(
+        "Sheet1",
+        SheetProperties {
+            default_col_width: Some(12.5),
+            default_row_height: Some(20.0),
+            summary_below: Some(true),
+            summary_right: Some(false),
+        },
+    )?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    let pr = sheet.find("<sheetPr").unwrap();
+    let tab = sheet.find(r#"<tabColor rgb="FF00B050"/>"#).unwrap();
+    let outline = sheet.find(r#"<outlinePr summaryRight="0"/>"#).unwrap();
+    assert!(pr < tab && tab < outline);
+    assert!(pr < sheet.find("<dimension").unwrap_or(usize::MAX));
+    assert!(sheet.contains(r#"defaultColWidth="12.5""#));
+    assert!(sheet.contains(r#"defaultRowHeight="20""#));
+
+    xl.set_tab_color("Data", "FF0000")?;
+    let data = String::from_utf8(xl.read_part("xl/worksheets/sheet2.xml")?.unwrap())?;
+    assert!(data.contains(r#"<tabColor rgb="FFFF0000"/>"#));
+    assert!(xl.set_tab_color("Data", "red").is_err());
+    assert!(
+        xl.set_sheet_properties(
+            "Data",
+            SheetProperties {
+                default_row_height: Some(500.0),
+                ..Default::default()
+            },
+        )
+        .is_err()
+    );
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn part_registry_content_types() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_parts.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.add_worksheet("Notes")?;
+    xl.copy_worksheet("Sheet1", "Copy")?;
+    let ct = |xl: &mut XlsxEditor| -> Result<String> {
+        Ok(String::from_utf8(xl.read_part("[Content_Types].xml")?.unwrap())?)
+    };
+    let types = ct(&mut xl)?;
+    for sheet in xl.sheet_entries()? {
+        assert!(types.contains(&format!(r#"PartName="/{}""#, sheet.path)));
+    }
+
+    // vml регистрируется один раз через <Default>, связь пишется в rels листа
+    let (vml, rid) = xl.create_part("xl/worksheets/sheet1.xml", "vmlDrawing", b"<xml/>".to_vec())?;
+    assert_eq!(vml, "xl/drawings/vmlDrawing1.vml");
+    let (vml2, _) = xl.create_part("xl/worksheets/sheet1.xml", "vmlDrawing", b"<xml/>".to_vec())?;
+    assert_eq!(vml2, "xl/drawings/vmlDrawing2.vml");
+    let types = ct(&mut xl)?;
+    assert_eq!(types.matches(r#"Extension="vml""#).count(), 1);
+    assert!(types.find(r#"Extension="vml""#) < types.find("<Override"));
+    let rels = String::from_utf8(xl.read_part("xl/worksheets/_rels/sheet1.xml.rels")?.unwrap())?;
+    assert!(rels.contains(&format!(r#"Id="{rid}""#)));
+    assert!(rels.contains(r#"Target="../drawings/vmlDrawing1.vml""#));
+
+    // связи книги идут в workbook.xml.rels
+    let (sst, _) = xl.create_part("xl/workbook.xml", "sharedStrings", b"<sst/>".to_vec())?;
+    assert_eq!(sst, "xl/sharedStrings.xml");
+    assert!(String::from_utf8(xl.rels_xml.get(&xl.src)?.clone())?.contains(r#"Target="sharedStrings.xml""#));
+    assert!(ct(&mut xl)?.contains(r#"PartName="/xl/sharedStrings.xml""#));
+    assert!(xl.create_part("xl/workbook.xml", "nope", Vec::new()).is_err());
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn sheet_as_template() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_template_sheet.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.freeze_panes("A3")?;
+    xl.set_column_width("B", 24.0)?;
+    xl.set_cell("A1", "Report")?;
+    xl.set_cell("A2", "=Sheet1!D3")?;
+    xl.merge_cells("A1:C1")?;
+    xl.merge_cells("A5:B5")?;
+    xl.set_defined_name("_xlnm.Print_Titles", Some(0), Some("Sheet1!$1:$2"), false)?;
+    xl.set_defined_name("_xlnm.Print_Area", Some(0), Some("Sheet1!$A$1:$D$7"), false)?;
+    xl.duplicate_sheet_as_template("Sheet1", "May")?;
+    assert!(xl.duplicate_sheet_as_template("Sheet1", "may").is_err());
+    xl.save(file_name_out)?;
+
+    let may = XlsxEditor::open(file_name_out, "May")?;
+    let sheet = String::from_utf8(may.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<row r="2""#));
+    assert!(!sheet.contains(r#"<row r="3""#));
+    assert!(sheet.contains("<f>May!D3</f>"));
+    assert!(sheet.contains(r#"ySplit="2""#));
+    assert!(sheet.contains(r#"width="24""#));
+    assert!(!sheet.contains("tabSelected"));
+    assert_eq!(may.get_merged_ranges()?, ["A1:C1"]);
+    let wb = String::from_utf8(may.workbook_xml.get(&may.src)?.clone())?;
+    assert!(wb.contains(r#"localSheetId="1">May!$1:$2<"#));
+    assert!(!wb.contains("May!$A$1"));
+    Ok(())
+}
+
+#[test]
+fn active_sheet() -> Result<()> {
+    use crate::sheets_part::SheetVisibility;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_active.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.add_worksheet("Jan")?;
+    xl.add_worksheet("Feb")?;
+    xl.set_active_sheet("Feb")?;
+    assert!(String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains(r#"activeTab="2""#));
+    assert!(String::from_utf8(xl.sheet_xml.clone())?.contains(r#"tabSelected="1""#));
+    let first = String::from_utf8(xl.read_part("xl/worksheets/sheet1.xml")?.unwrap())?;
+    assert!(!first.contains("tabSelected"));
+
+    xl.hide_worksheet("Jan", SheetVisibility::Hidden)?;
+    assert!(xl.set_active_sheet("Jan").is_err());
+    xl.set_active_sheet("Sheet1")?;
+    assert!(String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains(r#"activeTab="0""#));
+    assert!(!String::from_utf8(xl.sheet_xml.clone())?.contains("tabSelected"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn sheet_and_workbook_protection() -> Result<()> {
+    use crate::protection_part::{PasswordHash, SheetPermissions};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_protection.xlsx";
+
+    let h = PasswordHash::with_salt("secret", &(0..16).collect::<Vec<u8>>());
+    assert_eq!(h.legacy, "DAA7");
+    assert_eq!(h.salt, "AAECAwQFBgcICQoLDA0ODw==");
+    assert_eq!(
+        h.hash,
+        "M5SOVnbQG4SHyBnRVAYzAx8mPtxyyzMuWxcMv7tkyFO3MBXX9OJjklwPglNHdoHVkKPm4MPfUblqHmAsXfF5HA=="
+    );
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_auto_filter("A1:D7")?;
+    xl.protect_sheet(
+        "Sheet1",
+        Some("password"),
+        SheetPermissions {
+            sort: true,
+            auto_filter: true,
+            select_locked_cells: false,
+            ..Default::default()
+        },
+    )?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"algorithmName="SHA-512""#));
+    assert!(sheet.contains(r#"spinCount="100000" password="83AF" sheet="1""#));
+    assert!(sheet.contains(r#"selectLockedCells="1" sort="0" autoFilter="0"/>"#));
+    assert!(!sheet.contains("formatCells"));
+    // <sheetProtection> идёт сразу после <sheetData>, до <autoFilter>
+    assert!(sheet.contains("</sheetData><sheetProtection"));
+    assert!(sheet.find("<sheetProtection") < sheet.find("<autoFilter"));
+
+    xl.protect_sheet("Sheet1", None, SheetPermissions::default())?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert_eq!(sheet.matches("<sheetProtection").count(), 1);
+    assert!(sheet.contains(r#"<sheetProtection sheet="1" objects="1" scenarios="1"/>"#));
+
+    xl.protect_workbook(Some("secret"))?;
+    let wb = String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?;
+    assert!(wb.contains(r#"workbookPassword="DAA7" lockStructure="1"/>"#));
+    assert!(wb.contains(r#"workbookAlgorithmName="SHA-512""#));
+    assert!(wb.find("<workbookProtection") < wb.find("<bookViews"));
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    xl.unprotect_sheet("Sheet1")?.unprotect_workbook()?;
+    assert!(!String::from_utf8(xl.sheet_xml.clone())?.contains("sheetProtection"));
+    assert!(!String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains("workbookProtection"));
+    Ok(())
+}
+
+#[test]
+fn cell_protection_flags() -> Result<()> {
+    use crate::protection_part::SheetPermissions;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_cell_protection.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_cell_protection("B2:C3", false, false)?;
+    xl.set_cell_protection("D1", true, true)?;
+    // последующая смена заливки не сбрасывает флаги
+    xl.set_fill("B2", "FFFF00")?;
+    xl.protect_sheet("Sheet1", None, SheetPermissions::default())?;
+
+    let styles = String::from_utf8(xl.styles_xml.get(&xl.src)?.clone())?;
+    assert!(styles.contains(r#"applyProtection="1"><protection locked="0"/></xf>"#));
+    assert!(styles.contains(r#"<protection hidden="1"/>"#));
+    let style_of = |xl: &XlsxEditor, cell: &str| -> Option<u32> {
+        let sheet = String::from_utf8(xl.sheet_xml.clone()).unwrap();
+        let at = sheet.find(&format!(r#"<c r="{cell}""#))?;
+        let tag = &sheet[at..at + sheet[at..].find('>')?];
+        crate::sheet_xml::get_attr(tag.as_bytes(), "s")?.parse().ok()
+    };
+    let xf = |sid: Option<u32>| -> String {
+        let xfs = &styles[styles.find("<cellXfs").unwrap()..styles.find("</cellXfs>").unwrap()];
+        let start = xfs.match_indices("<xf ").nth(sid.unwrap() as usize).unwrap().0;
+        let end = start + xfs[start..].find("</xf>").unwrap_or(0);
+        xfs[start..end].to_owned()
+    };
+    let b2 = xf(style_of(&xl, "B2"));
+    assert!(b2.contains("applyFill") && b2.contains(r#"<protection locked="0"/>"#));
+    assert!(xf(style_of(&xl, "C2")).contains(r#"<protection locked="0"/>"#));
+    assert!(xf(style_of(&xl, "D1")).contains(r#"<protection hidden="1"/>"#));
+    assert_ne!(style_of(&xl, "B2"), style_of(&xl, "C2"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn page_setup_and_print_titles() -> Result<()> {
+    use crate::print_part::{Orientation, PageMargins, PageSetup};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_page_setup.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_page_setup(PageSetup {
+        orientation: Some(Orientation::Landscape),
+        paper_size: Some(9),
+        fit_to_pages: Some((1, 0)),
+        margins: Some(PageMargins {
+            left: 0.5,
+            ..Default::default()
+        }),
+        center_horizontally: Some(true),
+        ..Default::default()
+    })?;
+    xl.set_print_area("a1:d7")?;
+    xl.set_print_titles(Some("1:2"), Some("A"))?;
+    assert!(
+        xl.set_page_setup(PageSetup {
+            scale: Some(5),
+            ..Default::default()
+        })
+        .is_err()
+    );
+
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<pageSetUpPr fitToPage="1"/>"#));
+    assert!(sheet.contains(r#"<printOptions horizontalCentered="1"/><pageMargins left="0.5" right="0.7" top="0.75" bottom="0.75" header="0.3" footer="0.3"/>"#));
+    assert!(sheet.contains(r#"paperSize="9""#) && sheet.contains(r#"fitToHeight="0""#));
+    assert!(sheet.contains(r#"orientation="landscape""#) && !sheet.contains("fitToWidth"));
+    let wb = String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?;
+    assert!(wb.contains(r#"localSheetId="0">Sheet1!$A$1:$D$7<"#));
+    assert!(wb.contains(">Sheet1!$A:$A,Sheet1!$1:$2<"));
+
+    // масштаб снимает «вписать в страницы»
+    xl.set_page_setup(PageSetup {
+        scale: Some(80),
+        ..Default::default()
+    })?;
+    xl.set_print_titles(None, None)?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains("<pageSetUpPr/>") && sheet.contains(r#"scale="80""#));
+    assert!(!String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains("Print_Titles"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn header_footer_and_page_breaks() -> Result<()> {
+    use crate::print_part::HeaderFooter;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_header_footer.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let page = format!("Page {} of {}", HeaderFooter::PAGE, HeaderFooter::PAGES);
+    assert_eq!(HeaderFooter::sections("Q1", "", "&&Co"), "&LQ1&R&&Co");
+    xl.set_header_footer(HeaderFooter {
+        header: Some(HeaderFooter::sections("Acme & Co", HeaderFooter::SHEET, "")),
+        footer: Some(HeaderFooter::sections("", &page, "")),
+        first_header: Some("&CCover".into()),
+        different_first: true,
+        ..Default::default()
+    })?;
+    xl.insert_page_break(40)?;
+    xl.insert_page_break(20)?;
+    xl.insert_page_break(40)?;
+    assert!(xl.insert_page_break(0).is_err());
+
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<headerFooter differentFirst="1"><oddHeader>&amp;LAcme &amp; Co&amp;C&amp;A</oddHeader><oddFooter>&amp;CPage &amp;P of &amp;N</oddFooter><firstHeader>&amp;CCover</firstHeader></headerFooter>"#));
+    assert!(sheet.contains(r#"<rowBreaks count="2" manualBreakCount="2"><brk id="20" max="16383" man="1"/><brk id="40" max="16383" man="1"/></rowBreaks>"#));
+    // разрывы идут после колонтитулов
+    assert!(sheet.find("<headerFooter").unwrap() < sheet.find("<rowBreaks").unwrap());
+
+    xl.set_header_footer(HeaderFooter::default())?;
+    assert!(!String::from_utf8(xl.sheet_xml.clone())?.contains("headerFooter"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn group_rows_and_columns() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_outline.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.group_rows("3:5", 1, true)?;
+    xl.group_rows("4", 2, false)?;
+    xl.group_columns("B:C", 1, true)?;
+    assert!(xl.group_rows("1:2", 8, false).is_err());
+
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    // строки 3 не было — она создаётся на своём месте
+    assert!(sheet.contains(r#"</row><row r="3" outlineLevel="1" hidden="1"></row><row r="4""#));
+    assert!(sheet.contains(r#"<row r="4" spans="1:4" x14ac:dyDescent="0.25" outlineLevel="2" hidden="1">"#));
+    assert!(sheet.contains(r#"<row r="6""#) && sheet.contains(r#"collapsed="1""#));
+    assert!(sheet.contains(r#"<col min="2" max="3" hidden="1" outlineLevel="1"/><col min="4" max="4" collapsed="1"/>"#));
+    assert!(sheet.contains(r#"outlineLevelRow="2" outlineLevelCol="1""#));
+
+    // уровень 0 снимает группировку столбцов, скрытые столбцы остаются скрытыми
+    xl.group_columns("B:C", 0, false)?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<cols><col min="2" max="3" hidden="1"/></cols>"#));
+    assert!(!sheet.contains("outlineLevelCol"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn insert_image_png() -> Result<()> {
+    use crate::image_part::ImageOptions;
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_image.xlsx";
+    // прозрачный PNG 1x1
+    let png = STANDARD.decode(
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=",
+    )?;
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_column_width("B", 20.0)?;
+    xl.insert_image(
+        "B2",
+        &png,
+        ImageOptions {
+            width: Some(150),
+            height: Some(30),
+            alt_text: Some("Logo & Co".into()),
+            ..Default::default()
+        },
+    )?;
+    xl.insert_image("E5", &png, ImageOptions::default())?;
+    assert!(xl.insert_image("A1", b"not an image", ImageOptions::default()).is_err());
+
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<drawing r:id="rId1"/>"#));
+    let drawing = String::from_utf8(xl.read_part("xl/drawings/drawing1.xml")?.unwrap())?;
+    // B шириной 145 px, строка по умолчанию 20 px: 150x30 доходит до C3
+    assert!(drawing.contains("<xdr:to><xdr:col>2</xdr:col><xdr:colOff>47625</xdr:colOff><xdr:row>2</xdr:row><xdr:rowOff>95250</xdr:rowOff></xdr:to>"));
+    assert!(drawing.contains(r#"<xdr:cNvPr id="2" name="Picture 1" descr="Logo &amp; Co"/>"#));
+    assert!(drawing.contains(r#"<xdr:cNvPr id="3" name="Picture 2" descr=""/>"#));
+    assert!(drawing.contains(r#"<a:ext cx="9525" cy="9525"/>"#));
+    let rels = String::from_utf8(xl.read_part("xl/drawings/_rels/drawing1.xml.rels")?.unwrap())?;
+    assert!(rels.contains(r#"Target="../media/image1.png""#));
+    assert!(rels.contains(r#"Target="../media/image2.png""#));
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    let ct = String::from_utf8(xl.read_part("[Content_Types].xml")?.unwrap())?;
+    assert!(ct.contains(r#"<Default Extension="png" ContentType="image/png"/>"#));
+    assert!(ct.contains(r#"PartName="/xl/drawings/drawing1.xml""#));
+    assert_eq!(xl.read_part("xl/media/image1.png")?, Some(png));
+    Ok(())
+}
+
+#[test]
+fn copy_sheet_with_drawing_and_dangling_rels() -> Result<()> {
+    use crate::image_part::ImageOptions;
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_sheet_rels.xlsx";
+    let png = STANDARD.decode(
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=",
+    )?;
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.insert_image("B2", &png, ImageOptions::default())?;
+    xl.copy_worksheet("Sheet1", "Copy")?;
+    // у копии свой рисунок, ссылающийся на ту же картинку
+    let copy_path = xl.sheet_path_of("Copy")?;
+    let copy_rels = xl.rels_of(&copy_path)?;
+    let drawing = copy_rels.of_kind("drawing").next().unwrap();
+    assert_eq!(drawing.target, "xl/drawings/drawing2.xml");
+    let rels = xl.rels_of("xl/drawings/drawing2.xml")?;
+    assert_eq!(rels.of_kind("image").next().unwrap().target, "xl/media/image1.png");
+
+    // ссылка на несуществующую связь и связь без ссылки
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    xl.sheet_xml = sheet
+        .replace(r#"<drawing r:id="rId1"/>"#, r#"<drawing r:id="rId9"/>"#)
+        .into_bytes()
+        .into();
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(!sheet.contains("<drawing"));
+    assert!(xl.rels_of("xl/worksheets/sheet1.xml")?.rels.is_empty());
+    assert_eq!(xl.read_part("xl/drawings/drawing1.xml")?, None);
+    assert!(xl.read_part("xl/drawings/drawing2.xml")?.is_some());
+    assert_eq!(xl.read_part("xl/media/image1.png")?, Some(png));
+    Ok(())
+}
+
+#[test]
+fn pivot_cache_refresh_and_growth() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let last = xl.last_row;
+    let cache = |sheet: &str| {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><pivotCacheDefinition xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" r:id="rId1" recordCount="0"><cacheSource type="worksheet"><worksheetSource ref="A1:C{last}" sheet="{sheet}"/></cacheSource></pivotCacheDefinition>"#
+        )
+        .into_bytes()
+    };
+    let ours = "xl/pivotCache/pivotCacheDefinition1.xml";
+    let other = "xl/pivotCache/pivotCacheDefinition2.xml";
+    xl.write_part(ours, cache("Sheet1"));
+    xl.write_part(other, cache("Other"));
+    let rid1 = xl.add_rel("xl/workbook.xml", "pivotCacheDefinition", ours)?;
+    let rid2 = xl.add_rel("xl/workbook.xml", "pivotCacheDefinition", other)?;
+    let wb = String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.replace(
+        "</workbook>",
+        &format!(
+            r#"<pivotCaches><pivotCache cacheId="1" r:id="{rid1}"/><pivotCache cacheId="2" r:id="{rid2}"/></pivotCaches></workbook>"#
+        ),
+    );
+    xl.workbook_xml.set(wb.into_bytes());
+
+    xl.append_row(["x", "1"])?;
+    xl.flush_appended_ranges()?;
+    let def = String::from_utf8(xl.read_part(ours)?.unwrap())?;
+    assert!(def.contains(&format!(r#"ref="A1:C{}""#, last + 1)));
+    assert!(def.contains(r#"refreshOnLoad="1""#));
+    // кэш другого листа не трогаем
+    xl.mark_pivot_caches_dirty()?;
+    let def = String::from_utf8(xl.read_part(other)?.unwrap())?;
+    assert!(def.contains(&format!(r#"ref="A1:C{last}""#)));
+    assert!(!def.contains("refreshOnLoad"));
+    Ok(())
+}
+
+#[test]
+fn recalc_on_change_drops_calc_chain() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_recalc.xlsx";
+
+    // без правок пересчёт не включается
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_recalc_on_change(true);
+    xl.save(file_name_out)?;
+    let xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    assert!(!String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?.contains("fullCalcOnLoad"));
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let chain = "xl/calcChain.xml";
+    xl.write_part(chain, br#"<calcChain xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><c r="A1" i="1"/></calcChain>"#.to_vec());
+    xl.add_rel("xl/workbook.xml", "calcChain", chain)?;
+    xl.set_recalc_on_change(true).append_row(["1", "=A1*2"])?;
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    let wb = String::from_utf8(xl.workbook_xml.get(&xl.src)?.clone())?;
+    assert_eq!(wb.matches(r#"fullCalcOnLoad="1""#).count(), 1);
+    assert_eq!(xl.read_part(chain)?, None);
+    assert!(xl.rels_of("xl/workbook.xml")?.of_kind("calcChain").next().is_none());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "calc")]
+fn evaluate_formulas_subset() -> Result<()> {
+    use crate::read_part::read_cells;
+    let mut xl = XlsxEditor::open("../test/style_test.xlsx", "Sheet1")?;
+    for (coord, value) in [
+        ("J1", "10"),
+        ("J2", "20"),
+        ("J3", "=SUM(J1:J2)"),
+        ("K1", "=J3*2"),
+        ("K2", r#"=IF(J1>5,"big","small")"#),
+        ("K3", "=VLOOKUP(20,J1:K2,2,FALSE)"),
+        ("K4", "=1/0"),
+        ("K5", "=AVERAGE(J:J)"),
+        ("K6", "=Other!A1+1"),
+        ("K7", r#"="a"&J1"#),
+        ("K8", "=IFERROR(K4,-1)"),
+        ("K9", "=-2^2+ROUND(2.5,0)"),
+    ] {
+        xl.set_cell(coord, value)?;
+    }
+    xl.evaluate_formulas()?;
+
+    let cells = read_cells(&xl.sheet_xml)?;
+    let get = |col: u32, row: u32| {
+        let c = cells.iter().find(|c| c.col == col && c.row == row).unwrap();
+        (c.t.clone(), c.v.clone(), c.f.clone())
+    };
+    let v = |s: &str| Some(s.to_owned());
+    assert_eq!(get(9, 3), (None, v("30"), v("SUM(J1:J2)")));
+    assert_eq!(get(10, 1), (None, v("60"), v("J3*2")));
+    assert_eq!(get(10, 2).1, v("big"));
+    assert_eq!(get(10, 2).0, v("str"));
+    assert_eq!(get(10, 3).1, v("big"));
+    assert_eq!((get(10, 4).0, get(10, 4).1), (v("e"), v("#DIV/0!")));
+    assert_eq!(get(10, 5).1, v("20"));
+    // ссылка на другой лист не вычисляется
+    assert_eq!(get(10, 6).1, None);
+    assert_eq!(get(10, 7).1, v("a10"));
+    assert_eq!(get(10, 8).1, v("-1"));
+    assert_eq!(get(10, 9).1, v("7"));
+    Ok(())
+}
+
+#[test]
+fn adjust_and_translate_formulas() -> Result<()> {
+    use crate::formula::{adjust_formula, translate_r1c1_to_a1};
+    assert_eq!(adjust_formula("SUM(A1:B$2)*$C3", 2, 1), "SUM(B3:C$2)*$C5");
+    assert_eq!(adjust_formula(r#"A2&"A2"&'My Sheet'!A2"#, -1, 0), r#"A1&"A2"&'My Sheet'!A1"#);
+    assert_eq!(adjust_formula("A1+1", -1, 0), "#REF!+1");
+
+    assert_eq!(translate_r1c1_to_a1("RC[-2]*R[-1]C", "C5")?, "A5*C4");
+    assert_eq!(translate_r1c1_to_a1("SUM(R2C:R[-1]C)", "B10")?, "SUM(B$2:B9)");
+    assert_eq!(translate_r1c1_to_a1("R1C1+Data!R[1]C[1]", "A1")?, "$A$1+Data!B2");
+    assert_eq!(translate_r1c1_to_a1("SUM(R[1])+COUNT(C2)", "B5")?, "SUM(6:6)+COUNT($B:$B)");
+    assert_eq!(translate_r1c1_to_a1(r#"ROUND(RC[-1],0)&"RC""#, "B3")?, r#"ROUND(A3,0)&"RC""#);
+    assert_eq!(translate_r1c1_to_a1("R[-1]C", "A1")?, "#REF!");
+    assert!(translate_r1c1_to_a1("RC", "1A").is_err());
+    Ok(())
+}
+
+#[test]
+fn used_range_bounds() -> Result<()> {
+    let app = XlsxEditor::open("../test/test_last_row_index.xlsx", "Sheet1")?;
+    let used = app.used_range()?;
+    assert_eq!((used.first_row, used.last_row), (1, 8));
+    assert_eq!((used.first_col, used.last_col), (0, 3));
+    assert_eq!(used.to_ref().as_deref(), Some("A1:D8"));
+    assert_eq!(used.last_row_in(1), 5);
+    assert_eq!(used.last_row_in(7), 0);
+    assert_eq!(used.last_col_by_row.get(&5), Some(&1));
+    assert_eq!(used.last_col_by_row.get(&6), None);
+    assert_eq!(app.get_last_row_index("B,D")?, 8);
+    assert!(app.get_last_row_index("1").is_err());
+    Ok(())
+}
+
+#[test]
+fn dimension_rewritten_on_save() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_dimension.xlsx";
+    let dimension = |xml: &[u8]| {
+        let xml = String::from_utf8_lossy(xml);
+        let at = xml.find("<dimension ref=\"").unwrap() + 16;
+        xml[at..at + xml[at..].find('"').unwrap()].to_owned()
+    };
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_cell("H200", "far away")?;
+    xl.add_worksheet("Empty")?;
+    xl.with_worksheet("Sheet1")?;
+    xl.save(file_name_out)?;
+
+    let xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    let used = xl.used_range()?;
+    assert_eq!(used.last_row, 200);
+    assert_eq!(Some(dimension(&xl.sheet_xml)), used.to_ref());
+    let empty = XlsxEditor::open(file_name_out, "Empty")?;
+    assert_eq!(dimension(&empty.sheet_xml), "A1");
+    Ok(())
+}
+
+#[test]
+fn set_cell_keeps_row_attributes() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let r = xl.last_row + 2;
+    let formatted = format!(
+        r#"<row r="{r}" spans="1:3" s="2" customFormat="1" ht="30" customHeight="1" outlineLevel="1"/>"#
+    );
+    let next = format!(r#"<row r="{}"><c r="A{}"><v>1</v></c></row>"#, r + 1, r + 1);
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?
+        .replace("</sheetData>", &format!("{formatted}{next}</sheetData>"));
+    xl.sheet_xml = sheet.into_bytes().into();
+
+    xl.set_cell(&format!("B{r}"), "kept")?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(&format!(
+        r#"<row r="{r}" spans="1:3" s="2" customFormat="1" ht="30" customHeight="1" outlineLevel="1"><c r="B{r}" t="inlineStr"><is><t>kept</t></is></c></row>{next}"#
+    )));
+    Ok(())
+}
+
+#[test]
+fn row_spans_on_save() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_spans.xlsx";
+    let file_name_out2 = "../test/style_test_out_spans2.xlsx";
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let r = xl.last_row + 1;
+    xl.append_row(["a", "b", "c"])?;
+    xl.set_cell(&format!("E{r}"), 5)?;
+    xl.set_row_spans(true).save(file_name_out)?;
+
+    let xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(&format!(r#"<row r="{r}" spans="1:5">"#)));
+    // без опции правятся только существующие spans
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    xl.set_cell(&format!("G{r}"), 7)?;
+    xl.append_row(["x"])?;
+    xl.save(file_name_out2)?;
+    let xl = XlsxEditor::open(file_name_out2, "Sheet1")?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(&format!(r#"<row r="{r}" spans="1:7">"#)));
+    assert!(sheet.contains(&format!(r#"<row r="{}">"#, r + 1)));
+    Ok(())
+}
+
+#[test]
+fn prefixed_sheet_markup() -> Result<()> {
+    use crate::sheet_xml::restore_prefix;
+    let file_name = "../test/style_test.xlsx";
+    let prefixed = "../test/style_test_out_prefixed.xlsx";
+    let file_name_out = "../test/style_test_out_prefixed_edit.xlsx";
+
+    // исходник в стиле <x:worksheet xmlns:x="…">
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    restore_prefix(&mut xl.sheet_xml, "x:");
+    assert!(!xl.sheet_xml.windows(5).any(|w| w == b"<row "));
+    xl.save(prefixed)?;
+
+    let mut xl = XlsxEditor::open(prefixed, "Sheet1")?;
+    let last = xl.last_row;
+    assert!(last > 0);
+    xl.append_row(["appended", "1"])?;
+    xl.set_cell("D2", "set")?;
+    xl.merge_cells(&format!("A{0}:B{0}", last + 3))?;
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    let raw = String::from_utf8(xl.source_part("xl/worksheets/sheet1.xml")?.unwrap())?;
+    assert!(raw.contains(&format!(r#"<x:row r="{}"><x:c r="A{0}" t="inlineStr"><x:is><x:t>appended</x:t></x:is></x:c>"#, last + 1)));
+    assert!(raw.contains("<x:mergeCells"));
+    assert!(!raw.contains("<row") && !raw.contains("</sheetData>"));
+    assert!(!raw.contains(r#" xmlns=""#));
+    assert_eq!(xl.last_row, last + 1);
+    assert_eq!(xl.used_range()?.last_row, last + 1);
+    Ok(())
+}
+
+#[test]
+fn unusual_sheet_data_markup() -> Result<()> {
+    use crate::sheet_xml::find_child;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_sheetdata.xlsx";
+
+    // лишние пробелы в тегах: быстрый поиск не срабатывает
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let last = xl.last_row;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?
+        .replace("<sheetData>", "<sheetData  >")
+        .replace("</sheetData>", "</sheetData >");
+    xl.sheet_xml = sheet.into_bytes().into();
+    xl.append_row(["tail"])?;
+    xl.set_cell(&format!("C{}", last + 3), 3)?;
+    xl.merge_cells(&format!("A{0}:B{0}", last + 3))?;
+    xl.save(file_name_out)?;
+    let xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    assert_eq!(xl.used_range()?.last_row, last + 3);
+    assert_eq!(xl.get_merged_ranges()?, vec![format!("A{0}:B{0}", last + 3)]);
+
+    // самозакрывающийся <sheetData />
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let sd = find_child(&xl.sheet_xml, "sheetData")?.unwrap();
+    xl.sheet_xml.splice(sd, b"<sheetData />".iter().copied());
+    xl.last_row = 0;
+    xl.set_cell("B3", "b")?;
+    xl.append_row(["c"])?;
+    xl.group_rows("5:6", 1, false)?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<sheetData><row r="3"><c r="B3" t="inlineStr"><is><t>b</t></is></c></row><row r="4">"#));
+    assert!(sheet.contains(r#"<row r="6" outlineLevel="1"></row></sheetData>"#));
+    Ok(())
+}
+
+#[test]
+fn validate_reports_issues() -> Result<()> {
+    use crate::lint_part::IssueKind;
+    let file_name = "../test/style_test.xlsx";
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.append_row(["fine", "1"])?;
+    let report = xl.validate()?;
+    assert!(report.is_ok(), "{report}");
+
+    let rows = r#"<row r="900"><c r="A900"/><c r="A900"/><c r="C900" s="999"/><c r="B900"/></row><row r="899"/></sheetData>"#;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?
+        .replace("</sheetData>", rows)
+        .replace(
+            "</worksheet>",
+            r#"<mergeCells count="3"><mergeCell ref="A1:B1"/></mergeCells><drawing r:id="rId42"/></worksheet>"#,
+        );
+    xl.sheet_xml = sheet.into_bytes().into();
+    xl.write_part("xl/custom.xml", b"<a><b></a>".to_vec());
+
+    let report = xl.validate()?;
+    let kinds = |k| report.of_kind(k).count();
+    assert_eq!(kinds(IssueKind::DuplicateCell), 1);
+    assert_eq!(kinds(IssueKind::OutOfOrder), 2); // B900 после C900, строка 899 после 900
+    assert_eq!(kinds(IssueKind::StyleIndex), 1);
+    assert_eq!(kinds(IssueKind::CountMismatch), 1);
+    assert_eq!(kinds(IssueKind::DanglingRel), 1);
+    let malformed: Vec<_> = report.of_kind(IssueKind::Malformed).collect();
+    assert_eq!(malformed.len(), 1);
+    assert_eq!(malformed[0].part, "xl/custom.xml");
+    assert_eq!(report.issues.len(), 7);
+    assert!(report.to_string().contains("xl/worksheets/sheet1.xml: duplicate-cell: cell A900"));
+    assert_eq!("dangling-rel".parse::<IssueKind>()?, IssueKind::DanglingRel);
+    Ok(())
+}
+
+#[test]
+fn open_with_repair_fixes_rows_and_counts() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let broken = "../test/style_test_out_broken.xlsx";
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let last = xl.last_row;
+    let rows = format!(
+        r#"<row r="{b}"><c r="A{b}"><v>2</v></c></row><row r="{c}"><c r="A{c}"><v>4</v></c></row><row r="{a}"><c r="A{a}"><v>1</v></c></row><row r="{b}"><c r="A{b}"><v>3</v></c></row></sheetData><mergeCells count="5"><mergeCell ref="B{a}:C{a}"/></mergeCells>"#,
+        a = last + 1,
+        b = last + 2,
+        c = last + 3
+    );
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?.replace("</sheetData>", &rows);
+    xl.sheet_xml = sheet.into_bytes().into();
+    let styles = String::from_utf8(xl.styles_xml.get(&xl.src)?.clone())?;
+    let at = styles.find("<cellXfs count=\"").unwrap() + 16;
+    let end = at + styles[at..].find('"').unwrap();
+    let xfs: u32 = styles[at..end].parse()?;
+    xl.styles_xml
+        .set(format!("{}{}{}", &styles[..at], xfs + 7, &styles[end..]).into_bytes());
+    xl.save(broken)?;
+
+    let (mut xl, fixed) = XlsxEditor::open_with_repair(broken, "Sheet1")?;
+    assert_eq!(
+        fixed,
+        vec![
+            format!("xl/worksheets/sheet1.xml: dropped duplicate row {}", last + 2),
+            "xl/worksheets/sheet1.xml: sorted rows by number (1 out of order)".to_owned(),
+            "xl/worksheets/sheet1.xml: mergeCells count 5 -> 1".to_owned(),
+            format!("xl/styles.xml: cellXfs count {} -> {xfs}", xfs + 7),
+        ]
+    );
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(&format!(
+        r#"<row r="{a}"><c r="A{a}"><v>1</v></c></row><row r="{b}"><c r="A{b}"><v>3</v></c></row><row r="{c}"><c r="A{c}"><v>4</v></c></row></sheetData>"#,
+        a = last + 1,
+        b = last + 2,
+        c = last + 3
+    )));
+    assert_eq!(xl.last_row, last + 3);
+    assert!(xl.validate()?.is_ok());
+    assert!(xl.repair_sheet()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn typed_errors() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let broken = "../test/style_test_out_malformed.xlsx";
+
+    let err = XlsxEditor::open(file_name, "Nope").err().unwrap();
+    assert!(matches!(err, XlsxError::SheetNotFound(ref n) if n == "Nope"));
+    let err = XlsxEditor::open("../test/missing.xlsx", "Sheet1").err().unwrap();
+    assert!(matches!(err, XlsxError::Zip(_)));
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let err = xl.merge_cells("A0:B2").unwrap_err();
+    assert!(matches!(err, XlsxError::InvalidRange(ref r) if r == "A0:B2"));
+    let err = xl.set_cell("A1048577", 1).unwrap_err();
+    assert!(matches!(err, XlsxError::RowLimitExceeded { row: 1_048_577 }));
+    let err = xl.with_worksheet("Nope").err().unwrap();
+    assert!(matches!(err, XlsxError::SheetNotFound(_)));
+
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?.replace("</sheetData>", "<row r=\"99\"></sheetData>");
+    xl.sheet_xml = sheet.into_bytes().into();
+    xl.save(broken)?;
+    let err = XlsxEditor::open(broken, "Sheet1").err().unwrap();
+    match err {
+        XlsxError::MalformedSheet { part, offset, .. } => {
+            assert_eq!(part, "xl/worksheets/sheet1.xml");
+            assert!(offset > 0);
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn malformed_sheet_xml_is_reported() -> Result<()> {
+    use crate::files_part::calc_last_row;
+    let file_name = "../test/style_test.xlsx";
+    let part = "xl/worksheets/sheet1.xml";
+    let xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let good = String::from_utf8(xl.sheet_xml.clone())?;
+    assert_eq!(calc_last_row(part, good.as_bytes())?, xl.last_row);
+
+    // детерминированный «фаззинг»: обрезки, выкинутые закрывающие теги, мусор
+    let mut seed = 0x2545_f491_u64;
+    let mut next = |n: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % n
+    };
+    let closes: Vec<usize> = good.match_indices("</c>").map(|(i, _)| i).collect();
+    let data = good.find("<sheetData").unwrap() + 1..good.find("</worksheet>").unwrap();
+    let tags: Vec<usize> = good
+        .match_indices('<')
+        .map(|(i, _)| i)
+        .filter(|i| data.contains(i))
+        .collect();
+    for round in 0..200 {
+        let (bad, must_fail) = match round % 3 {
+            0 => (good[..next(good.len())].to_owned(), false),
+            1 => {
+                let at = closes[next(closes.len())];
+                (format!("{}{}", &good[..at], &good[at + 4..]), true)
+            }
+            _ => {
+                let at = tags[next(tags.len())];
+                (format!("{}</x>{}", &good[..at], &good[at..]), true)
+            }
+        };
+        match calc_last_row(part, bad.as_bytes()) {
+            Ok(_) => assert!(!must_fail, "round {round}: broken XML accepted"),
+            Err(XlsxError::MalformedSheet { part: p, offset, .. }) => {
+                assert_eq!(p, part);
+                assert!(offset as usize <= bad.len());
+            }
+            Err(other) => panic!("round {round}: unexpected error {other}"),
+        }
+
+        // те же данные в редакторе: ошибки, а не паника, и validate() их видит
+        let mut ed = XlsxEditor::open(file_name, "Sheet1")?;
+        ed.sheet_xml = bad.into_bytes().into();
+        let broken = calc_last_row(part, &ed.sheet_xml).is_err();
+        let report = ed.validate()?;
+        if broken {
+            assert!(report.of_kind(crate::lint_part::IssueKind::Malformed).count() > 0);
+            let _ = ed.set_cell("B2", 1);
+            let _ = ed.append_row(["x"]);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn untouched_parts_copied_raw() -> Result<()> {
+    use std::io::{Read, Write};
+    use ::zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter, write::SimpleFileOptions};
+    let file_name = "../test/style_test.xlsx";
+    let src = "../test/style_test_out_raw_src.xlsx";
+    let out = "../test/style_test_out_raw.xlsx";
+    type Entry = (String, CompressionMethod, Vec<u8>, Option<DateTime>);
+
+    // исходник с «чужими» частями: разное сжатие и время
+    let time = DateTime::from_date_and_time(2021, 3, 4, 5, 6, 8).unwrap();
+    let mut zin = ZipArchive::new(std::fs::File::open(file_name)?)?;
+    let mut zw = ZipWriter::new(std::fs::File::create(src)?);
+    for i in 0..zin.len() {
+        let mut f = zin.by_index(i)?;
+        let opt = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(time);
+        let name = f.name().to_owned();
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        zw.start_file(name, opt)?;
+        zw.write_all(&buf)?;
+    }
+    let extra = [
+        ("xl/vbaProject.bin", CompressionMethod::Stored),
+        ("customXml/item1.xml", CompressionMethod::Deflated),
+        ("xl/printerSettings/printerSettings1.bin", CompressionMethod::Stored),
+        ("xl/externalLinks/externalLink1.xml", CompressionMethod::Deflated),
+    ];
+    for (name, method) in extra {
+        let opt = SimpleFileOptions::default()
+            .compression_method(method)
+            .compression_level((method == CompressionMethod::Deflated).then_some(9))
+            .last_modified_time(time);
+        zw.start_file(name, opt)?;
+        zw.write_all(format!("<x>{}</x>", name.repeat(40)).as_bytes())?;
+    }
+    zw.set_comment("generated");
+    zw.finish()?;
+
+    let raw = |path: &str| -> Result<Vec<Entry>> {
+        let mut z = ZipArchive::new(std::fs::File::open(path)?)?;
+        let mut out = Vec::new();
+        for i in 0..z.len() {
+            let mut f = z.by_index_raw(i)?;
+            let mut buf = Vec::new();
+            f.read_to_end(&mut buf)?;
+            out.push((f.name().to_owned(), f.compression(), buf, f.last_modified()));
+        }
+        Ok(out)
+    };
+    let before = raw(src)?;
+
+    for preserve in [false, true] {
+        let mut xl = XlsxEditor::open(src, "Sheet1")?;
+        xl.set_cell("B2", 42)?;
+        xl.set_preserve_zip_metadata(preserve).save(out)?;
+        let after = raw(out)?;
+        let names = |v: &[Entry]| v.iter().map(|e| e.0.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&after), names(&before), "entry order");
+        for (name, _) in extra {
+            let a = before.iter().find(|e| e.0 == name).unwrap();
+            let b = after.iter().find(|e| e.0 == name).unwrap();
+            assert_eq!(a, b, "{name} must be copied raw");
+        }
+        let sheet = after.iter().find(|e| e.0 == "xl/worksheets/sheet1.xml").unwrap();
+        let old = before.iter().find(|e| e.0 == "xl/worksheets/sheet1.xml").unwrap();
+        assert_ne!(sheet.2, old.2);
+        if preserve {
+            assert_eq!(sheet.3, Some(time));
+            assert_eq!(sheet.1, CompressionMethod::Deflated);
+            let z = ZipArchive::new(std::fs::File::open(out)?)?;
+            assert_eq!(z.comment(), b"generated");
+        } else {
+            assert_ne!(sheet.3, Some(time));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn macro_workbook_round_trip() -> Result<()> {
+    use std::io::{Read, Write};
+    use ::zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+    let file_name = "../test/style_test.xlsx";
+    let xlsm = "../test/style_test_out_macro.xlsm";
+    let out = "../test/style_test_out_macro_out.xlsm";
+    let plain = "../test/style_test_out_macro_out.xlsx";
+    let vba = b"\xd0\xcf\x11\xe0 fake vba project".to_vec();
+
+    // .xlsm из обычной книги: vbaProject.bin, связь и ContentType
+    let mut zin = ZipArchive::new(std::fs::File::open(file_name)?)?;
+    let mut zw = ZipWriter::new(std::fs::File::create(xlsm)?);
+    for i in 0..zin.len() {
+        let mut f = zin.by_index(i)?;
+        let name = f.name().to_owned();
+        let mut buf = String::new();
+        f.read_to_string(&mut buf)?;
+        let buf = match name.as_str() {
+            "[Content_Types].xml" => buf
+                .replace(
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml",
+                    "application/vnd.ms-excel.sheet.macroEnabled.main+xml",
+                )
+                .replace(
+                    "</Types>",
+                    r#"<Default Extension="bin" ContentType="application/vnd.ms-office.vbaProject"/></Types>"#,
+                ),
+            // как в настоящей .xlsm: лист известен VBA по codeName
+            "xl/worksheets/sheet1.xml" => {
+                buf.replacen("<dimension", r#"<sheetPr codeName="Sheet1"/><dimension"#, 1)
+            }
+            "xl/_rels/workbook.xml.rels" => buf.replace(
+                "</Relationships>",
+                r#"<Relationship Id="rId99" Type="http://schemas.microsoft.com/office/2006/relationships/vbaProject" Target="vbaProject.bin"/></Relationships>"#,
+            ),
+            _ => buf,
+        };
+        zw.start_file(name, SimpleFileOptions::default())?;
+        zw.write_all(buf.as_bytes())?;
+    }
+    zw.start_file("xl/vbaProject.bin", SimpleFileOptions::default())?;
+    zw.write_all(&vba)?;
+    zw.finish()?;
+
+    let read = |path: &str, part: &str| -> Result<Option<Vec<u8>>> {
+        let mut z = ZipArchive::new(std::fs::File::open(path)?)?;
+        let Ok(mut f) = z.by_name(part) else {
+            return Ok(None);
+        };
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        Ok(Some(buf))
+    };
+    let text = |path: &str, part: &str| -> Result<String> {
+        Ok(String::from_utf8(read(path, part)?.unwrap_or_default())?)
+    };
+
+    let mut xl = XlsxEditor::open(xlsm, "Sheet1")?;
+    assert!(xl.has_macros()?);
+    xl.copy_worksheet("Sheet1", "Copy")?;
+    xl.add_worksheet("New")?;
+    xl.append_row(["macro", "1"])?;
+    assert!(xl.save(plain).is_err(), "saving .xlsx must not drop macros silently");
+    xl.save(out)?;
+
+    assert_eq!(read(out, "xl/vbaProject.bin")?, Some(vba));
+    let ct = text(out, "[Content_Types].xml")?;
+    assert!(ct.contains(r#"PartName="/xl/workbook.xml" ContentType="application/vnd.ms-excel.sheet.macroEnabled.main+xml""#));
+    let mut names = std::collections::HashSet::new();
+    let mut back = XlsxEditor::open(out, "Sheet1")?;
+    for e in back.sheet_entries()? {
+        let xml = text(out, &e.path)?;
+        let at = xml.find("codeName=\"").expect("every sheet has a codeName") + 10;
+        let name = xml[at..at + xml[at..].find('"').unwrap()].to_owned();
+        assert!(names.insert(name), "{} repeats a codeName", e.name);
+    }
+
+    back.set_allow_macro_removal(true).save(plain)?;
+    assert_eq!(read(plain, "xl/vbaProject.bin")?, None);
+    assert!(!text(plain, "xl/_rels/workbook.xml.rels")?.contains("vbaProject"));
+    assert!(text(plain, "[Content_Types].xml")?.contains(
+        r#"PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml""#
+    ));
+    let mut reopened = XlsxEditor::open(plain, "Sheet1")?;
+    assert!(!reopened.has_macros()?);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn encrypted_workbook_round_trip() -> Result<()> {
+    use calamine::{Data, Reader, Xlsx, open_workbook};
+    let file_name = "../test/style_test.xlsx";
+    let enc = "../test/style_test_out_encrypted.xlsx";
+    let plain = "../test/style_test_out_decrypted.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.append_row(["зарплата", "1000"])?;
+    let row = xl.last_row;
+    xl.save_encrypted(enc, "пароль")?;
+
+    // составной файл, а не ZIP: без пароля содержимое не прочитать
+    let bytes = std::fs::read(enc)?;
+    assert_eq!(bytes[..8], [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1]);
+    assert!(::zip::ZipArchive::new(std::fs::File::open(enc)?).is_err());
+    assert!(XlsxEditor::open(enc, "Sheet1").is_err());
+    let wrong = XlsxEditor::open_encrypted(enc, "parol", "Sheet1").err().unwrap();
+    assert!(wrong.to_string().contains("wrong password"), "{wrong}");
+
+    // дописываем в зашифрованную книгу и снова шифруем
+    let mut xl = XlsxEditor::open_encrypted(enc, "пароль", "Sheet1")?;
+    assert_eq!(xl.last_row, row);
+    xl.append_row(["премия", "250"])?;
+    xl.save_encrypted(enc, "пароль")?;
+
+    let mut xl = XlsxEditor::open_encrypted(enc, "пароль", "Sheet1")?;
+    assert_eq!(xl.last_row, row + 1);
+    xl.save(plain)?;
+    let mut wb: Xlsx<_> = open_workbook(plain)?;
+    let range = wb.worksheet_range("Sheet1")?;
+    let cell = |r: u32, c: u32| range.get_value((r - 1, c)).cloned();
+    assert_eq!(cell(row, 0), Some(Data::String("зарплата".into())));
+    assert_eq!(cell(row, 1), Some(Data::Float(1000.0)));
+    assert_eq!(cell(row + 1, 0), Some(Data::String("премия".into())));
+    Ok(())
+}
+
+#[test]
+fn scan_info_reports_workbook_layout() -> Result<()> {
+    use crate::{scan_info, sheets_part::SheetVisibility};
+    let out = "../test/style_test_out_info.xlsx";
+    let mut xl = XlsxEditor::open("../test/style_test.xlsx", "Sheet1")?;
+    xl.append_row(["info", "1"])?;
+    xl.set_print_area("A1:B3")?;
+    let last = xl.last_row;
+    xl.add_worksheet("Скрытый")?;
+    xl.append_table_at("A3", [["x"], ["y"]])?;
+    xl.hide_worksheet("Скрытый", SheetVisibility::Hidden)?;
+    xl.save(out)?;
+
+    let info = scan_info(out)?;
+    let names: Vec<&str> = info.sheets.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, scan(out)?);
+    let (first, hidden) = (&info.sheets[0], &info.sheets[1]);
+    assert_eq!(first.visibility, SheetVisibility::Visible);
+    assert_eq!(first.last_row, last);
+    assert!(first.dimension.is_some());
+    assert_eq!(hidden.visibility, SheetVisibility::Hidden);
+    assert_eq!((hidden.rows, hidden.last_row), (2, 4));
+
+    let area = &info.defined_names[0];
+    assert_eq!(area.name, "_xlnm.Print_Area");
+    assert_eq!(area.value, "Sheet1!$A$1:$B$3");
+    assert_eq!(area.local_sheet_id, Some(0));
+    assert!(!info.has_macros);
+    assert_eq!(info.application.as_deref(), Some("Microsoft Excel"));
+    Ok(())
+}
+
+#[test]
+fn doc_properties_and_custom_properties() -> Result<()> {
+    use crate::docprops_part::DocProperties;
+    use std::io::Read;
+    let file_name = "../test/style_test.xlsx";
+    let out = "../test/style_test_out_docprops.xlsx";
+    let again = "../test/style_test_out_docprops_again.xlsx";
+    let text = |path: &str, part: &str| -> Result<String> {
+        let mut z = ::zip::ZipArchive::new(std::fs::File::open(path)?)?;
+        let mut buf = String::new();
+        z.by_name(part)?.read_to_string(&mut buf)?;
+        Ok(buf)
+    };
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_doc_properties(DocProperties {
+        title: Some("Зарплата <Q3>".into()),
+        author: Some("Бухгалтерия".into()),
+        company: Some("ООО «Ромашка»".into()),
+        ..Default::default()
+    })?;
+    xl.set_custom_property("Отдел", "Финансы")?
+        .set_custom_property("Версия", 3)?
+        .set_custom_property("Ставка", 0.15)?
+        .set_custom_property("Проверено", true)?
+        .set_custom_property("ВЕРСИЯ", 4)?;
+    xl.set_last_modified_by("robot").save(out)?;
+
+    let core = text(out, "docProps/core.xml")?;
+    assert!(core.contains("<dc:title>Зарплата &lt;Q3&gt;</dc:title>"));
+    assert!(core.contains("<dc:creator>Бухгалтерия</dc:creator>"));
+    assert!(core.contains("<cp:lastModifiedBy>robot</cp:lastModifiedBy>"));
+    assert!(!core.contains("2025-07-22T15:07:01Z"), "modified is stamped on save");
+    assert!(text(out, "docProps/app.xml")?.contains("<Company>ООО «Ромашка»</Company>"));
+    let custom = text(out, "docProps/custom.xml")?;
+    assert!(custom.contains(r#"pid="2" name="Отдел"><vt:lpwstr>Финансы</vt:lpwstr>"#));
+    assert!(custom.contains(r#"pid="3" name="ВЕРСИЯ"><vt:i4>4</vt:i4>"#));
+    assert!(custom.contains("<vt:r8>0.15</vt:r8>") && custom.contains("<vt:bool>true</vt:bool>"));
+    assert_eq!(custom.matches("<property ").count(), 4);
+    assert!(text(out, "[Content_Types].xml")?.contains(r#"PartName="/docProps/custom.xml""#));
+    assert!(text(out, "_rels/.rels")?.contains(r#"Target="docProps/custom.xml""#));
+    assert!(XlsxEditor::open(out, "Sheet1")?.validate()?.is_ok());
+
+    // без отметки core.xml не меняется
+    let mut xl = XlsxEditor::open(out, "Sheet1")?;
+    xl.append_row(["x"])?;
+    xl.set_stamp_modified(false).save(again)?;
+    assert_eq!(text(again, "docProps/core.xml")?, core);
+    Ok(())
+}
+
+#[test]
+fn append_columns_writes_typed_columns() -> Result<()> {
+    use crate::columns_part::ColumnData;
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_columns.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Cols")?;
+    ed.append_row(["keep", "old", "old", "old", "old", "keep"])?;
+    ed.append_row(["keep", "old", "old", "old", "old", "keep"])?;
+    let day = 19_723; // 2024-01-01
+    ed.append_columns(
+        "B1",
+        &[
+            ("n", ColumnData::F64(vec![1.5, f64::NAN, 3.0])),
+            ("s", ColumnData::Str(vec!["a".into(), String::new()])),
+            ("d", ColumnData::Date(vec![day])),
+            ("t", ColumnData::DateTime(vec![i64::from(day) * 86_400_000 + 43_200_000])),
+        ],
+    )?;
+    assert_eq!(ed.last_row, 4);
+    // блок ниже данных дописывается в конец
+    ed.append_columns(
+        "A6",
+        &[("flag", ColumnData::Bool(vec![true])), ("i", ColumnData::I64(vec![-7]))],
+    )?;
+    assert_eq!(ed.last_row, 7);
+    assert!(ed.append_columns("A1", &[]).is_err());
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range("Cols")?;
+    let cell = |r: u32, c: u32| range.get_value((r, c)).cloned().unwrap_or(Data::Empty);
+    assert_eq!(cell(0, 0), Data::String("keep".into()));
+    assert_eq!(cell(0, 5), Data::String("keep".into()));
+    assert_eq!(cell(0, 1), Data::String("n".into()));
+    assert_eq!(cell(0, 4), Data::String("t".into()));
+    assert_eq!(cell(1, 1), Data::Float(1.5));
+    assert_eq!(cell(2, 1), Data::Empty);
+    assert_eq!(cell(3, 1), Data::Float(3.0));
+    assert_eq!(cell(1, 2), Data::String("a".into()));
+    assert_eq!(cell(2, 2), Data::Empty, "old value is cleared");
+    let serial = |r, c| match cell(r, c) {
+        Data::DateTime(d) => d.as_f64(),
+        other => panic!("{other:?}"),
+    };
+    assert_eq!(serial(1, 3), 45_292.0);
+    assert_eq!(serial(1, 4), 45_292.5);
+    assert_eq!(cell(1, 5), Data::String("keep".into()));
+    assert_eq!(cell(6, 0), Data::Bool(true));
+    assert_eq!(cell(6, 1), Data::Float(-7.0));
+    Ok(())
+}
+
+#[test]
+fn append_values_keeps_types() -> Result<()> {
+    use crate::template_part::CellValue;
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_values.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Values")?;
+    ed.append_values(&[
+        (CellValue::Text("007".into()), None),
+        (CellValue::Number(2.5), None),
+        (CellValue::Empty, None),
+        (CellValue::Bool(true), None),
+        (CellValue::Number(45_292.0), Some("yyyy-mm-dd")),
+        (CellValue::Number(45_292.5), Some("yyyy-mm-dd hh:mm:ss")),
+        (CellValue::Formula("B1*2".into()), None),
+    ])?;
+    assert_eq!(ed.last_row, 1);
+    let xml = String::from_utf8_lossy(&ed.sheet_xml).into_owned();
+    assert!(!xml.contains(r#"r="C1""#), "{xml}");
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range("Values")?;
+    let cell = |c: u32| range.get_value((0, c)).cloned().unwrap_or(Data::Empty);
+    assert_eq!(cell(0), Data::String("007".into()));
+    assert_eq!(cell(1), Data::Float(2.5));
+    assert_eq!(cell(2), Data::Empty);
+    assert_eq!(cell(3), Data::Bool(true));
+    assert!(matches!(cell(4), Data::DateTime(d) if d.as_f64() == 45_292.0));
+    assert!(matches!(cell(5), Data::DateTime(d) if d.as_f64() == 45_292.5));
+    assert_eq!(wb.worksheet_formula("Values")?.get_value((0, 6)), Some(&"B1*2".to_string()));
+    Ok(())
+}
+
+#[test]
+fn set_row_height_creates_rows() -> Result<()> {
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Heights")?;
+    ed.append_row(["a"])?;
+    ed.set_row_height("1:3", 30.5)?.set_row_height("1", 12.0)?;
+    let xml = String::from_utf8_lossy(&ed.sheet_xml).into_owned();
+    assert!(xml.contains(r#"<row r="1" ht="12" customHeight="1">"#), "{xml}");
+    assert!(xml.contains(r#"<row r="3" ht="30.5" customHeight="1">"#), "{xml}");
+    assert!(ed.set_row_height("2", 500.0).is_err());
+    assert!(ed.validate()?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn get_cell_and_range_read_values() -> Result<()> {
+    use crate::read_part::ReadValue;
+    use crate::template_part::CellValue;
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Read")?;
+    ed.append_row(["Name", "Total"])?;
+    ed.append_values(&[
+        (CellValue::Text("Ann".into()), None),
+        (CellValue::Number(2.5), None),
+        (CellValue::Bool(false), None),
+        (CellValue::Number(45_292.0), Some("yyyy-mm-dd")),
+    ])?;
+    assert_eq!(ed.get_cell("A1")?, ReadValue::Text("Name".into()));
+    assert_eq!(ed.get_cell("B2")?, ReadValue::Number(2.5));
+    assert_eq!(ed.get_cell("D2")?, ReadValue::Date(45_292.0));
+    assert_eq!(ed.get_cell("Z9")?, ReadValue::Empty);
+    assert!(ed.get_cell("B").is_err());
+    let rows = ed.get_range("B1:C2")?;
+    assert_eq!(
+        rows,
+        vec![
+            vec![ReadValue::Text("Total".into()), ReadValue::Empty],
+            vec![ReadValue::Number(2.5), ReadValue::Bool(false)],
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn rows_iterates_lazily_with_gaps() -> Result<()> {
+    use crate::read_part::ReadValue;
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Data")?;
+    ed.append_row(["id", "name"])?;
+    ed.append_table_at("B4", [["7", "x"]])?;
+    ed.add_worksheet("Other")?;
+    // лист не текущий: правки берутся из несохранённых частей
+    let rows = ed.rows(Some("Data"))?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        rows,
+        vec![
+            vec![ReadValue::Text("id".into()), ReadValue::Text("name".into())],
+            vec![],
+            vec![],
+            vec![ReadValue::Empty, ReadValue::Number(7.0), ReadValue::Text("x".into())],
+        ]
+    );
+    assert_eq!(ed.rows(None)?.count(), 0);
+    assert!(ed.rows(Some("Missing")).is_err());
+    Ok(())
+}
+
+#[test]
+fn save_to_bytes_matches_parsed_values() -> Result<()> {
+    use crate::template_part::CellValue;
+    use calamine::{Data, Reader as _, Xlsx};
+    assert_eq!(CellValue::parse("=A1"), CellValue::Formula("A1".into()));
+    assert_eq!(CellValue::parse("1e3"), CellValue::Number(1000.0));
+    assert_eq!(CellValue::parse("inf"), CellValue::Text("inf".into()));
+    assert_eq!(CellValue::unix_days(0.5), CellValue::Number(25_569.5));
+
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Bytes")?;
+    ed.append_values(&[(CellValue::parse("42"), None), (CellValue::parse("x"), None)])?;
+    let bytes = ed.save_to_bytes()?;
+    assert!(bytes.starts_with(b"PK"));
+
+    let mut wb = Xlsx::new(std::io::Cursor::new(bytes))?;
+    let range = wb.worksheet_range("Bytes")?;
+    assert_eq!(range.get_value((0, 0)), Some(&Data::Float(42.0)));
+    assert_eq!(range.get_value((0, 1)), Some(&Data::String("x".into())));
+    Ok(())
+}
+
+#[test]
+fn open_bytes_edits_without_files() -> Result<()> {
+    use crate::read_part::ReadValue;
+    let data = std::fs::read("../test/test.xlsx")?;
+    let sheets = crate::scan_bytes(&data)?;
+    assert_eq!(sheets, scan("../test/test.xlsx")?);
+
+    let mut ed = XlsxEditor::open_bytes(data, &sheets[0])?;
+    let row = ed.last_row + 1;
+    ed.append_row(["in memory", "7"])?;
+    ed.add_worksheet("Extra")?;
+    ed.append_row(["x"])?;
+    let out = ed.save_to_bytes()?;
+
+    let mut back = XlsxEditor::open_bytes(out.clone(), &sheets[0])?;
+    assert_eq!(back.get_cell(&format!("B{row}"))?, ReadValue::Number(7.0));
+    assert_eq!(crate::scan_bytes(&out)?.last().map(String::as_str), Some("Extra"));
+    assert!(XlsxEditor::open_bytes(&b"not a zip"[..], "Sheet1").is_err());
+    Ok(())
+}
+
+#[test]
+fn json_rows_and_maps_parse_to_cell_values() -> Result<()> {
+    use crate::json_part::{parse_map, parse_row};
+    use crate::template_part::CellValue;
+    let row = parse_row(r#" [1.5, "=A1*2", "007x", "42", true, null, "a\"\u00e9\ud83d\ude00"] "#)?;
+    assert_eq!(
+        row,
+        vec![
+            CellValue::Number(1.5),
+            CellValue::Formula("A1*2".into()),
+            CellValue::Text("007x".into()),
+            CellValue::Number(42.0),
+            CellValue::Bool(true),
+            CellValue::Empty,
+            CellValue::Text("a\"é😀".into()),
+        ]
+    );
+    assert_eq!(parse_row("[]")?, vec![]);
+    let map = parse_map(r#"{"name": "Bob", "total": -3e2}"#)?;
+    assert_eq!(map["name"], CellValue::Text("Bob".into()));
+    assert_eq!(map["total"], CellValue::Number(-300.0));
+    for bad in ["[1, [2]]", "[1,", "[1] x", r#"["\ud800"]"#] {
+        assert!(parse_row(bad).is_err(), "{bad}");
+    }
+    for bad in ["{1: 2}", r#"{"a" 1}"#, r#"{"a": {}}"#] {
+        assert!(parse_map(bad).is_err(), "{bad}");
+    }
+    Ok(())
+}
+
+#[test]
+fn parallel_compression_matches_sequential() -> Result<()> {
+    use std::io::{Cursor, Read};
+    use ::zip::ZipArchive;
+    let src = "../test/test.xlsx";
+    // байты в память: другие тесты могут переписать исходник между сохранениями
+    let mut ed = XlsxEditor::open_bytes(std::fs::read(src)?, &scan(src)?[0])?;
+    // отметка времени в core.xml разошлась бы на границе секунды
+    ed.set_stamp_modified(false);
+    // два больших листа: их XML сжимается Deflate, а не пишется как есть
+    for name in ["Big1", "Big2"] {
+        ed.add_worksheet(name)?;
+        for i in 0..4000 {
+            ed.append_row([format!("{name} row {i}"), i.to_string()])?;
+        }
+    }
+    let entries = |bytes: Vec<u8>| -> Result<Vec<_>> {
+        let mut z = ZipArchive::new(Cursor::new(bytes))?;
+        let mut out = Vec::new();
+        for i in 0..z.len() {
+            let mut f = z.by_index(i)?;
+            let mut buf = Vec::new();
+            f.read_to_end(&mut buf)?;
+            out.push((f.name().to_owned(), f.compression(), f.unix_mode(), buf));
+        }
+        Ok(out)
+    };
+    let seq = entries(ed.set_compression_threads(1).save_to_bytes()?)?;
+    assert!(seq.iter().filter(|e| e.1 == ::zip::CompressionMethod::Deflated).count() >= 2);
+    for threads in [0, 3] {
+        let par = entries(ed.set_compression_threads(threads).save_to_bytes()?)?;
+        assert_eq!(par, seq, "threads = {threads}");
+    }
+    Ok(())
+}
+
+#[test]
+fn scattered_cell_edits_merge_in_order() -> Result<()> {
+    use crate::read_part::ReadValue;
+    use std::collections::BTreeMap;
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open_bytes(std::fs::read(src)?, &scan(src)?[0])?;
+    ed.add_worksheet("Scattered")?;
+    for i in 1..=50 {
+        ed.append_row([format!("r{i}"), i.to_string()])?;
+    }
+    // правки вразброс, в том числе поверх уже отложенных и существующих ячеек
+    let mut want = BTreeMap::new();
+    let mut seed = 7u64;
+    for i in 0..2000 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let (row, col) = ((seed >> 33) % 120 + 1, (seed >> 20) % 30);
+        let coord = format!("{}{row}", crate::style::col_letter(col as u32));
+        ed.set_cell(&coord, i)?;
+        want.insert(coord.clone(), i as f64);
+        if i % 97 == 0 {
+            // чтение посреди правок сливает их и не теряет следующие
+            assert_eq!(ed.get_cell(&coord)?, ReadValue::Number(i as f64));
+            ed.set_fill(&coord, "FFFF00")?;
+        }
+    }
+    ed.set_fill("C200", "00FF00")?;
+    assert_eq!(ed.get_cell("A1")?, ReadValue::Text("r1".into()));
+
+    let bytes = ed.save_to_bytes()?;
+    let mut re = XlsxEditor::open_bytes(bytes, "Scattered")?;
+    assert!(re.validate()?.is_ok());
+    let got = re.get_range("A1:AD120")?;
+    for (coord, v) in &want {
+        let (col, row) = crate::style::parse_cell(coord)?;
+        assert_eq!(got[row as usize - 1][col as usize], ReadValue::Number(*v), "{coord}");
+    }
+    // строки и ячейки в них идут по возрастанию
+    let xml = String::from_utf8(re.sheet_xml.to_vec())?;
+    let rows: Vec<u32> = xml
+        .match_indices("<row r=\"")
+        .map(|(p, m)| xml[p + m.len()..].split('"').next().unwrap().parse().unwrap())
+        .collect();
+    assert!(rows.windows(2).all(|w| w[0] < w[1]), "{rows:?}");
+    assert!(xml.contains(r#"<c r="C200" s=""#), "{xml}");
+    Ok(())
+}
+
+#[test]
+fn find_elem_checks_name_boundary() {
+    use crate::search::find_from;
+    use crate::sheet_xml::find_elem;
+    let xml = br#"<cols><col min="1"/></cols><sheetData><row r="1"><c r="A1"><v>1</v></c><c r="B1" s="2"/></row><row r="2"/></sheetData><rowBreaks count="0"/>"#;
+    let c = find_elem(xml, "c", 0).unwrap();
+    assert_eq!(&xml[c.clone()], br#"<c r="A1"><v>1</v></c>"#);
+    let c2 = find_elem(xml, "c", c.end).unwrap();
+    assert_eq!(&xml[c2], br#"<c r="B1" s="2"/>"#);
+    let r1 = find_elem(xml, "row", 0).unwrap();
+    let r2 = find_elem(xml, "row", r1.end).unwrap();
+    assert_eq!(&xml[r2.clone()], br#"<row r="2"/>"#);
+    assert_eq!(find_elem(xml, "row", r2.end), None);
+    assert!(find_elem(xml, "rowBreaks", 0).is_some());
+    assert_eq!(find_from(xml, b"<row", xml.len()), None);
+    assert_eq!(find_from(xml, b"<row", xml.len() + 5), None);
+}
+
+#[test]
+fn append_only_leaves_styles_and_workbook_unread() -> Result<()> {
+    use std::io::Read;
+    let entry = |bytes: &[u8], name: &str| -> Result<Vec<u8>> {
+        let mut z = ::zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+        let mut buf = Vec::new();
+        z.by_name(name)?.read_to_end(&mut buf)?;
+        Ok(buf)
+    };
+    let src = "../test/test.xlsx";
+    let orig = std::fs::read(src)?;
+    let mut ed = XlsxEditor::open_sheet(src, 1)?;
+    ed.append_row(["lazy", "1"])?;
+    assert!(ed.styles_xml.loaded().is_none());
+    assert!(ed.workbook_xml.loaded().is_none());
+    assert!(ed.rels_xml.loaded().is_none());
+    // несчитанные части уходят в архив как были
+    let bytes = ed.save_to_bytes()?;
+    for part in ["xl/styles.xml", "xl/workbook.xml", "xl/_rels/workbook.xml.rels"] {
+        assert_eq!(entry(&bytes, part)?, entry(&orig, part)?, "{part}");
+    }
+    assert!(ed.workbook_xml.loaded().is_none());
+
+    // первая правка стиля читает styles.xml
+    let last = ed.last_row;
+    ed.set_fill(&format!("A{last}"), "FFFF00")?;
+    assert!(ed.styles_xml.loaded().is_some());
+    assert!(ed.workbook_xml.loaded().is_none());
+    let bytes = ed.save_to_bytes()?;
+    assert_ne!(entry(&bytes, "xl/styles.xml")?, entry(&orig, "xl/styles.xml")?);
+    let mut re = XlsxEditor::open_bytes(bytes, &scan(src)?[0])?;
+    assert!(re.validate()?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn sheets_mut_edits_several_sheets_at_once() -> Result<()> {
+    use crate::read_part::ReadValue;
+    use crate::search::find;
+    use crate::template_part::CellValue;
+    let src = "../test/test.xlsx";
+    let first = scan(src)?[0].clone();
+    let mut ed = XlsxEditor::open_bytes(std::fs::read(src)?, &first)?;
+    ed.add_worksheet("A")?.add_worksheet("B")?;
+    ed.with_worksheet("A")?;
+    ed.append_table([["id", "name"], ["1", "x"]])?;
+    ed.add_table("A1:B2", "Items", None, None)?;
+    ed.with_worksheet(&first)?;
+    let first_last = ed.last_row;
+
+    ed.sheets_mut(&["A", "B", &first], |sheets| {
+        let [a, b, cur] = sheets else { unreachable!() };
+        for i in 2..=4 {
+            a.append_row([i.to_string(), format!("a{i}")])?;
+            b.append_values(&[CellValue::Number(i as f64), CellValue::Empty, CellValue::Bool(true)])?;
+        }
+        b.set_cell("D1", "=SUM(A1:A3)")?;
+        cur.append_row(["multi"])?;
+        assert_eq!((a.name(), a.last_row(), b.last_row()), ("A", 5, 3));
+        Ok(())
+    })?;
+    assert_eq!(ed.last_row, first_last + 1);
+    assert!(ed.sheets_mut(&["A", "a"], |_| Ok(())).is_err());
+    assert!(ed.sheets_mut(&["A", "Missing"], |_| Ok(())).is_err());
+    // ошибка в замыкании не теряет листы
+    let failed = ed.sheets_mut(&["B"], |_| -> Result<(), XlsxError> {
+        Err(anyhow::anyhow!("boom").into())
+    });
+    assert!(failed.is_err());
+
+    #[cfg(feature = "rayon")]
+    ed.par_sheets_mut(&["A", "B"], |s| s.append_row([format!("{}-par", s.name())]))?;
+
+    let bytes = ed.save_to_bytes()?;
+    let mut re = XlsxEditor::open_bytes(bytes, "A")?;
+    assert!(re.validate()?.is_ok());
+    let a = re.get_range("A1:B6")?;
+    assert_eq!(a[4][1], ReadValue::Text("a4".into()));
+    // таблица дотянулась до дописанных строк
+    let table = String::from_utf8(re.read_part("xl/tables/table1.xml")?.unwrap())?;
+    let last = if cfg!(feature = "rayon") { 6 } else { 5 };
+    assert!(table.contains(&format!(r#"ref="A1:B{last}""#)), "{table}");
+    re.with_worksheet("B")?;
+    assert_eq!(re.get_cell("A3")?, ReadValue::Number(4.0));
+    assert_eq!(re.get_cell("C1")?, ReadValue::Bool(true));
+    assert!(find(&re.sheet_xml, b"<f>SUM(A1:A3)</f>").is_some());
+    re.with_worksheet(&first)?;
+    assert_eq!(re.get_cell(&format!("A{}", first_last + 1))?, ReadValue::Text("multi".into()));
+    Ok(())
+}
+
+#[test]
+fn cells_are_written_straight_into_sheet_xml() -> Result<()> {
+    use crate::cell_xml::{is_number, push_col, push_f32, push_f64, push_int};
+    use crate::read_part::ReadValue;
+    use crate::search::find;
+    let text = |f: &dyn Fn(&mut Vec<u8>)| {
+        let mut out = Vec::new();
+        f(&mut out);
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(text(&|o| push_col(o, 0)), "A");
+    assert_eq!(text(&|o| push_col(o, 26)), "AA");
+    assert_eq!(text(&|o| push_col(o, 16_383)), "XFD");
+    assert_eq!(text(&|o| push_f64(o, 1.0)), "1");
+    assert_eq!(text(&|o| push_f64(o, -0.1)), "-0.1");
+    assert_eq!(text(&|o| push_f64(o, 1234567.25)), "1234567.25");
+    assert_eq!(text(&|o| push_f64(o, 1e21)), "1e21");
+    assert_eq!(text(&|o| push_f64(o, 1e20)), "100000000000000000000");
+    assert_eq!(text(&|o| push_f64(o, 1e-7)), "1e-7");
+    assert_eq!(text(&|o| push_f64(o, 0.000001)), "0.000001");
+    assert_eq!(text(&|o| push_f32(o, 0.1)), "0.1");
+    assert_eq!(text(&|o| push_int(o, -42i64)), "-42");
+    assert!(is_number("1e5") && is_number("-.5") && !is_number("NaN") && !is_number("inf"));
+
+    let src = "../test/test.xlsx";
+    let first = scan(src)?[0].clone();
+    let mut ed = XlsxEditor::open_bytes(std::fs::read(src)?, &first)?;
+    ed.add_worksheet("Dump")?;
+    let wide: Vec<String> = (0..28).map(|i| i.to_string()).collect();
+    ed.append_row(&wide)?;
+    ed.append_table([["1.5", "=A1*2", "a < b & c", "NaN"]])?;
+    ed.set_cell("C3", "1e3")?;
+    assert!(find(&ed.sheet_xml, br#"<c r="AB1"><v>27</v></c>"#).is_some());
+    assert!(find(&ed.sheet_xml, br#"<c r="B2"><f>A1*2</f></c>"#).is_some());
+
+    let mut re = XlsxEditor::open_bytes(ed.save_to_bytes()?, "Dump")?;
+    assert!(re.validate()?.is_ok());
+    let row = &re.get_range("A2:D2")?[0];
+    assert_eq!(row[0], ReadValue::Number(1.5));
+    assert_eq!(row[2], ReadValue::Text("a < b & c".into()));
+    assert_eq!(row[3], ReadValue::Text("NaN".into()));
+    assert_eq!(re.get_cell("C3")?, ReadValue::Number(1000.0));
+    Ok(())
+}
+
+#[test]
+fn save_copies_untouched_head_of_archive_as_is() -> Result<()> {
+    use crate::read_part::ReadValue;
+    use std::io::Read;
+    let src = "../test/test.xlsx";
+    let first = scan(src)?[0].clone();
+    let mut ed = XlsxEditor::open(src, &first)?;
+    ed.add_worksheet("Big")?;
+    ed.append_table((0..2_000).map(|i| [format!("row {i}"), i.to_string()]))?;
+    ed.add_worksheet("Tail")?;
+    let book = std::path::Path::new("../test/test_out_head_book.xlsx");
+    ed.save(book)?;
+
+    let mut ed = XlsxEditor::open(book, "Tail")?;
+    let tail_path = ed.sheet_path.clone();
+    ed.append_row(["appended"])?;
+    let out = std::path::Path::new("../test/test_out_head.xlsx");
+    ed.save(out)?;
+
+    let entries = |path: &std::path::Path| -> Result<Vec<(String, Vec<u8>)>> {
+        let mut zip = ::zip::ZipArchive::new(std::fs::File::open(path)?)?;
+        (0..zip.len())
+            .map(|i| {
+                let mut f = zip.by_index(i)?;
+                let mut buf = Vec::new();
+                f.read_to_end(&mut buf)?;
+                Ok((f.name().to_owned(), buf))
+            })
+            .collect()
+    };
+    let (before, after) = (entries(book)?, entries(out)?);
+    let names = |e: &[(String, Vec<u8>)]| e.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&before), names(&after));
+    // всё до листа Tail — байт в байт исходный файл, сам лист переписан
+    let head = ::zip::ZipArchive::new(std::fs::File::open(book)?)?
+        .by_name(&tail_path)?
+        .header_start() as usize;
+    let (book_bytes, out_bytes) = (std::fs::read(book)?, std::fs::read(out)?);
+    assert!(before.iter().position(|(n, _)| *n == tail_path) > Some(4));
+    assert_eq!(book_bytes[..head], out_bytes[..head]);
+    for ((name, old), (_, new)) in before.iter().zip(&after) {
+        if *name == tail_path {
+            assert_ne!(old, new);
+        } else if name != "docProps/core.xml" {
+            assert_eq!(old, new, "{name}");
+        }
+    }
+
+    // без правок переносится весь архив
+    let mut re = XlsxEditor::open(out, "Tail")?;
+    assert_eq!(re.get_cell("A1")?, ReadValue::Text("appended".into()));
+    let again = std::path::Path::new("../test/test_out_head_again.xlsx");
+    re.set_stamp_modified(false).save(again)?;
+    assert_eq!(names(&entries(again)?), names(&after));
+    let mut re = XlsxEditor::open(again, "Big")?;
+    assert!(re.validate()?.is_ok());
+    assert_eq!(re.last_row, 2_000);
+    Ok(())
+}
+
+#[test]
+fn save_reopens_source_with_data_descriptors_and_zip64_records() -> Result<()> {
+    use crate::read_part::ReadValue;
+    use ::zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+    use std::io::{Read, Write};
+    let src = "../test/test.xlsx";
+    let first = scan(src)?[0].clone();
+    let sheet = XlsxEditor::open(src, &first)?.sheet_path;
+    let mut zin = ZipArchive::new(std::fs::File::open(src)?)?;
+    let mut parts = Vec::new();
+    for i in 0..zin.len() {
+        let mut f = zin.by_index(i)?;
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        parts.push((f.name().to_owned(), buf));
+    }
+    // правленый лист — последним, всё до него может уйти одним куском
+    let at = parts.iter().position(|(n, _)| *n == sheet).unwrap();
+    let moved = parts.remove(at);
+    parts.push(moved);
+
+    // потоковая запись: размеры в дескрипторах данных после каждой записи;
+    // large_file: поле zip64 (0x0001) в записях каталога без zip64 EOCD
+    for large_file in [false, true] {
+        let mut zout = ZipWriter::new_stream(Vec::new());
+        for (name, buf) in &parts {
+            zout.start_file(name.as_str(), SimpleFileOptions::default().large_file(large_file))?;
+            zout.write_all(buf)?;
+        }
+        let data = zout.finish()?.into_inner();
+
+        let head = ZipArchive::new(std::io::Cursor::new(&data))?.by_name(&sheet)?.header_start() as usize;
+        let mut ed = XlsxEditor::open_bytes(data.clone(), &first)?;
+        ed.set_stamp_modified(false).set_cell("Z1", "edited")?;
+        let out = ed.save_to_bytes()?;
+        // записи с zip64 кусками не переносятся, с дескрипторами — да
+        assert_eq!(out[..head] == data[..head], !large_file, "large_file = {large_file}");
+        let mut z = ZipArchive::new(std::io::Cursor::new(out.clone()))?;
+        for i in 0..z.len() {
+            // чтение до конца сверяет CRC каждой записи
+            std::io::copy(&mut z.by_index(i)?, &mut std::io::sink())?;
+        }
+        let mut re = XlsxEditor::open_bytes(out, &first)?;
+        assert_eq!(re.get_cell("Z1")?, ReadValue::Text("edited".into()), "large_file = {large_file}");
+    }
+    Ok(())
+}
+
+#[test]
+fn xml_layout_mirrors_or_strips_source_indentation() -> Result<()> {
+    use crate::layout_part::XmlLayout;
+    let src = "../test/test.xlsx";
+    let pretty_book = "../test/test_out_layout_src.xlsx";
+    let first = scan(src)?[0].clone();
+    let row1 = "\n    <row r=\"1\">\n      <c r=\"B1\">\n        <v>1</v>\n      </c>\n    </row>";
+    let mut xl = XlsxEditor::open(src, &first)?;
+    xl.sheet_xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">\n  \
+         <dimension ref=\"B1\"/>\n  <sheetData>{row1}\n  </sheetData>\n</worksheet>"
+    )
+    .into_bytes()
+    .into();
+    xl.save(pretty_book)?;
+
+    let saved = |layout: XmlLayout| -> Result<String> {
+        let mut xl = XlsxEditor::open(pretty_book, &first)?;
+        xl.append_row(["  padded  ", "2"])?;
+        xl.set_xml_layout(layout);
+        let mut re = XlsxEditor::open_bytes(xl.save_to_bytes()?, &first)?;
+        Ok(String::from_utf8(re.source_part("xl/worksheets/sheet1.xml")?.unwrap())?)
+    };
+    let row2 = "<row r=\"2\"><c r=\"A2\" t=\"inlineStr\"><is><t>  padded  </t></is></c><c r=\"B2\"><v>2</v></c></row>";
+
+    let as_written = saved(XmlLayout::AsWritten)?;
+    assert!(as_written.contains(&format!("{row1}\n  {row2}</sheetData>")), "{as_written}");
+
+    // новая строка — с теми же отступами; уровень <t> продолжает шаг в два пробела
+    let matched = saved(XmlLayout::MatchSource)?;
+    let expected = format!(
+        "<sheetData>{row1}\n    <row r=\"2\">\n      <c r=\"A2\" t=\"inlineStr\">\n        <is>\n          \
+         <t>  padded  </t>\n        </is>\n      </c>\n      <c r=\"B2\">\n        <v>2</v>\n      </c>\n    \
+         </row>\n  </sheetData>\n</worksheet>"
+    );
+    assert!(matched.contains(&expected), "{matched}");
+
+    let compact = saved(XmlLayout::Compact)?;
+    let row1_compact = "<row r=\"1\"><c r=\"B1\"><v>1</v></c></row>";
+    assert!(compact.contains(&format!("\n  <sheetData>{row1_compact}{row2}</sheetData>\n</worksheet>")), "{compact}");
+
+    // книга в одну строку так и остаётся
+    let mut xl = XlsxEditor::open(src, &first)?;
+    xl.append_row(["dense"])?;
+    xl.set_xml_layout(XmlLayout::MatchSource);
+    let mut re = XlsxEditor::open_bytes(xl.save_to_bytes()?, &first)?;
+    let dense = String::from_utf8(re.source_part("xl/worksheets/sheet1.xml")?.unwrap())?;
+    assert!(!dense.contains("\n  "), "{dense}");
+    Ok(())
+}
+
+#[test]
+fn comments_and_pis_in_sheet_data_survive_edits() -> Result<()> {
+    use crate::range_part::ClearMode;
+    use crate::read_part::ReadValue;
+    use crate::sheet_xml::find_child;
+    let file_name = "../test/style_test.xlsx";
+
+    // в комментариях и PI — разметка, похожая на строки и ячейки
+    let notes = [
+        r#"<!-- generated: <row r="99"><c r="A99"><v>9</v></c></row> -->"#,
+        r#"<!-- </row> <c r="Z1"><v>9</v></c> -->"#,
+        "<!-- </c> -->",
+        r#"<?tool <row r="50"> ?>"#,
+        "<?tool </c> ?>",
+        "<!-- </sheetData> -->",
+    ];
+    let [n0, n1, n2, n3, n4, n5] = notes;
+    let data = format!(
+        r#"<sheetData>{n0}<row r="1"><c r="A1"><v>1</v></c>{n1}<c r="B1"><v>2</v></c></row>{n3}<row r="3"><c r="A3">{n2}<v>3</v></c>{n4}<c r="C3" t="inlineStr"><is><t>four</t></is></c></row>{n5}</sheetData>"#
+    );
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let sd = find_child(&xl.sheet_xml, "sheetData")?.unwrap();
+    xl.sheet_xml.splice(sd, data.bytes());
+    let book = xl.save_to_bytes()?;
+
+    let mut xl = XlsxEditor::open_bytes(book.clone(), "Sheet1")?;
+    assert_eq!(xl.last_row, 3);
+    assert_eq!(xl.get_cell("B1")?, ReadValue::Number(2.0));
+    assert_eq!(xl.get_cell("A3")?, ReadValue::Number(3.0));
+    assert_eq!(xl.get_cell("Z1")?, ReadValue::Empty);
+    assert_eq!(xl.get_cell("A99")?, ReadValue::Empty);
+
+    type Edit = fn(&mut XlsxEditor) -> Result<(), XlsxError>;
+    let edits: &[(&str, Edit)] = &[
+        ("append_row", |xl| xl.append_row(["tail"])),
+        ("set_cell", |xl| {
+            xl.set_cell("B2", "new row")?;
+            xl.set_cell("B1", "old row")?;
+            xl.set_cell("D3", "after pi")
+        }),
+        ("set_fill", |xl| xl.set_fill("A1:D3", "FFFF00").map(drop)),
+        ("set_number_format", |xl| xl.set_number_format("B1:C3", "0.00").map(drop)),
+        ("column format", |xl| xl.set_number_format("A:", "0.0").map(drop)),
+        ("insert_rows", |xl| xl.insert_rows(2, 2).map(drop)),
+        ("delete_rows", |xl| xl.delete_rows("2").map(drop)),
+        ("clear_range", |xl| xl.clear_range("B1:C3", ClearMode::All).map(drop)),
+        ("copy_range", |xl| xl.copy_range("A1:C3", "A5", false).map(drop)),
+        ("replace_all", |xl| xl.replace_all("four", "five", &Default::default()).map(drop)),
+    ];
+    for (name, edit) in edits {
+        let mut xl = XlsxEditor::open_bytes(book.clone(), "Sheet1")?;
+        edit(&mut xl)?;
+        assert!(xl.validate()?.is_ok(), "{name}: {:?}", xl.validate()?.issues);
+        let mut re = XlsxEditor::open_bytes(xl.save_to_bytes()?, "Sheet1")?;
+        let raw = String::from_utf8(re.source_part("xl/worksheets/sheet1.xml")?.unwrap())?;
+        for note in notes {
+            assert!(raw.contains(note), "{name} lost {note}: {raw}");
+        }
+        // ячейки из комментариев не ожили и не попали в размеры листа
+        assert_eq!(re.get_cell("Z1")?, ReadValue::Empty, "{name}: {raw}");
+        assert_eq!(re.get_cell("A99")?, ReadValue::Empty, "{name}: {raw}");
+        assert!(!raw.contains(r#"<dimension ref="A1:Z"#), "{name}: {raw}");
+    }
+
+    // правленые ячейки встали на места, а не внутрь комментариев
+    let mut xl = XlsxEditor::open_bytes(book, "Sheet1")?;
+    xl.set_cell("B2", "new row")?;
+    xl.set_cell("D3", "after pi")?;
+    let mut re = XlsxEditor::open_bytes(xl.save_to_bytes()?, "Sheet1")?;
+    assert_eq!(re.get_cell("B2")?, ReadValue::Text("new row".into()));
+    assert_eq!(re.get_cell("D3")?, ReadValue::Text("after pi".into()));
+    let raw = String::from_utf8(re.source_part("xl/worksheets/sheet1.xml")?.unwrap())?;
+    assert!(raw.contains(&format!(r#"</row>{n3}<row r="2">"#)), "{raw}");
+    let c3 = r#"<c r="C3" t="inlineStr"><is><t>four</t></is></c>"#;
+    assert!(raw.contains(&format!(r#"{n4}{c3}<c r="D3""#)), "{raw}");
+    Ok(())
+}
+
+#[test]
+fn append_records_follow_header_columns() -> Result<()> {
+    use crate::read_part::ReadValue::{self, Empty, Number, Text};
+    use crate::template_part::CellValue;
+    use std::collections::{BTreeMap, HashMap};
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_records.xlsx";
+    let first = &scan(file_name)?[0];
+    let t = |s: &str| Text(s.to_owned());
+
+    // заголовок со второй строки, столбцы не в том порядке, что у записей
+    let mut xl = XlsxEditor::open(file_name, first)?;
+    xl.add_worksheet("Orders")?;
+    xl.set_cell("B2", "Total")?;
+    xl.set_cell("C2", " Customer ")?;
+    xl.set_cell("D2", "Id")?;
+    let ann = HashMap::from([
+        ("Id", CellValue::from(1)),
+        ("Customer", CellValue::from("Ann")),
+        ("Total", CellValue::from(10.5)),
+    ]);
+    let bob = vec![("Customer", CellValue::from("Bob")), ("Total", CellValue::Empty)];
+    xl.append_records([ann.clone(), bob.into_iter().collect()], false)?;
+    assert_eq!(xl.last_row, 4);
+
+    // неизвестный ключ без add_columns — ошибка, лист не меняется
+    let note = BTreeMap::from([("Id", CellValue::from(3)), ("Note", CellValue::from("late"))]);
+    let err = xl.append_records([note.clone()], false).unwrap_err();
+    assert!(err.to_string().contains("\"Note\""), "{err}");
+    assert_eq!(xl.last_row, 4);
+    xl.append_records([note], true)?;
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Orders")?;
+    let expected: Vec<Vec<ReadValue>> = vec![
+        vec![t("Total"), t(" Customer "), t("Id"), t("Note")],
+        vec![Number(10.5), t("Ann"), Number(1.0), Empty],
+        vec![Empty, t("Bob"), Empty, Empty],
+        vec![Empty, Empty, Number(3.0), t("late")],
+    ];
+    assert_eq!(xl.get_range("B2:E5")?, expected);
+    assert!(xl.validate()?.is_ok());
+
+    // у пустого листа заголовок пишется первой строкой, в порядке ключей
+    xl.add_worksheet("Fresh")?;
+    xl.append_records([vec![("b", CellValue::from(2)), ("a", CellValue::from(1))]], true)?;
+    xl.append_records([BTreeMap::from([("a", CellValue::from(3))])], false)?;
+    assert_eq!(
+        xl.get_range("A1:B3")?,
+        vec![vec![t("b"), t("a")], vec![Number(2.0), Number(1.0)], vec![Empty, Number(3.0)]]
+    );
+    let err = XlsxEditor::open(file_name_out, first)?
+        .add_worksheet("Empty")?
+        .append_records([ann], false)
+        .unwrap_err();
+    assert!(err.to_string().contains("no header row"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn upsert_rows_update_by_key_and_append_new() -> Result<()> {
+    use crate::read_part::ReadValue::{self, Empty, Number, Text};
+    use crate::template_part::CellValue;
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_upsert.xlsx";
+    let first = &scan(file_name)?[0];
+    let t = |s: &str| Text(s.to_owned());
+
+    let mut xl = XlsxEditor::open(file_name, first)?;
+    xl.add_worksheet("Orders")?;
+    xl.append_table([["Id", "Status", "Total"], ["1", "new", "5"], ["2", "new", "7"]])?;
+    xl.set_cell("A4", "7")?;
+    xl.set_fill("B3", "FFFF00")?;
+    let rec = |id: CellValue, cells: &[(&str, CellValue)]| {
+        let mut r = vec![("Id".to_owned(), id)];
+        r.extend(cells.iter().map(|(k, v)| (k.to_string(), v.clone())));
+        r
+    };
+    xl.upsert_rows(
+        "Id",
+        [
+            rec(2.into(), &[("Status", "shipped".into())]),
+            rec(3.into(), &[("Status", "new".into()), ("Total", 1.into())]),
+            rec(3.into(), &[("Total", 4.into())]),
+            rec(1.into(), &[("Total", CellValue::Empty)]),
+            // число 7 и текст "7" — разные ключи
+            rec(7.into(), &[("Status", "number id".into())]),
+            rec("7".into(), &[("Status", "text id".into())]),
+        ],
+    )?;
+    assert_eq!(xl.last_row, 6);
+    assert!(String::from_utf8_lossy(&xl.sheet_xml).contains(r#"<c r="B3" s=""#));
+
+    // ошибки — до правки листа
+    let errs = [
+        vec![rec(9.into(), &[("Note", "x".into())])],
+        vec![vec![("Status".to_owned(), "lost".into())]],
+        vec![rec(CellValue::Formula("=1+1".into()), &[])],
+    ];
+    for (bad, text) in errs.into_iter().zip(["\"Note\"", "no key column", "needs a value"]) {
+        let err = xl.upsert_rows("Id", bad).unwrap_err();
+        assert!(err.to_string().contains(text), "{err}");
+    }
+    let err = xl.upsert_rows("Code", [rec(1.into(), &[])]).unwrap_err();
+    assert!(err.to_string().contains("no column \"Code\""), "{err}");
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Orders")?;
+    let expected: Vec<Vec<ReadValue>> = vec![
+        vec![t("Id"), t("Status"), t("Total")],
+        vec![Number(1.0), t("new"), Empty],
+        vec![Number(2.0), t("shipped"), Number(7.0)],
+        vec![Number(7.0), t("number id"), Empty],
+        vec![Number(3.0), t("new"), Number(4.0)],
+        vec![t("7"), t("text id"), Empty],
+    ];
+    assert_eq!(xl.get_range("A1:C6")?, expected);
+    assert!(xl.validate()?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn insert_row_sorted_keeps_column_order() -> Result<()> {
+    use crate::read_part::ReadValue::{Date, Number, Text};
+    use crate::template_part::CellValue;
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_sorted.xlsx";
+    let first = &scan(file_name)?[0];
+    let day = |d: f64| (CellValue::Number(d), None);
+    let rate = |r: f64| (CellValue::Number(r), None);
+
+    // даты по возрастанию, под ними итог по столбцу B
+    let mut xl = XlsxEditor::open(file_name, first)?;
+    xl.add_worksheet("Rates")?;
+    xl.append_row(["Date", "Rate"])?;
+    for (d, r) in [(45_300.0, 1.0), (45_302.0, 2.0), (45_305.0, 3.0)] {
+        xl.append_values(&[(CellValue::Number(d), Some("yyyy-mm-dd")), rate(r)])?;
+    }
+    xl.append_row(["Total", "=SUM(B2:B4)"])?;
+
+    assert_eq!(xl.insert_row_sorted("A", &[day(45_303.0), rate(4.0)])?, 4);
+    assert_eq!(xl.insert_row_sorted("A", &[day(45_310.0), rate(5.0)])?, 6);
+    assert_eq!(xl.insert_row_sorted("A", &[day(45_290.0), rate(6.0)])?, 2);
+    // равная дата — после уже стоящей
+    assert_eq!(xl.insert_row_sorted("$A", &[day(45_302.0), rate(7.0)])?, 5);
+    assert_eq!(xl.last_row, 9);
+
+    let dates: Vec<_> = (2..=8).map(|r| xl.get_cell(&format!("A{r}"))).collect::<Result<_, _>>()?;
+    let expected = [45_290.0, 45_300.0, 45_302.0, 45_302.0, 45_303.0, 45_305.0, 45_310.0];
+    assert_eq!(dates, expected.map(Date));
+    assert_eq!(xl.get_cell("B5")?, Number(7.0));
+    assert_eq!(xl.get_cell("A9")?, Text("Total".to_owned()));
+    // ссылки — как при insert_rows: строки на краях диапазона в него не входят
+    assert!(String::from_utf8_lossy(&xl.sheet_xml).contains("<f>SUM(B3:B7)</f>"));
+
+    // ошибки — до правки листа
+    let err = xl.insert_row_sorted("B", &[day(1.0)]).unwrap_err();
+    assert!(err.to_string().contains("no value in column B"), "{err}");
+    let err = xl.insert_row_sorted("A", &[(CellValue::from("x"), None)]).unwrap_err();
+    assert!(err.to_string().contains("needs a number"), "{err}");
+    let err = xl.insert_row_sorted("A:B", &[day(1.0)]).unwrap_err();
+    assert!(err.to_string().contains("one column"), "{err}");
+    let err = xl.insert_row_sorted("B", &[day(1.0), rate(1.0)]).unwrap_err();
+    assert!(err.to_string().contains("not sorted at row 4"), "{err}");
+    assert_eq!(xl.last_row, 9);
+    xl.save(file_name_out)?;
+
+    // по убыванию; в пустом столбце — под последней строкой
+    let mut xl = XlsxEditor::open(file_name_out, "Rates")?;
+    xl.add_worksheet("Desc")?;
+    assert_eq!(xl.insert_row_sorted("A", &[rate(5.0)])?, 1);
+    xl.append_table([["3"], ["1"]])?;
+    assert_eq!(xl.insert_row_sorted("A", &[rate(2.0)])?, 3);
+    assert_eq!(xl.insert_row_sorted("A", &[rate(9.0)])?, 1);
+    assert_eq!(
+        xl.get_range("A1:A5")?,
+        [9.0, 5.0, 3.0, 2.0, 1.0].map(|x| vec![Number(x)]).to_vec()
+    );
+    assert!(xl.validate()?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn transaction_rolls_back_failed_edits() -> Result<()> {
+    use crate::read_part::ReadValue::{Empty, Text};
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_transaction.xlsx";
+    let first = &scan(file_name)?[0];
+    let t = |s: &str| Text(s.to_owned());
+
+    let mut xl = XlsxEditor::open(file_name, first)?;
+    xl.set_cell("A1", "base")?;
+    xl.set_fill("A1", "00FF00")?;
+    let state = |xl: &XlsxEditor| -> Result<_> {
+        let parts: Vec<_> = xl.new_files.iter().map(|(p, c)| (p.clone(), c.to_vec())).collect();
+        Ok((
+            xl.sheet_path.clone(),
+            xl.sheet_xml.to_vec(),
+            xl.last_row,
+            xl.styles_xml.get(&xl.src)?.clone(),
+            xl.workbook_xml.get(&xl.src)?.clone(),
+            parts,
+        ))
+    };
+    let before = state(&xl)?;
+
+    // стиль, новый лист и дозапись уже легли, потом ошибка — всё отменяется
+    let err = xl
+        .transaction(|xl| -> Result<(), XlsxError> {
+            xl.set_fill("A1:B2", "FF0000")?;
+            xl.set_cell("B1", "half")?;
+            xl.add_worksheet("Extra")?;
+            xl.append_row(["x"])?;
+            xl.with_worksheet(first)?.delete_rows("1")?;
+            Err(anyhow::anyhow!("boom").into())
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "boom");
+    assert!(state(&xl)? == before);
+    assert!(xl.savepoints.is_empty());
+
+    // удачная транзакция оставляет правки
+    let width = xl.transaction(|xl| {
+        xl.set_cell("B1", "kept")?;
+        Ok(2)
+    })?;
+    assert_eq!(width, 2);
+    assert_eq!(xl.get_range("A1:B1")?, vec![vec![t("base"), t("kept")]]);
+
+    // вложенные: откат отменяет только внутреннюю
+    xl.begin().set_cell("C1", "outer")?;
+    xl.begin().set_cell("D1", "inner")?;
+    xl.set_column_width("D", 30.0)?;
+    xl.rollback()?.commit()?;
+    assert!(xl.commit().is_err() && xl.rollback().is_err());
+    assert_eq!(xl.get_range("C1:D1")?, vec![vec![t("outer"), Empty]]);
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, first)?;
+    assert_eq!(xl.get_range("A1:D1")?, vec![vec![t("base"), t("kept"), t("outer"), Empty]]);
+    assert!(!scan(file_name_out)?.iter().any(|n| n == "Extra"));
+    assert!(xl.validate()?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn change_summary_lists_edits_and_audit_sheet() -> Result<()> {
+    use crate::audit_part::PartChangeKind::Modified;
+    use crate::read_part::ReadValue::{Number, Text};
+    use crate::{scan_info, sheets_part::SheetVisibility};
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_audit.xlsx";
+    let first = &scan(file_name)?[0];
+
+    let mut xl = XlsxEditor::open(file_name, first)?;
+    assert!(xl.change_summary()?.is_empty());
+    xl.set_cell("A1", 1)?; // то же значение — не перезапись
+    xl.set_cell("B1", 2)?;
+    xl.set_cell("C1", "new")?; // ячейки не было — тоже
+    xl.set_number_format("C1", "0.000%")?;
+    let last = xl.last_row;
+    xl.append_table([["x", "1"], ["y", "2"]])?;
+    // отменённая дозапись в сводку не попадает
+    xl.begin().append_row(["z"])?;
+    xl.rollback()?;
+
+    let sum = xl.change_summary()?;
+    let sheet1 = "xl/worksheets/sheet1.xml";
+    assert!(sum.parts.iter().any(|p| p.path == sheet1 && p.kind == Modified));
+    assert!(sum.parts.iter().any(|p| p.path == "xl/styles.xml" && p.kind == Modified));
+    assert_eq!(sum.sheets.len(), 1);
+    let s = &sum.sheets[0];
+    assert_eq!((s.name.as_str(), s.path.as_str()), (first.as_str(), sheet1));
+    assert_eq!(s.rows_appended, 2);
+    assert_eq!(s.cells_overwritten, ["B1"]);
+    assert_eq!(sum.styles.cell_formats, 1);
+    assert_eq!(sum.styles.number_formats, ["0.000%"]);
+    assert!(sum.to_string().contains("cell overwritten: "));
+
+    xl.write_audit_sheet("audit")?;
+    assert_eq!(xl.current_sheet_name()?, *first);
+    assert_eq!(xl.last_row, last + 2);
+    xl.save(file_name_out)?;
+
+    let info = scan_info(file_name_out)?;
+    let audit = info.sheets.iter().find(|s| s.name == "audit").unwrap();
+    assert_eq!(audit.visibility, SheetVisibility::Hidden);
+    let mut xl = XlsxEditor::open(file_name_out, "audit")?;
+    let rows = xl.get_range(&format!("A1:C{}", xl.last_row))?;
+    let t = |s: &str| Text(s.to_owned());
+    assert_eq!(rows[0], [t("Change"), t("Target"), t("Detail")]);
+    assert!(rows.contains(&vec![t("cell overwritten"), t(first), t("B1")]));
+    assert!(rows.contains(&vec![t("rows appended"), t(first), Number(2.0)]));
+    // лист аудита о себе не пишет
+    assert!(!rows.iter().any(|r| r[1] == t("audit")));
+    assert!(xl.validate()?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn diff_reports_cell_value_and_style_changes() -> Result<()> {
+    use crate::read_part::ReadValue::Number;
+    let file_name = "../test/test.xlsx";
+    let file_name_out = "../test/test_out_diff.xlsx";
+    let first = &scan(file_name)?[0];
+    assert!(crate::diff(file_name, file_name)?.is_empty());
+
+    let mut xl = XlsxEditor::open(file_name, first)?;
+    // тот же текст, записанный заново (уже не из sharedStrings), — не различие
+    let a4 = xl.get_cell("A4")?.to_text().unwrap();
+    xl.set_cell("A4", a4.as_str())?;
+    xl.set_cell("B1", 2)?;
+    xl.set_fill("A3", "FFFF00")?;
+    xl.add_worksheet("Extra")?;
+    xl.save(file_name_out)?;
+
+    let d = crate::diff(file_name, file_name_out)?;
+    assert_eq!(d.added_sheets, ["Extra"]);
+    assert!(d.removed_sheets.is_empty());
+    let cells = &d.sheet(first).unwrap().cells;
+    let addrs: Vec<&str> = cells.iter().map(|c| c.cell.as_str()).collect();
+    assert_eq!(addrs, ["B1", "A3"]);
+    let (b1, a3) = (&cells[0], &cells[1]);
+    assert!(b1.value_changed() && !b1.style_changed());
+    assert_eq!((&b1.before.value, &b1.after.value), (&Number(1.0), &Number(2.0)));
+    assert!(a3.style_changed() && !a3.value_changed());
+    assert!(a3.after.style.fill.contains("FFFF00"));
+    assert_eq!(a3.before.style.number_format, "General");
+    assert!(d.to_string().contains(&format!("{first}!B1: 1 -> 2")));
+
+    // в обратную сторону лист считается удалённым
+    let back = crate::diff(file_name_out, file_name)?;
+    assert_eq!(back.removed_sheets, ["Extra"]);
+    assert_eq!(back.sheet(first).unwrap().cells.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn sheet_blocks_follow_schema_order() -> Result<()> {
+    use crate::protection_part::SheetPermissions;
+    use crate::sheet_xml::{WORKSHEET_ORDER, find_child, worksheet_children};
+    use crate::validation::{ValidationRule, ValidationSpec};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_schema_order.xlsx";
+
+    // блоки, что по схеме стоят между `</sheetData>` и `<mergeCells>`, — раньше
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.protect_sheet("Sheet1", None, SheetPermissions::default())?;
+    xl.set_auto_filter("A1:D7")?;
+    xl.set_data_validation(
+        "A2:A9",
+        ValidationSpec::new(ValidationRule::List(vec!["Yes".into(), "No".into()])),
+    )?;
+    xl.merge_cells("F1:G1")?;
+    xl.merge_cells("F2:G2")?;
+    xl.add_conditional_format(crate::conditional::ConditionalFormat::new("B2:B9").color_scale(
+        "FFFFFF",
+        None,
+        "FF0000",
+    ))?;
+
+    let names: Vec<String> = worksheet_children(&xl.sheet_xml)?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    let at = |n: &str| WORKSHEET_ORDER.iter().position(|o| *o == n).unwrap();
+    assert!(names.windows(2).all(|w| at(&w[0]) <= at(&w[1])), "{names:?}");
+    assert_eq!(xl.get_merged_ranges()?, ["F1:G1", "F2:G2"]);
+    let block = find_child(&xl.sheet_xml, "mergeCells")?.unwrap();
+    assert!(xl.sheet_xml[block].starts_with(br#"<mergeCells count="2">"#));
+    xl.save(file_name_out)?;
+    assert!(XlsxEditor::open(file_name_out, "Sheet1")?.validate()?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn merge_list_rewrites_block_with_count() -> Result<()> {
+    use crate::sheet_xml::find_child;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_merge_list.xlsx";
+    let block = |xl: &XlsxEditor| -> Result<Option<String>> {
+        let xml = &xl.sheet_xml;
+        Ok(find_child(xml, "mergeCells")?.map(|b| String::from_utf8_lossy(&xml[b]).into_owned()))
+    };
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.merge_cells("H1:I1")?;
+    xl.merge_cells("H2:I3")?;
+    xl.unmerge_cells("H2")?;
+    xl.merge_cells("h5:H6")?;
+    assert_eq!(xl.get_merged_ranges()?, ["H1:I1", "H5:H6"]);
+    assert_eq!(
+        block(&xl)?.as_deref(),
+        Some(r#"<mergeCells count="2"><mergeCell ref="H1:I1"/><mergeCell ref="H5:H6"/></mergeCells>"#)
+    );
+
+    // правка всего XML видит блок и сдвигает его, список перечитывается
+    xl.insert_rows(1, 1)?;
+    assert_eq!(xl.get_merged_ranges()?, ["H2:I2", "H6:H7"]);
+
+    // последнее разъединение убирает блок, откат возвращает список
+    xl.begin().unmerge_cells("A1:Z100")?;
+    assert!(xl.get_merged_ranges()?.is_empty());
+    assert_eq!(block(&xl)?, None);
+    xl.rollback()?;
+    assert_eq!(xl.get_merged_ranges()?, ["H2:I2", "H6:H7"]);
+    xl.merge_cells("A20:B20")?;
+    xl.save(file_name_out)?;
+
+    let xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    assert_eq!(xl.get_merged_ranges()?, ["H2:I2", "H6:H7", "A20:B20"]);
+    assert!(block(&xl)?.unwrap().starts_with(r#"<mergeCells count="3">"#));
+    Ok(())
+}
+
+#[test]
+fn theme_colors_for_fill_font_and_border() -> Result<()> {
+    use crate::read_part::read_cells;
+    use crate::theme_part::Color;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_theme.xlsx";
+    let style_of = |xl: &XlsxEditor, col: u32| -> Result<Option<u32>> {
+        Ok(read_cells(&xl.sheet_xml)?
+            .into_iter()
+            .find(|c| c.row == 1 && c.col == col)
+            .and_then(|c| c.s))
+    };
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let palette = xl.get_theme_colors()?;
+    let names: Vec<&str> = palette.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(&names[..5], ["lt1", "dk1", "lt2", "dk2", "accent1"]);
+    assert_eq!(palette[0].rgb, "FFFFFF");
+    assert_eq!(palette[4].rgb, "5B9BD5");
+
+    assert_eq!("theme:4:-0.25".parse::<Color>()?, Color::theme(4, -0.25));
+    assert_eq!("#ff0000".parse::<Color>()?, Color::Rgb("FF0000".into()));
+    assert!("theme:4:2".parse::<Color>().is_err());
+    assert!("red".parse::<Color>().is_err());
+
+    xl.set_fill("A1:B1", "theme:4:-0.25")?;
+    xl.set_font_with_color("A1:B1", "Calibri", 11.0, true, false, "theme:0")?;
+    xl.set_border_with_color("A1:B2", "thin", "theme:4")?;
+    let styles = String::from_utf8(xl.styles_xml.get(&xl.src)?.clone())?;
+    assert!(styles.contains(r#"<fgColor theme="4" tint="-0.25"/>"#));
+    assert!(styles.contains(r#"<sz val="11"/><color theme="0"/><name val="Calibri"/>"#));
+    assert!(styles.contains(r#"<left style="thin"><color theme="4"/></left>"#));
+    let s_a1 = style_of(&xl, 0)?;
+    assert!(s_a1.is_some());
+    xl.save(file_name_out)?;
+
+    // после переоткрытия те же цвета находятся в индексе — новых частей стиля нет
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    let before = xl.styles_xml.get(&xl.src)?.len();
+    xl.set_fill("C1", "theme:4:-0.25")?;
+    xl.set_font_with_color("C1", "Calibri", 11.0, true, false, "theme:0")?;
+    xl.set_border_with_color("C1", "thin", "theme:4")?;
+    assert_eq!(xl.styles_xml.get(&xl.src)?.len(), before);
+    assert_eq!(style_of(&xl, 2)?, s_a1);
+    Ok(())
+}
+
+#[test]
+fn cell_style_reads_back_format_font_fill_and_border() -> Result<()> {
+    use crate::format_part::BorderSide;
+    use crate::theme_part::Color;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_cell_style.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_number_format("H2", "0.00%")?;
+    xl.set_fill("H3", "theme:4:-0.25")?
+        .set_font_with_color("H3", "Arial", 12.0, true, false, "FF0000")?
+        .set_border_with_color("H3", "thin", "theme:1")?
+        .set_alignment(
+            "H3",
+            &AlignSpec {
+                horiz: Some(HorizAlignment::Center),
+                vert: None,
+                wrap: true,
+            },
+        )?;
+    xl.set_number_format("G:", "yyyy-mm-dd")?;
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    assert_eq!(xl.get_number_format("H2")?.as_deref(), Some("0.00%"));
+    let spec = xl.get_cell_style("H3")?;
+    assert_eq!(spec.fill, Some(Color::theme(4, -0.25)));
+    assert_eq!(spec.font.name, "Arial");
+    assert_eq!(spec.font.size, 12.0);
+    assert!(spec.font.bold && !spec.font.italic);
+    assert_eq!(spec.font.color, Some(Color::Rgb("FF0000".into())));
+    let thin = BorderSide {
+        style: "thin".into(),
+        color: Some(Color::theme(1, 0.0)),
+    };
+    assert_eq!(spec.border.left.as_ref(), Some(&thin));
+    assert_eq!(spec.border.bottom.as_ref(), Some(&thin));
+    assert_eq!(spec.alignment.horiz, Some(HorizAlignment::Center));
+    assert!(spec.alignment.wrap && spec.locked && !spec.hidden);
+
+    // пустая ячейка берёт стиль столбца; «Общий» — None
+    assert_eq!(xl.get_number_format("G500")?.as_deref(), Some("yyyy-mm-dd"));
+    assert_eq!(xl.get_number_format("G")?.as_deref(), Some("yyyy-mm-dd"));
+    assert_eq!(xl.get_number_format("Z500")?, None);
+    let blank = xl.get_cell_style("Z500")?;
+    assert_eq!((blank.fill, blank.border.left), (None, None));
+    assert!(xl.get_cell_style("G:H").is_err());
+    Ok(())
+}
+
+#[test]
+fn numfmt_builtins_skip_custom_numfmt() -> Result<()> {
+    use crate::numfmt::NumFmt;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_numfmt.xlsx";
+
+    assert_eq!(NumFmt::percent(2), NumFmt::PERCENT_DECIMAL);
+    assert_eq!(NumFmt::thousands(0).builtin_id(), Some(3));
+    assert_eq!(NumFmt::date_iso().builtin_id(), None);
+    assert_eq!(&*NumFmt::currency("EUR")?, "#,##0.00 [$€-407]");
+    assert_eq!(&*NumFmt::currency("USD")?, "[$$-409]#,##0.00");
+    assert_eq!(&*NumFmt::currency("JPY")?, "[$¥-411]#,##0");
+    assert_eq!(&*NumFmt::currency("NOK")?, "#,##0.00 [$NOK]");
+    assert!(NumFmt::currency("euro").is_err());
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let custom = |xl: &XlsxEditor| -> Result<usize> {
+        Ok(memchr::memmem::find_iter(xl.styles_xml.get(&xl.src)?, b"<numFmt ").count())
+    };
+    let before = custom(&xl)?;
+    xl.set_number_format("H2", &NumFmt::PERCENT_DECIMAL)?;
+    xl.set_number_format("H3", &NumFmt::scientific(2))?;
+    assert_eq!(custom(&xl)?, before);
+    // кавычки в коде экранируются, а при повторе код находится в индексе
+    xl.set_number_format("H4", r#"0 "pcs""#)?;
+    xl.set_number_format("H5", &NumFmt::currency("EUR")?)?;
+    assert_eq!(custom(&xl)?, before + 2);
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    xl.set_number_format("H6", r#"0 "pcs""#)?;
+    assert_eq!(custom(&xl)?, before + 2);
+    assert_eq!(xl.get_number_format("H2")?.as_deref(), Some("0.00%"));
+    assert_eq!(xl.get_number_format("H3")?.as_deref(), Some("0.00E+00"));
+    assert_eq!(xl.get_number_format("H6")?.as_deref(), Some(r#"0 "pcs""#));
+    assert_eq!(xl.get_number_format("H5")?.as_deref(), Some("#,##0.00 [$€-407]"));
+    Ok(())
+}
+
+#[test]
+fn dxf_dedup_across_conditional_formats_and_reopen() -> Result<()> {
+    use crate::conditional::{CfStyle, ConditionalFormat};
+    use crate::format_part::{BorderSide, FontSpec, StyleSpec};
+    use crate::theme_part::Color;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_dxf.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let spec = StyleSpec {
+        number_format: Some("0.0%".into()),
+        font: FontSpec { italic: true, color: Some(Color::theme(4, -0.25)), ..FontSpec::default() },
+        fill: Some("theme:5:0.4".parse()?),
+        ..StyleSpec::default()
+    };
+    let mut boxed = spec.clone();
+    boxed.border.bottom = Some(BorderSide { style: "thin".into(), color: None });
+    let a = xl.ensure_dxf(&spec)?;
+    let b = xl.ensure_dxf(&boxed)?;
+    assert_ne!(a, b);
+    assert_eq!(xl.ensure_dxf(&spec)?, a);
+    let styles = String::from_utf8(xl.styles_xml.get(&xl.src)?.clone())?;
+    assert!(styles.contains(concat!(
+        r#"<dxf><font><i/><color theme="4" tint="-0.25"/></font><numFmt numFmtId="164" formatCode="0.0%"/>"#,
+        r#"<fill><patternFill><bgColor theme="5" tint="0.4"/></patternFill></fill></dxf>"#
+    )));
+    assert!(styles.contains(r#"<border><bottom style="thin"/></border></dxf>"#));
+
+    let green = CfStyle { fill: Some("C6EFCE".into()), ..Default::default() };
+    xl.add_conditional_format(ConditionalFormat::new("D2:D9").expression("D2>0", green.clone()))?;
+    xl.save(file_name_out)?;
+
+    // после открытия <dxfs> из файла попадает в индекс: тот же dxf не дублируется
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    assert_eq!(xl.ensure_dxf(&boxed)?, b);
+    xl.add_conditional_format(ConditionalFormat::new("E2:E9").expression("E2>0", green))?;
+    let xml = String::from_utf8(xl.sheet_xml.clone())?;
+    assert_eq!(xml.matches(&format!(r#"dxfId="{}""#, b + 1)).count(), 2);
+    let styles = String::from_utf8(xl.styles_xml.get(&xl.src)?.clone())?;
+    assert!(styles.contains(&format!(r#"<dxfs count="{}">"#, b + 2)));
+    Ok(())
+}
+
+#[test]
+fn appended_cells_inherit_column_style() -> Result<()> {
+    use crate::template_part::CellValue;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_inherit.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_number_format("C:", "yyyy-mm-dd")?;
+    let cols = crate::sheet_xml::find_child(&xl.sheet_xml, "cols")?.unwrap();
+    let date = xl.read_cols_map(cols.start, cols.end)?[&3].style;
+    assert!(date.is_some());
+
+    // по умолчанию дописанные ячейки без s
+    xl.append_row(["a", "b", "45000"])?;
+    let row = xl.last_row;
+    assert_eq!(xl.cell_style_attr(row, 2), Some(None));
+
+    xl.set_inherit_column_styles(true);
+    xl.append_table([["c", "d", "45001"], ["e", "f", "45002"]])?;
+    let fixed = [(CellValue::Empty, None), (CellValue::Empty, None), (1.5.into(), Some("0.0"))];
+    xl.append_values(&fixed)?;
+    for r in row + 1..=row + 2 {
+        assert_eq!(xl.cell_style_attr(r, 2), Some(date));
+        assert_eq!(xl.cell_style_attr(r, 1), Some(None));
+    }
+    // свой формат ячейки важнее столбца, а прежние строки не трогаются
+    assert_ne!(xl.cell_style_attr(row + 3, 2), Some(date));
+    assert_eq!(xl.cell_style_attr(row, 2), Some(None));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn number_format_for_whole_row() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_row_numfmt.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_number_format("2:", "0.00%")?;
+    let far = xl.last_row + 3;
+    xl.set_number_format(&format!("{far}:"), "yyyy-mm-dd")?;
+    assert!(xl.set_number_format("0:", "0.00%").is_err());
+
+    let tag = String::from_utf8(xl.sheet_xml.base_row_tag(2).unwrap().to_vec())?;
+    assert!(tag.contains(r#"customFormat="1""#));
+    let sid = crate::sheet_xml::get_attr(tag.as_bytes(), "s").unwrap().parse::<u32>()?;
+    // все ячейки строки получили стиль, соседние строки — нет
+    let (row2, row3) = (xl.cell_style_attr(2, 0), xl.cell_style_attr(3, 0));
+    assert_eq!(row2, Some(Some(sid)));
+    assert_ne!(row3, Some(Some(sid)));
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    assert_eq!(xl.get_number_format("A2")?.as_deref(), Some("0.00%"));
+    // ячейки ещё нет — действует стиль строки
+    assert_eq!(xl.get_number_format("XF2")?.as_deref(), Some("0.00%"));
+    assert_eq!(xl.get_number_format(&format!("B{far}"))?.as_deref(), Some("yyyy-mm-dd"));
+    Ok(())
+}
+
+#[test]
+fn sheet_default_style_applies_to_columns_and_unstyled_cells() -> Result<()> {
+    use crate::format_part::{BorderSide, FontSpec, StyleSpec};
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_sheet_default.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_number_format("C:", "0.00%")?;
+    xl.set_fill("A1", "FFFF00")?;
+    let font = |name: &str| FontSpec { name: name.into(), size: 10.0, ..FontSpec::default() };
+    let mut spec = StyleSpec { font: font("Arial"), ..StyleSpec::default() };
+    // рамка только снизу
+    spec.border.bottom = Some(BorderSide { style: "thin".into(), color: None });
+    xl.set_sheet_default_style(&spec)?;
+
+    assert_eq!(xl.get_cell_style("B2")?, spec);
+    assert_eq!(xl.get_cell_style("Z")?, spec);
+    assert_eq!(xl.get_cell_style("XFD900")?, spec);
+    // свой стиль остаётся у столбца и у ячейки
+    assert_eq!(xl.get_number_format("C")?.as_deref(), Some("0.00%"));
+    assert_eq!(xl.get_cell_style("A1")?.font.name, "Calibri");
+
+    spec.font = font("Verdana");
+    xl.set_sheet_default_style(&spec)?;
+    xl.save(file_name_out)?;
+
+    let mut xl = XlsxEditor::open(file_name_out, "Sheet1")?;
+    assert_eq!(xl.get_cell_style("B2")?, spec);
+    assert_eq!(xl.get_cell_style("Z")?.font.name, "Verdana");
+    assert_eq!(xl.get_number_format("C")?.as_deref(), Some("0.00%"));
+    Ok(())
+}
+
+#[test]
+fn hide_show_and_outline_level_for_columns() -> Result<()> {
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_col_props.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_column_width("E", 20.0)?;
+    xl.hide_columns("D:F")?.set_column_outline_level("H:I", 2)?.set_column_best_fit("E", true)?;
+    assert!(xl.set_column_outline_level("A", 8).is_err());
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<col min="4" max="4" hidden="1"/><col min="5" max="5" width="20" customWidth="1" bestFit="1" hidden="1"/><col min="6" max="6" hidden="1"/><col min="8" max="9" outlineLevel="2"/>"#));
+    assert!(sheet.contains(r#"outlineLevelCol="2""#));
+
+    // показ возвращает столбцы без лишних <col>, ширина E остаётся
+    xl.show_columns("D:F")?.set_column_outline_level("H:I", 0)?;
+    let sheet = String::from_utf8(xl.sheet_xml.clone())?;
+    assert!(sheet.contains(r#"<cols><col min="5" max="5" width="20" customWidth="1" bestFit="1"/></cols>"#));
+    assert!(!sheet.contains("outlineLevelCol"));
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+#[test]
+fn column_style_for_span_with_and_without_stamping() -> Result<()> {
+    use crate::format_part::{FontSpec, StyleSpec};
+    use crate::template_part::CellValue;
+    let file_name = "../test/style_test.xlsx";
+    let file_name_out = "../test/style_test_out_column_style.xlsx";
+
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    xl.set_cell("B2", CellValue::Text("kept".into()))?;
+    xl.set_cell("D2", CellValue::Text("stamped".into()))?;
+    let bold = StyleSpec { font: FontSpec { bold: true, ..FontSpec::default() }, ..StyleSpec::default() };
+    xl.set_column_style("B:C", &bold, false)?.set_column_style("D", &bold, true)?;
+    assert!(xl.set_column_style("1:2", &bold, false).is_err());
+
+    let block = crate::sheet_xml::find_child(&xl.sheet_xml, "cols")?.unwrap();
+    let cols = xl.read_cols_map(block.start, block.end)?;
+    let sid = cols[&2].style.unwrap();
+    // один xf на все столбцы
+    assert_eq!((cols[&3].style, cols[&4].style), (Some(sid), Some(sid)));
+    assert_ne!(xl.cell_style_attr(2, 1), Some(Some(sid)));
+    assert_eq!(xl.cell_style_attr(2, 3), Some(Some(sid)));
+    assert!(xl.get_cell_style("D2")?.font.bold);
+    xl.save(file_name_out)?;
+    Ok(())
+}
+
+/// Случайная правка для property-теста движка вставки.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+enum FuzzOp {
+    Append(Vec<FuzzVal>),
+    Table(Vec<Vec<FuzzVal>>),
+    Set(u32, u32, FuzzVal),
+    Fill(u32, u32, &'static str),
+    NumFmt(u32, u32, &'static str),
+    Border(u32, u32),
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+enum FuzzVal {
+    Num(i32),
+    Text(String),
+}
+
+#[cfg(test)]
+impl std::fmt::Display for FuzzVal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuzzVal::Num(n) => write!(f, "{n}"),
+            FuzzVal::Text(t) => f.write_str(t),
+        }
+    }
+}
+
+#[cfg(test)]
+fn fuzz_val() -> impl proptest::strategy::Strategy<Value = FuzzVal> {
+    use proptest::prelude::*;
+    prop_oneof![
+        (-1000i32..1000).prop_map(FuzzVal::Num),
+        "[a-z]{1,6}( [a-z&<>]{1,4})?".prop_map(FuzzVal::Text),
+        prop::sample::select(vec!["nan", "inf", "-infinity", "1e3", " 7", "0x1F", ""])
+            .prop_map(|t| FuzzVal::Text(t.to_owned())),
+    ]
+}
+
+#[cfg(test)]
+fn fuzz_op(max_row: u32) -> impl proptest::strategy::Strategy<Value = FuzzOp> {
+    use proptest::prelude::*;
+    let cell = (0u32..8, 1..=max_row);
+    prop_oneof![
+        proptest::collection::vec(fuzz_val(), 1..6).prop_map(FuzzOp::Append),
+        proptest::collection::vec(proptest::collection::vec(fuzz_val(), 1..4), 1..4)
+            .prop_map(FuzzOp::Table),
+        (cell.clone(), fuzz_val()).prop_map(|((c, r), v)| FuzzOp::Set(c, r, v)),
+        (cell.clone(), prop::sample::select(vec!["FFFF0000", "FF00FF00", "FFFFFF00"]))
+            .prop_map(|((c, r), rgb)| FuzzOp::Fill(c, r, rgb)),
+        (cell.clone(), prop::sample::select(vec!["0.00", "0%", "dd.mm.yyyy"]))
+            .prop_map(|((c, r), f)| FuzzOp::NumFmt(c, r, f)),
+        cell.prop_map(|(c, r)| FuzzOp::Border(c, r)),
+    ]
+}
+
+/// Применяет правки к листу (существующему или новому), сохраняет, открывает
+/// заново и сверяет с моделью: validate() без замечаний, calamine читает книгу
+/// и видит записанные значения.
+#[cfg(test)]
+fn check_fuzz_ops(new_sheet: bool, ops: &[FuzzOp]) -> Result<()> {
+    use crate::style::col_letter;
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    use std::collections::HashMap;
+
+    let file_name = "../test/style_test.xlsx";
+    let out = "../test/style_test_out_fuzz.xlsx";
+    let mut xl = XlsxEditor::open(file_name, "Sheet1")?;
+    let sheet = if new_sheet {
+        xl.add_worksheet("Fuzz")?.with_worksheet("Fuzz")?;
+        "Fuzz"
+    } else {
+        "Sheet1"
+    };
+    let mut model: HashMap<(u32, u32), FuzzVal> = HashMap::new();
+    let mut last = xl.last_row;
+    for op in ops {
+        match op {
+            FuzzOp::Append(vals) => {
+                xl.append_row(vals.iter().map(|v| v.to_string()))?;
+                last += 1;
+                for (c, v) in vals.iter().enumerate() {
+                    model.insert((c as u32, last), v.clone());
+                }
+            }
+            FuzzOp::Table(rows) => {
+                xl.append_table(rows.iter().map(|r| r.iter().map(|v| v.to_string())))?;
+                for vals in rows {
+                    last += 1;
+                    for (c, v) in vals.iter().enumerate() {
+                        model.insert((c as u32, last), v.clone());
+                    }
+                }
+            }
+            FuzzOp::Set(c, r, v) => {
+                xl.set_cell(&format!("{}{r}", col_letter(*c)), v.to_string())?;
+                model.insert((*c, *r), v.clone());
+                last = last.max(*r);
+            }
+            // стиль на пустой строке создаёт её
+            FuzzOp::Fill(c, r, rgb) => {
+                xl.set_fill(&format!("{}{r}", col_letter(*c)), rgb)?;
+                last = last.max(*r);
+            }
+            FuzzOp::NumFmt(c, r, f) => {
+                xl.set_number_format(&format!("{}{r}", col_letter(*c)), f)?;
+                last = last.max(*r);
+            }
+            FuzzOp::Border(c, r) => {
+                xl.set_border(&format!("{}{r}", col_letter(*c)), "thin")?;
+                last = last.max(*r);
+            }
+        }
+    }
+    anyhow::ensure!(xl.last_row == last, "last_row {} != {last}", xl.last_row);
+    xl.save(out)?;
+
+    let mut back = XlsxEditor::open(out, sheet)?;
+    anyhow::ensure!(back.last_row == last, "reopened last_row {}", back.last_row);
+    let report = back.validate()?;
+    anyhow::ensure!(report.is_ok(), "{report}");
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range(sheet)?;
+    for ((c, r), v) in &model {
+        let got = range.get_value((r - 1, *c));
+        // числом становится всё, что читается как конечное число
+        let num = match v {
+            FuzzVal::Num(n) => Some(*n as f64),
+            FuzzVal::Text(t) => t.parse::<f64>().ok().filter(|f| f.is_finite()),
+        };
+        let same = match (num, got) {
+            (Some(n), Some(Data::Float(f))) => *f == n,
+            (Some(n), Some(Data::Int(i))) => *i as f64 == n,
+            (Some(n), Some(Data::DateTime(d))) => d.as_f64() == n,
+            (None, Some(Data::String(s))) => v.to_string() == *s,
+            (None, None | Some(Data::Empty)) => v.to_string().is_empty(),
+            _ => false,
+        };
+        anyhow::ensure!(same, "{}{r}: expected {v}, calamine read {got:?}", col_letter(*c));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    // число случаев — PROPTEST_CASES (по умолчанию 64; для долгого прогона — тысячи)
+    #![proptest_config(proptest::test_runner::Config::with_cases(
+        std::env::var("PROPTEST_CASES").ok().and_then(|n| n.parse().ok()).unwrap_or(64)
+    ))]
+    #[test]
+    fn random_edits_reopen_cleanly(
+        new_sheet in proptest::bool::ANY,
+        ops in proptest::collection::vec(fuzz_op(40), 1..30),
+    ) {
+        check_fuzz_ops(new_sheet, &ops)
+            .map_err(|e| proptest::test_runner::TestCaseError::fail(format!("{e:#}")))?;
+    }
+}
+
 #[cfg(test)]
 #[cfg(feature = "polars")]
 use polars_core::prelude::*;
@@ -221,3 +3628,585 @@ fn test_write_polars() -> Result<()> {
     app.save(file_name.to_owned() + "_appended.xlsx")?;
     Ok(())
 }
+
+#[test]
+#[cfg(feature = "polars")]
+fn with_polars_chunked_matches_single_pass() -> Result<()> {
+    let src = "../test/test.xlsx";
+    let ids: Vec<i64> = (0..10).collect();
+    let names: Vec<String> = ids.iter().map(|i| format!("row {i}")).collect();
+    let df = DataFrame::new(vec![
+        Column::new("Id".into(), ids),
+        Column::new("Name".into(), names),
+    ])?;
+    let write = |chunk: Option<usize>| -> Result<Vec<u8>> {
+        let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+        match chunk {
+            Some(n) => ed.with_polars_chunked(&df, Some("B5"), n)?,
+            None => ed.with_polars(&df, Some("B5"))?,
+        }
+        Ok(ed.sheet_xml.clone())
+    };
+    let whole = write(None)?;
+    for n in [1, 3, 10, 64] {
+        assert!(write(Some(n))? == whole, "chunk_rows = {n}");
+    }
+    assert!(write(Some(0)).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "polars")]
+fn write_polars_applies_options() -> Result<()> {
+    use crate::polars_part::{HeaderStyle, PolarsWriteOptions};
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Styled")?;
+    let df = DataFrame::new(vec![
+        Column::new("Item".into(), ["apple", "pear"]),
+        Column::new("Price".into(), [1.5, 20.25]),
+    ])?;
+    let opts = PolarsWriteOptions {
+        start_cell: Some("B3".into()),
+        number_formats: [("Price".to_string(), "#,##0.00".to_string())].into(),
+        header_style: Some(HeaderStyle {
+            bold: true,
+            fill: Some("D9E1F2".into()),
+            border: Some("thin".into()),
+        }),
+        autofit: true,
+        freeze_header: true,
+        table: Some("Prices".into()),
+        ..Default::default()
+    };
+    let letters = ed.write_polars(&df, &opts)?;
+    assert_eq!(
+        letters,
+        [("Item".into(), "B".into()), ("Price".into(), "C".into())]
+    );
+    let xml = String::from_utf8(ed.sheet_xml.clone())?;
+    assert!(xml.contains(r#"ySplit="3""#));
+    assert!(xml.contains("<tablePart "));
+    assert!(xml.contains("customWidth=\"1\""));
+    let style_of = |cell: &str| -> Option<String> {
+        let at = xml.find(&format!(r#"<c r="{cell}""#))?;
+        let tag = &xml[at..at + xml[at..].find('>')?];
+        crate::sheet_xml::get_attr(tag.as_bytes(), "s")
+    };
+    assert!(style_of("B3").is_some());
+    assert!(style_of("C4").is_some());
+    assert_eq!(style_of("C4"), style_of("C5"));
+    assert_ne!(style_of("B3"), style_of("C4"));
+
+    let bad = PolarsWriteOptions {
+        number_formats: [("Nope".to_string(), "0".to_string())].into(),
+        ..Default::default()
+    };
+    assert!(ed.write_polars(&df, &bad).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "polars")]
+fn with_polars_writes_temporal_columns_as_dates() -> Result<()> {
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_polars_dates.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Dates")?;
+    let day = Column::new("Day".into(), [19_723, 19_724]).cast(&DataType::Date)?;
+    // 2024-01-01 12:00, 2024-01-02 06:00
+    let at = Column::new("At".into(), [1_704_110_400_000i64, 1_704_175_200_000])
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+    // 1.5 ч, 36 ч
+    let took = Column::new("Took".into(), [5_400_000i64, 129_600_000])
+        .cast(&DataType::Duration(TimeUnit::Milliseconds))?;
+    // 12:00, 06:00
+    let clock = Column::new("Clock".into(), [43_200_000_000_000i64, 21_600_000_000_000])
+        .cast(&DataType::Time)?;
+    let df = DataFrame::new(vec![day, at, took, clock])?;
+    ed.with_polars(&df, None)?;
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range("Dates")?;
+    // calamine видит формат даты и отдаёт серийный номер
+    let cell = |r: u32, c: u32| match range.get_value((r, c)) {
+        Some(Data::DateTime(d)) => Some(d.as_f64()),
+        _ => None,
+    };
+    assert_eq!(cell(1, 0), Some(45_292.0));
+    assert_eq!(cell(2, 0), Some(45_293.0));
+    assert_eq!(cell(1, 1), Some(45_292.5));
+    assert_eq!(cell(2, 1), Some(45_293.25));
+    assert_eq!(cell(1, 2), Some(0.0625));
+    assert_eq!(cell(2, 2), Some(1.5));
+    assert_eq!(cell(1, 3), Some(0.5));
+    assert_eq!(cell(2, 3), Some(0.25));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "polars")]
+fn with_polars_writes_bool_decimal_categorical() -> Result<()> {
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_polars_dtypes.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Types")?;
+    let paid = Column::new("Paid".into(), [true, false]);
+    let amount = Column::new("Amount".into(), ["12345678901234.5678", "-0.05"])
+        .cast(&DataType::Decimal(Some(38), Some(4)))?;
+    let fruit = Column::new("Fruit".into(), ["Apple", "Pear"])
+        .cast(&DataType::Categorical(None, Default::default()))?;
+    let df = DataFrame::new(vec![paid, amount, fruit])?;
+    ed.with_polars(&df, None)?;
+    // все цифры Decimal, без потерь f64
+    let xml = String::from_utf8_lossy(&ed.sheet_xml).into_owned();
+    assert!(xml.contains("<v>12345678901234.5678</v>"), "{xml}");
+    assert!(xml.contains("<v>-0.0500</v>"), "{xml}");
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range("Types")?;
+    let cell = |r: u32, c: u32| range.get_value((r, c)).cloned();
+    assert_eq!(cell(1, 0), Some(Data::Bool(true)));
+    assert_eq!(cell(2, 0), Some(Data::Bool(false)));
+    assert_eq!(cell(2, 1), Some(Data::Float(-0.05)));
+    assert_eq!(cell(1, 2), Some(Data::String("Apple".into())));
+    assert_eq!(cell(2, 2), Some(Data::String("Pear".into())));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "polars")]
+fn read_range_into_polars() -> Result<()> {
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Data")?;
+    let s1 = Column::new("Name".into(), ["Apple", "Pear", "Plum"]);
+    let df = DataFrame::new(vec![s1])?;
+    ed.with_polars(&df, None)?;
+    let cells = [
+        ("B1", "Qty"),
+        ("B2", "3"),
+        ("B4", "7"),
+        ("C1", "Price"),
+        ("C2", "1.5"),
+        ("C3", "2"),
+        ("D1", "Day"),
+        ("D2", "45292"),
+        ("D3", "45293"),
+    ];
+    for (coord, value) in cells {
+        ed.set_cell(coord, value)?;
+    }
+    ed.set_number_format("D2:D3", "yyyy-mm-dd")?;
+
+    let names = |df: &DataFrame| -> Vec<String> {
+        df.get_column_names().iter().map(|n| n.to_string()).collect()
+    };
+    let out = ed.to_polars(None, true)?;
+    assert_eq!(out.shape(), (3, 4));
+    assert_eq!(names(&out), ["Name", "Qty", "Price", "Day"]);
+    assert_eq!(out.column("Name")?.dtype(), &DataType::String);
+    assert_eq!(out.column("Qty")?.dtype(), &DataType::Int64);
+    assert_eq!(out.column("Qty")?.null_count(), 1);
+    assert_eq!(out.column("Price")?.dtype(), &DataType::Float64);
+    assert_eq!(out.column("Day")?.dtype(), &DataType::Date);
+    // 45292 — 2024-01-01
+    assert_eq!(out.column("Day")?.get(0)?, AnyValue::Date(19_723));
+
+    let part = ed.to_polars(Some("A2:B3"), false)?;
+    assert_eq!(names(&part), ["column_1", "column_2"]);
+    assert_eq!(part.height(), 2);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "polars")]
+fn append_polars_maps_columns_by_header() -> Result<()> {
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Log")?;
+    ed.append_row(["Day", "Name", "Qty"])?;
+    ed.append_row(["45292", "Apple", "3"])?;
+    ed.set_number_format("A2", "yyyy-mm-dd")?;
+
+    let day = Column::new("Day".into(), [19_724, 19_725]).cast(&DataType::Date)?;
+    let qty = Column::new("Qty".into(), [5i64, 8]);
+    let name = Column::new("Name".into(), ["Pear", "Plum"]);
+    let note = Column::new("Note".into(), ["new", "new"]);
+    let df = DataFrame::new(vec![qty, name, note, day])?;
+    ed.append_polars(&df, false)?;
+
+    let out = ed.to_polars(None, true)?;
+    let names: Vec<String> = out.get_column_names().iter().map(|n| n.to_string()).collect();
+    assert_eq!(names, ["Day", "Name", "Qty", "Note"]);
+    assert_eq!(out.height(), 3);
+    assert_eq!(out.column("Day")?.dtype(), &DataType::Date);
+    assert_eq!(out.column("Day")?.get(2)?, AnyValue::Date(19_725));
+    assert_eq!(out.column("Qty")?.get(1)?, AnyValue::Int64(5));
+    assert_eq!(out.column("Note")?.null_count(), 1);
+
+    // пустой лист: столбцы подряд от A, с заголовком
+    ed.add_worksheet("Fresh")?;
+    ed.append_polars(&df, true)?;
+    let fresh = ed.to_polars(None, true)?;
+    let names: Vec<String> = fresh.get_column_names().iter().map(|n| n.to_string()).collect();
+    assert_eq!(names, ["Qty", "Name", "Note", "Day"]);
+    assert_eq!(fresh.height(), 2);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "arrow")]
+fn with_arrow_writes_record_batches() -> Result<()> {
+    use arrow_array::{
+        ArrayRef, BooleanArray, Date32Array, Decimal128Array, Float64Array, Int64Array,
+        RecordBatch, StringArray, TimestampMillisecondArray,
+    };
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    use std::sync::Arc;
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_arrow.xlsx";
+    let batch = |ids: Vec<i64>, names: Vec<Option<&str>>| -> Result<RecordBatch> {
+        let n = ids.len();
+        let cols: Vec<(&str, ArrayRef)> = vec![
+            ("Id", Arc::new(Int64Array::from(ids))),
+            ("Name", Arc::new(StringArray::from(names))),
+            ("Score", Arc::new(Float64Array::from(vec![Some(1.5); n]))),
+            ("Paid", Arc::new(BooleanArray::from(vec![true; n]))),
+            ("Day", Arc::new(Date32Array::from(vec![19_723; n]))), // 2024-01-01
+            // 2024-01-01 12:00
+            ("At", Arc::new(TimestampMillisecondArray::from(vec![1_704_110_400_000; n]))),
+            (
+                "Amount",
+                Arc::new(Decimal128Array::from(vec![-5; n]).with_precision_and_scale(10, 2)?),
+            ),
+        ];
+        Ok(RecordBatch::try_from_iter(cols)?)
+    };
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Arrow")?;
+    ed.set_cell("A3", "kept")?;
+    ed.set_cell("C3", "overwritten")?;
+    let batches = vec![
+        batch(vec![1, 2], vec![Some("a"), None])?,
+        batch(vec![3], vec![Some("c")])?,
+    ];
+    ed.with_arrow(batches, Some("B2"))?;
+    assert_eq!(ed.last_row, 5);
+    let xml = String::from_utf8_lossy(&ed.sheet_xml).into_owned();
+    assert!(xml.contains("<v>-0.05</v>"), "{xml}");
+    assert!(!xml.contains(r#"r="C4""#), "null is not written: {xml}");
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range("Arrow")?;
+    let cell = |r: u32, c: u32| range.get_value((r, c)).cloned();
+    assert_eq!(cell(1, 1), Some(Data::String("Id".into())));
+    assert_eq!(cell(1, 7), Some(Data::String("Amount".into())));
+    assert_eq!(cell(2, 0), Some(Data::String("kept".into())));
+    assert_eq!(cell(2, 1), Some(Data::Float(1.0)));
+    assert_eq!(cell(2, 2), Some(Data::String("a".into())));
+    assert_eq!(cell(4, 1), Some(Data::Float(3.0)));
+    assert_eq!(cell(4, 3), Some(Data::Float(1.5)));
+    assert_eq!(cell(4, 4), Some(Data::Bool(true)));
+    let serial = |r, c| match cell(r, c) {
+        Some(Data::DateTime(d)) => d.as_f64(),
+        other => panic!("{other:?}"),
+    };
+    assert_eq!(serial(2, 5), 45_292.0);
+    assert_eq!(serial(3, 6), 45_292.5);
+
+    // без батчей лист не меняется
+    let before = ed.sheet_xml.clone();
+    ed.with_arrow(Vec::new(), None)?;
+    assert!(*ed.sheet_xml == before);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "csv")]
+fn append_csv_infers_types() -> Result<()> {
+    use crate::csv_part::{CsvOptions, CsvType};
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_csv.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Csv")?;
+    ed.append_row(["Code", "Name", "Qty", "Paid", "Day"])?;
+    let csv = "code,name,qty,paid,day\n\
+               007,\"Smith, J\",3,true,2024-01-01\n\
+               010,=1+1,,FALSE,2024-01-01 12:00\n";
+    let opts = CsvOptions {
+        schema: vec![CsvType::Text],
+        ..Default::default()
+    };
+    assert_eq!(ed.append_csv(csv.as_bytes(), &opts)?, 2);
+    assert_eq!(ed.last_row, 3);
+    let xml = String::from_utf8_lossy(&ed.sheet_xml).into_owned();
+    assert!(!xml.contains("<f>"), "{xml}");
+    assert!(!xml.contains(r#"r="C3""#), "{xml}");
+
+    // значение не по схеме: ошибка с номером строки, лист не меняется
+    let before = ed.sheet_xml.clone();
+    let bad = CsvOptions {
+        delimiter: b'\t',
+        has_header: false,
+        schema: vec![CsvType::Number],
+        ..Default::default()
+    };
+    let err = ed.append_csv("1\n2\nx\n".as_bytes(), &bad).unwrap_err();
+    assert!(format!("{err:#}").contains("line 3, column 1"), "{err:#}");
+    assert!(*ed.sheet_xml == before);
+    assert_eq!(ed.last_row, 3);
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range("Csv")?;
+    let cell = |r: u32, c: u32| range.get_value((r, c)).cloned();
+    assert_eq!(cell(1, 0), Some(Data::String("007".into())));
+    assert_eq!(cell(1, 1), Some(Data::String("Smith, J".into())));
+    assert_eq!(cell(1, 2), Some(Data::Float(3.0)));
+    assert_eq!(cell(1, 3), Some(Data::Bool(true)));
+    assert_eq!(cell(2, 1), Some(Data::String("=1+1".into())));
+    assert_eq!(cell(2, 3), Some(Data::Bool(false)));
+    let serial = |r, c| match cell(r, c) {
+        Some(Data::DateTime(d)) => d.as_f64(),
+        other => panic!("{other:?}"),
+    };
+    assert_eq!(serial(1, 4), 45_292.0);
+    assert_eq!(serial(2, 4), 45_292.5);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn append_serialize_struct_rows() -> Result<()> {
+    use crate::serde_part::{XlsxColumn, XlsxRow};
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Open,
+        Closed,
+    }
+    #[derive(Serialize)]
+    struct Order {
+        id: u32,
+        customer: String,
+        total: f64,
+        paid: bool,
+        note: Option<String>,
+        status: Status,
+    }
+    impl XlsxRow for Order {
+        fn columns() -> Vec<XlsxColumn> {
+            vec![
+                XlsxColumn {
+                    field: "customer".into(),
+                    header: Some("Customer".into()),
+                    ..Default::default()
+                },
+                XlsxColumn {
+                    field: "total".into(),
+                    header: Some("Total".into()),
+                    number_format: Some("#,##0.00".into()),
+                },
+            ]
+        }
+    }
+    let orders = [
+        Order {
+            id: 1,
+            customer: "Acme".into(),
+            total: 1250.5,
+            paid: true,
+            note: None,
+            status: Status::Open,
+        },
+        Order {
+            id: 2,
+            customer: "Globex".into(),
+            total: 99.0,
+            paid: false,
+            note: Some("rush".into()),
+            status: Status::Closed,
+        },
+    ];
+
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_serde.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Orders")?;
+    ed.append_serialize(&orders, true)?;
+    assert_eq!(ed.last_row, 3);
+
+    // столбцы и форматы из XlsxRow
+    ed.add_worksheet("Totals")?;
+    ed.append_xlsx_rows(&orders, true)?;
+    let xml = String::from_utf8_lossy(&ed.sheet_xml).into_owned();
+    assert!(!xml.contains(r#"r="C2""#), "{xml}");
+    assert!(xml.contains(r#"<c r="B2" s=""#), "{xml}");
+
+    // словари: ключи в порядке первой встречи
+    let maps = [
+        BTreeMap::from([("a", 1)]),
+        BTreeMap::from([("a", 2), ("b", 3)]),
+    ];
+    ed.add_worksheet("Maps")?;
+    ed.append_serialize(&maps, true)?;
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let orders = wb.worksheet_range("Orders")?;
+    let row = |r: u32| (0..6).map(|c| orders.get_value((r, c)).cloned()).collect::<Vec<_>>();
+    let text = |s: &str| Some(Data::String(s.into()));
+    assert_eq!(
+        row(0),
+        ["id", "customer", "total", "paid", "note", "status"].map(text)
+    );
+    assert_eq!(
+        row(1),
+        [
+            Some(Data::Float(1.0)),
+            text("Acme"),
+            Some(Data::Float(1250.5)),
+            Some(Data::Bool(true)),
+            Some(Data::Empty),
+            text("open"),
+        ]
+    );
+    assert_eq!(row(2)[4], text("rush"));
+    let totals = wb.worksheet_range("Totals")?;
+    assert_eq!(totals.get_value((0, 0)), text("Customer").as_ref());
+    assert_eq!(totals.get_value((0, 1)), text("Total").as_ref());
+    assert_eq!(totals.get_value((2, 1)), Some(&Data::Float(99.0)));
+    let maps = wb.worksheet_range("Maps")?;
+    assert_eq!(maps.get_value((0, 1)), text("b").as_ref());
+    assert_eq!(maps.get_value((1, 1)), Some(&Data::Empty));
+    assert_eq!(maps.get_value((2, 1)), Some(&Data::Float(3.0)));
+
+    #[derive(Serialize)]
+    struct Nested {
+        items: Vec<u32>,
+    }
+    let err = ed.append_serialize(&[Nested { items: vec![1] }], false).unwrap_err();
+    assert!(err.to_string().contains("items"), "{err}");
+    assert!(ed.append_serialize(&[1, 2], false).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn derive_xlsx_row_columns() -> Result<()> {
+    use crate::read_part::ReadValue;
+    use crate::serde_part::{XlsxColumn, XlsxRow};
+    use serde::Serialize;
+
+    #[derive(Serialize, XlsxRow)]
+    #[serde(rename_all = "camelCase")]
+    struct Invoice {
+        invoice_id: u32,
+        #[xlsx(header = "Client")]
+        #[serde(rename = "customer")]
+        customer_name: String,
+        #[xlsx(header = "Sum", number_format = "#,##0.00")]
+        total: f64,
+        #[xlsx(skip)]
+        internal: bool,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        cache: u8,
+    }
+    let col = |field: &str, header: Option<&str>, number_format: Option<&str>| XlsxColumn {
+        field: field.into(),
+        header: header.map(Into::into),
+        number_format: number_format.map(Into::into),
+    };
+    assert_eq!(
+        Invoice::columns(),
+        [
+            col("invoiceId", None, None),
+            col("customer", Some("Client"), None),
+            col("total", Some("Sum"), Some("#,##0.00")),
+        ]
+    );
+
+    let src = "../test/test.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Invoices")?;
+    let rows = [Invoice {
+        invoice_id: 7,
+        customer_name: "Acme".into(),
+        total: 10.5,
+        internal: true,
+        cache: 0,
+    }];
+    ed.append_xlsx_rows(&rows, true)?;
+    assert_eq!(ed.get_cell("A1")?, ReadValue::Text("invoiceId".into()));
+    assert_eq!(ed.get_cell("B2")?, ReadValue::Text("Acme".into()));
+    assert_eq!(ed.get_cell("C2")?, ReadValue::Number(10.5));
+    assert_eq!(ed.get_cell("D2")?, ReadValue::Empty);
+
+    // столбец с полем, которого нет в записи
+    #[derive(Serialize)]
+    struct Short {
+        a: u32,
+    }
+    impl XlsxRow for Short {
+        fn columns() -> Vec<XlsxColumn> {
+            vec![XlsxColumn {
+                field: "b".into(),
+                ..Default::default()
+            }]
+        }
+    }
+    let last = ed.last_row;
+    let err = ed.append_xlsx_rows(&[Short { a: 1 }], true).unwrap_err();
+    assert!(matches!(err, XlsxError::Other(_)), "{err:?}");
+    assert!(err.to_string().contains("no field b in row 0"), "{err}");
+    assert_eq!(ed.last_row, last);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "ndarray")]
+fn write_matrix_numbers() -> Result<()> {
+    use calamine::{Data, Reader as _, Xlsx, open_workbook};
+    use ndarray::{Array2, array, s};
+    let src = "../test/test.xlsx";
+    let out = "../test/test_out_matrix.xlsx";
+    let mut ed = XlsxEditor::open(src, &scan(src)?[0])?;
+    ed.add_worksheet("Matrix")?;
+    ed.set_cell("A3", "kept")?;
+    ed.set_cell("C3", "replaced")?;
+    // поверх существующих строк
+    ed.write_matrix("B2", &array![[1.0, 2.5], [f64::NAN, -4.0], [1e-3, 6.0]])?;
+    assert_eq!(ed.last_row, 4);
+    // ниже данных: срез матрицы, строки дописываются в конец
+    let big = Array2::from_shape_fn((4, 3), |(i, j)| (i * 3 + j) as f64);
+    ed.write_matrix("A6", &big.slice(s![1.., ..2]))?;
+    assert_eq!(ed.last_row, 8);
+    assert!(ed.write_matrix("XFD1", &array![[1.0, 2.0]]).is_err());
+    ed.save(out)?;
+
+    let mut wb: Xlsx<_> = open_workbook(out)?;
+    let range = wb.worksheet_range("Matrix")?;
+    let cell = |r: u32, c: u32| range.get_value((r, c)).cloned();
+    assert_eq!(cell(1, 1), Some(Data::Float(1.0)));
+    assert_eq!(cell(1, 2), Some(Data::Float(2.5)));
+    assert_eq!(cell(2, 0), Some(Data::String("kept".into())));
+    assert_eq!(cell(2, 1), Some(Data::Empty));
+    assert_eq!(cell(2, 2), Some(Data::Float(-4.0)));
+    assert_eq!(cell(3, 1), Some(Data::Float(1e-3)));
+    assert_eq!(cell(5, 0), Some(Data::Float(3.0)));
+    assert_eq!(cell(7, 1), Some(Data::Float(10.0)));
+    assert_eq!(cell(7, 2), Some(Data::Empty));
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! theme_part.rs – цвета темы книги.
+//!
+//! Корпоративные шаблоны красят ячейки не RGB, а цветами темы
+//! (`<color theme="4" tint="-0.25"/>`): сменили тему — сменились и цвета.
+//! [`Color`] — цвет для заливки, шрифта и рамки (RGB или цвет темы с
+//! оттенком), а [`XlsxEditor::get_theme_colors`] отдаёт палитру темы
+//! (`xl/theme/theme1.xml`) в порядке индексов `theme`.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail, next_event};
+use anyhow::{Context, Result};
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+use std::{fmt, str::FromStr};
+
+/// Цвета `<a:clrScheme>` в порядке индексов `theme="…"` стилей. Первые
+/// две пары в файле идут наоборот (`dk1`, `lt1`, `dk2`, `lt2`), а в
+/// индексах — `lt1` = 0, `dk1` = 1, `lt2` = 2, `dk2` = 3.
+const SCHEME: [&str; 12] = [
+    "lt1", "dk1", "lt2", "dk2", "accent1", "accent2", "accent3", "accent4", "accent5", "accent6",
+    "hlink", "folHlink",
+];
+
+/// Цвет заливки, шрифта или рамки.
+///
+/// Из строки: `"FF0000"` / `"#FF0000"` / ARGB — RGB как есть, `"theme:4"` —
+/// цвет темы №4 (`accent1`), `"theme:4:-0.25"` — он же, затемнённый на 25 %
+/// (`tint` от -1 до 1, плюс — светлее).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Color {
+    Rgb(String),
+    Theme { index: u32, tint: f64 },
+}
+
+impl Color {
+    pub fn theme(index: u32, tint: f64) -> Self {
+        Color::Theme { index, tint }
+    }
+
+    /// Атрибуты `<color …/>` / `<fgColor …/>`: `rgb="…"` или
+    /// `theme="4" tint="-0.25"` (нулевой `tint` не пишется).
+    pub(crate) fn attrs(&self) -> String {
+        match self {
+            Color::Rgb(rgb) => format!(r#"rgb="{rgb}""#),
+            Color::Theme { index, tint } if *tint == 0.0 => format!(r#"theme="{index}""#),
+            Color::Theme { index, tint } => format!(r#"theme="{index}" tint="{tint}""#),
+        }
+    }
+
+    /// Цвет из атрибутов `rgb` / `theme` / `tint` элемента стиля;
+    /// `indexed` и `auto` не разбираются.
+    pub(crate) fn from_attrs(e: &BytesStart) -> Option<Self> {
+        let (mut rgb, mut theme, mut tint) = (None, None, 0.0);
+        for a in e.attributes().with_checks(false).flatten() {
+            let v = String::from_utf8_lossy(&a.value);
+            match a.key.as_ref() {
+                b"rgb" => rgb = Some(v.to_ascii_uppercase()),
+                b"theme" => theme = v.parse().ok(),
+                b"tint" => tint = v.parse().unwrap_or(0.0),
+                _ => {}
+            }
+        }
+        match (rgb, theme) {
+            (Some(rgb), _) => Some(Color::Rgb(rgb)),
+            (None, Some(index)) => Some(Color::Theme { index, tint }),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Каноничная запись (та же, что принимает `FromStr`) — ключ в индексе стилей.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Color::Rgb(rgb) => f.write_str(rgb),
+            Color::Theme { index, tint } if *tint == 0.0 => write!(f, "theme:{index}"),
+            Color::Theme { index, tint } => write!(f, "theme:{index}:{tint}"),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = XlsxError;
+    fn from_str(s: &str) -> Result<Self, XlsxError> {
+        let Some(rest) = s.strip_prefix("theme:") else {
+            let rgb = s.trim_start_matches('#');
+            if !matches!(rgb.len(), 6 | 8) || !rgb.bytes().all(|b| b.is_ascii_hexdigit()) {
+                bail!("invalid color: {s}");
+            }
+            return Ok(Color::Rgb(rgb.to_ascii_uppercase()));
+        };
+        let (index, tint) = rest.split_once(':').unwrap_or((rest, "0"));
+        let index: u32 = index
+            .parse()
+            .with_context(|| format!("invalid theme color index: {s}"))?;
+        let tint: f64 = tint
+            .parse()
+            .with_context(|| format!("invalid theme color tint: {s}"))?;
+        if !(-1.0..=1.0).contains(&tint) {
+            bail!("theme color tint must be within -1..=1, got {tint}");
+        }
+        Ok(Color::Theme { index, tint })
+    }
+}
+
+/// Цвет палитры темы: `name` — имя слота (`"accent1"`), `rgb` — `RRGGBB`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeColor {
+    pub name: String,
+    pub rgb: String,
+}
+
+impl XlsxEditor {
+    /// Палитра темы книги: элемент `i` — цвет `Color::theme(i, …)`
+    /// (`lt1`, `dk1`, `lt2`, `dk2`, `accent1`…`accent6`, `hlink`, `folHlink`).
+    /// Системные цвета (`windowText`) берутся по `lastClr`. Книга без темы —
+    /// пустой список.
+    pub fn get_theme_colors(&mut self) -> Result<Vec<ThemeColor>, XlsxError> {
+        let Some(path) = self
+            .rels_of("xl/workbook.xml")?
+            .of_kind("theme")
+            .next()
+            .map(|r| r.target.clone())
+        else {
+            return Ok(Vec::new());
+        };
+        let Some(xml) = self.read_part(&path)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut found: Vec<(usize, String)> = Vec::new();
+        let mut rdr = Reader::from_reader(xml.as_slice());
+        let mut in_scheme = false;
+        let mut slot = None;
+        loop {
+            match next_event(&mut rdr, &path)? {
+                Event::Start(e) if e.local_name().as_ref() == b"clrScheme" => in_scheme = true,
+                Event::End(e) if e.local_name().as_ref() == b"clrScheme" => break,
+                Event::Start(e) | Event::Empty(e) if in_scheme => {
+                    let key: &[u8] = match e.local_name().as_ref() {
+                        b"srgbClr" => b"val",
+                        b"sysClr" => b"lastClr",
+                        name => {
+                            if let Some(i) = SCHEME.iter().position(|s| s.as_bytes() == name) {
+                                slot = Some(i);
+                            }
+                            continue;
+                        }
+                    };
+                    let rgb = e
+                        .attributes()
+                        .with_checks(false)
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == key)
+                        .map(|a| String::from_utf8_lossy(&a.value).to_ascii_uppercase());
+                    if let (Some(i), Some(rgb)) = (slot.take(), rgb) {
+                        found.push((i, rgb));
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        found.sort_by_key(|&(i, _)| i);
+        Ok(found
+            .into_iter()
+            .map(|(i, rgb)| ThemeColor {
+                name: SCHEME[i].to_owned(),
+                rgb,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,150 @@
+//! transaction_part.rs – откат правок книги в памяти.
+//!
+//! Цепочка правок может упасть на середине: стиль уже лёг, а дозапись
+//! вернула ошибку. [`XlsxEditor::begin`] запоминает состояние книги, и
+//! [`XlsxEditor::rollback`] возвращает его целиком, а
+//! [`XlsxEditor::transaction`] делает то же вокруг замыкания. Снимок дешёвый:
+//! XML листа и частей лежит в `Arc` и копируется только при первой правке
+//! после снимка, так что нетронутые части не копируются вовсе.
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::layout_part::XmlLayout;
+use crate::lazy_part::LazyPart;
+use crate::sheet_buf::SheetBuf;
+use crate::table_part::PendingGrowth;
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Состояние книги на момент [`XlsxEditor::begin`]. Кэши (исходные части,
+/// sharedStrings, индекс стилей) не запоминаются: они либо совпадают с
+/// архивом, либо сбрасываются при откате.
+pub(crate) struct Snapshot {
+    sheet_path: String,
+    sheet_xml: SheetBuf,
+    sheet_prefix: String,
+    last_row: u32,
+    styles_xml: LazyPart,
+    workbook_xml: LazyPart,
+    rels_xml: LazyPart,
+    new_files: Vec<(String, Arc<Vec<u8>>)>,
+    removed_parts: HashSet<String>,
+    appended_rows: HashMap<String, u32>,
+    pending_growth: Option<PendingGrowth>,
+    recalc_on_change: bool,
+    row_spans: bool,
+    inherit_col_styles: bool,
+    preserve_zip_metadata: bool,
+    compression_threads: usize,
+    allow_macro_removal: bool,
+    stamp_modified: bool,
+    modified_by: Option<String>,
+    xml_layout: XmlLayout,
+}
+
+impl XlsxEditor {
+    /// Начинает транзакцию: всё, что изменится дальше, можно отменить
+    /// [`Self::rollback`] или оставить [`Self::commit`]. Транзакции
+    /// вкладываются: `rollback` отменяет правки с последнего `begin`.
+    pub fn begin(&mut self) -> &mut Self {
+        let snapshot = Snapshot {
+            sheet_path: self.sheet_path.clone(),
+            sheet_xml: self.sheet_xml.snapshot(),
+            sheet_prefix: self.sheet_prefix.clone(),
+            last_row: self.last_row,
+            styles_xml: self.styles_xml.clone(),
+            workbook_xml: self.workbook_xml.clone(),
+            rels_xml: self.rels_xml.clone(),
+            new_files: self.new_files.clone(),
+            removed_parts: self.removed_parts.clone(),
+            appended_rows: self.appended_rows.clone(),
+            pending_growth: self.pending_growth,
+            recalc_on_change: self.recalc_on_change,
+            row_spans: self.row_spans,
+            inherit_col_styles: self.inherit_col_styles,
+            preserve_zip_metadata: self.preserve_zip_metadata,
+            compression_threads: self.compression_threads,
+            allow_macro_removal: self.allow_macro_removal,
+            stamp_modified: self.stamp_modified,
+            modified_by: self.modified_by.clone(),
+            xml_layout: self.xml_layout,
+        };
+        self.savepoints.push(snapshot);
+        self
+    }
+
+    /// Оставляет правки последней транзакции; снимок отпускается. Без
+    /// открытой транзакции — ошибка.
+    pub fn commit(&mut self) -> Result<&mut Self, XlsxError> {
+        self.savepoints.pop().context("no transaction to commit")?;
+        Ok(self)
+    }
+
+    /// Возвращает книгу (листы, стили, части, настройки сохранения) к
+    /// последнему [`Self::begin`]. Без открытой транзакции — ошибка.
+    pub fn rollback(&mut self) -> Result<&mut Self, XlsxError> {
+        let s = self
+            .savepoints
+            .pop()
+            .context("no transaction to roll back")?;
+        self.sheet_path = s.sheet_path;
+        self.sheet_xml = s.sheet_xml;
+        self.sheet_prefix = s.sheet_prefix;
+        self.last_row = s.last_row;
+        self.styles_xml = s.styles_xml;
+        self.workbook_xml = s.workbook_xml;
+        self.rels_xml = s.rels_xml;
+        self.new_files = s.new_files;
+        self.removed_parts = s.removed_parts;
+        self.appended_rows = s.appended_rows;
+        self.pending_growth = s.pending_growth;
+        self.recalc_on_change = s.recalc_on_change;
+        self.row_spans = s.row_spans;
+        self.inherit_col_styles = s.inherit_col_styles;
+        self.preserve_zip_metadata = s.preserve_zip_metadata;
+        self.compression_threads = s.compression_threads;
+        self.allow_macro_removal = s.allow_macro_removal;
+        self.stamp_modified = s.stamp_modified;
+        self.modified_by = s.modified_by;
+        self.xml_layout = s.xml_layout;
+        // собраны по отменённым правкам — прочитаются заново
+        self.styles_index = None;
+        self.shared_strings = None;
+        Ok(self)
+    }
+
+    /// Выполняет `f` в транзакции: ошибка `f` откатывает все её правки и
+    /// возвращается как есть, успех их оставляет.
+    ///
+    /// ```no_run
+    /// # use rust_core::XlsxEditor;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut xl = XlsxEditor::open("in.xlsx", "Sheet1")?;
+    /// let res = xl.transaction(|xl| {
+    ///     xl.set_fill("A1:C1", "FFFF00")?;
+    ///     xl.append_row(["a", "b", "c"])
+    /// });
+    /// // при ошибке заливки в книге тоже нет
+    /// # Ok(()) }
+    /// ```
+    pub fn transaction<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, XlsxError>,
+    ) -> Result<T, XlsxError> {
+        self.begin();
+        let depth = self.savepoints.len();
+        let res = f(self);
+        // `f` могла сама закрыть свою транзакцию или оставить вложенные открытыми
+        while self.savepoints.len() > depth {
+            self.savepoints.pop();
+        }
+        if self.savepoints.len() == depth {
+            match &res {
+                Ok(_) => self.commit()?,
+                Err(_) => self.rollback()?,
+            };
+        }
+        res
+    }
+}
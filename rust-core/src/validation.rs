@@ -0,0 +1,250 @@
+//! validation.rs – проверка данных (`<dataValidations>`): выпадающие списки,
+//! числовые/датовые ограничения, длина текста и произвольные формулы.
+
+use crate::XlsxEditor;
+use crate::conditional::CfOperator;
+use crate::error::{XlsxError, bail};
+use crate::files_part::xml_escape;
+use crate::sheet_xml::{find_child, find_elem, get_attr, insert_child, set_attr, tag_prefix};
+use crate::view_part::normalize_sqref;
+use anyhow::Result;
+
+/// Операторы сравнения те же, что и у условного форматирования.
+pub type ValidationOperator = CfOperator;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationRule {
+    /// Список значений прямо в правиле (вместе с запятыми не длиннее 255 символов).
+    List(Vec<String>),
+    /// Список из диапазона: `"$A$1:$A$10"`, `"Lists!$A$1:$A$5"` или имя.
+    ListRange(String),
+    /// Целое число; `value2` нужен для `Between`/`NotBetween`.
+    Whole {
+        operator: ValidationOperator,
+        value1: String,
+        value2: Option<String>,
+    },
+    Decimal {
+        operator: ValidationOperator,
+        value1: String,
+        value2: Option<String>,
+    },
+    /// Дата: `YYYY-MM-DD`, серийный номер Excel или формула.
+    Date {
+        operator: ValidationOperator,
+        value1: String,
+        value2: Option<String>,
+    },
+    TextLength {
+        operator: ValidationOperator,
+        value1: String,
+        value2: Option<String>,
+    },
+    /// Произвольная формула, истинная для допустимых значений.
+    Custom(String),
+}
+
+/// Вид сообщения об ошибке: `Stop` запрещает ввод, остальные лишь предупреждают.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorStyle {
+    #[default]
+    Stop,
+    Warning,
+    Information,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationSpec {
+    pub rule: ValidationRule,
+    pub allow_blank: bool,
+    /// Показывать стрелку выпадающего списка (только для списков).
+    pub in_cell_dropdown: bool,
+    pub error_style: ErrorStyle,
+    pub error_title: Option<String>,
+    pub error_message: Option<String>,
+    pub prompt_title: Option<String>,
+    pub prompt_message: Option<String>,
+}
+
+impl ValidationSpec {
+    pub fn new(rule: ValidationRule) -> Self {
+        Self {
+            rule,
+            allow_blank: true,
+            in_cell_dropdown: true,
+            error_style: ErrorStyle::Stop,
+            error_title: None,
+            error_message: None,
+            prompt_title: None,
+            prompt_message: None,
+        }
+    }
+}
+
+impl XlsxEditor {
+    /// Ставит проверку данных на `range` (`"A2:A100"`, можно несколько через пробел).
+    /// Правило с точно таким же `sqref` заменяется.
+    pub fn set_data_validation(
+        &mut self,
+        range: &str,
+        spec: ValidationSpec,
+    ) -> Result<&mut Self, XlsxError> {
+        let sqref = normalize_sqref(range)?;
+        let elem = validation_xml(&sqref, &spec)?;
+
+        let Some(block) = find_child(&self.sheet_xml, "dataValidations")? else {
+            let xml = format!(r#"<dataValidations count="1">{elem}</dataValidations>"#);
+            insert_child(&mut self.sheet_xml, "dataValidations", xml.as_bytes())?;
+            return Ok(self);
+        };
+
+        let p = tag_prefix(&self.sheet_xml[block.clone()]);
+        let elem = if p.is_empty() {
+            elem
+        } else {
+            elem.replace("<dataValidation ", &format!("<{p}dataValidation "))
+                .replace("</dataValidation>", &format!("</{p}dataValidation>"))
+                .replace("<formula", &format!("<{p}formula"))
+                .replace("</formula", &format!("</{p}formula"))
+        };
+        let mut inner = self.sheet_xml[block.clone()].to_vec();
+        let tag_end = inner.iter().position(|&b| b == b'>').unwrap_or(0);
+        let self_closing = inner[tag_end - 1] == b'/';
+        let mut tag = if self_closing {
+            let mut t = inner[..tag_end - 1].to_vec();
+            t.push(b'>');
+            t
+        } else {
+            inner[..=tag_end].to_vec()
+        };
+        let close = format!("</{p}dataValidations>");
+        let mut body = if self_closing {
+            Vec::new()
+        } else {
+            inner[tag_end + 1..inner.len() - close.len()].to_vec()
+        };
+
+        // то же sqref — заменяем правило
+        let qname = format!("{p}dataValidation");
+        let mut from = 0;
+        let mut replaced = false;
+        while let Some(r) = find_elem(&body, &qname, from) {
+            let end = r.start + body[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+            if get_attr(&body[r.start..=end], "sqref").as_deref() == Some(sqref.as_str()) {
+                body.splice(r, elem.bytes());
+                replaced = true;
+                break;
+            }
+            from = r.end;
+        }
+        if !replaced {
+            body.extend_from_slice(elem.as_bytes());
+        }
+
+        let mut count = 0;
+        let mut from = 0;
+        while let Some(r) = find_elem(&body, &qname, from) {
+            count += 1;
+            from = r.end;
+        }
+        set_attr(&mut tag, "count", Some(&count.to_string()));
+        inner = tag;
+        inner.extend_from_slice(&body);
+        inner.extend_from_slice(close.as_bytes());
+        self.sheet_xml.splice(block, inner);
+        Ok(self)
+    }
+}
+
+fn validation_xml(sqref: &str, spec: &ValidationSpec) -> Result<String> {
+    let (typ, operator, f1, f2) = match &spec.rule {
+        ValidationRule::List(items) => {
+            if items.iter().any(|i| i.contains(',')) {
+                bail!("inline list items must not contain commas; use ListRange");
+            }
+            let joined = items.join(",");
+            if joined.chars().count() > 255 {
+                bail!("inline list is longer than 255 characters; use ListRange");
+            }
+            ("list", None, format!("\"{}\"", joined.replace('"', "\"\"")), None)
+        }
+        ValidationRule::ListRange(r) => ("list", None, strip_eq(r), None),
+        ValidationRule::Whole {
+            operator,
+            value1,
+            value2,
+        } => ("whole", Some(*operator), strip_eq(value1), value2.as_deref().map(strip_eq)),
+        ValidationRule::Decimal {
+            operator,
+            value1,
+            value2,
+        } => ("decimal", Some(*operator), strip_eq(value1), value2.as_deref().map(strip_eq)),
+        ValidationRule::Date {
+            operator,
+            value1,
+            value2,
+        } => ("date", Some(*operator), date_formula(value1), value2.as_deref().map(date_formula)),
+        ValidationRule::TextLength {
+            operator,
+            value1,
+            value2,
+        } => ("textLength", Some(*operator), strip_eq(value1), value2.as_deref().map(strip_eq)),
+        ValidationRule::Custom(f) => ("custom", None, strip_eq(f), None),
+    };
+    if let Some(op) = operator {
+        let two = matches!(op, CfOperator::Between | CfOperator::NotBetween);
+        if two != f2.is_some() {
+            bail!("operator {op} needs {} value(s)", if two { 2 } else { 1 });
+        }
+    }
+
+    let mut xml = format!(r#"<dataValidation type="{typ}""#);
+    match spec.error_style {
+        ErrorStyle::Stop => {}
+        ErrorStyle::Warning => xml.push_str(r#" errorStyle="warning""#),
+        ErrorStyle::Information => xml.push_str(r#" errorStyle="information""#),
+    }
+    // between — значение по умолчанию, атрибут не пишем
+    if let Some(op) = operator.filter(|o| *o != CfOperator::Between) {
+        xml.push_str(&format!(r#" operator="{op}""#));
+    }
+    if spec.allow_blank {
+        xml.push_str(r#" allowBlank="1""#);
+    }
+    // showDropDown="1" у Excel означает «скрыть стрелку»
+    if typ == "list" && !spec.in_cell_dropdown {
+        xml.push_str(r#" showDropDown="1""#);
+    }
+    xml.push_str(r#" showInputMessage="1" showErrorMessage="1""#);
+    for (attr, val, max) in [
+        ("errorTitle", &spec.error_title, 32),
+        ("error", &spec.error_message, 255),
+        ("promptTitle", &spec.prompt_title, 32),
+        ("prompt", &spec.prompt_message, 255),
+    ] {
+        if let Some(v) = val {
+            if v.chars().count() > max {
+                bail!("{attr} is longer than {max} characters");
+            }
+            xml.push_str(&format!(r#" {attr}="{}""#, xml_escape(v)));
+        }
+    }
+    xml.push_str(&format!(r#" sqref="{sqref}"><formula1>{}</formula1>"#, xml_escape(&f1)));
+    if let Some(f2) = f2 {
+        xml.push_str(&format!("<formula2>{}</formula2>", xml_escape(&f2)));
+    }
+    xml.push_str("</dataValidation>");
+    Ok(xml)
+}
+
+fn strip_eq(f: &str) -> String {
+    f.strip_prefix('=').unwrap_or(f).to_owned()
+}
+
+/// `2024-03-01` → `DATE(2024,3,1)`; прочее — как формула.
+fn date_formula(v: &str) -> String {
+    match v.split('-').map(str::parse::<u32>).collect::<Result<Vec<_>, _>>() {
+        Ok(n) if n.len() == 3 => format!("DATE({},{},{})", n[0], n[1], n[2]),
+        _ => strip_eq(v),
+    }
+}
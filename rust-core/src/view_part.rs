@@ -0,0 +1,278 @@
+//! view_part.rs – `<sheetViews>`: закрепление областей, разделение окна,
+//! сетка, масштаб, направление листа и выделение.
+
+use crate::XlsxEditor;
+use crate::error::{XlsxError, bail};
+use crate::sheet_xml::{
+    find_child, find_elem, get_attr, insert_child, remove_elems, set_attr, tag_prefix,
+};
+use crate::style::{col_letter, parse_cell};
+use anyhow::{Context, Result};
+
+/// Параметры отображения листа для [`XlsxEditor::set_sheet_view`].
+/// `None` — оставить как есть в файле.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SheetViewOptions {
+    pub show_grid_lines: Option<bool>,
+    /// Масштаб в процентах, 10..=400.
+    pub zoom_scale: Option<u16>,
+    pub right_to_left: Option<bool>,
+    /// Активная ячейка (`"C5"`). Без `selection` выделяется только она.
+    pub active_cell: Option<String>,
+    /// Выделение в формате sqref (`"A1:C3"`, `"A1 C3:D4"`).
+    /// Без `active_cell` активной становится первая ячейка выделения.
+    pub selection: Option<String>,
+}
+
+impl XlsxEditor {
+    /// Меняет отображение текущего листа: сетку, масштаб, направление
+    /// справа налево и активную ячейку/выделение. Если `<sheetViews>` нет,
+    /// он создаётся. При закреплённых областях выделение пишется в активную панель.
+    pub fn set_sheet_view(&mut self, opts: SheetViewOptions) -> Result<&mut Self, XlsxError> {
+        if let Some(z) = opts.zoom_scale.filter(|z| !(10..=400).contains(z)) {
+            bail!("zoom_scale must be within 10..=400, got {z}");
+        }
+        let sqref = match &opts.selection {
+            Some(s) => Some(normalize_sqref(s)?),
+            None => None,
+        };
+        let active = match (&opts.active_cell, &sqref) {
+            (Some(c), _) => {
+                let (col0, row) = parse_cell(c)?;
+                Some(format!("{}{}", col_letter(col0), row))
+            }
+            (None, Some(s)) => s.split([' ', ':']).next().map(str::to_owned),
+            (None, None) => None,
+        };
+
+        self.edit_sheet_view(|tag, inner, p| {
+            if let Some(show) = opts.show_grid_lines {
+                // по умолчанию сетка видна — атрибут нужен только для "0"
+                set_attr(tag, "showGridLines", (!show).then_some("0"));
+            }
+            if let Some(z) = opts.zoom_scale {
+                set_attr(tag, "zoomScale", Some(&z.to_string()));
+            }
+            if let Some(rtl) = opts.right_to_left {
+                set_attr(tag, "rightToLeft", rtl.then_some("1"));
+            }
+            if let Some(cell) = &active {
+                let sqref = sqref.as_deref().unwrap_or(cell);
+                set_selection(inner, p, cell, sqref);
+            }
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Закрепляет строки выше и столбцы левее ячейки `cell`
+    /// (`"B2"` — первая строка и столбец A, `"A2"` — только первая строка).
+    /// `"A1"` снимает закрепление.
+    pub fn freeze_panes(&mut self, cell: &str) -> Result<&mut Self, XlsxError> {
+        let (col0, row) = parse_cell(cell)?;
+        let x_split = col0;
+        let y_split = row - 1;
+        if x_split == 0 && y_split == 0 {
+            return self.unfreeze_panes();
+        }
+
+        let top_left = format!("{}{}", col_letter(col0), row);
+        let active = match (x_split > 0, y_split > 0) {
+            (true, true) => "bottomRight",
+            (false, true) => "bottomLeft",
+            _ => "topRight",
+        };
+
+        self.edit_sheet_view(|_, inner, p| {
+            let mut pane = format!("<{p}pane");
+            if x_split > 0 {
+                pane.push_str(&format!(r#" xSplit="{x_split}""#));
+            }
+            if y_split > 0 {
+                pane.push_str(&format!(r#" ySplit="{y_split}""#));
+            }
+            pane.push_str(&format!(
+                r#" topLeftCell="{top_left}" activePane="{active}" state="frozen"/>"#
+            ));
+            if x_split > 0 && y_split > 0 {
+                pane.push_str(&format!(r#"<{p}selection pane="topRight"/>"#));
+                pane.push_str(&format!(r#"<{p}selection pane="bottomLeft"/>"#));
+            }
+            pane.push_str(&format!(
+                r#"<{p}selection pane="{active}" activeCell="{top_left}" sqref="{top_left}"/>"#
+            ));
+            replace_pane(inner, p, &pane);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Закрепляет первую строку (шапку).
+    pub fn freeze_top_row(&mut self) -> Result<&mut Self, XlsxError> {
+        self.freeze_panes("A2")
+    }
+
+    /// Закрепляет первый столбец.
+    pub fn freeze_first_column(&mut self) -> Result<&mut Self, XlsxError> {
+        self.freeze_panes("B1")
+    }
+
+    /// Разделяет окно (без закрепления) на `x_split`/`y_split` — смещения
+    /// в twips (1/20 pt), как их хранит Excel; 0 — без разделения по оси.
+    pub fn split_panes(&mut self, x_split: u32, y_split: u32) -> Result<&mut Self, XlsxError> {
+        if x_split == 0 && y_split == 0 {
+            return self.unfreeze_panes();
+        }
+        let active = match (x_split > 0, y_split > 0) {
+            (true, true) => "bottomRight",
+            (false, true) => "bottomLeft",
+            _ => "topRight",
+        };
+        self.edit_sheet_view(|_, inner, p| {
+            let mut pane = format!("<{p}pane");
+            if x_split > 0 {
+                pane.push_str(&format!(r#" xSplit="{x_split}""#));
+            }
+            if y_split > 0 {
+                pane.push_str(&format!(r#" ySplit="{y_split}""#));
+            }
+            pane.push_str(&format!(r#" activePane="{active}"/>"#));
+            pane.push_str(&format!(r#"<{p}selection pane="{active}"/>"#));
+            replace_pane(inner, p, &pane);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Убирает закрепление/разделение окна, если оно было.
+    pub fn unfreeze_panes(&mut self) -> Result<&mut Self, XlsxError> {
+        if find_child(&self.sheet_xml, "sheetViews")?.is_none() {
+            return Ok(self);
+        }
+        self.edit_sheet_view(|_, inner, p| {
+            replace_pane(inner, p, "");
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Даёт поправить первый `<sheetView>` листа: открывающий тег (без `/`),
+    /// его содержимое и префикс пространства имён. Если `<sheetViews>` нет —
+    /// создаёт его в положенном по схеме месте.
+    pub(crate) fn edit_sheet_view<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>, &mut Vec<u8>, &str) -> Result<()>,
+    {
+        let views = match find_child(&self.sheet_xml, "sheetViews")? {
+            Some(r) => r,
+            None => {
+                let block = br#"<sheetViews><sheetView workbookViewId="0"/></sheetViews>"#;
+                let pos = insert_child(&mut self.sheet_xml, "sheetViews", block)?;
+                pos..pos + block.len()
+            }
+        };
+        let prefix = tag_prefix(&self.sheet_xml[views.clone()]);
+        let qname = format!("{prefix}sheetView");
+
+        let view = find_elem(&self.sheet_xml[..views.end], &qname, views.start + 1)
+            .filter(|r| r.start < views.end)
+            .context("<sheetView> not found inside <sheetViews>")?;
+        let src = &self.sheet_xml[view.clone()];
+        let tag_end = src.iter().position(|&b| b == b'>').context("malformed <sheetView>")?;
+
+        let (mut tag, mut inner) = if src[tag_end - 1] == b'/' {
+            let mut t = src[..tag_end - 1].to_vec();
+            while t.last().is_some_and(|b| b.is_ascii_whitespace()) {
+                t.pop();
+            }
+            t.push(b'>');
+            (t, Vec::new())
+        } else {
+            let close_len = qname.len() + 3; // "</" + qname + ">"
+            (
+                src[..=tag_end].to_vec(),
+                src[tag_end + 1..src.len() - close_len].to_vec(),
+            )
+        };
+
+        f(&mut tag, &mut inner, &prefix)?;
+
+        let mut out = tag;
+        out.extend_from_slice(&inner);
+        out.extend_from_slice(format!("</{qname}>").as_bytes());
+        self.sheet_xml.splice(view, out);
+        Ok(())
+    }
+}
+
+/// Заменяет `<pane>` и `<selection>` в содержимом `<sheetView>` на `pane_xml`
+/// (по схеме они идут первыми, до `pivotSelection`/`extLst`).
+fn replace_pane(inner: &mut Vec<u8>, prefix: &str, pane_xml: &str) {
+    remove_elems(inner, &format!("{prefix}pane"));
+    remove_elems(inner, &format!("{prefix}selection"));
+    inner.splice(0..0, pane_xml.bytes());
+}
+
+/// Проверяет sqref и приводит ссылки к верхнему регистру.
+pub(crate) fn normalize_sqref(sqref: &str) -> Result<String> {
+    let mut out = Vec::new();
+    for part in sqref.split_whitespace() {
+        let mut cells = Vec::new();
+        for cell in part.split(':') {
+            let (col0, row) = parse_cell(cell)?;
+            cells.push(format!("{}{}", col_letter(col0), row));
+        }
+        if cells.len() > 2 {
+            bail!("invalid range in selection: {part}");
+        }
+        out.push(cells.join(":"));
+    }
+    if out.is_empty() {
+        bail!("empty selection");
+    }
+    Ok(out.join(" "))
+}
+
+/// Ставит `activeCell`/`sqref` в `<selection>` активной панели
+/// (или создаёт его сразу после `<pane>`/прочих `<selection>`).
+fn set_selection(inner: &mut Vec<u8>, prefix: &str, cell: &str, sqref: &str) {
+    let open_tag = |xml: &[u8], r: &std::ops::Range<usize>| {
+        let end = xml[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+        r.start..r.start + end + 1
+    };
+
+    let mut insert_at = 0;
+    let active_pane = match find_elem(inner, &format!("{prefix}pane"), 0) {
+        Some(r) => {
+            insert_at = r.end;
+            get_attr(&inner[open_tag(inner, &r)], "activePane")
+                .unwrap_or_else(|| "topLeft".to_owned())
+        }
+        None => "topLeft".to_owned(),
+    };
+
+    let qname = format!("{prefix}selection");
+    let mut from = 0;
+    while let Some(r) = find_elem(inner, &qname, from) {
+        from = r.end;
+        insert_at = r.end;
+        let tag_range = open_tag(inner, &r);
+        let pane = get_attr(&inner[tag_range.clone()], "pane");
+        if pane.as_deref().unwrap_or("topLeft") != active_pane {
+            continue;
+        }
+        let mut tag = inner[tag_range.clone()].to_vec();
+        set_attr(&mut tag, "activeCell", Some(cell));
+        set_attr(&mut tag, "sqref", Some(sqref));
+        inner.splice(tag_range, tag);
+        return;
+    }
+
+    let pane_attr = if active_pane == "topLeft" {
+        String::new()
+    } else {
+        format!(r#" pane="{active_pane}""#)
+    };
+    let sel = format!(r#"<{qname}{pane_attr} activeCell="{cell}" sqref="{sqref}"/>"#);
+    inner.splice(insert_at..insert_at, sel.bytes());
+}
@@ -0,0 +1,150 @@
+//! wasm_part.rs – привязки wasm-bindgen для браузера: книга приходит и уходит
+//! как `Uint8Array`, к файловой системе ничего не обращается.
+//!
+//! Значения ячеек — как у привязок Python и Node: `null`/`undefined` — пустая
+//! ячейка, `Date` — сериал даты, строки через [`CellValue::parse`].
+
+use crate::XlsxEditor;
+use crate::error::XlsxError;
+use crate::template_part::{CellValue, DATE_FORMAT, DATETIME_FORMAT};
+use js_sys::{Array, Date};
+use wasm_bindgen::prelude::*;
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+fn js_err(e: XlsxError) -> JsError {
+    JsError::new(&format!("{e:#}"))
+}
+
+fn to_typed_cell(v: &JsValue) -> (CellValue, Option<&'static str>) {
+    if v.is_null() || v.is_undefined() {
+        return (CellValue::Empty, None);
+    }
+    if let Some(b) = v.as_bool() {
+        return (CellValue::Bool(b), None);
+    }
+    if let Some(n) = v.as_f64() {
+        let value = if n.is_finite() {
+            CellValue::Number(n)
+        } else {
+            CellValue::Empty
+        };
+        return (value, None);
+    }
+    if let Some(d) = v.dyn_ref::<Date>() {
+        let ms = d.get_time();
+        let fmt = if ms % MS_PER_DAY == 0.0 {
+            DATE_FORMAT
+        } else {
+            DATETIME_FORMAT
+        };
+        return (CellValue::unix_days(ms / MS_PER_DAY), Some(fmt));
+    }
+    let s = v
+        .as_string()
+        .unwrap_or_else(|| String::from(js_sys::JsString::from(v.clone())));
+    (CellValue::parse(&s), None)
+}
+
+/// Имена листов книги `data` по порядку.
+#[wasm_bindgen(js_name = scanBytes)]
+pub fn scan_bytes(data: &[u8]) -> Result<Vec<String>, JsError> {
+    crate::scan_bytes(data).map_err(js_err)
+}
+
+/// Редактор книги в памяти, `XlsxEditor` в JS.
+#[wasm_bindgen(js_name = XlsxEditor)]
+pub struct WasmEditor {
+    editor: XlsxEditor,
+}
+
+#[wasm_bindgen(js_class = XlsxEditor)]
+impl WasmEditor {
+    /// Открывает лист `sheetName` книги из байтов `data`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8], sheet_name: &str) -> Result<WasmEditor, JsError> {
+        let editor = XlsxEditor::open_bytes(data, sheet_name).map_err(js_err)?;
+        Ok(WasmEditor { editor })
+    }
+
+    #[wasm_bindgen(js_name = appendRow)]
+    pub fn append_row(&mut self, cells: Array) -> Result<(), JsError> {
+        let cells: Vec<_> = cells.iter().map(|v| to_typed_cell(&v)).collect();
+        self.editor.append_values(&cells).map_err(js_err)
+    }
+
+    /// Дописывает строки массива массивов подряд.
+    #[wasm_bindgen(js_name = appendTable)]
+    pub fn append_table(&mut self, rows: Array) -> Result<(), JsError> {
+        for row in rows.iter() {
+            self.append_row(
+                row.dyn_into()
+                    .map_err(|_| JsError::new("row is not an array"))?,
+            )?;
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = withWorksheet)]
+    pub fn with_worksheet(&mut self, sheet_name: &str) -> Result<(), JsError> {
+        self.editor.with_worksheet(sheet_name).map_err(js_err)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = addWorksheet)]
+    pub fn add_worksheet(&mut self, sheet_name: &str) -> Result<(), JsError> {
+        self.editor.add_worksheet(sheet_name).map_err(js_err)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setNumberFormat)]
+    pub fn set_number_format(&mut self, range: &str, fmt: &str) -> Result<(), JsError> {
+        self.editor.set_number_format(range, fmt).map_err(js_err)
+    }
+
+    #[wasm_bindgen(js_name = setFill)]
+    pub fn set_fill(&mut self, range: &str, rgb: &str) -> Result<(), JsError> {
+        self.editor.set_fill(range, rgb).map_err(js_err)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setFont)]
+    pub fn set_font(
+        &mut self,
+        range: &str,
+        name: &str,
+        size: f32,
+        bold: bool,
+        italic: bool,
+    ) -> Result<(), JsError> {
+        self.editor
+            .set_font(range, name, size, bold, italic)
+            .map_err(js_err)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setBorder)]
+    pub fn set_border(&mut self, range: &str, border_style: &str) -> Result<(), JsError> {
+        self.editor
+            .set_border(range, border_style)
+            .map_err(js_err)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setColumnWidth)]
+    pub fn set_column_width(&mut self, col: &str, width: f64) -> Result<(), JsError> {
+        self.editor.set_column_width(col, width).map_err(js_err)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = mergeCells)]
+    pub fn merge_cells(&mut self, range: &str) -> Result<(), JsError> {
+        self.editor.merge_cells(range).map_err(js_err)
+    }
+
+    /// Книга со всеми правками как `Uint8Array`.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&mut self) -> Result<Vec<u8>, JsError> {
+        self.editor.save_to_bytes().map_err(js_err)
+    }
+}
@@ -0,0 +1,216 @@
+//! workbook_part.rs – сведения из workbook.xml: список листов, индекс текущего
+//! листа и правка `<definedNames>`.
+
+use crate::XlsxEditor;
+use crate::error::next_event;
+use crate::files_part::xml_escape;
+use crate::sheet_xml::{find_elem, find_wb_child, get_attr, insert_wb_child, tag_prefix};
+use anyhow::{Context, Result};
+use quick_xml::{Reader, events::Event};
+
+/// Лист из `<sheets>`: имя и путь части внутри архива.
+#[derive(Debug, Clone)]
+pub(crate) struct SheetEntry {
+    pub name: String,
+    pub path: String,
+}
+
+impl XlsxEditor {
+    /// Листы книги в порядке `<sheets>` с путями, разрешёнными через workbook.xml.rels.
+    pub(crate) fn sheet_entries(&self) -> Result<Vec<SheetEntry>> {
+        parse_sheet_entries(self.workbook_xml.get(&self.src)?, self.rels_xml.get(&self.src)?)
+    }
+
+    /// 0-based позиция текущего листа в `<sheets>` (это `localSheetId` в definedNames).
+    pub(crate) fn current_sheet_index(&self) -> Result<usize> {
+        self.sheet_entries()?
+            .iter()
+            .position(|s| s.path == self.sheet_path)
+            .with_context(|| format!("{} is not listed in workbook.xml", self.sheet_path))
+    }
+
+    /// Имя текущего листа.
+    pub(crate) fn current_sheet_name(&self) -> Result<String> {
+        let idx = self.current_sheet_index()?;
+        Ok(self.sheet_entries()?.swap_remove(idx).name)
+    }
+
+    /// Ставит (`Some`) или убирает (`None`) определённое имя `name`
+    /// с областью видимости `local_sheet_id` (`None` — вся книга).
+    /// `value` — формула без экранирования, например `'Sheet 1'!$A$1:$F$1`.
+    pub(crate) fn set_defined_name(
+        &mut self,
+        name: &str,
+        local_sheet_id: Option<usize>,
+        value: Option<&str>,
+        hidden: bool,
+    ) -> Result<()> {
+        let wb = self.workbook_xml.get_mut(&self.src)?;
+        let block = find_wb_child(wb, "definedNames")?;
+        let prefix = match &block {
+            Some(r) => tag_prefix(&wb[r.clone()]),
+            None => find_wb_child(wb, "sheets")?
+                .map(|r| tag_prefix(&wb[r]))
+                .unwrap_or_default(),
+        };
+        let local = local_sheet_id.map(|i| i.to_string());
+
+        let new_elem = value.map(|v| {
+            let mut e = format!(r#"<{prefix}definedName name="{}""#, xml_escape(name));
+            if let Some(l) = &local {
+                e.push_str(&format!(r#" localSheetId="{l}""#));
+            }
+            if hidden {
+                e.push_str(r#" hidden="1""#);
+            }
+            e.push_str(&format!(">{}</{prefix}definedName>", xml_escape(v)));
+            e
+        });
+
+        let Some(block) = block else {
+            if let Some(e) = new_elem {
+                let xml = format!("<{prefix}definedNames>{e}</{prefix}definedNames>");
+                insert_wb_child(wb, "definedNames", xml.as_bytes())?;
+            }
+            return Ok(());
+        };
+
+        // ищем существующее имя с той же областью видимости
+        let qname = format!("{prefix}definedName");
+        let mut found = None;
+        let mut from = block.start + 1;
+        while let Some(r) = find_elem(&wb[..block.end], &qname, from) {
+            from = r.end;
+            let tag_end = r.start + wb[r.clone()].iter().position(|&b| b == b'>').unwrap_or(0);
+            let tag = &wb[r.start..=tag_end];
+            let same_name = get_attr(tag, "name").is_some_and(|n| n.eq_ignore_ascii_case(name));
+            if same_name && get_attr(tag, "localSheetId") == local {
+                found = Some(r);
+                break;
+            }
+        }
+
+        match (found, new_elem) {
+            (Some(r), Some(e)) => {
+                wb.splice(r, e.into_bytes());
+            }
+            (Some(r), None) => {
+                wb.splice(r, std::iter::empty());
+                // пустой <definedNames> убираем целиком
+                let block = find_wb_child(wb, "definedNames")?.context("definedNames vanished")?;
+                if find_elem(&wb[..block.end], &qname, block.start + 1).is_none() {
+                    wb.splice(block, std::iter::empty());
+                }
+            }
+            (None, Some(e)) => {
+                let close = format!("</{prefix}definedNames>");
+                let inner = &wb[block.clone()];
+                match memchr::memmem::rfind(inner, close.as_bytes()) {
+                    Some(p) => {
+                        let pos = block.start + p;
+                        wb.splice(pos..pos, e.into_bytes());
+                    }
+                    None => {
+                        // <definedNames/>
+                        let xml = format!("<{prefix}definedNames>{e}{close}");
+                        wb.splice(block, xml.into_bytes());
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Листы из workbook.xml по порядку с путями частей из workbook.xml.rels.
+pub(crate) fn parse_sheet_entries(workbook_xml: &[u8], rels_xml: &[u8]) -> Result<Vec<SheetEntry>> {
+    let mut targets = std::collections::HashMap::new();
+    let mut rdr = Reader::from_reader(rels_xml);
+
+    loop {
+        match next_event(&mut rdr, "xl/_rels/workbook.xml.rels")? {
+            Event::Empty(ref e) | Event::Start(ref e)
+                if e.local_name().as_ref() == b"Relationship" =>
+            {
+                let mut id = None;
+                let mut target = None;
+                for a in e.attributes().with_checks(false).flatten() {
+                    match a.key.as_ref() {
+                        b"Id" => id = Some(a.unescape_value()?.into_owned()),
+                        b"Target" => target = Some(a.unescape_value()?.into_owned()),
+                        _ => {}
+                    }
+                }
+                if let (Some(id), Some(t)) = (id, target) {
+                    targets.insert(id, part_path(&t));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut rdr = Reader::from_reader(workbook_xml);
+    loop {
+        match next_event(&mut rdr, "xl/workbook.xml")? {
+            Event::Empty(ref e) | Event::Start(ref e) if e.local_name().as_ref() == b"sheet" => {
+                let mut name = String::new();
+                let mut rid = String::new();
+                for a in e.attributes().with_checks(false).flatten() {
+                    match a.key.as_ref() {
+                        b"name" => name = a.unescape_value()?.into_owned(),
+                        k if k.ends_with(b":id") => rid = a.unescape_value()?.into_owned(),
+                        _ => {}
+                    }
+                }
+                let path = targets.get(&rid).cloned().unwrap_or_default();
+                out.push(SheetEntry { name, path });
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+/// Путь части в архиве по `Target` из workbook.xml.rels.
+fn part_path(target: &str) -> String {
+    match target.strip_prefix('/') {
+        Some(abs) => abs.to_owned(),
+        None if target.starts_with("xl/") => target.to_owned(),
+        None => format!("xl/{target}"),
+    }
+}
+
+/// Имя листа для ссылки в формуле: в кавычках, если без них не обойтись.
+pub(crate) fn quote_sheet_name(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        && !looks_like_cell_ref(name);
+    if plain {
+        name.to_owned()
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+fn looks_like_cell_ref(name: &str) -> bool {
+    let letters = name.bytes().take_while(|b| b.is_ascii_alphabetic()).count();
+    (1..=3).contains(&letters)
+        && letters < name.len()
+        && name.bytes().skip(letters).all(|b| b.is_ascii_digit())
+}
+
+/// `A1:F10` → `$A$1:$F$10`.
+pub(crate) fn absolute_ref(range: &str) -> String {
+    range
+        .split(':')
+        .map(|cell| {
+            let digits = cell.find(|c: char| c.is_ascii_digit()).unwrap_or(cell.len());
+            format!("${}${}", &cell[..digits], &cell[digits..])
+        })
+        .collect::<Vec<_>>()
+        .join(":")
+}
@@ -0,0 +1,128 @@
+//! Пик памяти при переключении листов и сохранении: буфер большого листа
+//! должен переезжать, а не копироваться.
+//!
+//! Отдельный тестовый бинарник, чтобы считающий аллокатор не стоял перед
+//! остальными тестами крейта. Обычный прогон берёт лист на ~8 МБ, полный
+//! размер — `cargo test --release -p rust-core --test sheet_memory -- --ignored`.
+
+use rust_core::{XlsxEditor, scan};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::{Cursor, Read, Write};
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+/// Счётчик живых байт кучи по потокам: тест меряет пик памяти своего
+/// потока, не задевая соседний.
+struct ThreadAllocProbe;
+
+thread_local! {
+    static ALLOC_LIVE: Cell<isize> = const { Cell::new(0) };
+    static ALLOC_PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track_alloc(delta: isize) {
+    let _ = ALLOC_LIVE.try_with(|live| {
+        live.set(live.get() + delta);
+        let _ = ALLOC_PEAK.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for ThreadAllocProbe {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track_alloc(layout.size() as isize);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        track_alloc(layout.size() as isize);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track_alloc(-(layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        track_alloc(size as isize - layout.size() as isize);
+        unsafe { System.realloc(ptr, layout, size) }
+    }
+}
+
+#[global_allocator]
+static ALLOC_PROBE: ThreadAllocProbe = ThreadAllocProbe;
+
+#[test]
+fn sheet_is_not_copied_on_switch_and_save() -> anyhow::Result<()> {
+    check_sheet_not_copied(8_000_000)
+}
+
+#[test]
+#[ignore = "лист на 200 МБ; cargo test --release -p rust-core --test sheet_memory -- --ignored"]
+fn big_sheet_is_not_copied_on_switch_and_save() -> anyhow::Result<()> {
+    check_sheet_not_copied(200_000_000)
+}
+
+/// Подменяет sheet1.xml тестовой книги листом на `target` байт (без сжатия),
+/// гоняет дозаписи с переключением листов и сохранения и сверяет пик.
+fn check_sheet_not_copied(target: usize) -> anyhow::Result<()> {
+    let src = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/test.xlsx");
+    let filler = "x".repeat(150);
+    let mut sheet = String::with_capacity(target + (10 << 20));
+    sheet.push_str(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><dimension ref="A1"/><sheetData>"#);
+    let mut rows = 0;
+    while sheet.len() < target {
+        rows += 1;
+        sheet.push_str(&format!(
+            r#"<row r="{rows}"><c r="A{rows}" t="inlineStr"><is><t>{filler}</t></is></c><c r="B{rows}"><v>{rows}</v></c></row>"#
+        ));
+    }
+    sheet.push_str("</sheetData></worksheet>");
+    let size = sheet.len() as isize;
+    let mut zin = ZipArchive::new(std::fs::File::open(src)?)?;
+    let mut zout = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..zin.len() {
+        let mut f = zin.by_index(i)?;
+        zout.start_file(f.name(), SimpleFileOptions::default())?;
+        if f.name() == "xl/worksheets/sheet1.xml" {
+            zout.write_all(sheet.as_bytes())?;
+        } else {
+            let mut buf = Vec::new();
+            f.read_to_end(&mut buf)?;
+            zout.write_all(&buf)?;
+        }
+    }
+    drop(sheet);
+    let data = zout.finish()?.into_inner();
+
+    let first = scan(src)?.remove(0);
+    let mut ed = XlsxEditor::open_bytes(data, &first)?;
+    ed.set_stamp_modified(false).set_compression_threads(1);
+    // первая дозапись растит буфер листа; дальше он только переезжает
+    ed.append_row(["first"])?;
+    ed.add_worksheet("Other")?;
+
+    let live = || ALLOC_LIVE.with(|l| l.get());
+    let base = live();
+    ALLOC_PEAK.with(|p| p.set(base));
+    for i in 0..3 {
+        ed.with_worksheet(&first)?;
+        ed.append_row([format!("switch {i}")])?;
+        ed.with_worksheet("Other")?;
+        ed.append_row([format!("other {i}")])?;
+    }
+    ed.with_worksheet(&first)?;
+    let out = ed.save_to_bytes()?;
+    let out_len = out.len() as isize;
+    drop(out);
+    ed.with_worksheet("Other")?;
+    drop(ed.save_to_bytes()?);
+
+    let peak = ALLOC_PEAK.with(|p| p.get()) - base;
+    assert!(
+        peak < size / 4 + 2 * out_len,
+        "peak grew by {peak} bytes for a {size}-byte sheet"
+    );
+    assert!(live() - base < size / 8, "sheet copies left behind");
+    Ok(())
+}